@@ -91,9 +91,14 @@ class Experimental(TypedDict, total=False):
     socket_recv_buffer: Union[str, int]
     socket_send_autotune: bool
     socket_send_buffer: Union[str, int]
+    strace_logging_display_len: int
     strace_logging_mode: Union[
-        Literal["off"], Literal["standard"], Literal["deterministic"]
+        Literal["off"],
+        Literal["standard"],
+        Literal["deterministic"],
+        Literal["hex-dump"],
     ]
+    strace_logging_utf8: bool
     unblocked_syscall_latency: str
     unblocked_vdso_latency: str
     use_cpu_pinning: bool
@@ -208,6 +208,8 @@ const fn errno_to_str(e: Errno) -> Option<&'static str> {
         Errno::EACCES => Some("EACCES"),
         Errno::ENOEXEC => Some("ENOEXEC"),
         Errno::ENOTDIR => Some("ENOTDIR"),
+        Errno::ENODEV => Some("ENODEV"),
+        Errno::ERANGE => Some("ERANGE"),
         _ => None,
     }
 }
@@ -348,6 +350,8 @@ impl Errno {
     pub const EACCES: Self = Self::from_u32_const(bindings::LINUX_EACCES);
     pub const ENOEXEC: Self = Self::from_u32_const(bindings::LINUX_ENOEXEC);
     pub const ENOTDIR: Self = Self::from_u32_const(bindings::LINUX_ENOTDIR);
+    pub const ENODEV: Self = Self::from_u32_const(bindings::LINUX_ENODEV);
+    pub const ERANGE: Self = Self::from_u32_const(bindings::LINUX_ERANGE);
     // NOTE: add new entries to `errno_to_str` above
 
     // Aliases
@@ -369,6 +373,14 @@ impl Errno {
         }
     }
 
+    /// Returns `true` if this is `EAGAIN` or `EWOULDBLOCK`, which both mean that the operation
+    /// would block. These are the same value on Linux, but code should use this rather than
+    /// comparing against either name so that both spellings are handled.
+    #[inline]
+    pub const fn is_wouldblock(self) -> bool {
+        self.0 == Self::EAGAIN.0 || self.0 == Self::EWOULDBLOCK.0
+    }
+
     /// For C interop.
     #[inline]
     pub const fn to_negated_i64(self) -> i64 {
@@ -488,3 +500,16 @@ impl core::convert::TryFrom<std::io::Error> for Errno {
 
 #[cfg(feature = "std")]
 impl std::error::Error for Errno {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_wouldblock() {
+        assert!(Errno::EAGAIN.is_wouldblock());
+        assert!(Errno::EWOULDBLOCK.is_wouldblock());
+        assert!(!Errno::EINTR.is_wouldblock());
+        assert!(!Errno::EINPROGRESS.is_wouldblock());
+    }
+}
@@ -1,3 +1,4 @@
+use linux_api::posix_types::kernel_mode_t;
 use shadow_shmem::allocator::ShMemBlockSerialized;
 use vasi::VirtualAddressSpaceIndependent;
 
@@ -12,6 +13,9 @@ pub struct ShimEventStartRes {
     // TODO: Consider replacing this with a seed for a shim-side PRNG, particularly
     // when implementing the getrandom vdso (#3362).
     pub auxvec_random: [u8; 16],
+    /// The process's umask, which the shim sets natively when starting the first thread of a
+    /// process so that it applies to natively-executed syscalls.
+    pub umask: kernel_mode_t,
 }
 
 #[derive(Copy, Clone, Debug, VirtualAddressSpaceIndependent)]
@@ -315,6 +315,7 @@ impl ThreadShmem {
                     pending_standard_siginfos: [siginfo_t::default();
                         Signal::STANDARD_MAX.as_i32() as usize],
                     blocked_signals: sigset_t::EMPTY,
+                    saved_blocked_signals: FfiOption::None,
                     sigaltstack: StackWrapper(stack_t {
                         ss_sp: core::ptr::null_mut(),
                         ss_flags: libc::SS_DISABLE,
@@ -355,6 +356,11 @@ pub struct ThreadShmemProtected {
     // actually supported by the kernel.
     pub blocked_signals: sigset_t,
 
+    // Signal mask to restore once the current syscall completes, if the syscall temporarily
+    // replaced the signal mask (ex: `ppoll`). If the syscall was interrupted by a signal, the mask
+    // is restored only after the signal handlers have run.
+    pub saved_blocked_signals: FfiOption<sigset_t>,
+
     // Configured alternate signal stack for this thread.
     sigaltstack: StackWrapper,
 }
@@ -576,6 +576,10 @@ fn wait_for_start_event(is_first_thread: bool) {
     if is_first_thread {
         let working_dir = CStr::from_bytes_until_nul(&working_dir).unwrap();
         rustix::process::chdir(working_dir).unwrap();
+
+        // Natively-executed syscalls should use the process's umask rather than the one inherited
+        // from shadow.
+        rustix::process::umask(rustix::fs::Mode::from_raw_mode(res.umask));
     }
 }
 
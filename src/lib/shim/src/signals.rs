@@ -6,6 +6,7 @@ use linux_api::signal::{
 };
 use linux_api::ucontext::ucontext;
 use log::{trace, warn};
+use shadow_shim_helper_rs::option::FfiOption;
 use shadow_shim_helper_rs::shim_shmem;
 
 use crate::tls::ShimTlsVar;
@@ -344,6 +345,21 @@ pub unsafe fn process_signals(mut ucontext: Option<&mut ucontext>) -> bool {
     restartable
 }
 
+/// Restore the signal mask that shadow saved when temporarily replacing the mask for a syscall
+/// (ex: `ppoll`), if any. Should be called after the syscall completes and any pending signals
+/// have been handled.
+pub fn restore_saved_signal_mask() {
+    let host = crate::global_host_shmem::get();
+    let host_lock = host.protected().lock();
+
+    tls_thread_shmem::with(|thread| {
+        let mut thread = thread.protected.borrow_mut(&host_lock.root);
+        if let FfiOption::Some(mask) = thread.saved_blocked_signals.take() {
+            thread.blocked_signals = mask;
+        }
+    });
+}
+
 /// Handle a hardware error signal that was raised in `exe_ctx`.
 ///
 /// # Safety
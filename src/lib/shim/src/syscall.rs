@@ -93,6 +93,10 @@ pub(crate) unsafe fn emulated_syscall_event(
                 let all_sigactions_had_sa_restart =
                     unsafe { crate::signals::process_signals(ctx.as_deref_mut()) };
 
+                // If shadow temporarily replaced the signal mask for this syscall (ex: `ppoll`),
+                // restore the original mask now that any signal handlers have run.
+                crate::signals::restore_saved_signal_mask();
+
                 if i64::from(syscall_complete.retval) == Errno::EINTR.to_negated_i64()
                     && all_sigactions_had_sa_restart
                     && syscall_complete.restartable
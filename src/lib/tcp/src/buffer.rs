@@ -199,6 +199,11 @@ impl<T: Instant> SendQueue<T> {
         unreachable!();
     }
 
+    /// Returns true if any segment has been transmitted.
+    pub fn has_transmitted(&self) -> bool {
+        self.time_last_segment_sent.is_some()
+    }
+
     pub fn mark_as_transmitted(&mut self, up_to: Seq, time: T) {
         assert!(self.contains(up_to) || up_to == self.end_seq);
 
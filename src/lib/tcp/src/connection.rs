@@ -28,11 +28,6 @@ pub(crate) struct Connection<I: Instant> {
 }
 
 impl<I: Instant> Connection<I> {
-    /// The max number of bytes allowed in the send and receive buffers. These should be made
-    /// dynamic in the future.
-    const SEND_BUF_MAX: usize = 100_000;
-    const RECV_BUF_MAX: u32 = 100_000;
-
     pub fn new(
         local_addr: SocketAddrV4,
         remote_addr: SocketAddrV4,
@@ -96,7 +91,7 @@ impl<I: Instant> Connection<I> {
         }
 
         let send_buffer_len = self.send.buffer.len() as usize;
-        let send_buffer_space = Self::SEND_BUF_MAX.saturating_sub(send_buffer_len);
+        let send_buffer_space = self.send_buffer_capacity().saturating_sub(send_buffer_len);
 
         let len = std::cmp::min(len, send_buffer_space);
         if let Err(e) = self.send.buffer.add_data(reader, len) {
@@ -598,6 +593,11 @@ impl<I: Instant> Connection<I> {
         self.recv.as_ref().map(|x| x.is_closed).unwrap_or(false)
     }
 
+    /// Returns true if we've sent the SYN packet to the peer.
+    pub fn sent_syn(&self) -> bool {
+        self.send.syn_acked || self.send.buffer.has_transmitted()
+    }
+
     /// Returns true if the peer acknowledged the SYN packet we sent.
     pub fn syn_was_acked(&self) -> bool {
         self.send.syn_acked
@@ -613,7 +613,7 @@ impl<I: Instant> Connection<I> {
     pub fn send_buf_has_space(&self) -> bool {
         let send_buffer_len = self.send.buffer.len() as usize;
 
-        send_buffer_len < Self::SEND_BUF_MAX
+        send_buffer_len < self.send_buffer_capacity()
     }
 
     /// Returns true if the recv buffer has data to read. Does not consider whether the connection
@@ -657,9 +657,14 @@ impl<I: Instant> Connection<I> {
         Some(SeqRange::new(window_left, window_left + window_len))
     }
 
+    /// The total capacity of the send buffer.
+    fn send_buffer_capacity(&self) -> usize {
+        self.config.send_buffer_size.try_into().unwrap()
+    }
+
     /// The total capacity of the receive buffer.
     fn recv_buffer_capacity(&self) -> u32 {
-        Self::RECV_BUF_MAX
+        self.config.recv_buffer_size
     }
 }
 
@@ -669,6 +674,9 @@ pub(crate) struct ConnectionSend<I: Instant> {
     pub(crate) window: u32,
     pub(crate) is_closed: bool,
     pub(crate) syn_acked: bool,
+    /// The start of the send buffer when the `TCP_USER_TIMEOUT` timer was last armed, or `None` if
+    /// no timer is armed.
+    pub(crate) user_timeout_seq: Option<Seq>,
 }
 
 impl<I: Instant> ConnectionSend<I> {
@@ -679,6 +687,7 @@ impl<I: Instant> ConnectionSend<I> {
             window: 2048,
             is_closed: false,
             syn_acked: false,
+            user_timeout_seq: None,
         }
     }
 }
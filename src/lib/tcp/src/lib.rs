@@ -207,6 +207,16 @@ where
         (self.into(), Err(SendError::InvalidState))
     }
 
+    /// Queue data to be sent in the SYN packet (TCP fast open). This must be called after
+    /// [`connect`](Self::connect) and before the SYN packet has been popped.
+    fn send_fast_open(
+        self,
+        _reader: impl Read,
+        _len: usize,
+    ) -> (TcpStateEnum<X>, Result<usize, SendError>) {
+        (self.into(), Err(SendError::InvalidState))
+    }
+
     fn recv(self, _writer: impl Write, _len: usize) -> (TcpStateEnum<X>, Result<usize, RecvError>) {
         (self.into(), Err(RecvError::InvalidState))
     }
@@ -300,6 +310,11 @@ impl<X: Dependencies> TcpState<X> {
         self.with_state(|state| state.send(reader, len))
     }
 
+    #[inline]
+    pub fn send_fast_open(&mut self, reader: impl Read, len: usize) -> Result<usize, SendError> {
+        self.with_state(|state| state.send_fast_open(reader, len))
+    }
+
     #[inline]
     pub fn recv(&mut self, writer: impl Write, len: usize) -> Result<usize, RecvError> {
         self.with_state(|state| state.recv(writer, len))
@@ -338,6 +353,29 @@ impl<X: Dependencies> TcpState<X> {
     pub fn local_remote_addrs(&self) -> Option<(SocketAddrV4, SocketAddrV4)> {
         self.0.as_ref().unwrap().local_remote_addrs()
     }
+
+    /// Modify the config of the state. Changes will only apply to future operations (for example
+    /// the window scale won't change after the SYN has been sent). Does nothing if the state has no
+    /// config (for example the "closed" state). A listening state's config will be used for new
+    /// child states.
+    pub fn with_config(&mut self, f: impl FnOnce(&mut TcpConfig)) {
+        let config = match self.0.as_mut().unwrap() {
+            TcpStateEnum::Init(x) => &mut x.config,
+            TcpStateEnum::Listen(x) => &mut x.config,
+            TcpStateEnum::SynSent(x) => &mut x.connection.config,
+            TcpStateEnum::SynReceived(x) => &mut x.connection.config,
+            TcpStateEnum::Established(x) => &mut x.connection.config,
+            TcpStateEnum::FinWaitOne(x) => &mut x.connection.config,
+            TcpStateEnum::FinWaitTwo(x) => &mut x.connection.config,
+            TcpStateEnum::Closing(x) => &mut x.connection.config,
+            TcpStateEnum::TimeWait(x) => &mut x.connection.config,
+            TcpStateEnum::CloseWait(x) => &mut x.connection.config,
+            TcpStateEnum::LastAck(x) => &mut x.connection.config,
+            TcpStateEnum::Rst(_) | TcpStateEnum::Closed(_) => return,
+        };
+
+        f(config)
+    }
 }
 
 /// A macro that forwards an argument-less method to the inner type.
@@ -645,18 +683,49 @@ bitflags::bitflags! {
 #[non_exhaustive]
 pub struct TcpConfig {
     pub(crate) window_scaling_enabled: bool,
+    /// The max number of bytes allowed in the send buffer.
+    pub(crate) send_buffer_size: u32,
+    /// The max number of bytes allowed in the receive buffer. The size when the SYN is sent decides
+    /// the window scale, which limits the receive window of any future size increases.
+    pub(crate) recv_buffer_size: u32,
+    /// The number of seconds that a new child of a listening state waits for data from the peer
+    /// before it can be accepted, or 0 if it can be accepted as soon as it's established.
+    pub(crate) defer_accept_secs: u32,
+    /// The number of milliseconds that sent data may remain unacknowledged before the connection
+    /// is reset, or 0 if the connection should never time out.
+    pub(crate) user_timeout_ms: u32,
 }
 
 impl TcpConfig {
     pub fn window_scaling(&mut self, enable: bool) {
         self.window_scaling_enabled = enable;
     }
+
+    pub fn send_buffer_size(&mut self, size: u32) {
+        self.send_buffer_size = size;
+    }
+
+    pub fn recv_buffer_size(&mut self, size: u32) {
+        self.recv_buffer_size = size;
+    }
+
+    pub fn defer_accept(&mut self, secs: u32) {
+        self.defer_accept_secs = secs;
+    }
+
+    pub fn user_timeout(&mut self, ms: u32) {
+        self.user_timeout_ms = ms;
+    }
 }
 
 impl Default for TcpConfig {
     fn default() -> Self {
         Self {
             window_scaling_enabled: true,
+            send_buffer_size: 100_000,
+            recv_buffer_size: 100_000,
+            defer_accept_secs: 0,
+            user_timeout_ms: 0,
         }
     }
 }
@@ -243,6 +243,9 @@ pub(crate) struct ChildEntry<X: Dependencies> {
     /// example `child.push_packet()`.
     state: Option<TcpStateEnum<X>>,
     conn_addrs: RemoteLocalPair,
+    /// If set, the child shouldn't be added to the accept queue until it has received data or this
+    /// time has passed (`TCP_DEFER_ACCEPT`).
+    defer_until: Option<X::Instant>,
 }
 
 // state implementations
@@ -380,11 +383,31 @@ impl<X: Dependencies> ListenState<X> {
             ChildEntry {
                 state: Some(new_tcp.into()),
                 conn_addrs,
+                defer_until: None,
             }
         });
 
         assert!(self.conn_map.insert(conn_addrs, key).is_none());
 
+        if self.config.defer_accept_secs > 0 {
+            let defer_until = self.common.current_time()
+                + X::Duration::from_secs(self.config.defer_accept_secs.into());
+            self.children[key].defer_until = Some(defer_until);
+
+            // once the defer period ends, the child can be accepted even if it hasn't received any
+            // data
+            self.common.register_timer(defer_until, move |state| {
+                let TcpStateEnum::Listen(mut state) = state else {
+                    return state;
+                };
+
+                // ignore the error since the child may have been closed
+                let _ = state.sync_child(key);
+
+                state.into()
+            });
+        }
+
         // make sure the child is added to all of the correct lists
         self.sync_child(key).unwrap();
 
@@ -413,11 +436,26 @@ impl<X: Dependencies> ListenState<X> {
                 remove_from_list(&mut self.to_send, &key);
             }
 
+            // a deferred child can't be accepted until it receives data or a FIN, or until the
+            // defer period ends
+            if let Some(defer_until) = entry.defer_until {
+                let received = match child.as_ref().unwrap() {
+                    TcpStateEnum::Established(x) => x.connection.recv_buf_has_data(),
+                    TcpStateEnum::CloseWait(_) => true,
+                    _ => false,
+                };
+
+                if received || self.common.current_time() >= defer_until {
+                    entry.defer_until = None;
+                }
+            }
+
             // add to or remove from the accept queue
             if matches!(
                 child.as_ref().unwrap(),
                 TcpStateEnum::Established(_) | TcpStateEnum::CloseWait(_)
-            ) {
+            ) && entry.defer_until.is_none()
+            {
                 // if in the "established" or "close-wait" state, but not in the accept queue
                 if !self.accept_queue.contains(&key) {
                     // add to the accept queue
@@ -815,6 +853,20 @@ impl<X: Dependencies> TcpStateTrait<X> for SynSentState<X> {
         (self.into(), Err(SendError::NotConnected))
     }
 
+    fn send_fast_open(
+        mut self,
+        reader: impl Read,
+        len: usize,
+    ) -> (TcpStateEnum<X>, Result<usize, SendError>) {
+        // the data can only be carried by the SYN if the SYN hasn't been sent yet
+        if self.connection.sent_syn() {
+            return (self.into(), Err(SendError::NotConnected));
+        }
+
+        let rv = self.connection.send(reader, len);
+        (self.into(), rv)
+    }
+
     fn recv(self, _writer: impl Write, _len: usize) -> (TcpStateEnum<X>, Result<usize, RecvError>) {
         (self.into(), Err(RecvError::NotConnected))
     }
@@ -1107,6 +1159,7 @@ impl<X: Dependencies> TcpStateTrait<X> for EstablishedState<X> {
         len: usize,
     ) -> (TcpStateEnum<X>, Result<usize, SendError>) {
         let rv = self.connection.send(reader, len);
+        update_user_timeout(&self.common, &mut self.connection);
         (self.into(), rv)
     }
 
@@ -1145,6 +1198,9 @@ impl<X: Dependencies> TcpStateTrait<X> for EstablishedState<X> {
             return (new_state, Ok(pushed_len));
         }
 
+        // the peer may have acknowledged new data
+        update_user_timeout(&self.common, &mut self.connection);
+
         // if received FIN, move to the "close-wait" state
         if self.connection.received_fin() {
             let new_state = CloseWaitState::new(self.common, self.connection);
@@ -1276,6 +1332,9 @@ impl<X: Dependencies> TcpStateTrait<X> for FinWaitOneState<X> {
             return (new_state, Ok(pushed_len));
         }
 
+        // the peer may have acknowledged new data
+        update_user_timeout(&self.common, &mut self.connection);
+
         // if received FIN and ACK, move to the "time-wait" state
         if self.connection.received_fin() && self.connection.fin_was_acked() {
             let new_state = TimeWaitState::new(self.common, self.connection);
@@ -1557,6 +1616,9 @@ impl<X: Dependencies> TcpStateTrait<X> for ClosingState<X> {
             return (new_state, Ok(pushed_len));
         }
 
+        // the peer may have acknowledged new data
+        update_user_timeout(&self.common, &mut self.connection);
+
         // if received ACK, move to the "time-wait" state
         if self.connection.fin_was_acked() {
             let new_state = TimeWaitState::new(self.common, self.connection);
@@ -1820,6 +1882,7 @@ impl<X: Dependencies> TcpStateTrait<X> for CloseWaitState<X> {
         len: usize,
     ) -> (TcpStateEnum<X>, Result<usize, SendError>) {
         let rv = self.connection.send(reader, len);
+        update_user_timeout(&self.common, &mut self.connection);
         (self.into(), rv)
     }
 
@@ -1865,6 +1928,9 @@ impl<X: Dependencies> TcpStateTrait<X> for CloseWaitState<X> {
             return (new_state, Ok(pushed_len));
         }
 
+        // the peer may have acknowledged new data
+        update_user_timeout(&self.common, &mut self.connection);
+
         (self.into(), Ok(pushed_len))
     }
 
@@ -1999,6 +2065,9 @@ impl<X: Dependencies> TcpStateTrait<X> for LastAckState<X> {
             return (new_state, Ok(pushed_len));
         }
 
+        // the peer may have acknowledged new data
+        update_user_timeout(&self.common, &mut self.connection);
+
         // if received ACK, move to the "closed" state
         if self.connection.fin_was_acked() {
             let recv_buffer = self.connection.into_recv_buffer();
@@ -2313,6 +2382,58 @@ fn reset_connection<X: Dependencies>(
     new_state
 }
 
+/// Arm a `TCP_USER_TIMEOUT` timer if the connection has data that is waiting to be acknowledged,
+/// and the peer has acknowledged new data since the last timer was armed. A timer that was armed
+/// before the peer acknowledged new data does nothing when it expires.
+fn update_user_timeout<X: Dependencies>(
+    common: &Common<X>,
+    connection: &mut Connection<X::Instant>,
+) {
+    let timeout_ms = connection.config.user_timeout_ms;
+    let acked_up_to = connection.send.buffer.start_seq();
+
+    if timeout_ms == 0 || connection.send.buffer.len() == 0 {
+        connection.send.user_timeout_seq = None;
+        return;
+    }
+
+    // a timer is already armed, and the peer hasn't acknowledged anything since
+    if connection.send.user_timeout_seq == Some(acked_up_to) {
+        return;
+    }
+
+    connection.send.user_timeout_seq = Some(acked_up_to);
+
+    let timeout = common.current_time() + X::Duration::from_millis(timeout_ms.into());
+    common.register_timer(timeout, move |state| {
+        user_timeout_expired(state, acked_up_to)
+    });
+}
+
+/// Reset the connection with a "timed out" error if the peer hasn't acknowledged any new data
+/// since the `TCP_USER_TIMEOUT` timer was armed.
+fn user_timeout_expired<X: Dependencies>(
+    state: TcpStateEnum<X>,
+    acked_up_to: Seq,
+) -> TcpStateEnum<X> {
+    let timed_out = |connection: &Connection<X::Instant>| {
+        connection.config.user_timeout_ms != 0
+            && connection.send.user_timeout_seq == Some(acked_up_to)
+    };
+
+    let (mut common, connection) = match state {
+        TcpStateEnum::Established(x) if timed_out(&x.connection) => (x.common, x.connection),
+        TcpStateEnum::FinWaitOne(x) if timed_out(&x.connection) => (x.common, x.connection),
+        TcpStateEnum::Closing(x) if timed_out(&x.connection) => (x.common, x.connection),
+        TcpStateEnum::CloseWait(x) if timed_out(&x.connection) => (x.common, x.connection),
+        TcpStateEnum::LastAck(x) if timed_out(&x.connection) => (x.common, x.connection),
+        state => return state,
+    };
+
+    common.set_error_if_unset(TcpError::TimedOut);
+    reset_connection(common, connection).into()
+}
+
 /// For a connection that was reset (either by us or by the peer), check if it has a remaining RST
 /// packet to send, and return a new `RstState` that will send this RST packet or a new
 /// `ClosedState` if not.
@@ -664,6 +664,25 @@ impl TcpSocket {
         }
     }
 
+    pub fn send_fast_open(
+        socket: &Rc<RefCell<Self>>,
+        buffer: impl Read,
+        len: usize,
+    ) -> Result<usize, Errno> {
+        let socket_ref = &mut *socket.borrow_mut();
+
+        let rv = socket_ref.with_tcp_state(|state| state.send_fast_open(buffer, len));
+
+        match rv {
+            Ok(n) => Ok(n),
+            Err(SendError::Full) => Err(Errno::EWOULDBLOCK),
+            Err(SendError::NotConnected) => Err(Errno::EPIPE),
+            Err(SendError::StreamClosed) => Err(Errno::EPIPE),
+            Err(SendError::InvalidState) => Err(Errno::EINVAL),
+            Err(SendError::Io(_e)) => Err(Errno::EINVAL),
+        }
+    }
+
     pub fn recvmsg(
         socket: &Rc<RefCell<Self>>,
         buffer: impl Write,
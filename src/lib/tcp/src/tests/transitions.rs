@@ -3,9 +3,11 @@
 use std::cell::{Ref, RefCell};
 use std::rc::Rc;
 
+use bytes::Bytes;
+
 use crate::tests::util::time::Duration;
 use crate::tests::{Errno, Host, Scheduler, TcpSocket, TestEnvState, establish_helper};
-use crate::{Ipv4Header, Payload, TcpConfig, TcpFlags, TcpHeader, TcpState};
+use crate::{Ipv4Header, Payload, TcpConfig, TcpError, TcpFlags, TcpHeader, TcpState};
 
 #[test]
 fn test_close() {
@@ -203,6 +205,84 @@ fn test_accept_close_wait() {
     assert_eq!(s(&tcp).as_listen().unwrap().children.len(), 0);
 }
 
+/// Test that with `TCP_DEFER_ACCEPT`, an established child can't be accept()ed until it receives
+/// data or the defer period ends.
+#[test]
+fn test_accept_deferred() {
+    let scheduler = Scheduler::new();
+    let mut host = Host::new();
+
+    /// Helper to get the state from a socket.
+    fn s(tcp: &Rc<RefCell<TcpSocket>>) -> Ref<TcpState<TestEnvState>> {
+        Ref::map(tcp.borrow(), |x| x.tcp_state())
+    }
+
+    /// Helper to build a packet from the peer.
+    fn header(host: &Host, src_port: u16, flags: TcpFlags, seq: u32) -> TcpHeader {
+        TcpHeader {
+            ip: Ipv4Header {
+                src: "5.6.7.8".parse().unwrap(),
+                dst: host.ip_addr,
+            },
+            flags,
+            src_port,
+            dst_port: 20,
+            seq,
+            ack: 1,
+            window_size: 10000,
+            selective_acks: None,
+            window_scale: None,
+            timestamp: None,
+            timestamp_echo: None,
+        }
+    }
+
+    let mut config = TcpConfig::default();
+    config.defer_accept(5);
+
+    let tcp = TcpSocket::new(&scheduler, config);
+    TcpSocket::listen(&tcp, &mut host, 10).unwrap();
+
+    // complete the handshake for two connections
+    for src_port in [10, 11] {
+        let mut syn = header(&host, src_port, TcpFlags::SYN, 0);
+        syn.ack = 0;
+        tcp.borrow_mut().push_in_packet(&syn, Payload::default());
+
+        let (response_header, _) = scheduler.pop_packet().unwrap();
+        assert_eq!(response_header.flags, TcpFlags::SYN | TcpFlags::ACK);
+
+        let ack = header(&host, src_port, TcpFlags::ACK, 1);
+        tcp.borrow_mut().push_in_packet(&ack, Payload::default());
+    }
+    assert_eq!(s(&tcp).as_listen().unwrap().children.len(), 2);
+
+    // the connections are established, but haven't received any data
+    assert_eq!(
+        tcp.borrow_mut().accept(&mut host).err(),
+        Some(Errno::EAGAIN)
+    );
+
+    // the second connection sends data, so it can be accepted
+    let data = header(&host, 11, TcpFlags::ACK, 1);
+    tcp.borrow_mut()
+        .push_in_packet(&data, Bytes::from(&b"hello"[..]).into());
+
+    let accepted_socket = tcp.borrow_mut().accept(&mut host).unwrap();
+    assert!(s(&accepted_socket).as_established().is_some());
+    assert_eq!(
+        tcp.borrow_mut().accept(&mut host).err(),
+        Some(Errno::EAGAIN)
+    );
+
+    // the first connection can be accepted after the defer period even without any data
+    scheduler.advance(Duration::from_secs(5));
+
+    let accepted_socket = tcp.borrow_mut().accept(&mut host).unwrap();
+    assert!(s(&accepted_socket).as_established().is_some());
+    assert_eq!(s(&tcp).as_listen().unwrap().children.len(), 0);
+}
+
 #[test]
 fn test_connect_active_open() {
     let scheduler = Scheduler::new();
@@ -251,6 +331,85 @@ fn test_connect_active_open() {
     assert_eq!(response_header.flags, TcpFlags::ACK);
 }
 
+/// Test that with TCP fast open, data queued before the SYN is sent is carried in the SYN, and that
+/// a listener delivers the SYN's data to the accept()ed child.
+#[test]
+fn test_connect_fast_open() {
+    let scheduler = Scheduler::new();
+    let mut host = Host::new();
+
+    /// Helper to get the state from a socket.
+    fn s(tcp: &Rc<RefCell<TcpSocket>>) -> Ref<TcpState<TestEnvState>> {
+        Ref::map(tcp.borrow(), |x| x.tcp_state())
+    }
+
+    let tcp = TcpSocket::new(&scheduler, TcpConfig::default());
+
+    // don't send the SYN until the data has been queued
+    tcp.borrow_mut().collect_packets(false);
+    TcpSocket::connect(&tcp, "5.6.7.8:10".parse().unwrap(), &mut host).unwrap();
+    assert!(s(&tcp).as_syn_sent().is_some());
+    assert_eq!(TcpSocket::send_fast_open(&tcp, &b"hello"[..], 5), Ok(5));
+    tcp.borrow_mut().collect_packets(true);
+
+    // read the SYN, which should contain the data
+    let (syn_header, syn_payload) = scheduler.pop_packet().unwrap();
+    assert_eq!(syn_header.flags, TcpFlags::SYN);
+    assert_eq!(syn_payload.concat(), &b"hello"[..]);
+
+    // data can't be added to a SYN that has already been sent
+    assert_eq!(
+        TcpSocket::send_fast_open(&tcp, &b"world"[..], 5),
+        Err(Errno::EPIPE)
+    );
+
+    // give the SYN to a listener, as if it was sent from a peer
+    let listener = TcpSocket::new(&scheduler, TcpConfig::default());
+    TcpSocket::listen(&listener, &mut host, 10).unwrap();
+
+    let syn = TcpHeader {
+        ip: Ipv4Header {
+            src: "5.6.7.8".parse().unwrap(),
+            dst: host.ip_addr,
+        },
+        src_port: 10,
+        dst_port: 20,
+        ..syn_header
+    };
+    listener.borrow_mut().push_in_packet(&syn, syn_payload);
+
+    let (response_header, _) = scheduler.pop_packet().unwrap();
+    assert_eq!(response_header.flags, TcpFlags::SYN | TcpFlags::ACK);
+    assert_eq!(response_header.ack, syn.seq + 1 + 5);
+
+    // acknowledge the SYN+ACK
+    let ack = TcpHeader {
+        ip: syn.ip,
+        flags: TcpFlags::ACK,
+        src_port: syn.src_port,
+        dst_port: syn.dst_port,
+        seq: syn.seq + 1 + 5,
+        ack: response_header.seq + 1,
+        window_size: 10000,
+        selective_acks: None,
+        window_scale: None,
+        timestamp: None,
+        timestamp_echo: None,
+    };
+    listener
+        .borrow_mut()
+        .push_in_packet(&ack, Payload::default());
+
+    // the accept()ed child should have the SYN's data
+    let accepted_socket = listener.borrow_mut().accept(&mut host).unwrap();
+    let mut recv_buf = [0u8; 10];
+    assert_eq!(
+        TcpSocket::recvmsg(&accepted_socket, &mut recv_buf[..], 10),
+        Ok(5)
+    );
+    assert_eq!(&recv_buf[..5], b"hello");
+}
+
 #[test]
 fn test_connect_simultaneous_open() {
     let scheduler = Scheduler::new();
@@ -392,6 +551,68 @@ fn test_passive_close() {
     assert!(s(&tcp).as_closed().is_some());
 }
 
+/// Test that with `TCP_USER_TIMEOUT`, the connection is reset if the peer doesn't acknowledge sent
+/// data before the timeout, and that the timeout restarts when the peer acknowledges data.
+#[test]
+fn test_user_timeout() {
+    let scheduler = Scheduler::new();
+    let mut host = Host::new();
+
+    /// Helper to get the state from a socket.
+    fn s(tcp: &Rc<RefCell<TcpSocket>>) -> Ref<TcpState<TestEnvState>> {
+        Ref::map(tcp.borrow(), |x| x.tcp_state())
+    }
+
+    // get an established tcp socket
+    let tcp = establish_helper(&scheduler, &mut host);
+    tcp.borrow_mut()
+        .with_tcp_state(|state| state.with_config(|config| config.user_timeout(2000)));
+
+    // send on the socket
+    TcpSocket::sendmsg(&tcp, &b"hello"[..], 5).unwrap();
+    let (_, payload) = scheduler.pop_packet().unwrap();
+    assert_eq!(payload.concat()[..], b"hello"[..]);
+
+    // the peer acknowledges the data after one second
+    scheduler.advance(Duration::from_secs(1));
+    let header = TcpHeader {
+        ip: Ipv4Header {
+            src: "5.6.7.8".parse().unwrap(),
+            dst: "1.2.3.4".parse().unwrap(),
+        },
+        flags: TcpFlags::ACK,
+        src_port: 20,
+        dst_port: 10,
+        seq: 1,
+        ack: 6,
+        window_size: 10000,
+        selective_acks: None,
+        window_scale: None,
+        timestamp: None,
+        timestamp_echo: None,
+    };
+    tcp.borrow_mut().push_in_packet(&header, Payload::default());
+
+    // send more data, which the peer never acknowledges
+    TcpSocket::sendmsg(&tcp, &b"world"[..], 5).unwrap();
+    let (_, payload) = scheduler.pop_packet().unwrap();
+    assert_eq!(payload.concat()[..], b"world"[..]);
+
+    // the timeout for the first data shouldn't apply since it was acknowledged
+    scheduler.advance(Duration::from_millis(1500));
+    assert!(s(&tcp).as_established().is_some());
+
+    // the timeout for the second data expires
+    scheduler.advance(Duration::from_millis(500));
+    assert!(s(&tcp).as_established().is_none());
+
+    let (header, _) = scheduler.pop_packet().unwrap();
+    assert!(header.flags.contains(TcpFlags::RST));
+
+    let error = tcp.borrow_mut().with_tcp_state(|state| state.clear_error());
+    assert!(matches!(error, Some(TcpError::TimedOut)));
+}
+
 #[test]
 fn test_active_close_1() {
     let scheduler = Scheduler::new();
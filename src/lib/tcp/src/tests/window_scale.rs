@@ -573,3 +573,64 @@ fn test_duplicate_syn_with_different_window_scale() {
     assert!(connection.window_scaling.is_configured());
     assert_eq!(connection.window_scaling.send_window_scale_shift(), 3);
 }
+
+#[test]
+fn test_large_recv_buffer() {
+    let scheduler = Scheduler::new();
+    let mut host = Host::new();
+
+    /// Helper to get the state from a socket.
+    fn s(tcp: &Rc<RefCell<TcpSocket>>) -> Ref<TcpState<TestEnvState>> {
+        Ref::map(tcp.borrow(), |x| x.tcp_state())
+    }
+
+    // use a receive buffer much larger than the max unscaled window
+    let recv_buffer_size = 4_000_000;
+    let config = TcpConfig {
+        window_scaling_enabled: true,
+        recv_buffer_size,
+        ..Default::default()
+    };
+
+    let tcp = TcpSocket::new(&scheduler, config);
+    assert!(s(&tcp).as_init().is_some());
+
+    TcpSocket::connect(&tcp, "5.6.7.8:10".parse().unwrap(), &mut host).unwrap();
+    assert!(s(&tcp).as_syn_sent().is_some());
+
+    // read the SYN and check that the window scale is large enough for the buffer
+    let (response_header, _) = scheduler.pop_packet().unwrap();
+    assert_eq!(response_header.flags, TcpFlags::SYN);
+    let window_scale = response_header.window_scale.unwrap();
+    assert!((u16::MAX as u32) << window_scale >= recv_buffer_size);
+
+    // get the autobind address of the socket
+    let tcp_bind_addr = response_header.src();
+
+    // send the SYN+ACK with a window scale option
+    let header = TcpHeader {
+        ip: Ipv4Header {
+            src: "5.6.7.8".parse().unwrap(),
+            dst: *tcp_bind_addr.ip(),
+        },
+        flags: TcpFlags::SYN | TcpFlags::ACK,
+        src_port: 10,
+        dst_port: tcp_bind_addr.port(),
+        seq: 0,
+        ack: 1,
+        window_size: 10000,
+        selective_acks: None,
+        window_scale: Some(0),
+        timestamp: None,
+        timestamp_echo: None,
+    };
+    tcp.borrow_mut().push_in_packet(&header, Payload::default());
+    assert!(s(&tcp).as_established().is_some());
+
+    // read the ACK and make sure that the advertised window is larger than 64 KiB
+    let (response_header, _) = scheduler.pop_packet().unwrap();
+    assert_eq!(response_header.flags, TcpFlags::ACK);
+    let advertised_window = u32::from(response_header.window_size) << window_scale;
+    assert!(advertised_window > u16::MAX as u32);
+    assert!(advertised_window <= recv_buffer_size);
+}
@@ -25,7 +25,7 @@ use serde::{Deserialize, Serialize};
 use shadow_shim_helper_rs::simulation_time::SimulationTime;
 
 use crate::cshadow as c;
-use crate::host::syscall::formatter::FmtOptions;
+use crate::host::syscall::formatter::{FmtMode, FmtOptions};
 use crate::utility::units::{self, Unit};
 
 const START_HELP_TEXT: &str = "\
@@ -206,11 +206,18 @@ impl ConfigOptions {
     }
 
     pub fn strace_logging_mode(&self) -> Option<FmtOptions> {
-        match self.experimental.strace_logging_mode.as_ref().unwrap() {
-            StraceLoggingMode::Standard => Some(FmtOptions::Standard),
-            StraceLoggingMode::Deterministic => Some(FmtOptions::Deterministic),
-            StraceLoggingMode::Off => None,
-        }
+        let mode = match self.experimental.strace_logging_mode.as_ref().unwrap() {
+            StraceLoggingMode::Standard => FmtMode::Standard,
+            StraceLoggingMode::Deterministic => FmtMode::Deterministic,
+            StraceLoggingMode::HexDump => FmtMode::HexDump,
+            StraceLoggingMode::Off => return None,
+        };
+
+        Some(FmtOptions {
+            mode,
+            display_len: self.experimental.strace_logging_display_len.unwrap(),
+            utf8_strings: self.experimental.strace_logging_utf8.unwrap(),
+        })
     }
 }
 
@@ -453,6 +460,20 @@ pub struct ExperimentalOptions {
     #[clap(help = EXP_HELP.get("strace_logging_mode").unwrap().as_str())]
     pub strace_logging_mode: Option<StraceLoggingMode>,
 
+    /// Maximum length of the preview shown for byte buffers and strings in "strace" files. Longer
+    /// buffers and strings are truncated.
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "length")]
+    #[clap(help = EXP_HELP.get("strace_logging_display_len").unwrap().as_str())]
+    pub strace_logging_display_len: Option<usize>,
+
+    /// Display strings in "strace" files as UTF-8, only escaping invalid bytes. If false, strings
+    /// are displayed as escaped ASCII.
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "bool")]
+    #[clap(help = EXP_HELP.get("strace_logging_utf8").unwrap().as_str())]
+    pub strace_logging_utf8: Option<bool>,
+
     /// Max amount of execution-time latency allowed to accumulate before the
     /// clock is moved forward. Moving the clock forward is a potentially
     /// expensive operation, so larger values reduce simulation overhead, at the
@@ -566,6 +587,8 @@ impl Default for ExperimentalOptions {
             socket_recv_autotune: Some(true),
             interface_qdisc: Some(QDiscMode::Fifo),
             strace_logging_mode: Some(StraceLoggingMode::Off),
+            strace_logging_display_len: Some(FmtOptions::DEFAULT_DISPLAY_LEN),
+            strace_logging_utf8: Some(false),
             scheduler: Some(Scheduler::ThreadPerCore),
             report_errors_to_stderr: Some(true),
             use_new_tcp: Some(false),
@@ -607,6 +630,12 @@ pub struct HostDefaultOptions {
     #[clap(long, value_name = "bytes")]
     #[clap(help = HOST_HELP.get("pcap_capture_size").unwrap().as_str())]
     pub pcap_capture_size: Option<units::Bytes<units::SiPrefixUpper>>,
+
+    /// Should TCP sockets use selective acknowledgements (SACK)? SACK is negotiated during the
+    /// handshake, and is only supported by the legacy TCP implementation.
+    #[clap(long, value_name = "bool")]
+    #[clap(help = HOST_HELP.get("tcp_sack").unwrap().as_str())]
+    pub tcp_sack: Option<bool>,
 }
 
 impl HostDefaultOptions {
@@ -618,6 +647,7 @@ impl HostDefaultOptions {
             // capture all the data available from the packet". The maximum length of an IP packet
             // (including the header) is 65535 bytes.
             pcap_capture_size: Some(units::Bytes::new(65535, units::SiPrefixUpper::Base)),
+            tcp_sack: Some(true),
         }
     }
 
@@ -640,6 +670,7 @@ impl Default for HostDefaultOptions {
             log_level: None,
             pcap_enabled: None,
             pcap_capture_size: None,
+            tcp_sack: None,
         }
     }
 }
@@ -1174,6 +1205,7 @@ pub enum StraceLoggingMode {
     Off,
     Standard,
     Deterministic,
+    HexDump,
 }
 
 impl FromStr for StraceLoggingMode {
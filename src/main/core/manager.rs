@@ -599,6 +599,7 @@ impl<'a> Manager<'a> {
                     .map(|x| x.to_c_loglevel())
                     .unwrap_or(logger::_LogLevel_LOGLEVEL_UNSET),
                 pcap_config: host_info.pcap_config,
+                tcp_sack: host_info.tcp_sack,
                 qdisc: host_info.qdisc,
                 init_sock_recv_buf_size: host_info.recv_buf_size,
                 autotune_recv_buf: host_info.autotune_recv_buf,
@@ -178,6 +178,7 @@ pub struct HostInfo {
     pub ip_addr: Option<std::net::IpAddr>,
     pub log_level: Option<LogLevel>,
     pub pcap_config: Option<PcapConfig>,
+    pub tcp_sack: bool,
     pub send_buf_size: u64,
     pub recv_buf_size: u64,
     pub autotune_send_buf: bool,
@@ -268,6 +269,7 @@ fn build_host(
                     .unwrap()
                     .value(),
             }),
+        tcp_sack: host.host_options.tcp_sack.unwrap(),
 
         // some options come from the config options and not the host options
         send_buf_size: config
@@ -466,6 +466,11 @@ impl Worker {
         Worker::with(|w| w.shared.is_routable(src, dst)).unwrap()
     }
 
+    /// The latency of the network path from `src` to `dst`, or `None` if there is no such path.
+    pub fn latency(src: std::net::IpAddr, dst: std::net::IpAddr) -> Option<SimulationTime> {
+        Worker::with(|w| w.shared.latency(src, dst)).unwrap()
+    }
+
     pub fn increment_plugin_error_count() {
         Worker::with(|w| w.shared.increment_plugin_error_count()).unwrap()
     }
@@ -1,10 +1,11 @@
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet};
 
+use linux_api::fcntl::DescriptorFlags;
 use log::*;
 use shadow_shim_helper_rs::explicit_drop::ExplicitDrop;
 use shadow_shim_helper_rs::syscall_types::SyscallReg;
 
-use crate::host::descriptor::Descriptor;
+use crate::host::descriptor::{Descriptor, DescriptorKind, FileType};
 use crate::host::host::Host;
 use crate::utility::ObjectCounter;
 use crate::utility::callback_queue::CallbackQueue;
@@ -14,9 +15,12 @@ pub const FD_MAX: u32 = i32::MAX as u32;
 
 /// Map of file handles to file descriptors. Typically owned by a
 /// [`Thread`][crate::host::thread::Thread].
+///
+/// New descriptors are always assigned the lowest available fd, and the table is iterated in fd
+/// order, so that fd numbers and the order in which descriptors are closed are deterministic.
 #[derive(Clone)]
 pub struct DescriptorTable {
-    descriptors: HashMap<DescriptorHandle, Descriptor>,
+    descriptors: BTreeMap<DescriptorHandle, Descriptor>,
 
     // Indices less than `next_index` known to be available.
     available_indices: BTreeSet<u32>,
@@ -31,7 +35,7 @@ pub struct DescriptorTable {
 impl DescriptorTable {
     pub fn new() -> Self {
         DescriptorTable {
-            descriptors: HashMap::new(),
+            descriptors: BTreeMap::new(),
             available_indices: BTreeSet::new(),
             next_index: 0,
             _counter: ObjectCounter::new("DescriptorTable"),
@@ -170,6 +174,44 @@ impl DescriptorTable {
         self.add(desc, min_fd)
     }
 
+    /// Register several descriptors and return their fd handles, in the same order as the
+    /// descriptors. Only fds less than `fd_limit` are used (for example the process' soft
+    /// `RLIMIT_NOFILE`). Either all of the descriptors are registered, or none of them are and the
+    /// descriptors are returned in the `Err`.
+    pub fn register_descriptors(
+        &mut self,
+        descs: Vec<Descriptor>,
+        fd_limit: u32,
+    ) -> Result<Vec<DescriptorHandle>, Vec<Descriptor>> {
+        let mut fds = Vec::with_capacity(descs.len());
+        let mut descs = descs.into_iter();
+
+        while let Some(desc) = descs.next() {
+            let failed_desc = match self.register_descriptor(desc) {
+                Ok(fd) if fd.val() < fd_limit => {
+                    fds.push(fd);
+                    continue;
+                }
+                Ok(fd) => self.deregister_descriptor(fd).unwrap(),
+                Err(desc) => desc,
+            };
+
+            // free the fds in reverse order so that the table is left as it was
+            let mut failed: Vec<_> = fds
+                .into_iter()
+                .rev()
+                .map(|fd| self.deregister_descriptor(fd).unwrap())
+                .collect();
+            failed.reverse();
+            failed.push(failed_desc);
+            failed.extend(descs);
+
+            return Err(failed);
+        }
+
+        Ok(fds)
+    }
+
     /// Register a descriptor with a given fd handle and return the descriptor that it replaced.
     #[must_use]
     pub fn register_descriptor_with_fd(
@@ -203,9 +245,8 @@ impl DescriptorTable {
         &mut self,
         range: impl std::ops::RangeBounds<DescriptorHandle>,
     ) -> impl Iterator<Item = Descriptor> {
-        // This code is not very efficient but it shouldn't be called often, so it should be fine
-        // for now. If we wanted something more efficient, we'd need to redesign the descriptor
-        // table to not use a hash map.
+        // We don't use `BTreeMap::range` since it panics for some ranges (ex: if the start is
+        // greater than the end). This shouldn't be called often, so it should be fine for now.
 
         let fds: Vec<_> = self
             .iter()
@@ -220,10 +261,49 @@ impl DescriptorTable {
         descriptors.into_iter()
     }
 
+    /// Close all descriptors that have the `FD_CLOEXEC` flag set, retaining all others. This
+    /// should be called when the process performs an execve.
+    pub fn do_exec_cloexec(&mut self, host: &Host) {
+        let fds: Vec<_> = self
+            .iter()
+            .filter_map(|(fd, desc)| {
+                desc.flags()
+                    .contains(DescriptorFlags::FD_CLOEXEC)
+                    .then_some(*fd)
+            })
+            .collect();
+
+        let mut descriptors = Vec::with_capacity(fds.len());
+        for fd in fds {
+            trace!("Unregistering FD_CLOEXEC descriptor {fd:?}");
+            descriptors.push((fd, self.deregister_descriptor(fd).unwrap()));
+        }
+
+        CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
+            for (fd, desc) in descriptors {
+                if let Some(Err(e)) = desc.close(host, cb_queue) {
+                    debug!("Error closing {fd:?}: {e:?}");
+                };
+            }
+        });
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (&DescriptorHandle, &Descriptor)> {
         self.descriptors.iter()
     }
 
+    /// Iterate over the open descriptors in fd order, along with the kind of each descriptor and
+    /// the type of its file. None of the files are borrowed, so this can be used for debugging or
+    /// introspection (for example to log a process's open files) even while a syscall handler has
+    /// borrowed one of the files.
+    pub fn file_types(
+        &self,
+    ) -> impl Iterator<Item = (DescriptorHandle, DescriptorKind, FileType)> + '_ {
+        self.descriptors
+            .iter()
+            .map(|(fd, desc)| (*fd, desc.file().kind(), desc.file().file_type()))
+    }
+
     pub fn iter_mut(&mut self) -> impl Iterator<Item = (&DescriptorHandle, &mut Descriptor)> {
         self.descriptors.iter_mut()
     }
@@ -360,3 +440,121 @@ impl std::fmt::Display for DescriptorHandleError {
 }
 
 impl std::error::Error for DescriptorHandleError {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use atomic_refcell::AtomicRefCell;
+
+    use super::*;
+    use crate::host::descriptor::eventfd::EventFd;
+    use crate::host::descriptor::pipe::Pipe;
+    use crate::host::descriptor::socket::Socket;
+    use crate::host::descriptor::socket::abstract_unix_ns::AbstractUnixNamespace;
+    use crate::host::descriptor::socket::unix::{UnixSocket, UnixSocketType};
+    use crate::host::descriptor::{CompatFile, File, FileMode, FileStatus, OpenFile};
+
+    fn new_descriptor(file: File) -> Descriptor {
+        Descriptor::new(CompatFile::New(OpenFile::new(file)))
+    }
+
+    #[test]
+    fn test_file_types() {
+        let namespace = Arc::new(AtomicRefCell::new(AbstractUnixNamespace::new()));
+        let socket = UnixSocket::new(FileStatus::empty(), UnixSocketType::Stream, &namespace);
+        let pipe = Arc::new(AtomicRefCell::new(Pipe::new(
+            FileMode::READ,
+            FileStatus::empty(),
+        )));
+        let eventfd = Arc::new(AtomicRefCell::new(EventFd::new(
+            0,
+            false,
+            FileStatus::empty(),
+        )));
+
+        let mut table = DescriptorTable::new();
+
+        let files = [
+            File::Socket(Socket::Unix(socket)),
+            File::Pipe(Arc::clone(&pipe)),
+            File::EventFd(eventfd),
+            File::Pipe(Arc::clone(&pipe)),
+        ];
+        let fds: Vec<_> = files
+            .into_iter()
+            .map(|file| table.register_descriptor(new_descriptor(file)).unwrap())
+            .collect();
+
+        // leave a gap in the fds
+        let removed = table.deregister_descriptor(fds[2]).unwrap();
+
+        {
+            // the table shouldn't need to borrow the files
+            let _pipe = pipe.borrow_mut();
+
+            assert_eq!(
+                table.file_types().collect::<Vec<_>>(),
+                [
+                    (fds[0], DescriptorKind::New, FileType::Socket),
+                    (fds[1], DescriptorKind::New, FileType::Pipe),
+                    (fds[3], DescriptorKind::New, FileType::Pipe),
+                ],
+            );
+        }
+
+        drop(removed);
+        drop(table);
+    }
+
+    fn new_pipe_descriptor() -> Descriptor {
+        new_descriptor(File::Pipe(Arc::new(AtomicRefCell::new(Pipe::new(
+            FileMode::READ,
+            FileStatus::empty(),
+        )))))
+    }
+
+    #[test]
+    fn test_register_descriptors() {
+        let mut table = DescriptorTable::new();
+
+        let fds = table
+            .register_descriptors(vec![new_pipe_descriptor(), new_pipe_descriptor()], u32::MAX)
+            .unwrap();
+        assert_eq!(
+            fds,
+            [
+                DescriptorHandle::new(0).unwrap(),
+                DescriptorHandle::new(1).unwrap()
+            ]
+        );
+
+        for fd in fds {
+            drop(table.deregister_descriptor(fd).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_register_descriptors_over_limit() {
+        let mut table = DescriptorTable::new();
+
+        let first = table.register_descriptor(new_pipe_descriptor()).unwrap();
+        let second = table.register_descriptor(new_pipe_descriptor()).unwrap();
+
+        // only fd 2 is available below the limit, so neither descriptor should be registered
+        let descs = table
+            .register_descriptors(vec![new_pipe_descriptor(), new_pipe_descriptor()], 3)
+            .unwrap_err();
+        assert_eq!(descs.len(), 2);
+        drop(descs);
+
+        // the table should be unchanged
+        let third = table.register_descriptor(new_pipe_descriptor()).unwrap();
+        assert_eq!(third, DescriptorHandle::new(2).unwrap());
+        assert_eq!(table.file_types().count(), 3);
+
+        for fd in [first, second, third] {
+            drop(table.deregister_descriptor(fd).unwrap());
+        }
+    }
+}
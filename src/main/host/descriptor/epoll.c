@@ -52,6 +52,13 @@ enum _EpollWatchFlags {
     /* used to track that ONESHOT mode is used, an event was already reported, and the
      * socket has not been modified since. This prevents duplicate reporting in ONESHOT mode. */
     EWF_ONESHOT_REPORTED = 1 << 12,
+    /* the underlying shadow descriptor has hung up or has an error, which is always reported */
+    EWF_HUP = 1 << 13,
+    EWF_ERROR = 1 << 14,
+    /* the peer of the underlying shadow descriptor has shut down writing */
+    EWF_RDHUP = 1 << 15,
+    /* the application is waiting for a read hangup event on the underlying shadow descriptor */
+    EWF_WAITINGRDHUP = 1 << 16,
 };
 
 typedef enum _EpollWatchTypes EpollWatchTypes;
@@ -369,8 +376,12 @@ static void _epollwatch_updateStatus(EpollWatch* watch) {
     watch->flags |= (status & FileState_READABLE) ? EWF_READABLE : EWF_NONE;
     watch->flags |= (status & FileState_WRITABLE) ? EWF_WRITEABLE : EWF_NONE;
     watch->flags |= (status & FileState_CLOSED) ? EWF_CLOSED : EWF_NONE;
+    watch->flags |= (status & FileState_HUP) ? EWF_HUP : EWF_NONE;
+    watch->flags |= (status & FileState_ERR) ? EWF_ERROR : EWF_NONE;
+    watch->flags |= (status & FileState_RDHUP) ? EWF_RDHUP : EWF_NONE;
     watch->flags |= (watch->event.events & EPOLLIN) ? EWF_WAITINGREAD : EWF_NONE;
     watch->flags |= (watch->event.events & EPOLLOUT) ? EWF_WAITINGWRITE : EWF_NONE;
+    watch->flags |= (watch->event.events & EPOLLRDHUP) ? EWF_WAITINGRDHUP : EWF_NONE;
     watch->flags |= (watch->event.events & EPOLLET) ? EWF_EDGETRIGGER : EWF_NONE;
     watch->flags |= (watch->event.events & EPOLLONESHOT) ? EWF_ONESHOT : EWF_NONE;
 
@@ -398,6 +409,8 @@ static gboolean _epollwatch_isReady(EpollWatch* watch) {
 
     gboolean hasReadEvent = (watch->flags & EWF_READABLE) && (watch->flags & EWF_WAITINGREAD) ? TRUE : FALSE;
     gboolean hasWriteEvent = (watch->flags & EWF_WRITEABLE) && (watch->flags & EWF_WAITINGWRITE) ? TRUE : FALSE;
+    gboolean hasRdHupEvent = (watch->flags & EWF_RDHUP) && (watch->flags & EWF_WAITINGRDHUP) ? TRUE : FALSE;
+    gboolean hasHangupEvent = (watch->flags & (EWF_HUP | EWF_ERROR)) || hasRdHupEvent ? TRUE : FALSE;
 
     /* figure out if we should report an event */
     if(watch->flags & EWF_EDGETRIGGER) {
@@ -409,9 +422,12 @@ static gboolean _epollwatch_isReady(EpollWatch* watch) {
         if(hasWriteEvent && ((watch->flags & EWF_WRITECHANGED) || !(watch->flags & EWF_EDGETRIGGER_REPORTED))) {
             isReady = TRUE;
         }
+        if(hasHangupEvent && !(watch->flags & EWF_EDGETRIGGER_REPORTED)) {
+            isReady = TRUE;
+        }
     } else {
         /* default level-triggered mode always reports events that exist */
-        if(hasReadEvent || hasWriteEvent) {
+        if(hasReadEvent || hasWriteEvent || hasHangupEvent) {
             isReady =  TRUE;
         }
     }
@@ -527,7 +543,8 @@ gint epoll_control(Epoll* epoll, gint operation, int fd, const Descriptor* descr
              */
             statuslistener_setMonitorStatus(
                 watch->listener,
-                FileState_ACTIVE | FileState_CLOSED | FileState_READABLE | FileState_WRITABLE,
+                FileState_ACTIVE | FileState_CLOSED | FileState_READABLE | FileState_WRITABLE |
+                    FileState_HUP | FileState_ERR | FileState_RDHUP,
                 SLF_ALWAYS);
 
             if (watch->watchType == EWT_LEGACY_FILE) {
@@ -668,6 +685,15 @@ gint epoll_getEvents(Epoll* epoll, struct epoll_event* eventArray, gint eventArr
             if((watch->flags & EWF_WRITEABLE) && (watch->flags & EWF_WAITINGWRITE)) {
                 eventArray[eventIndex].events |= EPOLLOUT;
             }
+            if((watch->flags & EWF_RDHUP) && (watch->flags & EWF_WAITINGRDHUP)) {
+                eventArray[eventIndex].events |= EPOLLRDHUP;
+            }
+            if(watch->flags & EWF_HUP) {
+                eventArray[eventIndex].events |= EPOLLHUP;
+            }
+            if(watch->flags & EWF_ERROR) {
+                eventArray[eventIndex].events |= EPOLLERR;
+            }
 
             /* Record that we are reporting the event now. */
             watch->last_reported_event_time = worker_getCurrentEmulatedTime();
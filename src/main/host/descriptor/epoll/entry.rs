@@ -111,6 +111,12 @@ impl Entry {
         signals
     }
 
+    /// Returns true if the entry was added with `EPOLLEXCLUSIVE`, so that a wakeup of the file only
+    /// wakes up one of the epoll instances that are exclusively monitoring it.
+    pub fn is_exclusive(&self) -> bool {
+        self.interest.contains(EpollEvents::EPOLLEXCLUSIVE)
+    }
+
     pub fn set_listener_handle(&mut self, handle: Option<StateListenHandle>) {
         self.listener_handle = handle;
     }
@@ -179,6 +185,15 @@ impl Entry {
         if state.intersects(FileState::RDHUP) {
             events.insert(EpollEvents::EPOLLRDHUP);
         }
+        if state.intersects(FileState::PRIORITY) {
+            events.insert(EpollEvents::EPOLLPRI);
+        }
+        if state.intersects(FileState::HUP) {
+            events.insert(EpollEvents::EPOLLHUP);
+        }
+        if state.intersects(FileState::ERR) {
+            events.insert(EpollEvents::EPOLLERR);
+        }
 
         events
     }
@@ -195,6 +210,15 @@ impl Entry {
         if events.intersects(EpollEvents::EPOLLRDHUP) {
             state.insert(FileState::RDHUP)
         }
+        if events.intersects(EpollEvents::EPOLLPRI) {
+            state.insert(FileState::PRIORITY)
+        }
+        if events.intersects(EpollEvents::EPOLLHUP) {
+            state.insert(FileState::HUP)
+        }
+        if events.intersects(EpollEvents::EPOLLERR) {
+            state.insert(FileState::ERR)
+        }
 
         state
     }
@@ -323,6 +347,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn poll_on_hup() {
+        // epoll always reports EPOLLERR and EPOLLHUP
+        let interest = EpollEvents::EPOLLIN | EpollEvents::EPOLLERR | EpollEvents::EPOLLHUP;
+
+        let mut entry = Entry::new(interest, DATA, FileState::READABLE);
+        entry.notify(
+            FileState::READABLE | FileState::HUP,
+            FileState::HUP,
+            FileSignals::empty(),
+        );
+        let (ev, _) = entry.collect_ready_events().unwrap();
+        assert_eq!(ev, EpollEvents::EPOLLIN | EpollEvents::EPOLLHUP);
+
+        let mut entry = Entry::new(interest, DATA, FileState::empty());
+        assert!(!entry.has_ready_events());
+        entry.notify(FileState::ERR, FileState::ERR, FileSignals::empty());
+        let (ev, _) = entry.collect_ready_events().unwrap();
+        assert_eq!(ev, EpollEvents::EPOLLERR);
+    }
+
     /// Checks that an entry starting in state `init` is only not ready after `change` turns off
     /// when waiting for `interest`.
     fn poll_off_state(
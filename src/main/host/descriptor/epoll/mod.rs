@@ -192,6 +192,13 @@ impl Epoll {
             }
             EpollCtlOp::EPOLL_CTL_MOD => {
                 let entry = self.monitoring.get_mut(&key).ok_or(Errno::ENOENT)?;
+
+                // From epoll_ctl(2): Returns EINVAL when "op was EPOLL_CTL_MOD and the
+                // EPOLLEXCLUSIVE flag has previously been applied to this epfd, fd pair."
+                if entry.is_exclusive() {
+                    return Err(Errno::EINVAL);
+                }
+
                 entry.modify(events, data, state);
             }
             EpollCtlOp::EPOLL_CTL_DEL => {
@@ -292,7 +299,11 @@ impl Epoll {
         // We always listen for closed so we know when to stop monitoring the entry.
         let listen_state = entry.get_listener_state().union(FileState::CLOSED);
         let listen_signals = entry.get_listener_signals();
-        let filter = StateListenerFilter::Always;
+        let filter = if entry.is_exclusive() {
+            StateListenerFilter::Exclusive
+        } else {
+            StateListenerFilter::Always
+        };
 
         // Set up a callback so we get informed when the file changes.
         let file = key.file().clone();
@@ -364,6 +375,9 @@ impl Epoll {
         !self.ready.is_empty()
     }
 
+    /// Collect up to `max_events` ready events. Entries are reported in the order that they became
+    /// ready, so the order doesn't depend on how entries are stored. Entries that are still ready
+    /// after being reported are moved behind all other ready entries, like Linux's ready list.
     pub fn collect_ready_events(
         &mut self,
         cb_queue: &mut CallbackQueue,
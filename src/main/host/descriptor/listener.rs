@@ -12,6 +12,10 @@ pub enum StateListenerFilter {
     OffToOn,
     OnToOff,
     Always,
+    /// Like `Always`, but when a monitored bit turns on or a monitored signal is emitted, only one
+    /// of the `Exclusive` listeners that are monitoring it is notified, in round-robin order. This
+    /// is used to avoid waking every waiter for `EPOLLEXCLUSIVE`.
+    Exclusive,
 }
 
 /// A wrapper for a `*mut c::StatusListener` that increments its ref count when created,
@@ -124,36 +128,53 @@ impl StateEventSource {
         + Sync
         + 'static,
     ) -> StateListenHandle {
-        self.inner
-            .add_listener(move |(state, changed, signals), cb_queue| {
-                // true if any of the bits we're monitoring have changed
-                let flipped = monitoring_state.intersects(changed);
-
-                // true if any of the bits we're monitoring are set
-                let on = monitoring_state.intersects(state);
-
-                let notify = match filter {
-                    // at least one monitored bit is on, and at least one has changed
-                    StateListenerFilter::OffToOn => flipped && on,
-                    // all monitored bits are off, and at least one has changed
-                    StateListenerFilter::OnToOff => flipped && !on,
-                    // at least one monitored bit has changed
-                    StateListenerFilter::Always => flipped,
-                    StateListenerFilter::Never => false,
+        let listener_fn = move |(state, changed, signals): (FileState, FileState, FileSignals),
+                                cb_queue: &mut CallbackQueue| {
+            // true if any of the bits we're monitoring have changed
+            let flipped = monitoring_state.intersects(changed);
+
+            // true if any of the bits we're monitoring are set
+            let on = monitoring_state.intersects(state);
+
+            let notify = match filter {
+                // at least one monitored bit is on, and at least one has changed
+                StateListenerFilter::OffToOn => flipped && on,
+                // all monitored bits are off, and at least one has changed
+                StateListenerFilter::OnToOff => flipped && !on,
+                // at least one monitored bit has changed
+                StateListenerFilter::Always | StateListenerFilter::Exclusive => flipped,
+                StateListenerFilter::Never => false,
+            };
+
+            // filter the signals to only the ones we're monitoring
+            let signals = signals.intersection(monitoring_signals);
+
+            // also want to notify if a monitored signal was emitted
+            let notify = notify || !signals.is_empty();
+
+            if !notify {
+                return;
+            }
+
+            (notify_fn)(state, changed, signals, cb_queue)
+        };
+
+        if let StateListenerFilter::Exclusive = filter {
+            // a wakeup is when a monitored bit turns on or a monitored signal is emitted; closes,
+            // hangups, and errors are never treated as wakeups so that every listener sees them
+            let always_notify = FileState::CLOSED | FileState::HUP | FileState::ERR;
+            let is_wakeup =
+                move |(state, changed, signals): &(FileState, FileState, FileSignals)| {
+                    if changed.intersects(always_notify) {
+                        return false;
+                    }
+                    monitoring_state.intersects(*changed & *state)
+                        || monitoring_signals.intersects(*signals)
                 };
-
-                // filter the signals to only the ones we're monitoring
-                let signals = signals.intersection(monitoring_signals);
-
-                // also want to notify if a monitored signal was emitted
-                let notify = notify || !signals.is_empty();
-
-                if !notify {
-                    return;
-                }
-
-                (notify_fn)(state, changed, signals, cb_queue)
-            })
+            self.inner.add_exclusive_listener(is_wakeup, listener_fn)
+        } else {
+            self.inner.add_listener(listener_fn)
+        }
     }
 
     pub fn add_legacy_listener(&mut self, ptr: HostTreePointer<c::StatusListener>) {
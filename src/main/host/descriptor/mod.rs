@@ -11,8 +11,10 @@ use crate::core::worker;
 use crate::cshadow as c;
 use crate::host::descriptor::listener::{StateListenHandle, StateListenerFilter};
 use crate::host::descriptor::socket::{Socket, SocketRef, SocketRefMut};
+use crate::host::file_lock_table::{self, FileId, LockOwner, OpenFileId};
 use crate::host::host::Host;
 use crate::host::memory_manager::MemoryManager;
+use crate::host::process::ProcessId;
 use crate::host::syscall::io::IoVec;
 use crate::host::syscall::types::{SyscallError, SyscallResult};
 use crate::utility::callback_queue::CallbackQueue;
@@ -22,7 +24,9 @@ pub mod descriptor_table;
 pub mod epoll;
 pub mod eventfd;
 pub mod listener;
+pub mod pidfd;
 pub mod pipe;
+pub mod proc_fd_dir;
 pub mod shared_buf;
 pub mod socket;
 pub mod timerfd;
@@ -145,6 +149,16 @@ bitflags::bitflags! {
         /// "read hangup" - Stream socket peer has shut down connection for
         /// writing (or completely closed it), as for EPOLLRDHUP.
         const RDHUP = 1 << 7;
+        /// There is urgent (out-of-band) data waiting to be read, as for EPOLLPRI.
+        const PRIORITY = 1 << 8;
+        /// A record lock was released or downgraded, as for a waiting `fcntl(F_SETLKW)`.
+        const LOCK_RELEASED = 1 << 9;
+        /// "hangup" - The peer end of the file has been closed, as for EPOLLHUP. For example the
+        /// read end of a pipe that has no remaining writers.
+        const HUP = 1 << 10;
+        /// An error condition occurred on the file, as for EPOLLERR. For example the write end of
+        /// a pipe that has no remaining readers.
+        const ERR = 1 << 11;
     }
 }
 
@@ -158,6 +172,26 @@ bitflags::bitflags! {
     }
 }
 
+/// The type of an open file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FileType {
+    Pipe,
+    EventFd,
+    Socket,
+    TimerFd,
+    Epoll,
+    PidFd,
+    ProcFdDir,
+    RegularFile,
+}
+
+/// Whether a descriptor refers to a [`File`] or to a legacy C file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DescriptorKind {
+    New,
+    Legacy,
+}
+
 /// A wrapper for any type of file object.
 #[derive(Clone)]
 pub enum File {
@@ -166,6 +200,8 @@ pub enum File {
     Socket(Socket),
     TimerFd(Arc<AtomicRefCell<timerfd::TimerFd>>),
     Epoll(Arc<AtomicRefCell<epoll::Epoll>>),
+    PidFd(Arc<AtomicRefCell<pidfd::PidFd>>),
+    ProcFdDir(Arc<AtomicRefCell<proc_fd_dir::ProcFdDir>>),
 }
 
 // will not compile if `File` is not Send + Sync
@@ -180,6 +216,8 @@ impl File {
             Self::Socket(f) => FileRef::Socket(f.borrow()),
             Self::TimerFd(f) => FileRef::TimerFd(f.borrow()),
             Self::Epoll(f) => FileRef::Epoll(f.borrow()),
+            Self::PidFd(f) => FileRef::PidFd(f.borrow()),
+            Self::ProcFdDir(f) => FileRef::ProcFdDir(f.borrow()),
         }
     }
 
@@ -190,6 +228,8 @@ impl File {
             Self::Socket(f) => FileRef::Socket(f.try_borrow()?),
             Self::TimerFd(f) => FileRef::TimerFd(f.try_borrow()?),
             Self::Epoll(f) => FileRef::Epoll(f.try_borrow()?),
+            Self::PidFd(f) => FileRef::PidFd(f.try_borrow()?),
+            Self::ProcFdDir(f) => FileRef::ProcFdDir(f.try_borrow()?),
         })
     }
 
@@ -200,6 +240,8 @@ impl File {
             Self::Socket(f) => FileRefMut::Socket(f.borrow_mut()),
             Self::TimerFd(f) => FileRefMut::TimerFd(f.borrow_mut()),
             Self::Epoll(f) => FileRefMut::Epoll(f.borrow_mut()),
+            Self::PidFd(f) => FileRefMut::PidFd(f.borrow_mut()),
+            Self::ProcFdDir(f) => FileRefMut::ProcFdDir(f.borrow_mut()),
         }
     }
 
@@ -210,6 +252,8 @@ impl File {
             Self::Socket(f) => FileRefMut::Socket(f.try_borrow_mut()?),
             Self::TimerFd(f) => FileRefMut::TimerFd(f.try_borrow_mut()?),
             Self::Epoll(f) => FileRefMut::Epoll(f.try_borrow_mut()?),
+            Self::PidFd(f) => FileRefMut::PidFd(f.try_borrow_mut()?),
+            Self::ProcFdDir(f) => FileRefMut::ProcFdDir(f.try_borrow_mut()?),
         })
     }
 
@@ -220,6 +264,21 @@ impl File {
             Self::Socket(f) => f.canonical_handle(),
             Self::TimerFd(f) => Arc::as_ptr(f) as usize,
             Self::Epoll(f) => Arc::as_ptr(f) as usize,
+            Self::PidFd(f) => Arc::as_ptr(f) as usize,
+            Self::ProcFdDir(f) => Arc::as_ptr(f) as usize,
+        }
+    }
+
+    /// The type of the file. This doesn't borrow the file.
+    pub fn file_type(&self) -> FileType {
+        match self {
+            Self::Pipe(_) => FileType::Pipe,
+            Self::EventFd(_) => FileType::EventFd,
+            Self::Socket(_) => FileType::Socket,
+            Self::TimerFd(_) => FileType::TimerFd,
+            Self::Epoll(_) => FileType::Epoll,
+            Self::PidFd(_) => FileType::PidFd,
+            Self::ProcFdDir(_) => FileType::ProcFdDir,
         }
     }
 }
@@ -232,6 +291,8 @@ impl std::fmt::Debug for File {
             Self::Socket(_) => write!(f, "Socket")?,
             Self::TimerFd(_) => write!(f, "TimerFd")?,
             Self::Epoll(_) => write!(f, "Epoll")?,
+            Self::PidFd(_) => write!(f, "PidFd")?,
+            Self::ProcFdDir(_) => write!(f, "ProcFdDir")?,
         }
 
         if let Ok(file) = self.try_borrow() {
@@ -251,6 +312,8 @@ pub enum FileRef<'a> {
     Socket(SocketRef<'a>),
     TimerFd(atomic_refcell::AtomicRef<'a, timerfd::TimerFd>),
     Epoll(atomic_refcell::AtomicRef<'a, epoll::Epoll>),
+    PidFd(atomic_refcell::AtomicRef<'a, pidfd::PidFd>),
+    ProcFdDir(atomic_refcell::AtomicRef<'a, proc_fd_dir::ProcFdDir>),
 }
 
 /// Wraps a mutably borrowed [`File`]. Created from [`File::borrow_mut`] or
@@ -261,61 +324,63 @@ pub enum FileRefMut<'a> {
     Socket(SocketRefMut<'a>),
     TimerFd(atomic_refcell::AtomicRefMut<'a, timerfd::TimerFd>),
     Epoll(atomic_refcell::AtomicRefMut<'a, epoll::Epoll>),
+    PidFd(atomic_refcell::AtomicRefMut<'a, pidfd::PidFd>),
+    ProcFdDir(atomic_refcell::AtomicRefMut<'a, proc_fd_dir::ProcFdDir>),
 }
 
 impl FileRef<'_> {
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn state(&self) -> FileState
     );
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn mode(&self) -> FileMode
     );
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn status(&self) -> FileStatus
     );
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn stat(&self) -> Result<linux_api::stat::stat, SyscallError>
     );
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn has_open_file(&self) -> bool
     );
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn supports_sa_restart(&self) -> bool
     );
 }
 
 impl FileRefMut<'_> {
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn state(&self) -> FileState
     );
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn mode(&self) -> FileMode
     );
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn status(&self) -> FileStatus
     );
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn stat(&self) -> Result<linux_api::stat::stat, SyscallError>
     );
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn has_open_file(&self) -> bool
     );
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn supports_sa_restart(&self) -> bool
     );
-    enum_passthrough!(self, (val), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (val), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn set_has_open_file(&mut self, val: bool)
     );
-    enum_passthrough!(self, (cb_queue), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (cb_queue), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError>
     );
-    enum_passthrough!(self, (status), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (status), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn set_status(&mut self, status: FileStatus)
     );
-    enum_passthrough!(self, (request, arg_ptr, memory_manager), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (request, arg_ptr, memory_manager), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn ioctl(&mut self, request: IoctlRequest, arg_ptr: ForeignPtr<()>, memory_manager: &mut MemoryManager) -> SyscallResult
     );
-    enum_passthrough!(self, (monitoring_state, monitoring_signals, filter, notify_fn), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (monitoring_state, monitoring_signals, filter, notify_fn), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn add_listener(
             &mut self,
             monitoring_state: FileState,
@@ -324,17 +389,17 @@ impl FileRefMut<'_> {
             notify_fn: impl Fn(FileState, FileState, FileSignals, &mut CallbackQueue) + Send + Sync + 'static,
         ) -> StateListenHandle
     );
-    enum_passthrough!(self, (ptr), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (ptr), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn add_legacy_listener(&mut self, ptr: HostTreePointer<c::StatusListener>)
     );
-    enum_passthrough!(self, (ptr), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (ptr), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn remove_legacy_listener(&mut self, ptr: *mut c::StatusListener)
     );
-    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn readv(&mut self, iovs: &[IoVec], offset: Option<libc::off_t>, flags: libc::c_int,
                      mem: &mut MemoryManager, cb_queue: &mut CallbackQueue) -> Result<libc::ssize_t, SyscallError>
     );
-    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, ProcFdDir;
         pub fn writev(&mut self, iovs: &[IoVec], offset: Option<libc::off_t>, flags: libc::c_int,
                       mem: &mut MemoryManager, cb_queue: &mut CallbackQueue) -> Result<libc::ssize_t, SyscallError>
     );
@@ -348,6 +413,8 @@ impl std::fmt::Debug for FileRef<'_> {
             Self::Socket(_) => write!(f, "Socket")?,
             Self::TimerFd(_) => write!(f, "TimerFd")?,
             Self::Epoll(_) => write!(f, "Epoll")?,
+            Self::PidFd(_) => write!(f, "PidFd")?,
+            Self::ProcFdDir(_) => write!(f, "ProcFdDir")?,
         }
 
         let state = self.state();
@@ -364,6 +431,8 @@ impl std::fmt::Debug for FileRefMut<'_> {
             Self::Socket(_) => write!(f, "Socket")?,
             Self::TimerFd(_) => write!(f, "TimerFd")?,
             Self::Epoll(_) => write!(f, "Epoll")?,
+            Self::PidFd(_) => write!(f, "PidFd")?,
+            Self::ProcFdDir(_) => write!(f, "ProcFdDir")?,
         }
 
         let state = self.state();
@@ -516,6 +585,14 @@ impl Descriptor {
         host: &Host,
         cb_queue: &mut CallbackQueue,
     ) -> Option<Result<(), SyscallError>> {
+        // closing any of a process' descriptors for a file releases the process' record locks on
+        // that file, even if the process has other descriptors open for the same file
+        if let CompatFile::Legacy(file) = &self.file {
+            if let Some(pid) = worker::Worker::active_process_id() {
+                file.release_process_locks(host, pid, cb_queue);
+            }
+        }
+
         self.file.close(host, cb_queue)
     }
 
@@ -621,6 +698,8 @@ pub struct LegacyFileCounter {
     file: Option<CountedLegacyFileRef>,
     /// A count of how many open descriptors there are with reference to this legacy file.
     open_count: Arc<()>,
+    /// Identifies the open file description, which is shared by all of its descriptors.
+    open_file_id: OpenFileId,
 }
 
 impl LegacyFileCounter {
@@ -628,6 +707,7 @@ impl LegacyFileCounter {
         Self {
             file: Some(file),
             open_count: Arc::new(()),
+            open_file_id: OpenFileId::new(),
         }
     }
 
@@ -635,12 +715,46 @@ impl LegacyFileCounter {
         unsafe { self.file.as_ref().unwrap().ptr() }
     }
 
+    /// The id of the open file description, which owns any open file description locks.
+    pub fn open_file_id(&self) -> OpenFileId {
+        self.open_file_id
+    }
+
+    /// Release any record locks that the process holds on this file.
+    fn release_process_locks(&self, host: &Host, pid: ProcessId, cb_queue: &mut CallbackQueue) {
+        let ptr = self.ptr();
+        if unsafe { c::legacyfile_getType(ptr) } != c::_LegacyFileType_DT_FILE {
+            return;
+        }
+
+        let mut lock_table = host.file_lock_table_borrow_mut();
+
+        // avoid a stat syscall on every close if no files are locked
+        if lock_table.is_empty() {
+            return;
+        }
+
+        let Ok(stat) = (unsafe { file_lock_table::regular_file_stat(ptr as *mut c::RegularFile) })
+        else {
+            return;
+        };
+
+        lock_table.release_file_owner(FileId::from(&stat), LockOwner::Process(pid), cb_queue);
+    }
+
     /// Should drop `self` immediately after calling this.
     fn close_helper(&mut self, host: &Host) {
         // this isn't subject to race conditions since we should never access descriptors
         // from multiple threads at the same time
         if Arc::<()>::strong_count(&self.open_count) == 1 {
             if let Some(file) = self.file.take() {
+                // the open file description is going away, so release its record locks
+                let owner = LockOwner::OpenFile(self.open_file_id);
+                CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
+                    host.file_lock_table_borrow_mut()
+                        .release_owner(owner, cb_queue)
+                });
+
                 unsafe { c::legacyfile_close(file.ptr(), host) }
             }
         }
@@ -667,6 +781,26 @@ pub enum CompatFile {
 }
 
 impl CompatFile {
+    pub fn kind(&self) -> DescriptorKind {
+        match self {
+            Self::New(_) => DescriptorKind::New,
+            Self::Legacy(_) => DescriptorKind::Legacy,
+        }
+    }
+
+    /// The type of the file. This doesn't borrow the file.
+    pub fn file_type(&self) -> FileType {
+        match self {
+            Self::New(file) => file.inner_file().file_type(),
+            Self::Legacy(file) => match unsafe { c::legacyfile_getType(file.ptr()) } {
+                c::_LegacyFileType_DT_TCPSOCKET => FileType::Socket,
+                c::_LegacyFileType_DT_EPOLL => FileType::Epoll,
+                c::_LegacyFileType_DT_FILE => FileType::RegularFile,
+                x => panic!("Unexpected legacy file type: {x}"),
+            },
+        }
+    }
+
     /// Close the file. The `host` option is a legacy option for legacy files.
     pub fn close(
         self,
@@ -0,0 +1,195 @@
+use linux_api::errno::Errno;
+use linux_api::ioctls::IoctlRequest;
+use shadow_shim_helper_rs::syscall_types::ForeignPtr;
+
+use crate::cshadow as c;
+use crate::host::descriptor::listener::{StateEventSource, StateListenHandle, StateListenerFilter};
+use crate::host::descriptor::{FileMode, FileSignals, FileState, FileStatus};
+use crate::host::memory_manager::MemoryManager;
+use crate::host::process::ProcessId;
+use crate::host::syscall::io::IoVec;
+use crate::host::syscall::types::{SyscallError, SyscallResult};
+use crate::utility::HostTreePointer;
+use crate::utility::callback_queue::CallbackQueue;
+
+/// A file that refers to a process. The file becomes readable when the process exits.
+pub struct PidFd {
+    pid: ProcessId,
+    event_source: StateEventSource,
+    state: FileState,
+    status: FileStatus,
+    // should only be used by `OpenFile` to make sure there is only ever one `OpenFile` instance for
+    // this file
+    has_open_file: bool,
+}
+
+impl PidFd {
+    /// Create a new [`PidFd`] for the process `pid`. The process should call
+    /// [`PidFd::set_exited`] when it exits, or `has_exited` should be set if the process has
+    /// already exited.
+    pub fn new(pid: ProcessId, has_exited: bool, status: FileStatus) -> Self {
+        let mut state = FileState::ACTIVE;
+        state.set(FileState::READABLE, has_exited);
+
+        Self {
+            pid,
+            event_source: StateEventSource::new(),
+            state,
+            status,
+            has_open_file: false,
+        }
+    }
+
+    /// The process that this file refers to.
+    pub fn pid(&self) -> ProcessId {
+        self.pid
+    }
+
+    pub fn status(&self) -> FileStatus {
+        self.status
+    }
+
+    pub fn set_status(&mut self, status: FileStatus) {
+        self.status = status;
+    }
+
+    pub fn mode(&self) -> FileMode {
+        FileMode::READ
+    }
+
+    pub fn has_open_file(&self) -> bool {
+        self.has_open_file
+    }
+
+    pub fn supports_sa_restart(&self) -> bool {
+        false
+    }
+
+    pub fn set_has_open_file(&mut self, val: bool) {
+        self.has_open_file = val;
+    }
+
+    /// Mark the process as having exited, which makes the file readable.
+    pub fn set_exited(&mut self, cb_queue: &mut CallbackQueue) {
+        if self.state.contains(FileState::CLOSED) {
+            return;
+        }
+
+        self.update_state(
+            FileState::READABLE,
+            FileState::READABLE,
+            FileSignals::empty(),
+            cb_queue,
+        );
+    }
+
+    pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError> {
+        // set the closed flag and remove the active and readable flags
+        self.update_state(
+            FileState::CLOSED | FileState::ACTIVE | FileState::READABLE,
+            FileState::CLOSED,
+            FileSignals::empty(),
+            cb_queue,
+        );
+
+        Ok(())
+    }
+
+    pub fn readv(
+        &mut self,
+        _iovs: &[IoVec],
+        _offset: Option<libc::off_t>,
+        _flags: libc::c_int,
+        _mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::ssize_t, SyscallError> {
+        // pidfds can only be polled
+        Err(Errno::EINVAL.into())
+    }
+
+    pub fn writev(
+        &mut self,
+        _iovs: &[IoVec],
+        _offset: Option<libc::off_t>,
+        _flags: libc::c_int,
+        _mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::ssize_t, SyscallError> {
+        // pidfds can only be polled
+        Err(Errno::EINVAL.into())
+    }
+
+    pub fn ioctl(
+        &mut self,
+        request: IoctlRequest,
+        _arg_ptr: ForeignPtr<()>,
+        _memory_manager: &mut MemoryManager,
+    ) -> SyscallResult {
+        log::warn!("We do not yet handle ioctl request {request:?} on pidfds");
+        Err(Errno::EINVAL.into())
+    }
+
+    pub fn stat(&self) -> Result<linux_api::stat::stat, SyscallError> {
+        warn_once_then_debug!("We do not yet handle stat calls on pidfds");
+        Err(Errno::EINVAL.into())
+    }
+
+    pub fn add_listener(
+        &mut self,
+        monitoring_state: FileState,
+        monitoring_signals: FileSignals,
+        filter: StateListenerFilter,
+        notify_fn: impl Fn(FileState, FileState, FileSignals, &mut CallbackQueue)
+        + Send
+        + Sync
+        + 'static,
+    ) -> StateListenHandle {
+        self.event_source
+            .add_listener(monitoring_state, monitoring_signals, filter, notify_fn)
+    }
+
+    pub fn add_legacy_listener(&mut self, ptr: HostTreePointer<c::StatusListener>) {
+        self.event_source.add_legacy_listener(ptr);
+    }
+
+    pub fn remove_legacy_listener(&mut self, ptr: *mut c::StatusListener) {
+        self.event_source.remove_legacy_listener(ptr);
+    }
+
+    pub fn state(&self) -> FileState {
+        self.state
+    }
+
+    fn update_state(
+        &mut self,
+        mask: FileState,
+        state: FileState,
+        signals: FileSignals,
+        cb_queue: &mut CallbackQueue,
+    ) {
+        let old_state = self.state;
+
+        // remove the masked flags, then copy the masked flags
+        self.state.remove(mask);
+        self.state.insert(state & mask);
+
+        self.handle_state_change(old_state, signals, cb_queue);
+    }
+
+    fn handle_state_change(
+        &mut self,
+        old_state: FileState,
+        signals: FileSignals,
+        cb_queue: &mut CallbackQueue,
+    ) {
+        let states_changed = self.state ^ old_state;
+
+        // if nothing changed
+        if states_changed.is_empty() && signals.is_empty() {
+            return;
+        }
+
+        self.event_source
+            .notify_listeners(self.state, states_changed, signals, cb_queue);
+    }
+}
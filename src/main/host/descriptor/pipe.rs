@@ -110,9 +110,14 @@ impl Pipe {
         // no need to hold on to the buffer anymore
         self.buffer = None;
 
-        // set the closed flag and remove the active, readable, and writable flags
+        // set the closed flag and remove the active, readable, writable, and hangup/error flags
         self.update_state(
-            FileState::CLOSED | FileState::ACTIVE | FileState::READABLE | FileState::WRITABLE,
+            FileState::CLOSED
+                | FileState::ACTIVE
+                | FileState::READABLE
+                | FileState::WRITABLE
+                | FileState::HUP
+                | FileState::ERR,
             FileState::CLOSED,
             FileSignals::empty(),
             cb_queue,
@@ -205,7 +210,16 @@ impl Pipe {
         let mut reader = IoVecReader::new(iovs, mem);
 
         let num_copied = match self.write_mode {
-            WriteMode::Stream => buffer.write_stream(&mut reader, len, cb_queue)?,
+            WriteMode::Stream => {
+                // pipe(7): "POSIX.1 says that write(2)s of less than PIPE_BUF bytes must be atomic:
+                // the output data is written to the pipe as a contiguous sequence. Writes of more
+                // than PIPE_BUF bytes may be nonatomic"
+                if len <= libc::PIPE_BUF && len > buffer.space_available() {
+                    return Err(Errno::EAGAIN.into());
+                }
+
+                buffer.write_stream(&mut reader, len, cb_queue)?
+            }
             WriteMode::Packet => {
                 let mut num_written = 0;
 
@@ -237,6 +251,55 @@ impl Pipe {
         Ok(num_copied.try_into().unwrap())
     }
 
+    /// Copy up to `len` bytes from the front of this pipe's buffer to the `dst` pipe's buffer
+    /// without consuming them. Returns `EWOULDBLOCK` if this pipe has no data but still has
+    /// writers, or if `dst` has no space available.
+    pub fn tee(
+        &self,
+        dst: &mut Pipe,
+        len: usize,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<usize, SyscallError> {
+        // tee(2): "EBADF: fd_in or fd_out is not valid or does not have proper read-write mode"
+        if !self.mode.contains(FileMode::READ) || !dst.mode.contains(FileMode::WRITE) {
+            return Err(Errno::EBADF.into());
+        }
+
+        let src_buffer = self.buffer.as_ref().unwrap();
+        let dst_buffer = dst.buffer.as_ref().unwrap();
+
+        // tee(2): "EINVAL: [...] or fd_in and fd_out refer to the same pipe"
+        if Arc::ptr_eq(src_buffer, dst_buffer) {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let src_buffer = src_buffer.borrow();
+        let mut dst_buffer = dst_buffer.borrow_mut();
+
+        if !src_buffer.has_data() {
+            // there's nothing to copy, and there never will be if there are no writers
+            if src_buffer.num_writers() == 0 {
+                return Ok(0);
+            }
+            return Err(Errno::EWOULDBLOCK.into());
+        }
+
+        if dst_buffer.num_readers() == 0 {
+            return Err(Errno::EPIPE.into());
+        }
+
+        let len = std::cmp::min(len, dst_buffer.space_available());
+        if len == 0 {
+            return Err(Errno::EWOULDBLOCK.into());
+        }
+
+        let mut bytes = vec![0u8; len];
+        let (num_copied, _num_in_buf) = src_buffer.peek(&mut bytes[..])?;
+        let num_written = dst_buffer.write_stream(&bytes[..num_copied], num_copied, cb_queue)?;
+
+        Ok(num_written)
+    }
+
     pub fn ioctl(
         &mut self,
         request: IoctlRequest,
@@ -411,11 +474,15 @@ impl Pipe {
 
         // only update the readable state if the file is open for reading
         if self.mode.contains(FileMode::READ) {
-            mask.insert(FileState::READABLE);
-            // file is readable if the buffer is readable or there are no writers
-            if buffer_state.intersects(BufferState::READABLE | BufferState::NO_WRITERS) {
+            mask.insert(FileState::READABLE | FileState::HUP);
+            // file is readable if the buffer is readable
+            if buffer_state.intersects(BufferState::READABLE) {
                 file_state.insert(FileState::READABLE);
             }
+            // file is hung up if there are no writers (any remaining data is still readable)
+            if buffer_state.intersects(BufferState::NO_WRITERS) {
+                file_state.insert(FileState::HUP);
+            }
             if buffer_signals.intersects(BufferSignals::BUFFER_GREW) {
                 file_signals.insert(FileSignals::READ_BUFFER_GREW);
             }
@@ -423,11 +490,15 @@ impl Pipe {
 
         // only update the writable state if the file is open for writing
         if self.mode.contains(FileMode::WRITE) {
-            mask.insert(FileState::WRITABLE);
-            // file is writable if the buffer is writable or there are no readers
-            if buffer_state.intersects(BufferState::WRITABLE | BufferState::NO_READERS) {
+            mask.insert(FileState::WRITABLE | FileState::ERR);
+            // file is writable if the buffer is writable
+            if buffer_state.intersects(BufferState::WRITABLE) {
                 file_state.insert(FileState::WRITABLE);
             }
+            // writing to the file is an error if there are no readers
+            if buffer_state.intersects(BufferState::NO_READERS) {
+                file_state.insert(FileState::ERR);
+            }
         }
 
         // update the file's state
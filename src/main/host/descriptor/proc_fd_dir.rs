@@ -0,0 +1,227 @@
+use linux_api::errno::Errno;
+use linux_api::ioctls::IoctlRequest;
+use linux_api::stat::SFlag;
+use shadow_shim_helper_rs::syscall_types::ForeignPtr;
+
+use crate::cshadow as c;
+use crate::host::descriptor::listener::{StateEventSource, StateListenHandle, StateListenerFilter};
+use crate::host::descriptor::{FileMode, FileSignals, FileState, FileStatus};
+use crate::host::memory_manager::MemoryManager;
+use crate::host::process::ProcessId;
+use crate::host::syscall::io::IoVec;
+use crate::host::syscall::types::{SyscallError, SyscallResult};
+use crate::utility::HostTreePointer;
+use crate::utility::callback_queue::CallbackQueue;
+
+/// A directory that lists the open file descriptors of a process, like linux's `/proc/<pid>/fd`.
+/// The directory doesn't store any entries; they're generated from the process' descriptor table
+/// each time the directory is read.
+pub struct ProcFdDir {
+    pid: ProcessId,
+    /// The directory offset of the next entry to read. The offset of the "." and ".." entries are
+    /// 0 and 1, and the offset of the entry for fd `n` is `n + 2`. This way the directory position
+    /// stays valid when descriptors are opened or closed between reads.
+    position: u64,
+    event_source: StateEventSource,
+    state: FileState,
+    status: FileStatus,
+    // should only be used by `OpenFile` to make sure there is only ever one `OpenFile` instance for
+    // this file
+    has_open_file: bool,
+}
+
+impl ProcFdDir {
+    pub fn new(pid: ProcessId, status: FileStatus) -> Self {
+        Self {
+            pid,
+            position: 0,
+            event_source: StateEventSource::new(),
+            state: FileState::ACTIVE | FileState::READABLE,
+            status,
+            has_open_file: false,
+        }
+    }
+
+    /// The process whose descriptors this directory lists.
+    pub fn pid(&self) -> ProcessId {
+        self.pid
+    }
+
+    /// The directory offset of the next entry to read.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: u64) {
+        self.position = position;
+    }
+
+    /// Change the directory position like `lseek()`. Only seeking to an absolute position or
+    /// reading the current position is supported.
+    pub fn seek(&mut self, offset: libc::off_t, whence: std::ffi::c_uint) -> Result<u64, Errno> {
+        let position = match (whence as i32, offset) {
+            (libc::SEEK_SET, _) => u64::try_from(offset).or(Err(Errno::EINVAL))?,
+            (libc::SEEK_CUR, 0) => self.position,
+            _ => return Err(Errno::EINVAL),
+        };
+
+        self.position = position;
+        Ok(position)
+    }
+
+    pub fn status(&self) -> FileStatus {
+        self.status
+    }
+
+    pub fn set_status(&mut self, status: FileStatus) {
+        self.status = status;
+    }
+
+    pub fn mode(&self) -> FileMode {
+        FileMode::READ
+    }
+
+    pub fn has_open_file(&self) -> bool {
+        self.has_open_file
+    }
+
+    pub fn supports_sa_restart(&self) -> bool {
+        false
+    }
+
+    pub fn set_has_open_file(&mut self, val: bool) {
+        self.has_open_file = val;
+    }
+
+    pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError> {
+        // set the closed flag and remove the active and readable flags
+        self.update_state(
+            FileState::CLOSED | FileState::ACTIVE | FileState::READABLE,
+            FileState::CLOSED,
+            FileSignals::empty(),
+            cb_queue,
+        );
+
+        Ok(())
+    }
+
+    pub fn readv(
+        &mut self,
+        _iovs: &[IoVec],
+        _offset: Option<libc::off_t>,
+        _flags: libc::c_int,
+        _mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::ssize_t, SyscallError> {
+        // directories can only be read using getdents
+        Err(Errno::EISDIR.into())
+    }
+
+    pub fn writev(
+        &mut self,
+        _iovs: &[IoVec],
+        _offset: Option<libc::off_t>,
+        _flags: libc::c_int,
+        _mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::ssize_t, SyscallError> {
+        // the directory is only ever open for reading
+        Err(Errno::EBADF.into())
+    }
+
+    pub fn ioctl(
+        &mut self,
+        request: IoctlRequest,
+        _arg_ptr: ForeignPtr<()>,
+        _memory_manager: &mut MemoryManager,
+    ) -> SyscallResult {
+        log::warn!("We do not yet handle ioctl request {request:?} on /proc fd directories");
+        Err(Errno::ENOTTY.into())
+    }
+
+    pub fn stat(&self) -> Result<linux_api::stat::stat, SyscallError> {
+        warn_once_then_debug!("Not all fields of 'struct stat' are implemented for /proc fd dirs");
+
+        Ok(linux_api::stat::stat {
+            st_dev: 0,
+            st_ino: 0,
+            st_nlink: 2,
+            // linux uses 'dr-x------' for these directories
+            st_mode: (SFlag::S_IFDIR | SFlag::S_IRUSR | SFlag::S_IXUSR).bits(),
+            // shadow pretends to run as root
+            st_uid: 0,
+            st_gid: 0,
+            l__pad0: 0,
+            st_rdev: 0,
+            st_size: 0,
+            st_blksize: 1024,
+            st_blocks: 0,
+            st_atime: 0,
+            st_atime_nsec: 0,
+            st_mtime: 0,
+            st_mtime_nsec: 0,
+            st_ctime: 0,
+            st_ctime_nsec: 0,
+            l__unused: [0; 3],
+        })
+    }
+
+    pub fn add_listener(
+        &mut self,
+        monitoring_state: FileState,
+        monitoring_signals: FileSignals,
+        filter: StateListenerFilter,
+        notify_fn: impl Fn(FileState, FileState, FileSignals, &mut CallbackQueue)
+        + Send
+        + Sync
+        + 'static,
+    ) -> StateListenHandle {
+        self.event_source
+            .add_listener(monitoring_state, monitoring_signals, filter, notify_fn)
+    }
+
+    pub fn add_legacy_listener(&mut self, ptr: HostTreePointer<c::StatusListener>) {
+        self.event_source.add_legacy_listener(ptr);
+    }
+
+    pub fn remove_legacy_listener(&mut self, ptr: *mut c::StatusListener) {
+        self.event_source.remove_legacy_listener(ptr);
+    }
+
+    pub fn state(&self) -> FileState {
+        self.state
+    }
+
+    fn update_state(
+        &mut self,
+        mask: FileState,
+        state: FileState,
+        signals: FileSignals,
+        cb_queue: &mut CallbackQueue,
+    ) {
+        let old_state = self.state;
+
+        // remove the masked flags, then copy the masked flags
+        self.state.remove(mask);
+        self.state.insert(state & mask);
+
+        self.handle_state_change(old_state, signals, cb_queue);
+    }
+
+    fn handle_state_change(
+        &mut self,
+        old_state: FileState,
+        signals: FileSignals,
+        cb_queue: &mut CallbackQueue,
+    ) {
+        let states_changed = self.state ^ old_state;
+
+        // if nothing changed
+        if states_changed.is_empty() && signals.is_empty() {
+            return;
+        }
+
+        self.event_source
+            .notify_listeners(self.state, states_changed, signals, cb_queue);
+    }
+}
@@ -273,6 +273,34 @@ int _regularfile_initRoInMemoryFile(RegularFile* file, int flags, mode_t mode, s
     return 0;
 }
 
+/* Open an os-backed file. The managed process's umask has already been applied to `mode`, so if the
+ * file is created, its mode is set to exactly `mode` rather than also applying shadow's own umask.
+ * Returns the fd, or -1 and sets errno. */
+static int _regularfile_openWithExactMode(const char* path, int flags, mode_t mode) {
+    bool isTmpFile = (flags & O_TMPFILE) == O_TMPFILE;
+
+    if (!(flags & O_CREAT) && !isTmpFile) {
+        return open(path, flags, mode);
+    }
+
+    int osfd = -1;
+    if (isTmpFile || (flags & O_EXCL)) {
+        osfd = open(path, flags, mode);
+    } else {
+        /* we only change the mode if we're the one that created the file */
+        osfd = open(path, flags | O_EXCL, mode);
+        if (osfd < 0 && errno == EEXIST) {
+            return open(path, flags & ~O_CREAT, mode);
+        }
+    }
+
+    if (osfd >= 0 && fchmod(osfd, mode & 07777) < 0) {
+        trace("Could not set the mode of new file '%s': %s", path, strerror(errno));
+    }
+
+    return osfd;
+}
+
 int regularfile_openat(RegularFile* file, RegularFile* dir, const char* pathname, int flags,
                        mode_t mode, const char* workingDir) {
     MAGIC_ASSERT(file);
@@ -286,6 +314,11 @@ int regularfile_openat(RegularFile* file, RegularFile* dir, const char* pathname
     }
 #endif
 
+    /* In-memory files are never directories, so can't be used to resolve relative paths. */
+    if (dir && dir->type == FILE_TYPE_IN_MEMORY && pathname[0] != '/') {
+        return -ENOTDIR;
+    }
+
     /* The default case is a regular file. We do this first so that we have
      * an absolute path to compare for special files. */
     char* abspath = _regularfile_getAbsolutePath(dir, pathname, workingDir);
@@ -332,7 +365,7 @@ int regularfile_openat(RegularFile* file, RegularFile* dir, const char* pathname
     // TODO: we should open the os-backed file in non-blocking mode even if a
     // non-block is not requested, and then properly handle the io by, e.g.,
     // epolling on all such files with a shadow support thread.
-    int osfd = open(abspath, flags, mode);
+    int osfd = _regularfile_openWithExactMode(abspath, flags, mode);
     int errcode = errno;
 
     if (osfd < 0) {
@@ -694,10 +727,79 @@ int regularfile_fallocate(RegularFile* file, int mode, off_t offset, off_t lengt
         return -EBADF;
     }
 
-    trace("RegularFile %p fallocate os-backed file %i", file, _regularfile_getOSBackedFD(file));
+    int fd = _regularfile_getOSBackedFD(file);
 
-    int result = fallocate(_regularfile_getOSBackedFD(file), mode, offset, length);
-    return (result < 0) ? -errno : result;
+    trace("RegularFile %p fallocate os-backed file %i", file, fd);
+
+    if (offset < 0 || length <= 0) {
+        return -EINVAL;
+    }
+
+    if (offset > INT64_MAX - length) {
+        return -EFBIG;
+    }
+
+    int flags = fcntl(fd, F_GETFL);
+    if (flags < 0) {
+        return -errno;
+    }
+
+    /* the file must be open for writing */
+    if ((flags & O_ACCMODE) == O_RDONLY) {
+        return -EBADF;
+    }
+
+    struct stat st = {0};
+    if (fstat(fd, &st) < 0) {
+        return -errno;
+    }
+
+    /* We don't model disk space, so there's nothing to reserve. We only need to make sure that the
+     * file's contents and size are what the application expects, which keeps the result the same
+     * no matter which filesystem the native file is on. */
+    switch (mode) {
+        case 0: {
+            /* extend the file with zeros if the range goes past the end of the file */
+            if (offset + length > st.st_size && ftruncate(fd, offset + length) < 0) {
+                return -errno;
+            }
+            return 0;
+        }
+        case FALLOC_FL_KEEP_SIZE: {
+            return 0;
+        }
+        case FALLOC_FL_PUNCH_HOLE | FALLOC_FL_KEEP_SIZE: {
+            /* the file size doesn't change, so we only zero the part of the range within the
+             * file */
+            off_t end = MIN(offset + length, st.st_size);
+
+            if (offset >= end) {
+                return 0;
+            }
+
+            /* deallocate the range if the native filesystem supports it, otherwise write zeros */
+            if (fallocate(fd, mode, offset, end - offset) == 0) {
+                return 0;
+            } else if (errno != EOPNOTSUPP) {
+                return -errno;
+            }
+
+            static const char zeros[4096] = {0};
+            while (offset < end) {
+                size_t count = MIN(sizeof(zeros), (size_t)(end - offset));
+                ssize_t written = pwrite(fd, zeros, count, offset);
+                if (written < 0) {
+                    return -errno;
+                }
+                offset += written;
+            }
+            return 0;
+        }
+        default: {
+            debug("Unsupported fallocate mode %d", mode);
+            return -EOPNOTSUPP;
+        }
+    }
 }
 
 int regularfile_fadvise(RegularFile* file, off_t offset, off_t len, int advice) {
@@ -803,7 +905,51 @@ ssize_t regularfile_readahead(RegularFile* file, off64_t offset, size_t count) {
 
     trace("RegularFile %p readahead os-backed file %i", file, _regularfile_getOSBackedFD(file));
 
-    ssize_t result = readahead(_regularfile_getOSBackedFD(file), offset, count);
+    int flags = fcntl(_regularfile_getOSBackedFD(file), F_GETFL);
+    if (flags < 0) {
+        return -errno;
+    }
+
+    /* the file must be open for reading */
+    if ((flags & O_ACCMODE) == O_WRONLY) {
+        return -EBADF;
+    }
+
+    /* We don't model a page cache, so there's nothing to read ahead. This also means that the
+     * result doesn't depend on whether the native filesystem supports readahead. */
+    return 0;
+}
+
+ssize_t regularfile_copyFileRange(RegularFile* fileIn, off64_t* offsetIn, RegularFile* fileOut,
+                                  off64_t* offsetOut, size_t len, unsigned int flags) {
+    MAGIC_ASSERT(fileIn);
+    MAGIC_ASSERT(fileOut);
+
+    /* In-memory files can't be written to, like in regularfile_write(). */
+    if (fileOut->type == FILE_TYPE_IN_MEMORY) {
+        return -EBADF;
+    }
+
+    /* In-memory and random files are emulated, so there's no native file with the contents that
+     * the plugin would read. On linux these are on a different filesystem (sysfs, devtmpfs, etc). */
+    if (fileIn->type == FILE_TYPE_IN_MEMORY || fileIn->type == FILE_TYPE_RANDOM ||
+        fileOut->type == FILE_TYPE_RANDOM) {
+        return -EXDEV;
+    }
+
+    if (!_fd_isValid(_regularfile_getOSBackedFD(fileIn)) ||
+        !_fd_isValid(_regularfile_getOSBackedFD(fileOut))) {
+        return -EBADF;
+    }
+
+    trace("RegularFile %p will copy %zu bytes from os-backed file %i to os-backed file %i", fileIn,
+          len, _regularfile_getOSBackedFD(fileIn), _regularfile_getOSBackedFD(fileOut));
+
+    /* The offsets of os-backed files are the native file offsets, so linux will update them (or
+     * the given offsets) for us. It also checks the file modes and overlapping ranges within the
+     * same file, and returns EXDEV if it can't copy between the two filesystems. */
+    ssize_t result = copy_file_range(_regularfile_getOSBackedFD(fileIn), offsetIn,
+                                     _regularfile_getOSBackedFD(fileOut), offsetOut, len, flags);
     return (result < 0) ? -errno : result;
 }
 
@@ -1070,6 +1216,12 @@ int regularfile_mkdirat(RegularFile* dir, const char* pathname, mode_t mode,
 
     int result = mkdirat(osFd, pathnameTmp, mode);
 
+    /* the managed process's umask has already been applied to the mode, so don't let shadow's own
+     * umask also apply */
+    if (result == 0) {
+        result = fchmodat(osFd, pathnameTmp, mode & 07777, 0);
+    }
+
     if (pathnameTmp != pathname) {
         free((char*)pathnameTmp);
     }
@@ -1091,6 +1243,12 @@ int regularfile_mknodat(RegularFile* dir, const char* pathname, mode_t mode, dev
 
     int result = mknodat(osFd, pathnameTmp, mode, dev);
 
+    /* the managed process's umask has already been applied to the mode, so don't let shadow's own
+     * umask also apply */
+    if (result == 0) {
+        result = fchmodat(osFd, pathnameTmp, mode & 07777, 0);
+    }
+
     if (pathnameTmp != pathname) {
         free((char*)pathnameTmp);
     }
@@ -115,6 +115,8 @@ ssize_t regularfile_flistxattr(RegularFile* file, char* list, size_t size);
 int regularfile_fremovexattr(RegularFile* file, const char* name);
 int regularfile_sync_range(RegularFile* file, off64_t offset, off64_t nbytes, unsigned int flags);
 ssize_t regularfile_readahead(RegularFile* file, off64_t offset, size_t count);
+ssize_t regularfile_copyFileRange(RegularFile* fileIn, off64_t* offsetIn, RegularFile* fileOut,
+                                  off64_t* offsetOut, size_t len, unsigned int flags);
 off_t regularfile_lseek(RegularFile* file, off_t offset, int whence);
 int regularfile_getdents(RegularFile* file, struct linux_dirent* dirp, unsigned int count);
 int regularfile_getdents64(RegularFile* file, struct linux_dirent64* dirp, unsigned int count);
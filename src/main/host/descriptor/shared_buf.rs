@@ -9,6 +9,8 @@ use crate::utility::callback_queue::{CallbackQueue, EventSource, Handle};
 pub struct SharedBuf {
     queue: ByteQueue,
     max_len: usize,
+    /// The buffer is only writable when at least this many bytes of space are available.
+    writable_space: usize,
     state: BufferState,
     num_readers: u16,
     num_writers: u16,
@@ -21,6 +23,7 @@ impl SharedBuf {
         Self {
             queue: ByteQueue::new(4096),
             max_len,
+            writable_space: 1,
             state: BufferState::WRITABLE | BufferState::NO_READERS | BufferState::NO_WRITERS,
             num_readers: 0,
             num_writers: 0,
@@ -32,6 +35,11 @@ impl SharedBuf {
         self.queue.has_chunks()
     }
 
+    /// The number of bytes in the buffer.
+    pub fn num_bytes(&self) -> usize {
+        self.queue.num_bytes()
+    }
+
     pub fn max_len(&self) -> usize {
         self.max_len
     }
@@ -40,6 +48,16 @@ impl SharedBuf {
         self.max_len - self.queue.num_bytes()
     }
 
+    /// Only consider the buffer to be writable when at least `len` bytes of space are available
+    /// (or the buffer is empty). This is useful for files like pipes that guarantee that writes of
+    /// up to some length are atomic, so that a writer waiting for the buffer to become writable
+    /// isn't woken before its write can complete.
+    pub fn set_writable_space(&mut self, len: usize, cb_queue: &mut CallbackQueue) {
+        assert_ne!(len, 0);
+        self.writable_space = len;
+        self.refresh_state(BufferSignals::empty(), cb_queue);
+    }
+
     /// Register as a reader. The [`ReaderHandle`] must be returned to the buffer later with
     /// [`remove_reader()`](Self::remove_reader).
     pub fn add_reader(&mut self, cb_queue: &mut CallbackQueue) -> ReaderHandle {
@@ -192,7 +210,10 @@ impl SharedBuf {
         let mut new_state = BufferState::empty();
 
         new_state.set(BufferState::READABLE, self.has_data());
-        new_state.set(BufferState::WRITABLE, self.space_available() > 0);
+        new_state.set(
+            BufferState::WRITABLE,
+            self.space_available() >= std::cmp::min(self.writable_space, self.max_len),
+        );
         new_state.set(BufferState::NO_READERS, self.num_readers() == 0);
         new_state.set(BufferState::NO_WRITERS, self.num_writers() == 0);
 
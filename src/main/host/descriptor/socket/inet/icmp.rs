@@ -0,0 +1,702 @@
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::sync::{Arc, Weak};
+
+use atomic_refcell::AtomicRefCell;
+use bytes::{BufMut, Bytes, BytesMut};
+use linux_api::errno::Errno;
+use linux_api::ioctls::IoctlRequest;
+use linux_api::socket::Shutdown;
+use nix::sys::socket::{MsgFlags, SockaddrIn};
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+use shadow_shim_helper_rs::syscall_types::ForeignPtr;
+
+use crate::core::work::task::TaskRef;
+use crate::core::worker::Worker;
+use crate::cshadow as c;
+use crate::host::descriptor::listener::{StateEventSource, StateListenHandle, StateListenerFilter};
+use crate::host::descriptor::socket::inet::InetSocket;
+use crate::host::descriptor::socket::{
+    AncillaryData, RecvmsgArgs, RecvmsgReturn, SendmsgArgs, send_flags,
+};
+use crate::host::descriptor::{
+    File, FileMode, FileSignals, FileState, FileStatus, OpenFile, Socket, SyscallResult,
+};
+use crate::host::memory_manager::MemoryManager;
+use crate::host::network::interface::FifoPacketPriority;
+use crate::host::network::namespace::NetworkNamespace;
+use crate::host::syscall::io::{IoVec, IoVecReader, IoVecWriter, write_partial};
+use crate::host::syscall::types::SyscallError;
+use crate::network::packet::PacketRc;
+use crate::utility::callback_queue::CallbackQueue;
+use crate::utility::sockaddr::SockaddrStorage;
+use crate::utility::{HostTreePointer, ObjectCounter};
+
+/// Maximum size of an ICMP message we are allowed to send.
+// 65,535 (2^16 - 1) - 20 (ip header)
+const ICMP_MESSAGE_MAX_SIZE: usize = 65515;
+
+/// Length of the IPv4 header that we prepend to received messages. We never use IP options.
+const IPV4_HEADER_LEN: usize = 20;
+
+/// Length of the ICMP header (type, code, checksum, and the 4-byte "rest of header").
+const ICMP_HEADER_LEN: usize = 8;
+
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_ECHO_REQUEST: u8 = 8;
+
+/// A raw `IPPROTO_ICMP` socket, as used by tools like `ping`.
+///
+/// Shadow doesn't simulate ICMP packets. Instead echo requests are answered directly by the sending
+/// socket's network namespace after the round-trip time to the destination host has elapsed. Any
+/// other ICMP messages are silently dropped. Like Linux raw sockets, received messages include the
+/// IPv4 header.
+pub struct IcmpSocket {
+    event_source: StateEventSource,
+    status: FileStatus,
+    state: FileState,
+    recv_buffer: VecDeque<(Ipv4Addr, Bytes)>,
+    recv_buffer_len: usize,
+    recv_buffer_limit: usize,
+    peer_addr: Option<Ipv4Addr>,
+    bound_addr: Option<Ipv4Addr>,
+    // should only be used by `OpenFile` to make sure there is only ever one `OpenFile` instance for
+    // this file
+    has_open_file: bool,
+    _counter: ObjectCounter,
+}
+
+impl IcmpSocket {
+    pub fn new(status: FileStatus, recv_buf_size: usize) -> Arc<AtomicRefCell<Self>> {
+        let mut socket = Self {
+            event_source: StateEventSource::new(),
+            status,
+            state: FileState::ACTIVE,
+            recv_buffer: VecDeque::new(),
+            recv_buffer_len: 0,
+            recv_buffer_limit: recv_buf_size,
+            peer_addr: None,
+            bound_addr: None,
+            has_open_file: false,
+            _counter: ObjectCounter::new("IcmpSocket"),
+        };
+
+        CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
+            socket.refresh_readable_writable(FileSignals::empty(), cb_queue)
+        });
+
+        Arc::new(AtomicRefCell::new(socket))
+    }
+
+    pub fn status(&self) -> FileStatus {
+        self.status
+    }
+
+    pub fn set_status(&mut self, status: FileStatus) {
+        self.status = status;
+    }
+
+    pub fn mode(&self) -> FileMode {
+        FileMode::READ | FileMode::WRITE
+    }
+
+    pub fn has_open_file(&self) -> bool {
+        self.has_open_file
+    }
+
+    pub fn supports_sa_restart(&self) -> bool {
+        true
+    }
+
+    pub fn set_has_open_file(&mut self, val: bool) {
+        self.has_open_file = val;
+    }
+
+    pub fn push_in_packet(
+        &mut self,
+        _packet: PacketRc,
+        _cb_queue: &mut CallbackQueue,
+        _recv_time: EmulatedTime,
+    ) {
+        // ICMP sockets are never associated with a network interface
+        log::warn!("Dropping a packet pushed to an ICMP socket");
+    }
+
+    pub fn pull_out_packet(&mut self, _cb_queue: &mut CallbackQueue) -> Option<PacketRc> {
+        // ICMP sockets are never associated with a network interface
+        log::warn!("Attempted to pull a packet from an ICMP socket");
+        None
+    }
+
+    pub fn peek_next_packet_priority(&self) -> Option<FifoPacketPriority> {
+        None
+    }
+
+    pub fn has_data_to_send(&self) -> bool {
+        false
+    }
+
+    pub fn getsockname(&self) -> Result<Option<SockaddrIn>, Errno> {
+        let addr = self.bound_addr.unwrap_or(Ipv4Addr::UNSPECIFIED);
+        Ok(Some(SocketAddrV4::new(addr, 0).into()))
+    }
+
+    pub fn getpeername(&self) -> Result<Option<SockaddrIn>, Errno> {
+        let addr = self.peer_addr.ok_or(Errno::ENOTCONN)?;
+        Ok(Some(SocketAddrV4::new(addr, 0).into()))
+    }
+
+    pub fn address_family(&self) -> linux_api::socket::AddressFamily {
+        linux_api::socket::AddressFamily::AF_INET
+    }
+
+    pub fn recv_timeout(&self) -> Option<SimulationTime> {
+        // `SO_RCVTIMEO` isn't supported for these sockets
+        None
+    }
+
+    pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError> {
+        // any replies that are still in flight will be dropped
+        self.recv_buffer.clear();
+        self.recv_buffer_len = 0;
+
+        self.update_state(
+            /* mask= */ FileState::all(),
+            FileState::CLOSED,
+            FileSignals::empty(),
+            cb_queue,
+        );
+        Ok(())
+    }
+
+    pub fn bind(
+        socket: &Arc<AtomicRefCell<Self>>,
+        addr: Option<&SockaddrStorage>,
+        net_ns: &NetworkNamespace,
+        _rng: impl rand::Rng,
+    ) -> Result<(), SyscallError> {
+        // if the address pointer was NULL
+        let Some(addr) = addr else {
+            return Err(Errno::EFAULT.into());
+        };
+
+        // if not an inet socket address
+        let Some(addr) = addr.as_inet() else {
+            return Err(Errno::EINVAL.into());
+        };
+
+        // raw sockets don't have ports, so the port is ignored
+        let addr = SocketAddrV4::from(*addr);
+        let addr = *addr.ip();
+
+        if net_ns.interface_borrow(addr).is_none() {
+            return Err(Errno::EADDRNOTAVAIL.into());
+        }
+
+        socket.borrow_mut().bound_addr = Some(addr);
+
+        Ok(())
+    }
+
+    pub fn readv(
+        &mut self,
+        _iovs: &[IoVec],
+        _offset: Option<libc::off_t>,
+        _flags: libc::c_int,
+        _mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::ssize_t, SyscallError> {
+        // we could call IcmpSocket::recvmsg() here, but for now we expect that there are no code
+        // paths that would call IcmpSocket::readv() since the readv() syscall handler should have
+        // called IcmpSocket::recvmsg() instead
+        panic!("Called IcmpSocket::readv() on an ICMP socket");
+    }
+
+    pub fn writev(
+        &mut self,
+        _iovs: &[IoVec],
+        _offset: Option<libc::off_t>,
+        _flags: libc::c_int,
+        _mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::ssize_t, SyscallError> {
+        // we could call IcmpSocket::sendmsg() here, but for now we expect that there are no code
+        // paths that would call IcmpSocket::writev() since the writev() syscall handler should have
+        // called IcmpSocket::sendmsg() instead
+        panic!("Called IcmpSocket::writev() on an ICMP socket");
+    }
+
+    pub fn sendmsg(
+        socket: &Arc<AtomicRefCell<Self>>,
+        args: SendmsgArgs,
+        mem: &mut MemoryManager,
+        net_ns: &NetworkNamespace,
+        _rng: impl rand::Rng,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::ssize_t, SyscallError> {
+        let socket_ref = socket.borrow();
+
+        // raw sockets don't have out-of-band data
+        if send_flags(args.flags).contains(MsgFlags::MSG_OOB) {
+            return Err(Errno::EOPNOTSUPP.into());
+        }
+
+        let dst_addr = match args.addr {
+            Some(addr) => match addr.as_inet() {
+                // an inet socket address; raw sockets ignore the port
+                Some(x) => *SocketAddrV4::from(*x).ip(),
+                // not an inet socket address
+                None => return Err(Errno::EAFNOSUPPORT.into()),
+            },
+            // no destination address provided
+            None => match socket_ref.peer_addr {
+                Some(x) => x,
+                None => return Err(Errno::EDESTADDRREQ.into()),
+            },
+        };
+
+        let len: libc::size_t = args.iovs.iter().map(|x| x.len).sum();
+
+        if len > ICMP_MESSAGE_MAX_SIZE {
+            return Err(Errno::EMSGSIZE.into());
+        }
+
+        let mut reader = IoVecReader::new(args.iovs, mem);
+        let mut message = vec![0; len];
+        reader
+            .read_exact(&mut message[..])
+            .map_err(|e| Errno::try_from(e).unwrap())?;
+
+        let src_addr = match socket_ref.bound_addr {
+            Some(addr) if !addr.is_unspecified() => addr,
+            // depending on the destination address, choose either localhost or the public IP
+            // address
+            _ if dst_addr.is_loopback() => Ipv4Addr::LOCALHOST,
+            _ => net_ns.default_ip,
+        };
+
+        let Some(reply) = echo_reply(&message, dst_addr, src_addr) else {
+            log::trace!("Dropping an ICMP message that isn't an echo request");
+            return Ok(len.try_into().unwrap());
+        };
+
+        let Some(round_trip_time) = net_ns.echo_round_trip_time(src_addr, dst_addr) else {
+            log::trace!("Dropping an ICMP echo request to unreachable address {dst_addr}");
+            return Ok(len.try_into().unwrap());
+        };
+
+        // deliver the reply after the round-trip time; use a weak reference so that the task
+        // doesn't keep a closed socket alive
+        let weak = Arc::downgrade(socket);
+        let task = TaskRef::new(move |_host| Self::push_reply(&weak, dst_addr, reply.clone()));
+        Worker::with_active_host(|host| host.schedule_task_with_delay(task, round_trip_time))
+            .unwrap();
+
+        Ok(len.try_into().unwrap())
+    }
+
+    /// Add an echo reply from `src` to the receive buffer.
+    fn push_reply(socket: &Weak<AtomicRefCell<Self>>, src: Ipv4Addr, reply: Bytes) {
+        let Some(socket) = socket.upgrade() else {
+            log::trace!("ICMP socket no longer exists; dropping the echo reply");
+            return;
+        };
+
+        CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
+            let mut socket = socket.borrow_mut();
+
+            if socket.state.contains(FileState::CLOSED) {
+                return;
+            }
+
+            // a connected raw socket only receives messages from its peer
+            if socket.peer_addr.is_some_and(|peer| peer != src) {
+                return;
+            }
+
+            // like linux, the message is dropped if the receive buffer is full
+            if socket.recv_buffer_len + reply.len() > socket.recv_buffer_limit {
+                log::trace!("ICMP socket's receive buffer is full; dropping the echo reply");
+                return;
+            }
+
+            socket.recv_buffer_len += reply.len();
+            socket.recv_buffer.push_back((src, reply));
+
+            socket.refresh_readable_writable(FileSignals::READ_BUFFER_GREW, cb_queue);
+        });
+    }
+
+    pub fn recvmsg(
+        socket: &Arc<AtomicRefCell<Self>>,
+        args: RecvmsgArgs,
+        mem: &mut MemoryManager,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<RecvmsgReturn, SyscallError> {
+        let socket_ref = &mut *socket.borrow_mut();
+
+        let Some(mut flags) = MsgFlags::from_bits(args.flags) else {
+            log::debug!("Unrecognized recv flags: {:#b}", args.flags);
+            return Err(Errno::EINVAL.into());
+        };
+
+        if socket_ref.status().contains(FileStatus::NONBLOCK) {
+            flags.insert(MsgFlags::MSG_DONTWAIT);
+        }
+
+        let len: libc::size_t = args.iovs.iter().map(|x| x.len).sum();
+
+        // run in a closure so that an early return doesn't skip checking if we should block
+        let result = (|| {
+            let (src, message) = if !flags.contains(MsgFlags::MSG_PEEK) {
+                let (src, message) = socket_ref
+                    .recv_buffer
+                    .pop_front()
+                    .ok_or(Errno::EWOULDBLOCK)?;
+                socket_ref.recv_buffer_len -= message.len();
+                (src, message)
+            } else {
+                socket_ref
+                    .recv_buffer
+                    .front()
+                    .cloned()
+                    .ok_or(Errno::EWOULDBLOCK)?
+            };
+
+            // truncate the message if it's larger than the user-provided buffers
+            let truncated_message = &message[..std::cmp::min(len, message.len())];
+
+            let mut writer = IoVecWriter::new(args.iovs, mem);
+            writer
+                .write_all(truncated_message)
+                .map_err(|e| Errno::try_from(e).unwrap())?;
+
+            let return_val = if flags.contains(MsgFlags::MSG_TRUNC) {
+                message.len()
+            } else {
+                truncated_message.len()
+            };
+
+            let mut return_flags = MsgFlags::empty();
+            return_flags.set(MsgFlags::MSG_TRUNC, truncated_message.len() < message.len());
+
+            Ok(RecvmsgReturn {
+                return_val: return_val.try_into().unwrap(),
+                addr: Some(SocketAddrV4::new(src, 0).into()),
+                msg_flags: return_flags.bits(),
+                control_len: 0,
+                ancillary: AncillaryData::default(),
+            })
+        })();
+
+        socket_ref.refresh_readable_writable(FileSignals::empty(), cb_queue);
+
+        // if the syscall would block and we don't have the MSG_DONTWAIT flag
+        if result.as_ref().is_err_and(|e| e.is_wouldblock())
+            && !flags.contains(MsgFlags::MSG_DONTWAIT)
+        {
+            return Err(SyscallError::new_blocked_on_file(
+                File::Socket(Socket::Inet(InetSocket::Icmp(socket.clone()))),
+                FileState::READABLE,
+                socket_ref.supports_sa_restart(),
+            ));
+        }
+
+        Ok(result?)
+    }
+
+    pub fn ioctl(
+        &mut self,
+        request: IoctlRequest,
+        arg_ptr: ForeignPtr<()>,
+        mem: &mut MemoryManager,
+    ) -> SyscallResult {
+        match request {
+            // equivalent to SIOCINQ
+            IoctlRequest::FIONREAD => {
+                let len = self
+                    .recv_buffer
+                    .front()
+                    .map(|m| m.1.len())
+                    .unwrap_or(0)
+                    .try_into()
+                    .unwrap();
+
+                let arg_ptr = arg_ptr.cast::<libc::c_int>();
+                mem.write(arg_ptr, &len)?;
+
+                Ok(0.into())
+            }
+            IoctlRequest::FIONBIO => {
+                panic!("This should have been handled by the ioctl syscall handler");
+            }
+            request => {
+                warn_once_then_debug!(
+                    "We do not yet handle ioctl request {request:?} on icmp sockets"
+                );
+                Err(Errno::EINVAL.into())
+            }
+        }
+    }
+
+    pub fn stat(&self) -> Result<linux_api::stat::stat, SyscallError> {
+        warn_once_then_debug!("We do not yet handle stat calls on icmp sockets");
+        Err(Errno::EINVAL.into())
+    }
+
+    pub fn listen(
+        _socket: &Arc<AtomicRefCell<Self>>,
+        _backlog: i32,
+        _net_ns: &NetworkNamespace,
+        _rng: impl rand::Rng,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<(), Errno> {
+        Err(Errno::EOPNOTSUPP)
+    }
+
+    pub fn connect(
+        socket: &Arc<AtomicRefCell<Self>>,
+        peer_addr: &SockaddrStorage,
+        _net_ns: &NetworkNamespace,
+        _rng: impl rand::Rng,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        // if not an inet socket address
+        let Some(peer_addr) = peer_addr.as_inet() else {
+            return Err(Errno::EINVAL.into());
+        };
+
+        // raw sockets don't have ports, so the port is ignored
+        let peer_addr = SocketAddrV4::from(*peer_addr);
+        socket.borrow_mut().peer_addr = Some(*peer_addr.ip());
+
+        Ok(())
+    }
+
+    pub fn accept(
+        &mut self,
+        _net_ns: &NetworkNamespace,
+        _rng: impl rand::Rng,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<OpenFile, SyscallError> {
+        Err(Errno::EOPNOTSUPP.into())
+    }
+
+    pub fn shutdown(
+        &mut self,
+        _how: Shutdown,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        warn_once_then_debug!("shutdown() syscall not yet supported for icmp sockets");
+        Err(Errno::EOPNOTSUPP.into())
+    }
+
+    pub fn getsockopt(
+        &mut self,
+        level: libc::c_int,
+        optname: libc::c_int,
+        optval_ptr: ForeignPtr<()>,
+        optlen: libc::socklen_t,
+        mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::socklen_t, SyscallError> {
+        let val = match (level, optname) {
+            (libc::SOL_SOCKET, libc::SO_RCVBUF) => self.recv_buffer_limit.try_into().unwrap(),
+            (libc::SOL_SOCKET, libc::SO_ERROR) => 0,
+            (libc::SOL_SOCKET, libc::SO_DOMAIN) => libc::AF_INET,
+            (libc::SOL_SOCKET, libc::SO_TYPE) => libc::SOCK_RAW,
+            (libc::SOL_SOCKET, libc::SO_PROTOCOL) => libc::IPPROTO_ICMP,
+            _ => {
+                log_once_per_value_at_level!(
+                    (level, optname),
+                    (i32, i32),
+                    log::Level::Warn,
+                    log::Level::Debug,
+                    "getsockopt called with unsupported level {level} and opt {optname}"
+                );
+                return Err(Errno::ENOPROTOOPT.into());
+            }
+        };
+
+        let optval_ptr = optval_ptr.cast::<libc::c_int>();
+        let bytes_written = write_partial(mem, &val, optval_ptr, optlen as usize)?;
+
+        Ok(bytes_written as libc::socklen_t)
+    }
+
+    pub fn setsockopt(
+        &mut self,
+        level: libc::c_int,
+        optname: libc::c_int,
+        optval_ptr: ForeignPtr<()>,
+        optlen: libc::socklen_t,
+        mem: &MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        match (level, optname) {
+            (libc::SOL_SOCKET, libc::SO_RCVBUF) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val: usize = mem.read(optval_ptr)?.try_into().or(Err(Errno::EINVAL))?;
+
+                // linux kernel doubles this value upon setting
+                let val = val * 2;
+
+                // Linux also has a minimum of 256, but we use a higher minimum so that replies
+                // with a full-sized IP header and ICMP header always fit
+                let val = std::cmp::max(val, 2048);
+                let val = std::cmp::min(val, 268435456); // 2^28 = 256 MiB
+
+                self.recv_buffer_limit = val;
+            }
+            _ => {
+                log_once_per_value_at_level!(
+                    (level, optname),
+                    (i32, i32),
+                    log::Level::Warn,
+                    log::Level::Debug,
+                    "setsockopt called with unsupported level {level} and opt {optname}"
+                );
+                return Err(Errno::ENOPROTOOPT.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn add_listener(
+        &mut self,
+        monitoring_state: FileState,
+        monitoring_signals: FileSignals,
+        filter: StateListenerFilter,
+        notify_fn: impl Fn(FileState, FileState, FileSignals, &mut CallbackQueue)
+        + Send
+        + Sync
+        + 'static,
+    ) -> StateListenHandle {
+        self.event_source
+            .add_listener(monitoring_state, monitoring_signals, filter, notify_fn)
+    }
+
+    pub fn add_legacy_listener(&mut self, ptr: HostTreePointer<c::StatusListener>) {
+        self.event_source.add_legacy_listener(ptr);
+    }
+
+    pub fn remove_legacy_listener(&mut self, ptr: *mut c::StatusListener) {
+        self.event_source.remove_legacy_listener(ptr);
+    }
+
+    pub fn state(&self) -> FileState {
+        self.state
+    }
+
+    fn refresh_readable_writable(&mut self, signals: FileSignals, cb_queue: &mut CallbackQueue) {
+        // we don't have a send buffer, so we're always writable
+        let readable = !self.recv_buffer.is_empty();
+        let readable = readable.then_some(FileState::READABLE).unwrap_or_default();
+
+        self.update_state(
+            /* mask= */ FileState::READABLE | FileState::WRITABLE,
+            readable | FileState::WRITABLE,
+            signals,
+            cb_queue,
+        );
+    }
+
+    fn update_state(
+        &mut self,
+        mask: FileState,
+        state: FileState,
+        signals: FileSignals,
+        cb_queue: &mut CallbackQueue,
+    ) {
+        let old_state = self.state;
+
+        // remove the masked flags, then copy the masked flags
+        self.state.remove(mask);
+        self.state.insert(state & mask);
+
+        self.handle_state_change(old_state, signals, cb_queue);
+    }
+
+    fn handle_state_change(
+        &mut self,
+        old_state: FileState,
+        signals: FileSignals,
+        cb_queue: &mut CallbackQueue,
+    ) {
+        let states_changed = self.state ^ old_state;
+
+        // if nothing changed
+        if states_changed.is_empty() && signals.is_empty() {
+            return;
+        }
+
+        self.event_source
+            .notify_listeners(self.state, states_changed, signals, cb_queue);
+    }
+}
+
+/// If `request` is an ICMP echo request, returns the IPv4 packet containing the echo reply that
+/// `src` would send back to `dst`. Returns `None` for any other ICMP message.
+fn echo_reply(request: &[u8], src: Ipv4Addr, dst: Ipv4Addr) -> Option<Bytes> {
+    if request.len() < ICMP_HEADER_LEN {
+        return None;
+    }
+
+    let (icmp_type, icmp_code) = (request[0], request[1]);
+    if icmp_type != ICMP_ECHO_REQUEST || icmp_code != 0 {
+        return None;
+    }
+
+    // the reply echoes the identifier, sequence number, and data of the request
+    let mut reply = request.to_vec();
+    reply[0] = ICMP_ECHO_REPLY;
+    reply[2..4].copy_from_slice(&[0, 0]);
+    let checksum = internet_checksum(&reply);
+    reply[2..4].copy_from_slice(&checksum.to_be_bytes());
+
+    let total_len = u16::try_from(IPV4_HEADER_LEN + reply.len()).unwrap();
+
+    let mut header = [0u8; IPV4_HEADER_LEN];
+    // version 4 and a header length of 5 32-bit words
+    header[0] = 0x45;
+    header[2..4].copy_from_slice(&total_len.to_be_bytes());
+    // don't fragment
+    header[6] = 0x40;
+    // ttl
+    header[8] = 64;
+    header[9] = libc::IPPROTO_ICMP as u8;
+    header[12..16].copy_from_slice(&src.octets());
+    header[16..20].copy_from_slice(&dst.octets());
+    let checksum = internet_checksum(&header);
+    header[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+    let mut packet = BytesMut::with_capacity(usize::from(total_len));
+    packet.put_slice(&header);
+    packet.put_slice(&reply);
+
+    Some(packet.freeze())
+}
+
+/// The internet checksum (RFC 1071) used by the IPv4 and ICMP headers.
+fn internet_checksum(bytes: &[u8]) -> u16 {
+    let mut sum: u32 = bytes
+        .chunks(2)
+        .map(|x| u32::from(u16::from_be_bytes([x[0], *x.get(1).unwrap_or(&0)])))
+        .sum();
+
+    // fold the carries back into the lower 16 bits
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
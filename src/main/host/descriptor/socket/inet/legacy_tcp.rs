@@ -8,13 +8,17 @@ use linux_api::ioctls::IoctlRequest;
 use linux_api::socket::Shutdown;
 use nix::sys::socket::{MsgFlags, SockaddrIn};
 use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 
 use crate::core::worker::Worker;
 use crate::cshadow as c;
 use crate::host::descriptor::listener::{StateListenHandle, StateListenerFilter};
 use crate::host::descriptor::socket::inet::{self, InetSocket};
-use crate::host::descriptor::socket::{RecvmsgArgs, RecvmsgReturn, SendmsgArgs, Socket};
+use crate::host::descriptor::socket::{
+    AncillaryData, RecvmsgArgs, RecvmsgReturn, SendmsgArgs, Socket, read_timeout, send_flags,
+    write_timeout,
+};
 use crate::host::descriptor::{
     CompatFile, File, FileMode, FileSignals, FileState, FileStatus, OpenFile, SyscallResult,
 };
@@ -37,6 +41,21 @@ pub struct LegacyTcpSocket {
     has_open_file: bool,
     /// Did the last connect() call block, and if so what thread?
     thread_of_blocked_connect: Option<ThreadId>,
+    /// Was `SO_REUSEPORT` enabled? Only applies to future associations.
+    reuse_port: bool,
+    /// The busy polling timeout set by `SO_BUSY_POLL`. Shadow doesn't busy poll, so it's only
+    /// returned by `getsockopt()`.
+    busy_poll: u32,
+    /// The timeout set by `SO_RCVTIMEO` for blocking receives and accepts, if any.
+    recv_timeout: Option<SimulationTime>,
+    /// The value set by `SO_LINGER`. Only a zero timeout (an abortive close) affects `close()`,
+    /// which never blocks.
+    linger: libc::linger,
+    /// The network interface set by `SO_BINDTODEVICE`. Only applies to future connections.
+    bound_device: Option<&'static str>,
+    /// Was `SO_DONTROUTE` enabled? Only applies to future connections, which can then only be to
+    /// directly-connected networks.
+    dont_route: bool,
     _counter: ObjectCounter,
 }
 
@@ -65,6 +84,15 @@ impl LegacyTcpSocket {
             socket: HostTreePointer::new(legacy_tcp),
             has_open_file: false,
             thread_of_blocked_connect: None,
+            reuse_port: false,
+            busy_poll: 0,
+            recv_timeout: None,
+            linger: libc::linger {
+                l_onoff: 0,
+                l_linger: 0,
+            },
+            bound_device: None,
+            dont_route: false,
             _counter: ObjectCounter::new("LegacyTcpSocket"),
         };
 
@@ -228,6 +256,10 @@ impl LegacyTcpSocket {
         linux_api::socket::AddressFamily::AF_INET
     }
 
+    pub fn recv_timeout(&self) -> Option<SimulationTime> {
+        self.recv_timeout
+    }
+
     pub fn close(&mut self, _cb_queue: &mut CallbackQueue) -> Result<(), SyscallError> {
         Worker::with_active_host(|h| {
             unsafe { c::legacyfile_close(self.as_legacy_file(), h) };
@@ -277,12 +309,15 @@ impl LegacyTcpSocket {
         // this will allow us to receive packets from any peer
         let peer_addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
 
+        let reuse_port = socket.borrow().reuse_port;
+
         // associate the socket
         let (addr, handle) = inet::associate_socket(
             InetSocket::LegacyTcp(Arc::clone(socket)),
             addr,
             peer_addr,
             /* check_generic_peer= */ true,
+            reuse_port,
             net_ns,
             rng,
         )?;
@@ -337,11 +372,11 @@ impl LegacyTcpSocket {
         socket: &Arc<AtomicRefCell<Self>>,
         args: SendmsgArgs,
         mem: &mut MemoryManager,
-        _net_ns: &NetworkNamespace,
-        _rng: impl rand::Rng,
+        net_ns: &NetworkNamespace,
+        rng: impl rand::Rng,
         _cb_queue: &mut CallbackQueue,
     ) -> Result<libc::ssize_t, SyscallError> {
-        let socket_ref = socket.borrow_mut();
+        let mut socket_ref = socket.borrow_mut();
         let tcp = socket_ref.as_legacy_tcp();
 
         if socket_ref.state().contains(FileState::CLOSED) {
@@ -354,20 +389,86 @@ impl LegacyTcpSocket {
             return Err(Errno::EBADF.into());
         }
 
-        let Some(mut flags) = MsgFlags::from_bits(args.flags) else {
-            log::warn!("Unrecognized send flags: {:#b}", args.flags);
-            return Err(Errno::EINVAL.into());
-        };
+        // nix doesn't support `MSG_FASTOPEN`, so we handle it separately
+        let fast_open = args.flags & libc::MSG_FASTOPEN != 0;
+
+        let mut flags = send_flags(args.flags);
 
         if socket_ref.status().contains(FileStatus::NONBLOCK) {
             flags.insert(MsgFlags::MSG_DONTWAIT);
         }
 
+        if fast_open {
+            // like Linux, the socket must not already be connected or connecting
+            match unsafe { c::tcp_getConnectionError(tcp) } {
+                errcode if errcode > 0 => {}
+                errcode if errcode == -libc::EALREADY => return Err(Errno::EALREADY.into()),
+                _ => return Err(Errno::EISCONN.into()),
+            }
+
+            let Some(peer_addr) = args.addr else {
+                return Err(Errno::EINVAL.into());
+            };
+
+            // check the buffers before we change any connection state
+            if args
+                .iovs
+                .iter()
+                .any(|iov| iov.base.is_null() && iov.len > 0)
+            {
+                return Err(Errno::EFAULT.into());
+            }
+
+            // connect without sending the SYN, and then send the SYN with the first segment of
+            // data
+            let errcode = Self::start_connect(
+                socket,
+                &mut socket_ref,
+                &peer_addr,
+                net_ns,
+                rng,
+                /* defer_syn= */ true,
+            )?;
+
+            if errcode < 0 && errcode != -libc::EINPROGRESS {
+                return Err(Errno::try_from(-errcode).unwrap().into());
+            }
+
+            // the SYN carries up to one segment of data gathered from all of the buffers
+            let bases: Vec<ForeignPtr<()>> =
+                args.iovs.iter().map(|iov| iov.base.cast::<()>()).collect();
+            let lens: Vec<_> = args
+                .iovs
+                .iter()
+                .map(|iov| iov.len.try_into().unwrap())
+                .collect();
+
+            // SAFETY: We're passing an immutable pointer to the memory manager. We should not have
+            // any other mutable references to the memory manager at this point.
+            let rv = Worker::with_active_host(|host| unsafe {
+                c::tcp_sendFastOpenData(
+                    tcp,
+                    host,
+                    bases.as_ptr(),
+                    lens.as_ptr(),
+                    bases.len().try_into().unwrap(),
+                    mem,
+                )
+            })
+            .unwrap();
+
+            if rv < 0 {
+                return Err(Errno::try_from(-rv).unwrap().into());
+            }
+
+            return Ok(rv.try_into().unwrap());
+        }
+
         // run in a closure so that an early return doesn't skip checking if we should block
         let result = (|| {
             let mut bytes_sent = 0;
 
-            for iov in args.iovs {
+            for (i, iov) in args.iovs.iter().enumerate() {
                 let errcode = unsafe { c::tcp_getConnectionError(tcp) };
 
                 log::trace!("Connection error state is currently {errcode}");
@@ -388,8 +489,8 @@ impl LegacyTcpSocket {
                 } else if errcode == -libc::EISCONN {
                     // they are connected, and we can send now
                 } else if errcode == -libc::EALREADY {
-                    // connection in progress
-                    // TODO: should we wait, or just return -EALREADY?
+                    // connection in progress, so like linux we wait for the handshake to complete
+                    // (the socket becomes writable once it's established)
                     if bytes_sent == 0 {
                         return Err(Errno::EWOULDBLOCK);
                     } else {
@@ -397,6 +498,14 @@ impl LegacyTcpSocket {
                     }
                 }
 
+                // like linux, the urgent byte is the last byte that was actually sent, which is in
+                // this iov if the send buffer can't hold any data from later iovs
+                let urgent = flags.contains(MsgFlags::MSG_OOB) && {
+                    let sendable = unsafe { c::tcp_getSendableLength(tcp) };
+                    u64::try_from(iov.len).unwrap() >= sendable
+                        || args.iovs[i + 1..].iter().all(|iov| iov.len == 0)
+                };
+
                 // SAFETY: We're passing an immutable pointer to the memory manager. We should not
                 // have any other mutable references to the memory manager at this point.
                 let rv = Worker::with_active_host(|host| unsafe {
@@ -407,6 +516,7 @@ impl LegacyTcpSocket {
                         iov.len.try_into().unwrap(),
                         0,
                         0,
+                        urgent.into(),
                         mem,
                     )
                 })
@@ -432,7 +542,9 @@ impl LegacyTcpSocket {
         })();
 
         // if the syscall would block and we don't have the MSG_DONTWAIT flag
-        if result == Err(Errno::EWOULDBLOCK) && !flags.contains(MsgFlags::MSG_DONTWAIT) {
+        if result.as_ref().is_err_and(|e| e.is_wouldblock())
+            && !flags.contains(MsgFlags::MSG_DONTWAIT)
+        {
             return Err(SyscallError::new_blocked_on_file(
                 File::Socket(Socket::Inet(InetSocket::LegacyTcp(socket.clone()))),
                 FileState::WRITABLE,
@@ -474,6 +586,35 @@ impl LegacyTcpSocket {
             flags.insert(MsgFlags::MSG_DONTWAIT);
         }
 
+        // urgent data is read separately from the normal data stream, and never blocks
+        if flags.contains(MsgFlags::MSG_OOB) {
+            let rv = unsafe { c::tcp_takeUrgentData(tcp, true.into()) };
+            if rv < 0 {
+                return Err(Errno::try_from(-rv).unwrap().into());
+            }
+            let byte = u8::try_from(rv).unwrap();
+
+            let (return_val, msg_flags) = match args.iovs.iter().find(|iov| iov.len > 0) {
+                Some(iov) => {
+                    mem.write(iov.base, &byte)?;
+                    (1, 0)
+                }
+                None => (0, libc::MSG_TRUNC),
+            };
+
+            if !flags.contains(MsgFlags::MSG_PEEK) {
+                unsafe { c::tcp_takeUrgentData(tcp, false.into()) };
+            }
+
+            return Ok(RecvmsgReturn {
+                return_val,
+                addr: None,
+                msg_flags,
+                control_len: 0,
+                ancillary: AncillaryData::default(),
+            });
+        }
+
         // run in a closure so that an early return doesn't skip checking if we should block
         let result = (|| {
             let mut bytes_read = 0;
@@ -542,11 +683,12 @@ impl LegacyTcpSocket {
                 addr: None,
                 msg_flags: 0,
                 control_len: 0,
+                ancillary: AncillaryData::default(),
             })
         })();
 
         // if the syscall would block and we don't have the MSG_DONTWAIT flag
-        if result.as_ref().err() == Some(&Errno::EWOULDBLOCK)
+        if result.as_ref().is_err_and(|e| e.is_wouldblock())
             && !flags.contains(MsgFlags::MSG_DONTWAIT)
         {
             return Err(SyscallError::new_blocked_on_file(
@@ -599,7 +741,7 @@ impl LegacyTcpSocket {
                 Ok(0.into())
             }
             // this isn't supported by tcp
-            IoctlRequest::SIOCGSTAMP => Err(Errno::ENOENT.into()),
+            IoctlRequest::SIOCGSTAMP | IoctlRequest::SIOCGSTAMPNS => Err(Errno::ENOENT.into()),
             IoctlRequest::FIONBIO => {
                 panic!("This should have been handled by the ioctl syscall handler");
             }
@@ -672,6 +814,7 @@ impl LegacyTcpSocket {
                 local_addr,
                 peer_addr,
                 /* check_generic_peer= */ true,
+                socket_ref.reuse_port,
                 net_ns,
                 rng,
             )?;
@@ -705,27 +848,17 @@ impl LegacyTcpSocket {
         Ok(())
     }
 
-    pub fn connect(
+    /// Bind the socket if needed and start connecting to `peer_addr`. Returns the error code from
+    /// the C TCP code, which is `-EINPROGRESS` for a new connection attempt. If `defer_syn` is set,
+    /// the SYN isn't sent until the first user data is sent (`MSG_FASTOPEN`).
+    fn start_connect(
         socket: &Arc<AtomicRefCell<Self>>,
+        socket_ref: &mut Self,
         peer_addr: &SockaddrStorage,
         net_ns: &NetworkNamespace,
         rng: impl rand::Rng,
-        _cb_queue: &mut CallbackQueue,
-    ) -> Result<(), SyscallError> {
-        let mut socket_ref = socket.borrow_mut();
-
-        if let Some(tid) = socket_ref.thread_of_blocked_connect {
-            // check if there is already a blocking connect() call on another thread
-            if tid != Worker::active_thread_id().unwrap() {
-                // connect(2) says "Generally,  connection-based protocol sockets may successfully
-                // connect() only once", but the application is attempting to call connect() in two
-                // threads on a blocking socket at the same time. Let's just return an error and
-                // hope no one ever does this.
-                log::warn!("Two threads are attempting to connect() on a blocking socket");
-                return Err(Errno::EBADFD.into());
-            }
-        }
-
+        defer_syn: bool,
+    ) -> Result<libc::c_int, SyscallError> {
         // if the socket is already listening, return EISCONN
         let is_valid_listener = unsafe { c::tcp_isValidListener(socket_ref.as_legacy_tcp()) } == 1;
         if is_valid_listener {
@@ -743,6 +876,18 @@ impl LegacyTcpSocket {
             peer_addr.set_ip(std::net::Ipv4Addr::LOCALHOST);
         }
 
+        // a socket bound to an interface can only connect to addresses routed through it
+        if let Some(device) = socket_ref.bound_device {
+            if inet::route_interface_name(*peer_addr.ip()) != device {
+                return Err(Errno::ENETUNREACH.into());
+            }
+        }
+
+        // a socket that bypasses the routing table can only connect to directly-connected networks
+        if socket_ref.dont_route && !inet::is_on_link(*peer_addr.ip(), net_ns.default_ip) {
+            return Err(Errno::ENETUNREACH.into());
+        }
+
         let host_default_ip = net_ns.default_ip;
 
         // NOTE: it would be nice to use `Ipv4Addr::is_loopback` in this code rather than comparing
@@ -782,6 +927,7 @@ impl LegacyTcpSocket {
                 local_addr,
                 peer_addr,
                 /* check_generic_peer= */ true,
+                /* reuse_port= */ false,
                 net_ns,
                 rng,
             )?;
@@ -816,6 +962,40 @@ impl LegacyTcpSocket {
             }
         }
 
+        // if the peer is on this host and there's no socket that would accept the connection, the
+        // peer would respond to our SYN with a RST, so we can refuse the connection immediately
+        if let Some(local_addr) = socket_ref.getsockname()? {
+            let mut local_addr = SocketAddrV4::from(local_addr);
+            if local_addr.ip().is_unspecified() {
+                if peer_addr.ip() == &Ipv4Addr::LOCALHOST {
+                    local_addr.set_ip(Ipv4Addr::LOCALHOST);
+                } else {
+                    local_addr.set_ip(host_default_ip);
+                }
+            }
+
+            let is_refused = super::is_tcp_connection_refused(
+                &InetSocket::LegacyTcp(Arc::clone(socket)),
+                local_addr,
+                peer_addr,
+                net_ns,
+            );
+
+            let is_blocking = !socket_ref.status().contains(FileStatus::NONBLOCK);
+            let tcp = socket_ref.as_legacy_tcp();
+
+            if is_refused && unsafe { c::tcp_refuseConnection(tcp, is_blocking.into()) } == 1 {
+                log::trace!("Connection to {peer_addr} was refused");
+
+                // a non-blocking socket will report the error asynchronously
+                return if is_blocking {
+                    Err(Errno::ECONNREFUSED.into())
+                } else {
+                    Err(Errno::EINPROGRESS.into())
+                };
+            }
+        }
+
         unsafe {
             c::legacysocket_setPeerName(
                 socket_ref.as_legacy_socket(),
@@ -824,6 +1004,10 @@ impl LegacyTcpSocket {
             )
         };
 
+        if defer_syn {
+            unsafe { c::tcp_deferSyn(socket_ref.as_legacy_tcp()) };
+        }
+
         // now we are ready to connect
         let errcode = Worker::with_active_host(|host| unsafe {
             c::legacysocket_connectToPeer(
@@ -836,6 +1020,39 @@ impl LegacyTcpSocket {
         })
         .unwrap();
 
+        Ok(errcode)
+    }
+
+    pub fn connect(
+        socket: &Arc<AtomicRefCell<Self>>,
+        peer_addr: &SockaddrStorage,
+        net_ns: &NetworkNamespace,
+        rng: impl rand::Rng,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        let mut socket_ref = socket.borrow_mut();
+
+        if let Some(tid) = socket_ref.thread_of_blocked_connect {
+            // check if there is already a blocking connect() call on another thread
+            if tid != Worker::active_thread_id().unwrap() {
+                // connect(2) says "Generally,  connection-based protocol sockets may successfully
+                // connect() only once", but the application is attempting to call connect() in two
+                // threads on a blocking socket at the same time. Let's just return an error and
+                // hope no one ever does this.
+                log::warn!("Two threads are attempting to connect() on a blocking socket");
+                return Err(Errno::EBADFD.into());
+            }
+        }
+
+        let errcode = Self::start_connect(
+            socket,
+            &mut socket_ref,
+            peer_addr,
+            net_ns,
+            rng,
+            /* defer_syn= */ false,
+        )?;
+
         assert!(errcode <= 0);
 
         let mut errcode = if errcode < 0 {
@@ -1019,13 +1236,11 @@ impl LegacyTcpSocket {
                 Ok(bytes_written as libc::socklen_t)
             }
             (libc::SOL_TCP, libc::TCP_NODELAY) => {
-                // shadow doesn't support nagle's algorithm, so shadow always behaves as if
-                // TCP_NODELAY is enabled
-                let val = 1;
+                let no_delay = unsafe { c::tcp_getNoDelay(self.as_legacy_tcp()) };
 
                 let optval_ptr = optval_ptr.cast::<libc::c_int>();
                 let bytes_written =
-                    write_partial(memory_manager, &val, optval_ptr, optlen as usize)?;
+                    write_partial(memory_manager, &no_delay, optval_ptr, optlen as usize)?;
 
                 Ok(bytes_written as libc::socklen_t)
             }
@@ -1057,6 +1272,42 @@ impl LegacyTcpSocket {
                 // the len value returned by linux seems to be independent from the actual string length
                 Ok(std::cmp::min(optlen as usize, CONG_NAME_MAX) as libc::socklen_t)
             }
+            (libc::SOL_TCP, libc::TCP_QUICKACK) => {
+                let quick_ack = unsafe { c::tcp_getQuickAck(self.as_legacy_tcp()) };
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written =
+                    write_partial(memory_manager, &quick_ack, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_TCP, libc::TCP_DEFER_ACCEPT) => {
+                let secs = unsafe { c::tcp_getDeferAccept(self.as_legacy_tcp()) };
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written =
+                    write_partial(memory_manager, &secs, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_TCP, libc::TCP_FASTOPEN) => {
+                let qlen = unsafe { c::tcp_getFastOpen(self.as_legacy_tcp()) };
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written =
+                    write_partial(memory_manager, &qlen, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_TCP, libc::TCP_USER_TIMEOUT) => {
+                let ms: libc::c_uint = unsafe { c::tcp_getUserTimeout(self.as_legacy_tcp()) };
+
+                let optval_ptr = optval_ptr.cast::<libc::c_uint>();
+                let bytes_written =
+                    write_partial(memory_manager, &ms, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
             (libc::SOL_SOCKET, libc::SO_SNDBUF) => {
                 let sndbuf_size: libc::c_int =
                     unsafe { c::legacysocket_getOutputBufferSize(self.as_legacy_socket()) }
@@ -1085,7 +1336,10 @@ impl LegacyTcpSocket {
                 // return error for failed connect() attempts
                 let conn_err = unsafe { c::tcp_getConnectionError(self.as_legacy_tcp()) };
 
-                let error = if conn_err == -libc::ECONNRESET || conn_err == -libc::ECONNREFUSED {
+                let error = if conn_err == -libc::ECONNRESET {
+                    // a reset is only reported once, either here or by a send/recv call
+                    -unsafe { c::tcp_takeResetError(self.as_legacy_tcp()) }
+                } else if conn_err == -libc::ECONNREFUSED {
                     // result is a positive errcode
                     -conn_err
                 } else {
@@ -1134,6 +1388,24 @@ impl LegacyTcpSocket {
 
                 Ok(bytes_written as libc::socklen_t)
             }
+            (libc::SOL_SOCKET, libc::SO_OOBINLINE) => {
+                let is_inline = unsafe { c::tcp_getUrgentInline(self.as_legacy_tcp()) };
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written =
+                    write_partial(memory_manager, &is_inline, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_REUSEPORT) => {
+                let reuse_port = self.reuse_port as libc::c_int;
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written =
+                    write_partial(memory_manager, &reuse_port, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
             (libc::SOL_SOCKET, libc::SO_BROADCAST) => {
                 let optval_ptr = optval_ptr.cast::<libc::c_int>();
                 // we don't support broadcast sockets, so just just return the default 0
@@ -1141,6 +1413,64 @@ impl LegacyTcpSocket {
 
                 Ok(bytes_written as libc::socklen_t)
             }
+            (libc::SOL_SOCKET, libc::SO_MARK) => {
+                let mark = unsafe { c::tcp_getMark(self.as_legacy_tcp()) };
+
+                let optval_ptr = optval_ptr.cast::<u32>();
+                let bytes_written =
+                    write_partial(memory_manager, &mark, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_BUSY_POLL) => {
+                let optval_ptr = optval_ptr.cast::<u32>();
+                let bytes_written =
+                    write_partial(memory_manager, &self.busy_poll, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_INCOMING_CPU) => {
+                // shadow doesn't simulate cpus, so always return -1 like linux does for a socket
+                // that hasn't received any packets
+                let cpu: libc::c_int = -1;
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written =
+                    write_partial(memory_manager, &cpu, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_RCVTIMEO) => {
+                write_timeout(self.recv_timeout, optval_ptr, optlen, memory_manager)
+            }
+            (libc::SOL_SOCKET, libc::SO_LINGER) => {
+                let optval_ptr = optval_ptr.cast::<libc::linger>();
+                let bytes_written =
+                    write_partial(memory_manager, &self.linger, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_BINDTODEVICE) => {
+                inet::write_bind_to_device(self.bound_device, optval_ptr, optlen, memory_manager)
+            }
+            (libc::SOL_SOCKET, libc::SO_PRIORITY) => {
+                let priority = unsafe { c::tcp_getSocketPriority(self.as_legacy_tcp()) };
+
+                let optval_ptr = optval_ptr.cast::<u32>();
+                let bytes_written =
+                    write_partial(memory_manager, &priority, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_DONTROUTE) => {
+                let enabled = libc::c_int::from(self.dont_route);
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written =
+                    write_partial(memory_manager, &enabled, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
             _ => {
                 log_once_per_value_at_level!(
                     (level, optname),
@@ -1161,14 +1491,10 @@ impl LegacyTcpSocket {
         optval_ptr: ForeignPtr<()>,
         optlen: libc::socklen_t,
         memory_manager: &MemoryManager,
+        _cb_queue: &mut CallbackQueue,
     ) -> Result<(), SyscallError> {
         match (level, optname) {
             (libc::SOL_TCP, libc::TCP_NODELAY) => {
-                // Shadow doesn't support nagle's algorithm, so Shadow always behaves as if
-                // TCP_NODELAY is enabled. Some programs will fail if `setsockopt(fd, SOL_TCP,
-                // TCP_NODELAY, &1, sizeof(int))` returns an error, so we treat this as a no-op for
-                // compatibility.
-
                 type OptType = libc::c_int;
 
                 if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
@@ -1176,18 +1502,13 @@ impl LegacyTcpSocket {
                 }
 
                 let optval_ptr = optval_ptr.cast::<OptType>();
-                let enable = memory_manager.read(optval_ptr)?;
+                let val = memory_manager.read(optval_ptr)?;
 
-                if enable != 0 {
-                    // wants to enable TCP_NODELAY
-                    log::debug!("Ignoring TCP_NODELAY");
-                } else {
-                    // wants to disable TCP_NODELAY
-                    log::warn!(
-                        "Cannot disable TCP_NODELAY since shadow does not implement Nagle's algorithm."
-                    );
-                    return Err(Errno::ENOPROTOOPT.into());
-                }
+                // enabling TCP_NODELAY sends any small segment that nagle's algorithm is holding
+                Worker::with_active_host(|host| unsafe {
+                    c::tcp_setNoDelay(self.as_legacy_tcp(), host, (val != 0).into())
+                })
+                .unwrap();
             }
             (libc::SOL_TCP, libc::TCP_CONGESTION) => {
                 // the value of TCP_CA_NAME_MAX in linux
@@ -1219,6 +1540,66 @@ impl LegacyTcpSocket {
 
                 // shadow doesn't support other congestion types, so do nothing
             }
+            (libc::SOL_TCP, libc::TCP_QUICKACK) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val = memory_manager.read(optval_ptr)?;
+
+                // like linux, this only applies to the next ACK that would have been delayed
+                unsafe { c::tcp_setQuickAck(self.as_legacy_tcp(), (val != 0).into()) };
+            }
+            (libc::SOL_TCP, libc::TCP_DEFER_ACCEPT) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let secs = memory_manager.read(optval_ptr)?;
+
+                unsafe { c::tcp_setDeferAccept(self.as_legacy_tcp(), secs) };
+            }
+            (libc::SOL_TCP, libc::TCP_FASTOPEN) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let qlen = memory_manager.read(optval_ptr)?;
+
+                if qlen < 0 {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                unsafe { c::tcp_setFastOpen(self.as_legacy_tcp(), qlen) };
+            }
+            (libc::SOL_TCP, libc::TCP_USER_TIMEOUT) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let ms = memory_manager.read(optval_ptr)?;
+
+                let Ok(ms) = libc::c_uint::try_from(ms) else {
+                    return Err(Errno::EINVAL.into());
+                };
+
+                Worker::with_active_host(|host| unsafe {
+                    c::tcp_setUserTimeout(self.as_legacy_tcp(), host, ms)
+                })
+                .unwrap();
+            }
             (libc::SOL_SOCKET, libc::SO_SNDBUF) => {
                 type OptType = libc::c_int;
 
@@ -1282,14 +1663,34 @@ impl LegacyTcpSocket {
                 log::trace!("setsockopt SO_REUSEADDR not yet implemented");
             }
             (libc::SOL_SOCKET, libc::SO_REUSEPORT) => {
-                // TODO: implement this, tgen uses it
-                log::trace!("setsockopt SO_REUSEPORT not yet implemented");
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val = memory_manager.read(optval_ptr)?;
+
+                self.reuse_port = val != 0;
             }
             (libc::SOL_SOCKET, libc::SO_KEEPALIVE) => {
                 // TODO: implement this, libevent uses it in
                 // evconnlistener_new_bind()
                 log::trace!("setsockopt SO_KEEPALIVE not yet implemented");
             }
+            (libc::SOL_SOCKET, libc::SO_OOBINLINE) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val = memory_manager.read(optval_ptr)?;
+
+                unsafe { c::tcp_setUrgentInline(self.as_legacy_tcp(), (val != 0).into()) };
+            }
             (libc::SOL_SOCKET, libc::SO_BROADCAST) => {
                 type OptType = libc::c_int;
 
@@ -1309,6 +1710,61 @@ impl LegacyTcpSocket {
                     );
                 }
             }
+            (libc::SOL_SOCKET, libc::SO_MARK) => {
+                type OptType = u32;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let mark = memory_manager.read(optval_ptr)?;
+                unsafe { c::tcp_setMark(self.as_legacy_tcp(), mark) };
+            }
+            (libc::SOL_SOCKET, libc::SO_BUSY_POLL) => {
+                self.busy_poll = inet::read_busy_poll(optval_ptr, optlen, memory_manager)?;
+            }
+            (libc::SOL_SOCKET, libc::SO_RCVTIMEO) => {
+                self.recv_timeout = read_timeout(optval_ptr, optlen, memory_manager)?;
+            }
+            (libc::SOL_SOCKET, libc::SO_LINGER) => {
+                type OptType = libc::linger;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let linger = memory_manager.read(optval_ptr)?;
+
+                let abort_on_close = linger.l_onoff != 0 && linger.l_linger == 0;
+                if linger.l_onoff != 0 && !abort_on_close {
+                    warn_once_then_debug!(
+                        "setsockopt SO_LINGER with a non-zero timeout is not yet implemented; \
+                        close() will not block"
+                    );
+                }
+
+                unsafe { c::tcp_setAbortOnClose(self.as_legacy_tcp(), abort_on_close.into()) };
+                self.linger = linger;
+            }
+            (libc::SOL_SOCKET, libc::SO_BINDTODEVICE) => {
+                self.bound_device = inet::read_bind_to_device(optval_ptr, optlen, memory_manager)?;
+            }
+            (libc::SOL_SOCKET, libc::SO_DONTROUTE) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                self.dont_route = memory_manager.read(optval_ptr)? != 0;
+            }
+            (libc::SOL_SOCKET, libc::SO_PRIORITY) => {
+                let priority = inet::read_priority(optval_ptr, optlen, memory_manager)?;
+                unsafe { c::tcp_setSocketPriority(self.as_legacy_tcp(), priority) };
+            }
             _ => {
                 log_once_per_value_at_level!(
                     (level, optname),
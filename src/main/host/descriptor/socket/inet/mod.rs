@@ -6,6 +6,7 @@ use linux_api::errno::Errno;
 use linux_api::ioctls::IoctlRequest;
 use linux_api::socket::Shutdown;
 use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 
 use crate::cshadow as c;
@@ -16,18 +17,22 @@ use crate::host::descriptor::{
 };
 use crate::host::memory_manager::MemoryManager;
 use crate::host::network::interface::FifoPacketPriority;
-use crate::host::network::namespace::{AssociationHandle, NetworkNamespace};
+use crate::host::network::namespace::{
+    AssociationHandle, INTERNET_INTERFACE_NAME, LOCALHOST_INTERFACE_NAME, NetworkNamespace,
+};
 use crate::host::syscall::io::IoVec;
-use crate::host::syscall::types::SyscallError;
+use crate::host::syscall::types::{ForeignArrayPtr, SyscallError};
 use crate::network::packet::{IanaProtocol, PacketRc};
 use crate::utility::HostTreePointer;
 use crate::utility::callback_queue::CallbackQueue;
 use crate::utility::sockaddr::SockaddrStorage;
 
+use self::icmp::IcmpSocket;
 use self::legacy_tcp::LegacyTcpSocket;
 use self::tcp::TcpSocket;
 use self::udp::UdpSocket;
 
+pub mod icmp;
 pub mod legacy_tcp;
 pub mod tcp;
 pub mod udp;
@@ -37,6 +42,7 @@ pub enum InetSocket {
     LegacyTcp(Arc<AtomicRefCell<LegacyTcpSocket>>),
     Tcp(Arc<AtomicRefCell<TcpSocket>>),
     Udp(Arc<AtomicRefCell<UdpSocket>>),
+    Icmp(Arc<AtomicRefCell<IcmpSocket>>),
 }
 
 impl InetSocket {
@@ -45,6 +51,7 @@ impl InetSocket {
             Self::LegacyTcp(f) => InetSocketRef::LegacyTcp(f.borrow()),
             Self::Tcp(f) => InetSocketRef::Tcp(f.borrow()),
             Self::Udp(f) => InetSocketRef::Udp(f.borrow()),
+            Self::Icmp(f) => InetSocketRef::Icmp(f.borrow()),
         }
     }
 
@@ -53,6 +60,7 @@ impl InetSocket {
             Self::LegacyTcp(f) => InetSocketRef::LegacyTcp(f.try_borrow()?),
             Self::Tcp(f) => InetSocketRef::Tcp(f.try_borrow()?),
             Self::Udp(f) => InetSocketRef::Udp(f.try_borrow()?),
+            Self::Icmp(f) => InetSocketRef::Icmp(f.try_borrow()?),
         })
     }
 
@@ -61,6 +69,7 @@ impl InetSocket {
             Self::LegacyTcp(f) => InetSocketRefMut::LegacyTcp(f.borrow_mut()),
             Self::Tcp(f) => InetSocketRefMut::Tcp(f.borrow_mut()),
             Self::Udp(f) => InetSocketRefMut::Udp(f.borrow_mut()),
+            Self::Icmp(f) => InetSocketRefMut::Icmp(f.borrow_mut()),
         }
     }
 
@@ -69,6 +78,7 @@ impl InetSocket {
             Self::LegacyTcp(f) => InetSocketRefMut::LegacyTcp(f.try_borrow_mut()?),
             Self::Tcp(f) => InetSocketRefMut::Tcp(f.try_borrow_mut()?),
             Self::Udp(f) => InetSocketRefMut::Udp(f.try_borrow_mut()?),
+            Self::Icmp(f) => InetSocketRefMut::Icmp(f.try_borrow_mut()?),
         })
     }
 
@@ -77,6 +87,7 @@ impl InetSocket {
             Self::LegacyTcp(x) => InetSocketWeak::LegacyTcp(Arc::downgrade(x)),
             Self::Tcp(x) => InetSocketWeak::Tcp(Arc::downgrade(x)),
             Self::Udp(x) => InetSocketWeak::Udp(Arc::downgrade(x)),
+            Self::Icmp(x) => InetSocketWeak::Icmp(Arc::downgrade(x)),
         }
     }
 
@@ -90,6 +101,7 @@ impl InetSocket {
             Self::LegacyTcp(f) => f.borrow().canonical_handle(),
             Self::Tcp(f) => Arc::as_ptr(f) as usize,
             Self::Udp(f) => Arc::as_ptr(f) as usize,
+            Self::Icmp(f) => Arc::as_ptr(f) as usize,
         }
     }
 
@@ -103,6 +115,7 @@ impl InetSocket {
             Self::LegacyTcp(socket) => LegacyTcpSocket::bind(socket, addr, net_ns, rng),
             Self::Tcp(socket) => TcpSocket::bind(socket, addr, net_ns, rng),
             Self::Udp(socket) => UdpSocket::bind(socket, addr, net_ns, rng),
+            Self::Icmp(socket) => IcmpSocket::bind(socket, addr, net_ns, rng),
         }
     }
 
@@ -119,6 +132,7 @@ impl InetSocket {
             }
             Self::Tcp(socket) => TcpSocket::listen(socket, backlog, net_ns, rng, cb_queue),
             Self::Udp(socket) => UdpSocket::listen(socket, backlog, net_ns, rng, cb_queue),
+            Self::Icmp(socket) => IcmpSocket::listen(socket, backlog, net_ns, rng, cb_queue),
         }
     }
 
@@ -135,6 +149,7 @@ impl InetSocket {
             }
             Self::Tcp(socket) => TcpSocket::connect(socket, addr, net_ns, rng, cb_queue),
             Self::Udp(socket) => UdpSocket::connect(socket, addr, net_ns, rng, cb_queue),
+            Self::Icmp(socket) => IcmpSocket::connect(socket, addr, net_ns, rng, cb_queue),
         }
     }
 
@@ -156,6 +171,9 @@ impl InetSocket {
             Self::Udp(socket) => {
                 UdpSocket::sendmsg(socket, args, memory_manager, net_ns, rng, cb_queue)
             }
+            Self::Icmp(socket) => {
+                IcmpSocket::sendmsg(socket, args, memory_manager, net_ns, rng, cb_queue)
+            }
         }
     }
 
@@ -171,6 +189,7 @@ impl InetSocket {
             }
             Self::Tcp(socket) => TcpSocket::recvmsg(socket, args, memory_manager, cb_queue),
             Self::Udp(socket) => UdpSocket::recvmsg(socket, args, memory_manager, cb_queue),
+            Self::Icmp(socket) => IcmpSocket::recvmsg(socket, args, memory_manager, cb_queue),
         }
     }
 }
@@ -181,6 +200,7 @@ impl std::fmt::Debug for InetSocket {
             Self::LegacyTcp(_) => write!(f, "LegacyTcp")?,
             Self::Tcp(_) => write!(f, "Tcp")?,
             Self::Udp(_) => write!(f, "Udp")?,
+            Self::Icmp(_) => write!(f, "Icmp")?,
         }
 
         if let Ok(file) = self.try_borrow() {
@@ -215,6 +235,7 @@ impl PartialEq for InetSocket {
             (Self::LegacyTcp(self_), Self::LegacyTcp(other)) => Arc::ptr_eq(self_, other),
             (Self::Tcp(self_), Self::Tcp(other)) => Arc::ptr_eq(self_, other),
             (Self::Udp(self_), Self::Udp(other)) => Arc::ptr_eq(self_, other),
+            (Self::Icmp(self_), Self::Icmp(other)) => Arc::ptr_eq(self_, other),
             _ => false,
         }
     }
@@ -238,6 +259,7 @@ impl std::hash::Hash for InetSocket {
             Self::LegacyTcp(x) => Arc::as_ptr(x).cast::<libc::c_void>(),
             Self::Tcp(x) => Arc::as_ptr(x).cast(),
             Self::Udp(x) => Arc::as_ptr(x).cast(),
+            Self::Icmp(x) => Arc::as_ptr(x).cast(),
         }
         .hash(state);
     }
@@ -247,32 +269,34 @@ pub enum InetSocketRef<'a> {
     LegacyTcp(atomic_refcell::AtomicRef<'a, LegacyTcpSocket>),
     Tcp(atomic_refcell::AtomicRef<'a, TcpSocket>),
     Udp(atomic_refcell::AtomicRef<'a, UdpSocket>),
+    Icmp(atomic_refcell::AtomicRef<'a, IcmpSocket>),
 }
 
 pub enum InetSocketRefMut<'a> {
     LegacyTcp(atomic_refcell::AtomicRefMut<'a, LegacyTcpSocket>),
     Tcp(atomic_refcell::AtomicRefMut<'a, TcpSocket>),
     Udp(atomic_refcell::AtomicRefMut<'a, UdpSocket>),
+    Icmp(atomic_refcell::AtomicRefMut<'a, IcmpSocket>),
 }
 
 // file functions
 impl InetSocketRef<'_> {
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
         pub fn state(&self) -> FileState
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
         pub fn mode(&self) -> FileMode
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
         pub fn status(&self) -> FileStatus
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
         pub fn stat(&self) -> Result<linux_api::stat::stat, SyscallError>
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
         pub fn has_open_file(&self) -> bool
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
         pub fn supports_sa_restart(&self) -> bool
     );
 }
@@ -284,6 +308,7 @@ impl InetSocketRef<'_> {
             Self::LegacyTcp(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
             Self::Tcp(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
             Self::Udp(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
+            Self::Icmp(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
         }
     }
 
@@ -292,57 +317,62 @@ impl InetSocketRef<'_> {
             Self::LegacyTcp(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
             Self::Tcp(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
             Self::Udp(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
+            Self::Icmp(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
         }
     }
 
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
         pub fn address_family(&self) -> linux_api::socket::AddressFamily
     );
+
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
+        pub fn recv_timeout(&self) -> Option<SimulationTime>
+    );
 }
 
 // inet socket-specific functions
 impl InetSocketRef<'_> {
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
         pub fn peek_next_packet_priority(&self) -> Option<FifoPacketPriority>
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
         pub fn has_data_to_send(&self) -> bool
     );
 }
 
 // file functions
 impl InetSocketRefMut<'_> {
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
         pub fn state(&self) -> FileState
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
         pub fn mode(&self) -> FileMode
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
         pub fn status(&self) -> FileStatus
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
         pub fn stat(&self) -> Result<linux_api::stat::stat, SyscallError>
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
         pub fn has_open_file(&self) -> bool
     );
-    enum_passthrough!(self, (val), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (val), LegacyTcp, Tcp, Udp, Icmp;
         pub fn set_has_open_file(&mut self, val: bool)
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
         pub fn supports_sa_restart(&self) -> bool
     );
-    enum_passthrough!(self, (cb_queue), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (cb_queue), LegacyTcp, Tcp, Udp, Icmp;
         pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError>
     );
-    enum_passthrough!(self, (status), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (status), LegacyTcp, Tcp, Udp, Icmp;
         pub fn set_status(&mut self, status: FileStatus)
     );
-    enum_passthrough!(self, (request, arg_ptr, memory_manager), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (request, arg_ptr, memory_manager), LegacyTcp, Tcp, Udp, Icmp;
         pub fn ioctl(&mut self, request: IoctlRequest, arg_ptr: ForeignPtr<()>, memory_manager: &mut MemoryManager) -> SyscallResult
     );
-    enum_passthrough!(self, (monitoring_state, monitoring_signals, filter, notify_fn), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (monitoring_state, monitoring_signals, filter, notify_fn), LegacyTcp, Tcp, Udp, Icmp;
         pub fn add_listener(
             &mut self,
             monitoring_state: FileState,
@@ -351,17 +381,17 @@ impl InetSocketRefMut<'_> {
             notify_fn: impl Fn(FileState, FileState, FileSignals, &mut CallbackQueue) + Send + Sync + 'static,
         ) -> StateListenHandle
     );
-    enum_passthrough!(self, (ptr), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (ptr), LegacyTcp, Tcp, Udp, Icmp;
         pub fn add_legacy_listener(&mut self, ptr: HostTreePointer<c::StatusListener>)
     );
-    enum_passthrough!(self, (ptr), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (ptr), LegacyTcp, Tcp, Udp, Icmp;
         pub fn remove_legacy_listener(&mut self, ptr: *mut c::StatusListener)
     );
-    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), LegacyTcp, Tcp, Udp, Icmp;
         pub fn readv(&mut self, iovs: &[IoVec], offset: Option<libc::off_t>, flags: libc::c_int,
                      mem: &mut MemoryManager, cb_queue: &mut CallbackQueue) -> Result<libc::ssize_t, SyscallError>
     );
-    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), LegacyTcp, Tcp, Udp, Icmp;
         pub fn writev(&mut self, iovs: &[IoVec], offset: Option<libc::off_t>, flags: libc::c_int,
                       mem: &mut MemoryManager, cb_queue: &mut CallbackQueue) -> Result<libc::ssize_t, SyscallError>
     );
@@ -374,6 +404,7 @@ impl InetSocketRefMut<'_> {
             Self::LegacyTcp(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
             Self::Tcp(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
             Self::Udp(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
+            Self::Icmp(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
         }
     }
 
@@ -382,22 +413,23 @@ impl InetSocketRefMut<'_> {
             Self::LegacyTcp(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
             Self::Tcp(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
             Self::Udp(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
+            Self::Icmp(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
         }
     }
 
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
         pub fn address_family(&self) -> linux_api::socket::AddressFamily
     );
 
-    enum_passthrough!(self, (level, optname, optval_ptr, optlen, memory_manager, cb_queue), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (level, optname, optval_ptr, optlen, memory_manager, cb_queue), LegacyTcp, Tcp, Udp, Icmp;
         pub fn getsockopt(&mut self, level: libc::c_int, optname: libc::c_int, optval_ptr: ForeignPtr<()>,
                           optlen: libc::socklen_t, memory_manager: &mut MemoryManager, cb_queue: &mut CallbackQueue)
         -> Result<libc::socklen_t, SyscallError>
     );
 
-    enum_passthrough!(self, (level, optname, optval_ptr, optlen, memory_manager), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (level, optname, optval_ptr, optlen, memory_manager, cb_queue), LegacyTcp, Tcp, Udp, Icmp;
         pub fn setsockopt(&mut self, level: libc::c_int, optname: libc::c_int, optval_ptr: ForeignPtr<()>,
-                          optlen: libc::socklen_t, memory_manager: &MemoryManager)
+                          optlen: libc::socklen_t, memory_manager: &MemoryManager, cb_queue: &mut CallbackQueue)
         -> Result<(), SyscallError>
     );
 
@@ -411,26 +443,27 @@ impl InetSocketRefMut<'_> {
             Self::LegacyTcp(socket) => socket.accept(net_ns, rng, cb_queue),
             Self::Tcp(socket) => socket.accept(net_ns, rng, cb_queue),
             Self::Udp(socket) => socket.accept(net_ns, rng, cb_queue),
+            Self::Icmp(socket) => socket.accept(net_ns, rng, cb_queue),
         }
     }
 
-    enum_passthrough!(self, (how, cb_queue), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (how, cb_queue), LegacyTcp, Tcp, Udp, Icmp;
         pub fn shutdown(&mut self, how: Shutdown, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError>
     );
 }
 
 // inet socket-specific functions
 impl InetSocketRefMut<'_> {
-    enum_passthrough!(self, (packet, cb_queue, recv_time), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (packet, cb_queue, recv_time), LegacyTcp, Tcp, Udp, Icmp;
         pub fn push_in_packet(&mut self, packet: PacketRc, cb_queue: &mut CallbackQueue, recv_time: EmulatedTime)
     );
-    enum_passthrough!(self, (cb_queue), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (cb_queue), LegacyTcp, Tcp, Udp, Icmp;
         pub fn pull_out_packet(&mut self, cb_queue: &mut CallbackQueue) -> Option<PacketRc>
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
         pub fn peek_next_packet_priority(&self) -> Option<FifoPacketPriority>
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp;
         pub fn has_data_to_send(&self) -> bool
     );
 }
@@ -441,6 +474,7 @@ impl std::fmt::Debug for InetSocketRef<'_> {
             Self::LegacyTcp(_) => write!(f, "LegacyTcp")?,
             Self::Tcp(_) => write!(f, "Tcp")?,
             Self::Udp(_) => write!(f, "Udp")?,
+            Self::Icmp(_) => write!(f, "Icmp")?,
         }
 
         write!(
@@ -458,6 +492,7 @@ impl std::fmt::Debug for InetSocketRefMut<'_> {
             Self::LegacyTcp(_) => write!(f, "LegacyTcp")?,
             Self::Tcp(_) => write!(f, "Tcp")?,
             Self::Udp(_) => write!(f, "Udp")?,
+            Self::Icmp(_) => write!(f, "Icmp")?,
         }
 
         write!(
@@ -469,11 +504,12 @@ impl std::fmt::Debug for InetSocketRefMut<'_> {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum InetSocketWeak {
     LegacyTcp(Weak<AtomicRefCell<LegacyTcpSocket>>),
     Tcp(Weak<AtomicRefCell<TcpSocket>>),
     Udp(Weak<AtomicRefCell<UdpSocket>>),
+    Icmp(Weak<AtomicRefCell<IcmpSocket>>),
 }
 
 impl InetSocketWeak {
@@ -482,6 +518,21 @@ impl InetSocketWeak {
             Self::LegacyTcp(x) => x.upgrade().map(InetSocket::LegacyTcp),
             Self::Tcp(x) => x.upgrade().map(InetSocket::Tcp),
             Self::Udp(x) => x.upgrade().map(InetSocket::Udp),
+            Self::Icmp(x) => x.upgrade().map(InetSocket::Icmp),
+        }
+    }
+
+    /// Returns true if this is a weak reference to `socket`. Unlike [`upgrade`](Self::upgrade),
+    /// this doesn't require the socket to still be alive.
+    pub fn points_to(&self, socket: &InetSocket) -> bool {
+        match (self, socket) {
+            (Self::LegacyTcp(x), InetSocket::LegacyTcp(y)) => {
+                std::ptr::eq(x.as_ptr(), Arc::as_ptr(y))
+            }
+            (Self::Tcp(x), InetSocket::Tcp(y)) => std::ptr::eq(x.as_ptr(), Arc::as_ptr(y)),
+            (Self::Udp(x), InetSocket::Udp(y)) => std::ptr::eq(x.as_ptr(), Arc::as_ptr(y)),
+            (Self::Icmp(x), InetSocket::Icmp(y)) => std::ptr::eq(x.as_ptr(), Arc::as_ptr(y)),
+            _ => false,
         }
     }
 }
@@ -492,12 +543,15 @@ impl InetSocketWeak {
 /// unspecified and has a port of 0, the socket will receive packets from every peer address. The
 /// socket will be automatically disassociated when the returned [`AssociationHandle`] is dropped.
 /// If `check_generic_peer` is true, the association will also fail if there is already a socket
-/// associated with the local address `local_addr` and peer address 0.0.0.0:0.
+/// associated with the local address `local_addr` and peer address 0.0.0.0:0. If `reuse_port` is
+/// true, the socket may share the addresses with other sockets that were also associated with
+/// `reuse_port` (a reuseport group), but an ephemeral port will always be an unused port.
 fn associate_socket(
     socket: InetSocket,
     local_addr: SocketAddrV4,
     peer_addr: SocketAddrV4,
     check_generic_peer: bool,
+    reuse_port: bool,
     net_ns: &NetworkNamespace,
     rng: impl rand::Rng,
 ) -> Result<(SocketAddrV4, AssociationHandle), Errno> {
@@ -515,6 +569,11 @@ fn associate_socket(
         InetSocket::LegacyTcp(_) => IanaProtocol::Tcp,
         InetSocket::Tcp(_) => IanaProtocol::Tcp,
         InetSocket::Udp(_) => IanaProtocol::Udp,
+        // raw icmp sockets aren't bound to a port and their replies are generated by the host
+        InetSocket::Icmp(_) => {
+            log::warn!("Attempted to associate an ICMP socket with a network interface");
+            return Err(Errno::EINVAL);
+        }
     };
 
     // get a free ephemeral port if they didn't specify one
@@ -535,7 +594,7 @@ fn associate_socket(
     };
 
     // make sure the port is available at this address for this protocol
-    match net_ns.is_addr_in_use(protocol, local_addr, peer_addr) {
+    match net_ns.is_addr_in_use(protocol, local_addr, peer_addr, reuse_port) {
         Ok(true) => {
             log::debug!(
                 "The provided addresses (local={local_addr}, peer={peer_addr}) are not available"
@@ -551,6 +610,7 @@ fn associate_socket(
             protocol,
             local_addr,
             SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0),
+            reuse_port,
         ) {
             Ok(true) => {
                 log::debug!(
@@ -565,11 +625,183 @@ fn associate_socket(
     }
 
     // associate the interfaces corresponding to addr with socket
-    let handle = unsafe { net_ns.associate_interface(&socket, protocol, local_addr, peer_addr) };
+    let handle =
+        unsafe { net_ns.associate_interface(&socket, protocol, local_addr, peer_addr, reuse_port) };
 
     Ok((local_addr, handle))
 }
 
+/// Returns `true` if a TCP connection from `local_addr` to `peer_addr` would be refused because
+/// `peer_addr` is an address on this host and no socket there would accept the connection. Linux
+/// would respond to the SYN with a RST in this case. Connections to other hosts are never considered
+/// refused since we can't look up the sockets of other hosts.
+fn is_tcp_connection_refused(
+    socket: &InetSocket,
+    local_addr: SocketAddrV4,
+    peer_addr: SocketAddrV4,
+    net_ns: &NetworkNamespace,
+) -> bool {
+    let Some(interface) = net_ns.interface_borrow(*peer_addr.ip()) else {
+        // the peer address is not on this host
+        return false;
+    };
+
+    let Some(peer_socket) = interface.recv_socket(IanaProtocol::Tcp, peer_addr.port(), local_addr)
+    else {
+        // no socket is bound to the peer address
+        return true;
+    };
+
+    // the socket is connecting to itself
+    if &peer_socket == socket {
+        return false;
+    }
+
+    let peer_socket = peer_socket.borrow();
+
+    // a socket that is itself connecting to us will accept the connection (simultaneous open)
+    let is_connecting_to_us = matches!(
+        peer_socket.getpeername(),
+        Ok(Some(addr)) if addr.as_inet().map(|x| SocketAddrV4::from(*x)) == Some(local_addr),
+    );
+
+    let is_listening = match &peer_socket {
+        InetSocketRef::LegacyTcp(x) => unsafe { c::tcp_isValidListener(x.as_legacy_tcp()) == 1 },
+        InetSocketRef::Tcp(x) => x.is_listening(),
+        InetSocketRef::Udp(_) => false,
+        InetSocketRef::Icmp(_) => false,
+    };
+
+    !is_listening && !is_connecting_to_us
+}
+
+/// Returns the name of the network interface that packets to `addr` would be routed through.
+fn route_interface_name(addr: Ipv4Addr) -> &'static str {
+    // shadow treats any non-127.0.0.1 address as an "internet" address
+    if addr == Ipv4Addr::LOCALHOST {
+        LOCALHOST_INTERFACE_NAME
+    } else {
+        INTERNET_INTERFACE_NAME
+    }
+}
+
+/// Returns true if `addr` is on a network that is directly connected to one of the host's
+/// interfaces, and so can be reached without a gateway (used for `SO_DONTROUTE`). The networks use
+/// the same prefix lengths that `getifaddrs()` reports for the interfaces.
+fn is_on_link(addr: Ipv4Addr, host_default_ip: Ipv4Addr) -> bool {
+    let same_network = |a: Ipv4Addr, b: Ipv4Addr, prefix_len: u32| {
+        let mask = u32::MAX.checked_shl(32 - prefix_len).unwrap_or(0);
+        u32::from(a) & mask == u32::from(b) & mask
+    };
+
+    same_network(addr, Ipv4Addr::LOCALHOST, 8) || same_network(addr, host_default_ip, 24)
+}
+
+/// Read the priority given to `setsockopt(SO_PRIORITY)`. Like linux, priorities outside of 0-6
+/// require `CAP_NET_ADMIN`, which we only allow when running as root (as for raw sockets).
+fn read_priority(
+    optval_ptr: ForeignPtr<()>,
+    optlen: libc::socklen_t,
+    mem: &MemoryManager,
+) -> Result<u32, SyscallError> {
+    type OptType = libc::c_int;
+
+    if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+        return Err(Errno::EINVAL.into());
+    }
+
+    let optval_ptr = optval_ptr.cast::<OptType>();
+    let priority = mem.read(optval_ptr)?;
+
+    if !(0..=6).contains(&priority) && !nix::unistd::geteuid().is_root() {
+        log::debug!("SO_PRIORITY {priority} requires root or CAP_NET_ADMIN");
+        return Err(Errno::EPERM.into());
+    }
+
+    Ok(priority as u32)
+}
+
+/// Read the busy polling timeout given to `setsockopt(SO_BUSY_POLL)`. Like linux, negative values
+/// aren't allowed.
+fn read_busy_poll(
+    optval_ptr: ForeignPtr<()>,
+    optlen: libc::socklen_t,
+    mem: &MemoryManager,
+) -> Result<u32, SyscallError> {
+    type OptType = libc::c_int;
+
+    if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+        return Err(Errno::EINVAL.into());
+    }
+
+    let optval_ptr = optval_ptr.cast::<OptType>();
+    let busy_poll = mem.read(optval_ptr)?;
+
+    u32::try_from(busy_poll).or(Err(Errno::EINVAL.into()))
+}
+
+/// Read the interface name given to `setsockopt(SO_BINDTODEVICE)`. Returns `None` if the name is
+/// empty, which removes an existing binding, or `ENODEV` if there's no interface with that name.
+fn read_bind_to_device(
+    optval_ptr: ForeignPtr<()>,
+    optlen: libc::socklen_t,
+    mem: &MemoryManager,
+) -> Result<Option<&'static str>, SyscallError> {
+    // like linux, the name doesn't need a NUL and is truncated to IFNAMSIZ-1 bytes
+    let mut name = [0u8; libc::IFNAMSIZ - 1];
+
+    let optlen = std::cmp::min(optlen as usize, name.len());
+    let name = &mut name[..optlen];
+
+    let optval_ptr = optval_ptr.cast::<u8>();
+    let optval_ptr = ForeignArrayPtr::new(optval_ptr, optlen);
+    mem.copy_from_ptr(name, optval_ptr)?;
+
+    // truncate the name at the first NUL character if there is one
+    let name = name
+        .iter()
+        .position(|x| *x == 0)
+        .map(|x| &name[..x])
+        .unwrap_or(name);
+
+    if name.is_empty() {
+        return Ok(None);
+    }
+
+    [LOCALHOST_INTERFACE_NAME, INTERNET_INTERFACE_NAME]
+        .into_iter()
+        .find(|x| x.as_bytes() == name)
+        .map(Some)
+        .ok_or(Errno::ENODEV.into())
+}
+
+/// Write the interface name for `getsockopt(SO_BINDTODEVICE)`, and return the option length.
+fn write_bind_to_device(
+    device: Option<&str>,
+    optval_ptr: ForeignPtr<()>,
+    optlen: libc::socklen_t,
+    mem: &mut MemoryManager,
+) -> Result<libc::socklen_t, SyscallError> {
+    // like linux, the optlen is checked even if the socket isn't bound to an interface
+    if (optlen as usize) < libc::IFNAMSIZ {
+        return Err(Errno::EINVAL.into());
+    }
+
+    // like linux, return an empty value if the socket isn't bound to an interface
+    let Some(device) = device else {
+        return Ok(0);
+    };
+
+    let name = std::ffi::CString::new(device).unwrap();
+    let name = name.as_bytes_with_nul();
+
+    let optval_ptr = optval_ptr.cast::<u8>();
+    let optval_ptr = ForeignArrayPtr::new(optval_ptr, name.len());
+    mem.copy_to_ptr(optval_ptr, name)?;
+
+    Ok(name.len().try_into().unwrap())
+}
+
 mod export {
     use super::*;
 
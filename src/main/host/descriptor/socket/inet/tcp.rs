@@ -15,7 +15,10 @@ use crate::core::worker::Worker;
 use crate::cshadow as c;
 use crate::host::descriptor::listener::{StateEventSource, StateListenHandle, StateListenerFilter};
 use crate::host::descriptor::socket::inet;
-use crate::host::descriptor::socket::{InetSocket, RecvmsgArgs, RecvmsgReturn, SendmsgArgs};
+use crate::host::descriptor::socket::{
+    AncillaryData, InetSocket, RecvmsgArgs, RecvmsgReturn, SendmsgArgs, read_timeout, send_flags,
+    write_timeout,
+};
 use crate::host::descriptor::{File, Socket};
 use crate::host::descriptor::{
     FileMode, FileSignals, FileState, FileStatus, OpenFile, SyscallResult,
@@ -25,7 +28,7 @@ use crate::host::network::interface::FifoPacketPriority;
 use crate::host::network::namespace::{AssociationHandle, NetworkNamespace};
 use crate::host::syscall::io::{IoVec, IoVecReader, IoVecWriter, write_partial};
 use crate::host::syscall::types::SyscallError;
-use crate::network::packet::{PacketRc, PacketStatus};
+use crate::network::packet::{Packet, PacketRc, PacketStatus};
 use crate::utility::callback_queue::CallbackQueue;
 use crate::utility::sockaddr::SockaddrStorage;
 use crate::utility::{HostTreePointer, ObjectCounter};
@@ -37,7 +40,40 @@ pub struct TcpSocket {
     status: FileStatus,
     file_state: FileState,
     association: Option<AssociationHandle>,
+    /// Was `SO_REUSEPORT` enabled? Only applies to future associations.
+    reuse_port: bool,
+    /// The send buffer size, as returned by `SO_SNDBUF`.
+    send_buffer_size: u32,
+    /// The receive buffer size, as returned by `SO_RCVBUF`.
+    recv_buffer_size: u32,
+    /// The `TCP_DEFER_ACCEPT` timeout in seconds, as returned by `TCP_DEFER_ACCEPT`.
+    defer_accept_secs: u32,
+    /// The `TCP_FASTOPEN` queue length, as returned by `TCP_FASTOPEN`.
+    fast_open_queue_len: u32,
+    /// The `TCP_USER_TIMEOUT` timeout in milliseconds, as returned by `TCP_USER_TIMEOUT`.
+    user_timeout_ms: u32,
+    /// The `TCP_QUICKACK` flag, as returned by `TCP_QUICKACK`. This tcp implementation never
+    /// delays ACKs, so the flag has no other effect.
+    quick_ack: bool,
+    /// The timeout set by `SO_RCVTIMEO` for blocking receives and accepts, if any.
+    recv_timeout: Option<SimulationTime>,
+    /// The mark set by `SO_MARK`, which is given to the packets we send. Shadow doesn't support
+    /// policy routing, so it isn't used for routing decisions.
+    mark: u32,
+    /// The busy polling timeout set by `SO_BUSY_POLL`. Shadow doesn't busy poll, so it's only
+    /// returned by `getsockopt()`.
+    busy_poll: u32,
+    /// The priority set by `SO_PRIORITY`, which is given to the packets we send.
+    priority: u32,
+    /// The network interface set by `SO_BINDTODEVICE`. Only applies to future connections.
+    bound_device: Option<&'static str>,
+    /// Was `SO_DONTROUTE` enabled? Only applies to future connections, which can then only be to
+    /// directly-connected networks.
+    dont_route: bool,
     connect_result_is_pending: bool,
+    /// Was the connection ever established? A RST received before then means that the connection
+    /// was refused rather than reset.
+    was_established: bool,
     shutdown_status: Option<Shutdown>,
     // should only be used by `OpenFile` to make sure there is only ever one `OpenFile` instance for
     // this file
@@ -46,6 +82,9 @@ pub struct TcpSocket {
 }
 
 impl TcpSocket {
+    /// The initial size of the send and receive buffers.
+    const DEFAULT_BUFFER_SIZE: u32 = 100_000;
+
     pub fn new(status: FileStatus) -> Arc<AtomicRefCell<Self>> {
         let rv = Arc::new_cyclic(|weak: &Weak<AtomicRefCell<Self>>| {
             let tcp_dependencies = TcpDeps {
@@ -55,8 +94,12 @@ impl TcpSocket {
                 })),
             };
 
+            let mut config = tcp::TcpConfig::default();
+            config.send_buffer_size(Self::DEFAULT_BUFFER_SIZE);
+            config.recv_buffer_size(Self::DEFAULT_BUFFER_SIZE);
+
             AtomicRefCell::new(Self {
-                tcp_state: tcp::TcpState::new(tcp_dependencies, tcp::TcpConfig::default()),
+                tcp_state: tcp::TcpState::new(tcp_dependencies, config),
                 socket_weak: weak.clone(),
                 event_source: StateEventSource::new(),
                 status,
@@ -64,7 +107,21 @@ impl TcpSocket {
                 // `with_tcp_state` below to update it, but we need ACTIVE set so that epoll works
                 file_state: FileState::ACTIVE,
                 association: None,
+                reuse_port: false,
+                send_buffer_size: Self::DEFAULT_BUFFER_SIZE,
+                recv_buffer_size: Self::DEFAULT_BUFFER_SIZE,
+                defer_accept_secs: 0,
+                fast_open_queue_len: 0,
+                user_timeout_ms: 0,
+                quick_ack: true,
+                recv_timeout: None,
+                mark: 0,
+                busy_poll: 0,
+                priority: 0,
+                bound_device: None,
+                dont_route: false,
                 connect_result_is_pending: false,
+                was_established: false,
                 shutdown_status: None,
                 has_open_file: false,
                 _counter: ObjectCounter::new("TcpSocket"),
@@ -156,12 +213,30 @@ impl TcpSocket {
         let mut read_write_flags = FileState::empty();
         let poll_state = self.tcp_state.poll();
 
+        // a connection attempt that failed is also "connected", but has an error or is closed
+        if poll_state.contains(tcp::PollState::CONNECTED)
+            && !poll_state.intersects(tcp::PollState::ERROR | tcp::PollState::CLOSED)
+        {
+            self.was_established = true;
+        }
+
         if poll_state.intersects(tcp::PollState::READABLE | tcp::PollState::RECV_CLOSED) {
             read_write_flags.insert(FileState::READABLE);
         }
         if poll_state.intersects(tcp::PollState::WRITABLE) {
             read_write_flags.insert(FileState::WRITABLE);
         }
+        // after `shutdown(SHUT_WR)` a send will return `EPIPE` rather than block
+        if poll_state.intersects(tcp::PollState::SEND_CLOSED) {
+            read_write_flags.insert(FileState::WRITABLE);
+        }
+        // after `shutdown(SHUT_RD)` a recv will return an EOF rather than block
+        if matches!(
+            self.shutdown_status,
+            Some(Shutdown::SHUT_RD | Shutdown::SHUT_RDWR)
+        ) {
+            read_write_flags.insert(FileState::READABLE);
+        }
         if poll_state.intersects(tcp::PollState::READY_TO_ACCEPT) {
             read_write_flags.insert(FileState::READABLE);
         }
@@ -261,7 +336,11 @@ impl TcpSocket {
         // We transfer the `Bytes` objects directly from the tcp state's `Payload` object to the
         // packet without copying the bytes themselves.
         // TODO: set packet priority?
-        let packet = PacketRc::new_ipv4_tcp(header, payload, 0);
+        let packet = Packet::new_ipv4_tcp(header, payload, 0);
+        let packet = packet
+            .with_socket_priority(self.priority)
+            .with_mark(self.mark);
+        let packet = PacketRc::from(packet);
         packet.add_status(PacketStatus::SndCreated);
 
         Some(packet)
@@ -288,6 +367,11 @@ impl TcpSocket {
         ))
     }
 
+    /// Returns `true` if the socket is listening for new connections.
+    pub fn is_listening(&self) -> bool {
+        self.tcp_state.poll().contains(tcp::PollState::LISTENING)
+    }
+
     pub fn getpeername(&self) -> Result<Option<SockaddrIn>, Errno> {
         // The association won't always have the peer address. For example if the socket was bound
         // before connect() was called, the association will have a peer of 0.0.0.0. Instead we
@@ -311,6 +395,10 @@ impl TcpSocket {
         linux_api::socket::AddressFamily::AF_INET
     }
 
+    pub fn recv_timeout(&self) -> Option<SimulationTime> {
+        self.recv_timeout
+    }
+
     pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError> {
         // we don't expect close() to ever have an error
         self.with_tcp_state(cb_queue, |state| state.close())
@@ -361,6 +449,7 @@ impl TcpSocket {
             addr,
             peer_addr,
             /* check_generic_peer= */ true,
+            socket_ref.reuse_port,
             net_ns,
             rng,
         )?;
@@ -402,16 +491,16 @@ impl TcpSocket {
         socket: &Arc<AtomicRefCell<Self>>,
         args: SendmsgArgs,
         mem: &mut MemoryManager,
-        _net_ns: &NetworkNamespace,
-        _rng: impl rand::Rng,
+        net_ns: &NetworkNamespace,
+        rng: impl rand::Rng,
         cb_queue: &mut CallbackQueue,
     ) -> Result<libc::ssize_t, SyscallError> {
         let mut socket_ref = socket.borrow_mut();
 
-        let Some(mut flags) = MsgFlags::from_bits(args.flags) else {
-            log::debug!("Unrecognized send flags: {:#b}", args.flags);
-            return Err(Errno::EINVAL.into());
-        };
+        // nix doesn't support `MSG_FASTOPEN`, so we handle it separately
+        let fast_open = args.flags & libc::MSG_FASTOPEN != 0;
+
+        let mut flags = send_flags(args.flags);
 
         if socket_ref.status().contains(FileStatus::NONBLOCK) {
             flags.insert(MsgFlags::MSG_DONTWAIT);
@@ -419,8 +508,47 @@ impl TcpSocket {
 
         let len: libc::size_t = args.iovs.iter().map(|x| x.len).sum();
 
+        if fast_open {
+            // like Linux, an address is required and the socket must not already be connected or
+            // connecting
+            let Some(peer_addr) = args.addr else {
+                return Err(Errno::EINVAL.into());
+            };
+
+            Self::start_connect(socket, &mut socket_ref, &peer_addr, net_ns, rng, cb_queue)?;
+
+            // a future connect() call should return the result
+            socket_ref.connect_result_is_pending = true;
+
+            // the SYN hasn't been sent yet, so the data will be sent in the SYN (and any data that
+            // doesn't fit will be sent after the handshake)
+            let reader = IoVecReader::new(args.iovs, mem);
+            let rv = socket_ref.with_tcp_state(cb_queue, |state| state.send_fast_open(reader, len));
+
+            let num_sent = match rv {
+                Ok(x) => x,
+                Err(tcp::SendError::Full) => return Err(Errno::EWOULDBLOCK.into()),
+                Err(tcp::SendError::NotConnected) => return Err(Errno::EPIPE.into()),
+                Err(tcp::SendError::StreamClosed) => return Err(Errno::EPIPE.into()),
+                Err(tcp::SendError::Io(e)) => return Err(Errno::try_from(e).unwrap().into()),
+                Err(tcp::SendError::InvalidState) => return Err(Errno::EINVAL.into()),
+            };
+
+            return Ok(num_sent.try_into().unwrap());
+        }
+
         // run in a closure so that an early return doesn't skip checking if we should block
         let result = (|| {
+            // like linux, a send while the connection is being established waits for the handshake
+            // to complete rather than failing
+            if socket_ref
+                .tcp_state
+                .poll()
+                .contains(tcp::PollState::CONNECTING)
+            {
+                return Err(Errno::EWOULDBLOCK);
+            }
+
             let reader = IoVecReader::new(args.iovs, mem);
 
             let rv = socket_ref.with_tcp_state(cb_queue, |state| state.send(reader, len));
@@ -438,7 +566,9 @@ impl TcpSocket {
         })();
 
         // if the syscall would block and we don't have the MSG_DONTWAIT flag
-        if result == Err(Errno::EWOULDBLOCK) && !flags.contains(MsgFlags::MSG_DONTWAIT) {
+        if result.as_ref().is_err_and(|e| e.is_wouldblock())
+            && !flags.contains(MsgFlags::MSG_DONTWAIT)
+        {
             return Err(SyscallError::new_blocked_on_file(
                 File::Socket(Socket::Inet(InetSocket::Tcp(socket.clone()))),
                 FileState::WRITABLE | FileState::CLOSED,
@@ -463,7 +593,7 @@ impl TcpSocket {
             // connect() result
             socket_ref.connect_result_is_pending = false;
 
-            return Err(tcp_error_to_errno(error).into());
+            return Err(tcp_error_to_errno(error, socket_ref.was_established).into());
         }
 
         let Some(mut flags) = MsgFlags::from_bits(args.flags) else {
@@ -506,11 +636,12 @@ impl TcpSocket {
                 addr: None,
                 msg_flags: MsgFlags::empty().bits(),
                 control_len: 0,
+                ancillary: AncillaryData::default(),
             })
         })();
 
         // if the syscall would block and we don't have the MSG_DONTWAIT flag
-        if result.as_ref().err() == Some(&Errno::EWOULDBLOCK)
+        if result.as_ref().is_err_and(|e| e.is_wouldblock())
             && !flags.contains(MsgFlags::MSG_DONTWAIT)
         {
             return Err(SyscallError::new_blocked_on_file(
@@ -551,6 +682,7 @@ impl TcpSocket {
         let backlog = backlog as u32;
 
         let is_associated = socket_ref.association.is_some();
+        let reuse_port = socket_ref.reuse_port;
 
         let rv = if is_associated {
             // if already associated, do nothing
@@ -572,6 +704,7 @@ impl TcpSocket {
                     local_addr,
                     peer_addr,
                     /* check_generic_peer= */ true,
+                    reuse_port,
                     net_ns,
                     rng,
                 )?;
@@ -596,43 +729,17 @@ impl TcpSocket {
         Ok(())
     }
 
-    pub fn connect(
+    /// Start connecting to `peer_addr`, implicitly binding the socket if it isn't already bound.
+    /// This doesn't block, and the caller is responsible for deciding when the result should be
+    /// returned.
+    fn start_connect(
         socket: &Arc<AtomicRefCell<Self>>,
+        socket_ref: &mut Self,
         peer_addr: &SockaddrStorage,
         net_ns: &NetworkNamespace,
         rng: impl rand::Rng,
         cb_queue: &mut CallbackQueue,
     ) -> Result<(), SyscallError> {
-        let socket_ref = &mut *socket.borrow_mut();
-
-        // if there was an asynchronous error, return it
-        if let Some(error) = socket_ref.with_tcp_state(cb_queue, |state| state.clear_error()) {
-            // by returning this error, we're probably (but not necessarily) returning a previous
-            // connect() result
-            socket_ref.connect_result_is_pending = false;
-
-            return Err(tcp_error_to_errno(error).into());
-        }
-
-        // if connect() had previously been called (either blocking or non-blocking), we need to
-        // return the result
-        if socket_ref.connect_result_is_pending {
-            // ignore all connect arguments and just check if we've connected
-
-            // check if it's still connecting (in the "syn-sent" or "syn-received" state)
-            if socket_ref
-                .tcp_state
-                .poll()
-                .contains(tcp::PollState::CONNECTING)
-            {
-                return Err(Errno::EALREADY.into());
-            }
-
-            // if not connecting and there were no socket errors (checked above)
-            socket_ref.connect_result_is_pending = false;
-            return Ok(());
-        }
-
         // if not an inet socket address
         let Some(peer_addr) = peer_addr.as_inet() else {
             return Err(Errno::EINVAL.into());
@@ -646,6 +753,18 @@ impl TcpSocket {
             peer_addr.set_ip(std::net::Ipv4Addr::LOCALHOST);
         }
 
+        // a socket bound to an interface can only connect to addresses routed through it
+        if let Some(device) = socket_ref.bound_device {
+            if inet::route_interface_name(*peer_addr.ip()) != device {
+                return Err(Errno::ENETUNREACH.into());
+            }
+        }
+
+        // a socket that bypasses the routing table can only connect to directly-connected networks
+        if socket_ref.dont_route && !inet::is_on_link(*peer_addr.ip(), net_ns.default_ip) {
+            return Err(Errno::ENETUNREACH.into());
+        }
+
         let local_addr = socket_ref.association.as_ref().map(|x| x.local_addr());
 
         let rv = if let Some(mut local_addr) = local_addr {
@@ -681,6 +800,7 @@ impl TcpSocket {
                     local_addr,
                     peer_addr,
                     /* check_generic_peer= */ true,
+                    /* reuse_port= */ false,
                     net_ns,
                     rng,
                 )?;
@@ -706,10 +826,114 @@ impl TcpSocket {
             socket_ref.association = Some(handle);
         }
 
+        Ok(())
+    }
+
+    /// If the peer is on this host and there's no socket that would accept the connection, the peer
+    /// would respond to our SYN with a RST. Instead of sending the SYN, we give the RST to the tcp
+    /// state directly so that the connection is refused. Returns `true` if the connection was
+    /// refused.
+    fn refuse_if_no_listener(
+        socket: &Arc<AtomicRefCell<Self>>,
+        socket_ref: &mut Self,
+        net_ns: &NetworkNamespace,
+        cb_queue: &mut CallbackQueue,
+    ) -> bool {
+        let Some((local_addr, peer_addr)) = socket_ref.tcp_state.local_remote_addrs() else {
+            return false;
+        };
+
+        if !inet::is_tcp_connection_refused(
+            &InetSocket::Tcp(Arc::clone(socket)),
+            local_addr,
+            peer_addr,
+            net_ns,
+        ) {
+            return false;
+        }
+
+        // we haven't received a SYN from the peer, so the tcp state will accept the RST without
+        // checking its sequence number
+        let header = tcp::TcpHeader {
+            ip: tcp::Ipv4Header {
+                src: *peer_addr.ip(),
+                dst: *local_addr.ip(),
+            },
+            flags: tcp::TcpFlags::RST,
+            src_port: peer_addr.port(),
+            dst_port: local_addr.port(),
+            seq: 0,
+            ack: 0,
+            window_size: 0,
+            selective_acks: None,
+            window_scale: None,
+            timestamp: None,
+            timestamp_echo: None,
+        };
+
+        socket_ref
+            .with_tcp_state(cb_queue, |state| {
+                state.push_packet(&header, tcp::Payload::default())
+            })
+            .unwrap();
+
+        true
+    }
+
+    pub fn connect(
+        socket: &Arc<AtomicRefCell<Self>>,
+        peer_addr: &SockaddrStorage,
+        net_ns: &NetworkNamespace,
+        rng: impl rand::Rng,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        let socket_ref = &mut *socket.borrow_mut();
+
+        // if there was an asynchronous error, return it
+        if let Some(error) = socket_ref.with_tcp_state(cb_queue, |state| state.clear_error()) {
+            // by returning this error, we're probably (but not necessarily) returning a previous
+            // connect() result
+            socket_ref.connect_result_is_pending = false;
+
+            return Err(tcp_error_to_errno(error, socket_ref.was_established).into());
+        }
+
+        // if connect() had previously been called (either blocking or non-blocking), we need to
+        // return the result
+        if socket_ref.connect_result_is_pending {
+            // ignore all connect arguments and just check if we've connected
+
+            // check if it's still connecting (in the "syn-sent" or "syn-received" state)
+            if socket_ref
+                .tcp_state
+                .poll()
+                .contains(tcp::PollState::CONNECTING)
+            {
+                return Err(Errno::EALREADY.into());
+            }
+
+            // if not connecting and there were no socket errors (checked above)
+            socket_ref.connect_result_is_pending = false;
+            return Ok(());
+        }
+
+        Self::start_connect(socket, socket_ref, peer_addr, net_ns, rng, cb_queue)?;
+
         // we're attempting to connect, so set a flag so that we know a future connect() call should
         // return the result
         socket_ref.connect_result_is_pending = true;
 
+        if Self::refuse_if_no_listener(socket, socket_ref, net_ns, cb_queue) {
+            log::trace!("Connection was refused");
+
+            // a non-blocking socket will report the error asynchronously
+            if !socket_ref.status.contains(FileStatus::NONBLOCK) {
+                socket_ref.with_tcp_state(cb_queue, |state| state.clear_error());
+                socket_ref.connect_result_is_pending = false;
+                return Err(Errno::ECONNREFUSED.into());
+            }
+        }
+
         if socket_ref.status.contains(FileStatus::NONBLOCK) {
             Err(Errno::EINPROGRESS.into())
         } else {
@@ -776,7 +1000,22 @@ impl TcpSocket {
                 // `with_tcp_state` below to update it, but we need ACTIVE set so that epoll works
                 file_state: FileState::ACTIVE,
                 association: None,
+                // linux copies the socket options of the listening socket
+                reuse_port: self.reuse_port,
+                send_buffer_size: self.send_buffer_size,
+                recv_buffer_size: self.recv_buffer_size,
+                defer_accept_secs: self.defer_accept_secs,
+                fast_open_queue_len: self.fast_open_queue_len,
+                user_timeout_ms: self.user_timeout_ms,
+                quick_ack: true,
+                recv_timeout: self.recv_timeout,
+                mark: self.mark,
+                busy_poll: self.busy_poll,
+                priority: self.priority,
+                bound_device: self.bound_device,
+                dont_route: self.dont_route,
                 connect_result_is_pending: false,
+                was_established: false,
                 shutdown_status: None,
                 has_open_file: false,
                 _counter: ObjectCounter::new("TcpSocket"),
@@ -797,6 +1036,7 @@ impl TcpSocket {
             local_addr,
             remote_addr,
             /* check_generic_peer= */ false,
+            /* reuse_port= */ false,
             net_ns,
             rng,
         )?;
@@ -861,6 +1101,10 @@ impl TcpSocket {
         // the shutdown was successful, so update our shutdown status
         self.shutdown_status = Some(how);
 
+        // the shutdown status affects the file state, so any blocked reads or writes may need to
+        // wake up
+        self.with_tcp_state(cb_queue, |_state| ());
+
         Ok(())
     }
 
@@ -878,7 +1122,10 @@ impl TcpSocket {
                 // may update the socket's state (for example, reading `SO_ERROR` will make `poll()`
                 // stop returning `POLLERR` for the socket)
                 let error = self.with_tcp_state(cb_queue, |state| state.clear_error());
-                let error = error.map(tcp_error_to_errno).map(Into::into).unwrap_or(0);
+                let error = error
+                    .map(|e| tcp_error_to_errno(e, self.was_established))
+                    .map(Into::into)
+                    .unwrap_or(0);
 
                 let optval_ptr = optval_ptr.cast::<libc::c_int>();
                 let bytes_written = write_partial(mem, &error, optval_ptr, optlen as usize)?;
@@ -918,6 +1165,30 @@ impl TcpSocket {
 
                 Ok(bytes_written as libc::socklen_t)
             }
+            (libc::SOL_SOCKET, libc::SO_REUSEPORT) => {
+                let reuse_port = self.reuse_port as libc::c_int;
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written = write_partial(mem, &reuse_port, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_SNDBUF) => {
+                let sndbuf_size: libc::c_int = self.send_buffer_size.try_into().unwrap();
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written = write_partial(mem, &sndbuf_size, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_RCVBUF) => {
+                let rcvbuf_size: libc::c_int = self.recv_buffer_size.try_into().unwrap();
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written = write_partial(mem, &rcvbuf_size, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
             (libc::SOL_SOCKET, libc::SO_BROADCAST) => {
                 let optval_ptr = optval_ptr.cast::<libc::c_int>();
                 // we don't support broadcast sockets, so just just return the default 0
@@ -925,6 +1196,82 @@ impl TcpSocket {
 
                 Ok(bytes_written as libc::socklen_t)
             }
+            (libc::SOL_SOCKET, libc::SO_RCVTIMEO) => {
+                write_timeout(self.recv_timeout, optval_ptr, optlen, mem)
+            }
+            (libc::SOL_SOCKET, libc::SO_MARK) => {
+                let optval_ptr = optval_ptr.cast::<u32>();
+                let bytes_written = write_partial(mem, &self.mark, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_BUSY_POLL) => {
+                let optval_ptr = optval_ptr.cast::<u32>();
+                let bytes_written =
+                    write_partial(mem, &self.busy_poll, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_INCOMING_CPU) => {
+                // shadow doesn't simulate cpus, so always return -1 like linux does for a socket
+                // that hasn't received any packets
+                let cpu: libc::c_int = -1;
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written = write_partial(mem, &cpu, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_BINDTODEVICE) => {
+                inet::write_bind_to_device(self.bound_device, optval_ptr, optlen, mem)
+            }
+            (libc::SOL_SOCKET, libc::SO_PRIORITY) => {
+                let optval_ptr = optval_ptr.cast::<u32>();
+                let bytes_written =
+                    write_partial(mem, &self.priority, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_DONTROUTE) => {
+                let enabled = libc::c_int::from(self.dont_route);
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written = write_partial(mem, &enabled, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_TCP, libc::TCP_QUICKACK) => {
+                let val = libc::c_int::from(self.quick_ack);
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written = write_partial(mem, &val, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_TCP, libc::TCP_DEFER_ACCEPT) => {
+                let secs: libc::c_int = self.defer_accept_secs.try_into().unwrap();
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written = write_partial(mem, &secs, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_TCP, libc::TCP_FASTOPEN) => {
+                let qlen: libc::c_int = self.fast_open_queue_len.try_into().unwrap();
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written = write_partial(mem, &qlen, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_TCP, libc::TCP_USER_TIMEOUT) => {
+                let ms: libc::c_uint = self.user_timeout_ms;
+
+                let optval_ptr = optval_ptr.cast::<libc::c_uint>();
+                let bytes_written = write_partial(mem, &ms, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
             _ => {
                 log_once_per_value_at_level!(
                     (level, optname),
@@ -945,6 +1292,7 @@ impl TcpSocket {
         optval_ptr: ForeignPtr<()>,
         optlen: libc::socklen_t,
         mem: &MemoryManager,
+        cb_queue: &mut CallbackQueue,
     ) -> Result<(), SyscallError> {
         match (level, optname) {
             (libc::SOL_SOCKET, libc::SO_REUSEADDR) => {
@@ -952,8 +1300,89 @@ impl TcpSocket {
                 log::trace!("setsockopt SO_REUSEADDR not yet implemented");
             }
             (libc::SOL_SOCKET, libc::SO_REUSEPORT) => {
-                // TODO: implement this, tgen uses it
-                log::trace!("setsockopt SO_REUSEPORT not yet implemented");
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val = mem.read(optval_ptr)?;
+
+                self.reuse_port = val != 0;
+            }
+            (libc::SOL_SOCKET, libc::SO_SNDBUF) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val: u32 = mem.read(optval_ptr)?.try_into().or(Err(Errno::EINVAL))?;
+
+                // linux kernel doubles this value upon setting, and we use the same limits as the
+                // legacy tcp socket
+                let val = val.saturating_mul(2).clamp(4096, 268435456); // 2^28 = 256 MiB
+
+                self.send_buffer_size = val;
+                self.with_tcp_state(cb_queue, |state| {
+                    state.with_config(|config| config.send_buffer_size(val))
+                });
+            }
+            (libc::SOL_SOCKET, libc::SO_RCVBUF) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val: u32 = mem.read(optval_ptr)?.try_into().or(Err(Errno::EINVAL))?;
+
+                // linux kernel doubles this value upon setting, and we use the same limits as the
+                // legacy tcp socket
+                let val = val.saturating_mul(2).clamp(2048, 268435456); // 2^28 = 256 MiB
+
+                // a receive buffer larger than 64 KiB will use window scaling if it's set before
+                // the SYN is sent (before `connect()` or `listen()`), but the window scale won't
+                // change for an existing connection
+                self.recv_buffer_size = val;
+                self.with_tcp_state(cb_queue, |state| {
+                    state.with_config(|config| config.recv_buffer_size(val))
+                });
+            }
+            (libc::SOL_SOCKET, libc::SO_RCVTIMEO) => {
+                self.recv_timeout = read_timeout(optval_ptr, optlen, mem)?;
+            }
+            (libc::SOL_SOCKET, libc::SO_MARK) => {
+                type OptType = u32;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                self.mark = mem.read(optval_ptr)?;
+            }
+            (libc::SOL_SOCKET, libc::SO_BUSY_POLL) => {
+                self.busy_poll = inet::read_busy_poll(optval_ptr, optlen, mem)?;
+            }
+            (libc::SOL_SOCKET, libc::SO_BINDTODEVICE) => {
+                self.bound_device = inet::read_bind_to_device(optval_ptr, optlen, mem)?;
+            }
+            (libc::SOL_SOCKET, libc::SO_PRIORITY) => {
+                self.priority = inet::read_priority(optval_ptr, optlen, mem)?;
+            }
+            (libc::SOL_SOCKET, libc::SO_DONTROUTE) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                self.dont_route = mem.read(optval_ptr)? != 0;
             }
             (libc::SOL_SOCKET, libc::SO_KEEPALIVE) => {
                 // TODO: implement this, libevent uses it in evconnlistener_new_bind()
@@ -978,6 +1407,72 @@ impl TcpSocket {
                     );
                 }
             }
+            (libc::SOL_TCP, libc::TCP_QUICKACK) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val = mem.read(optval_ptr)?;
+
+                // this tcp implementation never delays ACKs, so we only need to remember the value
+                self.quick_ack = val != 0;
+            }
+            (libc::SOL_TCP, libc::TCP_DEFER_ACCEPT) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val = mem.read(optval_ptr)?;
+
+                // only applies to connections that arrive in the future
+                let val = round_defer_accept_secs(val);
+                self.defer_accept_secs = val;
+                self.with_tcp_state(cb_queue, |state| {
+                    state.with_config(|config| config.defer_accept(val))
+                });
+            }
+            (libc::SOL_TCP, libc::TCP_FASTOPEN) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val = mem.read(optval_ptr)?;
+
+                let Ok(val) = u32::try_from(val) else {
+                    return Err(Errno::EINVAL.into());
+                };
+
+                // the listener always accepts data in a SYN, so the queue length is only reported
+                self.fast_open_queue_len = val;
+            }
+            (libc::SOL_TCP, libc::TCP_USER_TIMEOUT) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val = mem.read(optval_ptr)?;
+
+                let Ok(val) = u32::try_from(val) else {
+                    return Err(Errno::EINVAL.into());
+                };
+
+                self.user_timeout_ms = val;
+                self.with_tcp_state(cb_queue, |state| {
+                    state.with_config(|config| config.user_timeout(val))
+                });
+            }
             _ => {
                 log_once_per_value_at_level!(
                     (level, optname),
@@ -1053,10 +1548,42 @@ impl TcpSocket {
     }
 }
 
-fn tcp_error_to_errno(error: tcp::TcpError) -> Errno {
+/// Linux converts the `TCP_DEFER_ACCEPT` timeout to a number of SYN-ACK retransmissions, so the
+/// timeout is rounded up to the total time of those retransmissions. See `secs_to_retrans()` and
+/// `retrans_to_secs()` in linux's "net/ipv4/tcp.c".
+fn round_defer_accept_secs(secs: libc::c_int) -> u32 {
+    // the initial and maximum SYN-ACK retransmission timeouts in seconds
+    const TIMEOUT_INIT: u32 = 1;
+    const RTO_MAX: u32 = 120;
+    // the retransmission count is stored as a `u8`
+    const MAX_RETRANS: u32 = u8::MAX as u32;
+
+    let Ok(secs) = u32::try_from(secs) else {
+        return 0;
+    };
+
+    if secs == 0 {
+        return 0;
+    }
+
+    let mut timeout = TIMEOUT_INIT;
+    let mut period = timeout;
+    let mut retrans = 1;
+
+    while secs > period && retrans < MAX_RETRANS {
+        retrans += 1;
+        timeout = std::cmp::min(timeout * 2, RTO_MAX);
+        period += timeout;
+    }
+
+    period
+}
+
+fn tcp_error_to_errno(error: tcp::TcpError, was_established: bool) -> Errno {
     match error {
         tcp::TcpError::ResetSent => Errno::ECONNRESET,
-        // TODO: when should this be ECONNREFUSED vs ECONNRESET? maybe we need more context?
+        tcp::TcpError::ResetReceived if was_established => Errno::ECONNRESET,
+        // the peer responded to our SYN with a RST
         tcp::TcpError::ResetReceived => Errno::ECONNREFUSED,
         tcp::TcpError::ClosedWhileConnecting => Errno::ECONNRESET,
         tcp::TcpError::TimedOut => Errno::ETIMEDOUT,
@@ -10,13 +10,17 @@ use linux_api::ioctls::IoctlRequest;
 use linux_api::socket::Shutdown;
 use nix::sys::socket::{MsgFlags, SockaddrIn};
 use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 
 use crate::core::worker::Worker;
 use crate::cshadow as c;
 use crate::host::descriptor::listener::{StateEventSource, StateListenHandle, StateListenerFilter};
 use crate::host::descriptor::socket::inet::{self, InetSocket};
-use crate::host::descriptor::socket::{RecvmsgArgs, RecvmsgReturn, SendmsgArgs, ShutdownFlags};
+use crate::host::descriptor::socket::{
+    AncillaryData, RecvTimestamp, RecvmsgArgs, RecvmsgReturn, SendmsgArgs, ShutdownFlags,
+    read_timeout, send_flags, write_timeout,
+};
 use crate::host::descriptor::{
     File, FileMode, FileSignals, FileState, FileStatus, OpenFile, Socket, SyscallResult,
 };
@@ -25,7 +29,7 @@ use crate::host::network::interface::FifoPacketPriority;
 use crate::host::network::namespace::{AssociationHandle, NetworkNamespace};
 use crate::host::syscall::io::{IoVec, IoVecReader, IoVecWriter, write_partial};
 use crate::host::syscall::types::SyscallError;
-use crate::network::packet::{PacketRc, PacketStatus};
+use crate::network::packet::{Packet, PacketRc, PacketStatus};
 use crate::utility::callback_queue::CallbackQueue;
 use crate::utility::sockaddr::SockaddrStorage;
 use crate::utility::{HostTreePointer, ObjectCounter};
@@ -47,6 +51,26 @@ pub struct UdpSocket {
     /// The receive time of the last packet returned to the managed process during a call to
     /// `recvmsg()`. Used for `SIOCGSTAMP`.
     recv_time_of_last_read_packet: Option<EmulatedTime>,
+    /// The format of the receive timestamps returned by `recvmsg()`, if enabled by `SO_TIMESTAMP`
+    /// or `SO_TIMESTAMPNS`.
+    recv_timestamp_format: Option<TimestampFormat>,
+    /// Should `recvmsg()` return the receiving interface and address (`IP_PKTINFO`)?
+    recv_pktinfo: bool,
+    /// The mark set by `SO_MARK`, which is given to the packets we send. Shadow doesn't support
+    /// policy routing, so it isn't used for routing decisions.
+    mark: u32,
+    /// The busy polling timeout set by `SO_BUSY_POLL`. Shadow doesn't busy poll, so it's only
+    /// returned by `getsockopt()`.
+    busy_poll: u32,
+    /// The timeout set by `SO_RCVTIMEO` for blocking receives, if any.
+    recv_timeout: Option<SimulationTime>,
+    /// The network interface set by `SO_BINDTODEVICE`. Packets are only sent and received on this
+    /// interface.
+    bound_device: Option<&'static str>,
+    /// Was `SO_DONTROUTE` enabled? Packets can then only be sent to directly-connected networks.
+    dont_route: bool,
+    /// The priority set by `SO_PRIORITY`, which is given to the packets we send.
+    priority: u32,
     // should only be used by `OpenFile` to make sure there is only ever one `OpenFile` instance for
     // this file
     has_open_file: bool,
@@ -70,6 +94,14 @@ impl UdpSocket {
             bound_addr: None,
             association: None,
             recv_time_of_last_read_packet: None,
+            recv_timestamp_format: None,
+            recv_pktinfo: false,
+            mark: 0,
+            busy_poll: 0,
+            recv_timeout: None,
+            bound_device: None,
+            dont_route: false,
+            priority: 0,
             has_open_file: false,
             _counter: ObjectCounter::new("UdpSocket"),
         };
@@ -136,6 +168,14 @@ impl UdpSocket {
 
         // TODO: also check the dst address to make sure we are the intended socket?
 
+        // a socket bound to an interface only receives packets that arrived on that interface
+        if let Some(device) = self.bound_device {
+            if inet::route_interface_name(*packet.dst_ipv4_address().ip()) != device {
+                packet.add_status(PacketStatus::RcvSocketDropped);
+                return;
+            }
+        }
+
         // don't bother copying the bytes if we know the push will fail
         if !self.recv_buffer.has_space() {
             packet.add_status(PacketStatus::RcvSocketDropped);
@@ -179,8 +219,11 @@ impl UdpSocket {
         log::trace!("Removed a message from the UDP socket's send buffer");
 
         // We transfer the `Bytes` directly from the buffer to the packet without copying them.
-        let packet =
-            PacketRc::new_ipv4_udp(header.src, header.dst, message, header.packet_priority);
+        let packet = Packet::new_ipv4_udp(header.src, header.dst, message, header.packet_priority);
+        let packet = packet
+            .with_socket_priority(header.socket_priority)
+            .with_mark(header.mark);
+        let packet = PacketRc::from(packet);
         packet.add_status(PacketStatus::SndCreated);
 
         self.refresh_readable_writable(FileSignals::empty(), cb_queue);
@@ -220,6 +263,10 @@ impl UdpSocket {
         linux_api::socket::AddressFamily::AF_INET
     }
 
+    pub fn recv_timeout(&self) -> Option<SimulationTime> {
+        self.recv_timeout
+    }
+
     pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError> {
         // drop the existing association handle to disassociate the socket
         self.association = None;
@@ -276,6 +323,7 @@ impl UdpSocket {
             addr,
             unspecified_addr,
             /* check_generic_peer= */ true,
+            /* reuse_port= */ false,
             net_ns,
             rng,
         )?;
@@ -333,10 +381,17 @@ impl UdpSocket {
             return Err(linux_api::errno::Errno::EPIPE.into());
         }
 
-        let Some(mut flags) = MsgFlags::from_bits(args.flags) else {
-            log::debug!("Unrecognized send flags: {:#b}", args.flags);
-            return Err(Errno::EINVAL.into());
-        };
+        // `MSG_CONFIRM` only affects the neighbour cache, which we don't have, so is ignored
+        let mut flags = send_flags(args.flags);
+
+        // udp sockets don't have out-of-band data
+        if flags.contains(MsgFlags::MSG_OOB) {
+            return Err(Errno::EOPNOTSUPP.into());
+        }
+
+        if args.flags & libc::MSG_MORE != 0 {
+            warn_once_then_debug!("MSG_MORE is not supported for UDP sockets; sending immediately");
+        }
 
         // TODO: If we have a peer AND a destination address is provided, should we use the peer or
         // the destination address? Do we have a test for this?
@@ -358,6 +413,20 @@ impl UdpSocket {
             flags.insert(MsgFlags::MSG_DONTWAIT);
         }
 
+        // a socket bound to an interface can only send packets that are routed through it
+        if let Some(device) = socket_ref.bound_device {
+            if inet::route_interface_name(*dst_addr.ip()) != device {
+                return Err(Errno::ENETUNREACH.into());
+            }
+        }
+
+        // a socket that bypasses the routing table can only send to directly-connected networks, and
+        // `MSG_DONTROUTE` bypasses it for this send only
+        let dont_route = socket_ref.dont_route || args.flags & libc::MSG_DONTROUTE != 0;
+        if dont_route && !inet::is_on_link(*dst_addr.ip(), net_ns.default_ip) {
+            return Err(Errno::ENETUNREACH.into());
+        }
+
         let len: libc::size_t = args.iovs.iter().map(|x| x.len).sum();
 
         // TODO: should use IP fragmentation to make sure packets fit within the MTU
@@ -402,6 +471,7 @@ impl UdpSocket {
                 local_addr,
                 unspecified_addr,
                 /* check_generic_peer= */ true,
+                /* reuse_port= */ false,
                 net_ns,
                 rng,
             )?;
@@ -445,6 +515,8 @@ impl UdpSocket {
                 src: src_addr,
                 dst: dst_addr,
                 packet_priority,
+                socket_priority: socket_ref.priority,
+                mark: socket_ref.mark,
             };
 
             // push the message to the send buffer (shouldn't fail since we checked for available
@@ -471,7 +543,9 @@ impl UdpSocket {
         socket_ref.refresh_readable_writable(FileSignals::empty(), cb_queue);
 
         // if the syscall would block and we don't have the MSG_DONTWAIT flag
-        if result == Err(Errno::EWOULDBLOCK) && !flags.contains(MsgFlags::MSG_DONTWAIT) {
+        if result.as_ref().is_err_and(|e| e.is_wouldblock())
+            && !flags.contains(MsgFlags::MSG_DONTWAIT)
+        {
             return Err(SyscallError::new_blocked_on_file(
                 File::Socket(Socket::Inet(InetSocket::Udp(socket.clone()))),
                 FileState::WRITABLE,
@@ -542,21 +616,41 @@ impl UdpSocket {
             let mut return_flags = MsgFlags::empty();
             return_flags.set(MsgFlags::MSG_TRUNC, truncated_message.len() < message.len());
 
-            // update the cache of the last recv time
-            socket_ref.recv_time_of_last_read_packet = Some(header.recv_time);
+            let recv_time = header.recv_time - EmulatedTime::UNIX_EPOCH;
+            let timestamp = match socket_ref.recv_timestamp_format {
+                Some(TimestampFormat::Timeval) => {
+                    Some(RecvTimestamp::Timeval(recv_time.try_into().unwrap()))
+                }
+                Some(TimestampFormat::Timespec) => {
+                    Some(RecvTimestamp::Timespec(recv_time.try_into().unwrap()))
+                }
+                None => {
+                    // update the cache of the last recv time; like linux, this isn't updated for
+                    // packets that were timestamped
+                    socket_ref.recv_time_of_last_read_packet = Some(header.recv_time);
+                    None
+                }
+            };
+
+            let pktinfo = socket_ref.recv_pktinfo.then(|| pktinfo(header.dst));
 
             Ok(RecvmsgReturn {
                 return_val: return_val.try_into().unwrap(),
                 addr: Some(header.src.into()),
                 msg_flags: return_flags.bits(),
                 control_len: 0,
+                ancillary: AncillaryData {
+                    timestamp,
+                    pktinfo,
+                    ..Default::default()
+                },
             })
         })();
 
         socket_ref.refresh_readable_writable(FileSignals::empty(), cb_queue);
 
         // if the syscall would block and we don't have the MSG_DONTWAIT flag
-        if result.as_ref().err() == Some(&Errno::EWOULDBLOCK)
+        if result.as_ref().is_err_and(|e| e.is_wouldblock())
             && !flags.contains(MsgFlags::MSG_DONTWAIT)
         {
             // if the syscall would block but the file's reading has been shut down, return EOF
@@ -566,6 +660,7 @@ impl UdpSocket {
                     addr: None,
                     msg_flags: 0,
                     control_len: 0,
+                    ancillary: AncillaryData::default(),
                 });
             }
 
@@ -610,7 +705,7 @@ impl UdpSocket {
 
                 Ok(0.into())
             }
-            IoctlRequest::SIOCGSTAMP => {
+            IoctlRequest::SIOCGSTAMP | IoctlRequest::SIOCGSTAMPNS => {
                 // socket(7): "Return a struct timeval with the receive timestamp of the last packet
                 // passed to the user. [...] This ioctl should only be used if the socket option
                 // SO_TIMESTAMP is not set on the socket. Otherwise, it returns the timestamp of the
@@ -621,12 +716,16 @@ impl UdpSocket {
                     return Err(Errno::ENOENT.into());
                 };
 
-                let last_recv_time = (last_recv_time - EmulatedTime::UNIX_EPOCH)
-                    .try_into()
-                    .unwrap();
+                let last_recv_time = last_recv_time - EmulatedTime::UNIX_EPOCH;
 
-                let arg_ptr = arg_ptr.cast::<libc::timeval>();
-                mem.write(arg_ptr, &last_recv_time)?;
+                // SIOCGSTAMPNS is the same as SIOCGSTAMP, but returns a struct timespec
+                if request == IoctlRequest::SIOCGSTAMP {
+                    let last_recv_time: libc::timeval = last_recv_time.try_into().unwrap();
+                    mem.write(arg_ptr.cast::<libc::timeval>(), &last_recv_time)?;
+                } else {
+                    let last_recv_time: libc::timespec = last_recv_time.try_into().unwrap();
+                    mem.write(arg_ptr.cast::<libc::timespec>(), &last_recv_time)?;
+                }
 
                 Ok(0.into())
             }
@@ -754,6 +853,7 @@ impl UdpSocket {
                     local_addr,
                     unspecified_addr,
                     /* check_generic_peer= */ true,
+                    /* reuse_port= */ false,
                     net_ns,
                     rng,
                 )?;
@@ -871,6 +971,71 @@ impl UdpSocket {
 
                 Ok(bytes_written as libc::socklen_t)
             }
+            (libc::SOL_SOCKET, libc::SO_TIMESTAMP | libc::SO_TIMESTAMPNS) => {
+                let format = match optname {
+                    libc::SO_TIMESTAMP => TimestampFormat::Timeval,
+                    libc::SO_TIMESTAMPNS => TimestampFormat::Timespec,
+                    _ => unreachable!(),
+                };
+                let enabled = libc::c_int::from(self.recv_timestamp_format == Some(format));
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written = write_partial(mem, &enabled, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::IPPROTO_IP, libc::IP_PKTINFO) => {
+                let enabled = libc::c_int::from(self.recv_pktinfo);
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written = write_partial(mem, &enabled, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_MARK) => {
+                let optval_ptr = optval_ptr.cast::<u32>();
+                let bytes_written = write_partial(mem, &self.mark, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_BUSY_POLL) => {
+                let optval_ptr = optval_ptr.cast::<u32>();
+                let bytes_written =
+                    write_partial(mem, &self.busy_poll, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_INCOMING_CPU) => {
+                // shadow doesn't simulate cpus, so always return -1 like linux does for a socket
+                // that hasn't received any packets
+                let cpu: libc::c_int = -1;
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written = write_partial(mem, &cpu, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_RCVTIMEO) => {
+                write_timeout(self.recv_timeout, optval_ptr, optlen, mem)
+            }
+            (libc::SOL_SOCKET, libc::SO_BINDTODEVICE) => {
+                inet::write_bind_to_device(self.bound_device, optval_ptr, optlen, mem)
+            }
+            (libc::SOL_SOCKET, libc::SO_PRIORITY) => {
+                let optval_ptr = optval_ptr.cast::<u32>();
+                let bytes_written =
+                    write_partial(mem, &self.priority, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_DONTROUTE) => {
+                let enabled = libc::c_int::from(self.dont_route);
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written = write_partial(mem, &enabled, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
             (libc::SOL_SOCKET, _) => {
                 log_once_per_value_at_level!(
                     (level, optname),
@@ -901,6 +1066,7 @@ impl UdpSocket {
         optval_ptr: ForeignPtr<()>,
         optlen: libc::socklen_t,
         mem: &MemoryManager,
+        _cb_queue: &mut CallbackQueue,
     ) -> Result<(), SyscallError> {
         match (level, optname) {
             (libc::SOL_SOCKET, libc::SO_SNDBUF) => {
@@ -989,6 +1155,67 @@ impl UdpSocket {
                     );
                 }
             }
+            (libc::SOL_SOCKET, libc::SO_TIMESTAMP | libc::SO_TIMESTAMPNS) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val = mem.read(optval_ptr)?;
+
+                // like linux, enabling one format replaces the other, and disabling either format
+                // disables timestamps
+                self.recv_timestamp_format = match (val, optname) {
+                    (0, _) => None,
+                    (_, libc::SO_TIMESTAMP) => Some(TimestampFormat::Timeval),
+                    (_, libc::SO_TIMESTAMPNS) => Some(TimestampFormat::Timespec),
+                    _ => unreachable!(),
+                };
+            }
+            (libc::IPPROTO_IP, libc::IP_PKTINFO) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                self.recv_pktinfo = mem.read(optval_ptr)? != 0;
+            }
+            (libc::SOL_SOCKET, libc::SO_MARK) => {
+                type OptType = u32;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                self.mark = mem.read(optval_ptr)?;
+            }
+            (libc::SOL_SOCKET, libc::SO_BUSY_POLL) => {
+                self.busy_poll = inet::read_busy_poll(optval_ptr, optlen, mem)?;
+            }
+            (libc::SOL_SOCKET, libc::SO_RCVTIMEO) => {
+                self.recv_timeout = read_timeout(optval_ptr, optlen, mem)?;
+            }
+            (libc::SOL_SOCKET, libc::SO_BINDTODEVICE) => {
+                self.bound_device = inet::read_bind_to_device(optval_ptr, optlen, mem)?;
+            }
+            (libc::SOL_SOCKET, libc::SO_DONTROUTE) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                self.dont_route = mem.read(optval_ptr)? != 0;
+            }
+            (libc::SOL_SOCKET, libc::SO_PRIORITY) => {
+                self.priority = inet::read_priority(optval_ptr, optlen, mem)?;
+            }
             _ => {
                 log_once_per_value_at_level!(
                     (level, optname),
@@ -1079,6 +1306,32 @@ impl UdpSocket {
     }
 }
 
+/// Returns the `IP_PKTINFO` for a message received with destination address `dst`.
+fn pktinfo(dst: SocketAddrV4) -> libc::in_pktinfo {
+    // the interface indexes are the same as those returned by netlink and `getifaddrs()`
+    let ifindex = if dst.ip().is_loopback() { 1 } else { 2 };
+    let dst = libc::in_addr {
+        s_addr: u32::from(*dst.ip()).to_be(),
+    };
+
+    libc::in_pktinfo {
+        ipi_ifindex: ifindex,
+        // we don't support routing, so this is always the local address that the message was
+        // sent to
+        ipi_spec_dst: dst,
+        ipi_addr: dst,
+    }
+}
+
+/// The format of receive timestamps.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum TimestampFormat {
+    /// `SO_TIMESTAMP`
+    Timeval,
+    /// `SO_TIMESTAMPNS`
+    Timespec,
+}
+
 /// Non-payload data for a message in the send buffer.
 #[derive(Debug)]
 struct MessageSendHeader {
@@ -1089,6 +1342,10 @@ struct MessageSendHeader {
     dst: SocketAddrV4,
     /// The priority for the packet that we'll create in the future, given to us by the host.
     packet_priority: FifoPacketPriority,
+    /// The socket's `SO_PRIORITY` when the message was sent.
+    socket_priority: u32,
+    /// The socket's `SO_MARK` when the message was sent.
+    mark: u32,
 }
 
 /// Non-payload data for a message in the receive buffer.
@@ -1096,9 +1353,8 @@ struct MessageSendHeader {
 struct MessageRecvHeader {
     /// The source address (for example the peer).
     src: SocketAddrV4,
-    /// The destination address (typically the bind address). The application can theoretically use
-    /// `IP_PKTINFO` to get the packet destination address.
-    #[allow(dead_code)]
+    /// The destination address (typically the bind address). The application can use `IP_PKTINFO`
+    /// to get the packet destination address.
     dst: SocketAddrV4,
     /// The time when the network interface received the message.
     recv_time: EmulatedTime,
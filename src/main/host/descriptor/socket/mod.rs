@@ -6,17 +6,19 @@ use linux_api::errno::Errno;
 use linux_api::ioctls::IoctlRequest;
 use linux_api::socket::Shutdown;
 use netlink::NetlinkSocket;
+use nix::sys::socket::MsgFlags;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
-use unix::UnixSocket;
+use unix::{UnixSocket, UnixSocketType};
 
 use crate::cshadow as c;
 use crate::host::descriptor::listener::{StateListenHandle, StateListenerFilter};
 use crate::host::descriptor::{
-    FileMode, FileSignals, FileState, FileStatus, OpenFile, SyscallResult,
+    CompatFile, FileMode, FileSignals, FileState, FileStatus, OpenFile, SyscallResult,
 };
 use crate::host::memory_manager::MemoryManager;
 use crate::host::network::namespace::NetworkNamespace;
-use crate::host::syscall::io::IoVec;
+use crate::host::syscall::io::{IoVec, write_partial};
 use crate::host::syscall::types::{ForeignArrayPtr, SyscallError};
 use crate::utility::HostTreePointer;
 use crate::utility::callback_queue::CallbackQueue;
@@ -85,6 +87,16 @@ impl Socket {
         }
     }
 
+    /// Returns true if the socket is connectionless (ex: `SOCK_DGRAM`), and therefore can't listen
+    /// for or accept connections.
+    pub fn is_connectionless(&self) -> bool {
+        match self {
+            Self::Unix(socket) => socket.borrow().socket_type() == UnixSocketType::Dgram,
+            Self::Inet(socket) => matches!(socket, InetSocket::Udp(_) | InetSocket::Icmp(_)),
+            Self::Netlink(_) => true,
+        }
+    }
+
     pub fn bind(
         &self,
         addr: Option<&SockaddrStorage>,
@@ -237,6 +249,10 @@ impl SocketRef<'_> {
     enum_passthrough!(self, (), Unix, Inet, Netlink;
         pub fn address_family(&self) -> linux_api::socket::AddressFamily
     );
+
+    enum_passthrough!(self, (), Unix, Inet, Netlink;
+        pub fn recv_timeout(&self) -> Option<SimulationTime>
+    );
 }
 
 // file functions
@@ -324,9 +340,9 @@ impl SocketRefMut<'_> {
         -> Result<libc::socklen_t, SyscallError>
     );
 
-    enum_passthrough!(self, (level, optname, optval_ptr, optlen, memory_manager), Unix, Inet, Netlink;
+    enum_passthrough!(self, (level, optname, optval_ptr, optlen, memory_manager, cb_queue), Unix, Inet, Netlink;
         pub fn setsockopt(&mut self, level: libc::c_int, optname: libc::c_int, optval_ptr: ForeignPtr<()>,
-                          optlen: libc::socklen_t, memory_manager: &MemoryManager)
+                          optlen: libc::socklen_t, memory_manager: &MemoryManager, cb_queue: &mut CallbackQueue)
         -> Result<(), SyscallError>
     );
 
@@ -390,10 +406,32 @@ pub struct SendmsgArgs<'a> {
     pub iovs: &'a [IoVec],
     /// Buffer in plugin memory containg message control data.
     pub control_ptr: ForeignArrayPtr<u8>,
+    /// Ancillary data to pass to the receiver, as read from the control messages.
+    pub ancillary: AncillaryData,
     /// Send flags.
     pub flags: libc::c_int,
 }
 
+/// Send flags that sockets check for in the raw [`SendmsgArgs::flags`], since nix's [`MsgFlags`]
+/// doesn't support them.
+const RAW_SEND_FLAGS: libc::c_int =
+    libc::MSG_FASTOPEN | libc::MSG_DONTROUTE | libc::MSG_MORE | libc::MSG_CONFIRM;
+
+/// Convert the flags of a send syscall to [`MsgFlags`]. Linux ignores send flags that it doesn't
+/// recognize or that don't apply to the socket rather than returning an error, so any flags that
+/// [`MsgFlags`] doesn't support are dropped. Sockets that handle one of the flags in
+/// [`RAW_SEND_FLAGS`] must check for it in the raw flags.
+pub fn send_flags(flags: libc::c_int) -> MsgFlags {
+    let msg_flags = MsgFlags::from_bits_truncate(flags);
+
+    let unrecognized = flags & !msg_flags.bits() & !RAW_SEND_FLAGS;
+    if unrecognized != 0 {
+        warn_once_then_debug!("Ignoring unrecognized send flags: {unrecognized:#b}");
+    }
+
+    msg_flags
+}
+
 /// Arguments for [`Socket::recvmsg()`].
 pub struct RecvmsgArgs<'a> {
     /// [`IoVec`] buffers in plugin memory to store the message data.
@@ -415,4 +453,88 @@ pub struct RecvmsgReturn {
     pub msg_flags: libc::c_int,
     /// The number of control data bytes read.
     pub control_len: libc::size_t,
+    /// Ancillary data received with the message. The caller is responsible for writing the control
+    /// messages.
+    pub ancillary: AncillaryData,
+}
+
+/// Ancillary data sent or received with a message. The syscall handler converts it to and from the
+/// plugin's control messages.
+#[derive(Default)]
+pub struct AncillaryData {
+    /// Open files passed in `SCM_RIGHTS` control messages.
+    pub rights: Vec<CompatFile>,
+    /// Process credentials passed in an `SCM_CREDENTIALS` control message.
+    pub creds: Option<libc::ucred>,
+    /// The time that the message was received, if receive timestamps are enabled.
+    pub timestamp: Option<RecvTimestamp>,
+    /// The receiving interface and address, if enabled by `IP_PKTINFO`.
+    pub pktinfo: Option<libc::in_pktinfo>,
+}
+
+impl AncillaryData {
+    pub fn is_empty(&self) -> bool {
+        self.rights.is_empty()
+            && self.creds.is_none()
+            && self.timestamp.is_none()
+            && self.pktinfo.is_none()
+    }
+}
+
+/// A receive timestamp, as enabled by `SO_TIMESTAMP` or `SO_TIMESTAMPNS`.
+#[derive(Copy, Clone)]
+pub enum RecvTimestamp {
+    /// Passed in an `SCM_TIMESTAMP` control message.
+    Timeval(libc::timeval),
+    /// Passed in an `SCM_TIMESTAMPNS` control message.
+    Timespec(libc::timespec),
+}
+
+/// Read the timeout given to `setsockopt(SO_RCVTIMEO)`. Like linux, a zero timeout disables the
+/// timeout and is returned as `None`, and a negative timeout is treated as an immediate timeout.
+fn read_timeout(
+    optval_ptr: ForeignPtr<()>,
+    optlen: libc::socklen_t,
+    mem: &MemoryManager,
+) -> Result<Option<SimulationTime>, SyscallError> {
+    type OptType = linux_api::time::kernel_old_timeval;
+
+    if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+        return Err(Errno::EINVAL.into());
+    }
+
+    let optval_ptr = optval_ptr.cast::<OptType>();
+    let timeout = mem.read(optval_ptr)?;
+
+    if !(0..1_000_000).contains(&timeout.tv_usec) {
+        return Err(Errno::EDOM.into());
+    }
+
+    if timeout.tv_sec < 0 {
+        return Ok(Some(SimulationTime::ZERO));
+    }
+
+    if timeout.tv_sec == 0 && timeout.tv_usec == 0 {
+        return Ok(None);
+    }
+
+    // a timeout too large for the simulation will never be reached
+    Ok(SimulationTime::try_from(timeout).ok())
+}
+
+/// Write a timeout for `getsockopt(SO_RCVTIMEO)`. A socket without a timeout has a zero timeout.
+/// Returns the number of bytes written.
+fn write_timeout(
+    timeout: Option<SimulationTime>,
+    optval_ptr: ForeignPtr<()>,
+    optlen: libc::socklen_t,
+    mem: &mut MemoryManager,
+) -> Result<libc::socklen_t, SyscallError> {
+    let timeout = timeout.unwrap_or(SimulationTime::ZERO);
+    let timeout = linux_api::time::kernel_old_timeval::try_from(timeout).unwrap();
+
+    let optval_ptr = optval_ptr.cast::<linux_api::time::kernel_old_timeval>();
+    let bytes_written = write_partial(mem, &timeout, optval_ptr, optlen as usize)?;
+
+    Ok(bytes_written as libc::socklen_t)
 }
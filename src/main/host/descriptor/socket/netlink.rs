@@ -17,6 +17,7 @@ use neli::rtnl::{Ifaddrmsg, Ifinfomsg, Rtattr};
 use neli::types::{Buffer, RtBuffer};
 use neli::{FromBytes, ToBytes};
 use nix::sys::socket::{MsgFlags, NetlinkAddr};
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 
 use crate::core::worker::Worker;
@@ -25,12 +26,16 @@ use crate::host::descriptor::listener::{StateEventSource, StateListenHandle, Sta
 use crate::host::descriptor::shared_buf::{
     BufferHandle, BufferSignals, BufferState, ReaderHandle, SharedBuf,
 };
-use crate::host::descriptor::socket::{RecvmsgArgs, RecvmsgReturn, SendmsgArgs, Socket};
+use crate::host::descriptor::socket::{
+    AncillaryData, RecvmsgArgs, RecvmsgReturn, SendmsgArgs, Socket, send_flags,
+};
 use crate::host::descriptor::{
     File, FileMode, FileSignals, FileState, FileStatus, OpenFile, SyscallResult,
 };
 use crate::host::memory_manager::MemoryManager;
-use crate::host::network::namespace::NetworkNamespace;
+use crate::host::network::namespace::{
+    INTERNET_INTERFACE_NAME, LOCALHOST_INTERFACE_NAME, NetworkNamespace,
+};
 use crate::host::syscall::io::{IoVec, IoVecReader, IoVecWriter};
 use crate::host::syscall::types::SyscallError;
 use crate::utility::HostTreePointer;
@@ -64,7 +69,7 @@ impl NetlinkSocket {
             let interfaces = vec![
                 Interface {
                     address: Ipv4Addr::LOCALHOST,
-                    label: String::from("lo"),
+                    label: String::from(LOCALHOST_INTERFACE_NAME),
                     prefix_len: 8,
                     if_type: Arphrd::Loopback,
                     mtu: c::CONFIG_MTU,
@@ -73,7 +78,7 @@ impl NetlinkSocket {
                 },
                 Interface {
                     address: default_ip,
-                    label: String::from("eth0"),
+                    label: String::from(INTERNET_INTERFACE_NAME),
                     prefix_len: 24,
                     if_type: Arphrd::Ether,
                     mtu: c::CONFIG_MTU,
@@ -144,6 +149,11 @@ impl NetlinkSocket {
         linux_api::socket::AddressFamily::AF_NETLINK
     }
 
+    pub fn recv_timeout(&self) -> Option<SimulationTime> {
+        // `SO_RCVTIMEO` isn't supported for these sockets
+        None
+    }
+
     pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError> {
         self.protocol_state.close(&mut self.common, cb_queue)
     }
@@ -186,6 +196,7 @@ impl NetlinkSocket {
         optval_ptr: ForeignPtr<()>,
         optlen: libc::socklen_t,
         memory_manager: &MemoryManager,
+        _cb_queue: &mut CallbackQueue,
     ) -> Result<(), SyscallError> {
         match (level, optname) {
             (libc::SOL_SOCKET, libc::SO_SNDBUF) => {
@@ -300,8 +311,7 @@ impl NetlinkSocket {
         _rng: impl rand::Rng,
         _cb_queue: &mut CallbackQueue,
     ) -> Result<(), Errno> {
-        warn_once_then_debug!("We do not yet handle listen request on netlink sockets");
-        Err(Errno::EINVAL)
+        Err(Errno::EOPNOTSUPP)
     }
 
     pub fn connect(
@@ -765,6 +775,7 @@ impl InitialState {
             addr: Some(src_addr),
             msg_flags: 0,
             control_len: 0,
+            ancillary: AncillaryData::default(),
         })
     }
 
@@ -1097,19 +1108,14 @@ impl NetlinkSocketCommon {
         mem: &mut MemoryManager,
         cb_queue: &mut CallbackQueue,
     ) -> Result<usize, SyscallError> {
-        // MSG_NOSIGNAL is a no-op, since netlink sockets are not stream-oriented.
-        // Ignore the MSG_TRUNC flag since it doesn't do anything when sending.
-        let supported_flags = MsgFlags::MSG_DONTWAIT | MsgFlags::MSG_NOSIGNAL | MsgFlags::MSG_TRUNC;
-
-        // if there's a flag we don't support, it's probably best to raise an error rather than do
-        // the wrong thing
-        let Some(mut flags) = MsgFlags::from_bits(flags) else {
-            warn_once_then_debug!("Unrecognized send flags: {:#b}", flags);
-            return Err(Errno::EINVAL.into());
-        };
-        if flags.intersects(!supported_flags) {
-            warn_once_then_debug!("Unsupported send flags: {:?}", flags);
-            return Err(Errno::EINVAL.into());
+        // MSG_NOSIGNAL is a no-op, since netlink sockets are not stream-oriented. Like Linux, flags
+        // that don't do anything when sending on a netlink socket (for example MSG_TRUNC) are
+        // ignored.
+        let mut flags = send_flags(flags);
+
+        // netlink sockets don't have out-of-band data
+        if flags.contains(MsgFlags::MSG_OOB) {
+            return Err(Errno::EOPNOTSUPP.into());
         }
 
         if self.status.contains(FileStatus::NONBLOCK) {
@@ -1158,7 +1164,7 @@ impl NetlinkSocketCommon {
         })();
 
         // if the syscall would block and we don't have the MSG_DONTWAIT flag
-        if result.as_ref().err() == Some(&Errno::EWOULDBLOCK)
+        if result.as_ref().is_err_and(|e| e.is_wouldblock())
             && !flags.contains(MsgFlags::MSG_DONTWAIT)
         {
             return Err(SyscallError::new_blocked_on_file(
@@ -1218,7 +1224,7 @@ impl NetlinkSocketCommon {
         })();
 
         // if the syscall would block and we don't have the MSG_DONTWAIT flag
-        if result.as_ref().err() == Some(&Errno::EWOULDBLOCK)
+        if result.as_ref().is_err_and(|e| e.is_wouldblock())
             && !flags.contains(MsgFlags::MSG_DONTWAIT)
         {
             return Err(SyscallError::new_blocked_on_file(
@@ -8,21 +8,26 @@ use linux_api::errno::Errno;
 use linux_api::ioctls::IoctlRequest;
 use linux_api::socket::Shutdown;
 use nix::sys::socket::MsgFlags;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 
+use crate::core::worker::Worker;
 use crate::cshadow as c;
 use crate::host::descriptor::listener::{StateEventSource, StateListenHandle, StateListenerFilter};
 use crate::host::descriptor::shared_buf::{
     BufferHandle, BufferSignals, BufferState, ReaderHandle, SharedBuf, WriterHandle,
 };
 use crate::host::descriptor::socket::abstract_unix_ns::AbstractUnixNamespace;
-use crate::host::descriptor::socket::{RecvmsgArgs, RecvmsgReturn, SendmsgArgs, Socket};
+use crate::host::descriptor::socket::{
+    AncillaryData, RecvmsgArgs, RecvmsgReturn, SendmsgArgs, ShutdownFlags, Socket, read_timeout,
+    send_flags, write_timeout,
+};
 use crate::host::descriptor::{
-    File, FileMode, FileSignals, FileState, FileStatus, OpenFile, SyscallResult,
+    CompatFile, File, FileMode, FileSignals, FileState, FileStatus, OpenFile, SyscallResult,
 };
 use crate::host::memory_manager::MemoryManager;
 use crate::host::network::namespace::NetworkNamespace;
-use crate::host::syscall::io::{IoVec, IoVecReader, IoVecWriter};
+use crate::host::syscall::io::{IoVec, IoVecReader, IoVecWriter, write_partial};
 use crate::host::syscall::types::SyscallError;
 use crate::utility::HostTreePointer;
 use crate::utility::callback_queue::CallbackQueue;
@@ -60,6 +65,9 @@ impl UnixSocket {
                 status,
                 socket_type,
                 namespace: Arc::clone(namespace),
+                pass_cred: false,
+                recv_timeout: None,
+                shutdown_status: ShutdownFlags::empty(),
                 has_open_file: false,
             };
 
@@ -119,6 +127,14 @@ impl UnixSocket {
         linux_api::socket::AddressFamily::AF_UNIX
     }
 
+    pub fn recv_timeout(&self) -> Option<SimulationTime> {
+        self.common.recv_timeout
+    }
+
+    pub fn socket_type(&self) -> UnixSocketType {
+        self.common.socket_type
+    }
+
     fn recv_buffer(&self) -> &Arc<AtomicRefCell<SharedBuf>> {
         &self.common.recv_buffer
     }
@@ -182,10 +198,16 @@ impl UnixSocket {
         args: SendmsgArgs,
         mem: &mut MemoryManager,
         _net_ns: &NetworkNamespace,
-        _rng: impl rand::Rng,
+        rng: impl rand::Rng,
         cb_queue: &mut CallbackQueue,
     ) -> Result<libc::ssize_t, SyscallError> {
         let socket_ref = &mut *socket.borrow_mut();
+
+        // an unbound dgram socket is autobound when sending so that the receiver sees its address
+        if matches!(socket_ref.protocol_state, ProtocolState::ConnLessInitial(_)) {
+            socket_ref.autobind_for_passcred(socket, rng)?;
+        }
+
         socket_ref
             .protocol_state
             .sendmsg(&mut socket_ref.common, socket, args, mem, cb_queue)
@@ -236,15 +258,41 @@ impl UnixSocket {
         socket: &Arc<AtomicRefCell<Self>>,
         addr: &SockaddrStorage,
         _net_ns: &NetworkNamespace,
-        _rng: impl rand::Rng,
+        rng: impl rand::Rng,
         cb_queue: &mut CallbackQueue,
     ) -> Result<(), SyscallError> {
         let socket_ref = &mut *socket.borrow_mut();
+
+        // an unbound socket is autobound when connecting so that the peer sees its address
+        if matches!(
+            socket_ref.protocol_state,
+            ProtocolState::ConnOrientedInitial(_) | ProtocolState::ConnLessInitial(_)
+        ) {
+            socket_ref.autobind_for_passcred(socket, rng)?;
+        }
+
         socket_ref
             .protocol_state
             .connect(&mut socket_ref.common, socket, addr, cb_queue)
     }
 
+    /// If `SO_PASSCRED` is enabled and the socket is not yet bound, bind it to an autogenerated
+    /// abstract address. Linux does this so that the peer can identify the sender of any
+    /// credentials, although we don't support sending credentials (`SCM_CREDENTIALS`).
+    fn autobind_for_passcred(
+        &mut self,
+        socket: &Arc<AtomicRefCell<Self>>,
+        rng: impl rand::Rng,
+    ) -> Result<(), SyscallError> {
+        if !self.common.pass_cred || self.protocol_state.bound_address()?.is_some() {
+            return Ok(());
+        }
+
+        let addr = SockaddrStorage::from(SockaddrUnix::new_unnamed());
+        self.protocol_state
+            .bind(&mut self.common, socket, Some(&addr), rng)
+    }
+
     pub fn accept(
         &mut self,
         _net_ns: &NetworkNamespace,
@@ -256,36 +304,124 @@ impl UnixSocket {
 
     pub fn shutdown(
         &mut self,
-        _how: Shutdown,
-        _cb_queue: &mut CallbackQueue,
+        how: Shutdown,
+        cb_queue: &mut CallbackQueue,
     ) -> Result<(), SyscallError> {
-        log::warn!("shutdown() syscall not yet supported for unix sockets; Returning ENOSYS");
-        Err(Errno::ENOSYS.into())
+        self.protocol_state
+            .shutdown(&mut self.common, how, cb_queue)
     }
 
     pub fn getsockopt(
         &mut self,
-        _level: libc::c_int,
-        _optname: libc::c_int,
-        _optval_ptr: ForeignPtr<()>,
-        _optlen: libc::socklen_t,
-        _memory_manager: &mut MemoryManager,
+        level: libc::c_int,
+        optname: libc::c_int,
+        optval_ptr: ForeignPtr<()>,
+        optlen: libc::socklen_t,
+        memory_manager: &mut MemoryManager,
         _cb_queue: &mut CallbackQueue,
     ) -> Result<libc::socklen_t, SyscallError> {
-        log::warn!("getsockopt() syscall not yet supported for unix sockets; Returning ENOSYS");
-        Err(Errno::ENOSYS.into())
+        match (level, optname) {
+            (libc::SOL_SOCKET, libc::SO_PASSCRED) => {
+                let pass_cred = self.common.pass_cred as libc::c_int;
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written =
+                    write_partial(memory_manager, &pass_cred, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_DOMAIN) => {
+                let domain = libc::AF_UNIX;
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written =
+                    write_partial(memory_manager, &domain, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_TYPE) => {
+                let sock_type = libc::c_int::from(self.socket_type());
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written =
+                    write_partial(memory_manager, &sock_type, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_PROTOCOL) => {
+                // unix sockets only have the default protocol
+                let protocol: libc::c_int = 0;
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written =
+                    write_partial(memory_manager, &protocol, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_ACCEPTCONN) => {
+                let is_listener =
+                    matches!(self.protocol_state, ProtocolState::ConnOrientedListening(_));
+                let is_listener = is_listener as libc::c_int;
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written =
+                    write_partial(memory_manager, &is_listener, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_SOCKET, libc::SO_RCVTIMEO) => {
+                write_timeout(self.common.recv_timeout, optval_ptr, optlen, memory_manager)
+            }
+            _ => {
+                log::warn!(
+                    "getsockopt() syscall not yet supported for unix sockets; Returning ENOSYS"
+                );
+                Err(Errno::ENOSYS.into())
+            }
+        }
     }
 
     pub fn setsockopt(
         &mut self,
-        _level: libc::c_int,
-        _optname: libc::c_int,
-        _optval_ptr: ForeignPtr<()>,
-        _optlen: libc::socklen_t,
-        _memory_manager: &MemoryManager,
+        level: libc::c_int,
+        optname: libc::c_int,
+        optval_ptr: ForeignPtr<()>,
+        optlen: libc::socklen_t,
+        memory_manager: &MemoryManager,
+        _cb_queue: &mut CallbackQueue,
     ) -> Result<(), SyscallError> {
-        log::warn!("setsockopt() syscall not yet supported for unix sockets; Returning ENOSYS");
-        Err(Errno::ENOSYS.into())
+        match (level, optname) {
+            (libc::SOL_SOCKET, libc::SO_PASSCRED) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val = memory_manager.read(optval_ptr)?;
+
+                self.common.pass_cred = val != 0;
+                Ok(())
+            }
+            (libc::SOL_SOCKET, libc::SO_RCVTIMEO) => {
+                self.common.recv_timeout = read_timeout(optval_ptr, optlen, memory_manager)?;
+                Ok(())
+            }
+            (
+                libc::SOL_SOCKET,
+                libc::SO_DOMAIN | libc::SO_TYPE | libc::SO_PROTOCOL | libc::SO_ACCEPTCONN,
+            ) => {
+                // these options are read-only
+                Err(Errno::ENOPROTOOPT.into())
+            }
+            _ => {
+                log::warn!(
+                    "setsockopt() syscall not yet supported for unix sockets; Returning ENOSYS"
+                );
+                Err(Errno::ENOSYS.into())
+            }
+        }
     }
 
     pub fn pair(
@@ -370,7 +506,8 @@ struct ConnOrientedConnected {
     peer_addr: Option<SockaddrUnix<libc::sockaddr_un>>,
     peer: Arc<AtomicRefCell<UnixSocket>>,
     reader_handle: ReaderHandle,
-    writer_handle: WriterHandle,
+    /// The writer handle for the peer's receive buffer. This is removed after `shutdown(SHUT_WR)`.
+    writer_handle: Option<WriterHandle>,
     // these handles are never accessed, but we store them because of their drop impls
     _recv_buffer_handle: BufferHandle,
     _send_buffer_handle: BufferHandle,
@@ -711,6 +848,22 @@ impl ProtocolState {
         }
     }
 
+    fn shutdown(
+        &mut self,
+        common: &mut UnixSocketCommon,
+        how: Shutdown,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        match self {
+            Self::ConnOrientedInitial(x) => x.as_mut().unwrap().shutdown(common, how, cb_queue),
+            Self::ConnOrientedListening(x) => x.as_mut().unwrap().shutdown(common, how, cb_queue),
+            Self::ConnOrientedConnected(x) => x.as_mut().unwrap().shutdown(common, how, cb_queue),
+            Self::ConnOrientedClosed(x) => x.as_mut().unwrap().shutdown(common, how, cb_queue),
+            Self::ConnLessInitial(x) => x.as_mut().unwrap().shutdown(common, how, cb_queue),
+            Self::ConnLessClosed(x) => x.as_mut().unwrap().shutdown(common, how, cb_queue),
+        }
+    }
+
     fn listen(
         &mut self,
         common: &mut UnixSocketCommon,
@@ -950,6 +1103,16 @@ where
         Err(Errno::EOPNOTSUPP.into())
     }
 
+    fn shutdown(
+        &mut self,
+        _common: &mut UnixSocketCommon,
+        _how: Shutdown,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        // only connected sockets can be shut down
+        Err(Errno::ENOTCONN.into())
+    }
+
     fn listen(
         self,
         _common: &mut UnixSocketCommon,
@@ -1228,7 +1391,7 @@ impl Protocol for ConnOrientedInitial {
             peer_addr: Some(addr.into_owned()),
             peer: Arc::clone(peer),
             reader_handle,
-            writer_handle,
+            writer_handle: Some(writer_handle),
             _recv_buffer_handle: recv_buffer_handle,
             _send_buffer_handle: send_buffer_handle,
         };
@@ -1295,7 +1458,7 @@ impl Protocol for ConnOrientedInitial {
             peer_addr: None,
             peer,
             reader_handle,
-            writer_handle,
+            writer_handle: Some(writer_handle),
             _recv_buffer_handle: recv_buffer_handle,
             _send_buffer_handle: send_buffer_handle,
         };
@@ -1392,6 +1555,26 @@ impl Protocol for ConnOrientedListening {
         (self.into(), Err(Errno::EINVAL.into()))
     }
 
+    fn sendmsg(
+        &mut self,
+        common: &mut UnixSocketCommon,
+        _socket: &Arc<AtomicRefCell<UnixSocket>>,
+        args: SendmsgArgs,
+        _mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::ssize_t, SyscallError> {
+        // a listening socket has no peer, so behaves like a socket that was never connected
+        match (common.socket_type, args.addr) {
+            (UnixSocketType::Stream, Some(_)) => Err(Errno::EOPNOTSUPP.into()),
+            (UnixSocketType::Stream, None) => Err(Errno::ENOTCONN.into()),
+            (UnixSocketType::SeqPacket, _) => Err(Errno::ENOTCONN.into()),
+            (UnixSocketType::Dgram, _) => panic!(
+                "A dgram unix socket is in the connection-oriented {:?} state",
+                std::any::type_name::<Self>()
+            ),
+        }
+    }
+
     fn accept(
         &mut self,
         common: &mut UnixSocketCommon,
@@ -1473,7 +1656,7 @@ impl Protocol for ConnOrientedListening {
             peer_addr: from_address,
             peer: Arc::clone(peer),
             reader_handle,
-            writer_handle,
+            writer_handle: Some(writer_handle),
             _recv_buffer_handle: recv_buffer_handle,
             _send_buffer_handle: send_buffer_handle,
         };
@@ -1524,13 +1707,18 @@ impl Protocol for ConnOrientedConnected {
             let peer = self.peer.borrow();
             let send_buffer = peer.recv_buffer().borrow();
 
-            new_state.set(
-                FileState::READABLE,
-                recv_buffer.has_data() || recv_buffer.num_writers() == 0,
-            );
+            // the peer has shut down writing (or closed), or we've shut down reading
+            let read_hangup = recv_buffer.num_writers() == 0
+                || common.shutdown_status.contains(ShutdownFlags::READ);
+
+            new_state.set(FileState::READABLE, recv_buffer.has_data() || read_hangup);
+            new_state.set(FileState::RDHUP, read_hangup);
             new_state.set(
                 FileState::WRITABLE,
-                common.sent_len < common.send_limit || send_buffer.num_readers() == 0,
+                common.sent_len < common.send_limit
+                    || send_buffer.num_readers() == 0
+                    || common.shutdown_status.contains(ShutdownFlags::WRITE)
+                    || peer.common.shutdown_status.contains(ShutdownFlags::READ),
             );
         }
 
@@ -1554,11 +1742,13 @@ impl Protocol for ConnOrientedConnected {
             .remove_reader(self.reader_handle, cb_queue);
 
         // inform the buffer that there is one fewer writers
-        self.peer
-            .borrow()
-            .recv_buffer()
-            .borrow_mut()
-            .remove_writer(self.writer_handle, cb_queue);
+        if let Some(writer_handle) = self.writer_handle {
+            self.peer
+                .borrow()
+                .recv_buffer()
+                .borrow_mut()
+                .remove_writer(writer_handle, cb_queue);
+        }
 
         let new_state = ConnOrientedClosed {};
         new_state.refresh_file_state(common, FileSignals::empty(), cb_queue);
@@ -1578,6 +1768,18 @@ impl Protocol for ConnOrientedConnected {
             return Err(Errno::EINVAL.into());
         }
 
+        // we can't send after `shutdown(SHUT_WR)`, or after the peer has run `shutdown(SHUT_RD)`
+        if common.shutdown_status.contains(ShutdownFlags::WRITE)
+            || self
+                .peer
+                .borrow()
+                .common
+                .shutdown_status
+                .contains(ShutdownFlags::READ)
+        {
+            return Err(Errno::EPIPE.into());
+        }
+
         let recv_socket = common.resolve_destination(Some(&self.peer), args.addr)?;
         let rv = common.sendmsg(socket, args.iovs, args.flags, &recv_socket, mem, cb_queue)?;
 
@@ -1599,6 +1801,19 @@ impl Protocol for ConnOrientedConnected {
             return Err(Errno::EINVAL.into());
         }
 
+        // after `shutdown(SHUT_RD)`, return an EOF once all buffered data has been read
+        if common.shutdown_status.contains(ShutdownFlags::READ)
+            && !common.recv_buffer.borrow().has_data()
+        {
+            return Ok(RecvmsgReturn {
+                return_val: 0,
+                addr: self.peer_addr.map(Into::into),
+                msg_flags: 0,
+                control_len: 0,
+                ancillary: AncillaryData::default(),
+            });
+        }
+
         let (rv, num_removed_from_buf, msg_flags) =
             common.recvmsg(socket, args.iovs, args.flags, mem, cb_queue)?;
         let num_removed_from_buf = u64::try_from(num_removed_from_buf).unwrap();
@@ -1619,6 +1834,7 @@ impl Protocol for ConnOrientedConnected {
             addr: self.peer_addr.map(Into::into),
             msg_flags,
             control_len: 0,
+            ancillary: AncillaryData::default(),
         })
     }
 
@@ -1642,6 +1858,44 @@ impl Protocol for ConnOrientedConnected {
         common.ioctl(request, arg_ptr, memory_manager)
     }
 
+    fn shutdown(
+        &mut self,
+        common: &mut UnixSocketCommon,
+        how: Shutdown,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        if matches!(how, Shutdown::SHUT_WR | Shutdown::SHUT_RDWR) {
+            common.shutdown_status.insert(ShutdownFlags::WRITE);
+
+            // All data that we've sent is already in the peer's receive buffer, so removing our
+            // writer handle doesn't discard anything. The peer will read an EOF after it has read
+            // the buffered data.
+            if let Some(writer_handle) = self.writer_handle.take() {
+                self.peer
+                    .borrow()
+                    .recv_buffer()
+                    .borrow_mut()
+                    .remove_writer(writer_handle, cb_queue);
+            }
+        }
+
+        if matches!(how, Shutdown::SHUT_RD | Shutdown::SHUT_RDWR) {
+            common.shutdown_status.insert(ShutdownFlags::READ);
+
+            // the peer can no longer send to us, so a blocked send on the peer should return
+            // `EPIPE`
+            let peer = Arc::clone(&self.peer);
+            cb_queue.add(move |cb_queue| {
+                peer.borrow_mut()
+                    .refresh_file_state(FileSignals::empty(), cb_queue);
+            });
+        }
+
+        self.refresh_file_state(common, FileSignals::empty(), cb_queue);
+
+        Ok(())
+    }
+
     fn accept(
         &mut self,
         _common: &mut UnixSocketCommon,
@@ -1748,6 +2002,10 @@ impl Protocol for ConnLessInitial {
                     .from_socket
                     .borrow_mut()
                     .inform_bytes_read(byte_data.num_bytes, cb_queue);
+
+                // close any files that were passed with the unread message, which may be other
+                // sockets that can't be closed while this socket is borrowed
+                drop(byte_data.rights);
             });
         }
 
@@ -1780,11 +2038,6 @@ impl Protocol for ConnLessInitial {
         mem: &mut MemoryManager,
         cb_queue: &mut CallbackQueue,
     ) -> Result<libc::ssize_t, SyscallError> {
-        if !args.control_ptr.ptr().is_null() {
-            log::debug!("Unix sockets don't yet support control data for sendmsg()");
-            return Err(Errno::EINVAL.into());
-        }
-
         let recv_socket = common.resolve_destination(self.peer.as_ref(), args.addr)?;
         let rv = common.sendmsg(socket, args.iovs, args.flags, &recv_socket, mem, cb_queue)?;
 
@@ -1792,6 +2045,8 @@ impl Protocol for ConnLessInitial {
             from_socket: self.this_socket.upgrade().unwrap(),
             from_addr: self.bound_addr,
             num_bytes: rv.try_into().unwrap(),
+            rights: args.ancillary.rights,
+            creds: args.ancillary.creds.unwrap_or_else(current_creds),
         };
 
         match &mut recv_socket.borrow_mut().protocol_state {
@@ -1817,18 +2072,20 @@ impl Protocol for ConnLessInitial {
         mem: &mut MemoryManager,
         cb_queue: &mut CallbackQueue,
     ) -> Result<RecvmsgReturn, SyscallError> {
-        if !args.control_ptr.ptr().is_null() {
-            log::debug!("Unix sockets don't yet support control data for recvmsg()");
-            return Err(Errno::EINVAL.into());
-        }
-
         let (rv, num_removed_from_buf, msg_flags) =
             common.recvmsg(socket, args.iovs, args.flags, mem, cb_queue)?;
         let num_removed_from_buf = u64::try_from(num_removed_from_buf).unwrap();
 
-        let byte_data = self.recv_data.pop_front().unwrap();
+        let mut byte_data = self.recv_data.pop_front().unwrap();
         assert!(num_removed_from_buf == byte_data.num_bytes);
 
+        // the syscall handler will write the ancillary data as control messages, and the sender's
+        // credentials are only received if `SO_PASSCRED` is enabled
+        let ancillary = AncillaryData {
+            rights: std::mem::take(&mut byte_data.rights),
+            creds: common.pass_cred.then_some(byte_data.creds),
+        };
+
         // defer informing the sender until we're done processing the current socket
         cb_queue.add(move |cb_queue| {
             byte_data
@@ -1844,6 +2101,7 @@ impl Protocol for ConnLessInitial {
             addr: byte_data.from_addr.map(Into::into),
             msg_flags,
             control_len: 0,
+            ancillary,
         })
     }
 
@@ -1974,6 +2232,12 @@ struct UnixSocketCommon {
     status: FileStatus,
     socket_type: UnixSocketType,
     namespace: Arc<AtomicRefCell<AbstractUnixNamespace>>,
+    /// Was `SO_PASSCRED` enabled? If so, the socket is autobound when connecting or sending.
+    pass_cred: bool,
+    /// The timeout set by `SO_RCVTIMEO` for blocking receives and accepts, if any.
+    recv_timeout: Option<SimulationTime>,
+    /// Which directions have been shut down with `shutdown()`.
+    shutdown_status: ShutdownFlags,
     // should only be used by `OpenFile` to make sure there is only ever one `OpenFile` instance for
     // this file
     has_open_file: bool,
@@ -2110,23 +2374,14 @@ impl UnixSocketCommon {
         mem: &mut MemoryManager,
         cb_queue: &mut CallbackQueue,
     ) -> Result<usize, SyscallError> {
-        // MSG_NOSIGNAL is currently a no-op, since we haven't implemented the behavior
-        // it's meant to disable.
-        // TODO: Once we've implemented generating a SIGPIPE when the peer on a
-        // stream-oriented socket has closed the connection, MSG_NOSIGNAL should
-        // disable it.
-        // Ignore the MSG_TRUNC flag since it doesn't do anything when sending.
-        let supported_flags = MsgFlags::MSG_DONTWAIT | MsgFlags::MSG_NOSIGNAL | MsgFlags::MSG_TRUNC;
-
-        // if there's a flag we don't support, it's probably best to raise an error rather than do
-        // the wrong thing
-        let Some(mut flags) = MsgFlags::from_bits(flags) else {
-            log::warn!("Unrecognized send flags: {:#b}", flags);
-            return Err(Errno::EINVAL.into());
-        };
-        if flags.intersects(!supported_flags) {
-            log::warn!("Unsupported send flags: {:?}", flags);
-            return Err(Errno::EINVAL.into());
+        // MSG_NOSIGNAL is handled by the syscall handler, which raises SIGPIPE on EPIPE. Like Linux,
+        // flags that don't do anything when sending on a unix socket (for example MSG_TRUNC,
+        // MSG_EOR, and MSG_MORE) are ignored.
+        let mut flags = send_flags(flags);
+
+        // we don't support out-of-band data on unix sockets
+        if flags.contains(MsgFlags::MSG_OOB) {
+            return Err(Errno::EOPNOTSUPP.into());
         }
 
         if self.status.contains(FileStatus::NONBLOCK) {
@@ -2163,6 +2418,8 @@ impl UnixSocketCommon {
             }
 
             let len = match self.socket_type {
+                // a stream socket writes as much as fits across all iovecs, and returns the short
+                // count rather than blocking for the remainder
                 UnixSocketType::Stream => std::cmp::min(len, space_available),
                 UnixSocketType::Dgram | UnixSocketType::SeqPacket => {
                     if len <= space_available {
@@ -2205,7 +2462,7 @@ impl UnixSocketCommon {
         })();
 
         // if the syscall would block and we don't have the MSG_DONTWAIT flag
-        if result.as_ref().err() == Some(&Errno::EWOULDBLOCK)
+        if result.as_ref().is_err_and(|e| e.is_wouldblock())
             && !flags.contains(MsgFlags::MSG_DONTWAIT)
         {
             return Err(SyscallError::new_blocked_on_file(
@@ -2226,7 +2483,10 @@ impl UnixSocketCommon {
         mem: &mut MemoryManager,
         cb_queue: &mut CallbackQueue,
     ) -> Result<(usize, usize, libc::c_int), SyscallError> {
-        let supported_flags = MsgFlags::MSG_DONTWAIT | MsgFlags::MSG_TRUNC;
+        // MSG_CMSG_CLOEXEC is handled by the syscall handler when it adds any passed files to the
+        // descriptor table
+        let supported_flags =
+            MsgFlags::MSG_DONTWAIT | MsgFlags::MSG_TRUNC | MsgFlags::MSG_CMSG_CLOEXEC;
 
         // if there's a flag we don't support, it's probably best to raise an error rather than do
         // the wrong thing
@@ -2284,7 +2544,7 @@ impl UnixSocketCommon {
         })();
 
         // if the syscall would block and we don't have the MSG_DONTWAIT flag
-        if result.as_ref().err() == Some(&Errno::EWOULDBLOCK)
+        if result.as_ref().is_err_and(|e| e.is_wouldblock())
             && !flags.contains(MsgFlags::MSG_DONTWAIT)
         {
             return Err(SyscallError::new_blocked_on_file(
@@ -2300,11 +2560,42 @@ impl UnixSocketCommon {
     pub fn ioctl(
         &mut self,
         request: IoctlRequest,
-        _arg_ptr: ForeignPtr<()>,
-        _memory_manager: &mut MemoryManager,
+        arg_ptr: ForeignPtr<()>,
+        memory_manager: &mut MemoryManager,
     ) -> SyscallResult {
-        log::warn!("We do not yet handle ioctl request {request:?} on unix sockets");
-        Err(Errno::EINVAL.into())
+        match request {
+            // equivalent to SIOCINQ
+            IoctlRequest::FIONREAD => {
+                let recv_buffer = self.recv_buffer.borrow();
+
+                let len = match self.socket_type {
+                    UnixSocketType::Stream | UnixSocketType::SeqPacket => recv_buffer.num_bytes(),
+                    // only the size of the next message
+                    UnixSocketType::Dgram => recv_buffer.peek(std::io::sink())?.1,
+                };
+
+                let len: libc::c_int = len.try_into().unwrap();
+
+                let arg_ptr = arg_ptr.cast::<libc::c_int>();
+                memory_manager.write(arg_ptr, &len)?;
+
+                Ok(0.into())
+            }
+            // equivalent to SIOCOUTQ
+            IoctlRequest::TIOCOUTQ => {
+                // the bytes we've sent that the peer hasn't read yet
+                let len: libc::c_int = self.sent_len.try_into().unwrap();
+
+                let arg_ptr = arg_ptr.cast::<libc::c_int>();
+                memory_manager.write(arg_ptr, &len)?;
+
+                Ok(0.into())
+            }
+            _ => {
+                log::warn!("We do not yet handle ioctl request {request:?} on unix sockets");
+                Err(Errno::EINVAL.into())
+            }
+        }
     }
 
     fn update_state(
@@ -2391,6 +2682,16 @@ impl TryFrom<libc::c_int> for UnixSocketType {
     }
 }
 
+impl From<UnixSocketType> for libc::c_int {
+    fn from(val: UnixSocketType) -> Self {
+        match val {
+            UnixSocketType::Stream => libc::SOCK_STREAM,
+            UnixSocketType::Dgram => libc::SOCK_DGRAM,
+            UnixSocketType::SeqPacket => libc::SOCK_SEQPACKET,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct UnixSocketTypeConversionError(libc::c_int);
 
@@ -2416,4 +2717,18 @@ struct ByteData {
     from_socket: Arc<AtomicRefCell<UnixSocket>>,
     from_addr: Option<SockaddrUnix<libc::sockaddr_un>>,
     num_bytes: u64,
+    /// Open files passed with the message. They're closed if the message is never received.
+    rights: Vec<CompatFile>,
+    /// The credentials of the sender.
+    creds: libc::ucred,
+}
+
+/// The credentials of the current process, which are sent with each message unless the process
+/// provides different credentials in an `SCM_CREDENTIALS` control message.
+fn current_creds() -> libc::ucred {
+    libc::ucred {
+        pid: Worker::active_process_id().unwrap().into(),
+        uid: nix::unistd::getuid().as_raw(),
+        gid: nix::unistd::getgid().as_raw(),
+    }
 }
@@ -61,6 +61,7 @@ enum TCPFlags {
     TCPF_WAS_ESTABLISHED = 1 << 6,
     TCPF_CONNECT_SIGNAL_NEEDED = 1 << 7,
     TCPF_SHOULD_SEND_WR_FIN = 1 << 8,
+    TCPF_RESET_REPORTED = 1 << 9,
 };
 
 enum TCPError {
@@ -68,10 +69,12 @@ enum TCPError {
     TCPE_CONNECTION_RESET = 1 << 0,
     TCPE_SEND_EOF = 1 << 1,
     TCPE_RECEIVE_EOF = 1 << 2,
+    /* the connection was aborted because sent data wasn't acknowledged in time */
+    TCPE_TIMED_OUT = 1 << 3,
 };
 
 enum TCPChildState {
-    TCPCS_NONE, TCPCS_INCOMPLETE, TCPCS_PENDING, TCPCS_ACCEPTED
+    TCPCS_NONE, TCPCS_INCOMPLETE, TCPCS_DEFERRED, TCPCS_PENDING, TCPCS_ACCEPTED
 };
 
 typedef enum TCPReceiveState TCPReceiveState;
@@ -168,8 +171,12 @@ struct _TCP {
         guint32 packetsSent;
         /* total number of quick acknowledgments sent */
         guint32 numQuickACKsSent;
+        /* the next ACK should be sent immediately rather than delayed (TCP_QUICKACK) */
+        gboolean quickACK;
         gboolean delayedACKIsScheduled;
         guint32 delayedACKCounter;
+        /* amount of received data that is waiting for a delayed ACK */
+        gsize delayedACKBytes;
         /* list of selective ACKs, packets received after a missing packet */
         GList* selectiveACKs;
     } send;
@@ -205,6 +212,11 @@ struct _TCP {
     /* congestion object for implementing different types of congestion control (aimd, reno, cubic) */
     TCPCong cong;
 
+    /* send selective ACKs for out-of-order data, and avoid retransmitting data that the other end
+     * selectively acknowledged. this starts as the host's tcp_sack option, and is only kept if the
+     * peer also permits SACK in its SYN. */
+    gboolean useSACK;
+
     struct {
       gint rttSmoothed;
       gint rttVariance;
@@ -226,6 +238,24 @@ struct _TCP {
     /* track amount of queued application data */
     gsize throttledOutputLength;
 
+    /* Nagle's algorithm holds back small data segments while earlier data is unacknowledged */
+    struct {
+        /* the algorithm is disabled (TCP_NODELAY) */
+        gboolean noDelay;
+        /* the last throttled data packet if it's smaller than a full segment, so that more user
+         * data can be appended to it before it's sent */
+        Packet* partialSegment;
+    } nagle;
+
+    /* close() aborts the connection with a RST instead of a FIN (SO_LINGER with a zero timeout) */
+    gboolean abortOnClose;
+
+    /* the priority given to the packets we send (SO_PRIORITY) */
+    guint32 socketPriority;
+
+    /* the mark given to the packets we send (SO_MARK) */
+    guint32 mark;
+
     /* TCP ensures that the user receives data in-order */
     PriorityQueue* unorderedInput;
     /* track amount of queued application data */
@@ -235,6 +265,41 @@ struct _TCP {
     Packet* partialUserDataPacket;
     guint partialOffset;
 
+    /* urgent (out-of-band) data, which is always the last byte of a packet with PTCP_URG set */
+    struct {
+        /* deliver the urgent byte in the normal data stream (SO_OOBINLINE) */
+        gboolean isInline;
+        /* the urgent byte is still in the readable data stream */
+        gboolean markPending;
+        /* number of readable bytes that precede the urgent byte in the data stream */
+        gsize markOffset;
+        /* the urgent byte is waiting to be read with MSG_OOB */
+        gboolean byteValid;
+        guchar byte;
+    } urgent;
+
+    /* number of SYN-ACK retransmissions (TCP_DEFER_ACCEPT) to wait for data on a new child before
+     * it can be accepted, or 0 if children can be accepted as soon as they're established */
+    guchar deferAcceptRetrans;
+
+    /* the TCP_FASTOPEN queue length of a listening socket. a listener only accepts data carried
+     * by a SYN if this is positive */
+    gint fastOpenQueueLength;
+
+    /* the SYN for our connection attempt hasn't been sent yet, and will carry the first segment of
+     * user data when it's sent (MSG_FASTOPEN) */
+    gboolean synDeferred;
+
+    /* abort the connection if sent data isn't acknowledged in time (TCP_USER_TIMEOUT) */
+    struct {
+        /* the timeout in milliseconds, or 0 if the connection never times out */
+        guint timeout;
+        /* when our outstanding data started waiting on the peer, or when the peer last acknowledged
+         * new data; 0 if no data is outstanding */
+        CSimulationTime waitingSince;
+        gboolean timerIsScheduled;
+    } userTimeout;
+
     /* if I am a server, I parent many multiplexed child sockets */
     TCPServer* server;
 
@@ -724,15 +789,16 @@ static void _tcp_setState(TCP* tcp, const Host* host, enum TCPState state) {
                     if((parent->state == TCPS_CLOSED) && (g_hash_table_size(parent->server->children) <= 0)) {
                         if (disassociate) {
                             /* this will unbind from the network interface and free socket */
-                            host_disassociateInterface(
-                                host, PTCP, sock_ip, sock_port, peer_ip, peer_port);
+                            host_disassociateInterface(host, parent->rustSocket, PTCP, sock_ip,
+                                                       sock_port, peer_ip, peer_port);
                         }
                     }
                 }
 
                 if (disassociate) {
                     /* TODO: we should only be disassociating non-child sockets */
-                    host_disassociateInterface(host, PTCP, sock_ip, sock_port, peer_ip, peer_port);
+                    host_disassociateInterface(
+                        host, tcp->rustSocket, PTCP, sock_ip, sock_port, peer_ip, peer_port);
                 }
             }
             break;
@@ -804,6 +870,13 @@ static gsize _tcp_getBufferSpaceOut(TCP* tcp) {
     return space;
 }
 
+/* returns the number of bytes that the next call to tcp_sendUserData() can accept */
+gsize tcp_getSendableLength(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+    /* maximum data we can send network, o/w tcp truncates and only sends 65536*/
+    return MIN(_tcp_getBufferSpaceOut(tcp), 65535);
+}
+
 static gsize _tcp_getBufferSpaceIn(TCP* tcp) {
     MAGIC_ASSERT(tcp);
     /* account for unordered input buffer */
@@ -830,6 +903,28 @@ static void _tcp_bufferPacketOut(TCP* tcp, Packet* packet) {
     }
 }
 
+/* stop appending user data to the partial segment, if there is one */
+static void _tcp_clearPartialSegment(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+
+    if (tcp->nagle.partialSegment != NULL) {
+        packet_unref(tcp->nagle.partialSegment);
+        tcp->nagle.partialSegment = NULL;
+    }
+}
+
+/* Nagle's algorithm: a small segment isn't sent while earlier data is still unacknowledged, unless
+ * the connection is being closed */
+static gboolean _tcp_shouldHoldSegment(TCP* tcp, Packet* packet, PacketTCPHeader* header) {
+    MAGIC_ASSERT(tcp);
+
+    if (tcp->nagle.noDelay || packet != tcp->nagle.partialSegment) {
+        return FALSE;
+    }
+
+    return header->sequence > tcp->send.unacked && !(tcp->flags & TCPF_SHOULD_SEND_WR_FIN);
+}
+
 static void _tcp_bufferPacketIn(TCP* tcp, Packet* packet) {
     MAGIC_ASSERT(tcp);
 
@@ -849,6 +944,87 @@ static void _tcp_bufferPacketIn(TCP* tcp, Packet* packet) {
     }
 }
 
+/* returns the amount of in-order user data that has not yet been read by the user */
+static gsize _tcp_getReadableLength(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+    gsize length = legacysocket_getInputBufferLength(&(tcp->super));
+    if (tcp->partialUserDataPacket != NULL) {
+        length += packet_getPayloadSize(tcp->partialUserDataPacket) - tcp->partialOffset;
+    }
+    return length;
+}
+
+static void _tcp_updateUrgentStatus(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+    /* urgent data is reported to the user (for example with POLLPRI) until it has been read */
+    gboolean pending = tcp->urgent.isInline ? tcp->urgent.markPending : tcp->urgent.byteValid;
+    legacyfile_adjustStatus(&(tcp->super.super), FileState_PRIORITY, pending, 0);
+}
+
+/* If SO_OOBINLINE is not set, the urgent byte is removed from the data stream once the user has
+ * read all of the data preceding it, so that it can only be read with MSG_OOB. */
+static void _tcp_skipUrgentByteIfAtMark(TCP* tcp, const Host* host) {
+    MAGIC_ASSERT(tcp);
+
+    if (!tcp->urgent.markPending || tcp->urgent.markOffset > 0 || tcp->urgent.isInline) {
+        return;
+    }
+
+    tcp->urgent.markPending = FALSE;
+
+    if (tcp->partialUserDataPacket == NULL) {
+        /* the urgent byte is the first byte of the next packet that has a payload */
+        Packet* packet = NULL;
+        while ((packet = legacysocket_removeFromInputBuffer(&(tcp->super), host)) != NULL) {
+            if (packet_getPayloadSize(packet) > 0) {
+                break;
+            }
+            packet_addDeliveryStatus(packet, PDS_RCV_SOCKET_DELIVERED);
+            packet_unref(packet);
+        }
+
+        if (packet == NULL) {
+            utility_debugAssert(FALSE);
+            return;
+        }
+
+        tcp->partialUserDataPacket = packet;
+        tcp->partialOffset = 0;
+    }
+
+    tcp->partialOffset++;
+
+    if (tcp->partialOffset >= packet_getPayloadSize(tcp->partialUserDataPacket)) {
+        packet_addDeliveryStatus(tcp->partialUserDataPacket, PDS_RCV_SOCKET_DELIVERED);
+        packet_unref(tcp->partialUserDataPacket);
+        tcp->partialUserDataPacket = NULL;
+        tcp->partialOffset = 0;
+    }
+}
+
+/* The packet was just appended to the input buffer, and its last byte is urgent data. */
+static void _tcp_markUrgentData(TCP* tcp, const Host* host, Packet* packet) {
+    MAGIC_ASSERT(tcp);
+
+    gsize length = packet_getPayloadSize(packet);
+    if (length == 0) {
+        return;
+    }
+
+    /* like linux, only the most recent urgent byte is tracked; an earlier urgent byte that is
+     * still in the data stream becomes normal data */
+    tcp->urgent.markPending = TRUE;
+    tcp->urgent.markOffset = _tcp_getReadableLength(tcp) - 1;
+    packet_copyPayloadShadow(packet, length - 1, &tcp->urgent.byte, 1);
+    tcp->urgent.byteValid = !tcp->urgent.isInline;
+
+    trace("%s <-> %s: received urgent data with %" G_GSIZE_FORMAT " bytes before the mark",
+          tcp->super.boundString, tcp->super.peerString, tcp->urgent.markOffset);
+
+    _tcp_skipUrgentByteIfAtMark(tcp, host);
+    _tcp_updateUrgentStatus(tcp);
+}
+
 static void _tcp_updateReceiveWindow(TCP* tcp) {
     MAGIC_ASSERT(tcp);
 
@@ -908,6 +1084,11 @@ static Packet* _tcp_createPacketWithoutPayload(TCP* tcp, const Host* host, Proto
     /* make sure our receive window is up to date before putting it in the packet */
     _tcp_updateReceiveWindow(tcp);
 
+    /* like the SACK-permitted option, a SYN advertises that we can use SACK */
+    if ((flags & PTCP_SYN) && tcp->useSACK) {
+        flags |= PTCP_SACK;
+    }
+
     /* control packets have no sequence number
      * (except SYN and FIN, so we close after sending everything) */
     /* TODO: all FIN packets (including FIN,ACK) should increment the sequence number */
@@ -926,7 +1107,8 @@ static Packet* _tcp_createPacketWithoutPayload(TCP* tcp, const Host* host, Proto
     guint hostID = host_getID(host);
     guint64 packetID = host_getNewPacketID(host);
     Packet* packet = packet_new_tcp(hostID, packetID, flags, sourceIP, sourcePort, destinationIP,
-                                    destinationPort, sequence, priority);
+                                    destinationPort, sequence, priority, tcp->socketPriority,
+                                    tcp->mark);
     packet_addDeliveryStatus(packet, PDS_SND_CREATED);
 
     /* update sequence number */
@@ -1289,7 +1471,10 @@ void tcp_networkInterfaceIsAboutToSendPacket(TCP* tcp, const Host* host, Packet*
 
     CSimulationTime now = worker_getCurrentSimulationTime();
 
-    PacketSelectiveAcks sel_acks = _tcp_selective_acks_from_list(tcp->send.selectiveACKs);
+    PacketSelectiveAcks sel_acks = {0};
+    if (tcp->useSACK) {
+        sel_acks = _tcp_selective_acks_from_list(tcp->send.selectiveACKs);
+    }
 
     /* update TCP header to our current advertised window and acknowledgment and timestamps */
     packet_updateTCP(packet, tcp->receive.next, sel_acks, tcp->receive.window, 0, false, now,
@@ -1305,6 +1490,7 @@ void tcp_networkInterfaceIsAboutToSendPacket(TCP* tcp, const Host* host, Packet*
     if (header.flags & PTCP_ACK) {
         /* we are sending an ACK already, so we may not need any delayed ACK */
         tcp->send.delayedACKCounter = 0;
+        tcp->send.delayedACKBytes = 0;
     }
 
     if (header.sequence > 0) {
@@ -1373,6 +1559,12 @@ static void _tcp_flush(TCP* tcp, const Host* host) {
         PacketTCPHeader header = packet_getTCPHeader(packet);
 
         if(length > 0) {
+            if (_tcp_shouldHoldSegment(tcp, packet, &header)) {
+                trace("%s <-> %s: holding back small segment %u until earlier data is acknowledged",
+                      tcp->super.boundString, tcp->super.peerString, header.sequence);
+                break;
+            }
+
             /* we cant send it if our window is too small */
             gboolean fitsInWindow =
                 (header.sequence < (guint)(tcp->send.unacked + tcp->send.window)) ? TRUE : FALSE;
@@ -1396,6 +1588,11 @@ static void _tcp_flush(TCP* tcp, const Host* host) {
         priorityqueue_pop(tcp->throttledOutput);
         tcp->throttledOutputLength -= length;
 
+        /* no more data can be appended once it's sent */
+        if (packet == tcp->nagle.partialSegment) {
+            _tcp_clearPartialSegment(tcp);
+        }
+
         /* packet will get stored in retrans queue in tcp_networkInterfaceIsAboutToSendPacket */
 
         /* socket will queue it ASAP */
@@ -1435,8 +1632,12 @@ static void _tcp_flush(TCP* tcp, const Host* host) {
             /* move from the unordered buffer to user input buffer */
             gboolean fitInBuffer = legacysocket_addToInputBuffer(&(tcp->super), host, packet);
 
+            if (fitInBuffer && (header.flags & PTCP_URG)) {
+                _tcp_markUrgentData(tcp, host, packet);
+            }
+
             /* we just added a packet, so we are readable */
-            if (fitInBuffer && legacysocket_getInputBufferLength(&(tcp->super)) > 0) {
+            if (fitInBuffer && _tcp_getReadableLength(tcp) > 0) {
                 FileSignals signals = 0;
                 if (packet_getPayloadSize(packet) > 0) {
                     signals |= FileSignals_READ_BUFFER_GREW;
@@ -1487,6 +1688,10 @@ static void _tcp_flush(TCP* tcp, const Host* host) {
         legacyfile_adjustStatus((LegacyFile*)tcp, FileState_WRITABLE, FALSE, 0);
     } else if((tcp->error & TCPE_SEND_EOF) && (tcp->flags & TCPF_EOF_WR_SIGNALED)) {
         legacyfile_adjustStatus((LegacyFile*)tcp, FileState_WRITABLE, FALSE, 0);
+    } else if((tcp->error & TCPE_SEND_EOF) &&
+              (legacyfile_getStatus((LegacyFile*)tcp) & FileState_ACTIVE)) {
+        /* a send would fail with EPIPE even if the buffer is full, so wake any blocked writers */
+        legacyfile_adjustStatus((LegacyFile*)tcp, FileState_WRITABLE, TRUE, 0);
     } else if(_tcp_getBufferSpaceOut(tcp) <= 0) {
         legacyfile_adjustStatus((LegacyFile*)tcp, FileState_WRITABLE, FALSE, 0);
     } else if (legacyfile_getStatus((LegacyFile*)tcp) & FileState_ACTIVE) {
@@ -1554,6 +1759,109 @@ static void _tcp_runRetransmitTimerExpiredTask(const Host* host, gpointer voidIn
     _tcp_flush(tcp, host);
 }
 
+/* abort the connection since our data wasn't acknowledged within the TCP_USER_TIMEOUT */
+static void _tcp_abortUserTimeout(TCP* tcp, const Host* host) {
+    MAGIC_ASSERT(tcp);
+
+    trace("%s <-> %s: data was unacknowledged for %u ms, aborting connection",
+          tcp->super.boundString, tcp->super.peerString, tcp->userTimeout.timeout);
+
+    /* drop any data that is still waiting to be sent or acknowledged */
+    priorityqueue_clear(tcp->throttledOutput);
+    tcp->throttledOutputLength = 0;
+    _tcp_clearPartialSegment(tcp);
+    _tcp_stopRetransmitTimer(tcp);
+    _tcp_clearRetransmit(tcp, (guint)-1);
+    tcp->userTimeout.waitingSince = 0;
+
+    _tcp_sendControlPacket(tcp, host, PTCP_RST);
+
+    tcp->error |= TCPE_CONNECTION_RESET | TCPE_SEND_EOF | TCPE_TIMED_OUT;
+    tcp->flags |= TCPF_REMOTE_CLOSED;
+    legacyfile_adjustStatus((LegacyFile*)tcp, FileState_RDHUP, TRUE, 0);
+
+    _tcp_setState(tcp, host, TCPS_TIMEWAIT);
+
+    /* we won't receive any more user data after what we have now */
+    tcp->receive.end = tcp->receive.next;
+
+    /* wake up any blocked readers and writers so that they can see the error */
+    legacyfile_adjustStatus((LegacyFile*)tcp, FileState_READABLE | FileState_WRITABLE, TRUE, 0);
+}
+
+static void _tcp_runUserTimeoutTimerExpiredTask(const Host* host, gpointer voidInetSocket,
+                                                gpointer userData);
+
+static void _tcp_scheduleUserTimeoutTimer(TCP* tcp, const Host* host, CSimulationTime delay) {
+    MAGIC_ASSERT(tcp);
+
+    utility_alwaysAssert(tcp->rustSocket != NULL);
+    const InetSocket* inetSocket = inetsocketweak_upgrade(tcp->rustSocket);
+    utility_alwaysAssert(inetSocket != NULL);
+    TaskRef* timeoutTask =
+        taskref_new_bound(host_getID(host), _tcp_runUserTimeoutTimerExpiredTask,
+                          (void*)inetSocket, NULL, inetsocket_dropVoid, NULL);
+
+    host_scheduleTaskWithDelay(host, timeoutTask, delay);
+    taskref_drop(timeoutTask);
+
+    tcp->userTimeout.timerIsScheduled = TRUE;
+}
+
+static void _tcp_runUserTimeoutTimerExpiredTask(const Host* host, gpointer voidInetSocket,
+                                                gpointer userData) {
+    const InetSocket* inetSocket = voidInetSocket;
+    utility_alwaysAssert(inetSocket != NULL);
+    TCP* tcp = inetsocket_asLegacyTcp(inetSocket);
+    MAGIC_ASSERT(tcp);
+
+    tcp->userTimeout.timerIsScheduled = FALSE;
+
+    /* the timeout was disabled, our data was acknowledged, or the connection is already gone */
+    if (tcp->userTimeout.timeout == 0 || tcp->userTimeout.waitingSince == 0 ||
+        tcp->state == TCPS_CLOSED || (tcp->error & TCPE_CONNECTION_RESET)) {
+        return;
+    }
+
+    CSimulationTime now = worker_getCurrentSimulationTime();
+    CSimulationTime expiration =
+        tcp->userTimeout.waitingSince + tcp->userTimeout.timeout * SIMTIME_ONE_MILLISECOND;
+
+    if (expiration > now) {
+        /* the peer acknowledged new data after this timer was scheduled */
+        _tcp_scheduleUserTimeoutTimer(tcp, host, expiration - now);
+        return;
+    }
+
+    _tcp_abortUserTimeout(tcp, host);
+}
+
+/* track how long our outstanding data has been waiting on the peer, and make sure that a timer
+ * will check it against the TCP_USER_TIMEOUT. `progress` should be TRUE if the peer just
+ * acknowledged new data. */
+static void _tcp_updateUserTimeout(TCP* tcp, const Host* host, gboolean progress) {
+    MAGIC_ASSERT(tcp);
+
+    if (tcp->userTimeout.timeout == 0 || tcp_getOutputBufferLength(tcp) == 0 ||
+        (tcp->error & TCPE_CONNECTION_RESET)) {
+        tcp->userTimeout.waitingSince = 0;
+        return;
+    }
+
+    CSimulationTime now = worker_getCurrentSimulationTime();
+
+    if (progress || tcp->userTimeout.waitingSince == 0) {
+        tcp->userTimeout.waitingSince = now;
+    }
+
+    /* an already scheduled timer will reschedule itself if needed */
+    if (!tcp->userTimeout.timerIsScheduled) {
+        CSimulationTime expiration =
+            tcp->userTimeout.waitingSince + tcp->userTimeout.timeout * SIMTIME_ONE_MILLISECOND;
+        _tcp_scheduleUserTimeoutTimer(tcp, host, expiration > now ? expiration - now : 0);
+    }
+}
+
 static gboolean _tcp_isFamilySupported(LegacySocket* socket, sa_family_t family) {
     TCP* tcp = _tcp_fromLegacyFile((LegacyFile*)socket);
     MAGIC_ASSERT(tcp);
@@ -1631,6 +1939,200 @@ gint tcp_getConnectionError(TCP* tcp) {
     }
 }
 
+gint tcp_takeResetError(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+
+    /* a reset of an established connection is reported to the user only once */
+    if ((tcp->error & TCPE_CONNECTION_RESET) && (tcp->flags & TCPF_WAS_ESTABLISHED) &&
+        !(tcp->flags & TCPF_RESET_REPORTED)) {
+        tcp->flags |= TCPF_RESET_REPORTED;
+        return (tcp->error & TCPE_TIMED_OUT) ? -ETIMEDOUT : -ECONNRESET;
+    }
+
+    return 0;
+}
+
+gint tcp_takeUrgentData(TCP* tcp, gboolean peek) {
+    MAGIC_ASSERT(tcp);
+
+    if (tcp->urgent.isInline || !tcp->urgent.byteValid) {
+        return -EINVAL;
+    }
+
+    guchar byte = tcp->urgent.byte;
+
+    if (!peek) {
+        tcp->urgent.byteValid = FALSE;
+        _tcp_updateUrgentStatus(tcp);
+    }
+
+    return byte;
+}
+
+gboolean tcp_getUrgentInline(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+    return tcp->urgent.isInline;
+}
+
+void tcp_setUrgentInline(TCP* tcp, gboolean isInline) {
+    MAGIC_ASSERT(tcp);
+    tcp->urgent.isInline = isInline;
+    _tcp_updateUrgentStatus(tcp);
+}
+
+void tcp_setAbortOnClose(TCP* tcp, gboolean abortOnClose) {
+    MAGIC_ASSERT(tcp);
+    tcp->abortOnClose = abortOnClose;
+}
+
+guint32 tcp_getSocketPriority(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+    return tcp->socketPriority;
+}
+
+void tcp_setSocketPriority(TCP* tcp, guint32 priority) {
+    MAGIC_ASSERT(tcp);
+    tcp->socketPriority = priority;
+}
+
+guint32 tcp_getMark(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+    return tcp->mark;
+}
+
+void tcp_setMark(TCP* tcp, guint32 mark) {
+    MAGIC_ASSERT(tcp);
+    tcp->mark = mark;
+}
+
+/* the initial and maximum SYN-ACK retransmission timeouts in seconds that linux uses to convert
+ * between TCP_DEFER_ACCEPT seconds and retransmissions */
+#define TCP_DEFER_ACCEPT_TIMEOUT_INIT 1
+#define TCP_DEFER_ACCEPT_RTO_MAX 120
+
+/* see secs_to_retrans() in linux's net/ipv4/tcp.c */
+static guchar _tcp_secsToRetrans(gint seconds) {
+    guchar retrans = 0;
+
+    if (seconds > 0) {
+        gint timeout = TCP_DEFER_ACCEPT_TIMEOUT_INIT;
+        gint period = timeout;
+
+        retrans = 1;
+        while (seconds > period && retrans < G_MAXUINT8) {
+            retrans++;
+            timeout = MIN(timeout << 1, TCP_DEFER_ACCEPT_RTO_MAX);
+            period += timeout;
+        }
+    }
+
+    return retrans;
+}
+
+/* see retrans_to_secs() in linux's net/ipv4/tcp.c */
+static gint _tcp_retransToSecs(guchar retrans) {
+    gint period = 0;
+
+    if (retrans > 0) {
+        gint timeout = TCP_DEFER_ACCEPT_TIMEOUT_INIT;
+        period = timeout;
+
+        while (--retrans) {
+            timeout = MIN(timeout << 1, TCP_DEFER_ACCEPT_RTO_MAX);
+            period += timeout;
+        }
+    }
+
+    return period;
+}
+
+gboolean tcp_getQuickAck(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+    return tcp->send.quickACK;
+}
+
+void tcp_setQuickAck(TCP* tcp, gboolean quickAck) {
+    MAGIC_ASSERT(tcp);
+    tcp->send.quickACK = quickAck;
+}
+
+gboolean tcp_getNoDelay(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+    return tcp->nagle.noDelay;
+}
+
+void tcp_setNoDelay(TCP* tcp, const Host* host, gboolean noDelay) {
+    MAGIC_ASSERT(tcp);
+    tcp->nagle.noDelay = noDelay;
+
+    /* like linux, enabling TCP_NODELAY sends any segment that is being held back */
+    if (noDelay) {
+        _tcp_flush(tcp, host);
+    }
+}
+
+gint tcp_getDeferAccept(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+    return _tcp_retransToSecs(tcp->deferAcceptRetrans);
+}
+
+void tcp_setDeferAccept(TCP* tcp, gint seconds) {
+    MAGIC_ASSERT(tcp);
+    tcp->deferAcceptRetrans = _tcp_secsToRetrans(seconds);
+}
+
+gint tcp_getFastOpen(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+    return tcp->fastOpenQueueLength;
+}
+
+void tcp_setFastOpen(TCP* tcp, gint queueLength) {
+    MAGIC_ASSERT(tcp);
+    tcp->fastOpenQueueLength = queueLength;
+}
+
+void tcp_deferSyn(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+    tcp->synDeferred = TRUE;
+}
+
+guint tcp_getUserTimeout(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+    return tcp->userTimeout.timeout;
+}
+
+void tcp_setUserTimeout(TCP* tcp, const Host* host, guint milliseconds) {
+    MAGIC_ASSERT(tcp);
+    tcp->userTimeout.timeout = milliseconds;
+
+    /* the timeout also applies to data that is already outstanding */
+    _tcp_updateUserTimeout(tcp, host, FALSE);
+}
+
+gboolean tcp_refuseConnection(TCP* tcp, gboolean isBlocking) {
+    MAGIC_ASSERT(tcp);
+
+    /* only a connection attempt that hasn't sent a SYN yet can be refused */
+    if (tcp->state != TCPS_CLOSED || (tcp->flags & TCPF_WAS_ESTABLISHED) ||
+        (tcp->error & TCPE_CONNECTION_RESET)) {
+        return FALSE;
+    }
+
+    /* a blocking connect returns the error directly, so like linux it isn't reported again by
+     * SO_ERROR or a later connect */
+    if (isBlocking) {
+        return TRUE;
+    }
+
+    tcp->error |= TCPE_CONNECTION_RESET;
+    tcp->flags |= TCPF_REMOTE_CLOSED;
+
+    /* the connection will never be established, so wake up anyone waiting for it */
+    legacyfile_adjustStatus((LegacyFile*)tcp, FileState_READABLE | FileState_WRITABLE, TRUE, 0);
+
+    return TRUE;
+}
+
 static guint8 _tcp_getTCPInfoState(TCP* tcp) {
     switch(tcp->state) {
         case TCPS_ESTABLISHED: return (guint8) TCP_ESTABLISHED;
@@ -1658,7 +2160,9 @@ void tcp_getInfo(TCP* tcp, struct tcp_info *tcpinfo) {
 //  tcpinfo->tcpi_retransmits;
 //  tcpinfo->tcpi_probes;
 //  tcpinfo->tcpi_backoff;
-//  tcpinfo->tcpi_options;
+    if (tcp->useSACK) {
+        tcpinfo->tcpi_options |= TCPI_OPT_SACK;
+    }
 //  tcpinfo->tcpi_snd_wscale;
 //  tcpinfo->tcpi_rcv_wscale;
 
@@ -1695,7 +2199,8 @@ void tcp_getInfo(TCP* tcp, struct tcp_info *tcpinfo) {
     //  tcpinfo->tcpi_reordering;
 
     tcpinfo->tcpi_rcv_rtt = (u_int32_t)tcp->info.rtt;
-    tcpinfo->tcpi_rcv_space = (u_int32_t)tcp->receive.window;
+    /* the window is measured in packets, but linux reports the receive space in bytes */
+    tcpinfo->tcpi_rcv_space = (u_int32_t)(tcp->receive.window * CONFIG_TCP_MAX_SEGMENT_SIZE);
 
     tcpinfo->tcpi_total_retrans = (u_int32_t)tcp->info.retransmitCount;
 }
@@ -1717,8 +2222,11 @@ static gint _tcp_connectToPeer(LegacySocket* socket, const Host* host, in_addr_t
         return -EISCONN;
     }
 
-    /* send 1st part of 3-way handshake, state->syn_sent */
-    _tcp_sendControlPacket(tcp, host, PTCP_SYN);
+    /* send 1st part of 3-way handshake, state->syn_sent. with MSG_FASTOPEN, the SYN is sent along
+     * with the first user data instead */
+    if (!tcp->synDeferred) {
+        _tcp_sendControlPacket(tcp, host, PTCP_SYN);
+    }
 
     trace("%s <-> %s: user initiated connection", tcp->super.boundString, tcp->super.peerString);
     _tcp_setState(tcp, host, TCPS_SYNSENT);
@@ -1779,9 +2287,9 @@ gint tcp_acceptServerPeer(TCP* tcp, const Host* host, in_addr_t* ip, in_port_t*
     tcp->server->pendingCount -= 1;
 
     MAGIC_ASSERT(tcpChild);
-    if(tcpChild->error == TCPE_CONNECTION_RESET) {
-        return -ECONNABORTED;
-    }
+
+    /* like linux, a child that was reset after it was established is still accepted, and the reset
+     * is reported to the user by the first read or write */
 
     /* better have a peer if we are established */
     utility_debugAssert(tcpChild->super.peerIP && tcpChild->super.peerPort);
@@ -2023,6 +2531,8 @@ TCPProcessFlags _tcp_ackProcessing(TCP* tcp, const Host* host, Packet* packet,
         _tcp_setRetransmitTimer(tcp, host, now);
     }
 
+    _tcp_updateUserTimeout(tcp, host, nPacketsAcked > 0);
+
     tcp->info.lastAckReceived = now;
 
     trace("processing acks returning flags %i", (int)flags);
@@ -2057,11 +2567,65 @@ static void _tcp_sendACKTaskCallback(const Host* host, gpointer voidInetSocket,
         trace("sending a delayed ACK now");
         _tcp_sendControlPacket(tcp, host, PTCP_ACK);
         tcp->send.delayedACKCounter = 0;
+        tcp->send.delayedACKBytes = 0;
     } else {
         trace("delayed ACK was cancelled");
     }
 }
 
+/* queue an established child so that it can be accepted from its parent */
+static void _tcp_setChildPending(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+    MAGIC_ASSERT(tcp->child);
+
+    tcp->child->state = TCPCS_PENDING;
+    g_queue_push_tail(tcp->child->parent->server->pending, tcp);
+    /* user should accept new child from parent */
+    legacyfile_adjustStatus(&(tcp->child->parent->super.super), FileState_READABLE, TRUE, 0);
+}
+
+static void _tcp_runDeferAcceptTimerExpiredTask(const Host* host, gpointer voidInetSocket,
+                                                gpointer userData) {
+    const InetSocket* inetSocket = voidInetSocket;
+    utility_alwaysAssert(inetSocket != NULL);
+    TCP* tcp = inetsocket_asLegacyTcp(inetSocket);
+    MAGIC_ASSERT(tcp);
+
+    /* the peer didn't send any data, but the child can be accepted anyway */
+    if (tcp->child && tcp->child->state == TCPCS_DEFERRED &&
+        tcp->child->parent->state == TCPS_LISTEN) {
+        trace("defer accept period expired; child can now be accepted");
+        _tcp_setChildPending(tcp);
+    }
+}
+
+/* hold back an established child from being accepted until the peer sends data or the parent's
+ * TCP_DEFER_ACCEPT period expires */
+static void _tcp_deferChild(TCP* tcp, const Host* host) {
+    MAGIC_ASSERT(tcp);
+    MAGIC_ASSERT(tcp->child);
+
+    tcp->child->state = TCPCS_DEFERRED;
+
+    utility_alwaysAssert(tcp->rustSocket != NULL);
+    const InetSocket* inetSocket = inetsocketweak_upgrade(tcp->rustSocket);
+    utility_alwaysAssert(inetSocket != NULL);
+    TaskRef* deferTask =
+        taskref_new_bound(host_getID(host), _tcp_runDeferAcceptTimerExpiredTask,
+                          (void*)inetSocket, NULL, inetsocket_dropVoid, NULL);
+    CSimulationTime delay =
+        _tcp_retransToSecs(tcp->child->parent->deferAcceptRetrans) * SIMTIME_ONE_SECOND;
+
+    host_scheduleTaskWithDelay(host, deferTask, delay);
+    taskref_drop(deferTask);
+}
+
+/* the number of ACKs at the start of a connection that are only delayed by the quick delayed ACK
+ * timeout, and the delayed ACK timeouts during and after that period */
+#define TCP_DELAYED_ACK_NUM_QUICK 1000
+#define TCP_DELAYED_ACK_QUICK_TIMEOUT (1 * SIMTIME_ONE_MILLISECOND)
+#define TCP_DELAYED_ACK_TIMEOUT (5 * SIMTIME_ONE_MILLISECOND)
+
 /* return TRUE if the packet should be retransmitted */
 static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* packet) {
     TCP* tcp = _tcp_fromLegacyFile((LegacyFile*)socket);
@@ -2085,6 +2649,7 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
 
         if(!(tcp->state & TCPS_LISTEN) && !(tcp->error & TCPE_CONNECTION_RESET)) {
             tcp->error |= TCPE_CONNECTION_RESET;
+            tcp->error |= TCPE_SEND_EOF;
             tcp->flags |= TCPF_REMOTE_CLOSED;
             legacyfile_adjustStatus((LegacyFile*)tcp, FileState_RDHUP, TRUE, 0);
 
@@ -2092,6 +2657,10 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
 
             /* it will send no more user data after what we have now */
             tcp->receive.end = tcp->receive.next;
+
+            /* wake up any blocked readers and writers so that they can see the reset */
+            legacyfile_adjustStatus(
+                (LegacyFile*)tcp, FileState_READABLE | FileState_WRITABLE, TRUE, 0);
         }
         return;
     }
@@ -2109,6 +2678,7 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
     /* go through the state machine, tracking processing and response */
     TCPProcessFlags flags = TCP_PF_NONE;
     ProtocolTCPFlags responseFlags = PTCP_NONE;
+    gboolean ignoreData = FALSE;
 
     trace("processing packet while in state %s", _tcp_stateToAscii(tcp->state));
 
@@ -2169,9 +2739,27 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
 
                 tcp->server->pendingCount += 1;
 
+                /* like linux, the child inherits the listener's TCP_USER_TIMEOUT, SO_PRIORITY,
+                 * and SO_MARK */
+                multiplexed->userTimeout.timeout = tcp->userTimeout.timeout;
+                multiplexed->socketPriority = tcp->socketPriority;
+                multiplexed->mark = tcp->mark;
+
                 multiplexed->receive.start = header->sequence;
                 multiplexed->receive.next = multiplexed->receive.start + 1;
 
+                /* SACK is only used if both ends permit it */
+                multiplexed->useSACK = multiplexed->useSACK && (header->flags & PTCP_SACK);
+
+                /* a SYN carrying data (TCP fast open) uses its sequence number for the data, so
+                 * the data will be processed below as the next expected packet. if fast open
+                 * isn't enabled on the listener, we ignore the data and don't acknowledge the
+                 * SYN's sequence number so that the peer sends the data again. */
+                if (packetLength > 0) {
+                    multiplexed->receive.next = multiplexed->receive.start;
+                    ignoreData = tcp->fastOpenQueueLength <= 0;
+                }
+
                 trace("%s <-> %s: server multiplexed child socket %s <-> %s",
                         tcp->super.boundString, tcp->super.peerString,
                         multiplexed->super.boundString, multiplexed->super.peerString);
@@ -2193,18 +2781,35 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
                 flags |= TCP_PF_PROCESSED;
                 tcp->receive.start = header->sequence;
                 tcp->receive.next = tcp->receive.start + 1;
+                tcp->useSACK = tcp->useSACK && (header->flags & PTCP_SACK);
 
                 responseFlags |= PTCP_ACK;
                 _tcp_setState(tcp, host, TCPS_ESTABLISHED);
+
+                /* if our SYN carried data (TCP fast open) and the peer didn't accept it, the
+                 * SYN's sequence number wasn't acknowledged, so send the data again now */
+                if(header->acknowledgment == (guint)tcp->send.unacked &&
+                   tcp->send.unacked != tcp->send.next) {
+                    _tcp_retransmitPacket(tcp, host, tcp->send.unacked);
+                }
             }
             /* receive SYN, send ACK, move to SYNRECEIVED (simultaneous open) */
             else if(header->flags & PTCP_SYN) {
                 flags |= TCP_PF_PROCESSED;
                 tcp->receive.start = header->sequence;
                 tcp->receive.next = tcp->receive.start + 1;
+                tcp->useSACK = tcp->useSACK && (header->flags & PTCP_SACK);
 
                 responseFlags |= PTCP_ACK;
-                _tcp_setState(tcp, host, TCPS_SYNRECEIVED);
+
+                /* if the peer already acked our SYN (its ACK crossed with this SYN), then both
+                 * SYNs have been acknowledged once the peer receives our ACK, and the peer will
+                 * not send another ACK that we could wait for in SYNRECEIVED */
+                if(tcp->send.unacked == tcp->send.next) {
+                    _tcp_setState(tcp, host, TCPS_ESTABLISHED);
+                } else {
+                    _tcp_setState(tcp, host, TCPS_SYNRECEIVED);
+                }
             }
 
             break;
@@ -2216,13 +2821,17 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
                 flags |= TCP_PF_PROCESSED;
                 _tcp_setState(tcp, host, TCPS_ESTABLISHED);
 
-                /* if this is a child, mark it accordingly */
+                /* if this is a child, mark it accordingly. with TCP_DEFER_ACCEPT, a child can't
+                 * be accepted until the peer sends data (possibly in its SYN) or the defer period
+                 * expires */
                 if(tcp->child) {
-                    tcp->child->state = TCPCS_PENDING;
-                    g_queue_push_tail(tcp->child->parent->server->pending, tcp);
-                    /* user should accept new child from parent */
-                    legacyfile_adjustStatus(
-                        &(tcp->child->parent->super.super), FileState_READABLE, TRUE, 0);
+                    if(tcp->child->parent->deferAcceptRetrans > 0 && packetLength == 0 &&
+                       !(header->flags & PTCP_FIN) &&
+                       tcp_getInputBufferLength(tcp) == 0) {
+                        _tcp_deferChild(tcp, host);
+                    } else {
+                        _tcp_setChildPending(tcp);
+                    }
                 }
             }
             break;
@@ -2331,10 +2940,16 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
     trace("state after switch is %s", _tcp_stateToAscii(tcp->state));
 
     /* if TCPE_RECEIVE_EOF, we are not supposed to receive any more */
-    if(packetLength > 0 && !(tcp->error & TCPE_RECEIVE_EOF)) {
+    if(packetLength > 0 && !ignoreData && !(tcp->error & TCPE_RECEIVE_EOF)) {
         flags |= _tcp_dataProcessing(tcp, packet, header);
     }
 
+    /* a deferred child can be accepted once the peer sends data or closes */
+    if(tcp->child && tcp->child->state == TCPCS_DEFERRED &&
+       ((flags & TCP_PF_DATA_RECEIVED) || (tcp->flags & TCPF_REMOTE_CLOSED))) {
+        _tcp_setChildPending(tcp);
+    }
+
     if(header->flags & PTCP_ACK) {
         flags |= _tcp_ackProcessing(tcp, host, packet, header);
     }
@@ -2348,7 +2963,7 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
         return;
     }
 
-    if (header->selectiveACKs.len > 0) {
+    if (tcp->useSACK && header->selectiveACKs.len > 0) {
         retransmit_tally_mark_sacked(tcp->retransmit.tally, header->selectiveACKs);
     }
 
@@ -2395,6 +3010,20 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
             /* just send the response now */
             trace("sending ACK control packet now");
             _tcp_sendControlPacket(tcp, host, responseFlags);
+        } else if(tcp->send.quickACK) {
+            /* the user asked for the next ACK to be sent without delay, which also cancels any
+             * delayed ACK that was already scheduled */
+            trace("sending quick ACK control packet now");
+            tcp->send.quickACK = FALSE;
+            _tcp_sendControlPacket(tcp, host, PTCP_ACK);
+        } else if (tcp->send.delayedACKBytes + packetLength > CONFIG_TCP_MAX_SEGMENT_SIZE) {
+            /* like linux, we don't delay the ACK once more than a full segment of data is
+             * unacknowledged (usually when a second segment arrives), which also cancels the
+             * scheduled delayed ACK */
+            trace("sending ACK control packet now for more than one segment of data");
+            tcp->send.delayedACKCounter = 0;
+            tcp->send.delayedACKBytes = 0;
+            _tcp_sendControlPacket(tcp, host, PTCP_ACK);
         } else {
             trace("waiting for delayed ACK control packet");
             if(tcp->send.delayedACKIsScheduled == FALSE) {
@@ -2410,12 +3039,12 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
                 /* figure out what we should use as delay */
                 CSimulationTime delay = 0;
                 /* "quick acknowledgments" happen at the beginning of a connection */
-                if(tcp->send.numQuickACKsSent < 1000) {
+                if(tcp->send.numQuickACKsSent < TCP_DELAYED_ACK_NUM_QUICK) {
                     /* we want the other side to get the ACKs sooner so we don't throttle its sending rate */
-                    delay = 1*SIMTIME_ONE_MILLISECOND;
+                    delay = TCP_DELAYED_ACK_QUICK_TIMEOUT;
                     tcp->send.numQuickACKsSent++;
                 } else {
-                    delay = 5*SIMTIME_ONE_MILLISECOND;
+                    delay = TCP_DELAYED_ACK_TIMEOUT;
                 }
 
                 host_scheduleTaskWithDelay(host, sendACKTask, delay);
@@ -2424,6 +3053,7 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
                 tcp->send.delayedACKIsScheduled = TRUE;
             }
             tcp->send.delayedACKCounter++;
+            tcp->send.delayedACKBytes += packetLength;
         }
     }
 
@@ -2465,9 +3095,15 @@ static void _tcp_endOfFileSignalled(TCP* tcp, enum TCPFlags flags) {
 
 /* Address and port must be in network byte order. */
 gssize tcp_sendUserData(TCP* tcp, const Host* host, UntypedForeignPtr buffer, gsize nBytes,
-                        in_addr_t ip, in_port_t port, const MemoryManager* mem) {
+                        in_addr_t ip, in_port_t port, gboolean urgent, const MemoryManager* mem) {
     MAGIC_ASSERT(tcp);
 
+    /* the first send after a reset reports the reset */
+    gint resetError = tcp_takeResetError(tcp);
+    if (resetError < 0) {
+        return resetError;
+    }
+
     /* return 0 to signal close, if necessary */
     if(tcp->error & TCPE_SEND_EOF)
     {
@@ -2480,10 +3116,7 @@ gssize tcp_sendUserData(TCP* tcp, const Host* host, UntypedForeignPtr buffer, gs
         }
     }
 
-    /* maximum data we can send network, o/w tcp truncates and only sends 65536*/
-    gsize acceptable = MIN(nBytes, 65535);
-    gsize space = _tcp_getBufferSpaceOut(tcp);
-    gsize remaining = MIN(acceptable, space);
+    gsize remaining = MIN(nBytes, tcp_getSendableLength(tcp));
 
     /* break data into segments and send each in a packet */
     gsize maxPacketLength = CONFIG_TCP_MAX_SEGMENT_SIZE;
@@ -2500,12 +3133,40 @@ gssize tcp_sendUserData(TCP* tcp, const Host* host, UntypedForeignPtr buffer, gs
         return -EFAULT;
     }
 
+    /* fill up the small segment that hasn't been sent yet before creating new segments */
+    Packet* partialSegment = tcp->nagle.partialSegment;
+    if (partialSegment != NULL && remaining > 0 && !urgent) {
+        gsize partialLength = packet_getPayloadSize(partialSegment);
+        gsize copyLength = MIN(maxPacketLength - partialLength, remaining);
+
+        packet_appendPayloadWithMemoryManager(partialSegment, buffer, copyLength, mem);
+
+        /* the packet takes up more space */
+        tcp->throttledOutputLength += copyLength;
+        if (_tcp_getBufferSpaceOut(tcp) == 0) {
+            legacyfile_adjustStatus((LegacyFile*)tcp, FileState_WRITABLE, FALSE, 0);
+        }
+
+        if (partialLength + copyLength >= maxPacketLength) {
+            _tcp_clearPartialSegment(tcp);
+        }
+
+        remaining -= copyLength;
+        bytesCopied += copyLength;
+    }
+
     /* create as many packets as needed */
     while(remaining > 0) {
         gsize copyLength = MIN(maxPacketLength, remaining);
 
+        /* the urgent byte is the last byte of the data, so mark the last packet as urgent */
+        ProtocolTCPFlags flags = PTCP_ACK;
+        if (urgent && copyLength == remaining) {
+            flags |= PTCP_URG;
+        }
+
         /* use helper to create the packet */
-        Packet* packet = _tcp_createDataPacket(tcp, host, PTCP_ACK,
+        Packet* packet = _tcp_createDataPacket(tcp, host, flags,
                                                (UntypedForeignPtr){.val = buffer.val + bytesCopied},
                                                copyLength, mem);
 
@@ -2517,6 +3178,13 @@ gssize tcp_sendUserData(TCP* tcp, const Host* host, UntypedForeignPtr buffer, gs
         /* buffer the outgoing packet in TCP */
         _tcp_bufferPacketOut(tcp, packet);
 
+        /* remember a small segment so that more user data can be appended to it later */
+        _tcp_clearPartialSegment(tcp);
+        if (copyLength < maxPacketLength && !(flags & PTCP_URG)) {
+            packet_ref(packet);
+            tcp->nagle.partialSegment = packet;
+        }
+
         /* the output buffer holds the packet ref now */
         packet_unref(packet);
 
@@ -2529,9 +3197,67 @@ gssize tcp_sendUserData(TCP* tcp, const Host* host, UntypedForeignPtr buffer, gs
     /* now flush as much as possible out to socket */
     _tcp_flush(tcp, host);
 
+    _tcp_updateUserTimeout(tcp, host, FALSE);
+
     return (gssize)(bytesCopied == 0 && nBytes != 0 ? -EWOULDBLOCK : bytesCopied);
 }
 
+gssize tcp_sendFastOpenData(TCP* tcp, const Host* host, const UntypedForeignPtr* buffers,
+                            const gsize* lengths, gsize count, const MemoryManager* mem) {
+    MAGIC_ASSERT(tcp);
+    utility_debugAssert(tcp->synDeferred);
+
+    /* check the buffers before we change any connection state */
+    gsize total = 0;
+    for (gsize i = 0; i < count; i++) {
+        if (buffers[i].val == 0 && lengths[i] > 0) {
+            return -EFAULT;
+        }
+        total += lengths[i];
+    }
+
+    /* the SYN carries a single segment, and we don't send anything else until the handshake
+     * completes */
+    gsize length = MIN(total, MIN(_tcp_getBufferSpaceOut(tcp), CONFIG_TCP_MAX_SEGMENT_SIZE));
+
+    tcp->synDeferred = FALSE;
+
+    if (length == 0) {
+        /* there's no data for the SYN to carry, but we still need to connect */
+        _tcp_sendControlPacket(tcp, host, PTCP_SYN);
+        return 0;
+    }
+
+    /* the SYN and its data share a single sequence number, so the peer acknowledges both at once
+     * if it accepts the data, or neither if it doesn't (see `_tcp_processPacket`) */
+    Packet* packet = _tcp_createPacketWithoutPayload(tcp, host, PTCP_SYN, /*isEmpty=*/false);
+
+    gsize bytesCopied = 0;
+    for (gsize i = 0; i < count && bytesCopied < length; i++) {
+        gsize copyLength = MIN(lengths[i], length - bytesCopied);
+        if (copyLength > 0) {
+            packet_appendPayloadWithMemoryManager(packet, buffers[i], copyLength, mem);
+            bytesCopied += copyLength;
+        }
+    }
+
+    /* we are sending more user data */
+    tcp->send.end++;
+
+    /* buffer the outgoing packet in TCP, which holds the packet ref now */
+    _tcp_bufferPacketOut(tcp, packet);
+    packet_unref(packet);
+
+    trace("%s <-> %s: sending %" G_GSIZE_FORMAT " user bytes in SYN", tcp->super.boundString,
+          tcp->super.peerString, bytesCopied);
+
+    _tcp_flush(tcp, host);
+
+    _tcp_updateUserTimeout(tcp, host, FALSE);
+
+    return (gssize)bytesCopied;
+}
+
 static void _tcp_sendWindowUpdate(const Host* host, gpointer voidInetSocket, gpointer data) {
     const InetSocket* inetSocket = voidInetSocket;
     utility_alwaysAssert(inetSocket != NULL);
@@ -2564,15 +3290,26 @@ gssize tcp_receiveUserData(TCP* tcp, const Host* host, UntypedForeignPtr buffer,
     /* make sure we pull in all readable user data */
     _tcp_flush(tcp, host);
 
+    /* in case SO_OOBINLINE was unset while the urgent byte was next in the data stream */
+    _tcp_skipUrgentByteIfAtMark(tcp, host);
+
     gsize remaining = nBytes;
     gsize totalCopied = 0;
     gsize offset = 0;
     gsize copyLength = 0;
 
     if ((legacysocket_getInputBufferLength(&tcp->super) == 0) &&
-        (tcp->partialUserDataPacket == NULL) && !(tcp->error & TCPE_RECEIVE_EOF)) {
-        // there is no data, and we have not received an EOF
-        return -EWOULDBLOCK;
+        (tcp->partialUserDataPacket == NULL)) {
+        // there is no data, so report a reset if there was one
+        gint resetError = tcp_takeResetError(tcp);
+        if (resetError < 0) {
+            return resetError;
+        }
+
+        if (!(tcp->error & TCPE_RECEIVE_EOF)) {
+            // there is no data, and we have not received an EOF
+            return -EWOULDBLOCK;
+        }
     }
 
     if (buffer.val == 0 && nBytes > 0) {
@@ -2580,6 +3317,11 @@ gssize tcp_receiveUserData(TCP* tcp, const Host* host, UntypedForeignPtr buffer,
         return -EFAULT;
     }
 
+    /* reads stop at the urgent mark */
+    if (tcp->urgent.markPending && tcp->urgent.markOffset > 0) {
+        remaining = MIN(remaining, tcp->urgent.markOffset);
+    }
+
     /* check if we have a partial packet waiting to get finished */
     if(remaining > 0 && tcp->partialUserDataPacket) {
         gsize partialLength = packet_getPayloadSize(tcp->partialUserDataPacket);
@@ -2655,6 +3397,17 @@ gssize tcp_receiveUserData(TCP* tcp, const Host* host, UntypedForeignPtr buffer,
         packet_unref(packet);
     }
 
+    if (tcp->urgent.markPending) {
+        if (tcp->urgent.markOffset > 0) {
+            tcp->urgent.markOffset -= totalCopied;
+            _tcp_skipUrgentByteIfAtMark(tcp, host);
+        } else if (totalCopied > 0) {
+            /* the inline urgent byte was just read */
+            tcp->urgent.markPending = FALSE;
+        }
+        _tcp_updateUrgentStatus(tcp);
+    }
+
     bool more_readable_data = false;
 
     /* now we update readability of the socket */
@@ -2743,6 +3496,7 @@ static void _tcp_free(LegacyFile* descriptor) {
     priorityqueue_free(tcp->unorderedInput);
     g_hash_table_destroy(tcp->retransmit.queue);
     priorityqueue_free(tcp->retransmit.scheduledTimerExpirations);
+    _tcp_clearPartialSegment(tcp);
 
     if (tcp->partialUserDataPacket != NULL) {
         packet_unref(tcp->partialUserDataPacket);
@@ -2798,11 +3552,18 @@ static void _tcp_close(LegacyFile* descriptor, const Host* host) {
         case TCPS_SYNRECEIVED:
         case TCPS_ESTABLISHED:
         case TCPS_CLOSEWAIT: {
-            if(tcp_getOutputBufferLength(tcp) == 0) {
+            if (tcp->abortOnClose) {
+                trace("%s <-> %s: closed with a zero linger timeout, sending reset",
+                      tcp->super.boundString, tcp->super.peerString);
+                _tcp_sendControlPacket(tcp, host, PTCP_RST);
+                _tcp_setState(tcp, host, TCPS_CLOSED);
+            } else if(tcp_getOutputBufferLength(tcp) == 0) {
                 _tcp_sendShutdownFin(tcp, host);
             } else {
                 /* we still have data. send that first, and then finish with fin */
                 tcp->flags |= TCPF_SHOULD_SEND_WR_FIN;
+                /* a small segment no longer needs to be held back */
+                _tcp_flush(tcp, host);
             }
             return;
         }
@@ -2851,6 +3612,10 @@ gint tcp_shutdown(TCP* tcp, const Host* host, gint how) {
         }
     }
 
+    /* a small segment no longer needs to be held back, and the readable and writable status
+     * needs to be updated so that blocked reads and writes wake up */
+    _tcp_flush(tcp, host);
+
     return 0;
 }
 
@@ -2922,6 +3687,8 @@ TCP* tcp_new(const Host* host, guint receiveBufferSize, guint sendBufferSize) {
 
     tcp->autotune.isEnabled = TRUE;
 
+    tcp->useSACK = host_useTcpSack(host);
+
     tcp->throttledOutput = priorityqueue_new((GCompareDataFunc)packet_compareTCPSequence, NULL,
                                              (GDestroyNotify)packet_unref, NULL, NULL);
     tcp->unorderedInput = priorityqueue_new((GCompareDataFunc)packet_compareTCPSequence, NULL,
@@ -64,6 +64,68 @@ void tcp_setRustSocket(TCP* tcp, InetSocketWeak* rustSocket);
 gint tcp_getConnectionError(TCP* tcp);
 // clang-format on
 
+/* Returns -ECONNRESET if the established connection was reset by the peer and the reset hasn't
+ * been reported yet, otherwise returns 0. A reset is only reported once. */
+gint tcp_takeResetError(TCP* tcp);
+
+/* Returns the urgent (out-of-band) byte if one is waiting to be read, otherwise returns -EINVAL.
+ * The byte remains available if `peek` is TRUE. */
+gint tcp_takeUrgentData(TCP* tcp, gboolean peek);
+
+/* Get or set whether urgent data is delivered in the normal data stream (SO_OOBINLINE). */
+gboolean tcp_getUrgentInline(TCP* tcp);
+void tcp_setUrgentInline(TCP* tcp, gboolean isInline);
+void tcp_setAbortOnClose(TCP* tcp, gboolean abortOnClose);
+
+/* Get or set the priority given to the packets that the socket sends (SO_PRIORITY). */
+guint32 tcp_getSocketPriority(TCP* tcp);
+void tcp_setSocketPriority(TCP* tcp, guint32 priority);
+
+/* Get or set the mark given to the packets that the socket sends (SO_MARK). */
+guint32 tcp_getMark(TCP* tcp);
+void tcp_setMark(TCP* tcp, guint32 mark);
+
+/* Get or set whether the next ACK will be sent immediately instead of being delayed (TCP_QUICKACK).
+ * This is reset once the ACK has been sent. */
+gboolean tcp_getQuickAck(TCP* tcp);
+void tcp_setQuickAck(TCP* tcp, gboolean quickAck);
+
+/* Get or set whether Nagle's algorithm is disabled (TCP_NODELAY). When enabled, small segments are
+ * held back while earlier data is unacknowledged so that later writes can be coalesced into them.
+ * Disabling the algorithm sends any segment that is being held back. */
+gboolean tcp_getNoDelay(TCP* tcp);
+void tcp_setNoDelay(TCP* tcp, const Host* host, gboolean noDelay);
+
+/* Get or set the number of seconds that a listening socket waits for data on a new connection
+ * before it can be accepted (TCP_DEFER_ACCEPT). The value is rounded up to a whole number of SYN-ACK
+ * retransmissions like in linux, so the returned value may be larger than the value that was set. */
+gint tcp_getDeferAccept(TCP* tcp);
+void tcp_setDeferAccept(TCP* tcp, gint seconds);
+
+/* Get or set the queue length of a listening socket for connections that send data in their SYN
+ * (TCP_FASTOPEN). Data in a SYN is only accepted if the queue length is positive. */
+gint tcp_getFastOpen(TCP* tcp);
+void tcp_setFastOpen(TCP* tcp, gint queueLength);
+
+/* Get or set the number of milliseconds that sent data may remain unacknowledged before the
+ * connection is aborted with ETIMEDOUT (TCP_USER_TIMEOUT), or 0 if it never times out. */
+guint tcp_getUserTimeout(TCP* tcp);
+void tcp_setUserTimeout(TCP* tcp, const Host* host, guint milliseconds);
+
+/* Don't send the SYN for the next connection attempt until user data is sent, so that the SYN can
+ * carry the first segment of data (MSG_FASTOPEN). */
+void tcp_deferSyn(TCP* tcp);
+
+/* Send the deferred SYN, carrying up to one segment of data gathered from the `count` buffers.
+ * Returns the number of bytes carried by the SYN, or a negative errno. */
+gssize tcp_sendFastOpenData(TCP* tcp, const Host* host, const UntypedForeignPtr* buffers,
+                            const gsize* lengths, gsize count, const MemoryManager* mem);
+
+/* Fail a connection attempt before it starts, as if the peer had responded to our SYN with a RST.
+ * Returns FALSE and does nothing if a connection attempt was already made. The error is kept to be
+ * reported later only if the socket is non-blocking. */
+gboolean tcp_refuseConnection(TCP* tcp, gboolean isBlocking);
+
 void tcp_getInfo(TCP* tcp, struct tcp_info *tcpinfo);
 void tcp_enterServerMode(TCP* tcp, const Host* host, pid_t process, gint backlog);
 void tcp_updateServerBacklog(TCP* tcp, gint backlog);
@@ -78,6 +140,7 @@ void tcp_clearAllChildrenIfServer(TCP* tcp);
 gsize tcp_getOutputBufferLength(TCP* tcp);
 gsize tcp_getInputBufferLength(TCP* tcp);
 gsize tcp_getNotSentBytes(TCP* tcp);
+gsize tcp_getSendableLength(TCP* tcp);
 
 void tcp_disableSendBufferAutotuning(TCP* tcp);
 void tcp_disableReceiveBufferAutotuning(TCP* tcp);
@@ -86,7 +149,7 @@ gboolean tcp_isValidListener(TCP* tcp);
 gboolean tcp_isListeningAllowed(TCP* tcp);
 
 gssize tcp_sendUserData(TCP* tcp, const Host* host, UntypedForeignPtr buffer, gsize nBytes,
-                        in_addr_t ip, in_port_t port, const MemoryManager* mem);
+                        in_addr_t ip, in_port_t port, gboolean urgent, const MemoryManager* mem);
 gssize tcp_receiveUserData(TCP* tcp, const Host* host, UntypedForeignPtr buffer, gsize nBytes,
                            in_addr_t* ip, in_port_t* port, MemoryManager* mem);
 
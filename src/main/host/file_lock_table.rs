@@ -0,0 +1,364 @@
+//! Advisory record locks, as used with `fcntl(F_SETLK)` and friends.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use linux_api::errno::Errno;
+
+use crate::cshadow as c;
+use crate::host::descriptor::listener::StateEventSource;
+use crate::host::descriptor::{FileSignals, FileState};
+use crate::host::process::ProcessId;
+use crate::host::thread::ThreadId;
+use crate::utility::callback_queue::CallbackQueue;
+use crate::utility::{HostTreePointer, ObjectCounter};
+
+/// Identifies a file by its device and inode numbers, so that locks placed through different open
+/// file descriptions of the same file will conflict with each other.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FileId {
+    pub dev: u64,
+    pub ino: u64,
+}
+
+impl From<&libc::stat> for FileId {
+    fn from(stat: &libc::stat) -> Self {
+        Self {
+            dev: stat.st_dev,
+            ino: stat.st_ino,
+        }
+    }
+}
+
+/// Returns the stat of the native file that backs a legacy regular file.
+///
+/// # Safety
+///
+/// `file` must be a valid [`RegularFile`](c::RegularFile) pointer.
+pub unsafe fn regular_file_stat(file: *mut c::RegularFile) -> Result<libc::stat, Errno> {
+    let fd = unsafe { c::regularfile_getOSBackedFD(file) };
+    if fd < 0 {
+        return Err(Errno::EBADF);
+    }
+
+    let mut stat = unsafe { std::mem::zeroed() };
+    Errno::result_from_libc_errno(-1, unsafe { libc::fstat(fd, &mut stat) })?;
+    Ok(stat)
+}
+
+/// Identifies an open file description. Unlike the address of the file object, which may be reused
+/// once the file is freed, an id is never reused.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct OpenFileId(u64);
+
+impl OpenFileId {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// The owner of a record lock.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LockOwner {
+    /// A traditional POSIX record lock (ex: `F_SETLK`), which is owned by a process.
+    Process(ProcessId),
+    /// An open file description lock (ex: `F_OFD_SETLK`), which is owned by an open file
+    /// description.
+    OpenFile(OpenFileId),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LockType {
+    Read,
+    Write,
+}
+
+impl LockType {
+    fn conflicts_with(self, other: LockType) -> bool {
+        self == LockType::Write || other == LockType::Write
+    }
+}
+
+/// A lock over the byte range `[start, end)`. An `end` of `None` means that the lock extends to the
+/// end of the file, no matter how large the file grows.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RecordLock {
+    pub owner: LockOwner,
+    pub lock_type: LockType,
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+impl RecordLock {
+    /// Returns true if the lock overlaps with the byte range `[start, end)`.
+    fn overlaps(&self, start: u64, end: Option<u64>) -> bool {
+        end.is_none_or(|end| self.start < end) && self.end.is_none_or(|self_end| start < self_end)
+    }
+
+    /// Returns true if the lock overlaps with or is adjacent to the byte range `[start, end)`.
+    fn touches(&self, start: u64, end: Option<u64>) -> bool {
+        end.is_none_or(|end| self.start <= end) && self.end.is_none_or(|self_end| start <= self_end)
+    }
+}
+
+/// A process that has a thread blocked in `F_SETLKW`, waiting for a lock held by another process.
+#[derive(Copy, Clone, Debug)]
+struct LockWaiter {
+    owner: ProcessId,
+    blocked_on: ProcessId,
+}
+
+/// The record locks held on all files of a host.
+pub struct FileLockTable {
+    locks: HashMap<FileId, Vec<RecordLock>>,
+    /// The threads that are blocked waiting for a lock, used to detect deadlocks.
+    waiters: HashMap<ThreadId, LockWaiter>,
+    /// Notified whenever a lock is released or downgraded, so that blocked `F_SETLKW` calls can
+    /// retry.
+    release_listeners: StateEventSource,
+    _counter: ObjectCounter,
+}
+
+impl FileLockTable {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            locks: HashMap::new(),
+            waiters: HashMap::new(),
+            release_listeners: StateEventSource::new(),
+            _counter: ObjectCounter::new("FileLockTable"),
+        }
+    }
+
+    /// Returns true if there are no locks held on any files.
+    pub fn is_empty(&self) -> bool {
+        self.locks.is_empty()
+    }
+
+    /// Returns a lock held by a different owner that would conflict with a lock of type
+    /// `lock_type` over the byte range `[start, end)`.
+    pub fn conflicting_lock(
+        &self,
+        file: FileId,
+        owner: LockOwner,
+        lock_type: LockType,
+        start: u64,
+        end: Option<u64>,
+    ) -> Option<RecordLock> {
+        self.locks
+            .get(&file)?
+            .iter()
+            .find(|lock| {
+                lock.owner != owner
+                    && lock.lock_type.conflicts_with(lock_type)
+                    && lock.overlaps(start, end)
+            })
+            .copied()
+    }
+
+    /// Set the owner's lock over the byte range `[start, end)`, or remove it if `lock_type` is
+    /// `None`. Any of the owner's existing locks within the range are replaced, and the new lock is
+    /// merged with any of the owner's adjacent or overlapping locks of the same type. If a
+    /// different owner holds a conflicting lock, that lock is returned as an error and nothing is
+    /// changed.
+    pub fn set(
+        &mut self,
+        file: FileId,
+        owner: LockOwner,
+        lock_type: Option<LockType>,
+        mut start: u64,
+        mut end: Option<u64>,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<(), RecordLock> {
+        if let Some(lock_type) = lock_type {
+            if let Some(conflict) = self.conflicting_lock(file, owner, lock_type, start, end) {
+                return Err(conflict);
+            }
+        }
+
+        let locks = self.locks.entry(file).or_default();
+
+        // extend the range to cover any of the owner's locks of the same type that it touches, so
+        // that they're merged into a single lock
+        if let Some(lock_type) = lock_type {
+            loop {
+                let mut extended = false;
+                for lock in locks.iter() {
+                    if lock.owner == owner
+                        && lock.lock_type == lock_type
+                        && lock.touches(start, end)
+                        && (lock.start < start
+                            || end.is_some_and(|end| lock.end.is_none_or(|x| x > end)))
+                    {
+                        start = std::cmp::min(start, lock.start);
+                        end = end.zip(lock.end).map(|(a, b)| std::cmp::max(a, b));
+                        extended = true;
+                    }
+                }
+                if !extended {
+                    break;
+                }
+            }
+        }
+
+        let mut released = false;
+        let mut new_locks = Vec::with_capacity(locks.len() + 2);
+
+        for lock in locks.drain(..) {
+            if lock.owner != owner || !lock.overlaps(start, end) {
+                new_locks.push(lock);
+                continue;
+            }
+
+            // a read lock that's replaced by a write lock over the same range isn't a release, but
+            // any other change may allow a waiting lock to be acquired
+            if lock_type != Some(LockType::Write) || lock.lock_type != LockType::Read {
+                released = true;
+            }
+
+            // keep the parts of the existing lock that are outside of the range
+            if lock.start < start {
+                new_locks.push(RecordLock {
+                    end: Some(start),
+                    ..lock
+                });
+            }
+            if let Some(end) = end {
+                if lock.end.is_none_or(|x| x > end) {
+                    new_locks.push(RecordLock { start: end, ..lock });
+                }
+            }
+        }
+
+        if let Some(lock_type) = lock_type {
+            new_locks.push(RecordLock {
+                owner,
+                lock_type,
+                start,
+                end,
+            });
+        }
+
+        if new_locks.is_empty() {
+            self.locks.remove(&file);
+        } else {
+            *locks = new_locks;
+        }
+
+        if released {
+            self.notify_released(cb_queue);
+        }
+
+        Ok(())
+    }
+
+    /// Remove all of the owner's locks on the file.
+    pub fn release_file_owner(
+        &mut self,
+        file: FileId,
+        owner: LockOwner,
+        cb_queue: &mut CallbackQueue,
+    ) {
+        let Some(locks) = self.locks.get_mut(&file) else {
+            return;
+        };
+
+        let num_locks = locks.len();
+        locks.retain(|lock| lock.owner != owner);
+        let released = locks.len() != num_locks;
+
+        if locks.is_empty() {
+            self.locks.remove(&file);
+        }
+
+        if released {
+            self.notify_released(cb_queue);
+        }
+    }
+
+    /// Returns true if blocking `owner` until `blocked_on` releases its lock would deadlock,
+    /// meaning that `blocked_on` is already waiting (directly or through other processes) for a
+    /// lock held by `owner`. Like linux, only locks owned by processes are checked, so open file
+    /// description locks never deadlock.
+    pub fn would_deadlock(&self, owner: LockOwner, blocked_on: LockOwner) -> bool {
+        let (LockOwner::Process(owner), LockOwner::Process(blocked_on)) = (owner, blocked_on)
+        else {
+            return false;
+        };
+
+        let mut visited = HashSet::new();
+        let mut to_visit = vec![blocked_on];
+
+        while let Some(pid) = to_visit.pop() {
+            if pid == owner {
+                return true;
+            }
+            if !visited.insert(pid) {
+                continue;
+            }
+
+            // a process with several blocked threads may be waiting on several processes
+            to_visit.extend(
+                self.waiters
+                    .values()
+                    .filter(|waiter| waiter.owner == pid)
+                    .map(|waiter| waiter.blocked_on),
+            );
+        }
+
+        false
+    }
+
+    /// Record that `thread` is blocked until `blocked_on` releases its lock. The thread must be
+    /// removed using [`remove_waiter`](Self::remove_waiter) when it tries again.
+    pub fn add_waiter(&mut self, thread: ThreadId, owner: LockOwner, blocked_on: LockOwner) {
+        if let (LockOwner::Process(owner), LockOwner::Process(blocked_on)) = (owner, blocked_on) {
+            self.waiters
+                .insert(thread, LockWaiter { owner, blocked_on });
+        }
+    }
+
+    /// Record that `thread` is no longer blocked waiting for a lock.
+    pub fn remove_waiter(&mut self, thread: ThreadId) {
+        self.waiters.remove(&thread);
+    }
+
+    /// Remove all of the owner's locks on all files.
+    pub fn release_owner(&mut self, owner: LockOwner, cb_queue: &mut CallbackQueue) {
+        // the owner's threads are no longer waiting for any locks
+        self.waiters
+            .retain(|_thread, waiter| LockOwner::Process(waiter.owner) != owner);
+
+        let mut released = false;
+
+        self.locks.retain(|_file, locks| {
+            let num_locks = locks.len();
+            locks.retain(|lock| lock.owner != owner);
+            released |= locks.len() != num_locks;
+            !locks.is_empty()
+        });
+
+        if released {
+            self.notify_released(cb_queue);
+        }
+    }
+
+    pub fn add_legacy_listener(&mut self, ptr: HostTreePointer<c::StatusListener>) {
+        self.release_listeners.add_legacy_listener(ptr);
+    }
+
+    pub fn remove_legacy_listener(&mut self, ptr: *mut c::StatusListener) {
+        self.release_listeners.remove_legacy_listener(ptr);
+    }
+
+    fn notify_released(&mut self, cb_queue: &mut CallbackQueue) {
+        self.release_listeners.notify_listeners(
+            FileState::LOCK_RELEASED,
+            FileState::LOCK_RELEASED,
+            FileSignals::empty(),
+            cb_queue,
+        );
+    }
+}
@@ -38,6 +38,7 @@ use crate::core::worker::Worker;
 use crate::cshadow;
 use crate::host::descriptor::socket::abstract_unix_ns::AbstractUnixNamespace;
 use crate::host::descriptor::socket::inet::InetSocket;
+use crate::host::file_lock_table::FileLockTable;
 use crate::host::futex_table::FutexTable;
 use crate::host::network::interface::{FifoPacketPriority, NetworkInterface, PcapOptions};
 use crate::host::network::namespace::NetworkNamespace;
@@ -66,6 +67,7 @@ pub struct HostParameters {
     pub cpu_precision: Option<SimulationTime>,
     pub log_level: LogLevel,
     pub pcap_config: Option<PcapConfig>,
+    pub tcp_sack: bool,
     pub qdisc: QDiscMode,
     pub init_sock_recv_buf_size: u64,
     pub autotune_recv_buf: bool,
@@ -131,6 +133,9 @@ pub struct Host {
     // map address to futex objects
     futex_table: RefCell<FutexTable>,
 
+    // advisory record locks held on files
+    file_lock_table: RefCell<FileLockTable>,
+
     #[cfg(feature = "perf_timers")]
     execution_timer: RefCell<PerfTimer>,
 
@@ -262,7 +267,7 @@ impl Host {
             capture_size_bytes: x.capture_size.try_into().unwrap(),
         });
 
-        let net_ns = NetworkNamespace::new(public_ip, pcap_options, params.qdisc);
+        let net_ns = NetworkNamespace::new(public_ip, pcap_options, params.qdisc, params.node_seed);
 
         // Packets that are not for localhost or our public ip go to the router.
         // Use `Ipv4Addr::UNSPECIFIED` for the router to encode this for our
@@ -293,6 +298,7 @@ impl Host {
             relay_inet_in: Arc::new(relay_inet_in),
             relay_loopback: Arc::new(relay_loopback),
             futex_table: RefCell::new(FutexTable::new()),
+            file_lock_table: RefCell::new(FileLockTable::new()),
             random,
             shim_shmem,
             shim_shmem_lock: RefCell::new(None),
@@ -611,6 +617,16 @@ impl Host {
         self.futex_table.borrow_mut()
     }
 
+    #[track_caller]
+    pub fn file_lock_table_borrow(&self) -> impl Deref<Target = FileLockTable> + '_ {
+        self.file_lock_table.borrow()
+    }
+
+    #[track_caller]
+    pub fn file_lock_table_borrow_mut(&self) -> impl DerefMut<Target = FileLockTable> + '_ {
+        self.file_lock_table.borrow_mut()
+    }
+
     #[allow(non_snake_case)]
     pub fn bw_up_kiBps(&self) -> u64 {
         self.params.requested_bw_up_bits / (8 * 1024)
@@ -981,7 +997,9 @@ mod export {
 
     use super::*;
     use crate::cshadow::{CEmulatedTime, CSimulationTime};
+    use crate::host::descriptor::socket::inet::InetSocketWeak;
     use crate::network::packet::IanaProtocol;
+    use crate::utility::HostTreePointer;
 
     #[unsafe(no_mangle)]
     pub unsafe extern "C-unwind" fn host_execute(hostrc: *const Host, until: CEmulatedTime) {
@@ -1055,6 +1073,12 @@ mod export {
         hostrc.params.autotune_send_buf
     }
 
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C-unwind" fn host_useTcpSack(hostrc: *const Host) -> bool {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc.params.tcp_sack
+    }
+
     #[unsafe(no_mangle)]
     pub unsafe extern "C-unwind" fn host_getConfiguredRecvBufSize(hostrc: *const Host) -> u64 {
         let hostrc = unsafe { hostrc.as_ref().unwrap() };
@@ -1096,6 +1120,7 @@ mod export {
     #[unsafe(no_mangle)]
     pub unsafe extern "C-unwind" fn host_disassociateInterface(
         hostrc: *const Host,
+        socket: *const InetSocketWeak,
         c_protocol: cshadow::ProtocolType,
         bind_ip: in_addr_t,
         bind_port: in_port_t,
@@ -1104,6 +1129,13 @@ mod export {
     ) {
         let hostrc = unsafe { hostrc.as_ref().unwrap() };
 
+        // every legacy tcp socket (including child sockets) is given its rust socket when it's
+        // created, so this should never be null, but if it is then the socket can't have been
+        // associated
+        let Some(socket) = (unsafe { socket.as_ref() }) else {
+            return;
+        };
+
         let bind_ip = Ipv4Addr::from(u32::from_be(bind_ip));
         let peer_ip = Ipv4Addr::from(u32::from_be(peer_ip));
         let bind_port = u16::from_be(bind_port);
@@ -1114,10 +1146,10 @@ mod export {
 
         let protocol = IanaProtocol::from(c_protocol);
 
-        // associate the interfaces corresponding to bind_addr with socket
+        // disassociate the interfaces corresponding to bind_addr from socket
         hostrc
             .net_ns
-            .disassociate_interface(protocol, bind_addr, peer_addr);
+            .disassociate_interface(socket, protocol, bind_addr, peer_addr);
     }
 
     #[unsafe(no_mangle)]
@@ -1161,6 +1193,29 @@ mod export {
         &mut *hostrc.futextable_borrow_mut()
     }
 
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C-unwind" fn host_addFileLockListener(
+        hostrc: *const Host,
+        listener: *mut cshadow::StatusListener,
+    ) {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        let listener = HostTreePointer::new_for_host(hostrc.id(), listener);
+        hostrc
+            .file_lock_table_borrow_mut()
+            .add_legacy_listener(listener);
+    }
+
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C-unwind" fn host_removeFileLockListener(
+        hostrc: *const Host,
+        listener: *mut cshadow::StatusListener,
+    ) {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc
+            .file_lock_table_borrow_mut()
+            .remove_legacy_listener(listener);
+    }
+
     /// Returns the specified process, or NULL if it doesn't exist.
     #[unsafe(no_mangle)]
     pub unsafe extern "C-unwind" fn host_getProcess(
@@ -246,6 +246,7 @@ impl ManagedThread {
                         ctx.host,
                         &ShimEventToShim::StartRes(ShimEventStartRes {
                             auxvec_random: ctx.host.random_mut().random(),
+                            umask: ctx.process.umask(),
                         }),
                     )
                 }
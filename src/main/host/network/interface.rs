@@ -2,13 +2,14 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::collections::hash_map::Entry;
 use std::fs::File;
+use std::hash::Hasher;
 use std::io::BufWriter;
 use std::net::{Ipv4Addr, SocketAddrV4};
 use std::path::PathBuf;
 
 use crate::core::configuration::QDiscMode;
 use crate::core::worker::Worker;
-use crate::host::descriptor::socket::inet::InetSocket;
+use crate::host::descriptor::socket::inet::{InetSocket, InetSocketWeak};
 use crate::host::network::queuing::{NetworkQueue, NetworkQueueKind};
 use crate::network::PacketDevice;
 use crate::network::packet::{IanaProtocol, PacketRc, PacketStatus};
@@ -42,6 +43,35 @@ impl AssociatedSocketKey {
     }
 }
 
+/// The sockets associated with a single key. There will only be more than one socket if every
+/// socket was associated with `SO_REUSEPORT` enabled.
+struct AssociatedSockets {
+    sockets: Vec<InetSocket>,
+    reuse_port: bool,
+}
+
+impl AssociatedSockets {
+    /// Choose the socket that should receive packets from `peer`. Sockets in a reuseport group are
+    /// chosen by hashing the peer's address with a seed, so that all packets from a given peer are
+    /// received by the same socket and the choice doesn't depend on the timing of the packets.
+    fn choose(&self, peer: SocketAddrV4, seed: u64) -> &InetSocket {
+        if self.sockets.len() == 1 {
+            return &self.sockets[0];
+        }
+
+        // The algorithm used by `DefaultHasher` and the `Hash` impls of std types may change
+        // between rust releases, which would change which socket is chosen. `SipHasher` is
+        // deprecated but its algorithm is fixed, and we write the address bytes ourselves.
+        #[allow(deprecated)]
+        let mut hasher = std::hash::SipHasher::new_with_keys(seed, 0);
+        hasher.write(&peer.ip().octets());
+        hasher.write(&peer.port().to_be_bytes());
+        let index = hasher.finish() % u64::try_from(self.sockets.len()).unwrap();
+
+        &self.sockets[usize::try_from(index).unwrap()]
+    }
+}
+
 fn setup_pcap_writer(
     name: &str,
     options: &PcapOptions,
@@ -60,7 +90,9 @@ pub struct NetworkInterface {
     send_sockets: RefCell<NetworkQueue<InetSocket>>,
     /// The sockets to which we will push incoming packets so they can be received by the network
     /// stack and their payloads read by the managed process.
-    recv_sockets: RefCell<HashMap<AssociatedSocketKey, InetSocket>>,
+    recv_sockets: RefCell<HashMap<AssociatedSocketKey, AssociatedSockets>>,
+    /// Used to choose among the sockets of a reuseport group.
+    reuse_port_seed: u64,
     /// If configured, assists us in writing out pcap files of our packet flows.
     pcap: RefCell<Option<PcapWriter<BufWriter<File>>>>,
     /// Used to prevent recursion during cleanup.
@@ -73,12 +105,14 @@ pub struct NetworkInterface {
 impl NetworkInterface {
     /// Create a new network interface for the assigned `addr`. The configured `name` will be used
     /// to construct a filesystem path for the pcap file (if enabled), so take care in choosing a
-    /// filesystem-appropriate static string.
+    /// filesystem-appropriate static string. The `reuse_port_seed` is used to choose which socket of
+    /// a `SO_REUSEPORT` group receives packets from a given peer.
     pub fn new(
         name: &str,
         addr: Ipv4Addr,
         pcap_options: Option<PcapOptions>,
         qdisc: QDiscMode,
+        reuse_port_seed: u64,
     ) -> Self {
         // Try to set up the pcap writer if configured.
         let pcap = pcap_options.and_then(|opt| match setup_pcap_writer(name, &opt) {
@@ -112,32 +146,54 @@ impl NetworkInterface {
             addr,
             send_sockets: RefCell::new(NetworkQueue::new(queue_kind)),
             recv_sockets: RefCell::new(HashMap::new()),
+            reuse_port_seed,
             pcap: RefCell::new(pcap),
             cleanup_in_progress: RefCell::new(false),
             _counter: ObjectCounter::new("NetworkInterface"),
         }
     }
 
+    /// Associate the socket with the key. If `reuse_port` is true and every socket already
+    /// associated with the key was also associated with `reuse_port`, the socket will join their
+    /// reuseport group.
     pub fn associate(
         &self,
         socket: &InetSocket,
         protocol: IanaProtocol,
         port: u16,
         peer: SocketAddrV4,
+        reuse_port: bool,
     ) {
         let local = SocketAddrV4::new(self.addr, port);
         let key = AssociatedSocketKey::new(protocol, local, peer);
         log::trace!("Associating socket key {key:?}");
 
-        if let Entry::Vacant(entry) = self.recv_sockets.borrow_mut().entry(key) {
-            entry.insert(socket.clone());
-        } else {
-            // TODO: Return an error if the association fails.
-            debug_panic!("Entry is unexpectedly occupied");
+        match self.recv_sockets.borrow_mut().entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(AssociatedSockets {
+                    sockets: vec![socket.clone()],
+                    reuse_port,
+                });
+            }
+            Entry::Occupied(mut entry) if reuse_port && entry.get().reuse_port => {
+                entry.get_mut().sockets.push(socket.clone());
+            }
+            Entry::Occupied(_) => {
+                // TODO: Return an error if the association fails.
+                debug_panic!("Entry is unexpectedly occupied");
+            }
         }
     }
 
-    pub fn disassociate(&self, protocol: IanaProtocol, port: u16, peer: SocketAddrV4) {
+    /// Disassociate the socket from the key. Any other sockets in the same reuseport group will
+    /// remain associated.
+    pub fn disassociate(
+        &self,
+        socket: &InetSocketWeak,
+        protocol: IanaProtocol,
+        port: u16,
+        peer: SocketAddrV4,
+    ) {
         if *self.cleanup_in_progress.borrow() {
             return;
         }
@@ -146,22 +202,80 @@ impl NetworkInterface {
         let key = AssociatedSocketKey::new(protocol, local, peer);
         log::trace!("Disassociating socket key {key:?}");
 
+        let mut recv_sockets = self.recv_sockets.borrow_mut();
+
         // TODO: Return an error if the disassociation fails. Generally the calling code should only
         // try to disassociate a socket if it thinks that the socket is actually associated with
         // this interface, and if it's not, then it's probably an error. But TCP sockets will
         // disassociate all sockets (including ones that have never been associated) and will try to
         // disassociate the same socket multiple times, so we can't just add an assert here.
-        if self.recv_sockets.borrow_mut().remove(&key).is_none() {
+        let Entry::Occupied(mut entry) = recv_sockets.entry(key) else {
             // Since this always occurs with our legacy TCP stack and is not really a bug, we log at
             // trace instead of warn level for now until the legacy TCP stack is removed.
             log::trace!("Attempted to disassociate a vacant socket key");
+            return;
+        };
+
+        let sockets = &mut entry.get_mut().sockets;
+        let num_sockets = sockets.len();
+        sockets.retain(|x| !socket.points_to(x));
+
+        if sockets.len() == num_sockets {
+            log::trace!("Attempted to disassociate a socket that isn't associated with the key");
+        }
+
+        if sockets.is_empty() {
+            entry.remove();
         }
     }
 
-    pub fn is_addr_in_use(&self, protocol: IanaProtocol, port: u16, peer: SocketAddrV4) -> bool {
+    /// Returns true if a socket can't be associated with the key. If `reuse_port` is true, a key
+    /// that is only used by a reuseport group is not considered in use.
+    pub fn is_addr_in_use(
+        &self,
+        protocol: IanaProtocol,
+        port: u16,
+        peer: SocketAddrV4,
+        reuse_port: bool,
+    ) -> bool {
+        let local = SocketAddrV4::new(self.addr, port);
+        let key = AssociatedSocketKey::new(protocol, local, peer);
+        self.recv_sockets
+            .borrow()
+            .get(&key)
+            .is_some_and(|x| !(reuse_port && x.reuse_port))
+    }
+
+    /// Returns the socket that would receive a packet sent to `port` on this interface from `peer`.
+    /// A socket associated with `peer` takes precedence over a socket with a wildcard association.
+    /// If there are multiple sockets in a reuseport group, the same socket will always be returned
+    /// for the same `peer`.
+    pub fn recv_socket(
+        &self,
+        protocol: IanaProtocol,
+        port: u16,
+        peer: SocketAddrV4,
+    ) -> Option<InetSocket> {
         let local = SocketAddrV4::new(self.addr, port);
         let key = AssociatedSocketKey::new(protocol, local, peer);
-        self.recv_sockets.borrow().contains_key(&key)
+
+        // First check for a socket with the specific association.
+        log::trace!("Looking for socket associated with specific key {key:?}");
+        let associated = self.recv_sockets.borrow();
+        associated
+            .get(&key)
+            .or_else(|| {
+                // Then fall back to checking for the wildcard association.
+                let wildcard = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
+                let key = AssociatedSocketKey::new(protocol, local, wildcard);
+                log::trace!("Looking for socket associated with general key {key:?}");
+                associated.get(&key)
+            })
+            .map(|x| x.choose(peer, self.reuse_port_seed))
+            // Pushing a packet to the socket may cause the socket to be disassociated, so we can't
+            // hold on to the borrow of `recv_sockets` when we call `push_in_packet`. We need to
+            // clone the socket instead so that we can drop the `recv_sockets` borrow.
+            .cloned()
     }
 
     // Add the socket to the list of sockets that have data ready for us to send out to the network.
@@ -268,29 +382,11 @@ impl PacketDevice for NetworkInterface {
         self.capture_if_configured(&packet);
 
         // Find the socket that should process the packet.
-        let protocol = packet.iana_protocol();
-        let local = SocketAddrV4::new(self.addr, packet.dst_ipv4_address().port());
-        let peer = packet.src_ipv4_address();
-        let key = AssociatedSocketKey::new(protocol, local, peer);
-
-        // First check for a socket with the specific association.
-        log::trace!("Looking for socket associated with specific key {key:?}");
-        let maybe_socket = {
-            let associated = self.recv_sockets.borrow();
-            associated
-                .get(&key)
-                .or_else(|| {
-                    // Then fall back to checking for the wildcard association.
-                    let wildcard = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
-                    let key = AssociatedSocketKey::new(protocol, local, wildcard);
-                    log::trace!("Looking for socket associated with general key {key:?}");
-                    associated.get(&key)
-                })
-                // Pushing a packet to the socket may cause the socket to be disassociated, so we
-                // can't hold on to the borrow of `recv_sockets` when we call `push_in_packet`. We
-                // need to clone the socket instead so that we can drop the `recv_sockets` borrow.
-                .cloned()
-        };
+        let maybe_socket = self.recv_socket(
+            packet.iana_protocol(),
+            packet.dst_ipv4_address().port(),
+            packet.src_ipv4_address(),
+        );
 
         if let Some(socket) = maybe_socket {
             let recv_time = Worker::current_time().unwrap();
@@ -4,11 +4,12 @@ use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
 
 use atomic_refcell::AtomicRefCell;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
 
 use crate::core::configuration::QDiscMode;
 use crate::core::worker::Worker;
 use crate::host::descriptor::socket::abstract_unix_ns::AbstractUnixNamespace;
-use crate::host::descriptor::socket::inet::InetSocket;
+use crate::host::descriptor::socket::inet::{InetSocket, InetSocketWeak};
 use crate::host::network::interface::{NetworkInterface, PcapOptions};
 use crate::network::packet::IanaProtocol;
 
@@ -16,6 +17,12 @@ use crate::network::packet::IanaProtocol;
 // specify the port it wants to bind to, and for client connections.
 const MIN_RANDOM_PORT: u16 = 10000;
 
+/// The name of the loopback interface.
+pub const LOCALHOST_INTERFACE_NAME: &str = "lo";
+
+/// The name of the interface with the host's public IP address.
+pub const INTERNET_INTERFACE_NAME: &str = "eth0";
+
 /// Represents a network namespace.
 ///
 /// Can be thought of as roughly equivalent to a Linux `struct net`. Shadow doesn't support multiple
@@ -36,10 +43,29 @@ pub struct NetworkNamespace {
 }
 
 impl NetworkNamespace {
-    pub fn new(public_ip: Ipv4Addr, pcap: Option<PcapOptions>, qdisc: QDiscMode) -> Self {
-        let localhost = NetworkInterface::new("lo", Ipv4Addr::LOCALHOST, pcap.clone(), qdisc);
-
-        let internet = NetworkInterface::new("eth0", public_ip, pcap, qdisc);
+    /// The `reuse_port_seed` is used to deterministically choose which socket of a `SO_REUSEPORT`
+    /// group receives a connection or datagram.
+    pub fn new(
+        public_ip: Ipv4Addr,
+        pcap: Option<PcapOptions>,
+        qdisc: QDiscMode,
+        reuse_port_seed: u64,
+    ) -> Self {
+        let localhost = NetworkInterface::new(
+            LOCALHOST_INTERFACE_NAME,
+            Ipv4Addr::LOCALHOST,
+            pcap.clone(),
+            qdisc,
+            reuse_port_seed,
+        );
+
+        let internet = NetworkInterface::new(
+            INTERNET_INTERFACE_NAME,
+            public_ip,
+            pcap,
+            qdisc,
+            reuse_port_seed,
+        );
 
         Self {
             unix: Arc::new(AtomicRefCell::new(AbstractUnixNamespace::new())),
@@ -107,24 +133,48 @@ impl NetworkNamespace {
         }
     }
 
+    /// Returns the round-trip time of an ICMP echo request sent from `src` to `dst`, or `None` if
+    /// there is no host at `dst` to reply. Like Linux with the default
+    /// `net.ipv4.icmp_echo_ignore_all` setting, every host replies to echo requests.
+    pub fn echo_round_trip_time(&self, src: Ipv4Addr, dst: Ipv4Addr) -> Option<SimulationTime> {
+        // the request never leaves this host
+        if self.interface_borrow(dst).is_some() {
+            return Some(SimulationTime::ZERO);
+        }
+
+        if !Worker::is_routable(src.into(), dst.into()) {
+            return None;
+        }
+
+        let request_latency = Worker::latency(src.into(), dst.into())?;
+        let reply_latency = Worker::latency(dst.into(), src.into())?;
+
+        Some(request_latency + reply_latency)
+    }
+
+    /// Returns true if a socket can't be associated with the addresses. If `reuse_port` is true,
+    /// addresses that are only used by reuseport groups are not considered in use.
     pub fn is_addr_in_use(
         &self,
         protocol_type: IanaProtocol,
         src: SocketAddrV4,
         dst: SocketAddrV4,
+        reuse_port: bool,
     ) -> Result<bool, NoInterface> {
         if src.ip().is_unspecified() {
             Ok(self
                 .localhost
                 .borrow()
-                .is_addr_in_use(protocol_type, src.port(), dst)
-                || self
-                    .internet
-                    .borrow()
-                    .is_addr_in_use(protocol_type, src.port(), dst))
+                .is_addr_in_use(protocol_type, src.port(), dst, reuse_port)
+                || self.internet.borrow().is_addr_in_use(
+                    protocol_type,
+                    src.port(),
+                    dst,
+                    reuse_port,
+                ))
         } else {
             match self.interface_borrow(*src.ip()) {
-                Some(i) => Ok(i.is_addr_in_use(protocol_type, src.port(), dst)),
+                Some(i) => Ok(i.is_addr_in_use(protocol_type, src.port(), dst, reuse_port)),
                 None => Err(NoInterface),
             }
         }
@@ -154,6 +204,7 @@ impl NetworkNamespace {
                     protocol_type,
                     SocketAddrV4::new(interface_ip, random_port),
                     peer,
+                    /* reuse_port= */ false,
                 )
                 .unwrap_or(true);
             let generic_in_use = self
@@ -161,6 +212,7 @@ impl NetworkNamespace {
                     protocol_type,
                     SocketAddrV4::new(interface_ip, random_port),
                     SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0),
+                    /* reuse_port= */ false,
                 )
                 .unwrap_or(true);
             if !specific_in_use && !generic_in_use {
@@ -174,13 +226,19 @@ impl NetworkNamespace {
         let start = rng.random_range(MIN_RANDOM_PORT..=u16::MAX);
         for port in (start..=u16::MAX).chain(MIN_RANDOM_PORT..start) {
             let specific_in_use = self
-                .is_addr_in_use(protocol_type, SocketAddrV4::new(interface_ip, port), peer)
+                .is_addr_in_use(
+                    protocol_type,
+                    SocketAddrV4::new(interface_ip, port),
+                    peer,
+                    /* reuse_port= */ false,
+                )
                 .unwrap_or(true);
             let generic_in_use = self
                 .is_addr_in_use(
                     protocol_type,
                     SocketAddrV4::new(interface_ip, port),
                     SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0),
+                    /* reuse_port= */ false,
                 )
                 .unwrap_or(true);
             if !specific_in_use && !generic_in_use {
@@ -193,7 +251,8 @@ impl NetworkNamespace {
     }
 
     /// Associate the socket with any applicable network interfaces. The socket will be
-    /// automatically disassociated when the returned handle is dropped.
+    /// automatically disassociated when the returned handle is dropped. If `reuse_port` is true, the
+    /// socket may join a reuseport group of other sockets associated with the same addresses.
     ///
     /// # Safety
     ///
@@ -204,23 +263,33 @@ impl NetworkNamespace {
         protocol: IanaProtocol,
         bind_addr: SocketAddrV4,
         peer_addr: SocketAddrV4,
+        reuse_port: bool,
     ) -> AssociationHandle {
         if bind_addr.ip().is_unspecified() {
             // need to associate all interfaces
-            self.localhost
-                .borrow()
-                .associate(socket, protocol, bind_addr.port(), peer_addr);
-            self.internet
-                .borrow()
-                .associate(socket, protocol, bind_addr.port(), peer_addr);
+            self.localhost.borrow().associate(
+                socket,
+                protocol,
+                bind_addr.port(),
+                peer_addr,
+                reuse_port,
+            );
+            self.internet.borrow().associate(
+                socket,
+                protocol,
+                bind_addr.port(),
+                peer_addr,
+                reuse_port,
+            );
         } else {
             // TODO: return error if interface does not exist
             if let Some(iface) = self.interface_borrow(*bind_addr.ip()) {
-                iface.associate(socket, protocol, bind_addr.port(), peer_addr);
+                iface.associate(socket, protocol, bind_addr.port(), peer_addr, reuse_port);
             }
         }
 
         AssociationHandle {
+            socket: socket.downgrade(),
             protocol,
             local_addr: bind_addr,
             remote_addr: peer_addr,
@@ -234,6 +303,7 @@ impl NetworkNamespace {
     /// should only be called from the [`AssociationHandle`].
     pub fn disassociate_interface(
         &self,
+        socket: &InetSocketWeak,
         protocol: IanaProtocol,
         bind_addr: SocketAddrV4,
         peer_addr: SocketAddrV4,
@@ -242,15 +312,15 @@ impl NetworkNamespace {
             // need to disassociate all interfaces
             self.localhost
                 .borrow()
-                .disassociate(protocol, bind_addr.port(), peer_addr);
+                .disassociate(socket, protocol, bind_addr.port(), peer_addr);
 
             self.internet
                 .borrow()
-                .disassociate(protocol, bind_addr.port(), peer_addr);
+                .disassociate(socket, protocol, bind_addr.port(), peer_addr);
         } else {
             // TODO: return error if interface does not exist
             if let Some(iface) = self.interface_borrow(*bind_addr.ip()) {
-                iface.disassociate(protocol, bind_addr.port(), peer_addr);
+                iface.disassociate(socket, protocol, bind_addr.port(), peer_addr);
             }
         }
     }
@@ -281,6 +351,7 @@ impl std::error::Error for NoInterface {}
 /// [`callback_queue::Handle`](crate::utility::callback_queue::Handle)).
 #[derive(Debug)]
 pub struct AssociationHandle {
+    socket: InetSocketWeak,
     protocol: IanaProtocol,
     local_addr: SocketAddrV4,
     remote_addr: SocketAddrV4,
@@ -300,6 +371,7 @@ impl std::ops::Drop for AssociationHandle {
     fn drop(&mut self) {
         Worker::with_active_host(|host| {
             host.network_namespace_borrow().disassociate_interface(
+                &self.socket,
                 self.protocol,
                 self.local_addr,
                 self.remote_addr,
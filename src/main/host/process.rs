@@ -8,14 +8,15 @@ use std::num::TryFromIntError;
 use std::ops::{Deref, DerefMut};
 use std::os::fd::AsRawFd;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
 use std::sync::atomic::Ordering;
+use std::sync::{Arc, Weak};
 #[cfg(feature = "perf_timers")]
 use std::time::Duration;
 
+use atomic_refcell::AtomicRefCell;
 use linux_api::errno::Errno;
 use linux_api::fcntl::OFlag;
-use linux_api::posix_types::Pid;
+use linux_api::posix_types::{Pid, kernel_mode_t};
 use linux_api::sched::{CloneFlags, SuidDump};
 use linux_api::signal::{
     LinuxDefaultAction, SigActionFlags, Signal, SignalFromI32Error, defaultaction, siginfo_t,
@@ -35,6 +36,7 @@ use shadow_shmem::allocator::ShMemBlock;
 
 use super::descriptor::descriptor_table::{DescriptorHandle, DescriptorTable};
 use super::descriptor::listener::StateEventSource;
+use super::descriptor::pidfd::PidFd;
 use super::descriptor::{FileSignals, FileState};
 use super::host::Host;
 use super::memory_manager::{MemoryManager, ProcessMemoryRef, ProcessMemoryRefMut};
@@ -48,6 +50,7 @@ use crate::core::worker::Worker;
 use crate::cshadow;
 use crate::host::context::ProcessContext;
 use crate::host::descriptor::Descriptor;
+use crate::host::file_lock_table::LockOwner;
 use crate::host::managed_thread::ManagedThread;
 use crate::host::syscall::formatter::FmtOptions;
 use crate::utility::callback_queue::CallbackQueue;
@@ -55,6 +58,9 @@ use crate::utility::callback_queue::CallbackQueue;
 use crate::utility::perf_timer::PerfTimer;
 use crate::utility::{self, debug_assert_cloexec};
 
+/// The umask of processes started by shadow. This is the usual default on Linux systems.
+const DEFAULT_UMASK: kernel_mode_t = 0o022;
+
 /// Virtual pid of a shadow process
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Ord, PartialOrd)]
 pub struct ProcessId(u32);
@@ -164,6 +170,9 @@ struct Common {
     // This must remain in sync with the actual working dir of the native process.
     // See https://github.com/shadow/shadow/issues/2960
     working_dir: CString,
+
+    // file mode creation mask, as set by `umask`
+    umask: kernel_mode_t,
 }
 
 impl Common {
@@ -285,6 +294,9 @@ pub struct RunnableProcess {
     // Listeners for child-events.
     // e.g. these listeners are notified when a child of this process exits.
     child_process_event_listeners: RefCell<StateEventSource>,
+
+    // Pidfds that refer to this process, which are notified when this process exits.
+    pidfds: RefCell<Vec<Weak<AtomicRefCell<PidFd>>>>,
 }
 
 impl RunnableProcess {
@@ -618,6 +630,8 @@ impl RunnableProcess {
             name,
             plugin_name,
             working_dir: self.common.working_dir.clone(),
+            // `fork(2)`: the child inherits the parent's umask
+            umask: self.common.umask,
             parent_pid: Cell::new(parent_pid),
             group_id: Cell::new(process_group_id),
             session_id: Cell::new(session_id),
@@ -662,6 +676,7 @@ impl RunnableProcess {
             unsafe_borrows: RefCell::new(Vec::new()),
             memory_manager: Box::new(RefCell::new(unsafe { MemoryManager::new(native_pid) })),
             child_process_event_listeners: Default::default(),
+            pidfds: Default::default(),
             shimlog_file: self.shimlog_file.clone(),
         };
         let child_process = Process {
@@ -1084,6 +1099,7 @@ impl Process {
             id: process_id,
             host_id: host.id(),
             working_dir,
+            umask: DEFAULT_UMASK,
             name,
             plugin_name,
             parent_pid: Cell::new(ProcessId::INIT),
@@ -1115,6 +1131,7 @@ impl Process {
                         #[cfg(feature = "perf_timers")]
                         total_run_time: Cell::new(Duration::ZERO),
                         child_process_event_listeners: Default::default(),
+                        pidfds: Default::default(),
                         shimlog_file,
                     }))),
                 },
@@ -1412,6 +1429,34 @@ impl Process {
         self.as_runnable().unwrap().native_pid()
     }
 
+    /// The process's soft `RLIMIT_NOFILE` limit. New fds must be less than this value. Resource
+    /// limits are handled natively, so this is read from the native process.
+    pub fn fd_limit(&self) -> u32 {
+        let mut limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        let native_pid = self.native_pid().as_raw_nonzero().get();
+
+        // SAFETY: `limit` is a valid pointer, and we don't set a new limit.
+        let rv = Errno::result_from_libc_errno(-1, unsafe {
+            libc::prlimit(
+                native_pid,
+                libc::RLIMIT_NOFILE,
+                std::ptr::null(),
+                &mut limit,
+            )
+        });
+
+        if let Err(e) = rv {
+            warn!("Could not get the fd limit of native process {native_pid}: {e}");
+            return u32::MAX;
+        }
+
+        // `RLIM_INFINITY` and other large limits don't fit in a `u32`
+        u32::try_from(limit.rlim_cur).unwrap_or(u32::MAX)
+    }
+
     /// Deprecated wrapper for `RunnableProcess::realtime_timer_borrow`
     #[track_caller]
     pub fn realtime_timer_borrow(&self) -> impl Deref<Target = Timer> + '_ {
@@ -1467,6 +1512,20 @@ impl Process {
         self.as_runnable().is_some()
     }
 
+    /// Register a pidfd that refers to this process. The pidfd will be notified when the process
+    /// exits. Does nothing if the process has already exited.
+    pub fn add_pidfd(&self, pidfd: &Arc<AtomicRefCell<PidFd>>) {
+        let Some(runnable) = self.as_runnable() else {
+            return;
+        };
+
+        let mut pidfds = runnable.pidfds.borrow_mut();
+
+        // forget any pidfds that have since been closed
+        pidfds.retain(|x| x.strong_count() > 0);
+        pidfds.push(Arc::downgrade(pidfd));
+    }
+
     /// Transitions `self` from a `RunnableProcess` to a `ZombieProcess`.
     fn handle_process_exit(&self, host: &Host, killed_by_shadow: bool) {
         debug!(
@@ -1487,6 +1546,23 @@ impl Process {
             }
         }
 
+        // Release any record locks that the process still holds. Closing the process' descriptors
+        // should have already released them, but a descriptor table may outlive the process.
+        CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
+            host.file_lock_table_borrow_mut()
+                .release_owner(LockOwner::Process(self.id()), cb_queue)
+        });
+
+        // Notify any pidfds that refer to the process that it has exited.
+        {
+            let pidfds = std::mem::take(&mut *self.as_runnable().unwrap().pidfds.borrow_mut());
+            CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
+                for pidfd in pidfds.iter().filter_map(Weak::upgrade) {
+                    pidfd.borrow_mut().set_exited(cb_queue);
+                }
+            });
+        }
+
         // Intentionally hold the borrow on self.state to ensure the state
         // transition is "atomic".
         let mut opt_state = self.state.borrow_mut();
@@ -1639,6 +1715,16 @@ impl Process {
         self.common_mut().working_dir = path;
     }
 
+    /// The process's file mode creation mask.
+    pub fn umask(&self) -> kernel_mode_t {
+        self.common().umask
+    }
+
+    /// Set the process's file mode creation mask, returning the previous mask.
+    pub fn set_umask(&self, umask: kernel_mode_t) -> kernel_mode_t {
+        std::mem::replace(&mut self.common_mut().umask, umask)
+    }
+
     /// Update `self` to complete an `exec` syscall from thread `tid`, replacing
     /// the running managed process with `mthread`.
     pub fn update_for_exec(&mut self, host: &Host, tid: ThreadId, mthread: ManagedThread) {
@@ -2148,6 +2234,12 @@ mod export {
         proc.common().working_dir.as_ptr()
     }
 
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C-unwind" fn process_getUmask(proc: *const Process) -> libc::mode_t {
+        let proc = unsafe { proc.as_ref().unwrap() };
+        proc.umask()
+    }
+
     #[unsafe(no_mangle)]
     pub unsafe extern "C-unwind" fn process_straceLoggingMode(
         proc: *const Process,
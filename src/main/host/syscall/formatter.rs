@@ -13,9 +13,33 @@ use crate::host::syscall::types::{SyscallError, SyscallResult};
 use crate::host::thread::ThreadId;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub enum FmtOptions {
+pub struct FmtOptions {
+    pub mode: FmtMode,
+    /// The maximum length of the preview shown for byte buffers and strings. For escaped strings
+    /// this is the number of displayed characters, and for hex dumps this is the number of bytes.
+    pub display_len: usize,
+    /// Display strings as UTF-8 rather than as escaped ASCII.
+    pub utf8_strings: bool,
+}
+
+impl FmtOptions {
+    pub const DEFAULT_DISPLAY_LEN: usize = 40;
+
+    pub fn new(mode: FmtMode) -> Self {
+        Self {
+            mode,
+            display_len: Self::DEFAULT_DISPLAY_LEN,
+            utf8_strings: false,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FmtMode {
     Standard,
     Deterministic,
+    /// Like [`FmtMode::Standard`], but byte buffers are displayed as a hex dump.
+    HexDump,
 }
 
 // this type is required until we no longer need to access the format options from C
@@ -25,29 +49,44 @@ pub enum StraceFmtMode {
     Off,
     Standard,
     Deterministic,
+    HexDump,
 }
 
-impl From<StraceFmtMode> for Option<FmtOptions> {
-    fn from(x: StraceFmtMode) -> Self {
-        match x {
-            StraceFmtMode::Off => None,
-            StraceFmtMode::Standard => Some(FmtOptions::Standard),
-            StraceFmtMode::Deterministic => Some(FmtOptions::Deterministic),
-        }
+impl StraceFmtMode {
+    /// Returns the format options for this mode using the configured preview length and string
+    /// encoding, or `None` if strace logging is off. The mode alone doesn't include these settings,
+    /// so they must be provided.
+    pub fn to_options(self, display_len: usize, utf8_strings: bool) -> Option<FmtOptions> {
+        let mode = match self {
+            StraceFmtMode::Off => return None,
+            StraceFmtMode::Standard => FmtMode::Standard,
+            StraceFmtMode::Deterministic => FmtMode::Deterministic,
+            StraceFmtMode::HexDump => FmtMode::HexDump,
+        };
+        Some(FmtOptions {
+            mode,
+            display_len,
+            utf8_strings,
+        })
     }
 }
 
 impl From<Option<FmtOptions>> for StraceFmtMode {
     fn from(x: Option<FmtOptions>) -> Self {
-        match x {
+        match x.map(|x| x.mode) {
             None => StraceFmtMode::Off,
-            Some(FmtOptions::Standard) => StraceFmtMode::Standard,
-            Some(FmtOptions::Deterministic) => StraceFmtMode::Deterministic,
+            Some(FmtMode::Standard) => StraceFmtMode::Standard,
+            Some(FmtMode::Deterministic) => StraceFmtMode::Deterministic,
+            Some(FmtMode::HexDump) => StraceFmtMode::HexDump,
         }
     }
 }
 
 pub trait SyscallDisplay {
+    /// Whether a failed syscall's return value is already displayed as an errno name (for example
+    /// `-EAGAIN`), in which case the errno doesn't need to be shown a second time.
+    const SHOWS_ERRNO_NAME: bool = false;
+
     fn fmt(
         &self,
         f: &mut std::fmt::Formatter<'_>,
@@ -61,6 +100,9 @@ pub trait SyscallDisplay {
 pub struct SyscallVal<'a, T> {
     pub reg: SyscallReg,
     pub args: [SyscallReg; 6],
+    /// The syscall's return value, if the syscall completed successfully. Some arguments (for
+    /// example output arrays) can only be formatted once the return value is known.
+    pub rv: Option<SyscallReg>,
     options: FmtOptions,
     mem: &'a MemoryManager,
     _phantom: PhantomData<T>,
@@ -70,12 +112,14 @@ impl<'a, T> SyscallVal<'a, T> {
     pub fn new(
         reg: SyscallReg,
         args: [SyscallReg; 6],
+        rv: Option<SyscallReg>,
         options: FmtOptions,
         mem: &'a MemoryManager,
     ) -> Self {
         Self {
             reg,
             args,
+            rv,
             options,
             mem,
             _phantom: PhantomData,
@@ -87,6 +131,7 @@ impl<'a, T> SyscallVal<'a, T> {
         SyscallVal {
             reg: self.reg,
             args: self.args,
+            rv: self.rv,
             options: self.options,
             mem: self.mem,
             _phantom: PhantomData,
@@ -137,14 +182,24 @@ where
     SyscallVal<'a, E>: Display,
     SyscallVal<'a, F>: Display,
 {
-    pub fn new(args: [SyscallReg; 6], options: FmtOptions, mem: &'a MemoryManager) -> Self {
+    pub fn new(
+        args: [SyscallReg; 6],
+        rv: &SyscallResult,
+        options: FmtOptions,
+        mem: &'a MemoryManager,
+    ) -> Self {
+        let rv = match rv {
+            SyscallResult::Ok(x) => Some(*x),
+            SyscallResult::Err(_) => None,
+        };
+
         Self {
-            a: SyscallVal::new(args[0], args, options, mem),
-            b: SyscallVal::new(args[1], args, options, mem),
-            c: SyscallVal::new(args[2], args, options, mem),
-            d: SyscallVal::new(args[3], args, options, mem),
-            e: SyscallVal::new(args[4], args, options, mem),
-            f: SyscallVal::new(args[5], args, options, mem),
+            a: SyscallVal::new(args[0], args, rv, options, mem),
+            b: SyscallVal::new(args[1], args, rv, options, mem),
+            c: SyscallVal::new(args[2], args, rv, options, mem),
+            d: SyscallVal::new(args[3], args, rv, options, mem),
+            e: SyscallVal::new(args[4], args, rv, options, mem),
+            f: SyscallVal::new(args[5], args, rv, options, mem),
         }
     }
 }
@@ -232,20 +287,24 @@ where
 
 impl<'a, RV> Display for SyscallResultFmt<'a, RV>
 where
-    SyscallVal<'a, RV>: Display,
+    SyscallVal<'a, RV>: Display + SyscallDisplay,
     RV: std::fmt::Debug,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.rv {
             SyscallResult::Ok(x) => {
-                let rv = SyscallVal::<'_, RV>::new(*x, self.args, self.options, self.mem);
+                let rv = SyscallVal::<'_, RV>::new(*x, self.args, Some(*x), self.options, self.mem);
                 write!(f, "{rv}")
             }
             SyscallResult::Err(SyscallError::Failed(failed)) => {
                 let errno = failed.errno;
                 let rv = SyscallReg::from(errno.to_negated_i64());
-                let rv = SyscallVal::<'_, RV>::new(rv, self.args, self.options, self.mem);
-                write!(f, "{rv} ({errno})")
+                let rv = SyscallVal::<'_, RV>::new(rv, self.args, None, self.options, self.mem);
+                if <SyscallVal<'a, RV> as SyscallDisplay>::SHOWS_ERRNO_NAME {
+                    write!(f, "{rv}")
+                } else {
+                    write!(f, "{rv} ({errno})")
+                }
             }
             SyscallResult::Err(SyscallError::Native) => {
                 write!(f, "<native>")
@@ -315,6 +374,22 @@ mod test {
 
     use super::*;
 
+    #[test]
+    fn test_strace_fmt_mode_options() {
+        let options = StraceFmtMode::HexDump.to_options(100, true).unwrap();
+        assert_eq!(
+            options,
+            FmtOptions {
+                mode: FmtMode::HexDump,
+                display_len: 100,
+                utf8_strings: true,
+            }
+        );
+        assert_eq!(StraceFmtMode::from(Some(options)), StraceFmtMode::HexDump);
+
+        assert_eq!(StraceFmtMode::Off.to_options(100, true), None);
+    }
+
     #[test]
     // can't call foreign function: gnu_get_libc_version
     #[cfg_attr(miri, ignore)]
@@ -331,7 +406,12 @@ mod test {
         let mem = unsafe { MemoryManager::new(pid) };
 
         // make sure that we can construct a `SyscallArgsFmt` with no generic types
-        let _syscall_args = <SyscallArgsFmt>::new(args.args, FmtOptions::Standard, &mem);
+        let _syscall_args = <SyscallArgsFmt>::new(
+            args.args,
+            &SyscallResult::Ok(SyscallReg::from(0i64)),
+            FmtOptions::new(FmtMode::Standard),
+            &mem,
+        );
 
         proc.kill().unwrap();
         proc.wait().unwrap();
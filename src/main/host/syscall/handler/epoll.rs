@@ -14,6 +14,7 @@ use crate::host::descriptor::epoll::Epoll;
 use crate::host::descriptor::{CompatFile, Descriptor, File, FileState, OpenFile};
 use crate::host::memory_manager::MemoryManager;
 use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
+use crate::host::syscall::type_formatting::SyscallEpollEventsArg;
 use crate::host::syscall::types::{ForeignArrayPtr, SyscallError};
 use crate::utility::callback_queue::CallbackQueue;
 
@@ -87,7 +88,7 @@ impl SyscallHandler {
         /* epfd */ std::ffi::c_int,
         /* op */ std::ffi::c_int,
         /* fd */ std::ffi::c_int,
-        /* event */ *const std::ffi::c_void,
+        /* event */ *const linux_api::epoll::epoll_event,
     );
     pub fn epoll_ctl(
         ctx: &mut SyscallContext,
@@ -173,6 +174,27 @@ impl SyscallHandler {
                 return Err(Errno::EINVAL);
             };
 
+            if events.contains(EpollEvents::EPOLLEXCLUSIVE) {
+                // From epoll_ctl(2): Returns EINVAL when "An invalid event type was specified along
+                // with EPOLLEXCLUSIVE in events", "op was EPOLL_CTL_MOD and events included
+                // EPOLLEXCLUSIVE", or "EPOLLEXCLUSIVE was specified in event and fd refers to an
+                // epoll instance."
+                let allowed = EpollEvents::EPOLLEXCLUSIVE
+                    | EpollEvents::EPOLLIN
+                    | EpollEvents::EPOLLOUT
+                    | EpollEvents::EPOLLERR
+                    | EpollEvents::EPOLLHUP
+                    | EpollEvents::EPOLLWAKEUP
+                    | EpollEvents::EPOLLET;
+
+                if op == EpollCtlOp::EPOLL_CTL_MOD
+                    || !allowed.contains(events)
+                    || matches!(target, File::Epoll(_))
+                {
+                    return Err(Errno::EINVAL);
+                }
+            }
+
             // epoll_ctl(2): epoll always reports for EPOLLERR and EPOLLHUP
             events.insert(EpollEvents::EPOLLERR | EpollEvents::EPOLLHUP);
 
@@ -194,7 +216,7 @@ impl SyscallHandler {
         epoll_wait,
         /* rv */ std::ffi::c_int,
         /* epfd */ std::ffi::c_int,
-        /* events */ *const std::ffi::c_void,
+        /* events */ SyscallEpollEventsArg,
         /* max_events */ std::ffi::c_int,
         /* timeout */ std::ffi::c_int,
     );
@@ -214,7 +236,7 @@ impl SyscallHandler {
         epoll_pwait,
         /* rv */ std::ffi::c_int,
         /* epfd */ std::ffi::c_int,
-        /* events */ *const std::ffi::c_void,
+        /* events */ SyscallEpollEventsArg,
         /* max_events */ std::ffi::c_int,
         /* timeout */ std::ffi::c_int,
         /* sigmask */ *const std::ffi::c_void,
@@ -246,7 +268,7 @@ impl SyscallHandler {
         epoll_pwait2,
         /* rv */ std::ffi::c_int,
         /* epfd */ std::ffi::c_int,
-        /* events */ *const std::ffi::c_void,
+        /* events */ SyscallEpollEventsArg,
         /* max_events */ std::ffi::c_int,
         /* timeout */ *const std::ffi::c_void,
         /* sigmask */ *const std::ffi::c_void,
@@ -407,19 +429,13 @@ impl SyscallHandler {
 
         log::trace!("No events are ready on epoll {epfd} and we need to block");
 
-        // Block on epoll state; an epoll descriptor is readable when it has events.
-        let mut rv = SyscallError::new_blocked_on_file(
-            File::Epoll(Arc::clone(epoll)),
+        // Block on epoll state; an epoll descriptor is readable when it has events. Epoll is never
+        // restartable (see `Epoll::supports_sa_restart`).
+        Err(Self::block_on_file(
+            &File::Epoll(Arc::clone(epoll)),
             FileState::READABLE,
-            /* restartable= */ false,
-        );
-
-        // Set timeout, if provided.
-        if abs_timeout_opt.is_some() {
-            rv.blocked_condition().unwrap().set_timeout(abs_timeout_opt);
-        }
-
-        Err(rv)
+            abs_timeout_opt,
+        ))
     }
 }
 
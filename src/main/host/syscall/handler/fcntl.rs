@@ -1,12 +1,15 @@
 use linux_api::errno::Errno;
 use linux_api::fcntl::{DescriptorFlags, FcntlCommand, OFlag};
 use log::debug;
+use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 
 use crate::cshadow;
 use crate::host::descriptor::{CompatFile, File, FileStatus};
+use crate::host::file_lock_table::{self, FileId, LockOwner, LockType, OpenFileId};
 use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
 use crate::host::syscall::type_formatting::SyscallNonDeterministicArg;
 use crate::host::syscall::types::SyscallError;
+use crate::utility::callback_queue::CallbackQueue;
 
 impl SyscallHandler {
     log_syscall!(
@@ -41,6 +44,7 @@ impl SyscallHandler {
         Ok(match cmd {
             FcntlCommand::F_SETLK
             | FcntlCommand::F_SETLKW
+            | FcntlCommand::F_OFD_SETLK
             | FcntlCommand::F_OFD_SETLKW
             | FcntlCommand::F_GETLK
             | FcntlCommand::F_OFD_GETLK => {
@@ -49,13 +53,30 @@ impl SyscallHandler {
                         warn_once_then_debug!("fcntl({cmd:?}) unimplemented for {:?}", desc.file());
                         return Err(Errno::ENOSYS.into());
                     }
-                    CompatFile::Legacy(_) => {
-                        warn_once_then_debug!(
-                            "Using fcntl({cmd:?}) implementation that assumes no lock contention. \
-                            See https://github.com/shadow/shadow/issues/2258"
-                        );
+                    CompatFile::Legacy(file) => {
+                        let open_file_id = file.open_file_id();
+                        let file = file.ptr();
+                        if unsafe { cshadow::legacyfile_getType(file) }
+                            != cshadow::_LegacyFileType_DT_FILE
+                        {
+                            warn_once_then_debug!(
+                                "Using fcntl({cmd:?}) implementation that assumes no lock \
+                                contention. See https://github.com/shadow/shadow/issues/2258"
+                            );
+                            drop(desc_table);
+                            return legacy_syscall_fn(ctx);
+                        }
+
+                        // the descriptor holds a reference to the file, so it will remain valid
+                        // for the rest of the syscall
                         drop(desc_table);
-                        return legacy_syscall_fn(ctx);
+                        return Self::fcntl_record_lock(
+                            ctx,
+                            file as *mut cshadow::RegularFile,
+                            open_file_id,
+                            cmd,
+                            ForeignPtr::from(arg).cast::<libc::flock>(),
+                        );
                     }
                 };
             }
@@ -183,4 +204,179 @@ impl SyscallHandler {
             }
         })
     }
+
+    /// Handles the record lock commands for regular files. Locks are tracked in the host's
+    /// [`FileLockTable`](crate::host::file_lock_table::FileLockTable) rather than with the native
+    /// file, so that locks conflict between processes of the same host.
+    fn fcntl_record_lock(
+        ctx: &mut SyscallContext,
+        file: *mut cshadow::RegularFile,
+        open_file_id: OpenFileId,
+        cmd: FcntlCommand,
+        flock_ptr: ForeignPtr<libc::flock>,
+    ) -> Result<std::ffi::c_long, SyscallError> {
+        let mut flock = ctx.objs.process.memory_borrow().read(flock_ptr)?;
+
+        let is_ofd = matches!(
+            cmd,
+            FcntlCommand::F_OFD_GETLK | FcntlCommand::F_OFD_SETLK | FcntlCommand::F_OFD_SETLKW
+        );
+
+        // fcntl(2): "When placing an open file description lock, l_pid must be set to zero"
+        if is_ofd && flock.l_pid != 0 {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let owner = if is_ofd {
+            LockOwner::OpenFile(open_file_id)
+        } else {
+            LockOwner::Process(ctx.objs.process.id())
+        };
+
+        let lock_type = match i32::from(flock.l_type) {
+            libc::F_RDLCK => Some(LockType::Read),
+            libc::F_WRLCK => Some(LockType::Write),
+            libc::F_UNLCK => None,
+            _ => return Err(Errno::EINVAL.into()),
+        };
+
+        let stat = unsafe { file_lock_table::regular_file_stat(file) }?;
+        let file_id = FileId::from(&stat);
+        let (start, end) = Self::record_lock_range(file, &stat, &flock)?;
+
+        if matches!(cmd, FcntlCommand::F_GETLK | FcntlCommand::F_OFD_GETLK) {
+            let Some(lock_type) = lock_type else {
+                return Err(Errno::EINVAL.into());
+            };
+
+            let conflict = ctx
+                .objs
+                .host
+                .file_lock_table_borrow()
+                .conflicting_lock(file_id, owner, lock_type, start, end);
+
+            if let Some(conflict) = conflict {
+                flock.l_type = match conflict.lock_type {
+                    LockType::Read => libc::F_RDLCK,
+                    LockType::Write => libc::F_WRLCK,
+                } as libc::c_short;
+                flock.l_whence = libc::SEEK_SET as libc::c_short;
+                flock.l_start = conflict.start.try_into().unwrap();
+                // a length of 0 means that the lock extends to the end of the file
+                flock.l_len = conflict
+                    .end
+                    .map(|end| (end - conflict.start).try_into().unwrap())
+                    .unwrap_or(0);
+                flock.l_pid = match conflict.owner {
+                    LockOwner::Process(pid) => pid.into(),
+                    // fcntl(2): "l_pid is set to -1" for open file description locks
+                    LockOwner::OpenFile(_) => -1,
+                };
+            } else {
+                flock.l_type = libc::F_UNLCK as libc::c_short;
+            }
+
+            ctx.objs
+                .process
+                .memory_borrow_mut()
+                .write(flock_ptr, &flock)?;
+            return Ok(0);
+        }
+
+        // fcntl(2): "In order to place a read lock, fd must be open for reading. In order to place
+        // a write lock, fd must be open for writing."
+        if let Some(lock_type) = lock_type {
+            let access_mode =
+                unsafe { cshadow::regularfile_getFlagsAtOpen(file) } & libc::O_ACCMODE;
+            let allowed = match lock_type {
+                LockType::Read => access_mode != libc::O_WRONLY,
+                LockType::Write => access_mode != libc::O_RDONLY,
+            };
+            if !allowed {
+                return Err(Errno::EBADF.into());
+            }
+        }
+
+        let thread_id = ctx.objs.thread.id();
+
+        let rv = CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
+            let mut lock_table = ctx.objs.host.file_lock_table_borrow_mut();
+
+            // if the thread was blocked on a lock, it's no longer waiting while it tries again
+            lock_table.remove_waiter(thread_id);
+            lock_table.set(file_id, owner, lock_type, start, end, cb_queue)
+        });
+
+        match rv {
+            Ok(()) => Ok(0),
+            Err(conflict) if matches!(cmd, FcntlCommand::F_SETLKW | FcntlCommand::F_OFD_SETLKW) => {
+                let mut lock_table = ctx.objs.host.file_lock_table_borrow_mut();
+
+                // fcntl(2): "If a blocking lock request (F_SETLKW) would cause a deadlock, the
+                // call fails with the error EDEADLK"
+                if lock_table.would_deadlock(owner, conflict.owner) {
+                    return Err(Errno::EDEADLK.into());
+                }
+
+                // if a signal is pending the syscall will be interrupted rather than block, so the
+                // thread won't be waiting for the lock
+                let is_unblocked_signal_pending = ctx.objs.thread.unblocked_signal_pending(
+                    ctx.objs.process,
+                    &ctx.objs.host.shim_shmem_lock_borrow().unwrap(),
+                );
+                if !is_unblocked_signal_pending {
+                    lock_table.add_waiter(thread_id, owner, conflict.owner);
+                }
+
+                // retry when some lock on the host is released
+                Err(SyscallError::new_blocked_on_file_lock(
+                    /* restartable= */ true,
+                ))
+            }
+            Err(_conflict) => Err(Errno::EAGAIN.into()),
+        }
+    }
+
+    /// Returns the byte range `[start, end)` described by `flock`, where an `end` of `None` means
+    /// that the range extends to the end of the file.
+    fn record_lock_range(
+        file: *mut cshadow::RegularFile,
+        stat: &libc::stat,
+        flock: &libc::flock,
+    ) -> Result<(u64, Option<u64>), Errno> {
+        let base = match i32::from(flock.l_whence) {
+            libc::SEEK_SET => 0,
+            libc::SEEK_CUR => {
+                let offset = unsafe { cshadow::regularfile_lseek(file, 0, libc::SEEK_CUR) };
+                if offset < 0 {
+                    return Err(Errno::try_from(-offset).unwrap_or(Errno::EBADF));
+                }
+                offset
+            }
+            libc::SEEK_END => stat.st_size,
+            _ => return Err(Errno::EINVAL),
+        };
+
+        let start = base.checked_add(flock.l_start).ok_or(Errno::EOVERFLOW)?;
+        if start < 0 {
+            return Err(Errno::EINVAL);
+        }
+
+        // fcntl(2): "If l_len is negative, the interval described by lock covers bytes
+        // l_start+l_len up to and including l_start-1."
+        let (start, end) = match flock.l_len {
+            0 => (start, None),
+            len if len > 0 => (start, Some(start.checked_add(len).ok_or(Errno::EOVERFLOW)?)),
+            len => (start + len, Some(start)),
+        };
+
+        if start < 0 {
+            return Err(Errno::EINVAL);
+        }
+
+        Ok((
+            start.try_into().unwrap(),
+            end.map(|end| end.try_into().unwrap()),
+        ))
+    }
 }
@@ -60,6 +60,9 @@ static SyscallReturn _syscallhandler_openHelper(SyscallHandler* sys, UntypedFore
         return syscallreturn_makeDoneErrno(-errcode);
     }
 
+    /* The process umask applies to the mode of any newly created file. */
+    mode &= ~process_getUmask(rustsyscallhandler_getProcess(sys));
+
     /* Create and open the file. */
     RegularFile* filed = regularfile_new();
     errcode = regularfile_open(filed, pathname, flags & ~O_CLOEXEC, mode,
@@ -354,6 +357,66 @@ SyscallReturn syscallhandler_sync_file_range(SyscallHandler* sys, const SyscallA
     return syscallreturn_makeDoneI64(regularfile_sync_range(file_desc, offset, nbytes, flags));
 }
 
+SyscallReturn syscallhandler_copy_file_range(SyscallHandler* sys, const SyscallArgs* args) {
+    int fdIn = args->args[0].as_i64;
+    UntypedForeignPtr offsetInPtr = args->args[1].as_ptr; // off64_t*
+    int fdOut = args->args[2].as_i64;
+    UntypedForeignPtr offsetOutPtr = args->args[3].as_ptr; // off64_t*
+    size_t len = args->args[4].as_u64;
+    unsigned int flags = args->args[5].as_u64;
+
+    /* copy_file_range(2): "The flags argument is provided to allow for future extensions and
+     * currently must be set to 0." */
+    if (flags != 0) {
+        return syscallreturn_makeDoneErrno(EINVAL);
+    }
+
+    /* Get and validate the file descriptors. */
+    RegularFile* fileIn = NULL;
+    int errcode = _syscallhandler_validateFileHelper(sys, fdIn, &fileIn);
+    if (errcode < 0) {
+        return syscallreturn_makeDoneErrno(-errcode);
+    }
+
+    RegularFile* fileOut = NULL;
+    errcode = _syscallhandler_validateFileHelper(sys, fdOut, &fileOut);
+    if (errcode < 0) {
+        return syscallreturn_makeDoneErrno(-errcode);
+    }
+
+    /* Read the explicit offsets, if any. */
+    off64_t offsetIn = 0;
+    off64_t offsetOut = 0;
+
+    if (offsetInPtr.val && process_readPtr(rustsyscallhandler_getProcess(sys), &offsetIn,
+                                           offsetInPtr, sizeof(offsetIn)) != 0) {
+        return syscallreturn_makeDoneErrno(EFAULT);
+    }
+
+    if (offsetOutPtr.val && process_readPtr(rustsyscallhandler_getProcess(sys), &offsetOut,
+                                            offsetOutPtr, sizeof(offsetOut)) != 0) {
+        return syscallreturn_makeDoneErrno(EFAULT);
+    }
+
+    ssize_t result = regularfile_copyFileRange(fileIn, offsetInPtr.val ? &offsetIn : NULL, fileOut,
+                                               offsetOutPtr.val ? &offsetOut : NULL, len, flags);
+
+    /* Linux only updates the explicit offsets if bytes were copied. */
+    if (result > 0) {
+        if (offsetInPtr.val && process_writePtr(rustsyscallhandler_getProcess(sys), offsetInPtr,
+                                                &offsetIn, sizeof(offsetIn)) != 0) {
+            return syscallreturn_makeDoneErrno(EFAULT);
+        }
+
+        if (offsetOutPtr.val && process_writePtr(rustsyscallhandler_getProcess(sys), offsetOutPtr,
+                                                 &offsetOut, sizeof(offsetOut)) != 0) {
+            return syscallreturn_makeDoneErrno(EFAULT);
+        }
+    }
+
+    return syscallreturn_makeDoneI64(result);
+}
+
 SyscallReturn syscallhandler_readahead(SyscallHandler* sys, const SyscallArgs* args) {
     int fd = args->args[0].as_i64;
     off64_t offset = args->args[1].as_u64;
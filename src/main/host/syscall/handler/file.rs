@@ -4,9 +4,10 @@ use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 
 use crate::cshadow;
 use crate::host::descriptor::CompatFile;
+use crate::host::descriptor::descriptor_table::DescriptorTable;
 use crate::host::syscall::File;
-use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
-use crate::host::syscall::type_formatting::SyscallStringArg;
+use crate::host::syscall::handler::{LegacySyscallFn, SyscallContext, SyscallHandler};
+use crate::host::syscall::type_formatting::{SyscallModeArg, SyscallStringArg};
 use crate::host::syscall::types::{SyscallError, SyscallResult};
 
 impl SyscallHandler {
@@ -15,18 +16,65 @@ impl SyscallHandler {
         /* rv */ std::ffi::c_int,
         /* pathname */ SyscallStringArg,
         /* flags */ linux_api::fcntl::OFlag,
-        /* mode */ nix::sys::stat::Mode,
+        /* mode */ SyscallModeArg,
     );
     pub fn open(
         ctx: &mut SyscallContext,
-        _path: ForeignPtr<()>,
-        _flags: std::ffi::c_int,
+        path: ForeignPtr<()>,
+        flags: std::ffi::c_int,
         _mode: kernel_mode_t,
     ) -> SyscallResult {
+        if let Some(rv) = Self::open_proc_fd_dir(ctx, path.cast(), flags) {
+            return rv.map(Into::into);
+        }
+
         Self::legacy_syscall(cshadow::syscallhandler_open, ctx)
     }
 
-    log_syscall!(creat, /* rv */ std::ffi::c_int);
+    log_syscall!(
+        copy_file_range,
+        /* rv */ isize,
+        /* fd_in */ std::ffi::c_int,
+        /* off_in */ *const libc::loff_t,
+        /* fd_out */ std::ffi::c_int,
+        /* off_out */ *const libc::loff_t,
+        /* len */ usize,
+        /* flags */ std::ffi::c_uint,
+    );
+    pub fn copy_file_range(
+        ctx: &mut SyscallContext,
+        fd_in: std::ffi::c_int,
+        _off_in: ForeignPtr<libc::loff_t>,
+        fd_out: std::ffi::c_int,
+        _off_out: ForeignPtr<libc::loff_t>,
+        _len: usize,
+        _flags: std::ffi::c_uint,
+    ) -> SyscallResult {
+        // a negative fd isn't a valid descriptor
+        let fd_in = std::ffi::c_uint::try_from(fd_in).or(Err(Errno::EBADF))?;
+        let fd_out = std::ffi::c_uint::try_from(fd_out).or(Err(Errno::EBADF))?;
+
+        let desc_table = ctx.objs.thread.descriptor_table_borrow(ctx.objs.host);
+
+        // check that both descriptors exist before checking their types
+        let is_file_in_regular = Self::is_regular_file(&desc_table, fd_in)?;
+        let is_file_out_regular = Self::is_regular_file(&desc_table, fd_out)?;
+
+        // copy_file_range(2): "EINVAL: Either fd_in or fd_out is not a regular file."
+        if !is_file_in_regular || !is_file_out_regular {
+            return Err(Errno::EINVAL.into());
+        }
+
+        drop(desc_table);
+        Self::legacy_syscall(cshadow::syscallhandler_copy_file_range, ctx)
+    }
+
+    log_syscall!(
+        creat,
+        /* rv */ std::ffi::c_int,
+        /* pathname */ SyscallStringArg,
+        /* mode */ SyscallModeArg,
+    );
     pub fn creat(ctx: &mut SyscallContext) -> SyscallResult {
         Self::legacy_syscall(cshadow::syscallhandler_creat, ctx)
     }
@@ -41,7 +89,12 @@ impl SyscallHandler {
         Self::legacy_syscall(cshadow::syscallhandler_fallocate, ctx)
     }
 
-    log_syscall!(fchmod, /* rv */ std::ffi::c_int);
+    log_syscall!(
+        fchmod,
+        /* rv */ std::ffi::c_int,
+        /* fd */ std::ffi::c_uint,
+        /* mode */ SyscallModeArg,
+    );
     pub fn fchmod(ctx: &mut SyscallContext) -> SyscallResult {
         Self::legacy_syscall(cshadow::syscallhandler_fchmod, ctx)
     }
@@ -51,9 +104,13 @@ impl SyscallHandler {
         Self::legacy_syscall(cshadow::syscallhandler_fchown, ctx)
     }
 
-    log_syscall!(fdatasync, /* rv */ std::ffi::c_int);
-    pub fn fdatasync(ctx: &mut SyscallContext) -> SyscallResult {
-        Self::legacy_syscall(cshadow::syscallhandler_fdatasync, ctx)
+    log_syscall!(
+        fdatasync,
+        /* rv */ std::ffi::c_int,
+        /* fd */ std::ffi::c_uint,
+    );
+    pub fn fdatasync(ctx: &mut SyscallContext, fd: std::ffi::c_uint) -> SyscallResult {
+        Self::regular_file_legacy_syscall(ctx, fd, cshadow::syscallhandler_fdatasync)
     }
 
     log_syscall!(fgetxattr, /* rv */ std::ffi::c_int);
@@ -81,9 +138,13 @@ impl SyscallHandler {
         Self::legacy_syscall(cshadow::syscallhandler_fsetxattr, ctx)
     }
 
-    log_syscall!(fsync, /* rv */ std::ffi::c_int);
-    pub fn fsync(ctx: &mut SyscallContext) -> SyscallResult {
-        Self::legacy_syscall(cshadow::syscallhandler_fsync, ctx)
+    log_syscall!(
+        fsync,
+        /* rv */ std::ffi::c_int,
+        /* fd */ std::ffi::c_uint,
+    );
+    pub fn fsync(ctx: &mut SyscallContext, fd: std::ffi::c_uint) -> SyscallResult {
+        Self::regular_file_legacy_syscall(ctx, fd, cshadow::syscallhandler_fsync)
     }
 
     log_syscall!(ftruncate, /* rv */ std::ffi::c_int);
@@ -91,13 +152,43 @@ impl SyscallHandler {
         Self::legacy_syscall(cshadow::syscallhandler_ftruncate, ctx)
     }
 
-    log_syscall!(getdents, /* rv */ std::ffi::c_int);
-    pub fn getdents(ctx: &mut SyscallContext) -> SyscallResult {
+    log_syscall!(
+        getdents,
+        /* rv */ std::ffi::c_int,
+        /* fd */ std::ffi::c_uint,
+        /* dirp */ *const std::ffi::c_void,
+        /* count */ std::ffi::c_uint,
+    );
+    pub fn getdents(
+        ctx: &mut SyscallContext,
+        fd: std::ffi::c_uint,
+        dirp: ForeignPtr<u8>,
+        count: std::ffi::c_uint,
+    ) -> SyscallResult {
+        if let Some(dir) = Self::get_proc_fd_dir(ctx, fd) {
+            return Self::getdents_proc_fd_dir(ctx, &dir, dirp, count, false).map(Into::into);
+        }
+
         Self::legacy_syscall(cshadow::syscallhandler_getdents, ctx)
     }
 
-    log_syscall!(getdents64, /* rv */ std::ffi::c_int);
-    pub fn getdents64(ctx: &mut SyscallContext) -> SyscallResult {
+    log_syscall!(
+        getdents64,
+        /* rv */ std::ffi::c_int,
+        /* fd */ std::ffi::c_uint,
+        /* dirp */ *const std::ffi::c_void,
+        /* count */ std::ffi::c_uint,
+    );
+    pub fn getdents64(
+        ctx: &mut SyscallContext,
+        fd: std::ffi::c_uint,
+        dirp: ForeignPtr<u8>,
+        count: std::ffi::c_uint,
+    ) -> SyscallResult {
+        if let Some(dir) = Self::get_proc_fd_dir(ctx, fd) {
+            return Self::getdents_proc_fd_dir(ctx, &dir, dirp, count, true).map(Into::into);
+        }
+
         Self::legacy_syscall(cshadow::syscallhandler_getdents64, ctx)
     }
 
@@ -111,8 +202,8 @@ impl SyscallHandler {
     pub fn lseek(
         ctx: &mut SyscallContext,
         fd: std::ffi::c_uint,
-        _offset: linux_api::posix_types::kernel_off_t,
-        _whence: std::ffi::c_uint,
+        offset: linux_api::posix_types::kernel_off_t,
+        whence: std::ffi::c_uint,
     ) -> Result<linux_api::posix_types::kernel_off_t, SyscallError> {
         let desc_table = ctx.objs.thread.descriptor_table_borrow(ctx.objs.host);
 
@@ -129,6 +220,10 @@ impl SyscallHandler {
 
         match file.inner_file() {
             File::Pipe(_) => Err(Errno::ESPIPE.into()),
+            File::ProcFdDir(dir) => {
+                let position = dir.borrow_mut().seek(offset, whence)?;
+                Ok(position.try_into().unwrap())
+            }
             _ => {
                 warn_once_then_debug!("lseek() is not implemented for this type");
                 Err(Errno::ENOTSUP.into())
@@ -136,9 +231,42 @@ impl SyscallHandler {
         }
     }
 
-    log_syscall!(readahead, /* rv */ std::ffi::c_int);
-    pub fn readahead(ctx: &mut SyscallContext) -> SyscallResult {
-        Self::legacy_syscall(cshadow::syscallhandler_readahead, ctx)
+    log_syscall!(
+        readlink,
+        /* rv */ isize,
+        /* pathname */ SyscallStringArg,
+        /* buf */ *const std::ffi::c_void,
+        /* bufsiz */ usize,
+    );
+    pub fn readlink(
+        ctx: &mut SyscallContext,
+        path: ForeignPtr<std::ffi::c_char>,
+        buf: ForeignPtr<u8>,
+        buf_size: usize,
+    ) -> Result<isize, SyscallError> {
+        if let Some(rv) = Self::readlink_proc_fd(ctx, path, buf, buf_size) {
+            return rv;
+        }
+
+        Err(SyscallError::Native)
+    }
+
+    log_syscall!(
+        readahead,
+        /* rv */ std::ffi::c_int,
+        /* fd */ std::ffi::c_int,
+        /* offset */ linux_api::posix_types::kernel_off_t,
+        /* count */ usize,
+    );
+    pub fn readahead(
+        ctx: &mut SyscallContext,
+        fd: std::ffi::c_int,
+        _offset: linux_api::posix_types::kernel_off_t,
+        _count: usize,
+    ) -> SyscallResult {
+        // a negative fd isn't a valid descriptor
+        let fd = std::ffi::c_uint::try_from(fd).or(Err(Errno::EBADF))?;
+        Self::regular_file_legacy_syscall(ctx, fd, cshadow::syscallhandler_readahead)
     }
 
     log_syscall!(sync_file_range, /* rv */ std::ffi::c_int);
@@ -146,6 +274,35 @@ impl SyscallHandler {
         Self::legacy_syscall(cshadow::syscallhandler_sync_file_range, ctx)
     }
 
+    /// Run the C syscall handler for syscalls like `fsync()` that only apply to regular files. Like
+    /// linux, other file types (pipes, sockets, eventfds, etc) return `EINVAL`.
+    fn regular_file_legacy_syscall(
+        ctx: &mut SyscallContext,
+        fd: std::ffi::c_uint,
+        legacy_syscall_fn: LegacySyscallFn,
+    ) -> SyscallResult {
+        let desc_table = ctx.objs.thread.descriptor_table_borrow(ctx.objs.host);
+
+        if !Self::is_regular_file(&desc_table, fd)? {
+            return Err(Errno::EINVAL.into());
+        }
+
+        drop(desc_table);
+        Self::legacy_syscall(legacy_syscall_fn, ctx)
+    }
+
+    /// Returns `true` if the descriptor is a regular file, or `EBADF` if the descriptor doesn't
+    /// exist.
+    fn is_regular_file(desc_table: &DescriptorTable, fd: std::ffi::c_uint) -> Result<bool, Errno> {
+        Ok(match Self::get_descriptor(desc_table, fd)?.file() {
+            CompatFile::New(_) => false,
+            CompatFile::Legacy(file) => {
+                let file_type = unsafe { cshadow::legacyfile_getType(file.ptr()) };
+                file_type == cshadow::_LegacyFileType_DT_FILE
+            }
+        })
+    }
+
     log_syscall!(syncfs, /* rv */ std::ffi::c_int);
     pub fn syncfs(ctx: &mut SyscallContext) -> SyscallResult {
         Self::legacy_syscall(cshadow::syscallhandler_syncfs, ctx)
@@ -123,6 +123,9 @@ SyscallReturn syscallhandler_openat(SyscallHandler* sys, const SyscallArgs* args
         return syscallreturn_makeDoneErrno(-errcode);
     }
 
+    /* The process umask applies to the mode of any newly created file. */
+    mode &= ~process_getUmask(rustsyscallhandler_getProcess(sys));
+
     /* Create and open the file. */
     RegularFile* file_desc = regularfile_new();
     errcode = regularfile_openat(file_desc, dir_desc, pathname, flags & ~O_CLOEXEC, mode,
@@ -356,6 +359,7 @@ SyscallReturn syscallhandler_mkdirat(SyscallHandler* sys, const SyscallArgs* arg
     }
 
     const char* plugin_cwd = process_getWorkingDir(rustsyscallhandler_getProcess(sys));
+    mode &= ~process_getUmask(rustsyscallhandler_getProcess(sys));
 
     return syscallreturn_makeDoneI64(regularfile_mkdirat(dir_desc, pathname, mode, plugin_cwd));
 }
@@ -377,6 +381,7 @@ SyscallReturn syscallhandler_mknodat(SyscallHandler* sys, const SyscallArgs* arg
     }
 
     const char* plugin_cwd = process_getWorkingDir(rustsyscallhandler_getProcess(sys));
+    mode &= ~process_getUmask(rustsyscallhandler_getProcess(sys));
 
     return syscallreturn_makeDoneI64(
         regularfile_mknodat(dir_desc, pathname, mode, dev, plugin_cwd));
@@ -1,9 +1,11 @@
+use linux_api::errno::Errno;
 use linux_api::posix_types::kernel_mode_t;
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 
 use crate::cshadow;
+use crate::host::descriptor::FileType;
 use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
-use crate::host::syscall::type_formatting::SyscallStringArg;
+use crate::host::syscall::type_formatting::{SyscallModeArg, SyscallStringArg};
 use crate::host::syscall::types::SyscallResult;
 
 impl SyscallHandler {
@@ -13,15 +15,67 @@ impl SyscallHandler {
         /* dirfd */ std::ffi::c_int,
         /* pathname */ SyscallStringArg,
         /* flags */ linux_api::fcntl::OFlag,
-        /* mode */ nix::sys::stat::Mode,
+        /* mode */ SyscallModeArg,
     );
     pub fn openat(
         ctx: &mut SyscallContext,
-        _dir_fd: std::ffi::c_int,
-        _path: ForeignPtr<()>,
-        _flags: std::ffi::c_int,
+        dir_fd: std::ffi::c_int,
+        path: ForeignPtr<()>,
+        flags: std::ffi::c_int,
         _mode: kernel_mode_t,
     ) -> SyscallResult {
+        // the directory fd is ignored for absolute paths, which are the only paths handled here
+        if let Some(rv) = Self::open_proc_fd_dir(ctx, path.cast(), flags) {
+            return rv.map(Into::into);
+        }
+
+        // if the path can't be read, the C syscall handler will return the error
+        let is_absolute = ctx
+            .objs
+            .process
+            .memory_borrow()
+            .read(path.cast::<u8>())
+            .is_ok_and(|c| c == b'/');
+
+        // openat(2): "If pathname is absolute, then dirfd is ignored."
+        if is_absolute {
+            // the C syscall handler validates the directory fd even for absolute paths, so don't
+            // give it one
+            let mut args = *ctx.args;
+            args.args[0] = libc::AT_FDCWD.into();
+            let mut ctx = SyscallContext {
+                objs: ctx.objs,
+                args: &args,
+                handler: &mut *ctx.handler,
+            };
+            return Self::legacy_syscall(cshadow::syscallhandler_openat, &mut ctx);
+        }
+
+        if dir_fd != libc::AT_FDCWD {
+            let desc_table = ctx.objs.thread.descriptor_table_borrow(ctx.objs.host);
+
+            // openat(2): "EBADF: pathname is relative but dirfd is neither AT_FDCWD nor a valid
+            // file descriptor."
+            let file_type = Self::get_descriptor(&desc_table, dir_fd)?
+                .file()
+                .file_type();
+            drop(desc_table);
+
+            match file_type {
+                // may or may not be a directory; the C syscall handler will check
+                FileType::RegularFile => {}
+                FileType::ProcFdDir => {
+                    warn_once_then_debug!(
+                        "openat() relative to a /proc/<pid>/fd directory is not supported"
+                    );
+                    return Err(Errno::EOPNOTSUPP.into());
+                }
+                // openat(2): "ENOTDIR: pathname is a relative pathname and dirfd is a file
+                // descriptor referring to a file other than a directory."
+                _ => return Err(Errno::ENOTDIR.into()),
+            }
+        }
+
         Self::legacy_syscall(cshadow::syscallhandler_openat, ctx)
     }
 
@@ -35,12 +89,25 @@ impl SyscallHandler {
         Self::legacy_syscall(cshadow::syscallhandler_faccessat2, ctx)
     }
 
-    log_syscall!(fchmodat, /* rv */ std::ffi::c_int);
+    log_syscall!(
+        fchmodat,
+        /* rv */ std::ffi::c_int,
+        /* dirfd */ std::ffi::c_int,
+        /* pathname */ SyscallStringArg,
+        /* mode */ SyscallModeArg,
+    );
     pub fn fchmodat(ctx: &mut SyscallContext) -> SyscallResult {
         Self::legacy_syscall(cshadow::syscallhandler_fchmodat, ctx)
     }
 
-    log_syscall!(fchmodat2, /* rv */ std::ffi::c_int);
+    log_syscall!(
+        fchmodat2,
+        /* rv */ std::ffi::c_int,
+        /* dirfd */ std::ffi::c_int,
+        /* pathname */ SyscallStringArg,
+        /* mode */ SyscallModeArg,
+        /* flags */ std::ffi::c_uint,
+    );
     pub fn fchmodat2(ctx: &mut SyscallContext) -> SyscallResult {
         Self::legacy_syscall(cshadow::syscallhandler_fchmodat2, ctx)
     }
@@ -60,7 +127,13 @@ impl SyscallHandler {
         Self::legacy_syscall(cshadow::syscallhandler_linkat, ctx)
     }
 
-    log_syscall!(mkdirat, /* rv */ std::ffi::c_int);
+    log_syscall!(
+        mkdirat,
+        /* rv */ std::ffi::c_int,
+        /* dirfd */ std::ffi::c_int,
+        /* pathname */ SyscallStringArg,
+        /* mode */ SyscallModeArg,
+    );
     pub fn mkdirat(ctx: &mut SyscallContext) -> SyscallResult {
         Self::legacy_syscall(cshadow::syscallhandler_mkdirat, ctx)
     }
@@ -70,8 +143,26 @@ impl SyscallHandler {
         Self::legacy_syscall(cshadow::syscallhandler_mknodat, ctx)
     }
 
-    log_syscall!(readlinkat, /* rv */ std::ffi::c_int);
-    pub fn readlinkat(ctx: &mut SyscallContext) -> SyscallResult {
+    log_syscall!(
+        readlinkat,
+        /* rv */ isize,
+        /* dirfd */ std::ffi::c_int,
+        /* pathname */ SyscallStringArg,
+        /* buf */ *const std::ffi::c_void,
+        /* bufsiz */ usize,
+    );
+    pub fn readlinkat(
+        ctx: &mut SyscallContext,
+        _dir_fd: std::ffi::c_int,
+        path: ForeignPtr<std::ffi::c_char>,
+        buf: ForeignPtr<u8>,
+        buf_size: usize,
+    ) -> SyscallResult {
+        // the directory fd is ignored for absolute paths, which are the only paths handled here
+        if let Some(rv) = Self::readlink_proc_fd(ctx, path, buf, buf_size) {
+            return rv.map(Into::into);
+        }
+
         Self::legacy_syscall(cshadow::syscallhandler_readlinkat, ctx)
     }
 
@@ -6,6 +6,8 @@ use std::time::Duration;
 use linux_api::errno::Errno;
 use linux_api::syscall::SyscallNum;
 use shadow_shim_helper_rs::HostId;
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::option::FfiOption;
 use shadow_shim_helper_rs::shadow_syscalls::ShadowSyscallNum;
 use shadow_shim_helper_rs::simulation_time::SimulationTime;
 use shadow_shim_helper_rs::syscall_types::SyscallArgs;
@@ -15,13 +17,13 @@ use shadow_shim_helper_rs::util::SendPointer;
 use crate::core::worker::Worker;
 use crate::cshadow as c;
 use crate::host::context::ThreadContext;
-use crate::host::descriptor::Descriptor;
 use crate::host::descriptor::descriptor_table::{DescriptorHandle, DescriptorTable};
+use crate::host::descriptor::{Descriptor, File, FileState, OpenFile};
 use crate::host::process::ProcessId;
 use crate::host::syscall::formatter::log_syscall_simple;
 use crate::host::syscall::is_shadow_syscall;
 use crate::host::syscall::types::SyscallReturn;
-use crate::host::syscall::types::{SyscallError, SyscallResult};
+use crate::host::syscall::types::{Failed, SyscallError, SyscallResult};
 use crate::host::thread::ThreadId;
 use crate::utility::counter::Counter;
 
@@ -38,8 +40,10 @@ mod fileat;
 mod futex;
 mod ioctl;
 mod mman;
+mod pidfd;
 mod poll;
 mod prctl;
+mod proc_fd;
 mod random;
 mod resource;
 mod sched;
@@ -47,6 +51,7 @@ mod select;
 mod shadow;
 mod signal;
 mod socket;
+mod splice;
 mod stat;
 mod sysinfo;
 mod time;
@@ -383,6 +388,7 @@ impl SyscallHandler {
             SyscallNum::NR_close => handle!(close),
             SyscallNum::NR_close_range => handle!(close_range),
             SyscallNum::NR_connect => handle!(connect),
+            SyscallNum::NR_copy_file_range => handle!(copy_file_range),
             SyscallNum::NR_creat => handle!(creat),
             SyscallNum::NR_dup => handle!(dup),
             SyscallNum::NR_dup2 => handle!(dup2),
@@ -450,6 +456,8 @@ impl SyscallHandler {
             SyscallNum::NR_newfstatat => handle!(newfstatat),
             SyscallNum::NR_open => handle!(open),
             SyscallNum::NR_openat => handle!(openat),
+            SyscallNum::NR_pidfd_open => handle!(pidfd_open),
+            SyscallNum::NR_pidfd_send_signal => handle!(pidfd_send_signal),
             SyscallNum::NR_pipe => handle!(pipe),
             SyscallNum::NR_pipe2 => handle!(pipe2),
             SyscallNum::NR_poll => handle!(poll),
@@ -465,9 +473,11 @@ impl SyscallHandler {
             SyscallNum::NR_pwritev2 => handle!(pwritev2),
             SyscallNum::NR_read => handle!(read),
             SyscallNum::NR_readahead => handle!(readahead),
+            SyscallNum::NR_readlink => handle!(readlink),
             SyscallNum::NR_readlinkat => handle!(readlinkat),
             SyscallNum::NR_readv => handle!(readv),
             SyscallNum::NR_recvfrom => handle!(recvfrom),
+            SyscallNum::NR_recvmmsg => handle!(recvmmsg),
             SyscallNum::NR_recvmsg => handle!(recvmsg),
             SyscallNum::NR_renameat => handle!(renameat),
             SyscallNum::NR_renameat2 => handle!(renameat2),
@@ -494,11 +504,13 @@ impl SyscallHandler {
             SyscallNum::NR_sync_file_range => handle!(sync_file_range),
             SyscallNum::NR_syncfs => handle!(syncfs),
             SyscallNum::NR_sysinfo => handle!(sysinfo),
+            SyscallNum::NR_tee => handle!(tee),
             SyscallNum::NR_tgkill => handle!(tgkill),
             SyscallNum::NR_timerfd_create => handle!(timerfd_create),
             SyscallNum::NR_timerfd_gettime => handle!(timerfd_gettime),
             SyscallNum::NR_timerfd_settime => handle!(timerfd_settime),
             SyscallNum::NR_tkill => handle!(tkill),
+            SyscallNum::NR_umask => handle!(umask),
             SyscallNum::NR_uname => handle!(uname),
             SyscallNum::NR_unlinkat => handle!(unlinkat),
             SyscallNum::NR_utimensat => handle!(utimensat),
@@ -562,7 +574,6 @@ impl SyscallHandler {
             | SyscallNum::NR_madvise
             | SyscallNum::NR_mkdir
             | SyscallNum::NR_mknod
-            | SyscallNum::NR_readlink
             | SyscallNum::NR_removexattr
             | SyscallNum::NR_rename
             | SyscallNum::NR_rmdir
@@ -680,6 +691,78 @@ impl SyscallHandler {
         }
     }
 
+    /// Internal helper that returns a `Blocked` error for a syscall that should wait until `file`
+    /// has any of the `state` flags, or until the absolute `timeout` (if any) is reached. Whether
+    /// the syscall can be restarted after a signal handler depends on the file's `SA_RESTART`
+    /// support.
+    fn block_on_file(file: &File, state: FileState, timeout: Option<EmulatedTime>) -> SyscallError {
+        let restartable = file.borrow().supports_sa_restart();
+        let mut err = SyscallError::new_blocked_on_file(file.clone(), state, restartable);
+
+        if timeout.is_some() {
+            err.blocked_condition().unwrap().set_timeout(timeout);
+        }
+
+        err
+    }
+
+    /// Internal helper that returns the absolute timeout for a syscall that's about to block on a
+    /// socket with the receive timeout `recv_timeout` (`SO_RCVTIMEO`). The timeout is measured
+    /// from when the syscall first blocked, so it isn't extended each time the syscall is resumed.
+    /// Returns `EAGAIN` if the syscall was already blocked and its timeout has passed.
+    fn recv_timeout_deadline(
+        ctx: &SyscallContext,
+        recv_timeout: Option<SimulationTime>,
+    ) -> Result<Option<EmulatedTime>, Errno> {
+        let Some(recv_timeout) = recv_timeout else {
+            return Ok(None);
+        };
+
+        let now = Worker::current_time().unwrap();
+
+        // the timeout from when the syscall first blocked, if we were previously blocked
+        let prev_timeout = ctx
+            .objs
+            .thread
+            .syscall_condition()
+            .and_then(|x| x.timeout());
+
+        match prev_timeout {
+            Some(timeout) if timeout <= now => Err(Errno::EAGAIN),
+            Some(timeout) => Ok(Some(timeout)),
+            None => Ok(Some(now.saturating_add(recv_timeout))),
+        }
+    }
+
+    /// Internal helper that sets the timeout of a syscall that's about to block on a socket
+    /// receive, so that it's resumed and fails with `EAGAIN` once the socket's receive timeout
+    /// (`SO_RCVTIMEO`) passes. Does nothing if `result` isn't blocked.
+    fn apply_recv_timeout<T>(
+        ctx: &SyscallContext,
+        recv_timeout: Option<SimulationTime>,
+        result: &mut Result<T, SyscallError>,
+    ) {
+        let Some(cond) = result.as_mut().err().and_then(|e| e.blocked_condition()) else {
+            return;
+        };
+
+        match Self::recv_timeout_deadline(ctx, recv_timeout) {
+            Ok(None) => {}
+            Ok(timeout) => cond.set_timeout(timeout),
+            Err(e) => *result = Err(e.into()),
+        }
+    }
+
+    /// Internal helper that keeps `file` open while the syscall is blocked, so that the syscall
+    /// uses the same file when it's restarted even if the descriptor is closed in the meantime.
+    fn keep_file_open_while_blocked<T>(result: &mut Result<T, SyscallError>, file: OpenFile) {
+        if let Some(err) = result.as_mut().err() {
+            if let Some(cond) = err.blocked_condition() {
+                cond.set_active_file(file);
+            }
+        }
+    }
+
     /// Run a legacy C syscall handler.
     fn legacy_syscall<T: From<SyscallReg>>(
         syscall: LegacySyscallFn,
@@ -705,6 +788,56 @@ impl SyscallHandler {
 
         rv.map(Into::into)
     }
+
+    /// Run a syscall handler with the thread's signal mask temporarily replaced by `sigmask` (if
+    /// provided), as done by syscalls like `ppoll` and `pselect6`. The original mask is restored
+    /// when the syscall completes. If the syscall is interrupted by a signal, the original mask is
+    /// instead restored by the shim after it has run the signal handlers, so that a signal that was
+    /// only unblocked by `sigmask` is still handled.
+    fn with_temporary_sigmask<T>(
+        ctx: &mut SyscallContext,
+        sigmask: Option<linux_api::signal::sigset_t>,
+        f: impl FnOnce(&mut SyscallContext) -> Result<T, SyscallError>,
+    ) -> Result<T, SyscallError> {
+        let Some(sigmask) = sigmask else {
+            return f(ctx);
+        };
+
+        {
+            let shmem_lock = ctx.objs.host.shim_shmem_lock_borrow().unwrap();
+            let thread_shmem = ctx.objs.thread.shmem();
+            let mut thread_protected = thread_shmem.protected.borrow_mut(&shmem_lock.root);
+
+            // if the syscall was previously blocked, the temporary mask is already installed
+            if !ctx.handler.is_blocked() {
+                thread_protected.saved_blocked_signals =
+                    FfiOption::Some(thread_protected.blocked_signals);
+            }
+            thread_protected.blocked_signals = sigmask;
+        }
+
+        let rv = f(ctx);
+
+        let interrupted = matches!(
+            rv,
+            Err(SyscallError::Failed(Failed {
+                errno: Errno::EINTR,
+                ..
+            }))
+        );
+
+        if !interrupted && !matches!(rv, Err(SyscallError::Blocked(_))) {
+            let shmem_lock = ctx.objs.host.shim_shmem_lock_borrow().unwrap();
+            let thread_shmem = ctx.objs.thread.shmem();
+            let mut thread_protected = thread_shmem.protected.borrow_mut(&shmem_lock.root);
+
+            if let FfiOption::Some(mask) = thread_protected.saved_blocked_signals.take() {
+                thread_protected.blocked_signals = mask;
+            }
+        }
+
+        rv
+    }
 }
 
 impl std::ops::Drop for SyscallHandler {
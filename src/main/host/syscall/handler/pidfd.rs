@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use atomic_refcell::AtomicRefCell;
+use linux_api::errno::Errno;
+use linux_api::fcntl::{DescriptorFlags, OFlag};
+use linux_api::posix_types::kernel_pid_t;
+use shadow_shim_helper_rs::syscall_types::ForeignPtr;
+
+use crate::host::descriptor::descriptor_table::DescriptorHandle;
+use crate::host::descriptor::pidfd::PidFd;
+use crate::host::descriptor::{CompatFile, Descriptor, File, FileStatus, OpenFile};
+use crate::host::process::ProcessId;
+use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
+use crate::host::syscall::type_formatting::SyscallSignalArg;
+
+impl SyscallHandler {
+    log_syscall!(
+        pidfd_open,
+        /* rv */ std::ffi::c_int,
+        /* pid */ kernel_pid_t,
+        /* flags */ std::ffi::c_uint,
+    );
+    pub fn pidfd_open(
+        ctx: &mut SyscallContext,
+        pid: kernel_pid_t,
+        flags: std::ffi::c_uint,
+    ) -> Result<DescriptorHandle, Errno> {
+        // pidfd_open(2): "PIDFD_NONBLOCK: Return a nonblocking file descriptor."
+        let pidfd_nonblock = OFlag::O_NONBLOCK.bits() as std::ffi::c_uint;
+
+        // pidfd_open(2): "EINVAL: flags is not valid."
+        if flags & !pidfd_nonblock != 0 {
+            log::debug!("Invalid pidfd_open flags: {flags}");
+            return Err(Errno::EINVAL);
+        }
+
+        // pidfd_open(2): "EINVAL: pid is not valid."
+        if pid <= 0 {
+            return Err(Errno::EINVAL);
+        }
+
+        let pid = ProcessId::try_from(pid).or(Err(Errno::EINVAL))?;
+
+        // pidfd_open(2): "ESRCH: The process specified by pid does not exist."
+        let Some(process) = ctx.objs.host.process_borrow(pid) else {
+            log::debug!("Process {pid} not found");
+            return Err(Errno::ESRCH);
+        };
+        let process = &*process.borrow(ctx.objs.host.root());
+
+        let mut file_flags = FileStatus::empty();
+        if flags & pidfd_nonblock != 0 {
+            file_flags.insert(FileStatus::NONBLOCK);
+        }
+
+        // if the process is a zombie, the pidfd is readable immediately
+        let file = PidFd::new(pid, !process.is_running(), file_flags);
+        let file = Arc::new(AtomicRefCell::new(file));
+        process.add_pidfd(&file);
+
+        // pidfd_open(2): "The close-on-exec flag is set on the file descriptor."
+        let mut desc = Descriptor::new(CompatFile::New(OpenFile::new(File::PidFd(file))));
+        desc.set_flags(DescriptorFlags::FD_CLOEXEC);
+
+        let fd = ctx
+            .objs
+            .thread
+            .descriptor_table_borrow_mut(ctx.objs.host)
+            .register_descriptor(desc)
+            .or(Err(Errno::ENFILE))?;
+
+        log::trace!("pidfd_open() returning fd {fd}");
+
+        Ok(fd)
+    }
+
+    log_syscall!(
+        pidfd_send_signal,
+        /* rv */ std::ffi::c_int,
+        /* pidfd */ std::ffi::c_int,
+        /* sig */ SyscallSignalArg,
+        /* info */ *const std::ffi::c_void,
+        /* flags */ std::ffi::c_uint,
+    );
+    pub fn pidfd_send_signal(
+        ctx: &mut SyscallContext,
+        pidfd: std::ffi::c_int,
+        sig: std::ffi::c_int,
+        info: ForeignPtr<()>,
+        flags: std::ffi::c_uint,
+    ) -> Result<(), Errno> {
+        // pidfd_send_signal(2): "The flags argument is reserved for future use; currently, this
+        // argument must be specified as 0."
+        if flags != 0 {
+            return Err(Errno::EINVAL);
+        }
+
+        if !info.is_null() {
+            warn_once_then_debug!("pidfd_send_signal() with a non-null info is not supported");
+            return Err(Errno::EINVAL);
+        }
+
+        let pid = {
+            let desc_table = ctx.objs.thread.descriptor_table_borrow(ctx.objs.host);
+            let desc = Self::get_descriptor(&desc_table, pidfd)?;
+
+            // pidfd_send_signal(2): "EBADF: pidfd is not a valid PID file descriptor."
+            let CompatFile::New(file) = desc.file() else {
+                return Err(Errno::EBADF);
+            };
+            let File::PidFd(file) = file.inner_file() else {
+                return Err(Errno::EBADF);
+            };
+
+            file.borrow().pid()
+        };
+
+        // pidfd_send_signal(2): "ESRCH: The target process does not exist (i.e., it has
+        // terminated and been waited on)."
+        let Some(target_process) = ctx.objs.host.process_borrow(pid) else {
+            log::debug!("Process {pid} not found");
+            return Err(Errno::ESRCH);
+        };
+        let target_process = &*target_process.borrow(ctx.objs.host.root());
+
+        // linux also returns ESRCH for zombie processes
+        if !target_process.is_running() {
+            log::debug!("Process {pid} has exited");
+            return Err(Errno::ESRCH);
+        }
+
+        Self::signal_process(ctx.objs, target_process, sig)
+    }
+}
@@ -26,6 +26,34 @@
 // Helpers
 ///////////////////////////////////////////////////////////
 
+// Returns the emulated time at which a poll that blocks with the given timeout should return, or
+// EMUTIME_INVALID if it should block indefinitely (a NULL timeout). All of the poll and select
+// variants convert their timeout to a timespec before calling this, so this is the only place
+// where a timeout is converted to a deadline.
+//
+// Emulated time has a granularity of one nanosecond, which is the finest granularity of any of
+// the timeout types (milliseconds for poll, microseconds for select, and nanoseconds for ppoll and
+// pselect6). The timeout is therefore never rounded: the deadline is exactly the current time plus
+// the timeout, so the wake time depends only on the simulation clock and is identical across
+// runs. A deadline that is too large to represent is saturated to EMUTIME_MAX.
+static CEmulatedTime _syscallhandler_getPollDeadline(const struct timespec* timeout) {
+    if (!timeout) {
+        return EMUTIME_INVALID;
+    }
+
+    CSimulationTime duration = simtime_from_timespec(*timeout);
+    if (duration == SIMTIME_INVALID) {
+        return EMUTIME_MAX;
+    }
+
+    CEmulatedTime deadline = emutime_add_simtime(worker_getCurrentEmulatedTime(), duration);
+    if (deadline == EMUTIME_INVALID) {
+        return EMUTIME_MAX;
+    }
+
+    return deadline;
+}
+
 static void _syscallhandler_getPollEventsHelper(const Descriptor* cdesc, struct pollfd* pfd) {
     // Handle legacy and non-legacy files. This will be NULL if it's not a legacy file.
     LegacyFile* ldesc = descriptor_asLegacyFile(cdesc);
@@ -57,6 +85,21 @@ static void _syscallhandler_getPollEventsHelper(const Descriptor* cdesc, struct
             (dstat & FileState_WRITABLE)) {
             pfd->revents |= POLLOUT;
         }
+        if ((pfd->events & POLLPRI) && (dstat & FileState_ACTIVE) &&
+            (dstat & FileState_PRIORITY)) {
+            pfd->revents |= POLLPRI;
+        }
+        if ((pfd->events & POLLRDHUP) && (dstat & FileState_ACTIVE) &&
+            (dstat & FileState_RDHUP)) {
+            pfd->revents |= POLLRDHUP;
+        }
+        // POLLHUP and POLLERR are always reported, even if not requested
+        if ((dstat & FileState_ACTIVE) && (dstat & FileState_HUP)) {
+            pfd->revents |= POLLHUP;
+        }
+        if ((dstat & FileState_ACTIVE) && (dstat & FileState_ERR)) {
+            pfd->revents |= POLLERR;
+        }
     }
 }
 
@@ -113,6 +156,12 @@ static void _syscallhandler_registerPollFDs(SyscallHandler* sys, struct pollfd*
         if (pfd->events & POLLOUT) {
             epev.events |= EPOLLOUT;
         }
+        if (pfd->events & POLLPRI) {
+            epev.events |= EPOLLPRI;
+        }
+        if (pfd->events & POLLRDHUP) {
+            epev.events |= EPOLLRDHUP;
+        }
 
         if (epev.events) {
             epoll_control(rustsyscallhandler_getEpoll(sys), EPOLL_CTL_ADD, pfd->fd, desc, &epev,
@@ -130,6 +179,7 @@ SyscallReturn _syscallhandler_pollHelper(SyscallHandler* sys, struct pollfd* fds
 
     // Block or not depending on the timeout values
     if (num_ready == 0) {
+        // a zero timeout is a non-blocking poll
         bool dont_block = timeout && timeout->tv_sec == 0 && timeout->tv_nsec == 0;
 
         if (dont_block || rustsyscallhandler_didListenTimeoutExpire(sys)) {
@@ -152,10 +202,9 @@ SyscallReturn _syscallhandler_pollHelper(SyscallHandler* sys, struct pollfd* fds
                                         .object = (LegacyFile*)rustsyscallhandler_getEpoll(sys),
                                         .state = FileState_READABLE};
             SysCallCondition* cond = syscallcondition_new(trigger);
-            if (timeout && (timeout->tv_sec > 0 || timeout->tv_nsec > 0)) {
-                syscallcondition_setTimeout(cond, worker_getCurrentEmulatedTime() +
-                                                      timeout->tv_sec * SIMTIME_ONE_SECOND +
-                                                      timeout->tv_nsec * SIMTIME_ONE_NANOSECOND);
+            CEmulatedTime deadline = _syscallhandler_getPollDeadline(timeout);
+            if (deadline != EMUTIME_INVALID) {
+                syscallcondition_setTimeout(cond, deadline);
             }
 
             // We either use our timer as a timeout, or no timeout
@@ -210,6 +259,9 @@ SyscallReturn syscallhandler_poll(SyscallHandler* sys, const SyscallArgs* args)
     int result = _syscallhandler_checkPollArgs(fds_ptr, nfds);
     if (result != 0) {
         return syscallreturn_makeDoneErrno(-result);
+    } else if (timeout_millis < 0) {
+        // A negative timeout means an infinite timeout
+        return _syscallhandler_pollHelperUntypedForeignPtr(sys, fds_ptr, nfds, NULL);
     } else {
         struct timespec timeout =
             (struct timespec){.tv_sec = timeout_millis / MILLIS_PER_SEC,
@@ -242,9 +294,10 @@ SyscallReturn syscallhandler_ppoll(SyscallHandler* sys, const SyscallArgs* args)
             return syscallreturn_makeDoneErrno(EFAULT);
         }
 
-        // Negative time values in the struct are invalid
-        if (ts_timeout_val.tv_sec < 0 || ts_timeout_val.tv_nsec < 0) {
-            trace("negative timeout given in timespec arg, returning EINVAL");
+        // Negative or unnormalized time values in the struct are invalid
+        if (ts_timeout_val.tv_sec < 0 || ts_timeout_val.tv_nsec < 0 ||
+            ts_timeout_val.tv_nsec >= SIMTIME_ONE_SECOND) {
+            trace("invalid timeout given in timespec arg, returning EINVAL");
             return syscallreturn_makeDoneErrno(EINVAL);
         }
     }
@@ -1,3 +1,4 @@
+use linux_api::errno::Errno;
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 
 use crate::cshadow as c;
@@ -35,9 +36,22 @@ impl SyscallHandler {
         _ufds: ForeignPtr<linux_api::poll::pollfd>,
         _nfds: std::ffi::c_uint,
         _tsp: ForeignPtr<linux_api::time::kernel_timespec>,
-        _sigmask: ForeignPtr<linux_api::signal::sigset_t>,
-        _sigsetsize: libc::size_t,
+        sigmask_ptr: ForeignPtr<linux_api::signal::sigset_t>,
+        sigsetsize: libc::size_t,
     ) -> Result<std::ffi::c_int, SyscallError> {
-        Self::legacy_syscall(c::syscallhandler_ppoll, ctx)
+        // ppoll(2): "If sigmask is specified as NULL, then no signal mask manipulation is
+        // performed"
+        let sigmask = if sigmask_ptr.is_null() {
+            None
+        } else {
+            if sigsetsize != size_of::<linux_api::signal::sigset_t>() {
+                return Err(Errno::EINVAL.into());
+            }
+            Some(ctx.objs.process.memory_borrow().read(sigmask_ptr)?)
+        };
+
+        Self::with_temporary_sigmask(ctx, sigmask, |ctx| {
+            Self::legacy_syscall(c::syscallhandler_ppoll, ctx)
+        })
     }
 }
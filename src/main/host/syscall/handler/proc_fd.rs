@@ -0,0 +1,421 @@
+//! Emulation of the `/proc/<pid>/fd` directories. Paths within these directories would otherwise
+//! be resolved by linux, which would list shadow's (or the managed process') native file
+//! descriptors rather than the simulated descriptors.
+
+use std::ffi::CStr;
+use std::os::unix::ffi::OsStringExt;
+use std::sync::Arc;
+
+use atomic_refcell::AtomicRefCell;
+use linux_api::errno::Errno;
+use linux_api::fcntl::{DescriptorFlags, OFlag};
+use shadow_shim_helper_rs::syscall_types::{ForeignArrayPtr, ForeignPtr};
+
+use crate::cshadow;
+use crate::host::descriptor::descriptor_table::{DescriptorHandle, DescriptorTable};
+use crate::host::descriptor::proc_fd_dir::ProcFdDir;
+use crate::host::descriptor::{CompatFile, Descriptor, File, FileStatus, FileType, OpenFile};
+use crate::host::process::ProcessId;
+use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
+use crate::host::syscall::types::SyscallError;
+
+/// A path within a `/proc/<pid>/fd` directory.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ProcFdPath {
+    /// The `/proc/<pid>/fd` directory.
+    Dir(ProcessId),
+    /// The `/proc/<pid>/fd/<fd>` link.
+    Link(ProcessId, DescriptorHandle),
+}
+
+impl ProcFdPath {
+    /// Parse an absolute path, where `current_pid` is the process that `/proc/self` refers to.
+    /// Returns `None` if the path isn't within a `/proc/<pid>/fd` directory.
+    fn parse(path: &CStr, current_pid: ProcessId) -> Option<Self> {
+        let path = path.to_bytes();
+
+        if !path.starts_with(b"/") {
+            return None;
+        }
+
+        let components: Vec<&[u8]> = path
+            .split(|c| *c == b'/')
+            .filter(|c| !c.is_empty() && **c != *b".")
+            .collect();
+
+        let parse_u32 = |x: &[u8]| -> Option<u32> {
+            // don't accept a leading '+' or leading zeros
+            if x.is_empty() || !x.iter().all(u8::is_ascii_digit) || (x.len() > 1 && x[0] == b'0') {
+                return None;
+            }
+            std::str::from_utf8(x).ok()?.parse().ok()
+        };
+
+        let pid = match *components.get(1)? {
+            // the fd table is shared by all threads in shadow, so "thread-self" is treated the
+            // same as "self"
+            b"self" | b"thread-self" => current_pid,
+            x => ProcessId::try_from(parse_u32(x)?).ok()?,
+        };
+
+        match components[..] {
+            [b"proc", _, b"fd"] => Some(Self::Dir(pid)),
+            [b"proc", _, b"fd", fd] => {
+                Some(Self::Link(pid, DescriptorHandle::new(parse_u32(fd)?)?))
+            }
+            _ => None,
+        }
+    }
+
+    fn pid(&self) -> ProcessId {
+        match self {
+            Self::Dir(pid) | Self::Link(pid, _) => *pid,
+        }
+    }
+}
+
+impl SyscallHandler {
+    /// Open a `/proc/<pid>/fd` directory. Returns `None` if the path isn't a `/proc/<pid>/fd`
+    /// directory of a process on this host, in which case the caller should open the path like
+    /// any other path.
+    pub(super) fn open_proc_fd_dir(
+        ctx: &mut SyscallContext,
+        path: ForeignPtr<std::ffi::c_char>,
+        flags: std::ffi::c_int,
+    ) -> Option<Result<DescriptorHandle, SyscallError>> {
+        let Some(ProcFdPath::Dir(pid)) = Self::read_proc_fd_path(ctx, path) else {
+            return None;
+        };
+
+        let flags = OFlag::from_bits_truncate(flags);
+
+        // open(2): "EISDIR: pathname refers to a directory and the access requested involved
+        // writing"
+        if flags.intersects(OFlag::O_WRONLY | OFlag::O_RDWR | OFlag::O_CREAT | OFlag::O_TRUNC) {
+            return Some(Err(Errno::EISDIR.into()));
+        }
+
+        if flags.contains(OFlag::O_PATH) {
+            warn_once_then_debug!("O_PATH is not supported for /proc/<pid>/fd directories");
+        }
+
+        let (status, _) = FileStatus::from_o_flags(flags);
+        let file = Arc::new(AtomicRefCell::new(ProcFdDir::new(pid, status)));
+
+        let mut desc = Descriptor::new(CompatFile::New(OpenFile::new(File::ProcFdDir(file))));
+        if flags.contains(OFlag::O_CLOEXEC) {
+            desc.set_flags(DescriptorFlags::FD_CLOEXEC);
+        }
+
+        let fd = ctx
+            .objs
+            .thread
+            .descriptor_table_borrow_mut(ctx.objs.host)
+            .register_descriptor(desc)
+            .or(Err(Errno::ENFILE));
+
+        Some(fd.map_err(Into::into))
+    }
+
+    /// Read a `/proc/<pid>/fd/<fd>` link. Returns `None` if the path isn't a `/proc/<pid>/fd/<fd>`
+    /// link of a process on this host, in which case the caller should read the link like any
+    /// other path.
+    pub(super) fn readlink_proc_fd(
+        ctx: &mut SyscallContext,
+        path: ForeignPtr<std::ffi::c_char>,
+        buf: ForeignPtr<u8>,
+        buf_size: usize,
+    ) -> Option<Result<isize, SyscallError>> {
+        let Some(ProcFdPath::Link(pid, fd)) = Self::read_proc_fd_path(ctx, path) else {
+            return None;
+        };
+
+        // readlink(2): "EINVAL: bufsiz is not positive"
+        if buf_size == 0 {
+            return Some(Err(Errno::EINVAL.into()));
+        }
+
+        let target = Self::with_proc_descriptor_table(ctx, pid, |desc_table| {
+            // a closed fd doesn't have an entry in the directory
+            let desc = desc_table.get(fd).ok_or(Errno::ENOENT)?;
+            proc_fd_link_target(desc.file())
+        });
+
+        let target = match target.and_then(|x| x) {
+            Ok(x) => x,
+            Err(e) => return Some(Err(e.into())),
+        };
+
+        // readlink(2): "readlink() does not append a terminating null byte to buf. It will
+        // (silently) truncate the contents (to a length of bufsiz characters), in case the buffer
+        // is too small to hold all of the contents."
+        let len = std::cmp::min(target.len(), buf_size);
+
+        let rv = ctx
+            .objs
+            .process
+            .memory_borrow_mut()
+            .copy_to_ptr(ForeignArrayPtr::new(buf, len), &target[..len]);
+
+        Some(rv.map(|()| len.try_into().unwrap()).map_err(Into::into))
+    }
+
+    /// Fill `dirp` with `linux_dirent64` entries (or `linux_dirent` entries if not `is_64`) for a
+    /// `/proc/<pid>/fd` directory. An entry is generated for each of the process' open
+    /// descriptors, starting at the directory's current position.
+    pub(super) fn getdents_proc_fd_dir(
+        ctx: &mut SyscallContext,
+        dir: &Arc<AtomicRefCell<ProcFdDir>>,
+        dirp: ForeignPtr<u8>,
+        count: std::ffi::c_uint,
+        is_64: bool,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        let (pid, start) = {
+            let dir = dir.borrow();
+            (dir.pid(), dir.position())
+        };
+
+        // the directory offset, name, and type of each remaining entry
+        let entries: Vec<(u64, Vec<u8>, u8)> =
+            Self::with_proc_descriptor_table(ctx, pid, |table| {
+                let dots = [
+                    (0, b".".to_vec(), libc::DT_DIR),
+                    (1, b"..".to_vec(), libc::DT_DIR),
+                ];
+                let fds = table.file_types().map(|(fd, _kind, _file_type)| {
+                    (u64::from(fd) + 2, fd.to_string().into_bytes(), libc::DT_LNK)
+                });
+
+                dots.into_iter()
+                    .chain(fds)
+                    .filter(|(offset, _, _)| *offset >= start)
+                    .collect()
+            })?;
+
+        let count = usize::try_from(count).unwrap();
+        let mut buf = Vec::new();
+        let mut position = start;
+
+        for (offset, name, d_type) in entries {
+            let dirent = dirent_bytes(offset + 1, &name, d_type, is_64);
+
+            if buf.len() + dirent.len() > count {
+                // getdents(2): "EINVAL: Result buffer is too small."
+                if buf.is_empty() {
+                    return Err(Errno::EINVAL.into());
+                }
+                break;
+            }
+
+            buf.extend_from_slice(&dirent);
+            position = offset + 1;
+        }
+
+        ctx.objs
+            .process
+            .memory_borrow_mut()
+            .copy_to_ptr(ForeignArrayPtr::new(dirp, buf.len()), &buf)?;
+
+        dir.borrow_mut().set_position(position);
+
+        Ok(buf.len().try_into().unwrap())
+    }
+
+    /// Returns the directory if `fd` refers to a `/proc/<pid>/fd` directory.
+    pub(super) fn get_proc_fd_dir(
+        ctx: &SyscallContext,
+        fd: std::ffi::c_uint,
+    ) -> Option<Arc<AtomicRefCell<ProcFdDir>>> {
+        let desc_table = ctx.objs.thread.descriptor_table_borrow(ctx.objs.host);
+
+        // invalid fds are handled by the caller
+        let CompatFile::New(file) = Self::get_descriptor(&desc_table, fd).ok()?.file() else {
+            return None;
+        };
+        let File::ProcFdDir(dir) = file.inner_file() else {
+            return None;
+        };
+
+        Some(Arc::clone(dir))
+    }
+
+    /// Read the path string and parse it as a path within a `/proc/<pid>/fd` directory of a
+    /// process on this host.
+    fn read_proc_fd_path(
+        ctx: &SyscallContext,
+        path: ForeignPtr<std::ffi::c_char>,
+    ) -> Option<ProcFdPath> {
+        let mut path_buf = [0u8; linux_api::limits::PATH_MAX];
+        let path_buf_capacity = path_buf.len();
+
+        // if the path can't be read, let the caller's normal path handling return the error
+        let path = ctx
+            .objs
+            .process
+            .memory_borrow()
+            .copy_str_from_ptr(
+                &mut path_buf,
+                ForeignArrayPtr::new(path.cast::<u8>(), path_buf_capacity),
+            )
+            .ok()?;
+
+        let path = ProcFdPath::parse(path, ctx.objs.process.id())?;
+
+        // other pids don't refer to simulated processes on this host
+        if path.pid() != ctx.objs.process.id() && ctx.objs.host.process_borrow(path.pid()).is_none()
+        {
+            return None;
+        }
+
+        Some(path)
+    }
+
+    /// Run `f` with the descriptor table of process `pid`. Returns `ENOENT` if the process has
+    /// exited.
+    fn with_proc_descriptor_table<T>(
+        ctx: &SyscallContext,
+        pid: ProcessId,
+        f: impl FnOnce(&DescriptorTable) -> T,
+    ) -> Result<T, Errno> {
+        if pid == ctx.objs.process.id() {
+            return Ok(f(&ctx.objs.thread.descriptor_table_borrow(ctx.objs.host)));
+        }
+
+        let host = ctx.objs.host;
+        let process = host.process_borrow(pid).ok_or(Errno::ENOENT)?;
+        let process = process.borrow(host.root());
+        let thread = process
+            .first_live_thread_borrow(host.root())
+            .ok_or(Errno::ENOENT)?;
+        let thread = thread.borrow(host.root());
+
+        Ok(f(&thread.descriptor_table_borrow(host)))
+    }
+}
+
+/// The target of the `/proc/<pid>/fd/<fd>` link for the file, for example "pipe:[ino]".
+fn proc_fd_link_target(file: &CompatFile) -> Result<Vec<u8>, Errno> {
+    // shadow doesn't assign inode numbers to pipes and sockets (their `st_ino` is 0), so we use
+    // the same inode number here
+    let target = match file.file_type() {
+        FileType::Pipe => "pipe:[0]".to_string(),
+        FileType::Socket => "socket:[0]".to_string(),
+        FileType::EventFd => "anon_inode:[eventfd]".to_string(),
+        FileType::TimerFd => "anon_inode:[timerfd]".to_string(),
+        FileType::Epoll => "anon_inode:[eventpoll]".to_string(),
+        FileType::PidFd => "anon_inode:[pidfd]".to_string(),
+        FileType::ProcFdDir => {
+            let CompatFile::New(file) = file else {
+                unreachable!();
+            };
+            let File::ProcFdDir(dir) = file.inner_file() else {
+                unreachable!();
+            };
+            format!("/proc/{}/fd", dir.borrow().pid())
+        }
+        FileType::RegularFile => {
+            let CompatFile::Legacy(file) = file else {
+                unreachable!();
+            };
+            let os_fd = unsafe {
+                cshadow::regularfile_getOSBackedFD(file.ptr() as *mut cshadow::RegularFile)
+            };
+
+            // in-memory files (ex: "/etc/hosts") don't have a native file to get a path from
+            if os_fd < 0 {
+                warn_once_then_debug!("Can't get the path of an in-memory file");
+                return Err(Errno::ENOENT);
+            }
+
+            // shadow opened the native file, so use shadow's own procfs link to get the path
+            let path = std::fs::read_link(format!("/proc/self/fd/{os_fd}"))
+                .map_err(|e| Errno::try_from(e).unwrap_or(Errno::ENOENT))?;
+            return Ok(path.into_os_string().into_vec());
+        }
+    };
+
+    Ok(target.into_bytes())
+}
+
+/// Returns the bytes of a `linux_dirent64` (or `linux_dirent` if not `is_64`), padded to an 8 byte
+/// boundary. `next_offset` is the directory offset of the following entry.
+fn dirent_bytes(next_offset: u64, name: &[u8], d_type: u8, is_64: bool) -> Vec<u8> {
+    // shadow doesn't assign inode numbers, but linux skips entries with an inode number of 0
+    const D_INO: u64 = 1;
+
+    let mut bytes = Vec::new();
+
+    if is_64 {
+        // struct linux_dirent64 { u64 d_ino; s64 d_off; u16 d_reclen; u8 d_type; char d_name[]; }
+        let len = (8 + 8 + 2 + 1 + name.len() + 1).next_multiple_of(8);
+        bytes.extend_from_slice(&D_INO.to_ne_bytes());
+        bytes.extend_from_slice(&next_offset.to_ne_bytes());
+        bytes.extend_from_slice(&u16::try_from(len).unwrap().to_ne_bytes());
+        bytes.push(d_type);
+        bytes.extend_from_slice(name);
+        bytes.resize(len, 0);
+    } else {
+        // struct linux_dirent { unsigned long d_ino; unsigned long d_off; unsigned short d_reclen;
+        // char d_name[]; } followed by a null byte and the file type in the last byte
+        let len = (8 + 8 + 2 + name.len() + 2).next_multiple_of(8);
+        bytes.extend_from_slice(&D_INO.to_ne_bytes());
+        bytes.extend_from_slice(&next_offset.to_ne_bytes());
+        bytes.extend_from_slice(&u16::try_from(len).unwrap().to_ne_bytes());
+        bytes.extend_from_slice(name);
+        bytes.resize(len - 1, 0);
+        bytes.push(d_type);
+    }
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_fd_path() {
+        let pid = ProcessId::try_from(1000u32).unwrap();
+        let other = ProcessId::try_from(1001u32).unwrap();
+        let fd = |x| DescriptorHandle::new(x).unwrap();
+        let parse = |x: &CStr| ProcFdPath::parse(x, pid);
+
+        assert_eq!(parse(c"/proc/self/fd"), Some(ProcFdPath::Dir(pid)));
+        assert_eq!(parse(c"/proc/self/fd/"), Some(ProcFdPath::Dir(pid)));
+        assert_eq!(parse(c"//proc/./self/fd"), Some(ProcFdPath::Dir(pid)));
+        assert_eq!(parse(c"/proc/thread-self/fd"), Some(ProcFdPath::Dir(pid)));
+        assert_eq!(parse(c"/proc/1001/fd"), Some(ProcFdPath::Dir(other)));
+        assert_eq!(
+            parse(c"/proc/self/fd/3"),
+            Some(ProcFdPath::Link(pid, fd(3)))
+        );
+        assert_eq!(
+            parse(c"/proc/1001/fd/0"),
+            Some(ProcFdPath::Link(other, fd(0)))
+        );
+
+        assert_eq!(parse(c"proc/self/fd"), None);
+        assert_eq!(parse(c"/proc/self"), None);
+        assert_eq!(parse(c"/proc/self/fdinfo"), None);
+        assert_eq!(parse(c"/proc/self/fd/03"), None);
+        assert_eq!(parse(c"/proc/self/fd/+3"), None);
+        assert_eq!(parse(c"/proc/self/fd/3/x"), None);
+        assert_eq!(parse(c"/proc/abc/fd"), None);
+        assert_eq!(parse(c"/proc/self/fd/99999999999"), None);
+    }
+
+    #[test]
+    fn test_dirent_bytes() {
+        let dirent = dirent_bytes(5, b"12", libc::DT_LNK, true);
+        assert_eq!(dirent.len(), 24);
+        assert_eq!(u16::from_ne_bytes(dirent[16..18].try_into().unwrap()), 24);
+        assert_eq!(dirent[18], libc::DT_LNK);
+        assert_eq!(&dirent[19..22], b"12\0");
+
+        let dirent = dirent_bytes(5, b"12", libc::DT_LNK, false);
+        assert_eq!(dirent.len(), 24);
+        assert_eq!(u16::from_ne_bytes(dirent[16..18].try_into().unwrap()), 24);
+        assert_eq!(&dirent[18..21], b"12\0");
+        assert_eq!(dirent[23], libc::DT_LNK);
+    }
+}
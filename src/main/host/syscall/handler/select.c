@@ -71,9 +71,10 @@ static SyscallReturn _syscallhandler_select_helper(SyscallHandler* sys, int nfds
             pfd->events |= POLLOUT;
         }
         if (FD_ISSET(i, &exceptfds)) {
-            // We need poll to process this slot to check for EBADF
+            // We also need poll to process this slot to check for EBADF
             trace("select wanting exceptions for fd %i", i);
             pfd->fd = i; // poll will process this slot
+            pfd->events |= POLLPRI;
         }
     }
 
@@ -102,8 +103,16 @@ static SyscallReturn _syscallhandler_select_helper(SyscallHandler* sys, int nfds
             continue;
         }
 
-        // The exceptional states listed in `man select` don't apply in Shadow,
-        // but POLLNVAL corresponds to an EBADF error.
+        // The only exceptional state listed in `man select` that applies in
+        // Shadow is out-of-band data on a TCP socket, and POLLNVAL corresponds
+        // to an EBADF error. Like linux, a hangup is reported as readable and an
+        // error as both readable and writable.
+        if ((pfd->revents & POLLHUP) && (pfd->events & POLLIN)) {
+            pfd->revents |= POLLIN;
+        }
+        if (pfd->revents & POLLERR) {
+            pfd->revents |= (pfd->events & (POLLIN | POLLOUT));
+        }
         if (pfd->revents & POLLIN) {
             trace("select found fd %i readable", i);
             FD_SET(i, &readfds);
@@ -114,6 +123,11 @@ static SyscallReturn _syscallhandler_select_helper(SyscallHandler* sys, int nfds
             FD_SET(i, &writefds);
             num_set_bits++;
         }
+        if (pfd->revents & POLLPRI) {
+            trace("select found fd %i has an exceptional condition", i);
+            FD_SET(i, &exceptfds);
+            num_set_bits++;
+        }
         if (pfd->revents & POLLNVAL) {
             trace("select found bad fd %i", i);
             num_bad_fds++;
@@ -165,9 +179,10 @@ static int _syscallhandler_check_nfds(int nfds) {
 
 static int _syscallhandler_check_timeout(const struct timespec* timeout) {
     // NULL timeout is allowed, it means block indefinitely.
-    // Negative time values in the struct are invalid.
-    if (timeout != NULL && (timeout->tv_sec < 0 || timeout->tv_nsec < 0)) {
-        trace("negative timeout given in timespec arg, returning EINVAL");
+    // Negative or unnormalized time values in the struct are invalid.
+    if (timeout != NULL &&
+        (timeout->tv_sec < 0 || timeout->tv_nsec < 0 || timeout->tv_nsec >= SIMTIME_ONE_SECOND)) {
+        trace("invalid timeout given in timespec arg, returning EINVAL");
         return -EINVAL;
     } else {
         return 0;
@@ -207,10 +222,16 @@ SyscallReturn syscallhandler_select(SyscallHandler* sys, const SyscallArgs* args
             return syscallreturn_makeDoneErrno(EFAULT);
         }
 
-        // Convert timeval to timespec.
+        // Convert timeval to timespec. Like linux, we normalize a tv_usec of more than one second.
         ts_timeout_val.tv_sec = tv_timeout_val.tv_sec;
 #define NSECS_PER_USEC 1000
-        ts_timeout_val.tv_nsec = tv_timeout_val.tv_usec * NSECS_PER_USEC;
+#define USECS_PER_SEC 1000000
+        if (tv_timeout_val.tv_usec >= 0) {
+            ts_timeout_val.tv_sec += tv_timeout_val.tv_usec / USECS_PER_SEC;
+            ts_timeout_val.tv_nsec = (tv_timeout_val.tv_usec % USECS_PER_SEC) * NSECS_PER_USEC;
+        } else {
+            ts_timeout_val.tv_nsec = tv_timeout_val.tv_usec * NSECS_PER_USEC;
+        }
     }
 
     result = _syscallhandler_check_timeout(timeout_ptr.val ? &ts_timeout_val : NULL);
@@ -228,7 +249,7 @@ SyscallReturn syscallhandler_pselect6(SyscallHandler* sys, const SyscallArgs* ar
     UntypedForeignPtr writefds_ptr = args->args[2].as_ptr;  // fd_set*
     UntypedForeignPtr exceptfds_ptr = args->args[3].as_ptr; // fd_set*
     UntypedForeignPtr timeout_ptr = args->args[4].as_ptr;   // const struct timespec*
-    // TODO how do we handle the sigmask arg and behavior
+    // the sigmask arg is handled by the rust syscall handler
 
     trace("select was called with nfds=%i, readfds=%p, writefds=%p, exceptfds=%p, and timeout=%p",
           nfds, (void*)readfds_ptr.val, (void*)writefds_ptr.val, (void*)exceptfds_ptr.val,
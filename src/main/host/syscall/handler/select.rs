@@ -1,7 +1,9 @@
+use linux_api::errno::Errno;
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 
 use crate::cshadow as c;
 use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
+use crate::host::syscall::type_formatting::SyscallFdSetArg;
 use crate::host::syscall::types::SyscallError;
 
 impl SyscallHandler {
@@ -9,9 +11,9 @@ impl SyscallHandler {
         select,
         /* rv */ std::ffi::c_int,
         /* n */ std::ffi::c_int,
-        /* inp */ *const std::ffi::c_void,
-        /* outp */ *const std::ffi::c_void,
-        /* exp */ *const std::ffi::c_void,
+        /* inp */ SyscallFdSetArg</* n */ 0>,
+        /* outp */ SyscallFdSetArg</* n */ 0>,
+        /* exp */ SyscallFdSetArg</* n */ 0>,
         /* tvp */ *const linux_api::time::kernel_old_timeval,
     );
     pub fn select(
@@ -29,9 +31,9 @@ impl SyscallHandler {
         pselect6,
         /* rv */ std::ffi::c_int,
         /* n */ std::ffi::c_int,
-        /* inp */ *const std::ffi::c_void,
-        /* outp */ *const std::ffi::c_void,
-        /* exp */ *const std::ffi::c_void,
+        /* inp */ SyscallFdSetArg</* n */ 0>,
+        /* outp */ SyscallFdSetArg</* n */ 0>,
+        /* exp */ SyscallFdSetArg</* n */ 0>,
         /* tsp */ *const linux_api::time::kernel_timespec,
         /* sig */ *const std::ffi::c_void,
     );
@@ -42,8 +44,32 @@ impl SyscallHandler {
         _outp: ForeignPtr<linux_api::posix_types::kernel_fd_set>,
         _exp: ForeignPtr<linux_api::posix_types::kernel_fd_set>,
         _tsp: ForeignPtr<linux_api::time::kernel_timespec>,
-        _sig: ForeignPtr<()>,
+        sig: ForeignPtr<()>,
     ) -> Result<std::ffi::c_int, SyscallError> {
-        Self::legacy_syscall(c::syscallhandler_pselect6, ctx)
+        // the last argument points to a `struct { const sigset_t *ss; size_t ss_len; }`, and no
+        // signal mask manipulation is performed if either `sig` or `ss` is NULL
+        let sigmask = if sig.is_null() {
+            None
+        } else {
+            let [ss, ss_len] = ctx
+                .objs
+                .process
+                .memory_borrow()
+                .read(sig.cast::<[u64; 2]>())?;
+            let ss = ForeignPtr::<()>::from(ss).cast::<linux_api::signal::sigset_t>();
+
+            if ss.is_null() {
+                None
+            } else {
+                if ss_len != size_of::<linux_api::signal::sigset_t>() as u64 {
+                    return Err(Errno::EINVAL.into());
+                }
+                Some(ctx.objs.process.memory_borrow().read(ss)?)
+            }
+        };
+
+        Self::with_temporary_sigmask(ctx, sigmask, |ctx| {
+            Self::legacy_syscall(c::syscallhandler_pselect6, ctx)
+        })
     }
 }
@@ -8,6 +8,7 @@ use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 
 use crate::host::process::Process;
 use crate::host::syscall::handler::{SyscallContext, SyscallHandler, ThreadContext};
+use crate::host::syscall::type_formatting::SyscallSignalArg;
 use crate::host::thread::Thread;
 
 impl SyscallHandler {
@@ -15,7 +16,7 @@ impl SyscallHandler {
         kill,
         /* rv */ std::ffi::c_int,
         /* pid */ linux_api::posix_types::kernel_pid_t,
-        /* sig */ std::ffi::c_int,
+        /* sig */ SyscallSignalArg,
     );
     pub fn kill(
         ctx: &mut SyscallContext,
@@ -65,7 +66,7 @@ impl SyscallHandler {
 
     /// Send a signal to `target_process` from the thread and process in `objs`. A signal of 0 will
     /// be ignored.
-    fn signal_process(
+    pub(super) fn signal_process(
         objs: &ThreadContext,
         target_process: &Process,
         signal: std::ffi::c_int,
@@ -95,7 +96,7 @@ impl SyscallHandler {
         tkill,
         /* rv */ std::ffi::c_int,
         /* pid */ linux_api::posix_types::kernel_pid_t,
-        /* sig */ std::ffi::c_int,
+        /* sig */ SyscallSignalArg,
     );
     pub fn tkill(
         ctx: &mut SyscallContext,
@@ -104,6 +105,10 @@ impl SyscallHandler {
     ) -> Result<(), Errno> {
         log::trace!("tkill called on tid {tid} with signal {sig}");
 
+        if tid <= 0 {
+            return Err(Errno::EINVAL);
+        }
+
         let tid = tid.try_into().or(Err(Errno::ESRCH))?;
 
         let Some(target_thread) = ctx.objs.host.thread_cloned_rc(tid) else {
@@ -122,7 +127,7 @@ impl SyscallHandler {
         /* rv */ std::ffi::c_int,
         /* tgid */ linux_api::posix_types::kernel_pid_t,
         /* pid */ linux_api::posix_types::kernel_pid_t,
-        /* sig */ std::ffi::c_int,
+        /* sig */ SyscallSignalArg,
     );
     pub fn tgkill(
         ctx: &mut SyscallContext,
@@ -132,6 +137,10 @@ impl SyscallHandler {
     ) -> Result<(), Errno> {
         log::trace!("tgkill called on tgid {tgid} and tid {tid} with signal {sig}");
 
+        if tgid <= 0 || tid <= 0 {
+            return Err(Errno::EINVAL);
+        }
+
         let tgid = tgid.try_into().or(Err(Errno::ESRCH))?;
         let tid = tid.try_into().or(Err(Errno::ESRCH))?;
 
@@ -251,7 +260,7 @@ impl SyscallHandler {
     log_syscall!(
         rt_sigaction,
         /* rv */ std::ffi::c_int,
-        /* sig */ std::ffi::c_int,
+        /* sig */ SyscallSignalArg,
         /* act */ *const std::ffi::c_void,
         /* oact */ *const std::ffi::c_void,
         /* sigsetsize */ libc::size_t,
@@ -1,26 +1,35 @@
+use std::net::{Ipv4Addr, SocketAddrV4};
+
 use linux_api::errno::Errno;
 use linux_api::fcntl::DescriptorFlags;
-use linux_api::socket::Shutdown;
+use linux_api::signal::{Signal, siginfo_t};
+use linux_api::socket::{AddressFamily, Shutdown};
 use log::*;
 use nix::sys::socket::SockFlag;
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 
-use crate::host::descriptor::descriptor_table::DescriptorHandle;
+use crate::host::descriptor::descriptor_table::{DescriptorHandle, DescriptorTable};
 use crate::host::descriptor::socket::inet::InetSocket;
+use crate::host::descriptor::socket::inet::icmp::IcmpSocket;
 use crate::host::descriptor::socket::inet::legacy_tcp::LegacyTcpSocket;
 use crate::host::descriptor::socket::inet::tcp::TcpSocket;
 use crate::host::descriptor::socket::inet::udp::UdpSocket;
 use crate::host::descriptor::socket::netlink::{NetlinkFamily, NetlinkSocket, NetlinkSocketType};
 use crate::host::descriptor::socket::unix::{UnixSocket, UnixSocketType};
-use crate::host::descriptor::socket::{RecvmsgArgs, RecvmsgReturn, SendmsgArgs, Socket};
+use crate::host::descriptor::socket::{
+    AncillaryData, RecvTimestamp, RecvmsgArgs, RecvmsgReturn, SendmsgArgs, Socket, SocketRef,
+};
 use crate::host::descriptor::{CompatFile, Descriptor, File, FileState, FileStatus, OpenFile};
+use crate::host::memory_manager::MemoryManager;
+use crate::host::process::ProcessId;
 use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
 use crate::host::syscall::io::{self, IoVec};
-use crate::host::syscall::type_formatting::{SyscallBufferArg, SyscallSockAddrArg};
+use crate::host::syscall::type_formatting::{SyscallBufferArg, SyscallErrnoRv, SyscallSockAddrArg};
 use crate::host::syscall::types::ForeignArrayPtr;
 use crate::host::syscall::types::SyscallError;
 use crate::utility::callback_queue::CallbackQueue;
-use crate::utility::sockaddr::SockaddrStorage;
+use crate::utility::cmsg::{ControlMessageWriter, ControlMessages};
+use crate::utility::sockaddr::{SockaddrStorage, SockaddrUnix};
 
 impl SyscallHandler {
     log_syscall!(
@@ -105,6 +114,21 @@ impl SyscallHandler {
                         recv_buf_size.try_into().unwrap(),
                     )))
                 }
+                libc::SOCK_RAW => {
+                    if protocol != libc::IPPROTO_ICMP {
+                        log::debug!("Unsupported inet raw socket protocol {protocol}");
+                        return Err(Errno::EPROTONOSUPPORT);
+                    }
+
+                    // raw sockets require CAP_NET_RAW on Linux, but an icmp socket only has
+                    // access to the simulated network, so we allow them for every process
+
+                    let recv_buf_size = ctx.objs.host.params.init_sock_recv_buf_size;
+                    Socket::Inet(InetSocket::Icmp(IcmpSocket::new(
+                        file_flags,
+                        recv_buf_size.try_into().unwrap(),
+                    )))
+                }
                 _ => return Err(Errno::ESOCKTNOSUPPORT),
             },
             libc::AF_NETLINK => {
@@ -127,6 +151,8 @@ impl SyscallHandler {
             _ => return Err(Errno::EAFNOSUPPORT),
         };
 
+        // the socket was created with its status flags, and the descriptor flags are set before the
+        // descriptor is registered, so the fd is never visible without them
         let mut desc = Descriptor::new(CompatFile::New(OpenFile::new(File::Socket(socket))));
         desc.set_flags(descriptor_flags);
 
@@ -183,7 +209,7 @@ impl SyscallHandler {
 
     log_syscall!(
         sendto,
-        /* rv */ libc::ssize_t,
+        /* rv */ SyscallErrnoRv<libc::ssize_t>,
         /* sockfd */ std::ffi::c_int,
         /* buf */ SyscallBufferArg</* len */ 2>,
         /* len */ libc::size_t,
@@ -244,6 +270,7 @@ impl SyscallHandler {
             addr,
             iovs: &[iov],
             control_ptr: ForeignArrayPtr::new(ForeignPtr::null(), 0),
+            ancillary: AncillaryData::default(),
             flags,
         };
 
@@ -252,12 +279,10 @@ impl SyscallHandler {
             Socket::sendmsg(socket, args, &mut mem, &net_ns, &mut *rng, cb_queue)
         });
 
+        Self::sigpipe_helper(ctx, socket, flags, &result);
+
         // if the syscall will block, keep the file open until the syscall restarts
-        if let Some(err) = result.as_mut().err() {
-            if let Some(cond) = err.blocked_condition() {
-                cond.set_active_file(file);
-            }
-        }
+        Self::keep_file_open_while_blocked(&mut result, file);
 
         let bytes_sent = result?;
         Ok(bytes_sent)
@@ -265,7 +290,7 @@ impl SyscallHandler {
 
     log_syscall!(
         sendmsg,
-        /* rv */ libc::ssize_t,
+        /* rv */ SyscallErrnoRv<libc::ssize_t>,
         /* sockfd */ std::ffi::c_int,
         /* msg */ *const libc::msghdr,
         /* flags */ nix::sys::socket::MsgFlags,
@@ -309,11 +334,21 @@ impl SyscallHandler {
         let net_ns = ctx.objs.host.network_namespace_borrow();
 
         let msg = io::read_msghdr(&mem, msg_ptr)?;
+        let control_ptr = ForeignArrayPtr::new(msg.control, msg.control_len);
+
+        // unix sockets can pass open files and credentials to the receiver
+        let ancillary = if matches!(socket, Socket::Unix(_)) && !control_ptr.is_null() {
+            let desc_table = ctx.objs.thread.descriptor_table_borrow(ctx.objs.host);
+            read_control_messages(&mem, &desc_table, ctx.objs.process.id(), control_ptr)?
+        } else {
+            AncillaryData::default()
+        };
 
         let args = SendmsgArgs {
             addr: io::read_sockaddr(&mem, msg.name, msg.name_len)?,
             iovs: &msg.iovs,
-            control_ptr: ForeignArrayPtr::new(msg.control, msg.control_len),
+            control_ptr,
+            ancillary,
             // note: "the msg_flags field is ignored" for sendmsg; see send(2)
             flags,
         };
@@ -323,12 +358,10 @@ impl SyscallHandler {
             Socket::sendmsg(socket, args, &mut mem, &net_ns, &mut *rng, cb_queue)
         });
 
+        Self::sigpipe_helper(ctx, socket, flags, &result);
+
         // if the syscall will block, keep the file open until the syscall restarts
-        if let Some(err) = result.as_mut().err() {
-            if let Some(cond) = err.blocked_condition() {
-                cond.set_active_file(file);
-            }
-        }
+        Self::keep_file_open_while_blocked(&mut result, file);
 
         let bytes_written = result?;
         Ok(bytes_written)
@@ -336,7 +369,7 @@ impl SyscallHandler {
 
     log_syscall!(
         recvfrom,
-        /* rv */ libc::ssize_t,
+        /* rv */ SyscallErrnoRv<libc::ssize_t>,
         /* sockfd */ std::ffi::c_int,
         /* buf */ *const std::ffi::c_void,
         /* len */ libc::size_t,
@@ -400,12 +433,11 @@ impl SyscallHandler {
             Socket::recvmsg(socket, args, &mut mem, cb_queue)
         });
 
+        // if the syscall will block, time out after the socket's receive timeout
+        Self::apply_recv_timeout(ctx, socket.borrow().recv_timeout(), &mut result);
+
         // if the syscall will block, keep the file open until the syscall restarts
-        if let Some(err) = result.as_mut().err() {
-            if let Some(cond) = err.blocked_condition() {
-                cond.set_active_file(file);
-            }
-        }
+        Self::keep_file_open_while_blocked(&mut result, file);
 
         let RecvmsgReturn {
             return_val,
@@ -422,7 +454,7 @@ impl SyscallHandler {
 
     log_syscall!(
         recvmsg,
-        /* rv */ libc::ssize_t,
+        /* rv */ SyscallErrnoRv<libc::ssize_t>,
         /* sockfd */ std::ffi::c_int,
         /* msg */ *const libc::msghdr,
         /* flags */ nix::sys::socket::MsgFlags,
@@ -461,6 +493,125 @@ impl SyscallHandler {
             return Err(Errno::ENOTSOCK.into());
         };
 
+        let mut result = Self::recvmsg_helper(ctx, socket, msg_ptr, flags);
+
+        // if the syscall will block, keep the file open until the syscall restarts
+        Self::keep_file_open_while_blocked(&mut result, file);
+
+        result
+    }
+
+    log_syscall!(
+        recvmmsg,
+        /* rv */ SyscallErrnoRv<std::ffi::c_int>,
+        /* sockfd */ std::ffi::c_int,
+        /* msgvec */ *const libc::mmsghdr,
+        /* vlen */ std::ffi::c_uint,
+        /* flags */ nix::sys::socket::MsgFlags,
+        /* timeout */ *const linux_api::time::kernel_timespec,
+    );
+    pub fn recvmmsg(
+        ctx: &mut SyscallContext,
+        fd: std::ffi::c_int,
+        msgvec_ptr: ForeignPtr<libc::mmsghdr>,
+        vlen: std::ffi::c_uint,
+        flags: std::ffi::c_int,
+        timeout_ptr: ForeignPtr<linux_api::time::kernel_timespec>,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        // if we were previously blocked, get the active file from the last syscall handler
+        // invocation since it may no longer exist in the descriptor table
+        let file = ctx
+            .objs
+            .thread
+            .syscall_condition()
+            // if this was for a C descriptor, then there won't be an active file object
+            .and_then(|x| x.active_file().cloned());
+
+        let file = match file {
+            // we were previously blocked, so re-use the file from the previous syscall invocation
+            Some(x) => x,
+            // get the file from the descriptor table, or return early if it doesn't exist
+            None => {
+                let desc_table = ctx.objs.thread.descriptor_table_borrow(ctx.objs.host);
+                match Self::get_descriptor(&desc_table, fd)?.file() {
+                    CompatFile::New(file) => file.clone(),
+                    CompatFile::Legacy(_file) => {
+                        return Err(Errno::ENOTSOCK.into());
+                    }
+                }
+            }
+        };
+
+        let File::Socket(socket) = file.inner_file() else {
+            return Err(Errno::ENOTSOCK.into());
+        };
+
+        if !timeout_ptr.is_null() {
+            // make sure the timeout is readable, but we don't otherwise use it since we never block
+            // after the first message
+            ctx.objs.process.memory_borrow().read(timeout_ptr)?;
+            warn_once_then_debug!("recvmmsg() timeouts are not supported and will be ignored");
+        }
+
+        // the kernel silently limits the number of messages
+        let vlen = std::cmp::min(vlen, libc::UIO_MAXIOV as std::ffi::c_uint);
+
+        // we only ever block for the first message, so MSG_WAITFORONE is always implied
+        let flags = flags & !libc::MSG_WAITFORONE;
+
+        let mut num_received = 0;
+
+        for i in 0..vlen {
+            let mmsg_ptr = msgvec_ptr.add(i.try_into().unwrap());
+
+            // only the first message may block
+            let recv_flags = if i == 0 {
+                flags
+            } else {
+                flags | libc::MSG_DONTWAIT
+            };
+            // each message's msghdr gets its own msg_flags (for example MSG_TRUNC and MSG_CTRUNC)
+            let mut result =
+                Self::recvmsg_helper(ctx, socket, mmsg_ptr.cast::<libc::msghdr>(), recv_flags);
+
+            // if the syscall will block, keep the file open until the syscall restarts
+            if i == 0 {
+                Self::keep_file_open_while_blocked(&mut result, file.clone());
+            }
+
+            let num_bytes = match result {
+                Ok(x) => x,
+                // recvmmsg(2): "If an error occurs after at least one message has been received,
+                // the call succeeds, and returns the number of messages received." Linux would
+                // return the error from a later call, but we drop it.
+                Err(_) if num_received > 0 => break,
+                Err(e) => return Err(e),
+            };
+
+            let msg_len_ptr = mmsg_ptr
+                .cast::<u8>()
+                .add(memoffset::offset_of!(libc::mmsghdr, msg_len))
+                .cast::<std::ffi::c_uint>();
+            let msg_len = std::ffi::c_uint::try_from(num_bytes).unwrap();
+            ctx.objs
+                .process
+                .memory_borrow_mut()
+                .write(msg_len_ptr, &msg_len)?;
+
+            num_received += 1;
+        }
+
+        Ok(num_received)
+    }
+
+    /// Receive a message into the plugin's [`libc::msghdr`] and update its name length, control
+    /// length, and flags. Returns the number of bytes received.
+    fn recvmsg_helper(
+        ctx: &mut SyscallContext,
+        socket: &Socket,
+        msg_ptr: ForeignPtr<libc::msghdr>,
+        flags: std::ffi::c_int,
+    ) -> Result<libc::ssize_t, SyscallError> {
         let mut mem = ctx.objs.process.memory_borrow_mut();
 
         let mut msg = io::read_msghdr(&mem, msg_ptr)?;
@@ -476,15 +627,34 @@ impl SyscallHandler {
             Socket::recvmsg(socket, args, &mut mem, cb_queue)
         });
 
-        // if the syscall will block, keep the file open until the syscall restarts
-        if let Some(err) = result.as_mut().err() {
-            if let Some(cond) = err.blocked_condition() {
-                cond.set_active_file(file);
+        // if the syscall will block, time out after the socket's receive timeout
+        Self::apply_recv_timeout(ctx, socket.borrow().recv_timeout(), &mut result);
+
+        let mut result = result?;
+
+        // write any ancillary data as control messages
+        if !result.ancillary.is_empty() {
+            let descriptor_flags = if flags & libc::MSG_CMSG_CLOEXEC != 0 {
+                DescriptorFlags::FD_CLOEXEC
+            } else {
+                DescriptorFlags::empty()
+            };
+
+            let mut desc_table = ctx.objs.thread.descriptor_table_borrow_mut(ctx.objs.host);
+            let (control_len, truncated) = write_control_messages(
+                &mut mem,
+                &mut desc_table,
+                ForeignArrayPtr::new(msg.control, msg.control_len),
+                std::mem::take(&mut result.ancillary),
+                descriptor_flags,
+            )?;
+
+            result.control_len = control_len;
+            if truncated {
+                result.msg_flags |= libc::MSG_CTRUNC;
             }
         }
 
-        let result = result?;
-
         // write the socket address to the plugin and update the length in msg
         if !msg.name.is_null() {
             if let Some(from_addr) = result.addr.as_ref() {
@@ -531,11 +701,6 @@ impl SyscallHandler {
                 return Err(Errno::ENOTSOCK);
             };
 
-            // linux will return an EFAULT before other errors
-            if addr_ptr.is_null() || addr_len_ptr.is_null() {
-                return Err(Errno::EFAULT);
-            }
-
             let socket = socket.borrow();
             socket.getsockname()?
         };
@@ -578,11 +743,6 @@ impl SyscallHandler {
                 return Err(Errno::ENOTSOCK);
             };
 
-            // linux will return an EFAULT before other errors like ENOTCONN
-            if addr_ptr.is_null() || addr_len_ptr.is_null() {
-                return Err(Errno::EFAULT);
-            }
-
             // this is a clippy false-positive
             #[allow(clippy::let_and_return)]
             let addr_to_write = socket.borrow().getpeername()?;
@@ -624,6 +784,11 @@ impl SyscallHandler {
             return Err(Errno::ENOTSOCK);
         };
 
+        // only connection-oriented sockets can listen
+        if socket.is_connectionless() {
+            return Err(Errno::EOPNOTSUPP);
+        }
+
         let mut rng = ctx.objs.host.random_mut();
         let net_ns = ctx.objs.host.network_namespace_borrow();
 
@@ -673,11 +838,7 @@ impl SyscallHandler {
         let mut result = Self::accept_helper(ctx, file.inner_file(), addr_ptr, addr_len_ptr, 0);
 
         // if the syscall will block, keep the file open until the syscall restarts
-        if let Some(err) = result.as_mut().err() {
-            if let Some(cond) = err.blocked_condition() {
-                cond.set_active_file(file);
-            }
-        }
+        Self::keep_file_open_while_blocked(&mut result, file);
 
         result
     }
@@ -723,15 +884,46 @@ impl SyscallHandler {
         let mut result = Self::accept_helper(ctx, file.inner_file(), addr_ptr, addr_len_ptr, flags);
 
         // if the syscall will block, keep the file open until the syscall restarts
-        if let Some(err) = result.as_mut().err() {
-            if let Some(cond) = err.blocked_condition() {
-                cond.set_active_file(file);
-            }
-        }
+        Self::keep_file_open_while_blocked(&mut result, file);
 
         result
     }
 
+    /// Send `SIGPIPE` to the calling process if a send on a stream socket failed with `EPIPE` (the
+    /// peer has closed, or we've shut down writing), unless `MSG_NOSIGNAL` was set. This is the only
+    /// place that decides whether a send raises `SIGPIPE`; `sendto()`, `sendmsg()`, and `write()`
+    /// (which has no flags, so never suppresses the signal) all go through here.
+    pub(super) fn sigpipe_helper<T>(
+        ctx: &SyscallContext,
+        socket: &Socket,
+        flags: std::ffi::c_int,
+        result: &Result<T, SyscallError>,
+    ) {
+        let Err(err) = result else {
+            return;
+        };
+
+        if *err != Errno::EPIPE.into() || flags & libc::MSG_NOSIGNAL != 0 {
+            return;
+        }
+
+        // like linux, only stream sockets raise SIGPIPE
+        let is_stream = match socket {
+            Socket::Unix(socket) => socket.borrow().socket_type() == UnixSocketType::Stream,
+            Socket::Inet(InetSocket::Tcp(_) | InetSocket::LegacyTcp(_)) => true,
+            Socket::Inet(InetSocket::Udp(_) | InetSocket::Icmp(_)) | Socket::Netlink(_) => false,
+        };
+
+        if !is_stream {
+            return;
+        }
+
+        let siginfo = siginfo_t::new_for_kill(Signal::SIGPIPE, ctx.objs.process.id().into(), 0);
+        ctx.objs
+            .process
+            .signal(ctx.objs.host, Some(ctx.objs.thread), &siginfo);
+    }
+
     fn accept_helper(
         ctx: &mut SyscallContext,
         file: &File,
@@ -763,14 +955,15 @@ impl SyscallHandler {
         let file_status = socket.borrow().status();
 
         // if the syscall would block and it's a blocking descriptor
-        if result.as_ref().err() == Some(&Errno::EWOULDBLOCK.into())
+        if result.as_ref().is_err_and(SyscallError::is_wouldblock)
             && !file_status.contains(FileStatus::NONBLOCK)
         {
-            return Err(SyscallError::new_blocked_on_file(
-                file.clone(),
-                FileState::READABLE,
-                socket.borrow().supports_sa_restart(),
-            ));
+            // If several threads are blocked here, a new connection will schedule a wakeup for
+            // each of them, but a condition re-checks that the socket is still readable before
+            // resuming its thread. So only as many threads as there are pending connections will
+            // resume, and the others will remain blocked.
+            let timeout = Self::recv_timeout_deadline(ctx, socket.borrow().recv_timeout())?;
+            return Err(Self::block_on_file(file, FileState::READABLE, timeout));
         }
 
         let new_socket = result?;
@@ -779,7 +972,18 @@ impl SyscallHandler {
             let File::Socket(new_socket) = new_socket.inner_file() else {
                 panic!("Accepted file should be a socket");
             };
-            new_socket.borrow().getpeername().unwrap()
+
+            let new_socket = new_socket.borrow();
+
+            // a connection that was reset before it was accepted may no longer have a peer, in
+            // which case the reset will be reported by the first read or write instead
+            match new_socket.getpeername() {
+                Ok(addr) => addr,
+                Err(e) => {
+                    log::debug!("Accepted socket has no peer address: {e:?}");
+                    unnamed_peer_address(new_socket.address_family())
+                }
+            }
         };
 
         if !addr_ptr.is_null() {
@@ -863,11 +1067,7 @@ impl SyscallHandler {
         });
 
         // if the syscall will block, keep the file open until the syscall restarts
-        if let Some(err) = result.as_mut().err() {
-            if let Some(cond) = err.blocked_condition() {
-                cond.set_active_file(file);
-            }
-        }
+        Self::keep_file_open_while_blocked(&mut result, file);
 
         result?;
 
@@ -922,9 +1122,17 @@ impl SyscallHandler {
         protocol: std::ffi::c_int,
         fd_ptr: ForeignPtr<[std::ffi::c_int; 2]>,
     ) -> Result<(), SyscallError> {
-        // remove any flags from the socket type
-        let flags = socket_type & (libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC);
-        let socket_type = socket_type & !flags;
+        // the lower bits are the socket type (linux's `SOCK_TYPE_MASK`), and the remaining bits
+        // are flags
+        const SOCK_TYPE_MASK: std::ffi::c_int = 0xf;
+        let flags = socket_type & !SOCK_TYPE_MASK;
+        let socket_type = socket_type & SOCK_TYPE_MASK;
+
+        // linux checks the flags before any of the other arguments
+        if flags & !(libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC) != 0 {
+            log::debug!("Unsupported flags {flags:#x} for socketpair()");
+            return Err(Errno::EINVAL.into());
+        }
 
         // only AF_UNIX (AF_LOCAL) is supported on Linux (and technically AF_TIPC)
         if domain != libc::AF_UNIX {
@@ -978,15 +1186,26 @@ impl SyscallHandler {
         desc_1.set_flags(descriptor_flags);
         desc_2.set_flags(descriptor_flags);
 
-        // register the file descriptors
+        // register the file descriptors; either both are registered or neither are
+        let fd_limit = ctx.objs.process.fd_limit();
         let mut dt = ctx.objs.thread.descriptor_table_borrow_mut(ctx.objs.host);
-        // unwrap here since the error handling would be messy (need to deregister) and we shouldn't
-        // ever need to worry about this in practice
-        let fd_1 = dt.register_descriptor(desc_1).unwrap();
-        let fd_2 = dt.register_descriptor(desc_2).unwrap();
+        let registered = dt.register_descriptors(vec![desc_1, desc_2], fd_limit);
+
+        let new_fds = match registered {
+            Ok(fds) => fds,
+            Err(descs) => {
+                CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
+                    for desc in descs {
+                        // ignore any errors when closing
+                        desc.close(ctx.objs.host, cb_queue);
+                    }
+                });
+                return Err(Errno::EMFILE.into());
+            }
+        };
 
         // try to write them to the caller
-        let fds = [i32::from(fd_1), i32::from(fd_2)];
+        let fds = [i32::from(new_fds[0]), i32::from(new_fds[1])];
         let write_res = ctx.objs.process.memory_borrow_mut().write(fd_ptr, &fds);
 
         // clean up in case of error
@@ -994,13 +1213,12 @@ impl SyscallHandler {
             Ok(_) => Ok(()),
             Err(e) => {
                 CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
-                    // ignore any errors when closing
-                    dt.deregister_descriptor(fd_1)
-                        .unwrap()
-                        .close(ctx.objs.host, cb_queue);
-                    dt.deregister_descriptor(fd_2)
-                        .unwrap()
-                        .close(ctx.objs.host, cb_queue);
+                    for fd in new_fds {
+                        // ignore any errors when closing
+                        dt.deregister_descriptor(fd)
+                            .unwrap()
+                            .close(ctx.objs.host, cb_queue);
+                    }
                 });
                 Err(e.into())
             }
@@ -1042,6 +1260,17 @@ impl SyscallHandler {
         // get the provided optlen
         let optlen = mem.read(optlen_ptr)?;
 
+        // linux interprets the optlen as a signed int and returns EINVAL if it's negative
+        if (optlen as std::ffi::c_int) < 0 {
+            return Err(Errno::EINVAL.into());
+        }
+
+        // SO_PEERSEC returns the required optlen even if the buffer is too small, so we handle it
+        // here rather than in the socket's getsockopt
+        if (level, optname) == (libc::SOL_SOCKET, libc::SO_PEERSEC) {
+            return getsockopt_peersec(socket, optval_ptr, optlen, optlen_ptr, &mut mem);
+        }
+
         let mut optlen_new = CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
             socket
                 .borrow_mut()
@@ -1096,10 +1325,217 @@ impl SyscallHandler {
 
         let mem = ctx.objs.process.memory_borrow();
 
-        socket
-            .borrow_mut()
-            .setsockopt(level, optname, optval_ptr, optlen, &mem)?;
+        CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
+            socket
+                .borrow_mut()
+                .setsockopt(level, optname, optval_ptr, optlen, &mem, cb_queue)
+        })?;
 
         Ok(())
     }
 }
+
+/// The security context returned by `SO_PEERSEC`. We don't emulate a Linux security module, so
+/// every peer has the same context as an unconfined process.
+const PEER_SECURITY_CONTEXT: &str = "unconfined";
+
+/// Write the peer's security context for `getsockopt(SO_PEERSEC)`. Like Linux, if the buffer is too
+/// small then `ERANGE` is returned and the required length is written to `optlen_ptr`.
+fn getsockopt_peersec(
+    socket: &Socket,
+    optval_ptr: ForeignPtr<()>,
+    optlen: libc::socklen_t,
+    optlen_ptr: ForeignPtr<libc::socklen_t>,
+    mem: &mut MemoryManager,
+) -> Result<(), SyscallError> {
+    let socket = socket.borrow();
+
+    // only unix sockets have a peer security context; other sockets would need labeled networking
+    if !matches!(&*socket, SocketRef::Unix(_)) {
+        return Err(Errno::ENOPROTOOPT.into());
+    }
+
+    // returns ENOTCONN if there is no peer
+    socket.getpeername()?;
+
+    let context = std::ffi::CString::new(PEER_SECURITY_CONTEXT).unwrap();
+    let context = context.as_bytes_with_nul();
+    let context_len = libc::socklen_t::try_from(context.len()).unwrap();
+
+    if optlen < context_len {
+        mem.write(optlen_ptr, &context_len)?;
+        return Err(Errno::ERANGE.into());
+    }
+
+    let optval_ptr = ForeignArrayPtr::new(optval_ptr.cast::<u8>(), context.len());
+    mem.copy_to_ptr(optval_ptr, context)?;
+    mem.write(optlen_ptr, &context_len)?;
+
+    Ok(())
+}
+
+/// The address returned by `accept()` for an accepted socket that has no peer address: the wildcard
+/// address for inet sockets, and an unnamed address for unix sockets.
+fn unnamed_peer_address(family: AddressFamily) -> Option<SockaddrStorage> {
+    match family {
+        AddressFamily::AF_INET => Some(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0).into()),
+        AddressFamily::AF_UNIX => Some(SockaddrUnix::new_unnamed().into()),
+        _ => None,
+    }
+}
+
+/// The max number of descriptors that can be passed in a single message. This is `SCM_MAX_FD` in
+/// Linux.
+const SCM_MAX_FD: usize = 253;
+
+/// Read the ancillary data from the control messages of a unix socket's `sendmsg()`. Only
+/// `SCM_RIGHTS` and `SCM_CREDENTIALS` control messages are supported.
+fn read_control_messages(
+    mem: &MemoryManager,
+    desc_table: &DescriptorTable,
+    pid: ProcessId,
+    control_ptr: ForeignArrayPtr<u8>,
+) -> Result<AncillaryData, SyscallError> {
+    let control = mem.memory_ref(control_ptr)?;
+    let mut ancillary = AncillaryData::default();
+
+    for cmsg in ControlMessages::new(&control) {
+        let cmsg = cmsg?;
+
+        match (cmsg.level, cmsg.ty) {
+            (libc::SOL_SOCKET, libc::SCM_RIGHTS) => {
+                let fds = cmsg.data.chunks_exact(std::mem::size_of::<libc::c_int>());
+
+                if ancillary.rights.len() + fds.len() > SCM_MAX_FD {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                for fd in fds {
+                    let fd = libc::c_int::from_ne_bytes(fd.try_into().unwrap());
+                    let desc = SyscallHandler::get_descriptor(desc_table, fd)?;
+                    ancillary.rights.push(desc.file().clone());
+                }
+            }
+            (libc::SOL_SOCKET, libc::SCM_CREDENTIALS) => {
+                const CREDS_LEN: usize = std::mem::size_of::<libc::ucred>();
+                let Ok(creds) = <&[u8; CREDS_LEN]>::try_from(cmsg.data) else {
+                    return Err(Errno::EINVAL.into());
+                };
+                let creds: libc::ucred = shadow_pod::from_array(creds);
+
+                // we don't emulate capabilities, so a process can only send its own credentials
+                let uids = [nix::unistd::getuid(), nix::unistd::geteuid()].map(|x| x.as_raw());
+                let gids = [nix::unistd::getgid(), nix::unistd::getegid()].map(|x| x.as_raw());
+                if creds.pid != libc::pid_t::from(pid)
+                    || !uids.contains(&creds.uid)
+                    || !gids.contains(&creds.gid)
+                {
+                    return Err(Errno::EPERM.into());
+                }
+
+                ancillary.creds = Some(creds);
+            }
+            (level, ty) => {
+                log::debug!("Unsupported unix socket control message (level {level}, type {ty})");
+                return Err(Errno::EINVAL.into());
+            }
+        }
+    }
+
+    Ok(ancillary)
+}
+
+/// Write the ancillary data from a socket's `recvmsg()` as control messages. Like Linux, only as
+/// many passed files as fit in the control buffer are added to the descriptor table, and the
+/// remaining files are closed. Returns the number of control bytes written, and whether the control
+/// data was truncated.
+fn write_control_messages(
+    mem: &mut MemoryManager,
+    desc_table: &mut DescriptorTable,
+    control_ptr: ForeignArrayPtr<u8>,
+    ancillary: AncillaryData,
+    descriptor_flags: DescriptorFlags,
+) -> Result<(libc::size_t, bool), SyscallError> {
+    let mut writer = ControlMessageWriter::new(control_ptr.len());
+
+    // like linux, write the timestamp first
+    match ancillary.timestamp {
+        Some(RecvTimestamp::Timeval(time)) => {
+            let time = [time.tv_sec.to_ne_bytes(), time.tv_usec.to_ne_bytes()];
+            writer.push(libc::SOL_SOCKET, libc::SCM_TIMESTAMP, &time.concat());
+        }
+        Some(RecvTimestamp::Timespec(time)) => {
+            let time = [time.tv_sec.to_ne_bytes(), time.tv_nsec.to_ne_bytes()];
+            writer.push(libc::SOL_SOCKET, libc::SCM_TIMESTAMPNS, &time.concat());
+        }
+        None => {}
+    }
+
+    if let Some(pktinfo) = ancillary.pktinfo {
+        let pktinfo = [
+            pktinfo.ipi_ifindex.to_ne_bytes(),
+            pktinfo.ipi_spec_dst.s_addr.to_ne_bytes(),
+            pktinfo.ipi_addr.s_addr.to_ne_bytes(),
+        ];
+        writer.push(libc::IPPROTO_IP, libc::IP_PKTINFO, &pktinfo.concat());
+    }
+
+    // like linux, write the credentials before any descriptors
+    if let Some(creds) = ancillary.creds {
+        let creds = [
+            creds.pid.to_ne_bytes(),
+            creds.uid.to_ne_bytes(),
+            creds.gid.to_ne_bytes(),
+        ];
+        writer.push(libc::SOL_SOCKET, libc::SCM_CREDENTIALS, &creds.concat());
+    }
+
+    if !ancillary.rights.is_empty() {
+        let num_rights = ancillary.rights.len();
+        let max_fds = writer.data_space() / std::mem::size_of::<libc::c_int>();
+
+        let mut fds = Vec::new();
+        for file in ancillary.rights.into_iter().take(max_fds) {
+            let mut desc = Descriptor::new(file);
+            desc.set_flags(descriptor_flags);
+
+            // like linux, stop adding descriptors once the descriptor table is full
+            let Ok(fd) = desc_table.register_descriptor(desc) else {
+                break;
+            };
+            fds.extend(libc::c_int::from(fd).to_ne_bytes());
+        }
+
+        if fds.len() / std::mem::size_of::<libc::c_int>() < num_rights {
+            writer.set_truncated();
+        }
+
+        if !fds.is_empty() {
+            writer.push(libc::SOL_SOCKET, libc::SCM_RIGHTS, &fds);
+        }
+    }
+
+    let control = writer.as_bytes();
+    mem.copy_to_ptr(control_ptr.slice(..control.len()), control)?;
+
+    Ok((control.len(), writer.is_truncated()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unnamed_peer_address() {
+        let addr = unnamed_peer_address(AddressFamily::AF_INET).unwrap();
+        assert_eq!(
+            addr.as_inet().map(|x| SocketAddrV4::from(*x)),
+            Some(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0)),
+        );
+
+        let addr = unnamed_peer_address(AddressFamily::AF_UNIX).unwrap();
+        assert!(addr.as_unix().unwrap().is_unnamed());
+
+        assert!(unnamed_peer_address(AddressFamily::AF_NETLINK).is_none());
+    }
+}
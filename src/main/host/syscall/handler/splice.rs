@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use linux_api::errno::Errno;
+
+use crate::host::descriptor::{CompatFile, File, FileState, FileStatus, OpenFile};
+use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
+use crate::host::syscall::types::SyscallError;
+use crate::utility::callback_queue::CallbackQueue;
+
+/// All of the flags accepted by the splice family of syscalls.
+const SPLICE_F_ALL: std::ffi::c_uint =
+    libc::SPLICE_F_MOVE | libc::SPLICE_F_NONBLOCK | libc::SPLICE_F_MORE | libc::SPLICE_F_GIFT;
+
+impl SyscallHandler {
+    log_syscall!(
+        tee,
+        /* rv */ isize,
+        /* fd_in */ std::ffi::c_int,
+        /* fd_out */ std::ffi::c_int,
+        /* len */ usize,
+        /* flags */ std::ffi::c_uint,
+    );
+    pub fn tee(
+        ctx: &mut SyscallContext,
+        fd_in: std::ffi::c_int,
+        fd_out: std::ffi::c_int,
+        len: usize,
+        flags: std::ffi::c_uint,
+    ) -> Result<isize, SyscallError> {
+        if flags & !SPLICE_F_ALL != 0 {
+            log::debug!("Invalid tee flags: {flags}");
+            return Err(Errno::EINVAL.into());
+        }
+
+        if len == 0 {
+            return Ok(0);
+        }
+
+        let (file_in, file_out) = {
+            let desc_table = ctx.objs.thread.descriptor_table_borrow(ctx.objs.host);
+            let get_file = |fd| -> Result<Option<OpenFile>, Errno> {
+                match Self::get_descriptor(&desc_table, fd)?.file() {
+                    CompatFile::New(file) => Ok(Some(file.clone())),
+                    // legacy files are never pipes
+                    CompatFile::Legacy(_) => Ok(None),
+                }
+            };
+            (get_file(fd_in)?, get_file(fd_out)?)
+        };
+
+        // tee(2): "EINVAL: fd_in or fd_out does not refer to a pipe"
+        let (Some(file_in), Some(file_out)) = (file_in, file_out) else {
+            return Err(Errno::EINVAL.into());
+        };
+        let (File::Pipe(pipe_in), File::Pipe(pipe_out)) =
+            (file_in.inner_file(), file_out.inner_file())
+        else {
+            return Err(Errno::EINVAL.into());
+        };
+
+        // a pipe file is never open for both reading and writing
+        if Arc::ptr_eq(pipe_in, pipe_out) {
+            return Err(Errno::EBADF.into());
+        }
+
+        let nonblocking = flags & libc::SPLICE_F_NONBLOCK != 0
+            || pipe_in.borrow().status().contains(FileStatus::NONBLOCK)
+            || pipe_out.borrow().status().contains(FileStatus::NONBLOCK);
+
+        let result = CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
+            pipe_in
+                .borrow()
+                .tee(&mut pipe_out.borrow_mut(), len, cb_queue)
+        });
+
+        if result.as_ref().is_err_and(SyscallError::is_wouldblock) && !nonblocking {
+            // if the source pipe is empty wait for data, otherwise wait for space in the
+            // destination pipe (both descriptors are looked up again when the syscall restarts)
+            let (file, wait_for) = if !pipe_in.borrow().state().contains(FileState::READABLE) {
+                (&file_in, FileState::READABLE | FileState::HUP)
+            } else {
+                (&file_out, FileState::WRITABLE | FileState::ERR)
+            };
+
+            return Err(Self::block_on_file(file.inner_file(), wait_for, None));
+        }
+
+        Ok(result?.try_into().unwrap())
+    }
+}
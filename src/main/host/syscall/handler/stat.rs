@@ -1,8 +1,10 @@
-use shadow_shim_helper_rs::syscall_types::ForeignPtr;
+use linux_api::posix_types::kernel_mode_t;
+use shadow_shim_helper_rs::syscall_types::{ForeignArrayPtr, ForeignPtr};
 
 use crate::cshadow;
 use crate::host::descriptor::CompatFile;
 use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
+use crate::host::syscall::type_formatting::{SyscallModeArg, SyscallStringArg};
 use crate::host::syscall::types::{SyscallError, SyscallResult};
 
 impl SyscallHandler {
@@ -49,8 +51,71 @@ impl SyscallHandler {
         Self::legacy_syscall(cshadow::syscallhandler_fstatfs, ctx)
     }
 
-    log_syscall!(newfstatat, /* rv */ std::ffi::c_int);
-    pub fn newfstatat(ctx: &mut SyscallContext) -> SyscallResult {
+    log_syscall!(
+        newfstatat,
+        /* rv */ std::ffi::c_int,
+        /* dirfd */ std::ffi::c_int,
+        /* pathname */ SyscallStringArg,
+        /* statbuf */ *const linux_api::stat::stat,
+        /* flags */ std::ffi::c_int,
+    );
+    pub fn newfstatat(
+        ctx: &mut SyscallContext,
+        dir_fd: std::ffi::c_int,
+        path: ForeignPtr<u8>,
+        statbuf_ptr: ForeignPtr<linux_api::stat::stat>,
+        flags: std::ffi::c_int,
+    ) -> SyscallResult {
+        // glibc implements fstat() using fstatat() with an empty path, so this is equivalent to
+        // fstat() for non-legacy files (the C syscall handler only supports legacy files)
+        if flags & libc::AT_EMPTY_PATH != 0 {
+            let mut path_buf = [0u8; 1];
+            let is_empty_path = ctx
+                .objs
+                .process
+                .memory_borrow()
+                .copy_str_from_ptr(&mut path_buf, ForeignArrayPtr::new(path, 1))
+                .is_ok_and(|path| path.is_empty());
+
+            let desc_table = ctx.objs.thread.descriptor_table_borrow(ctx.objs.host);
+            let file = match Self::get_descriptor(&desc_table, dir_fd) {
+                Ok(desc) if is_empty_path => match desc.file() {
+                    CompatFile::New(file) => Some(file.clone()),
+                    CompatFile::Legacy(_) => None,
+                },
+                _ => None,
+            };
+            drop(desc_table);
+
+            if let Some(file) = file {
+                let stat = file.inner_file().borrow().stat()?;
+                ctx.objs
+                    .process
+                    .memory_borrow_mut()
+                    .write(statbuf_ptr, &stat)?;
+                return Ok(0.into());
+            }
+        }
+
         Self::legacy_syscall(cshadow::syscallhandler_newfstatat, ctx)
     }
+
+    log_syscall!(
+        umask,
+        /* rv */ SyscallModeArg,
+        /* mask */ SyscallModeArg,
+    );
+    pub fn umask(
+        ctx: &mut SyscallContext,
+        mask: kernel_mode_t,
+    ) -> Result<kernel_mode_t, SyscallError> {
+        // umask(2): "only the file permission bits of mask are used"
+        let mask = mask & 0o777;
+
+        // Also set the native umask so that it applies to natively-executed syscalls like mkdir.
+        let (process, thread) = ctx.objs.split_thread();
+        thread.native_umask(&process, mask)?;
+
+        Ok(process.process.set_umask(mask))
+    }
 }
@@ -2,7 +2,9 @@ use linux_api::errno::Errno;
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 
 use crate::cshadow as c;
-use crate::host::descriptor::socket::{RecvmsgArgs, RecvmsgReturn, SendmsgArgs, Socket};
+use crate::host::descriptor::socket::{
+    AncillaryData, RecvmsgArgs, RecvmsgReturn, SendmsgArgs, Socket,
+};
 use crate::host::descriptor::{CompatFile, File, FileState, FileStatus};
 use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
 use crate::host::syscall::io::{self, IoVec};
@@ -60,11 +62,7 @@ impl SyscallHandler {
         let mut result = Self::readv_helper(ctx, file.inner_file(), &iovs, None, 0);
 
         // if the syscall will block, keep the file open until the syscall restarts
-        if let Some(err) = result.as_mut().err() {
-            if let Some(cond) = err.blocked_condition() {
-                cond.set_active_file(file);
-            }
-        }
+        Self::keep_file_open_while_blocked(&mut result, file);
 
         let bytes_read = result?;
         Ok(bytes_read)
@@ -133,11 +131,7 @@ impl SyscallHandler {
         let mut result = Self::readv_helper(ctx, file.inner_file(), &iovs, Some(offset), 0);
 
         // if the syscall will block, keep the file open until the syscall restarts
-        if let Some(err) = result.as_mut().err() {
-            if let Some(cond) = err.blocked_condition() {
-                cond.set_active_file(file);
-            }
-        }
+        Self::keep_file_open_while_blocked(&mut result, file);
 
         let bytes_read = result?;
         Ok(bytes_read)
@@ -214,11 +208,7 @@ impl SyscallHandler {
         let mut result = Self::readv_helper(ctx, file.inner_file(), &iovs, offset, flags);
 
         // if the syscall will block, keep the file open until the syscall restarts
-        if let Some(err) = result.as_mut().err() {
-            if let Some(cond) = err.blocked_condition() {
-                cond.set_active_file(file);
-            }
-        }
+        Self::keep_file_open_while_blocked(&mut result, file);
 
         let bytes_read = result?;
         Ok(bytes_read)
@@ -255,10 +245,14 @@ impl SyscallHandler {
             };
 
             // call the socket's recvmsg(), and run any resulting events
-            let RecvmsgReturn { return_val, .. } =
-                CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
-                    Socket::recvmsg(socket, args, &mut mem, cb_queue)
-                })?;
+            let mut result = CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
+                Socket::recvmsg(socket, args, &mut mem, cb_queue)
+            });
+
+            // if the syscall will block, time out after the socket's receive timeout
+            Self::apply_recv_timeout(ctx, socket.borrow().recv_timeout(), &mut result);
+
+            let RecvmsgReturn { return_val, .. } = result?;
 
             return Ok(return_val);
         }
@@ -277,20 +271,20 @@ impl SyscallHandler {
                 )
             });
 
-        // if the syscall would block and it's a blocking descriptor
-        if result == Err(Errno::EWOULDBLOCK.into()) && !file_status.contains(FileStatus::NONBLOCK) {
-            // TODO: should we block on the READABLE, HUP, and RDHUP states?
-            // https://github.com/shadow/shadow/issues/2181
-            let wait_for = FileState::READABLE;
+        // if the syscall would block and it's a blocking descriptor (a blocked syscall is only
+        // resumed once the file has the state it's waiting for, so setting `O_NONBLOCK` while
+        // blocked won't make it fail with `EWOULDBLOCK`, like in Linux)
+        if result.as_ref().is_err_and(SyscallError::is_wouldblock)
+            && !file_status.contains(FileStatus::NONBLOCK)
+        {
+            // a pipe with no writers is HUP but not READABLE, and the read should return 0
+            // TODO: should we also block on the RDHUP state?
+            let wait_for = FileState::READABLE | FileState::HUP;
 
             // check that we're not already in the state that we're going to wait for
             debug_assert!(!file.borrow().state().intersects(wait_for));
 
-            return Err(SyscallError::new_blocked_on_file(
-                file.clone(),
-                wait_for,
-                file.borrow().supports_sa_restart(),
-            ));
+            return Err(Self::block_on_file(file, wait_for, None));
         }
 
         result
@@ -346,11 +340,7 @@ impl SyscallHandler {
         let mut result = Self::writev_helper(ctx, file.inner_file(), &iovs, None, 0);
 
         // if the syscall will block, keep the file open until the syscall restarts
-        if let Some(err) = result.as_mut().err() {
-            if let Some(cond) = err.blocked_condition() {
-                cond.set_active_file(file);
-            }
-        }
+        Self::keep_file_open_while_blocked(&mut result, file);
 
         let bytes_written = result?;
         Ok(bytes_written)
@@ -419,11 +409,7 @@ impl SyscallHandler {
         let mut result = Self::writev_helper(ctx, file.inner_file(), &iovs, Some(offset), 0);
 
         // if the syscall will block, keep the file open until the syscall restarts
-        if let Some(err) = result.as_mut().err() {
-            if let Some(cond) = err.blocked_condition() {
-                cond.set_active_file(file);
-            }
-        }
+        Self::keep_file_open_while_blocked(&mut result, file);
 
         let bytes_written = result?;
         Ok(bytes_written)
@@ -500,11 +486,7 @@ impl SyscallHandler {
         let mut result = Self::writev_helper(ctx, file.inner_file(), &iovs, offset, flags);
 
         // if the syscall will block, keep the file open until the syscall restarts
-        if let Some(err) = result.as_mut().err() {
-            if let Some(cond) = err.blocked_condition() {
-                cond.set_active_file(file);
-            }
-        }
+        Self::keep_file_open_while_blocked(&mut result, file);
 
         let bytes_written = result?;
         Ok(bytes_written)
@@ -532,14 +514,18 @@ impl SyscallHandler {
                 addr: None,
                 iovs,
                 control_ptr: ForeignArrayPtr::new(ForeignPtr::null(), 0),
+                ancillary: AncillaryData::default(),
                 flags: 0,
             };
 
             // call the socket's sendmsg(), and run any resulting events
-            let bytes_written = CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
+            let result = CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
                 Socket::sendmsg(socket, args, &mut mem, &net_ns, &mut *rng, cb_queue)
-            })?;
+            });
 
+            Self::sigpipe_helper(ctx, socket, 0, &result);
+
+            let bytes_written = result?;
             return Ok(bytes_written);
         }
 
@@ -557,20 +543,19 @@ impl SyscallHandler {
                 )
             });
 
+        // pwritev2(2): "RWF_NOWAIT: Do not wait for data which is not immediately available"
+        let nonblocking =
+            file_status.contains(FileStatus::NONBLOCK) || flags & libc::RWF_NOWAIT != 0;
+
         // if the syscall would block and it's a blocking descriptor
-        if result == Err(Errno::EWOULDBLOCK.into()) && !file_status.contains(FileStatus::NONBLOCK) {
-            // TODO: should we block on the WRITABLE and HUP states?
-            // https://github.com/shadow/shadow/issues/2181
-            let wait_for = FileState::WRITABLE;
+        if result.as_ref().is_err_and(SyscallError::is_wouldblock) && !nonblocking {
+            // a pipe with no readers is ERR but not WRITABLE, and the write should return EPIPE
+            let wait_for = FileState::WRITABLE | FileState::ERR;
 
             // check that we're not already in the state that we're going to wait for
             debug_assert!(!file.borrow().state().intersects(wait_for));
 
-            return Err(SyscallError::new_blocked_on_file(
-                file.clone(),
-                wait_for,
-                file.borrow().supports_sa_restart(),
-            ));
+            return Err(Self::block_on_file(file, wait_for, None));
         }
 
         result
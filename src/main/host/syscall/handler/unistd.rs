@@ -22,7 +22,7 @@ use crate::host::descriptor::{CompatFile, Descriptor, File, FileMode, FileStatus
 use crate::host::process::{Process, ProcessId};
 use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
 use crate::host::syscall::io::{IoVec, read_cstring_vec};
-use crate::host::syscall::type_formatting::{SyscallBufferArg, SyscallStringArg};
+use crate::host::syscall::type_formatting::{SyscallBufferArg, SyscallErrnoRv, SyscallStringArg};
 use crate::host::syscall::types::{ForeignArrayPtr, SyscallError};
 use crate::utility::callback_queue::CallbackQueue;
 use crate::utility::u8_to_i8_slice;
@@ -180,7 +180,7 @@ impl SyscallHandler {
 
     log_syscall!(
         read,
-        /* rv */ isize,
+        /* rv */ SyscallErrnoRv<isize>,
         /* fd */ std::ffi::c_int,
         /* buf */ *const std::ffi::c_void,
         /* count */ usize,
@@ -220,11 +220,7 @@ impl SyscallHandler {
         let mut result = Self::read_helper(ctx, file.inner_file(), buf_ptr, buf_size, None);
 
         // if the syscall will block, keep the file open until the syscall restarts
-        if let Some(err) = result.as_mut().err() {
-            if let Some(cond) = err.blocked_condition() {
-                cond.set_active_file(file);
-            }
-        }
+        Self::keep_file_open_while_blocked(&mut result, file);
 
         let bytes_read = result?;
         Ok(bytes_read)
@@ -274,11 +270,7 @@ impl SyscallHandler {
         let mut result = Self::read_helper(ctx, file.inner_file(), buf_ptr, buf_size, Some(offset));
 
         // if the syscall will block, keep the file open until the syscall restarts
-        if let Some(err) = result.as_mut().err() {
-            if let Some(cond) = err.blocked_condition() {
-                cond.set_active_file(file);
-            }
-        }
+        Self::keep_file_open_while_blocked(&mut result, file);
 
         let bytes_read = result?;
         Ok(bytes_read)
@@ -300,7 +292,7 @@ impl SyscallHandler {
 
     log_syscall!(
         write,
-        /* rv */ isize,
+        /* rv */ SyscallErrnoRv<isize>,
         /* fd */ std::ffi::c_int,
         /* buf */ SyscallBufferArg</* count */ 2>,
         /* count */ usize,
@@ -340,11 +332,7 @@ impl SyscallHandler {
         let mut result = Self::write_helper(ctx, file.inner_file(), buf_ptr, buf_size, None);
 
         // if the syscall will block, keep the file open until the syscall restarts
-        if let Some(err) = result.as_mut().err() {
-            if let Some(cond) = err.blocked_condition() {
-                cond.set_active_file(file);
-            }
-        }
+        Self::keep_file_open_while_blocked(&mut result, file);
 
         let bytes_written = result?;
         Ok(bytes_written)
@@ -395,11 +383,7 @@ impl SyscallHandler {
             Self::write_helper(ctx, file.inner_file(), buf_ptr, buf_size, Some(offset));
 
         // if the syscall will block, keep the file open until the syscall restarts
-        if let Some(err) = result.as_mut().err() {
-            if let Some(cond) = err.blocked_condition() {
-                cond.set_active_file(file);
-            }
-        }
+        Self::keep_file_open_while_blocked(&mut result, file);
 
         let bytes_written = result?;
         Ok(bytes_written)
@@ -492,6 +476,11 @@ impl SyscallHandler {
 
         // set the file objects to listen for events on the buffer
         CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
+            // pipe(7): "POSIX.1 says that write(2)s of less than PIPE_BUF bytes must be atomic",
+            // so a blocked writer shouldn't be woken until there's space for an atomic write
+            buffer
+                .borrow_mut()
+                .set_writable_space(libc::PIPE_BUF, cb_queue);
             pipe::Pipe::connect_to_buffer(&reader, Arc::clone(&buffer), cb_queue);
             pipe::Pipe::connect_to_buffer(&writer, Arc::clone(&buffer), cb_queue);
         });
@@ -504,15 +493,26 @@ impl SyscallHandler {
         reader_desc.set_flags(descriptor_flags);
         writer_desc.set_flags(descriptor_flags);
 
-        // register the file descriptors
+        // register the file descriptors; either both are registered or neither are
+        let fd_limit = ctx.objs.process.fd_limit();
         let mut dt = ctx.objs.thread.descriptor_table_borrow_mut(ctx.objs.host);
-        // unwrap here since the error handling would be messy (need to deregister) and we shouldn't
-        // ever need to worry about this in practice
-        let read_fd = dt.register_descriptor(reader_desc).unwrap();
-        let write_fd = dt.register_descriptor(writer_desc).unwrap();
+        let registered = dt.register_descriptors(vec![reader_desc, writer_desc], fd_limit);
+
+        let new_fds = match registered {
+            Ok(fds) => fds,
+            Err(descs) => {
+                CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
+                    for desc in descs {
+                        // ignore any errors when closing
+                        desc.close(ctx.objs.host, cb_queue);
+                    }
+                });
+                return Err(Errno::EMFILE.into());
+            }
+        };
 
         // try to write them to the caller
-        let fds = [i32::from(read_fd), i32::from(write_fd)];
+        let fds = [i32::from(new_fds[0]), i32::from(new_fds[1])];
         let write_res = ctx.objs.process.memory_borrow_mut().write(fd_ptr, &fds);
 
         // clean up in case of error
@@ -520,13 +520,12 @@ impl SyscallHandler {
             Ok(_) => Ok(()),
             Err(e) => {
                 CallbackQueue::queue_and_run_with_legacy(|cb_queue| {
-                    // ignore any errors when closing
-                    dt.deregister_descriptor(read_fd)
-                        .unwrap()
-                        .close(ctx.objs.host, cb_queue);
-                    dt.deregister_descriptor(write_fd)
-                        .unwrap()
-                        .close(ctx.objs.host, cb_queue);
+                    for fd in new_fds {
+                        // ignore any errors when closing
+                        dt.deregister_descriptor(fd)
+                            .unwrap()
+                            .close(ctx.objs.host, cb_queue);
+                    }
                 });
                 Err(e.into())
             }
@@ -3,6 +3,7 @@ use std::mem::MaybeUninit;
 use std::ops::{Deref, DerefMut};
 
 use linux_api::errno::Errno;
+use linux_api::socket::AddressFamily;
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 
 use crate::host::memory_manager::MemoryManager;
@@ -16,47 +17,43 @@ use crate::utility::sockaddr::SockaddrStorage;
 /// size of the `plugin_addr` buffer. If the original value of `plugin_addr_len` is smaller than the
 /// socket address' length, then the written socket address will be truncated. In this case the
 /// value written to `plugin_addr_len` will be larger than its original value.
+///
+/// Like Linux's `move_addr_to_user()`, this returns `EFAULT` if `plugin_addr_len` can't be read,
+/// `EINVAL` if its value is negative when interpreted as an `int`, and `EFAULT` if the address
+/// can't be written. The address isn't accessed if the provided length is 0, so `plugin_addr` may be
+/// null in that case.
 pub fn write_sockaddr_and_len(
     mem: &mut MemoryManager,
     addr: Option<&SockaddrStorage>,
     plugin_addr: ForeignPtr<u8>,
     plugin_addr_len: ForeignPtr<libc::socklen_t>,
 ) -> Result<(), Errno> {
-    let addr = match addr {
-        Some(x) => x,
-        None => {
-            mem.write(plugin_addr_len, &0)?;
-            return Ok(());
-        }
-    };
-
-    let from_addr_slice = addr.as_slice();
+    let from_addr_slice = addr.map(|x| x.as_slice()).unwrap_or(&[]);
     let from_len: u32 = from_addr_slice.len().try_into().unwrap();
 
-    // get the provided address buffer length, and overwrite it with the real address length
-    let plugin_addr_len = {
-        let mut plugin_addr_len = mem.memory_ref_mut(ForeignArrayPtr::new(plugin_addr_len, 1))?;
-        let plugin_addr_len_value = plugin_addr_len.get_mut(0).unwrap();
+    // get the provided address buffer length
+    let plugin_addr_len_value = mem.read(plugin_addr_len)?;
 
-        // keep a copy before we change it
-        let plugin_addr_len_copy = *plugin_addr_len_value;
+    if (plugin_addr_len_value as std::ffi::c_int) < 0 {
+        return Err(Errno::EINVAL);
+    }
 
-        *plugin_addr_len_value = from_len;
+    // the minimum of the given address buffer length and the real address length
+    let len_to_copy = std::cmp::min(from_len, plugin_addr_len_value)
+        .try_into()
+        .unwrap();
 
-        plugin_addr_len.flush()?;
-        plugin_addr_len_copy
-    };
+    if len_to_copy > 0 {
+        if plugin_addr.is_null() {
+            return Err(Errno::EFAULT);
+        }
 
-    // return early if the address length is 0
-    if plugin_addr_len == 0 {
-        return Ok(());
+        let plugin_addr = ForeignArrayPtr::new(plugin_addr.cast::<MaybeUninit<u8>>(), len_to_copy);
+        mem.copy_to_ptr(plugin_addr, &from_addr_slice[..len_to_copy])?;
     }
 
-    // the minimum of the given address buffer length and the real address length
-    let len_to_copy = std::cmp::min(from_len, plugin_addr_len).try_into().unwrap();
-
-    let plugin_addr = ForeignArrayPtr::new(plugin_addr.cast::<MaybeUninit<u8>>(), len_to_copy);
-    mem.copy_to_ptr(plugin_addr, &from_addr_slice[..len_to_copy])?;
+    // overwrite the provided address buffer length with the real address length
+    mem.write(plugin_addr_len, &from_len)?;
 
     Ok(())
 }
@@ -122,6 +119,24 @@ pub fn read_sockaddr(
 
     let addr = unsafe { SockaddrStorage::from_bytes(addr_buf).ok_or(Errno::EINVAL)? };
 
+    // like linux, the address length must be valid for the address' family so that we never use an
+    // address with missing fields
+    let valid_len = match addr.family() {
+        Some(AddressFamily::AF_INET) => addr_len_usize >= std::mem::size_of::<libc::sockaddr_in>(),
+        Some(AddressFamily::AF_NETLINK) => {
+            addr_len_usize >= std::mem::size_of::<libc::sockaddr_nl>()
+        }
+        // the path is optional (an empty path is used for autobind), but can't be longer than
+        // `sun_path`
+        Some(AddressFamily::AF_UNIX) => addr_len_usize <= std::mem::size_of::<libc::sockaddr_un>(),
+        // the socket decides if the length is valid for other families, or if there is no family
+        _ => true,
+    };
+
+    if !valid_len {
+        return Err(Errno::EINVAL);
+    }
+
     Ok(Some(addr))
 }
 
@@ -259,6 +274,12 @@ impl<'a, I: Iterator<Item = &'a IoVec>> std::io::Read for IoVecReader<'a, I> {
                     // no iovs remaining
                     break;
                 };
+
+                // skip empty iovs; they don't mark the end of the data
+                if next_iov.len == 0 {
+                    continue;
+                }
+
                 self.current_src = Some((*next_iov).into());
             }
         }
@@ -334,6 +355,12 @@ impl<'a, I: Iterator<Item = &'a IoVec>> std::io::Write for IoVecWriter<'a, I> {
                     // no iovs remaining
                     break;
                 };
+
+                // skip empty iovs; they don't mark the end of the buffer
+                if next_iov.len == 0 {
+                    continue;
+                }
+
                 self.current_dst = Some((*next_iov).into());
             }
         }
@@ -346,7 +373,8 @@ impl<'a, I: Iterator<Item = &'a IoVec>> std::io::Write for IoVecWriter<'a, I> {
     }
 }
 
-/// Read a plugin's array of [`libc::iovec`] into a [`Vec<IoVec>`].
+/// Read a plugin's array of [`libc::iovec`] into a [`Vec<IoVec>`]. Returns `EINVAL` if there are
+/// more than `UIO_MAXIOV` iovecs, or if the total length of the iovecs doesn't fit in a `ssize_t`.
 pub fn read_iovecs(
     mem: &MemoryManager,
     iov_ptr: ForeignPtr<libc::iovec>,
@@ -356,13 +384,25 @@ pub fn read_iovecs(
         return Err(Errno::EINVAL);
     }
 
+    // linux doesn't read the array if it's empty, so the pointer may be invalid
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+
     let mut iovs = Vec::with_capacity(count);
+    let mut total_len: libc::ssize_t = 0;
 
     let iov_ptr = ForeignArrayPtr::new(iov_ptr, count);
     let mem_ref = mem.memory_ref(iov_ptr)?;
     let plugin_iovs = mem_ref.deref();
 
     for plugin_iov in plugin_iovs {
+        // the syscall returns the number of bytes as a `ssize_t`, so the total must not overflow it
+        total_len = libc::ssize_t::try_from(plugin_iov.iov_len)
+            .ok()
+            .and_then(|len| total_len.checked_add(len))
+            .ok_or(Errno::EINVAL)?;
+
         iovs.push(IoVec {
             base: ForeignPtr::from_raw_ptr(plugin_iov.iov_base as *mut u8),
             len: plugin_iov.iov_len,
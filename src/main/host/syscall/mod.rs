@@ -52,4 +52,14 @@ impl Trigger {
             state: FileState::CHILD_EVENT,
         })
     }
+
+    pub fn file_lock() -> Self {
+        Self(c::Trigger {
+            type_: c::_TriggerType_TRIGGER_FILE_LOCK,
+            object: c::TriggerObject {
+                as_pointer: core::ptr::null_mut(),
+            },
+            state: FileState::LOCK_RELEASED,
+        })
+    }
 }
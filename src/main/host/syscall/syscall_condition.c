@@ -77,6 +77,9 @@ SysCallCondition* syscallcondition_new(Trigger trigger) {
             case TRIGGER_CHILD: {
                 return cond;
             }
+            case TRIGGER_FILE_LOCK: {
+                return cond;
+            }
             case TRIGGER_NONE: {
                 return cond;
             }
@@ -146,6 +149,11 @@ static void _syscallcondition_cleanupListeners(SysCallCondition* cond) {
                 process_removeChildEventListener(host, proc, cond->triggerListener);
                 break;
             }
+            case TRIGGER_FILE_LOCK: {
+                const Host* host = worker_getCurrentHost();
+                host_removeFileLockListener(host, cond->triggerListener);
+                break;
+            }
             case TRIGGER_NONE: {
                 break;
             }
@@ -198,6 +206,10 @@ static void _syscallcondition_free(SysCallCondition* cond) {
                 // Not a pointer; nothing to unref.
                 break;
             }
+            case TRIGGER_FILE_LOCK: {
+                // Not a pointer; nothing to unref.
+                break;
+            }
             case TRIGGER_NONE: {
                 break;
             }
@@ -264,6 +276,11 @@ static void _syscallcondition_logListeningState(SysCallCondition* cond, const Pr
                             cond->timeoutExpiration != EMUTIME_INVALID ? " and " : "");
                 break;
             }
+            case TRIGGER_FILE_LOCK: {
+                g_string_append_printf(string, "status on file-lock-listener %s",
+                            cond->timeoutExpiration != EMUTIME_INVALID ? " and " : "");
+                break;
+            }
             case TRIGGER_NONE: {
                 break;
             }
@@ -311,6 +328,9 @@ static bool _syscallcondition_statusIsValid(SysCallCondition* cond) {
         case TRIGGER_CHILD: {
             return true;
         }
+        case TRIGGER_FILE_LOCK: {
+            return true;
+        }
         case TRIGGER_NONE: {
             break;
         }
@@ -516,6 +536,15 @@ void syscallcondition_waitNonblock(SysCallCondition* cond, const Host* host, con
 
                 break;
             }
+            case TRIGGER_FILE_LOCK: {
+                /* Monitor the requested status an every status change. */
+                statuslistener_setMonitorStatus(
+                    cond->triggerListener, cond->trigger.state, SLF_ALWAYS);
+
+                /* Attach the listener to the host's lock table. */
+                host_addFileLockListener(host, cond->triggerListener);
+                break;
+            }
             case TRIGGER_NONE: {
                 break;
             }
@@ -20,6 +20,8 @@ enum _TriggerType {
     TRIGGER_FUTEX,
     /* A child-process event, as for waitpid */
     TRIGGER_CHILD,
+    /* A record lock being released, as for fcntl(F_SETLKW) */
+    TRIGGER_FILE_LOCK,
 };
 
 /* Pointer to the object whose state we monitor for changes */
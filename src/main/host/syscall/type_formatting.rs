@@ -1,7 +1,9 @@
+use linux_api::errno::Errno;
+use linux_api::stat::SFlag;
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 use shadow_shim_helper_rs::util::DebugFormatter;
 
-use super::formatter::{FmtOptions, SyscallDisplay, SyscallVal};
+use super::formatter::{FmtMode, FmtOptions, SyscallDisplay, SyscallVal};
 use crate::host::memory_manager::MemoryManager;
 use crate::host::syscall::io::read_sockaddr;
 use crate::host::syscall::types::ForeignArrayPtr;
@@ -126,11 +128,11 @@ macro_rules! deref_pointer_impl {
                 mem: &MemoryManager,
             ) -> std::fmt::Result {
                 let ptr = ForeignPtr::<$type>::from(self.reg);
-                match (options, mem.memory_ref(ForeignArrayPtr::new(ptr, 1))) {
-                    (FmtOptions::Standard, Ok(vals)) => write!(f, "{:?} ({:p})", &(*vals)[0], ptr),
+                match (options.mode, mem.memory_ref(ForeignArrayPtr::new(ptr, 1))) {
+                    (FmtMode::Standard | FmtMode::HexDump, Ok(vals)) => write!(f, "{:?} ({:p})", &(*vals)[0], ptr),
                     // if we couldn't read the memory, just show the pointer instead
-                    (FmtOptions::Standard, Err(_)) => fmt_ptr_with_suffix(f, ptr, "<invalid-read>"),
-                    (FmtOptions::Deterministic, _) => write!(f, "<pointer>"),
+                    (FmtMode::Standard | FmtMode::HexDump, Err(_)) => fmt_ptr_with_suffix(f, ptr, "<invalid-read>"),
+                    (FmtMode::Deterministic, _) => write!(f, "<pointer>"),
                 }
             }
         }
@@ -153,9 +155,9 @@ macro_rules! safe_pointer_impl {
                 _mem: &MemoryManager,
             ) -> std::fmt::Result {
                 let ptr = ForeignPtr::<()>::from(self.reg);
-                match options {
-                    FmtOptions::Standard => write!(f, "{ptr:p}"),
-                    FmtOptions::Deterministic => write!(f, "<pointer>"),
+                match options.mode {
+                    FmtMode::Standard | FmtMode::HexDump => write!(f, "{ptr:p}"),
+                    FmtMode::Deterministic => write!(f, "<pointer>"),
                 }
             }
         }
@@ -178,11 +180,11 @@ macro_rules! deref_array_impl {
                 mem: &MemoryManager,
             ) -> std::fmt::Result {
                 let ptr = ForeignPtr::<$type>::from(self.reg);
-                match (options, mem.memory_ref(ForeignArrayPtr::new(ptr, K))) {
-                    (FmtOptions::Standard, Ok(vals)) => write!(f, "{:?} ({:p})", &(*vals), ptr),
+                match (options.mode, mem.memory_ref(ForeignArrayPtr::new(ptr, K))) {
+                    (FmtMode::Standard | FmtMode::HexDump, Ok(vals)) => write!(f, "{:?} ({:p})", &(*vals), ptr),
                     // if we couldn't read the memory, just show the pointer instead
-                    (FmtOptions::Standard, Err(_)) => fmt_ptr_with_suffix(f, ptr, "<invalid-read>"),
-                    (FmtOptions::Deterministic, _) => write!(f, "<pointer>"),
+                    (FmtMode::Standard | FmtMode::HexDump, Err(_)) => fmt_ptr_with_suffix(f, ptr, "<invalid-read>"),
+                    (FmtMode::Deterministic, _) => write!(f, "<pointer>"),
                 }
             }
         }
@@ -196,7 +198,6 @@ simple_display_impl!(u8, u16, u32, u64, usize);
 
 deref_pointer_impl!(i8, i16, i32, i64, isize);
 deref_pointer_impl!(u8, u16, u32, u64, usize);
-deref_pointer_impl!(linux_api::stat::stat);
 deref_pointer_impl!(linux_api::sched::clone_args);
 deref_pointer_impl!(linux_api::time::timespec);
 deref_pointer_impl!(linux_api::time::kernel_timespec);
@@ -210,11 +211,11 @@ safe_pointer_impl!(std::ffi::c_void);
 safe_pointer_impl!(libc::sockaddr);
 safe_pointer_impl!(linux_api::sysinfo::sysinfo);
 safe_pointer_impl!(libc::iovec);
+safe_pointer_impl!(libc::mmsghdr);
 
 // nix still uses an old bitflags version which isn't supported by `bitflags_impl`
 simple_debug_impl!(linux_api::sched::CloneFlags);
 simple_debug_impl!(linux_api::time::ITimerId);
-simple_debug_impl!(linux_api::time::ClockId);
 simple_debug_impl!(nix::sys::stat::Mode);
 simple_debug_impl!(nix::sys::eventfd::EfdFlags);
 simple_debug_impl!(nix::sys::socket::MsgFlags);
@@ -236,9 +237,9 @@ fn fmt_buffer(
     options: FmtOptions,
     mem: &MemoryManager,
 ) -> std::fmt::Result {
-    const DISPLAY_LEN: usize = 40;
+    let display_len = options.display_len;
 
-    if options == FmtOptions::Deterministic {
+    if options.mode == FmtMode::Deterministic {
         return write!(f, "<pointer>");
     }
 
@@ -248,7 +249,11 @@ fn fmt_buffer(
         Err(_) => return fmt_ptr_with_suffix(f, ptr, "<invalid-addr>"),
     };
 
-    let mut s = String::with_capacity(DISPLAY_LEN);
+    if options.mode == FmtMode::HexDump {
+        return fmt_hex_dump(f, &mem_ref, len, display_len);
+    }
+
+    let mut s = String::with_capacity(display_len);
 
     // the number of plugin mem bytes used; num_bytes <= s.len()
     let mut num_plugin_bytes = 0;
@@ -256,7 +261,7 @@ fn fmt_buffer(
     for c in mem_ref.iter() {
         let escaped = std::ascii::escape_default(*c);
 
-        if s.len() + escaped.len() > DISPLAY_LEN {
+        if s.len() + escaped.len() > display_len {
             break;
         }
 
@@ -274,6 +279,64 @@ fn fmt_buffer(
     }
 }
 
+/// Display up to `max_len` bytes of a buffer as a hex dump, with one row of 16 bytes per line.
+/// Each row shows the offset, the bytes in hex, and the printable ASCII characters. For example:
+///
+/// ```text
+/// [
+///   00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a        |Hello, world!.|
+/// ]
+/// ```
+///
+/// `len` is the full length of the buffer, which may be larger than `bytes` if not all of the
+/// buffer could be read.
+fn fmt_hex_dump(
+    f: &mut std::fmt::Formatter<'_>,
+    bytes: &[u8],
+    len: usize,
+    max_len: usize,
+) -> std::fmt::Result {
+    const ROW_LEN: usize = 16;
+
+    let bytes = &bytes[..std::cmp::min(bytes.len(), max_len)];
+
+    write!(f, "[")?;
+
+    for (row_idx, row) in bytes.chunks(ROW_LEN).enumerate() {
+        write!(f, "\n  {:08x} ", row_idx * ROW_LEN)?;
+
+        for i in 0..ROW_LEN {
+            // an extra space between the two groups of 8 bytes
+            if i == ROW_LEN / 2 {
+                write!(f, " ")?;
+            }
+
+            match row.get(i) {
+                Some(b) => write!(f, " {b:02x}")?,
+                // pad the row so that the ASCII column lines up
+                None => write!(f, "   ")?,
+            }
+        }
+
+        write!(f, "  |")?;
+        for b in row {
+            let c = if b.is_ascii_graphic() || *b == b' ' {
+                char::from(*b)
+            } else {
+                '.'
+            };
+            write!(f, "{c}")?;
+        }
+        write!(f, "|")?;
+    }
+
+    if len > bytes.len() {
+        write!(f, "\n  ...")?;
+    }
+
+    write!(f, "\n]")
+}
+
 fn fmt_string(
     f: &mut std::fmt::Formatter<'_>,
     ptr: ForeignPtr<u8>,
@@ -281,21 +344,27 @@ fn fmt_string(
     options: FmtOptions,
     mem: &MemoryManager,
 ) -> std::fmt::Result {
-    const DISPLAY_LEN: usize = 40;
+    let display_len = options.display_len;
 
-    if options == FmtOptions::Deterministic {
+    if options.mode == FmtMode::Deterministic {
         return write!(f, "<pointer>");
     }
 
     // the pointer may point to a buffer of unknown length, so we may have to choose our own size
-    let len = len.unwrap_or(
+    let len = len.unwrap_or(if options.utf8_strings {
+        // read up to one extra character to check if it's a NUL byte
+        //
+        // a character may take up to 4 bytes in UTF-8, so a buffer of size `4 * display_len + 1`
+        // should always be enough space to print a string of length `display_len`
+        display_len.saturating_mul(4).saturating_add(1)
+    } else {
         // read up to one extra character to check if it's a NUL byte
         //
         // each byte may take 1 byte to display (ex: 0x41 -> "A") or up to 4 bytes to display (ex:
-        // 0x00 -> "\x00"), so a buffer of size `DISPLAY_LEN + 1` should always be enough space to
-        // print a string of length `DISPLAY_LEN`
-        DISPLAY_LEN + 1,
-    );
+        // 0x00 -> "\x00"), so a buffer of size `display_len + 1` should always be enough space to
+        // print a string of length `display_len`
+        display_len.saturating_add(1)
+    });
 
     let mem_ref = match mem.memory_ref_prefix(ForeignArrayPtr::new(ptr, len)) {
         Ok(x) => x,
@@ -303,21 +372,33 @@ fn fmt_string(
         Err(_) => return fmt_ptr_with_suffix(f, ptr, "<invalid-addr>"),
     };
 
-    let mut s = String::with_capacity(DISPLAY_LEN);
+    let (s, found_nul) = if options.utf8_strings {
+        escape_string_utf8(&mem_ref, display_len)
+    } else {
+        escape_string_ascii(&mem_ref, display_len)
+    };
 
-    // the number of plugin mem bytes used; num_bytes <= s.len()
-    let mut found_nul = false;
+    if found_nul {
+        write!(f, "\"{s}\"")
+    } else {
+        write!(f, "\"{s}\"...")
+    }
+}
 
-    for c in mem_ref.iter() {
+/// Escape a string's bytes as ASCII, up to `display_len` characters or the first NUL byte. Returns
+/// the escaped string and whether a NUL byte was found.
+fn escape_string_ascii(bytes: &[u8], display_len: usize) -> (String, bool) {
+    let mut s = String::with_capacity(display_len);
+
+    for c in bytes {
         // if it's a NUL byte, it's the end of the string
         if *c == 0 {
-            found_nul = true;
-            break;
+            return (s, true);
         }
 
         let escaped = std::ascii::escape_default(*c);
 
-        if s.len() + escaped.len() > DISPLAY_LEN {
+        if s.len() + escaped.len() > display_len {
             break;
         }
 
@@ -326,11 +407,62 @@ fn fmt_string(
         }
     }
 
-    if found_nul {
-        write!(f, "\"{s}\"")
-    } else {
-        write!(f, "\"{s}\"...")
+    (s, false)
+}
+
+/// Decode a string's bytes as UTF-8, up to `display_len` characters or the first NUL byte. Valid
+/// printable characters are shown as-is, and invalid bytes are escaped (ex: 0xff -> "\xff"). A
+/// multi-byte character is never split. Returns the escaped string and whether a NUL byte was
+/// found.
+fn escape_string_utf8(bytes: &[u8], display_len: usize) -> (String, bool) {
+    let (bytes, found_nul) = match bytes.iter().position(|c| *c == 0) {
+        // if there's a NUL byte, it's the end of the string
+        Some(nul_idx) => (&bytes[..nul_idx], true),
+        None => (bytes, false),
+    };
+
+    let mut s = String::with_capacity(display_len);
+
+    // the number of displayed characters, which may be less than `s.len()`
+    let mut num_chars = 0;
+
+    let mut push_escaped = |escaped: &str| {
+        let escaped_chars = escaped.chars().count();
+        if num_chars + escaped_chars > display_len {
+            return false;
+        }
+        s.push_str(escaped);
+        num_chars += escaped_chars;
+        true
+    };
+
+    // whether we stopped before the end of the string
+    let mut truncated = false;
+
+    'outer: for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            let escaped = if c.is_ascii() {
+                // use the same escaping as for ASCII strings
+                std::ascii::escape_default(c as u8).to_string()
+            } else {
+                c.escape_debug().to_string()
+            };
+
+            if !push_escaped(&escaped) {
+                truncated = true;
+                break 'outer;
+            }
+        }
+
+        for b in chunk.invalid() {
+            if !push_escaped(&std::ascii::escape_default(*b).to_string()) {
+                truncated = true;
+                break 'outer;
+            }
+        }
     }
+
+    (s, found_nul && !truncated)
 }
 
 /// Format a plugin's `libc::msghdr`. Any pointers contained in the `libc::msghdr` must be pointers
@@ -379,6 +511,70 @@ fn fmt_msghdr(
     Ok(())
 }
 
+/// Format a plugin's `epoll_event`.
+fn fmt_epoll_event(
+    f: &mut std::fmt::Formatter<'_>,
+    event: &linux_api::epoll::epoll_event,
+) -> std::fmt::Result {
+    // copy the fields since the struct is packed
+    let events = event.events;
+    let data = event.data;
+
+    // prepare the event flags for formatting
+    let events = DebugFormatter(move |fmt| {
+        let events = linux_api::epoll::EpollEvents::from_bits_retain(events);
+        if events.is_empty() {
+            write!(fmt, "(empty)")
+        } else {
+            bitflags::parser::to_writer(&events, fmt)
+        }
+    });
+
+    // `data` is a union, but we don't know which member the plugin used (typically `fd` or `u64`),
+    // so show it as an integer
+    f.debug_struct("epoll_event")
+        .field("events", &events)
+        .field("data", &data)
+        .finish()
+}
+
+/// Format a plugin's `stat`, showing only the most useful fields.
+fn fmt_stat(f: &mut std::fmt::Formatter<'_>, stat: &linux_api::stat::stat) -> std::fmt::Result {
+    let mode = stat.st_mode;
+
+    // prepare the mode for formatting as the file type and permission bits (for example
+    // "S_IFSOCK|0777")
+    let st_mode = DebugFormatter(move |fmt| {
+        let file_type = SFlag::from_bits_retain(mode & SFlag::S_IFMT.bits());
+        let file_type = [
+            (SFlag::S_IFSOCK, "S_IFSOCK"),
+            (SFlag::S_IFLNK, "S_IFLNK"),
+            (SFlag::S_IFREG, "S_IFREG"),
+            (SFlag::S_IFBLK, "S_IFBLK"),
+            (SFlag::S_IFDIR, "S_IFDIR"),
+            (SFlag::S_IFCHR, "S_IFCHR"),
+            (SFlag::S_IFIFO, "S_IFIFO"),
+        ]
+        .into_iter()
+        .find(|(x, _)| *x == file_type)
+        .map(|(_, name)| name);
+
+        let permissions = mode & !SFlag::S_IFMT.bits();
+
+        match file_type {
+            Some(name) => write!(fmt, "{name}|{permissions:04o}"),
+            // unknown file type, so just show the mode as an integer
+            None => write!(fmt, "{mode:#o}"),
+        }
+    });
+
+    f.debug_struct("stat")
+        .field("st_mode", &st_mode)
+        .field("st_size", &stat.st_size)
+        .field("st_ino", &stat.st_ino)
+        .finish()
+}
+
 /// Displays a byte buffer with a specified length.
 pub struct SyscallBufferArg<const LEN_INDEX: usize> {}
 
@@ -395,6 +591,59 @@ impl<const LEN_INDEX: usize> SyscallDisplay for SyscallVal<'_, SyscallBufferArg<
     }
 }
 
+/// Displays the fds in an `fd_set`, where the number of fds in the set is given by another syscall
+/// argument (for example `select`'s `nfds`). Arguments are formatted after the syscall has run, so
+/// this shows the set as written back by the syscall (the ready fds), and is labelled as such.
+pub struct SyscallFdSetArg<const NFDS_INDEX: usize> {}
+
+impl<const NFDS_INDEX: usize> SyscallDisplay for SyscallVal<'_, SyscallFdSetArg<NFDS_INDEX>> {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        options: FmtOptions,
+        mem: &MemoryManager,
+    ) -> std::fmt::Result {
+        let ptr: ForeignPtr<u8> = self.reg.into();
+
+        if options.mode == FmtMode::Deterministic {
+            return write!(f, "<pointer>");
+        }
+
+        // if the syscall didn't complete successfully, the set wasn't written to and we don't know
+        // what it contained before the syscall ran
+        if self.rv.is_none() {
+            return write!(f, "{ptr:p}");
+        }
+
+        let nfds: libc::c_int = self.args[NFDS_INDEX].into();
+        // an `fd_set` can't hold more than `FD_SETSIZE` fds
+        let nfds = std::cmp::min(
+            usize::try_from(nfds).unwrap_or(0),
+            8 * size_of::<libc::fd_set>(),
+        );
+
+        // the `fd_set` is an array of longs, but we read it as bytes since only the first `nfds`
+        // bits are valid; we assume a little-endian architecture so that fd `i` is bit `i % 8` of
+        // byte `i / 8`
+        let ptr = ForeignArrayPtr::new(ptr, nfds.div_ceil(8));
+        let Ok(bytes) = mem.memory_ref(ptr) else {
+            // if we couldn't read the memory, just show the pointer instead
+            return fmt_ptr_with_suffix(f, ptr.ptr(), "<invalid-read>");
+        };
+
+        let fds = (0..nfds).filter(|fd| bytes[fd / 8] & (1 << (fd % 8)) != 0);
+
+        write!(f, "{{")?;
+        for (i, fd) in fds.enumerate() {
+            if i != 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{fd}")?;
+        }
+        write!(f, "}} (result) ({:p})", ptr.ptr())
+    }
+}
+
 /// Displays a nul-terminated string syscall argument.
 pub struct SyscallStringArg {}
 
@@ -410,6 +659,42 @@ impl SyscallDisplay for SyscallVal<'_, SyscallStringArg> {
     }
 }
 
+/// Displays a signal number syscall argument as the signal's name (for example `SIGTERM`). Signals
+/// without a name (such as realtime signals or the null signal 0) are shown as an integer.
+pub struct SyscallSignalArg {}
+
+impl SyscallDisplay for SyscallVal<'_, SyscallSignalArg> {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        _options: FmtOptions,
+        _mem: &MemoryManager,
+    ) -> std::fmt::Result {
+        let sig: std::ffi::c_int = self.reg.into();
+        match nix::sys::signal::Signal::try_from(sig) {
+            Ok(sig) => write!(f, "{}", sig.as_str()),
+            Err(_) => write!(f, "{sig}"),
+        }
+    }
+}
+
+/// Displays a `mode_t` syscall argument in octal (for example `0644` or `04755`), including any
+/// setuid, setgid, and sticky bits.
+#[derive(Debug)]
+pub struct SyscallModeArg {}
+
+impl SyscallDisplay for SyscallVal<'_, SyscallModeArg> {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        _options: FmtOptions,
+        _mem: &MemoryManager,
+    ) -> std::fmt::Result {
+        let mode: linux_api::posix_types::kernel_mode_t = self.reg.into();
+        write!(f, "0{mode:03o}")
+    }
+}
+
 pub struct SyscallSockAddrArg<const LEN_INDEX: usize> {}
 
 impl<const LEN_INDEX: usize> SyscallDisplay for SyscallVal<'_, SyscallSockAddrArg<LEN_INDEX>> {
@@ -419,7 +704,7 @@ impl<const LEN_INDEX: usize> SyscallDisplay for SyscallVal<'_, SyscallSockAddrAr
         options: FmtOptions,
         mem: &MemoryManager,
     ) -> std::fmt::Result {
-        if options == FmtOptions::Deterministic {
+        if options.mode == FmtMode::Deterministic {
             return write!(f, "<pointer>");
         }
 
@@ -459,7 +744,7 @@ where
         mem: &MemoryManager,
     ) -> std::fmt::Result {
         // if the user wants deterministic strace output, then we can't show the value
-        if options == FmtOptions::Deterministic {
+        if options.mode == FmtMode::Deterministic {
             return write!(f, "<non-deterministic>");
         }
 
@@ -468,6 +753,58 @@ where
     }
 }
 
+/// A wrapper type for syscall return values that displays a negative value in the errno range as
+/// the negated errno name (for example `-EAGAIN`). Other values are formatted as per the inner
+/// type.
+///
+/// This is opt-in for each syscall since some syscalls can return negative values on success. For
+/// example `lseek` may return a large offset, and `getpriority` may return a negative nice value.
+#[derive(Debug)]
+pub struct SyscallErrnoRv<T> {
+    _phantom: std::marker::PhantomData<T>,
+}
+
+impl<'a, T> SyscallDisplay for SyscallVal<'a, SyscallErrnoRv<T>>
+where
+    SyscallVal<'a, T>: SyscallDisplay,
+{
+    const SHOWS_ERRNO_NAME: bool = true;
+
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        options: FmtOptions,
+        mem: &MemoryManager,
+    ) -> std::fmt::Result {
+        // a value in the range [-4095, -1] is an error
+        let errno = i64::from(self.reg)
+            .checked_neg()
+            .and_then(|x| Errno::try_from(x).ok());
+
+        match errno {
+            Some(errno) => write!(f, "-{errno}"),
+            None => self.cast::<T>().fmt(f, options, mem),
+        }
+    }
+}
+
+/// Displays a clock id as its name (for example `CLOCK_MONOTONIC`). Clock ids without a name (such
+/// as the negative ids of process and thread CPU-time clocks) are shown as an integer.
+impl SyscallDisplay for SyscallVal<'_, linux_api::time::ClockId> {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        _options: FmtOptions,
+        _mem: &MemoryManager,
+    ) -> std::fmt::Result {
+        let clock_id: linux_api::time::linux___kernel_clockid_t = self.reg.into();
+        match linux_api::time::ClockId::try_from(clock_id) {
+            Ok(x) => write!(f, "{x:?}"),
+            Err(_) => write!(f, "{clock_id}"),
+        }
+    }
+}
+
 impl SyscallDisplay for SyscallVal<'_, *const libc::msghdr> {
     fn fmt(
         &self,
@@ -477,7 +814,7 @@ impl SyscallDisplay for SyscallVal<'_, *const libc::msghdr> {
     ) -> std::fmt::Result {
         let ptr: ForeignPtr<libc::msghdr> = self.reg.into();
 
-        if options == FmtOptions::Deterministic {
+        if options.mode == FmtMode::Deterministic {
             return write!(f, "<pointer>");
         }
 
@@ -496,3 +833,90 @@ impl SyscallDisplay for SyscallVal<'_, *const libc::msghdr> {
         write!(f, " ({:p})", ptr.ptr())
     }
 }
+
+impl SyscallDisplay for SyscallVal<'_, *const linux_api::epoll::epoll_event> {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        options: FmtOptions,
+        mem: &MemoryManager,
+    ) -> std::fmt::Result {
+        let ptr: ForeignPtr<linux_api::epoll::epoll_event> = self.reg.into();
+
+        if options.mode == FmtMode::Deterministic {
+            return write!(f, "<pointer>");
+        }
+
+        let ptr = ForeignArrayPtr::new(ptr, 1);
+        let Ok(event) = mem.memory_ref(ptr) else {
+            // if we couldn't read the memory, just show the pointer instead
+            return fmt_ptr_with_suffix(f, ptr.ptr(), "<invalid-read>");
+        };
+
+        fmt_epoll_event(f, &(*event)[0])?;
+        write!(f, " ({:p})", ptr.ptr())
+    }
+}
+
+impl SyscallDisplay for SyscallVal<'_, *const linux_api::stat::stat> {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        options: FmtOptions,
+        mem: &MemoryManager,
+    ) -> std::fmt::Result {
+        let ptr: ForeignPtr<linux_api::stat::stat> = self.reg.into();
+
+        if options.mode == FmtMode::Deterministic {
+            return write!(f, "<pointer>");
+        }
+
+        let ptr = ForeignArrayPtr::new(ptr, 1);
+        let Ok(stat) = mem.memory_ref(ptr) else {
+            // if we couldn't read the memory, just show the pointer instead
+            return fmt_ptr_with_suffix(f, ptr.ptr(), "<invalid-read>");
+        };
+
+        fmt_stat(f, &(*stat)[0])?;
+        write!(f, " ({:p})", ptr.ptr())
+    }
+}
+
+/// Displays the `epoll_event` array written by `epoll_wait` and similar syscalls. The number of
+/// events shown is the syscall's return value.
+pub struct SyscallEpollEventsArg {}
+
+impl SyscallDisplay for SyscallVal<'_, SyscallEpollEventsArg> {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        options: FmtOptions,
+        mem: &MemoryManager,
+    ) -> std::fmt::Result {
+        let ptr: ForeignPtr<linux_api::epoll::epoll_event> = self.reg.into();
+
+        if options.mode == FmtMode::Deterministic {
+            return write!(f, "<pointer>");
+        }
+
+        // if the syscall didn't complete successfully, the array wasn't written to
+        let Some(len) = self.rv.and_then(|rv| usize::try_from(i64::from(rv)).ok()) else {
+            return write!(f, "{ptr:p}");
+        };
+
+        let ptr = ForeignArrayPtr::new(ptr, len);
+        let Ok(events) = mem.memory_ref(ptr) else {
+            // if we couldn't read the memory, just show the pointer instead
+            return fmt_ptr_with_suffix(f, ptr.ptr(), "<invalid-read>");
+        };
+
+        write!(f, "[")?;
+        for (i, event) in events.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            fmt_epoll_event(f, event)?;
+        }
+        write!(f, "] ({:p})", ptr.ptr())
+    }
+}
@@ -228,6 +228,13 @@ impl SyscallError {
         })
     }
 
+    pub fn new_blocked_on_file_lock(restartable: bool) -> Self {
+        Self::Blocked(Blocked {
+            condition: SyscallCondition::new(Trigger::file_lock()),
+            restartable,
+        })
+    }
+
     pub fn new_blocked_until(unblock_time: EmulatedTime, restartable: bool) -> Self {
         Self::Blocked(Blocked {
             condition: SyscallCondition::new_from_wakeup_time(unblock_time),
@@ -242,6 +249,12 @@ impl SyscallError {
         })
     }
 
+    /// Returns `true` if the syscall failed with `EAGAIN` or `EWOULDBLOCK`. See
+    /// [`Errno::is_wouldblock`].
+    pub fn is_wouldblock(&self) -> bool {
+        matches!(self, Self::Failed(Failed { errno, .. }) if errno.is_wouldblock())
+    }
+
     /// Returns the [condition](SyscallCondition) that the syscall is blocked on.
     pub fn blocked_condition(&mut self) -> Option<&mut SyscallCondition> {
         if let Self::Blocked(Blocked { condition, .. }) = self {
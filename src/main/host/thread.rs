@@ -4,9 +4,8 @@ use std::cell::{Cell, RefCell};
 use std::ops::{Deref, DerefMut};
 
 use linux_api::errno::Errno;
-use linux_api::fcntl::DescriptorFlags;
 use linux_api::mman::{MapFlags, ProtFlags};
-use linux_api::posix_types::Pid;
+use linux_api::posix_types::{Pid, kernel_mode_t};
 use linux_api::signal::stack_t;
 use shadow_shim_helper_rs::HostId;
 use shadow_shim_helper_rs::explicit_drop::ExplicitDrop;
@@ -18,14 +17,13 @@ use shadow_shim_helper_rs::util::SendPointer;
 use shadow_shmem::allocator::{ShMemBlock, shmalloc};
 
 use super::context::ProcessContext;
-use super::descriptor::descriptor_table::{DescriptorHandle, DescriptorTable};
+use super::descriptor::descriptor_table::DescriptorTable;
 use super::host::Host;
 use super::managed_thread::{self, ManagedThread};
 use super::process::{Process, ProcessId};
 use crate::cshadow as c;
 use crate::host::syscall::condition::{SyscallConditionRef, SyscallConditionRefMut};
 use crate::host::syscall::handler::SyscallHandler;
-use crate::utility::callback_queue::CallbackQueue;
 use crate::utility::{IsSend, ObjectCounter, syscall};
 
 /// The thread's state after having been allowed to execute some code.
@@ -130,29 +128,7 @@ impl Thread {
             desc_table_rc.explicit_drop_recursive(host.root(), host);
 
             // Any descriptors with CLOEXEC are closed.
-            let to_close: Vec<DescriptorHandle> = desc_table
-                .iter()
-                .filter_map(|(handle, descriptor)| {
-                    if descriptor.flags().contains(DescriptorFlags::FD_CLOEXEC) {
-                        Some(*handle)
-                    } else {
-                        None
-                    }
-                })
-                .collect();
-
-            CallbackQueue::queue_and_run_with_legacy(|q| {
-                for handle in to_close {
-                    log::trace!("Unregistering FD_CLOEXEC descriptor {handle:?}");
-                    if let Some(Err(e)) = desc_table
-                        .deregister_descriptor(handle)
-                        .unwrap()
-                        .close(host, q)
-                    {
-                        log::debug!("Error closing {handle:?}: {e:?}");
-                    };
-                }
-            });
+            desc_table.do_exec_cloexec(host);
 
             self.desc_table = Some(RootedRc::new(
                 host.root(),
@@ -403,6 +379,16 @@ impl Thread {
         Ok(i32::from(res?))
     }
 
+    /// Natively execute a umask(2) syscall on the given thread.
+    pub fn native_umask(
+        &self,
+        ctx: &ProcessContext,
+        mask: kernel_mode_t,
+    ) -> Result<kernel_mode_t, Errno> {
+        let res = self.native_syscall(ctx, libc::SYS_umask, &[SyscallReg::from(mask)]);
+        Ok(kernel_mode_t::from(res?))
+    }
+
     /// Allocates some space in the plugin's memory. Use `get_writeable_ptr` to write to it, and
     /// `flush` to ensure that the write is flushed to the plugin's memory.
     pub fn malloc_foreign_ptr(
@@ -22,6 +22,7 @@ enum _ProtocolTCPFlags {
     PTCP_SACK = 1 << 4,
     PTCP_FIN = 1 << 5,
     PTCP_DUPACK = 1 << 6,
+    PTCP_URG = 1 << 7,
 };
 typedef enum _ProtocolTCPFlags ProtocolTCPFlags;
 
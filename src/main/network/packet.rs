@@ -450,6 +450,30 @@ impl Packet {
         self.meta.priority
     }
 
+    /// Sets the priority of the socket that sent the packet (`SO_PRIORITY`).
+    pub fn with_socket_priority(mut self, socket_priority: u32) -> Self {
+        self.meta.socket_priority = socket_priority;
+        self
+    }
+
+    /// Returns the priority of the socket that sent the packet (`SO_PRIORITY`). Shadow's queuing
+    /// disciplines don't currently use this, but it's available as a hint for QoS.
+    pub fn socket_priority(&self) -> u32 {
+        self.meta.socket_priority
+    }
+
+    /// Sets the mark of the socket that sent the packet (`SO_MARK`).
+    pub fn with_mark(mut self, mark: u32) -> Self {
+        self.meta.mark = mark;
+        self
+    }
+
+    /// Returns the mark of the socket that sent the packet (`SO_MARK`). Shadow doesn't support
+    /// policy routing, so this doesn't affect how the packet is routed.
+    pub fn mark(&self) -> u32 {
+        self.meta.mark
+    }
+
     /// Returns the packet's iana-assigned protocol type.
     pub fn iana_protocol(&self) -> IanaProtocol {
         self.data.iana_protocol()
@@ -747,6 +771,11 @@ struct Metadata {
     /// discipline. Smaller values have greater priority.
     // TODO: this can be removed once we support the TOS field in the `Header` struct.
     priority: FifoPacketPriority,
+    /// The priority set on the sending socket using `SO_PRIORITY`. Unlike `priority`, larger
+    /// values have greater priority.
+    socket_priority: u32,
+    /// The mark set on the sending socket using `SO_MARK`.
+    mark: u32,
     /// Tracks the sequence of operations that happen on this packet as is transits Shadow's
     /// network.
     statuses: Option<AtomicRefCell<Vec<PacketStatus>>>,
@@ -770,6 +799,8 @@ impl Metadata {
     pub fn new(priority: FifoPacketPriority) -> Self {
         Self {
             priority,
+            socket_priority: 0,
+            mark: 0,
             _host_id: None,
             _packet_id: None,
             // For efficiency, we only store statuses when tracing is enabled because they are
@@ -784,9 +815,17 @@ impl Metadata {
     ///
     /// This is currently used by the legacy C TCP stack and can be removed when the legacy TCP
     /// stack and our C packet API is removed.
-    fn new_legacy(priority: FifoPacketPriority, host_id: HostId, packet_id: u64) -> Self {
+    fn new_legacy(
+        priority: FifoPacketPriority,
+        socket_priority: u32,
+        mark: u32,
+        host_id: HostId,
+        packet_id: u64,
+    ) -> Self {
         Self {
             priority,
+            socket_priority,
+            mark,
             _host_id: Some(host_id),
             _packet_id: Some(packet_id),
             // For efficiency, we only store statuses when tracing is enabled because they are
@@ -966,6 +1005,34 @@ mod tests {
         assert_eq!(payload, chunks.first().unwrap());
     }
 
+    #[test]
+    fn ipv4_udp_socket_priority() {
+        let unspec = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
+        let payload = Bytes::from_static(b"Hello World!");
+
+        let packet = Packet::new_ipv4_udp(unspec, unspec, payload.clone(), 0);
+        assert_eq!(0, packet.socket_priority());
+
+        let packetrc = PacketRc::from(
+            Packet::new_ipv4_udp(unspec, unspec, payload, 123).with_socket_priority(6),
+        );
+        assert_eq!(6, packetrc.socket_priority());
+        assert_eq!(123, packetrc.priority());
+    }
+
+    #[test]
+    fn ipv4_udp_mark() {
+        let unspec = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
+        let payload = Bytes::from_static(b"Hello World!");
+
+        let packet = Packet::new_ipv4_udp(unspec, unspec, payload.clone(), 0);
+        assert_eq!(0, packet.mark());
+
+        let packetrc =
+            PacketRc::from(Packet::new_ipv4_udp(unspec, unspec, payload, 0).with_mark(0xABCD));
+        assert_eq!(0xABCD, packetrc.mark());
+    }
+
     #[test]
     fn ipv4_udp_empty() {
         let src = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 1), 10_000);
@@ -1038,6 +1105,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn ipv4_tcp_mark() {
+        let src = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 1), 10_000);
+        let dst = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 2), 80);
+        let tcp_hdr = make_tcp_header(src, dst);
+
+        let packet = Packet::new_ipv4_tcp(tcp_hdr, tcp::Payload(vec![]), 0);
+        assert_eq!(0, packet.mark());
+
+        let packetrc = PacketRc::from(
+            Packet::new_ipv4_tcp(tcp_hdr, tcp::Payload(vec![]), 0)
+                .with_socket_priority(6)
+                .with_mark(0xABCD),
+        );
+        assert_eq!(0xABCD, packetrc.mark());
+        assert_eq!(6, packetrc.socket_priority());
+    }
+
     #[test]
     fn ipv4_tcp_empty() {
         let src = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 1), 10_000);
@@ -1097,6 +1182,8 @@ mod export {
         dst_port: libc::in_port_t,
         seq: u32,
         priority: u64,
+        socket_priority: u32,
+        mark: u32,
     ) -> *mut Packet {
         // First construct the internet-level header.
         let header = Header::new(
@@ -1121,7 +1208,7 @@ mod export {
             payload: vec![],
         }));
 
-        let meta = Metadata::new_legacy(priority, host_id, packet_id);
+        let meta = Metadata::new_legacy(priority, socket_priority, mark, host_id, packet_id);
         let packet = Packet::new(header, data, meta);
 
         // Move ownership of the inner Arc reference to C (for now).
@@ -1345,6 +1432,53 @@ mod export {
         i64::try_from(tot_written).unwrap()
     }
 
+    /// Copy the payload data starting at `payload_offset` into the Shadow buffer `dst`. Returns
+    /// the number of bytes copied.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C-unwind" fn packet_copyPayloadShadow(
+        packet_ptr: *const Packet,
+        payload_offset: u64,
+        dst: *mut u8,
+        dst_len: u64,
+    ) -> u64 {
+        let packet = PacketRc::borrow_raw(packet_ptr);
+
+        let Data::LegacyTcp(tcp) = &packet.data else {
+            unimplemented!()
+        };
+
+        if dst_len == 0 {
+            return 0;
+        }
+
+        assert!(!dst.is_null());
+        let dst_len = usize::try_from(dst_len).unwrap();
+        let dst = unsafe { std::slice::from_raw_parts_mut(dst, dst_len) };
+
+        let mut num_copied = 0;
+        let mut src_offset = usize::try_from(payload_offset).unwrap_or(usize::MAX);
+
+        for bytes in &tcp.borrow().payload {
+            // This also skips over empty Bytes objects.
+            if src_offset >= bytes.len() {
+                src_offset = src_offset.saturating_sub(bytes.len());
+                continue;
+            }
+
+            let len = (bytes.len() - src_offset).min(dst_len - num_copied);
+            dst[num_copied..][..len].copy_from_slice(&bytes[src_offset..][..len]);
+
+            num_copied += len;
+            src_offset = 0;
+
+            if num_copied == dst_len {
+                break;
+            }
+        }
+
+        u64::try_from(num_copied).unwrap()
+    }
+
     #[unsafe(no_mangle)]
     pub extern "C-unwind" fn packet_getPriority(packet_ptr: *const Packet) -> u64 {
         let packet = PacketRc::borrow_raw(packet_ptr);
@@ -1420,7 +1554,7 @@ mod export {
     }
 
     fn legacy_flags_to_tcp_flags(legacy_flags: c::ProtocolTCPFlags) -> tcp::TcpFlags {
-        // The legacy flags use the first 7 bits of an i32. We could just do:
+        // The legacy flags use the first 8 bits of an i32. We could just do:
         // `u8::try_from(flags).expect("Legacy TCP flags use < 8 bits")` but we have to map the
         // values to support PCAP correctly.
         let mut tcp_flags = tcp::TcpFlags::empty();
@@ -1437,6 +1571,9 @@ mod export {
         if legacy_flags & c::_ProtocolTCPFlags_PTCP_ACK != 0 {
             tcp_flags.insert(tcp::TcpFlags::ACK);
         }
+        if legacy_flags & c::_ProtocolTCPFlags_PTCP_URG != 0 {
+            tcp_flags.insert(tcp::TcpFlags::URG);
+        }
         // These legacy flags don't exist as real TCP flags, so we overload the two bits that are
         // not shown in the PCAP output.
         if legacy_flags & c::_ProtocolTCPFlags_PTCP_SACK != 0 {
@@ -1467,6 +1604,9 @@ mod export {
         if tcp_flags.contains(tcp::TcpFlags::ACK) {
             legacy_flags |= c::_ProtocolTCPFlags_PTCP_ACK;
         }
+        if tcp_flags.contains(tcp::TcpFlags::URG) {
+            legacy_flags |= c::_ProtocolTCPFlags_PTCP_URG;
+        }
         // Extract these from the encoding used in `legacy_flags_to_tcp_flags()`.
         if tcp_flags.contains(tcp::TcpFlags::ECE) {
             legacy_flags |= c::_ProtocolTCPFlags_PTCP_SACK;
@@ -4,14 +4,66 @@
 //! attempting to mutate the same state simultaneously, an event queue is used to defer new events
 //! until the current event has finished running.
 
+use std::cell::Cell;
 use std::collections::VecDeque;
 use std::num::Wrapping;
 use std::sync::{Arc, Weak};
 
 use atomic_refcell::AtomicRefCell;
 
+thread_local! {
+    static RUN_STATS: Cell<RunStats> = const { Cell::new(RunStats::new()) };
+}
+
+/// Diagnostic information about the [`CallbackQueue`]s that are being run on the current thread.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RunStats {
+    /// The number of [`CallbackQueue::queue_and_run`] calls currently in progress.
+    pub depth: u32,
+    /// The number of queues currently running their callbacks.
+    pub running: u32,
+    /// The number of callbacks still waiting in the innermost queue that is running callbacks.
+    pub pending: usize,
+    /// The number of times a `queue_and_run` call was made from within a running callback. A
+    /// callback is given a queue to add new callbacks to, so it should rarely need to create its
+    /// own queue, and doing so runs the new callbacks before the callbacks that are already
+    /// waiting in the outer queue.
+    pub reentrant_calls: u64,
+}
+
+impl RunStats {
+    const fn new() -> Self {
+        Self {
+            depth: 0,
+            running: 0,
+            pending: 0,
+            reentrant_calls: 0,
+        }
+    }
+
+    fn update(f: impl FnOnce(&mut Self)) {
+        RUN_STATS.with(|stats| {
+            let mut x = stats.get();
+            f(&mut x);
+            stats.set(x);
+        });
+    }
+}
+
+/// Reverts a change to the [`RunStats`] when dropped, even if a callback panics.
+struct RunStatsGuard(fn(&mut RunStats));
+
+impl Drop for RunStatsGuard {
+    fn drop(&mut self) {
+        RunStats::update(self.0);
+    }
+}
+
 /// A queue of events (functions/closures) which when run can add their own events to the queue.
 /// This allows events to be deferred and run later.
+///
+/// Events are run in FIFO order. An event added while the queue is running is run after all events
+/// that were already in the queue, so the order that events run in is deterministic.
 #[allow(clippy::type_complexity)]
 pub struct CallbackQueue(VecDeque<Box<dyn FnOnce(&mut Self)>>);
 
@@ -34,19 +86,36 @@ impl CallbackQueue {
         self.0.push_back(Box::new(f));
     }
 
+    /// Get diagnostic information about the queues that are being run on the current thread.
+    pub fn run_stats() -> RunStats {
+        RUN_STATS.with(|stats| stats.get())
+    }
+
     /// Process all of the events in the queue (and any new events that are generated).
     pub fn run(&mut self) {
+        RunStats::update(|stats| stats.running += 1);
+        let _guard = RunStatsGuard(|stats| stats.running -= 1);
+
         // loop until there are no more events
         let mut count = 0;
         while let Some(f) = self.0.pop_front() {
+            let pending = self.len();
+            RunStats::update(|stats| stats.pending = pending);
+
             // run the event and allow it to add new events
             (f)(self);
 
             count += 1;
             if count == 10_000 {
-                log::trace!("Possible infinite loop of event callbacks.");
+                log::trace!(
+                    "Possible infinite loop of event callbacks ({} pending).",
+                    self.len()
+                );
             } else if count == 10_000_000 {
-                log::warn!("Very likely an infinite loop of event callbacks.");
+                log::warn!(
+                    "Very likely an infinite loop of event callbacks ({} pending).",
+                    self.len()
+                );
             }
         }
     }
@@ -57,6 +126,23 @@ impl CallbackQueue {
     where
         F: FnOnce(&mut Self) -> U,
     {
+        let mut reentrant = false;
+        RunStats::update(|stats| {
+            stats.depth += 1;
+            if stats.running > 0 {
+                stats.reentrant_calls += 1;
+                reentrant = true;
+            }
+        });
+        let _guard = RunStatsGuard(|stats| stats.depth -= 1);
+
+        if reentrant {
+            log::debug!(
+                "A callback started a new callback queue; {} callbacks are waiting in the outer queue",
+                Self::run_stats().pending,
+            );
+        }
+
         let mut cb_queue = Self::new();
         let rv = (f)(&mut cb_queue);
         cb_queue.run();
@@ -149,13 +235,51 @@ impl<T: Clone + Copy + 'static> EventSource<T> {
         notify_fn: impl Fn(T, &mut CallbackQueue) + Send + Sync + 'static,
     ) -> Handle<T> {
         let inner_ref = Arc::downgrade(&Arc::clone(&self.inner));
-        self.inner.borrow_mut().add_listener(inner_ref, notify_fn)
+        self.inner
+            .borrow_mut()
+            .add_listener(inner_ref, notify_fn, None)
+    }
+
+    /// Add an exclusive listener. A message that `is_wakeup` returns true for is only passed to one
+    /// of the exclusive listeners that it would wake up, chosen in round-robin order. All other
+    /// messages are passed to every exclusive listener.
+    pub fn add_exclusive_listener(
+        &mut self,
+        is_wakeup: impl Fn(&T) -> bool + Send + Sync + 'static,
+        notify_fn: impl Fn(T, &mut CallbackQueue) + Send + Sync + 'static,
+    ) -> Handle<T> {
+        let inner_ref = Arc::downgrade(&Arc::clone(&self.inner));
+        self.inner
+            .borrow_mut()
+            .add_listener(inner_ref, notify_fn, Some(Box::new(is_wakeup)))
     }
 
-    /// Notify all listeners.
+    /// Notify all listeners. The listeners are added to the queue in the order that they were added
+    /// to this event source.
     pub fn notify_listeners(&mut self, message: T, cb_queue: &mut CallbackQueue) {
-        for (_, l) in &self.inner.borrow().listeners {
-            let l_clone = l.clone();
+        let mut inner = self.inner.borrow_mut();
+
+        let is_wakeup: Vec<bool> = inner
+            .listeners
+            .iter()
+            .map(|l| l.is_wakeup.as_ref().is_some_and(|f| f(&message)))
+            .collect();
+
+        // choose the next exclusive listener to wake up, starting from the one after the listener
+        // that was woken up last time
+        let woken = (inner.next_exclusive..is_wakeup.len())
+            .chain(0..inner.next_exclusive)
+            .find(|&i| is_wakeup[i]);
+        if let Some(woken) = woken {
+            inner.next_exclusive = woken + 1;
+        }
+
+        for (i, l) in inner.listeners.iter().enumerate() {
+            if is_wakeup[i] && Some(i) != woken {
+                continue;
+            }
+
+            let l_clone = l.notify_fn.clone();
             cb_queue.add(move |cb_queue| (l_clone)(message, cb_queue));
         }
     }
@@ -168,10 +292,20 @@ impl<T: Clone + Copy + 'static> Default for EventSource<T> {
 }
 
 type Listener<T> = Arc<dyn Fn(T, &mut CallbackQueue) + Send + Sync>;
+type WakeupFilter<T> = Box<dyn Fn(&T) -> bool + Send + Sync>;
+
+struct ListenerEntry<T> {
+    id: HandleId,
+    notify_fn: Listener<T>,
+    /// Set for exclusive listeners.
+    is_wakeup: Option<WakeupFilter<T>>,
+}
 
 struct EventSourceInner<T> {
-    listeners: Vec<(HandleId, Listener<T>)>,
+    listeners: Vec<ListenerEntry<T>>,
     next_id: Wrapping<u32>,
+    /// The position in `listeners` to start searching from for the next exclusive listener to wake.
+    next_exclusive: usize,
 }
 
 impl<T> EventSourceInner<T> {
@@ -179,6 +313,7 @@ impl<T> EventSourceInner<T> {
         Self {
             listeners: Vec::new(),
             next_id: Wrapping(0),
+            next_exclusive: 0,
         }
     }
 
@@ -189,7 +324,7 @@ impl<T> EventSourceInner<T> {
             let id = HandleId(self.next_id.0);
             self.next_id += Wrapping(1);
 
-            if !self.listeners.iter().any(|x| x.0 == id) {
+            if !self.listeners.iter().any(|x| x.id == id) {
                 break id;
             }
         }
@@ -199,17 +334,27 @@ impl<T> EventSourceInner<T> {
         &mut self,
         inner: std::sync::Weak<AtomicRefCell<Self>>,
         notify_fn: impl Fn(T, &mut CallbackQueue) + Send + Sync + 'static,
+        is_wakeup: Option<WakeupFilter<T>>,
     ) -> Handle<T> {
         let handle_id = self.get_unused_id();
 
-        self.listeners.push((handle_id, Arc::new(notify_fn)));
+        self.listeners.push(ListenerEntry {
+            id: handle_id,
+            notify_fn: Arc::new(notify_fn),
+            is_wakeup,
+        });
 
         Handle::new(handle_id, inner)
     }
 
     pub fn remove_listener(&mut self, id: HandleId) {
-        self.listeners
-            .remove(self.listeners.iter().position(|x| x.0 == id).unwrap());
+        let position = self.listeners.iter().position(|x| x.id == id).unwrap();
+        self.listeners.remove(position);
+
+        // keep the round-robin position pointing at the same listener
+        if position < self.next_exclusive {
+            self.next_exclusive -= 1;
+        }
     }
 }
 
@@ -238,4 +383,117 @@ mod tests {
 
         assert_eq!(*counter.borrow(), 4);
     }
+
+    #[test]
+    fn test_fifo_order() {
+        let order = Arc::new(AtomicRefCell::new(Vec::new()));
+
+        CallbackQueue::queue_and_run(|queue| {
+            for i in 0..3 {
+                let order = Arc::clone(&order);
+                queue.add(move |queue| {
+                    order.borrow_mut().push(i);
+
+                    // events added by an event run after the events already in the queue
+                    let order = Arc::clone(&order);
+                    queue.add(move |_| order.borrow_mut().push(i + 10));
+                });
+            }
+        });
+
+        assert_eq!(*order.borrow(), [0, 1, 2, 10, 11, 12]);
+    }
+
+    #[test]
+    fn test_listener_order() {
+        let order = Arc::new(AtomicRefCell::new(Vec::new()));
+
+        let mut source = EventSource::new();
+
+        let mut handles: Vec<_> = (0..4)
+            .map(|i| {
+                let order = Arc::clone(&order);
+                source.add_listener(move |(), _| order.borrow_mut().push(i))
+            })
+            .collect();
+
+        CallbackQueue::queue_and_run(|queue| source.notify_listeners((), queue));
+        assert_eq!(*order.borrow(), [0, 1, 2, 3]);
+
+        // removing a listener doesn't change the order of the others
+        handles.remove(1).stop_listening();
+        order.borrow_mut().clear();
+
+        CallbackQueue::queue_and_run(|queue| source.notify_listeners((), queue));
+        assert_eq!(*order.borrow(), [0, 2, 3]);
+    }
+
+    #[test]
+    fn test_exclusive_listeners() {
+        let order = Arc::new(AtomicRefCell::new(Vec::new()));
+
+        let mut source = EventSource::new();
+
+        let _handles: Vec<_> = (0..3)
+            .map(|i| {
+                let order = Arc::clone(&order);
+                source.add_exclusive_listener(
+                    |wakeup: &bool| *wakeup,
+                    move |_, _| order.borrow_mut().push(i),
+                )
+            })
+            .collect();
+
+        let order_clone = Arc::clone(&order);
+        let _handle = source.add_listener(move |_, _| order_clone.borrow_mut().push(10));
+
+        // each wakeup goes to the next exclusive listener, and to every non-exclusive listener
+        for _ in 0..4 {
+            CallbackQueue::queue_and_run(|queue| source.notify_listeners(true, queue));
+        }
+        assert_eq!(*order.borrow(), [0, 10, 1, 10, 2, 10, 0, 10]);
+
+        // other messages go to every listener
+        order.borrow_mut().clear();
+        CallbackQueue::queue_and_run(|queue| source.notify_listeners(false, queue));
+        assert_eq!(*order.borrow(), [0, 1, 2, 10]);
+    }
+
+    #[test]
+    fn test_reentrant_queue_and_run() {
+        let order = Arc::new(AtomicRefCell::new(Vec::new()));
+
+        CallbackQueue::queue_and_run(|queue| {
+            // a nested call from the closure rather than from a callback is not reentrant
+            CallbackQueue::queue_and_run(|_| {
+                assert_eq!(CallbackQueue::run_stats().depth, 2);
+            });
+            assert_eq!(CallbackQueue::run_stats().reentrant_calls, 0);
+
+            let order_clone = Arc::clone(&order);
+            queue.add(move |_| {
+                let stats = CallbackQueue::run_stats();
+                assert_eq!(stats.depth, 1);
+                assert_eq!(stats.running, 1);
+                assert_eq!(stats.pending, 1);
+
+                // the new queue's callbacks run before the outer queue's remaining callbacks
+                let order_inner = Arc::clone(&order_clone);
+                CallbackQueue::queue_and_run(|queue| {
+                    queue.add(move |_| order_inner.borrow_mut().push("inner"))
+                });
+                order_clone.borrow_mut().push("first");
+            });
+
+            let order_clone = Arc::clone(&order);
+            queue.add(move |_| order_clone.borrow_mut().push("second"));
+        });
+
+        assert_eq!(*order.borrow(), ["inner", "first", "second"]);
+
+        let stats = CallbackQueue::run_stats();
+        assert_eq!(stats.reentrant_calls, 1);
+        assert_eq!(stats.depth, 0);
+        assert_eq!(stats.running, 0);
+    }
 }
@@ -0,0 +1,370 @@
+//! Parsing and building socket control messages ("cmsgs"). These follow the same alignment rules as
+//! the kernel's `CMSG_ALIGN()`, `CMSG_LEN()`, and `CMSG_SPACE()` macros so that the control buffers
+//! are laid out the way that libc's `CMSG_FIRSTHDR()` and `CMSG_NXTHDR()` expect.
+
+use linux_api::errno::Errno;
+use static_assertions::{assert_eq_size, const_assert_eq};
+
+/// The length of a control message header.
+const HDR_LEN: usize = std::mem::size_of::<libc::cmsghdr>();
+
+// the header has no padding bytes
+assert_eq_size!(
+    libc::cmsghdr,
+    [u8; std::mem::size_of::<libc::size_t>() + 2 * std::mem::size_of::<libc::c_int>()],
+);
+
+// the data immediately follows the header
+const_assert_eq!(cmsg_align(HDR_LEN), HDR_LEN);
+
+/// Round up a length to the alignment of a control message. Equivalent to `CMSG_ALIGN()`.
+pub const fn cmsg_align(len: usize) -> usize {
+    len.next_multiple_of(std::mem::size_of::<libc::c_long>())
+}
+
+/// The value of the `cmsg_len` field for a control message with `data_len` bytes of data.
+/// Equivalent to `CMSG_LEN()`.
+pub const fn cmsg_len(data_len: usize) -> usize {
+    HDR_LEN + data_len
+}
+
+/// The number of bytes used by a control message with `data_len` bytes of data, including the
+/// padding before the next control message. Equivalent to `CMSG_SPACE()`.
+pub const fn cmsg_space(data_len: usize) -> usize {
+    HDR_LEN + cmsg_align(data_len)
+}
+
+/// A control message in a control buffer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ControlMessage<'a> {
+    pub level: libc::c_int,
+    pub ty: libc::c_int,
+    pub data: &'a [u8],
+}
+
+/// An iterator over the control messages in a control buffer.
+///
+/// Like the kernel, any trailing bytes that are too short to hold a control message header are
+/// ignored. If a control message has a length that's too short for its header or that extends past
+/// the end of the buffer, an `EINVAL` error is returned and the iteration stops.
+pub struct ControlMessages<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> ControlMessages<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+}
+
+impl<'a> Iterator for ControlMessages<'a> {
+    type Item = Result<ControlMessage<'a>, Errno>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let hdr: &[u8; HDR_LEN] = self.buf.get(..HDR_LEN)?.try_into().unwrap();
+        let hdr: libc::cmsghdr = shadow_pod::from_array(hdr);
+
+        let len = hdr.cmsg_len;
+
+        if len < HDR_LEN || len > self.buf.len() {
+            self.buf = &[];
+            return Some(Err(Errno::EINVAL));
+        }
+
+        let msg = ControlMessage {
+            level: hdr.cmsg_level,
+            ty: hdr.cmsg_type,
+            data: &self.buf[HDR_LEN..len],
+        };
+
+        // the next control message starts after the padding, but the padding of the last control
+        // message may extend past the end of the buffer
+        self.buf = self.buf.get(cmsg_align(len)..).unwrap_or(&[]);
+
+        Some(Ok(msg))
+    }
+}
+
+/// Builds a control buffer of up to `capacity` bytes.
+///
+/// Like the kernel's `put_cmsg()`, a control message that doesn't fit in the remaining space is
+/// truncated and the buffer is marked as truncated, which should be reported to the plugin with
+/// `MSG_CTRUNC`.
+#[derive(Debug)]
+pub struct ControlMessageWriter {
+    buf: Vec<u8>,
+    capacity: usize,
+    truncated: bool,
+}
+
+impl ControlMessageWriter {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buf: Vec::new(),
+            capacity,
+            truncated: false,
+        }
+    }
+
+    /// The max number of data bytes that the next control message can have without being
+    /// truncated.
+    pub fn data_space(&self) -> usize {
+        (self.capacity - self.buf.len()).saturating_sub(HDR_LEN)
+    }
+
+    /// Append a control message. If there isn't enough space for all of the data, only as much of
+    /// the data as fits is written. If there isn't enough space for the header, nothing is written.
+    pub fn push(&mut self, level: libc::c_int, ty: libc::c_int, data: &[u8]) {
+        let space = self.capacity - self.buf.len();
+
+        if space < HDR_LEN {
+            self.truncated = true;
+            return;
+        }
+
+        let len = cmsg_len(data.len());
+        let written_len = std::cmp::min(len, space);
+        if written_len < len {
+            self.truncated = true;
+        }
+
+        let mut hdr: libc::cmsghdr = shadow_pod::zeroed();
+        hdr.cmsg_len = written_len;
+        hdr.cmsg_level = level;
+        hdr.cmsg_type = ty;
+
+        // SAFETY: the header has no padding bytes, so all of its bytes are initialized
+        let hdr: [u8; HDR_LEN] = unsafe { std::mem::transmute(hdr) };
+
+        self.buf.extend_from_slice(&hdr);
+        self.buf.extend_from_slice(&data[..written_len - HDR_LEN]);
+
+        // pad to the start of the next control message, if there's space
+        let padding = std::cmp::min(cmsg_space(data.len()), space) - written_len;
+        self.buf.resize(self.buf.len() + padding, 0);
+    }
+
+    /// Mark the buffer as truncated. This is useful when some data was intentionally left out of a
+    /// control message because it didn't fit.
+    pub fn set_truncated(&mut self) {
+        self.truncated = true;
+    }
+
+    /// Was any control data truncated or left out?
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// The control buffer, which is no larger than the capacity.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Convert a list of descriptors to `SCM_RIGHTS` data.
+    fn fds_to_bytes(fds: &[libc::c_int]) -> Vec<u8> {
+        fds.iter().flat_map(|x| x.to_ne_bytes()).collect()
+    }
+
+    /// Convert credentials to `SCM_CREDENTIALS` data.
+    fn creds_to_bytes(creds: libc::ucred) -> Vec<u8> {
+        [
+            creds.pid.to_ne_bytes(),
+            creds.uid.to_ne_bytes(),
+            creds.gid.to_ne_bytes(),
+        ]
+        .concat()
+    }
+
+    /// Write a control message with the given `cmsg_len` value and data to `buf` at `offset`.
+    fn write_message(buf: &mut [u8], offset: usize, len: usize, ty: i32, data: &[u8]) {
+        let mut hdr: libc::cmsghdr = shadow_pod::zeroed();
+        hdr.cmsg_len = len;
+        hdr.cmsg_level = libc::SOL_SOCKET;
+        hdr.cmsg_type = ty;
+
+        let hdr: [u8; HDR_LEN] = unsafe { std::mem::transmute(hdr) };
+        buf[offset..][..HDR_LEN].copy_from_slice(&hdr);
+        buf[offset + HDR_LEN..][..data.len()].copy_from_slice(data);
+    }
+
+    #[test]
+    fn macros_match_libc() {
+        for data_len in 0..64 {
+            let libc_len = unsafe { libc::CMSG_LEN(data_len as u32) };
+            let libc_space = unsafe { libc::CMSG_SPACE(data_len as u32) };
+            assert_eq!(cmsg_len(data_len), libc_len as usize);
+            assert_eq!(cmsg_space(data_len), libc_space as usize);
+        }
+    }
+
+    /// Parse a hand-constructed buffer with two control messages of different types, where the
+    /// first needs padding.
+    #[test]
+    fn parse_two_messages() {
+        let fds = fds_to_bytes(&[7]);
+        let creds = creds_to_bytes(libc::ucred {
+            pid: 10,
+            uid: 20,
+            gid: 30,
+        });
+
+        // the first message is 20 bytes, followed by 4 bytes of padding
+        let fds_len = unsafe { libc::CMSG_LEN(fds.len() as u32) } as usize;
+        let creds_len = unsafe { libc::CMSG_LEN(creds.len() as u32) } as usize;
+        let second_offset = unsafe { libc::CMSG_SPACE(fds.len() as u32) } as usize;
+        assert_eq!((fds_len, second_offset), (20, 24));
+
+        let mut buf = vec![0xff; second_offset + cmsg_space(creds.len())];
+        write_message(&mut buf, 0, fds_len, libc::SCM_RIGHTS, &fds);
+        write_message(
+            &mut buf,
+            second_offset,
+            creds_len,
+            libc::SCM_CREDENTIALS,
+            &creds,
+        );
+
+        let msgs: Vec<_> = ControlMessages::new(&buf)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(
+            msgs,
+            [
+                ControlMessage {
+                    level: libc::SOL_SOCKET,
+                    ty: libc::SCM_RIGHTS,
+                    data: &fds,
+                },
+                ControlMessage {
+                    level: libc::SOL_SOCKET,
+                    ty: libc::SCM_CREDENTIALS,
+                    data: &creds,
+                },
+            ]
+        );
+
+        // the last message's padding isn't required
+        let msgs: Vec<_> = ControlMessages::new(&buf[..second_offset + creds_len])
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(msgs.len(), 2);
+
+        // trailing bytes that are too short for a header are ignored
+        let msgs: Vec<_> = ControlMessages::new(&buf[..second_offset + HDR_LEN - 1])
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(msgs.len(), 1);
+    }
+
+    #[test]
+    fn parse_invalid_len() {
+        let mut buf = vec![0; 64];
+
+        // too short for the header
+        write_message(&mut buf, 0, HDR_LEN - 1, libc::SCM_RIGHTS, &[]);
+        let mut msgs = ControlMessages::new(&buf);
+        assert_eq!(msgs.next(), Some(Err(Errno::EINVAL)));
+        assert_eq!(msgs.next(), None);
+
+        // longer than the buffer
+        write_message(&mut buf, 0, 65, libc::SCM_RIGHTS, &[]);
+        let mut msgs = ControlMessages::new(&buf);
+        assert_eq!(msgs.next(), Some(Err(Errno::EINVAL)));
+        assert_eq!(msgs.next(), None);
+
+        // the second message is invalid
+        write_message(&mut buf, 0, HDR_LEN, libc::SCM_RIGHTS, &[]);
+        write_message(&mut buf, HDR_LEN, 64, libc::SCM_RIGHTS, &[]);
+        let mut msgs = ControlMessages::new(&buf);
+        assert!(msgs.next().unwrap().is_ok());
+        assert_eq!(msgs.next(), Some(Err(Errno::EINVAL)));
+        assert_eq!(msgs.next(), None);
+    }
+
+    /// Build a buffer with two control messages of different types, and walk it with libc's
+    /// macros.
+    #[test]
+    fn write_two_messages() {
+        let creds = creds_to_bytes(libc::ucred {
+            pid: 10,
+            uid: 20,
+            gid: 30,
+        });
+        let fds = fds_to_bytes(&[7, 8, 9]);
+
+        let mut writer = ControlMessageWriter::new(256);
+        writer.push(libc::SOL_SOCKET, libc::SCM_CREDENTIALS, &creds);
+        writer.push(libc::SOL_SOCKET, libc::SCM_RIGHTS, &fds);
+        assert!(!writer.is_truncated());
+
+        let bytes = writer.as_bytes();
+        assert_eq!(bytes.len(), cmsg_space(creds.len()) + cmsg_space(fds.len()));
+
+        // copy to an aligned buffer for libc
+        let mut buf = vec![0u64; bytes.len().div_ceil(8)];
+        let buf_bytes = unsafe { shadow_pod::to_u8_slice_mut(&mut buf) };
+        buf_bytes[..bytes.len()].copy_from_slice(shadow_pod::to_u8_slice(bytes));
+
+        let msg = libc::msghdr {
+            msg_name: std::ptr::null_mut(),
+            msg_namelen: 0,
+            msg_iov: std::ptr::null_mut(),
+            msg_iovlen: 0,
+            msg_control: buf.as_mut_ptr() as *mut libc::c_void,
+            msg_controllen: bytes.len(),
+            msg_flags: 0,
+        };
+
+        let mut found = Vec::new();
+        let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+        while !cmsg.is_null() {
+            let hdr = unsafe { *cmsg };
+            let data_len = hdr.cmsg_len - HDR_LEN;
+            let data = unsafe { std::slice::from_raw_parts(libc::CMSG_DATA(cmsg), data_len) };
+            found.push((hdr.cmsg_level, hdr.cmsg_type, data.to_vec()));
+            cmsg = unsafe { libc::CMSG_NXTHDR(&msg, cmsg) };
+        }
+
+        assert_eq!(
+            found,
+            [
+                (libc::SOL_SOCKET, libc::SCM_CREDENTIALS, creds),
+                (libc::SOL_SOCKET, libc::SCM_RIGHTS, fds),
+            ]
+        );
+    }
+
+    #[test]
+    fn write_truncated() {
+        let fds = fds_to_bytes(&[7, 8, 9]);
+
+        // space for the header and one descriptor
+        let mut writer = ControlMessageWriter::new(cmsg_len(4));
+        assert_eq!(writer.data_space(), 4);
+        writer.push(libc::SOL_SOCKET, libc::SCM_RIGHTS, &fds);
+        assert!(writer.is_truncated());
+        assert_eq!(writer.as_bytes().len(), cmsg_len(4));
+
+        let msgs: Vec<_> = ControlMessages::new(writer.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            msgs,
+            [ControlMessage {
+                level: libc::SOL_SOCKET,
+                ty: libc::SCM_RIGHTS,
+                data: &fds[..4],
+            }]
+        );
+
+        // no space for another header
+        writer.push(libc::SOL_SOCKET, libc::SCM_RIGHTS, &[]);
+        assert_eq!(writer.as_bytes().len(), cmsg_len(4));
+    }
+}
@@ -158,7 +158,7 @@ macro_rules! log_syscall {
                     mem: &crate::host::memory_manager::MemoryManager,
                 ) -> std::io::Result<()>
                 {
-                    let syscall_args = <crate::host::syscall::formatter::SyscallArgsFmt::<$($args),*>>::new(args, fmt, mem);
+                    let syscall_args = <crate::host::syscall::formatter::SyscallArgsFmt::<$($args),*>>::new(args, rv, fmt, mem);
                     let syscall_rv = crate::host::syscall::formatter::SyscallResultFmt::<$rv>::new(&rv, args, fmt, mem);
 
                     crate::host::syscall::formatter::write_syscall(
@@ -1124,6 +1124,56 @@ else:
     })
 }
 
+/// When only some descriptors have FD_CLOEXEC set, exactly those descriptors should be closed by
+/// execve and all others should be retained.
+fn test_fork_exec_cloexec_mixed(python_path: &Path) -> anyhow::Result<()> {
+    run_test_in_subprocess(|| {
+        let (reader_a, writer_a) = rustix::pipe::pipe().unwrap();
+        let (reader_b, writer_b) = rustix::pipe::pipe().unwrap();
+
+        // set FD_CLOEXEC on only one of the descriptors
+        rustix::io::fcntl_setfd(&writer_a, rustix::io::FdFlags::CLOEXEC).unwrap();
+
+        let clone_res = unsafe { linux_api::sched::fork() }.unwrap();
+        let child_pid = match clone_res {
+            CloneResult::CallerIsChild => {
+                let path = CString::new(python_path.as_os_str().as_bytes()).unwrap();
+                let closed_fd = writer_a.as_raw_fd();
+                let open_fds = [
+                    reader_a.as_raw_fd(),
+                    reader_b.as_raw_fd(),
+                    writer_b.as_raw_fd(),
+                ];
+                let script = CString::new(format!(
+                    r#"
+import ctypes
+import errno
+libc = ctypes.CDLL("libc.so.6", use_errno=True)
+F_GETFD = 1
+rv = libc.fcntl({closed_fd}, F_GETFD)
+assert rv == -1, f"fcntl unexpectedly returned {{rv}} for the FD_CLOEXEC fd"
+assert ctypes.get_errno() == errno.EBADF, f"unexpected errno {{ctypes.get_errno()}}"
+for fd in {open_fds:?}:
+    rv = libc.fcntl(fd, F_GETFD)
+    assert rv == 0, f"fcntl unexpectedly returned {{rv}} for fd {{fd}}"
+                                "#
+                ))
+                .unwrap();
+                let args = vec![path.clone(), CString::new("-c").unwrap(), script];
+                unsafe { libc::execv(path.as_ptr(), execv_argvec(&args).as_ptr()) };
+                unreachable!("execv shouldn't have returned");
+            }
+            CloneResult::CallerIsParent(child_pid) => child_pid,
+        };
+
+        let child_pid = nix::unistd::Pid::from_raw(child_pid.as_raw_nonzero().get());
+        assert_eq!(
+            nix::sys::wait::waitpid(Some(child_pid), None).unwrap(),
+            nix::sys::wait::WaitStatus::Exited(child_pid, 0)
+        );
+    })
+}
+
 /// After exec, the process should get its own copy of its DescriptorTable,
 /// undoing the effect of CLONE_FILES.
 fn test_fork_exec_desc_table_unshared(python_path: &Path) -> anyhow::Result<()> {
@@ -1295,6 +1345,83 @@ fn test_clone_clear_sighand(set_clear_sighand: bool) -> anyhow::Result<()> {
     })
 }
 
+fn test_pidfd() -> anyhow::Result<()> {
+    let clone_res = unsafe { linux_api::sched::fork() }.unwrap();
+    let child_pid = match clone_res {
+        CloneResult::CallerIsChild => {
+            // Wait to be killed by the parent.
+            loop {
+                unsafe { libc::pause() };
+            }
+        }
+        CloneResult::CallerIsParent(pid) => pid,
+    };
+
+    let pidfd = unsafe { libc::syscall(libc::SYS_pidfd_open, child_pid.as_raw_nonzero().get(), 0) };
+    ensure_ord!(pidfd, >=, 0);
+    let pidfd = pidfd as c_int;
+
+    // The child is still running, so the pidfd isn't readable yet.
+    let mut pollfd = libc::pollfd {
+        fd: pidfd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    ensure_ord!(unsafe { libc::poll(&mut pollfd, 1, 0) }, ==, 0);
+
+    // A descriptor that isn't a pidfd can't be used to send a signal.
+    let rv = unsafe {
+        libc::syscall(
+            libc::SYS_pidfd_send_signal,
+            libc::STDOUT_FILENO,
+            libc::SIGTERM,
+            std::ptr::null::<siginfo_t>(),
+            0,
+        )
+    };
+    ensure_ord!(rv, ==, -1);
+    ensure_ord!(nix::errno::Errno::last(), ==, nix::errno::Errno::EBADF);
+
+    let rv = unsafe {
+        libc::syscall(
+            libc::SYS_pidfd_send_signal,
+            pidfd,
+            libc::SIGTERM,
+            std::ptr::null::<siginfo_t>(),
+            0,
+        )
+    };
+    ensure_ord!(rv, ==, 0);
+
+    // The pidfd becomes readable once the child exits.
+    ensure_ord!(unsafe { libc::poll(&mut pollfd, 1, 1000) }, ==, 1);
+    ensure_ord!(pollfd.revents & libc::POLLIN, ==, libc::POLLIN);
+
+    let child_pid = nix::unistd::Pid::from_raw(child_pid.as_raw_nonzero().get());
+    ensure_ord!(
+        nix::sys::wait::waitpid(Some(child_pid), None).unwrap(),
+        ==,
+        nix::sys::wait::WaitStatus::Signaled(child_pid, nix::sys::signal::Signal::SIGTERM, false)
+    );
+
+    // The child has been reaped, so it can no longer be signalled.
+    let rv = unsafe {
+        libc::syscall(
+            libc::SYS_pidfd_send_signal,
+            pidfd,
+            libc::SIGTERM,
+            std::ptr::null::<siginfo_t>(),
+            0,
+        )
+    };
+    ensure_ord!(rv, ==, -1);
+    ensure_ord!(nix::errno::Errno::last(), ==, nix::errno::Errno::ESRCH);
+
+    nix::unistd::close(pidfd).unwrap();
+
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     // FIXME: take as a command-line arg
     let python_path = Path::new("/usr/bin/python3");
@@ -1934,6 +2061,15 @@ fn main() -> Result<(), Box<dyn Error>> {
         ));
     }
 
+    tests.push(ShadowTest::new(
+        "test_fork_exec_cloexec_mixed",
+        {
+            let python_path = python_path.to_path_buf();
+            move || test_fork_exec_cloexec_mixed(&python_path)
+        },
+        all_envs.clone(),
+    ));
+
     tests.push(ShadowTest::new(
         "test_fork_exec_desc_table_unshared",
         {
@@ -1960,6 +2096,8 @@ fn main() -> Result<(), Box<dyn Error>> {
         ));
     }
 
+    tests.push(ShadowTest::new("test_pidfd", test_pidfd, all_envs.clone()));
+
     // It'd be good to test signal config across exec, but this is tricky since
     // python re-initializes it at startup. We might have to write specialized
     // programs in C to exec, and have them verify or otherwise output the
@@ -9,11 +9,14 @@
 #include <fcntl.h>
 #include <netdb.h>
 #include <pthread.h>
+#include <sched.h>
 #include <stddef.h>
+#include <stdint.h>
 #include <stdio.h>
 #include <stdlib.h>
 #include <string.h>
 #include <sys/auxv.h>
+#include <sys/socket.h>
 #include <sys/types.h>
 #include <syscall.h>
 #include <unistd.h>
@@ -161,6 +164,102 @@ static int _test_getPID() {
     return EXIT_SUCCESS;
 }
 
+typedef struct _SocketpairWorker SocketpairWorker;
+struct _SocketpairWorker {
+    int fd;
+    int id;
+    /* a running hash of the result of every send and recv, in the order they were made */
+    uint64_t hash;
+    long total_sent;
+    long total_received;
+};
+
+static void _socketpair_hash(SocketpairWorker* worker, long value) {
+    /* FNV-1a over the bytes of each result */
+    for (size_t i = 0; i < sizeof(value); i++) {
+        worker->hash ^= (uint8_t)(value >> (8 * i));
+        worker->hash *= 0x100000001b3ULL;
+    }
+}
+
+static void* _test_socketpairThread(void* arg) {
+    SocketpairWorker* worker = (SocketpairWorker*)arg;
+    unsigned char sendbuf[64];
+    unsigned char recvbuf[48];
+
+    for (int iter = 0; iter < 500; iter++) {
+        /* vary the message sizes so that reads and writes don't line up */
+        size_t len = (size_t)((iter * (worker->id + 3)) % (int)sizeof(sendbuf)) + 1;
+        memset(sendbuf, (iter + worker->id) & 0xFF, len);
+
+        ssize_t rv = send(worker->fd, sendbuf, len, MSG_DONTWAIT);
+        _socketpair_hash(worker, rv < 0 ? -errno : rv);
+        if (rv > 0) {
+            worker->total_sent += rv;
+        }
+
+        rv = recv(worker->fd, recvbuf, sizeof(recvbuf), MSG_DONTWAIT);
+        _socketpair_hash(worker, rv < 0 ? -errno : rv);
+        for (ssize_t i = 0; i < rv; i++) {
+            _socketpair_hash(worker, recvbuf[i]);
+        }
+        if (rv > 0) {
+            worker->total_received += rv;
+        }
+
+        /* give the other thread a chance to run in the middle of the exchange */
+        if (iter % 7 == 0) {
+            sched_yield();
+        }
+    }
+
+    return NULL;
+}
+
+static int _test_socketpair() {
+    int fds[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, fds) < 0) {
+        fprintf(stdout, "error %i in socketpair: %s\n", errno, strerror(errno));
+        return EXIT_FAILURE;
+    }
+
+    pthread_t threads[2];
+    SocketpairWorker workers[2];
+    memset(&workers[0], 0, 2 * sizeof(SocketpairWorker));
+
+    for (int i = 0; i < 2; i++) {
+        workers[i].fd = fds[i];
+        workers[i].id = i;
+        workers[i].hash = 0xcbf29ce484222325ULL;
+
+        int retval = pthread_create(&threads[i], NULL, _test_socketpairThread, &workers[i]);
+        if (retval != 0) {
+            fprintf(stdout, "error %i in pthread_create: %s\n", retval, strerror(retval));
+            return EXIT_FAILURE;
+        }
+    }
+
+    for (int i = 0; i < 2; i++) {
+        int retval = pthread_join(threads[i], NULL);
+        if (retval != 0) {
+            fprintf(stdout, "error %i in pthread_join: %s\n", retval, strerror(retval));
+            return EXIT_FAILURE;
+        }
+    }
+
+    close(fds[0]);
+    close(fds[1]);
+
+    /* the interleaving of the two threads' sends and recvs must be identical across runs */
+    for (int i = 0; i < 2; i++) {
+        fprintf(stdout, "socketpair thread %i: sent=%li received=%li hash=%016llx\n", i,
+                workers[i].total_sent, workers[i].total_received,
+                (unsigned long long)workers[i].hash);
+    }
+
+    return EXIT_SUCCESS;
+}
+
 static int _test_nameAddress() {
     /* first get our hostname */
     char hostname[1024];
@@ -245,6 +344,13 @@ int main(int argc, char* argv[]) {
     }
     fprintf(stdout, "_test_getPID() passed\n");
 
+    fprintf(stdout, "starting _test_socketpair()\n");
+    if (_test_socketpair() < 0) {
+        fprintf(stdout, "########## _test_socketpair() failed\n");
+        return EXIT_FAILURE;
+    }
+    fprintf(stdout, "_test_socketpair() passed\n");
+
     fprintf(stdout, "starting _test_nameAddress()\n");
     if (_test_nameAddress() < 0) {
         fprintf(stdout, "########## _test_nameAddress() failed\n");
@@ -47,6 +47,11 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
             test_fcntl,
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
+        test_utils::ShadowTest::new(
+            "test_lowest_fd_reuse",
+            test_lowest_fd_reuse,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
     ];
 
     for dup_fn in &[
@@ -105,6 +110,11 @@ fn test_dup2() -> Result<(), String> {
         check_system_call!(|| unsafe { libc::dup2(5000, target) }, &[libc::EBADF])?;
         check_system_call!(|| unsafe { libc::dup2(fd, -1) }, &[libc::EBADF])?;
 
+        // duplicating a fd onto itself does nothing, but only if it's a valid fd
+        let fd_dup = check_system_call!(|| unsafe { libc::dup2(fd, fd) }, &[])?;
+        assert_eq!(fd_dup, fd);
+        check_system_call!(|| unsafe { libc::dup2(target, target) }, &[libc::EBADF])?;
+
         Ok(())
     };
 
@@ -216,6 +226,52 @@ fn test_fcntl() -> Result<(), String> {
     Ok(())
 }
 
+/// Test that new descriptors are assigned the lowest available fd, and that freed fds are reused
+/// lowest-first.
+fn test_lowest_fd_reuse() -> Result<(), String> {
+    let new_socket = || unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+
+    // the new fds should be consecutive since there are no free fds below them
+    let mut fds: Vec<libc::c_int> = (0..6).map(|_| new_socket()).collect();
+    assert!(fds.iter().all(|&fd| fd >= 0));
+
+    let rv = (|| {
+        for pair in fds.windows(2) {
+            test_utils::result_assert_eq(pair[1], pair[0] + 1, "Fds were not consecutive")?;
+        }
+
+        // a new socket should reuse the freed fd
+        assert_eq!(unsafe { libc::close(fds[2]) }, 0);
+        fds[2] = new_socket();
+        test_utils::result_assert_eq(fds[2], fds[1] + 1, "Freed fd was not reused")?;
+
+        // a new pipe should reuse the freed fds lowest-first, regardless of the order they were
+        // freed in
+        assert_eq!(unsafe { libc::close(fds[4]) }, 0);
+        assert_eq!(unsafe { libc::close(fds[1]) }, 0);
+        let mut pipe_fds = [-1; 2];
+        assert_eq!(unsafe { libc::pipe(pipe_fds.as_mut_ptr()) }, 0);
+        fds[1] = pipe_fds[0];
+        fds[4] = pipe_fds[1];
+        test_utils::result_assert_eq(
+            pipe_fds,
+            [fds[0] + 1, fds[3] + 1],
+            "Freed fds were not reused lowest-first",
+        )?;
+
+        // with no free fds, the next socket should use the fd after the highest fd
+        let fd = new_socket();
+        fds.push(fd);
+        test_utils::result_assert_eq(fd, fds[5] + 1, "Unexpected fd")
+    })();
+
+    for fd in fds {
+        assert_eq!(unsafe { libc::close(fd) }, 0);
+    }
+
+    rv
+}
+
 fn test_dup_io(dup_fn: &DupFn) -> Result<(), String> {
     let (read_fd, write_fd) = nix::unistd::pipe().unwrap();
 
@@ -457,6 +457,172 @@ fn test_ctl_invalid_op() -> anyhow::Result<()> {
     })
 }
 
+fn test_exclusive_wakeup() -> anyhow::Result<()> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    let listen_fd = listener.into_raw_fd();
+    let epoll_fds = [
+        epoll::epoll_create()?,
+        epoll::epoll_create()?,
+        epoll::epoll_create()?,
+    ];
+
+    let mut fds = epoll_fds.to_vec();
+    fds.push(listen_fd);
+    test_utils::run_and_close_fds(&fds, || {
+        for epoll_fd in epoll_fds {
+            let mut event =
+                epoll::EpollEvent::new(EpollFlags::EPOLLIN | EpollFlags::EPOLLEXCLUSIVE, 0);
+            epoll::epoll_ctl(
+                epoll_fd,
+                epoll::EpollOp::EpollCtlAdd,
+                listen_fd,
+                Some(&mut event),
+            )?;
+        }
+
+        let timeout = Duration::from_millis(500);
+
+        // each epoll instance has a thread waiting on it
+        let waiters = epoll_fds
+            .map(|epoll_fd| std::thread::spawn(move || do_epoll_wait(epoll_fd, timeout, false)));
+
+        // wait for the threads to start waiting
+        std::thread::sleep(Duration::from_millis(100));
+
+        // a single incoming connection
+        let _client = std::net::TcpStream::connect(addr)?;
+
+        let results = waiters.map(|t| t.join().unwrap());
+
+        // only one of the waiters should have been woken up
+        let num_woken = results.iter().filter(|res| res.epoll_res == Ok(1)).count();
+        ensure_ord!(num_woken, ==, 1);
+
+        // the other waiters should have timed out
+        for res in results.iter().filter(|res| res.epoll_res != Ok(1)) {
+            ensure_ord!(res.epoll_res, ==, Ok(0));
+            ensure_ord!(res.duration, >=, timeout);
+        }
+
+        Ok(())
+    })
+}
+
+fn test_ctl_exclusive_invalid() -> anyhow::Result<()> {
+    let (read_fd, write_fd) = unistd::pipe()?;
+    let epoll_fd = epoll::epoll_create()?;
+    let epoll_fd_inner = epoll::epoll_create()?;
+
+    let fds = [epoll_fd, epoll_fd_inner, read_fd, write_fd];
+    test_utils::run_and_close_fds(&fds, || {
+        let mut event = epoll::EpollEvent::new(EpollFlags::EPOLLIN | EpollFlags::EPOLLEXCLUSIVE, 0);
+
+        // EPOLLEXCLUSIVE can't be used to monitor an epoll instance
+        let rv = epoll::epoll_ctl(
+            epoll_fd,
+            epoll::EpollOp::EpollCtlAdd,
+            epoll_fd_inner,
+            Some(&mut event),
+        );
+        assert_eq!(rv, Err(Errno::EINVAL));
+
+        // EPOLLEXCLUSIVE can't be used with EPOLLONESHOT
+        let mut oneshot_event = epoll::EpollEvent::new(
+            EpollFlags::EPOLLIN | EpollFlags::EPOLLEXCLUSIVE | EpollFlags::EPOLLONESHOT,
+            0,
+        );
+        let rv = epoll::epoll_ctl(
+            epoll_fd,
+            epoll::EpollOp::EpollCtlAdd,
+            read_fd,
+            Some(&mut oneshot_event),
+        );
+        assert_eq!(rv, Err(Errno::EINVAL));
+
+        let mut plain_event = epoll::EpollEvent::new(EpollFlags::EPOLLIN, 0);
+        epoll::epoll_ctl(
+            epoll_fd,
+            epoll::EpollOp::EpollCtlAdd,
+            read_fd,
+            Some(&mut plain_event),
+        )?;
+
+        // EPOLLEXCLUSIVE can't be used with EPOLL_CTL_MOD
+        let rv = epoll::epoll_ctl(
+            epoll_fd,
+            epoll::EpollOp::EpollCtlMod,
+            read_fd,
+            Some(&mut event),
+        );
+        assert_eq!(rv, Err(Errno::EINVAL));
+
+        epoll::epoll_ctl(epoll_fd, epoll::EpollOp::EpollCtlDel, read_fd, None)?;
+        epoll::epoll_ctl(
+            epoll_fd,
+            epoll::EpollOp::EpollCtlAdd,
+            read_fd,
+            Some(&mut event),
+        )?;
+
+        // an entry added with EPOLLEXCLUSIVE can't be modified
+        let rv = epoll::epoll_ctl(
+            epoll_fd,
+            epoll::EpollOp::EpollCtlMod,
+            read_fd,
+            Some(&mut plain_event),
+        );
+        assert_eq!(rv, Err(Errno::EINVAL));
+
+        Ok(())
+    })
+}
+
+fn test_event_order() -> anyhow::Result<()> {
+    let pipes = [unistd::pipe()?, unistd::pipe()?, unistd::pipe()?];
+    let epoll_fd = epoll::epoll_create()?;
+
+    let mut fds = vec![epoll_fd];
+    fds.extend(
+        pipes
+            .iter()
+            .flat_map(|&(read_fd, write_fd)| [read_fd, write_fd]),
+    );
+
+    test_utils::run_and_close_fds(&fds, || {
+        for (i, &(read_fd, _)) in pipes.iter().enumerate() {
+            let mut event = epoll::EpollEvent::new(EpollFlags::EPOLLIN, i as u64);
+            epoll::epoll_ctl(
+                epoll_fd,
+                epoll::EpollOp::EpollCtlAdd,
+                read_fd,
+                Some(&mut event),
+            )?;
+        }
+
+        // make the pipes readable in an order different from the order they were added
+        for i in [2, 0, 1] {
+            unistd::write(pipes[i].1, &[0])?;
+        }
+
+        let wait = |max_events: usize| -> anyhow::Result<Vec<u64>> {
+            let mut events = vec![epoll::EpollEvent::empty(); max_events];
+            let num = epoll::epoll_wait(epoll_fd, &mut events, 0)?;
+            Ok(events[..num].iter().map(|e| e.data()).collect())
+        };
+
+        // events are returned in the order that the files became ready
+        ensure_ord!(wait(3)?, ==, [2, 0, 1]);
+
+        // reported entries that are still ready are moved to the back of the order
+        ensure_ord!(wait(3)?, ==, [2, 0, 1]);
+        ensure_ord!(wait(2)?, ==, [2, 0]);
+        ensure_ord!(wait(3)?, ==, [1, 2, 0]);
+
+        Ok(())
+    })
+}
+
 fn main() -> anyhow::Result<()> {
     // should we restrict the tests we run?
     let filter_shadow_passing = std::env::args().any(|x| x == "--shadow-passing");
@@ -485,21 +651,26 @@ fn main() -> anyhow::Result<()> {
             all_envs.clone(),
         ),
         ShadowTest::new("test_ctl_invalid_op", test_ctl_invalid_op, all_envs.clone()),
+        ShadowTest::new(
+            "test_exclusive_wakeup",
+            test_exclusive_wakeup,
+            all_envs.clone(),
+        ),
+        ShadowTest::new(
+            "test_ctl_exclusive_invalid",
+            test_ctl_exclusive_invalid,
+            all_envs.clone(),
+        ),
+        ShadowTest::new("test_event_order", test_event_order, all_envs.clone()),
     ];
     for use_edge in [UseEPOLLET::Yes, UseEPOLLET::No] {
         for use_rdhup in [UseEPOLLRDHUP::Yes, UseEPOLLRDHUP::No] {
             for make_readable in [MakeReadable::Yes, MakeReadable::No] {
                 for fd_type in [FdType::Pipe, FdType::TcpStream] {
-                    let passing = match fd_type {
-                        FdType::TcpStream => all_envs.clone(),
-                        // pipes should get EPOLLHUP events but these aren't implemented.
-                        // https://github.com/shadow/shadow/issues/2181
-                        FdType::Pipe => set![TestEnvironment::Libc],
-                    };
                     tests.push(ShadowTest::new(
                         &format!("threads-eof-edge:{use_edge:?}-rdhup:{use_rdhup:?}-readable:{make_readable:?}-type:{fd_type:?}"),
                         move || test_threads_eof(use_edge, use_rdhup, make_readable, fd_type),
-                        passing,
+                        all_envs.clone(),
                     ));
                 }
             }
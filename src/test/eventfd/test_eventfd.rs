@@ -4,8 +4,11 @@
  */
 
 use std::os::unix::io::RawFd;
+use std::time::Duration;
 
 use nix::errno::Errno;
+use nix::poll::{PollFd, PollFlags, poll};
+use nix::sys::epoll::{self, EpollFlags};
 use nix::sys::eventfd::EfdFlags;
 use nix::sys::uio::{readv, writev};
 use nix::unistd::{close, read, write};
@@ -53,6 +56,26 @@ fn main() -> Result<(), String> {
             test_eventfd_read_write_semaphore_nonblock,
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
+        test_utils::ShadowTest::new(
+            "test_eventfd_epoll_cross_thread_level",
+            || test_eventfd_epoll_cross_thread(/* edge_triggered = */ false),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_eventfd_epoll_cross_thread_edge",
+            || test_eventfd_epoll_cross_thread(/* edge_triggered = */ true),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_eventfd_epoll_writable",
+            test_eventfd_epoll_writable,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_eventfd_poll",
+            test_eventfd_poll,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
     ];
 
     if filter_shadow_passing {
@@ -408,3 +431,150 @@ fn test_eventfd_read_write_semaphore_nonblock() -> Result<(), String> {
         Ok(())
     })
 }
+
+/// Return the events reported by a non-blocking `epoll_wait` on `epfd`.
+fn epoll_ready_events(epfd: RawFd) -> Result<Vec<epoll::EpollEvent>, String> {
+    let mut events = [epoll::EpollEvent::empty(); 10];
+    let num = epoll::epoll_wait(epfd, &mut events, 0).map_err(|e| e.to_string())?;
+    Ok(events[..num].to_vec())
+}
+
+fn test_eventfd_epoll_cross_thread(edge_triggered: bool) -> Result<(), String> {
+    let efd: RawFd = call_eventfd(0, EfdFlags::EFD_NONBLOCK)?;
+    let epfd = epoll::epoll_create().map_err(|e| e.to_string())?;
+
+    test_utils::run_and_close_fds(&[efd, epfd], || {
+        let mut flags = EpollFlags::EPOLLIN;
+        if edge_triggered {
+            flags.insert(EpollFlags::EPOLLET);
+        }
+        let mut event = epoll::EpollEvent::new(flags, efd as u64);
+        epoll::epoll_ctl(epfd, epoll::EpollOp::EpollCtlAdd, efd, Some(&mut event))
+            .map_err(|e| e.to_string())?;
+
+        // the counter is 0, so the eventfd isn't readable
+        test_utils::result_assert(
+            epoll_ready_events(epfd)?.is_empty(),
+            "Eventfd with a counter of 0 was reported as readable",
+        )?;
+
+        // wake up the epoll waiter from another thread
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            check_write_success(efd, 1)
+        });
+
+        let mut events = [epoll::EpollEvent::empty(); 10];
+        let num = epoll::epoll_wait(epfd, &mut events, 5000).map_err(|e| e.to_string())?;
+        writer.join().unwrap()?;
+
+        test_utils::result_assert_eq(num, 1, "Unexpected number of epoll events")?;
+        test_utils::result_assert_eq(events[0].events(), EpollFlags::EPOLLIN, "Unexpected event")?;
+        test_utils::result_assert_eq(events[0].data(), efd as u64, "Unexpected event data")?;
+
+        if edge_triggered {
+            // the event was already reported and the counter hasn't changed
+            test_utils::result_assert(
+                epoll_ready_events(epfd)?.is_empty(),
+                "Edge-triggered event was reported twice",
+            )?;
+
+            // another write should report the eventfd again, even though it was already readable
+            check_write_success(efd, 2)?;
+            test_utils::result_assert_eq(
+                epoll_ready_events(epfd)?.len(),
+                1,
+                "Edge-triggered event wasn't reported after a second write",
+            )?;
+
+            check_read_success(efd, 3)?;
+        } else {
+            // the eventfd is still readable
+            test_utils::result_assert_eq(
+                epoll_ready_events(epfd)?.len(),
+                1,
+                "Level-triggered event wasn't reported again",
+            )?;
+
+            check_read_success(efd, 1)?;
+        }
+
+        // draining the counter should clear the readiness
+        test_utils::result_assert(
+            epoll_ready_events(epfd)?.is_empty(),
+            "Drained eventfd was reported as readable",
+        )?;
+
+        Ok(())
+    })
+}
+
+fn test_eventfd_epoll_writable() -> Result<(), String> {
+    let efd: RawFd = call_eventfd(0, EfdFlags::EFD_NONBLOCK)?;
+    let epfd = epoll::epoll_create().map_err(|e| e.to_string())?;
+
+    test_utils::run_and_close_fds(&[efd, epfd], || {
+        let mut event = epoll::EpollEvent::new(EpollFlags::EPOLLOUT, efd as u64);
+        epoll::epoll_ctl(epfd, epoll::EpollOp::EpollCtlAdd, efd, Some(&mut event))
+            .map_err(|e| e.to_string())?;
+
+        let events = epoll_ready_events(epfd)?;
+        test_utils::result_assert_eq(events.len(), 1, "Empty eventfd wasn't writable")?;
+        test_utils::result_assert_eq(events[0].events(), EpollFlags::EPOLLOUT, "Unexpected event")?;
+
+        // a write of 1 would overflow the counter, so the eventfd isn't writable
+        check_write_success(efd, u64::MAX - 1)?;
+        test_utils::result_assert(
+            epoll_ready_events(epfd)?.is_empty(),
+            "Full eventfd was reported as writable",
+        )?;
+
+        check_read_success(efd, u64::MAX - 1)?;
+        test_utils::result_assert_eq(
+            epoll_ready_events(epfd)?.len(),
+            1,
+            "Drained eventfd wasn't writable",
+        )?;
+
+        Ok(())
+    })
+}
+
+fn test_eventfd_poll() -> Result<(), String> {
+    let efd: RawFd = call_eventfd(0, EfdFlags::EFD_NONBLOCK)?;
+
+    test_utils::run_and_close_fds(&[efd], || {
+        let poll_eventfd = || -> Result<PollFlags, String> {
+            let mut fds = [PollFd::new(efd, PollFlags::POLLIN | PollFlags::POLLOUT)];
+            poll(&mut fds, 0).map_err(|e| e.to_string())?;
+            Ok(fds[0].revents().unwrap())
+        };
+
+        test_utils::result_assert_eq(poll_eventfd()?, PollFlags::POLLOUT, "Unexpected events")?;
+
+        check_write_success(efd, 1)?;
+        test_utils::result_assert_eq(
+            poll_eventfd()?,
+            PollFlags::POLLIN | PollFlags::POLLOUT,
+            "Unexpected events after write",
+        )?;
+
+        check_read_success(efd, 1)?;
+        test_utils::result_assert_eq(
+            poll_eventfd()?,
+            PollFlags::POLLOUT,
+            "Unexpected events after read",
+        )?;
+
+        check_write_success(efd, u64::MAX - 1)?;
+        test_utils::result_assert_eq(
+            poll_eventfd()?,
+            PollFlags::POLLIN,
+            "Unexpected events for a full eventfd",
+        )?;
+
+        check_read_success(efd, u64::MAX - 1)?;
+
+        Ok(())
+    })
+}
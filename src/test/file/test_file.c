@@ -10,10 +10,13 @@
 #include <fcntl.h>
 #include <glib.h>
 #include <libgen.h>
+#include <limits.h>
 #include <stdio.h>
 #include <stdlib.h>
 #include <string.h>
+#include <sys/eventfd.h>
 #include <sys/ioctl.h>
+#include <sys/socket.h>
 #include <sys/stat.h>
 #include <sys/syscall.h>
 #include <sys/types.h>
@@ -107,6 +110,135 @@ static void _test_openat() {
     close(fd); // not testing close yet so don't assert here
 }
 
+static void _test_openat_dirfd() {
+    g_auto(AutoDeleteFile) dir = _create_auto_dir();
+    const char contents[] = "hello world";
+    char rbuf[sizeof(contents)] = {0};
+    int fd;
+
+    // create a file relative to the directory fd
+    assert_nonneg_errno(fd = openat(dir.fd, "file", O_RDWR | O_CREAT | O_EXCL | O_CLOEXEC, 0600));
+    g_assert_cmpint(fcntl(fd, F_GETFD), ==, FD_CLOEXEC);
+
+    struct stat statbuf = {0};
+    assert_nonneg_errno(fstat(fd, &statbuf));
+    g_assert_true(S_ISREG(statbuf.st_mode));
+    g_assert_cmpint(statbuf.st_mode & 0777, ==, 0600);
+
+    assert_true_errno(write(fd, contents, 5) == 5);
+    assert_nonneg_errno(close(fd));
+
+    // the file should be visible relative to the working directory
+    char path[AUTOFILE_NAME_MAXLEN + 8] = {0};
+    snprintf(path, sizeof(path), "%s/file", dir.name);
+    assert_nonneg_errno(fd = openat(AT_FDCWD, path, O_WRONLY | O_APPEND | O_NONBLOCK));
+    g_assert_cmpint(fcntl(fd, F_GETFD), ==, 0);
+    g_assert_cmpint(fcntl(fd, F_GETFL) & (O_ACCMODE | O_APPEND | O_NONBLOCK), ==,
+                    O_WRONLY | O_APPEND | O_NONBLOCK);
+
+    // appends to the end of the file, even after seeking to the start
+    assert_nonneg_errno(lseek(fd, 0, SEEK_SET));
+    assert_true_errno(write(fd, contents + 5, sizeof(contents) - 5) == sizeof(contents) - 5);
+    assert_nonneg_errno(close(fd));
+
+    assert_nonneg_errno(fd = openat(dir.fd, "file", O_RDONLY));
+    assert_true_errno(read(fd, rbuf, sizeof(rbuf)) == sizeof(contents));
+    g_assert_cmpstr(rbuf, ==, contents);
+
+    // files opened read-only can't be written
+    g_assert_cmpint(write(fd, contents, 1), ==, -1);
+    assert_errno_is(EBADF);
+    assert_nonneg_errno(close(fd));
+
+    // O_EXCL fails for the existing file
+    g_assert_cmpint(openat(dir.fd, "file", O_RDWR | O_CREAT | O_EXCL, 0600), ==, -1);
+    assert_errno_is(EEXIST);
+
+    assert_nonneg_errno(unlinkat(dir.fd, "file", 0));
+}
+
+static void _test_umask() {
+    g_auto(AutoDeleteFile) dir = _create_auto_dir();
+    struct stat statbuf = {0};
+    int fd;
+
+    mode_t old_mask = umask(022);
+
+    // the umask bits are removed from the mode of new files
+    assert_nonneg_errno(fd = openat(dir.fd, "file", O_RDWR | O_CREAT | O_EXCL, 0777));
+    assert_nonneg_errno(fstat(fd, &statbuf));
+    g_assert_cmpint(statbuf.st_mode & 0777, ==, 0755);
+    assert_nonneg_errno(close(fd));
+    assert_nonneg_errno(unlinkat(dir.fd, "file", 0));
+
+    // umask returns the previous mask
+    g_assert_cmpint(umask(077), ==, 022);
+
+    assert_nonneg_errno(fd = openat(dir.fd, "file", O_RDWR | O_CREAT | O_EXCL, 0777));
+    assert_nonneg_errno(fstat(fd, &statbuf));
+    g_assert_cmpint(statbuf.st_mode & 0777, ==, 0700);
+    assert_nonneg_errno(close(fd));
+    assert_nonneg_errno(unlinkat(dir.fd, "file", 0));
+
+    // the umask also applies to new directories
+    assert_nonneg_errno(mkdirat(dir.fd, "subdir", 0777));
+    assert_nonneg_errno(fstatat(dir.fd, "subdir", &statbuf, 0));
+    g_assert_cmpint(statbuf.st_mode & 0777, ==, 0700);
+    assert_nonneg_errno(unlinkat(dir.fd, "subdir", AT_REMOVEDIR));
+
+    // with an empty umask, new files and directories get exactly the requested mode
+    g_assert_cmpint(umask(0), ==, 077);
+
+    assert_nonneg_errno(fd = openat(dir.fd, "file", O_RDWR | O_CREAT | O_EXCL, 0777));
+    assert_nonneg_errno(fstat(fd, &statbuf));
+    g_assert_cmpint(statbuf.st_mode & 0777, ==, 0777);
+    assert_nonneg_errno(close(fd));
+    assert_nonneg_errno(unlinkat(dir.fd, "file", 0));
+
+    assert_nonneg_errno(mkdirat(dir.fd, "subdir", 0777));
+    assert_nonneg_errno(fstatat(dir.fd, "subdir", &statbuf, 0));
+    g_assert_cmpint(statbuf.st_mode & 0777, ==, 0777);
+    assert_nonneg_errno(unlinkat(dir.fd, "subdir", AT_REMOVEDIR));
+
+    g_assert_cmpint(umask(old_mask), ==, 0);
+}
+
+static void _test_openat_bad_dirfd() {
+    g_auto(AutoDeleteFile) adf = _create_auto_file();
+    char abspath[PATH_MAX] = {0};
+    int fd;
+
+    // openat(2): "EBADF: pathname is relative but dirfd is neither AT_FDCWD nor a valid file
+    // descriptor."
+    g_assert_cmpint(openat(-5, adf.name, O_RDONLY), ==, -1);
+    assert_errno_is(EBADF);
+    g_assert_cmpint(openat(1000, adf.name, O_RDONLY), ==, -1);
+    assert_errno_is(EBADF);
+
+    // openat(2): "If pathname is absolute, then dirfd is ignored."
+    assert_nonnull_errno(realpath(adf.name, abspath));
+    assert_nonneg_errno(fd = openat(1000, abspath, O_RDONLY));
+    assert_nonneg_errno(close(fd));
+
+    // openat(2): "ENOTDIR: pathname is a relative pathname and dirfd is a file descriptor referring
+    // to a file other than a directory."
+    g_assert_cmpint(openat(adf.fd, "file", O_RDONLY), ==, -1);
+    assert_errno_is(ENOTDIR);
+
+    int pipefds[2];
+    assert_nonneg_errno(pipe(pipefds));
+    g_assert_cmpint(openat(pipefds[0], "file", O_RDONLY), ==, -1);
+    assert_errno_is(ENOTDIR);
+    assert_nonneg_errno(close(pipefds[0]));
+    assert_nonneg_errno(close(pipefds[1]));
+
+    int efd;
+    assert_nonneg_errno(efd = eventfd(0, 0));
+    g_assert_cmpint(openat(efd, "file", O_RDONLY), ==, -1);
+    assert_errno_is(ENOTDIR);
+    assert_nonneg_errno(close(efd));
+}
+
 static void _test_close() {
     g_auto(AutoDeleteFile) adf = _create_auto_file();
     int fd;
@@ -541,6 +673,228 @@ static void _test_lseek_pipe() {
     assert_nonneg_errno(close(pipes[1]));
 }
 
+static void _test_fsync() {
+    g_auto(AutoDeleteFile) adf = _create_auto_file();
+    const char wbuf[] = "test file fsync";
+    _set_contents(&adf, wbuf, sizeof(wbuf));
+
+    assert_nonneg_errno(fsync(adf.fd));
+    assert_nonneg_errno(fdatasync(adf.fd));
+}
+
+static void _test_fsync_nonfile() {
+    int fds[6] = {-1, -1, -1, -1, -1, -1};
+
+    assert_nonneg_errno(pipe(&fds[0]));
+    assert_nonneg_errno(socketpair(AF_UNIX, SOCK_STREAM, 0, &fds[2]));
+    assert_nonneg_errno(fds[4] = socket(AF_INET, SOCK_STREAM, 0));
+    assert_nonneg_errno(fds[5] = eventfd(0, 0));
+
+    // linux returns EINVAL for files that don't support syncing
+    for (int i = 0; i < 6; i++) {
+        g_assert_cmpint(fsync(fds[i]), ==, -1);
+        assert_errno_is(EINVAL);
+        g_assert_cmpint(fdatasync(fds[i]), ==, -1);
+        assert_errno_is(EINVAL);
+    }
+
+    for (int i = 0; i < 6; i++) {
+        assert_nonneg_errno(close(fds[i]));
+    }
+}
+
+static void _test_readahead() {
+    g_auto(AutoDeleteFile) adf = _create_auto_file();
+    const char wbuf[] = "test file readahead";
+    _set_contents(&adf, wbuf, sizeof(wbuf));
+
+    assert_nonneg_errno(readahead(adf.fd, 0, sizeof(wbuf)));
+    // reading ahead past the end of the file is fine
+    assert_nonneg_errno(readahead(adf.fd, 4096, 4096));
+}
+
+static void _test_readahead_pipe() {
+    int pipes[2] = {-1, -1};
+
+    assert_nonneg_errno(pipe(pipes));
+
+    g_assert_cmpint(readahead(pipes[0], 0, 10), ==, -1);
+    assert_errno_is(EINVAL);
+
+    assert_nonneg_errno(close(pipes[0]));
+    assert_nonneg_errno(close(pipes[1]));
+}
+
+static void _test_fallocate() {
+    g_auto(AutoDeleteFile) adf = _create_auto_file();
+    const char wbuf[] = "test file fallocate";
+    _set_contents(&adf, wbuf, sizeof(wbuf));
+
+    struct stat st = {0};
+
+    // a range within the file doesn't change the size
+    assert_nonneg_errno(fallocate(adf.fd, 0, 0, 4));
+    assert_nonneg_errno(fstat(adf.fd, &st));
+    g_assert_cmpint(st.st_size, ==, sizeof(wbuf));
+
+    // a range past the end of the file extends it
+    assert_nonneg_errno(fallocate(adf.fd, 0, 100, 100));
+    assert_nonneg_errno(fstat(adf.fd, &st));
+    g_assert_cmpint(st.st_size, ==, 200);
+
+    // unless we ask to keep the size
+    assert_nonneg_errno(fallocate(adf.fd, FALLOC_FL_KEEP_SIZE, 0, 1000));
+    assert_nonneg_errno(fstat(adf.fd, &st));
+    g_assert_cmpint(st.st_size, ==, 200);
+
+    // posix_fallocate returns the error rather than setting errno
+    g_assert_cmpint(posix_fallocate(adf.fd, 0, 300), ==, 0);
+    assert_nonneg_errno(fstat(adf.fd, &st));
+    g_assert_cmpint(st.st_size, ==, 300);
+
+    // the original contents are unchanged, and the new part of the file is zero-filled
+    char rbuf[300] = {0};
+    char zeros[sizeof(rbuf) - sizeof(wbuf)] = {0};
+    g_assert_cmpint(pread(adf.fd, rbuf, sizeof(rbuf), 0), ==, sizeof(rbuf));
+    g_assert_cmpmem(rbuf, sizeof(wbuf), wbuf, sizeof(wbuf));
+    g_assert_cmpmem(&rbuf[sizeof(wbuf)], sizeof(zeros), zeros, sizeof(zeros));
+
+    g_assert_cmpint(fallocate(adf.fd, 0, 0, 0), ==, -1);
+    assert_errno_is(EINVAL);
+    g_assert_cmpint(fallocate(adf.fd, 0, -1, 10), ==, -1);
+    assert_errno_is(EINVAL);
+}
+
+static void _test_fallocate_punch_hole() {
+    g_auto(AutoDeleteFile) adf = _create_auto_file();
+    char wbuf[8192];
+    memset(wbuf, 'a', sizeof(wbuf));
+    _set_contents(&adf, wbuf, sizeof(wbuf));
+
+    // punching a hole requires keeping the size
+    g_assert_cmpint(fallocate(adf.fd, FALLOC_FL_PUNCH_HOLE, 1000, 2000), ==, -1);
+    assert_errno_is(EOPNOTSUPP);
+
+    // the hole extends past the end of the file
+    assert_nonneg_errno(
+        fallocate(adf.fd, FALLOC_FL_PUNCH_HOLE | FALLOC_FL_KEEP_SIZE, 1000, 10000));
+    assert_nonneg_errno(
+        fallocate(adf.fd, FALLOC_FL_PUNCH_HOLE | FALLOC_FL_KEEP_SIZE, 10, 20));
+
+    struct stat st = {0};
+    assert_nonneg_errno(fstat(adf.fd, &st));
+    g_assert_cmpint(st.st_size, ==, sizeof(wbuf));
+
+    char expected[sizeof(wbuf)];
+    memset(expected, 'a', sizeof(expected));
+    memset(&expected[10], 0, 20);
+    memset(&expected[1000], 0, sizeof(expected) - 1000);
+
+    char rbuf[sizeof(wbuf)] = {0};
+    g_assert_cmpint(pread(adf.fd, rbuf, sizeof(rbuf), 0), ==, sizeof(rbuf));
+    g_assert_cmpmem(rbuf, sizeof(rbuf), expected, sizeof(expected));
+}
+
+static void _test_copy_file_range_offsets() {
+    g_auto(AutoDeleteFile) src = _create_auto_file();
+    g_auto(AutoDeleteFile) dst = _create_auto_file();
+    const char wbuf[] = "0123456789";
+    _set_contents(&src, wbuf, strlen(wbuf));
+
+    loff_t offsetIn = 2;
+    loff_t offsetOut = 1;
+    g_assert_cmpint(copy_file_range(src.fd, &offsetIn, dst.fd, &offsetOut, 5, 0), ==, 5);
+
+    // the given offsets are advanced
+    g_assert_cmpint(offsetIn, ==, 7);
+    g_assert_cmpint(offsetOut, ==, 6);
+
+    // but the file offsets are unchanged
+    g_assert_cmpint(lseek(src.fd, 0, SEEK_CUR), ==, strlen(wbuf));
+    g_assert_cmpint(lseek(dst.fd, 0, SEEK_CUR), ==, 0);
+
+    // the skipped part of the destination file is zero-filled
+    char rbuf[6] = {0};
+    g_assert_cmpint(pread(dst.fd, rbuf, sizeof(rbuf), 0), ==, sizeof(rbuf));
+    g_assert_cmpmem(rbuf, sizeof(rbuf), "\0" "23456", 6);
+
+    // only the remaining bytes are copied at the end of the file
+    g_assert_cmpint(copy_file_range(src.fd, &offsetIn, dst.fd, &offsetOut, 100, 0), ==, 3);
+    g_assert_cmpint(offsetIn, ==, 10);
+    g_assert_cmpint(offsetOut, ==, 9);
+    g_assert_cmpint(copy_file_range(src.fd, &offsetIn, dst.fd, &offsetOut, 100, 0), ==, 0);
+    g_assert_cmpint(offsetIn, ==, 10);
+    g_assert_cmpint(offsetOut, ==, 9);
+}
+
+static void _test_copy_file_range_implicit_offsets() {
+    g_auto(AutoDeleteFile) src = _create_auto_file();
+    g_auto(AutoDeleteFile) dst = _create_auto_file();
+    const char wbuf[] = "0123456789";
+    _set_contents(&src, wbuf, strlen(wbuf));
+
+    g_assert_cmpint(lseek(src.fd, 3, SEEK_SET), ==, 3);
+    g_assert_cmpint(copy_file_range(src.fd, NULL, dst.fd, NULL, 4, 0), ==, 4);
+
+    // the file offsets are advanced
+    g_assert_cmpint(lseek(src.fd, 0, SEEK_CUR), ==, 7);
+    g_assert_cmpint(lseek(dst.fd, 0, SEEK_CUR), ==, 4);
+
+    // a mix of given and implicit offsets
+    loff_t offsetIn = 0;
+    g_assert_cmpint(copy_file_range(src.fd, &offsetIn, dst.fd, NULL, 2, 0), ==, 2);
+    g_assert_cmpint(offsetIn, ==, 2);
+    g_assert_cmpint(lseek(src.fd, 0, SEEK_CUR), ==, 7);
+    g_assert_cmpint(lseek(dst.fd, 0, SEEK_CUR), ==, 6);
+
+    char rbuf[6] = {0};
+    g_assert_cmpint(pread(dst.fd, rbuf, sizeof(rbuf), 0), ==, sizeof(rbuf));
+    g_assert_cmpmem(rbuf, sizeof(rbuf), "345601", 6);
+}
+
+static void _test_copy_file_range_same_file() {
+    g_auto(AutoDeleteFile) adf = _create_auto_file();
+    const char wbuf[] = "0123456789";
+    _set_contents(&adf, wbuf, strlen(wbuf));
+
+    // overlapping ranges within the same file aren't allowed
+    loff_t offsetIn = 0;
+    loff_t offsetOut = 2;
+    g_assert_cmpint(copy_file_range(adf.fd, &offsetIn, adf.fd, &offsetOut, 5, 0), ==, -1);
+    assert_errno_is(EINVAL);
+
+    // but non-overlapping ranges are
+    offsetOut = 5;
+    g_assert_cmpint(copy_file_range(adf.fd, &offsetIn, adf.fd, &offsetOut, 5, 0), ==, 5);
+
+    char rbuf[10] = {0};
+    g_assert_cmpint(pread(adf.fd, rbuf, sizeof(rbuf), 0), ==, sizeof(rbuf));
+    g_assert_cmpmem(rbuf, sizeof(rbuf), "0123401234", 10);
+}
+
+static void _test_copy_file_range_invalid() {
+    g_auto(AutoDeleteFile) adf = _create_auto_file();
+    int pipes[2] = {-1, -1};
+    assert_nonneg_errno(pipe(pipes));
+
+    // flags must be 0
+    g_assert_cmpint(copy_file_range(adf.fd, NULL, adf.fd, NULL, 5, 1), ==, -1);
+    assert_errno_is(EINVAL);
+
+    // not a regular file
+    g_assert_cmpint(copy_file_range(pipes[0], NULL, adf.fd, NULL, 5, 0), ==, -1);
+    assert_errno_is(EINVAL);
+    g_assert_cmpint(copy_file_range(adf.fd, NULL, pipes[1], NULL, 5, 0), ==, -1);
+    assert_errno_is(EINVAL);
+
+    // not a valid descriptor
+    g_assert_cmpint(copy_file_range(-1, NULL, adf.fd, NULL, 5, 0), ==, -1);
+    assert_errno_is(EBADF);
+
+    assert_nonneg_errno(close(pipes[0]));
+    assert_nonneg_errno(close(pipes[1]));
+}
+
 static void _test_fopen() {
     g_auto(AutoDeleteFile) adf = _create_auto_file();
     FILE* file;
@@ -1066,6 +1420,9 @@ int main(int argc, char* argv[]) {
     g_test_add_func("/file/open", _test_open);
     g_test_add_func("/file/open_returns_lowest", _test_open_returns_lowest);
     g_test_add_func("/file/openat", _test_openat);
+    g_test_add_func("/file/openat_dirfd", _test_openat_dirfd);
+    g_test_add_func("/file/openat_bad_dirfd", _test_openat_bad_dirfd);
+    g_test_add_func("/file/umask", _test_umask);
     g_test_add_func("/file/close", _test_close);
     g_test_add_func("/file/close_nonexistent", _test_close_nonexistent);
     g_test_add_func("/file/write", _test_write);
@@ -1080,6 +1437,17 @@ int main(int argc, char* argv[]) {
     g_test_add_func("/file/preadv2", _test_preadv2);
     g_test_add_func("/file/lseek", _test_lseek);
     g_test_add_func("/file/lseek_pipe", _test_lseek_pipe);
+    g_test_add_func("/file/fsync", _test_fsync);
+    g_test_add_func("/file/fsync_nonfile", _test_fsync_nonfile);
+    g_test_add_func("/file/readahead", _test_readahead);
+    g_test_add_func("/file/readahead_pipe", _test_readahead_pipe);
+    g_test_add_func("/file/fallocate", _test_fallocate);
+    g_test_add_func("/file/fallocate_punch_hole", _test_fallocate_punch_hole);
+    g_test_add_func("/file/copy_file_range_offsets", _test_copy_file_range_offsets);
+    g_test_add_func(
+        "/file/copy_file_range_implicit_offsets", _test_copy_file_range_implicit_offsets);
+    g_test_add_func("/file/copy_file_range_same_file", _test_copy_file_range_same_file);
+    g_test_add_func("/file/copy_file_range_invalid", _test_copy_file_range_invalid);
     g_test_add_func("/file/fopen", _test_fopen);
     g_test_add_func("/file/fclose", _test_fclose);
     g_test_add_func("/file/fileno", _test_fileno);
@@ -0,0 +1,444 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+use std::os::fd::AsRawFd;
+use std::os::unix::ffi::OsStrExt;
+
+use test_utils::TestEnvironment as TestEnv;
+use test_utils::set;
+
+fn main() -> Result<(), String> {
+    // should we restrict the tests we run?
+    let filter_shadow_passing = std::env::args().any(|x| x == "--shadow-passing");
+    let filter_libc_passing = std::env::args().any(|x| x == "--libc-passing");
+    // should we summarize the results rather than exit on a failed test
+    let summarize = std::env::args().any(|x| x == "--summarize");
+
+    let mut tests = get_tests();
+    if filter_shadow_passing {
+        tests.retain(|x| x.passing(TestEnv::Shadow));
+    }
+    if filter_libc_passing {
+        tests.retain(|x| x.passing(TestEnv::Libc));
+    }
+
+    test_utils::run_tests(&tests, summarize)?;
+
+    println!("Success.");
+    Ok(())
+}
+
+fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
+    let tests: Vec<test_utils::ShadowTest<_, _>> = vec![
+        test_utils::ShadowTest::new(
+            "test_getlk_unlocked",
+            test_getlk_unlocked,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_setlk_conflict",
+            test_setlk_conflict,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_unlock_splits_lock",
+            test_unlock_splits_lock,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_setlkw_blocks",
+            test_setlkw_blocks,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_setlkw_deadlock",
+            test_setlkw_deadlock,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_released_on_close",
+            test_released_on_close,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_released_on_exit",
+            test_released_on_exit,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_access_mode",
+            test_access_mode,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_ofd_locks",
+            test_ofd_locks,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+    ];
+
+    tests
+}
+
+fn new_flock(l_type: libc::c_int, start: libc::off_t, len: libc::off_t) -> libc::flock {
+    let mut fl: libc::flock = unsafe { std::mem::zeroed() };
+    fl.l_type = l_type as libc::c_short;
+    fl.l_whence = libc::SEEK_SET as libc::c_short;
+    fl.l_start = start;
+    fl.l_len = len;
+    fl
+}
+
+/// Run a lock command, returning the errno on failure.
+fn lock_cmd(fd: libc::c_int, cmd: libc::c_int, fl: &mut libc::flock) -> Result<(), libc::c_int> {
+    let rv = unsafe { libc::fcntl(fd, cmd, std::ptr::from_mut(fl)) };
+    if rv == 0 {
+        Ok(())
+    } else {
+        Err(test_utils::get_errno())
+    }
+}
+
+fn set_lock(
+    fd: libc::c_int,
+    l_type: libc::c_int,
+    start: libc::off_t,
+    len: libc::off_t,
+) -> Result<(), libc::c_int> {
+    lock_cmd(fd, libc::F_SETLK, &mut new_flock(l_type, start, len))
+}
+
+/// Returns the lock that conflicts with a write lock over the given range, if any.
+fn get_conflict(fd: libc::c_int, start: libc::off_t, len: libc::off_t) -> Option<libc::flock> {
+    let mut fl = new_flock(libc::F_WRLCK, start, len);
+    lock_cmd(fd, libc::F_GETLK, &mut fl).unwrap();
+    (fl.l_type != libc::F_UNLCK as libc::c_short).then_some(fl)
+}
+
+/// Run `f` in a child process, returning the child's pid.
+fn spawn_child(f: impl FnOnce() -> Result<(), String>) -> libc::pid_t {
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+
+    if pid == 0 {
+        let code = match f() {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("Error in child process: {e}");
+                1
+            }
+        };
+        unsafe { libc::_exit(code) };
+    }
+
+    pid
+}
+
+/// Wait for a child started with [`spawn_child`] to exit successfully.
+fn wait_child(pid: libc::pid_t) -> Result<(), String> {
+    let mut status = 0;
+    test_utils::result_assert_eq(
+        unsafe { libc::waitpid(pid, &mut status, 0) },
+        pid,
+        "waitpid failed",
+    )?;
+    test_utils::result_assert(
+        libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0,
+        &format!("Child exited with status {status}"),
+    )
+}
+
+fn open_file(path: &std::path::Path, flags: libc::c_int) -> libc::c_int {
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).unwrap();
+    let fd = unsafe { libc::open(path.as_ptr(), flags) };
+    assert!(fd >= 0);
+    fd
+}
+
+fn test_getlk_unlocked() -> Result<(), String> {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let fd = file.as_file().as_raw_fd();
+
+    test_utils::result_assert(get_conflict(fd, 0, 0).is_none(), "File should be unlocked")?;
+
+    // a process' own locks never conflict with its other locks
+    set_lock(fd, libc::F_WRLCK, 0, 10).unwrap();
+    test_utils::result_assert(
+        get_conflict(fd, 0, 0).is_none(),
+        "Own lock shouldn't conflict",
+    )?;
+
+    // F_GETLK requires a read or write lock type
+    test_utils::result_assert_eq(
+        lock_cmd(fd, libc::F_GETLK, &mut new_flock(libc::F_UNLCK, 0, 0)),
+        Err(libc::EINVAL),
+        "Expected EINVAL for F_UNLCK",
+    )?;
+
+    Ok(())
+}
+
+fn test_setlk_conflict() -> Result<(), String> {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let fd = file.as_file().as_raw_fd();
+    let parent_pid = unsafe { libc::getpid() };
+
+    set_lock(fd, libc::F_WRLCK, 0, 10).unwrap();
+
+    let child = spawn_child(|| {
+        test_utils::result_assert_eq(
+            set_lock(fd, libc::F_WRLCK, 5, 10),
+            Err(libc::EAGAIN),
+            "Expected the write lock to conflict",
+        )?;
+        test_utils::result_assert_eq(
+            set_lock(fd, libc::F_RDLCK, 9, 1),
+            Err(libc::EAGAIN),
+            "Expected the read lock to conflict",
+        )?;
+
+        let conflict = get_conflict(fd, 5, 10).ok_or("Expected a conflicting lock")?;
+        test_utils::result_assert_eq(conflict.l_type, libc::F_WRLCK as libc::c_short, "l_type")?;
+        test_utils::result_assert_eq(
+            conflict.l_whence,
+            libc::SEEK_SET as libc::c_short,
+            "l_whence",
+        )?;
+        test_utils::result_assert_eq(conflict.l_start, 0, "l_start")?;
+        test_utils::result_assert_eq(conflict.l_len, 10, "l_len")?;
+        test_utils::result_assert_eq(conflict.l_pid, parent_pid, "l_pid")?;
+
+        // a lock on a range that doesn't overlap is fine
+        test_utils::result_assert_eq(
+            set_lock(fd, libc::F_WRLCK, 10, 10),
+            Ok(()),
+            "Expected the lock to succeed",
+        )?;
+
+        Ok(())
+    });
+
+    wait_child(child)
+}
+
+fn test_unlock_splits_lock() -> Result<(), String> {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let fd = file.as_file().as_raw_fd();
+
+    set_lock(fd, libc::F_WRLCK, 0, 30).unwrap();
+    set_lock(fd, libc::F_UNLCK, 10, 10).unwrap();
+
+    let child = spawn_child(|| {
+        test_utils::result_assert(
+            get_conflict(fd, 10, 10).is_none(),
+            "Middle of the lock should have been unlocked",
+        )?;
+
+        let conflict = get_conflict(fd, 5, 1).ok_or("Expected a conflicting lock")?;
+        test_utils::result_assert_eq(conflict.l_start, 0, "l_start")?;
+        test_utils::result_assert_eq(conflict.l_len, 10, "l_len")?;
+
+        let conflict = get_conflict(fd, 25, 1).ok_or("Expected a conflicting lock")?;
+        test_utils::result_assert_eq(conflict.l_start, 20, "l_start")?;
+        test_utils::result_assert_eq(conflict.l_len, 10, "l_len")?;
+
+        Ok(())
+    });
+
+    wait_child(child)
+}
+
+fn test_setlkw_blocks() -> Result<(), String> {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let fd = file.as_file().as_raw_fd();
+
+    let (reader, writer) = rustix::pipe::pipe().unwrap();
+
+    set_lock(fd, libc::F_WRLCK, 0, 0).unwrap();
+
+    let child = spawn_child(|| {
+        // should block until the parent releases its lock
+        test_utils::result_assert_eq(
+            lock_cmd(fd, libc::F_SETLKW, &mut new_flock(libc::F_WRLCK, 0, 0)),
+            Ok(()),
+            "Expected F_SETLKW to succeed",
+        )?;
+        test_utils::result_assert_eq(rustix::io::write(&writer, &[1]), Ok(1), "write failed")?;
+        Ok(())
+    });
+    drop(writer);
+
+    // give the child time to block
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    // the child should still be blocked
+    test_utils::result_assert(
+        !test_utils::is_readable(reader.as_raw_fd(), 0).unwrap(),
+        "Child should be blocked on the lock",
+    )?;
+
+    set_lock(fd, libc::F_UNLCK, 0, 0).unwrap();
+
+    let mut buf = [0u8];
+    test_utils::result_assert_eq(
+        rustix::io::read(&reader, &mut buf),
+        Ok(1),
+        "Child should have acquired the lock",
+    )?;
+
+    wait_child(child)
+}
+
+fn test_setlkw_deadlock() -> Result<(), String> {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let fd = file.as_file().as_raw_fd();
+
+    let (reader, writer) = rustix::pipe::pipe().unwrap();
+
+    set_lock(fd, libc::F_WRLCK, 0, 1).unwrap();
+
+    let child = spawn_child(|| {
+        test_utils::result_assert_eq(
+            set_lock(fd, libc::F_WRLCK, 1, 1),
+            Ok(()),
+            "Expected the lock to succeed",
+        )?;
+        test_utils::result_assert_eq(rustix::io::write(&writer, &[1]), Ok(1), "write failed")?;
+
+        // should block until the parent releases its lock
+        test_utils::result_assert_eq(
+            lock_cmd(fd, libc::F_SETLKW, &mut new_flock(libc::F_WRLCK, 0, 1)),
+            Ok(()),
+            "Expected F_SETLKW to succeed",
+        )?;
+        Ok(())
+    });
+    drop(writer);
+
+    // wait for the child to take its lock, and then give it time to block
+    let mut buf = [0u8];
+    test_utils::result_assert_eq(rustix::io::read(&reader, &mut buf), Ok(1), "read failed")?;
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    // the child is waiting for our lock, so waiting for the child's lock would deadlock
+    test_utils::result_assert_eq(
+        lock_cmd(fd, libc::F_SETLKW, &mut new_flock(libc::F_WRLCK, 1, 1)),
+        Err(libc::EDEADLK),
+        "Expected F_SETLKW to detect the deadlock",
+    )?;
+
+    // the child can continue once we release our lock
+    set_lock(fd, libc::F_UNLCK, 0, 1).unwrap();
+
+    wait_child(child)
+}
+
+fn test_released_on_close() -> Result<(), String> {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let fd = file.as_file().as_raw_fd();
+
+    set_lock(fd, libc::F_WRLCK, 0, 0).unwrap();
+
+    // closing any descriptor for the file releases the process' locks on the file
+    let other_fd = open_file(file.path(), libc::O_RDWR);
+    assert_eq!(unsafe { libc::close(other_fd) }, 0);
+
+    let child = spawn_child(|| {
+        test_utils::result_assert(get_conflict(fd, 0, 0).is_none(), "Lock wasn't released")
+    });
+
+    wait_child(child)
+}
+
+fn test_released_on_exit() -> Result<(), String> {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let fd = file.as_file().as_raw_fd();
+
+    let child = spawn_child(|| {
+        test_utils::result_assert_eq(
+            set_lock(fd, libc::F_WRLCK, 0, 0),
+            Ok(()),
+            "Expected the lock to succeed",
+        )
+    });
+    wait_child(child)?;
+
+    test_utils::result_assert_eq(
+        set_lock(fd, libc::F_WRLCK, 0, 0),
+        Ok(()),
+        "The child's lock wasn't released",
+    )
+}
+
+fn test_access_mode() -> Result<(), String> {
+    let file = tempfile::NamedTempFile::new().unwrap();
+
+    let fd = open_file(file.path(), libc::O_RDONLY);
+    test_utils::result_assert_eq(
+        set_lock(fd, libc::F_WRLCK, 0, 0),
+        Err(libc::EBADF),
+        "Write lock on a read-only file",
+    )?;
+    test_utils::result_assert_eq(set_lock(fd, libc::F_RDLCK, 0, 0), Ok(()), "Read lock")?;
+    assert_eq!(unsafe { libc::close(fd) }, 0);
+
+    let fd = open_file(file.path(), libc::O_WRONLY);
+    test_utils::result_assert_eq(
+        set_lock(fd, libc::F_RDLCK, 0, 0),
+        Err(libc::EBADF),
+        "Read lock on a write-only file",
+    )?;
+    test_utils::result_assert_eq(set_lock(fd, libc::F_WRLCK, 0, 0), Ok(()), "Write lock")?;
+    assert_eq!(unsafe { libc::close(fd) }, 0);
+
+    Ok(())
+}
+
+fn test_ofd_locks() -> Result<(), String> {
+    let file = tempfile::NamedTempFile::new().unwrap();
+
+    // two different open file descriptions for the same file
+    let fd_1 = open_file(file.path(), libc::O_RDWR);
+    let fd_2 = open_file(file.path(), libc::O_RDWR);
+
+    test_utils::result_assert_eq(
+        lock_cmd(fd_1, libc::F_OFD_SETLK, &mut new_flock(libc::F_WRLCK, 0, 0)),
+        Ok(()),
+        "Expected the lock to succeed",
+    )?;
+
+    // open file description locks conflict even within the same process
+    test_utils::result_assert_eq(
+        lock_cmd(fd_2, libc::F_OFD_SETLK, &mut new_flock(libc::F_WRLCK, 0, 0)),
+        Err(libc::EAGAIN),
+        "Expected the lock to conflict",
+    )?;
+
+    let mut fl = new_flock(libc::F_WRLCK, 0, 0);
+    lock_cmd(fd_2, libc::F_OFD_GETLK, &mut fl).unwrap();
+    test_utils::result_assert_eq(fl.l_type, libc::F_WRLCK as libc::c_short, "l_type")?;
+    test_utils::result_assert_eq(fl.l_pid, -1, "l_pid")?;
+
+    // l_pid must be 0
+    let mut fl = new_flock(libc::F_WRLCK, 0, 0);
+    fl.l_pid = 1;
+    test_utils::result_assert_eq(
+        lock_cmd(fd_2, libc::F_OFD_SETLK, &mut fl),
+        Err(libc::EINVAL),
+        "Expected EINVAL for a non-zero l_pid",
+    )?;
+
+    // closing the open file description releases its locks
+    assert_eq!(unsafe { libc::close(fd_1) }, 0);
+    test_utils::result_assert_eq(
+        lock_cmd(fd_2, libc::F_OFD_SETLK, &mut new_flock(libc::F_WRLCK, 0, 0)),
+        Ok(()),
+        "Expected the lock to succeed after close",
+    )?;
+
+    assert_eq!(unsafe { libc::close(fd_2) }, 0);
+
+    Ok(())
+}
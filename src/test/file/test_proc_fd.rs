@@ -0,0 +1,343 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+use std::collections::BTreeSet;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::UnixStream;
+
+use test_utils::TestEnvironment as TestEnv;
+use test_utils::set;
+
+fn main() -> Result<(), String> {
+    // should we restrict the tests we run?
+    let filter_shadow_passing = std::env::args().any(|x| x == "--shadow-passing");
+    let filter_libc_passing = std::env::args().any(|x| x == "--libc-passing");
+    // should we summarize the results rather than exit on a failed test
+    let summarize = std::env::args().any(|x| x == "--summarize");
+
+    let mut tests = get_tests();
+    if filter_shadow_passing {
+        tests.retain(|x| x.passing(TestEnv::Shadow));
+    }
+    if filter_libc_passing {
+        tests.retain(|x| x.passing(TestEnv::Libc));
+    }
+
+    test_utils::run_tests(&tests, summarize)?;
+
+    println!("Success.");
+    Ok(())
+}
+
+fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
+    let tests: Vec<test_utils::ShadowTest<_, _>> = vec![
+        test_utils::ShadowTest::new(
+            "test_list_fds",
+            test_list_fds,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_list_fds_pid",
+            test_list_fds_pid,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_readlink",
+            test_readlink,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_readlink_closed_fd",
+            test_readlink_closed_fd,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_getdents_small_buffer",
+            test_getdents_small_buffer,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_close_inherited_fds",
+            test_close_inherited_fds,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+    ];
+
+    tests
+}
+
+fn new_pipe() -> (OwnedFd, OwnedFd) {
+    let mut fds = [0; 2];
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+    unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) }
+}
+
+fn new_eventfd() -> OwnedFd {
+    let fd = unsafe { libc::eventfd(0, 0) };
+    assert!(fd >= 0);
+    unsafe { OwnedFd::from_raw_fd(fd) }
+}
+
+/// List the fds in a `/proc/<pid>/fd` directory.
+fn list_fds(dir: &str) -> Result<BTreeSet<libc::c_int>, String> {
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("read_dir({dir}) failed: {e}"))?;
+
+    entries
+        .map(|entry| {
+            let name = entry.map_err(|e| e.to_string())?.file_name();
+            let name = name.to_str().ok_or("Non-utf8 entry name")?;
+            name.parse()
+                .map_err(|e| format!("Unexpected entry name '{name}': {e}"))
+        })
+        .collect()
+}
+
+/// Read the `/proc/self/fd` link for `fd`.
+fn read_fd_link(fd: libc::c_int) -> std::io::Result<Vec<u8>> {
+    std::fs::read_link(format!("/proc/self/fd/{fd}"))
+        .map(|path| path.as_os_str().as_bytes().to_vec())
+}
+
+fn test_list_fds() -> Result<(), String> {
+    let (pipe_read, pipe_write) = new_pipe();
+    let (sock_1, sock_2) = UnixStream::pair().unwrap();
+    let eventfd = new_eventfd();
+
+    let opened = [
+        pipe_read.as_raw_fd(),
+        pipe_write.as_raw_fd(),
+        sock_1.as_raw_fd(),
+        sock_2.as_raw_fd(),
+        eventfd.as_raw_fd(),
+    ];
+
+    let listed = list_fds("/proc/self/fd")?;
+    for fd in opened {
+        test_utils::result_assert(listed.contains(&fd), &format!("fd {fd} wasn't listed"))?;
+    }
+
+    // a closed fd should no longer be listed
+    let closed_fd = eventfd.as_raw_fd();
+    drop(eventfd);
+
+    let listed = list_fds("/proc/self/fd")?;
+    test_utils::result_assert(
+        !listed.contains(&closed_fd),
+        &format!("Closed fd {closed_fd} was listed"),
+    )?;
+    test_utils::result_assert(
+        listed.contains(&pipe_read.as_raw_fd()),
+        "Pipe fd wasn't listed",
+    )?;
+
+    Ok(())
+}
+
+fn test_list_fds_pid() -> Result<(), String> {
+    let (pipe_read, _pipe_write) = new_pipe();
+
+    let pid = unsafe { libc::getpid() };
+    let listed = list_fds(&format!("/proc/{pid}/fd"))?;
+
+    test_utils::result_assert(
+        listed.contains(&pipe_read.as_raw_fd()),
+        "Pipe fd wasn't listed",
+    )?;
+
+    // the same fds should be listed for "self"
+    test_utils::result_assert_eq(listed, list_fds("/proc/self/fd")?, "Listings differ")?;
+
+    Ok(())
+}
+
+fn test_readlink() -> Result<(), String> {
+    let (pipe_read, _pipe_write) = new_pipe();
+    let (sock, _) = UnixStream::pair().unwrap();
+    let eventfd = new_eventfd();
+    let file = tempfile::NamedTempFile::new().unwrap();
+
+    let has_prefix = |fd: libc::c_int, prefix: &[u8]| -> Result<(), String> {
+        let target = read_fd_link(fd).map_err(|e| format!("readlink failed for fd {fd}: {e}"))?;
+        test_utils::result_assert(
+            target.starts_with(prefix),
+            &format!(
+                "Unexpected link target for fd {fd}: '{}'",
+                String::from_utf8_lossy(&target)
+            ),
+        )
+    };
+
+    has_prefix(pipe_read.as_raw_fd(), b"pipe:[")?;
+    has_prefix(sock.as_raw_fd(), b"socket:[")?;
+    has_prefix(eventfd.as_raw_fd(), b"anon_inode:[eventfd]")?;
+
+    let file_path = std::fs::canonicalize(file.path()).unwrap();
+    test_utils::result_assert_eq(
+        read_fd_link(file.as_file().as_raw_fd()).map_err(|e| e.to_string())?,
+        file_path.as_os_str().as_bytes().to_vec(),
+        "Unexpected link target for the regular file",
+    )?;
+
+    // the link target is truncated to the buffer size without a terminating null byte
+    let path = format!("/proc/self/fd/{}\0", pipe_read.as_raw_fd());
+    let mut buf = [0xFFu8; 8];
+    let rv = unsafe { libc::readlink(path.as_ptr() as *const _, buf.as_mut_ptr() as *mut _, 4) };
+    test_utils::result_assert_eq(rv, 4, "Unexpected readlink return value")?;
+    test_utils::result_assert_eq(buf, *b"pipe\xFF\xFF\xFF\xFF", "Unexpected buffer")?;
+
+    Ok(())
+}
+
+fn test_readlink_closed_fd() -> Result<(), String> {
+    let eventfd = new_eventfd();
+    let fd = eventfd.as_raw_fd();
+    drop(eventfd);
+
+    let rv = read_fd_link(fd);
+    test_utils::result_assert_eq(
+        rv.map_err(|e| e.raw_os_error()),
+        Err(Some(libc::ENOENT)),
+        "Expected ENOENT for a closed fd",
+    )?;
+
+    Ok(())
+}
+
+fn test_getdents_small_buffer() -> Result<(), String> {
+    let (pipe_read, pipe_write) = new_pipe();
+
+    let dir_fd = unsafe {
+        libc::open(
+            c"/proc/self/fd".as_ptr(),
+            libc::O_RDONLY | libc::O_DIRECTORY | libc::O_CLOEXEC,
+        )
+    };
+    test_utils::result_assert(dir_fd >= 0, "Couldn't open /proc/self/fd")?;
+    let dir = unsafe { OwnedFd::from_raw_fd(dir_fd) };
+
+    let getdents = |buf: &mut [u8]| unsafe {
+        libc::syscall(
+            libc::SYS_getdents64,
+            dir.as_raw_fd(),
+            buf.as_mut_ptr(),
+            buf.len(),
+        )
+    };
+
+    // getdents(2): "EINVAL: Result buffer is too small."
+    let mut buf = [0u8; 8];
+    test_utils::result_assert_eq(getdents(&mut buf), -1, "Expected an error")?;
+    test_utils::result_assert_eq(test_utils::get_errno(), libc::EINVAL, "Expected EINVAL")?;
+
+    // read the directory a few entries at a time
+    let mut names = Vec::new();
+    let mut buf = [0u8; 64];
+    loop {
+        let len = getdents(&mut buf);
+        test_utils::result_assert(len >= 0, "getdents64 failed")?;
+        if len == 0 {
+            break;
+        }
+
+        let mut offset = 0;
+        while offset < len as usize {
+            // struct linux_dirent64 { u64 d_ino; s64 d_off; u16 d_reclen; u8 d_type; char d_name[]; }
+            let reclen = u16::from_ne_bytes(buf[offset + 16..offset + 18].try_into().unwrap());
+            let name = std::ffi::CStr::from_bytes_until_nul(&buf[offset + 19..]).unwrap();
+            names.push(name.to_str().unwrap().to_string());
+            offset += usize::from(reclen);
+        }
+    }
+
+    let fds: BTreeSet<libc::c_int> = names
+        .iter()
+        .filter(|x| *x != "." && *x != "..")
+        .map(|x| x.parse().unwrap())
+        .collect();
+
+    test_utils::result_assert(names.contains(&".".to_string()), "Missing '.'")?;
+    test_utils::result_assert(names.contains(&"..".to_string()), "Missing '..'")?;
+    for fd in [
+        pipe_read.as_raw_fd(),
+        pipe_write.as_raw_fd(),
+        dir.as_raw_fd(),
+    ] {
+        test_utils::result_assert(fds.contains(&fd), &format!("fd {fd} wasn't listed"))?;
+    }
+
+    // rewinding the directory should list the entries again
+    test_utils::result_assert_eq(
+        unsafe { libc::lseek(dir.as_raw_fd(), 0, libc::SEEK_SET) },
+        0,
+        "lseek failed",
+    )?;
+    test_utils::result_assert(getdents(&mut buf) > 0, "Expected entries after rewinding")?;
+
+    Ok(())
+}
+
+/// Close all fds without `FD_CLOEXEC` set (other than stdio), like a process might do after
+/// forking.
+fn test_close_inherited_fds() -> Result<(), String> {
+    let (pipe_read, pipe_write) = new_pipe();
+    let eventfd = new_eventfd();
+
+    // the eventfd will be closed on exec, so shouldn't be closed by the child
+    assert_eq!(
+        unsafe { libc::fcntl(eventfd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) },
+        0
+    );
+
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+
+    if pid == 0 {
+        let rv = (|| -> Result<(), String> {
+            for fd in list_fds("/proc/self/fd")? {
+                let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+                // the fd of the directory listing was already closed
+                if fd > 2 && flags >= 0 && flags & libc::FD_CLOEXEC == 0 {
+                    unsafe { libc::close(fd) };
+                }
+            }
+
+            let is_open = |fd| unsafe { libc::fcntl(fd, libc::F_GETFD) } >= 0;
+            test_utils::result_assert(!is_open(pipe_read.as_raw_fd()), "Pipe is open")?;
+            test_utils::result_assert(!is_open(pipe_write.as_raw_fd()), "Pipe is open")?;
+            test_utils::result_assert(is_open(eventfd.as_raw_fd()), "Eventfd is closed")?;
+
+            Ok(())
+        })();
+
+        let code = match rv {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("Error in child process: {e}");
+                1
+            }
+        };
+        unsafe { libc::_exit(code) };
+    }
+
+    let mut status = 0;
+    test_utils::result_assert_eq(
+        unsafe { libc::waitpid(pid, &mut status, 0) },
+        pid,
+        "waitpid failed",
+    )?;
+    test_utils::result_assert(
+        libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0,
+        &format!("Child exited with status {status}"),
+    )?;
+
+    // the parent's fds are unaffected
+    test_utils::result_assert(
+        list_fds("/proc/self/fd")?.contains(&pipe_read.as_raw_fd()),
+        "Pipe fd wasn't listed",
+    )?;
+
+    Ok(())
+}
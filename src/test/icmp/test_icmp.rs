@@ -0,0 +1,253 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+use std::net::{Ipv4Addr, ToSocketAddrs};
+
+use test_utils::TestEnvironment as TestEnv;
+use test_utils::set;
+
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_ECHO_REQUEST: u8 = 8;
+const IPV4_HEADER_LEN: usize = 20;
+
+fn main() -> Result<(), String> {
+    // should we restrict the tests we run?
+    let filter_shadow_passing = std::env::args().any(|x| x == "--shadow-passing");
+    let filter_libc_passing = std::env::args().any(|x| x == "--libc-passing");
+    // should we summarize the results rather than exit on a failed test
+    let summarize = std::env::args().any(|x| x == "--summarize");
+
+    let mut tests = get_tests();
+    if filter_shadow_passing {
+        tests.retain(|x| x.passing(TestEnv::Shadow));
+    }
+    if filter_libc_passing {
+        tests.retain(|x| x.passing(TestEnv::Libc));
+    }
+
+    test_utils::run_tests(&tests, summarize)?;
+
+    println!("Success.");
+    Ok(())
+}
+
+fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
+    vec![
+        test_utils::ShadowTest::new("test_unprivileged", test_unprivileged, set![TestEnv::Libc]),
+        test_utils::ShadowTest::new(
+            "test_unsupported_protocol",
+            test_unsupported_protocol,
+            set![TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_ping_loopback",
+            || test_ping(Ipv4Addr::LOCALHOST),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_ping_peer",
+            || test_ping(lookup_peer()),
+            set![TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_ping_unreachable",
+            test_ping_unreachable,
+            set![TestEnv::Shadow],
+        ),
+    ]
+}
+
+fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// Returns the address of the "peer" host in the shadow simulation.
+fn lookup_peer() -> Ipv4Addr {
+    match ("peer", 0).to_socket_addrs().unwrap().next().unwrap() {
+        std::net::SocketAddr::V4(addr) => *addr.ip(),
+        std::net::SocketAddr::V6(addr) => panic!("Unexpected address {addr}"),
+    }
+}
+
+/// Open a raw ICMP socket, or return `None` if we don't have permission. Shadow allows raw ICMP
+/// sockets for every process, so this only returns `None` outside of shadow.
+fn raw_icmp_socket() -> Result<Option<libc::c_int>, String> {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_RAW, libc::IPPROTO_ICMP) };
+    if fd < 0 {
+        let errno = test_utils::get_errno();
+        test_utils::result_assert(
+            !test_utils::running_in_shadow(),
+            "Failed to create a raw socket in shadow",
+        )?;
+        test_utils::result_assert_eq(errno, libc::EPERM, "Unexpected errno")?;
+        // we may also be root without CAP_NET_RAW
+        println!("Skipping test since we don't have permission to open a raw socket");
+        return Ok(None);
+    }
+    Ok(Some(fd))
+}
+
+fn checksum(bytes: &[u8]) -> u16 {
+    let mut sum: u32 = bytes
+        .chunks(2)
+        .map(|x| u32::from(u16::from_be_bytes([x[0], *x.get(1).unwrap_or(&0)])))
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn echo_request(id: u16, seq: u16, data: &[u8]) -> Vec<u8> {
+    let mut msg = vec![ICMP_ECHO_REQUEST, 0, 0, 0];
+    msg.extend_from_slice(&id.to_be_bytes());
+    msg.extend_from_slice(&seq.to_be_bytes());
+    msg.extend_from_slice(data);
+    let checksum = checksum(&msg);
+    msg[2..4].copy_from_slice(&checksum.to_be_bytes());
+    msg
+}
+
+fn sendto(fd: libc::c_int, buf: &[u8], dst: Ipv4Addr) -> libc::ssize_t {
+    let addr = libc::sockaddr_in {
+        sin_family: libc::AF_INET as u16,
+        sin_port: 0,
+        sin_addr: libc::in_addr {
+            s_addr: u32::from(dst).to_be(),
+        },
+        sin_zero: [0; 8],
+    };
+    unsafe {
+        libc::sendto(
+            fd,
+            buf.as_ptr().cast(),
+            buf.len(),
+            0,
+            std::ptr::from_ref(&addr).cast(),
+            std::mem::size_of_val(&addr) as libc::socklen_t,
+        )
+    }
+}
+
+/// Test that unprivileged processes can't open raw sockets on Linux (shadow allows them).
+fn test_unprivileged() -> Result<(), String> {
+    if is_root() {
+        println!("Skipping test since we're running as root");
+        return Ok(());
+    }
+
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_RAW, libc::IPPROTO_ICMP) };
+    test_utils::result_assert_eq(fd, -1, "Expected socket() to fail")?;
+    test_utils::result_assert_eq(test_utils::get_errno(), libc::EPERM, "Unexpected errno")?;
+
+    Ok(())
+}
+
+/// Test that raw sockets for protocols other than ICMP aren't supported.
+fn test_unsupported_protocol() -> Result<(), String> {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_RAW, libc::IPPROTO_TCP) };
+    test_utils::result_assert_eq(fd, -1, "Expected socket() to fail")?;
+    test_utils::result_assert_eq(
+        test_utils::get_errno(),
+        libc::EPROTONOSUPPORT,
+        "Unexpected errno",
+    )?;
+
+    Ok(())
+}
+
+fn test_ping(dst: Ipv4Addr) -> Result<(), String> {
+    let Some(fd) = raw_icmp_socket()? else {
+        return Ok(());
+    };
+
+    test_utils::run_and_close_fds(&[fd], || {
+        let id = std::process::id() as u16;
+        let data = b"hello shadow";
+
+        for seq in 1..=3 {
+            let request = echo_request(id, seq, data);
+            let rv = sendto(fd, &request, dst);
+            test_utils::result_assert_eq(rv, request.len() as isize, "Unexpected sendto result")?;
+
+            // linux also delivers our own echo request to raw sockets when pinging a local
+            // address, so skip any messages that aren't our reply
+            let reply = loop {
+                let mut buf = [0u8; 1024];
+                let mut src: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+                let mut src_len = std::mem::size_of_val(&src) as libc::socklen_t;
+                let rv = unsafe {
+                    libc::recvfrom(
+                        fd,
+                        buf.as_mut_ptr().cast(),
+                        buf.len(),
+                        0,
+                        std::ptr::from_mut(&mut src).cast(),
+                        &mut src_len,
+                    )
+                };
+                test_utils::result_assert(rv > 0, "recvfrom failed")?;
+                let buf = &buf[..rv as usize];
+
+                // the received message includes the IP header
+                test_utils::result_assert(buf.len() >= IPV4_HEADER_LEN + 8, "Message too short")?;
+                test_utils::result_assert_eq(buf[0], 0x45, "Unexpected IP version/length")?;
+                test_utils::result_assert_eq(
+                    checksum(&buf[..IPV4_HEADER_LEN]),
+                    0,
+                    "Invalid IP checksum",
+                )?;
+                test_utils::result_assert_eq(
+                    u32::from(buf[9]),
+                    libc::IPPROTO_ICMP as u32,
+                    "Unexpected IP protocol",
+                )?;
+                test_utils::result_assert_eq(
+                    Ipv4Addr::from(u32::from_be(src.sin_addr.s_addr)),
+                    dst,
+                    "Unexpected source address",
+                )?;
+                test_utils::result_assert_eq(
+                    &buf[12..16],
+                    &dst.octets()[..],
+                    "Unexpected IP source address",
+                )?;
+
+                let icmp = &buf[IPV4_HEADER_LEN..];
+                if icmp[0] == ICMP_ECHO_REPLY && icmp[4..6] == id.to_be_bytes() {
+                    break icmp.to_vec();
+                }
+            };
+
+            test_utils::result_assert_eq(reply[1], 0, "Unexpected ICMP code")?;
+            test_utils::result_assert_eq(checksum(&reply), 0, "Invalid ICMP checksum")?;
+            test_utils::result_assert_eq(&reply[6..8], &seq.to_be_bytes()[..], "Unexpected seq")?;
+            test_utils::result_assert_eq(&reply[8..], &data[..], "Unexpected echo data")?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Test that an echo request to an address without a host isn't answered.
+fn test_ping_unreachable() -> Result<(), String> {
+    let Some(fd) = raw_icmp_socket()? else {
+        return Ok(());
+    };
+
+    test_utils::run_and_close_fds(&[fd], || {
+        // an address reserved for documentation (TEST-NET-3)
+        let dst = Ipv4Addr::new(203, 0, 113, 1);
+        let request = echo_request(1, 1, &[]);
+
+        let rv = sendto(fd, &request, dst);
+        test_utils::result_assert_eq(rv, request.len() as isize, "Unexpected sendto result")?;
+
+        let readable = test_utils::is_readable(fd, 500).unwrap();
+        test_utils::result_assert(!readable, "Unexpected echo reply")?;
+
+        Ok(())
+    })
+}
@@ -44,6 +44,21 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
             test_readv_writev,
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
+        test_utils::ShadowTest::new(
+            "test_readv_writev_iov_count",
+            test_readv_writev_iov_count,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_readv_writev_empty_iovs",
+            test_readv_writev_empty_iovs,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_writev_len_overflow",
+            test_writev_len_overflow,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
         test_utils::ShadowTest::new(
             "test_large_read_write",
             test_large_read_write,
@@ -79,6 +94,11 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
             test_read_after_write_close_with_nonempty_buffer,
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
+        test_utils::ShadowTest::new(
+            "test_poll_after_write_close",
+            test_poll_after_write_close,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
         test_utils::ShadowTest::new(
             "test_write_after_read_close_with_full_buffer",
             test_write_after_read_close_with_full_buffer,
@@ -139,16 +159,62 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
             test_o_direct_full_buffer_2,
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
+        test_utils::ShadowTest::new(
+            "test_o_direct_packets",
+            test_o_direct_packets,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_o_direct_short_read",
+            test_o_direct_short_read,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
         test_utils::ShadowTest::new(
             "test_close_during_blocking_read",
             test_close_during_blocking_read,
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
+        test_utils::ShadowTest::new(
+            "test_nonblock_set_during_blocking_read",
+            test_nonblock_set_during_blocking_read,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
         test_utils::ShadowTest::new(
             "test_close_during_blocking_write",
             test_close_during_blocking_write,
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
+        test_utils::ShadowTest::new(
+            "test_writev_full_buffer_nonblocking",
+            test_writev_full_buffer_nonblocking,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_pwritev2_full_buffer_nowait",
+            test_pwritev2_full_buffer_nowait,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_atomic_writes_two_writers",
+            test_atomic_writes_two_writers,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new("test_tee", test_tee, set![TestEnv::Libc, TestEnv::Shadow]),
+        test_utils::ShadowTest::new(
+            "test_tee_blocking",
+            test_tee_blocking,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_tee_invalid_fds",
+            test_tee_invalid_fds,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_fd_limit",
+            test_fd_limit,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
     ];
 
     tests
@@ -162,6 +228,51 @@ fn test_null() -> Result<(), String> {
     Ok(())
 }
 
+/// A pipe needs two descriptors; if only one is available below the soft RLIMIT_NOFILE, the pipe
+/// should fail without leaving a descriptor behind.
+fn test_fd_limit() -> Result<(), String> {
+    // find the lowest unused fd
+    let lowest_fd = test_utils::check_system_call!(|| unsafe { libc::dup(0) }, &[])?;
+    test_utils::check_system_call!(|| unsafe { libc::close(lowest_fd) }, &[])?;
+
+    let mut orig_limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    test_utils::check_system_call!(
+        || unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut orig_limit) },
+        &[]
+    )?;
+
+    // only allow one more fd
+    let limit = libc::rlimit {
+        rlim_cur: lowest_fd as libc::rlim_t + 1,
+        rlim_max: orig_limit.rlim_max,
+    };
+    test_utils::check_system_call!(
+        || unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &limit) },
+        &[]
+    )?;
+
+    let mut fds = [-1 as libc::c_int; 2];
+    let rv =
+        test_utils::check_system_call!(|| unsafe { libc::pipe(fds.as_mut_ptr()) }, &[libc::EMFILE]);
+
+    test_utils::check_system_call!(
+        || unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &orig_limit) },
+        &[]
+    )?;
+    rv?;
+
+    // the first end of the pipe shouldn't have been left open
+    test_utils::check_system_call!(
+        || unsafe { libc::fcntl(lowest_fd, libc::F_GETFD) },
+        &[libc::EBADF]
+    )?;
+
+    Ok(())
+}
+
 fn test_pipe() -> Result<(), String> {
     let mut fds = [0 as libc::c_int; 2];
     test_utils::check_system_call!(|| { unsafe { libc::pipe(fds.as_mut_ptr()) } }, &[])?;
@@ -280,6 +391,202 @@ fn test_readv_writev() -> Result<(), String> {
     })
 }
 
+/// Test the iovec count limits of readv() and writev().
+fn test_readv_writev_iov_count() -> Result<(), String> {
+    let mut fds = [0 as libc::c_int; 2];
+    test_utils::check_system_call!(|| { unsafe { libc::pipe(fds.as_mut_ptr()) } }, &[])?;
+
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+
+    test_utils::run_and_close_fds(&[write_fd, read_fd], || {
+        // no iovecs is a no-op, and the array isn't read
+        let rv = test_utils::check_system_call!(
+            || { unsafe { libc::writev(write_fd, std::ptr::null(), 0) } },
+            &[]
+        )?;
+        test_utils::result_assert_eq(rv, 0, "Expected to write 0 bytes")?;
+
+        // the pipe is empty, but the read shouldn't block
+        let rv = test_utils::check_system_call!(
+            || { unsafe { libc::readv(read_fd, std::ptr::null(), 0) } },
+            &[]
+        )?;
+        test_utils::result_assert_eq(rv, 0, "Expected to read 0 bytes")?;
+
+        let max_iovs = usize::try_from(libc::UIO_MAXIOV).unwrap();
+        let write_buf: Vec<u8> = (0..=max_iovs).map(|x| x as u8).collect();
+        let write_iovs = iov_helper(write_buf.chunks(1));
+
+        // one more than the maximum
+        test_utils::check_system_call!(
+            || unsafe {
+                libc::writev(
+                    write_fd,
+                    write_iovs.as_ptr() as *const libc::iovec,
+                    write_iovs.len() as i32,
+                )
+            },
+            &[libc::EINVAL]
+        )?;
+
+        // exactly the maximum
+        let rv = test_utils::check_system_call!(
+            || unsafe {
+                libc::writev(
+                    write_fd,
+                    write_iovs.as_ptr() as *const libc::iovec,
+                    libc::UIO_MAXIOV,
+                )
+            },
+            &[]
+        )?;
+        test_utils::result_assert_eq(rv, libc::UIO_MAXIOV as isize, "Unexpected write size")?;
+
+        let mut read_buf = vec![0u8; max_iovs + 1];
+        let mut read_iovs = iov_helper_mut(read_buf.chunks_mut(1));
+
+        // one more than the maximum
+        test_utils::check_system_call!(
+            || unsafe {
+                libc::readv(
+                    read_fd,
+                    read_iovs.as_mut_ptr() as *const libc::iovec,
+                    read_iovs.len() as i32,
+                )
+            },
+            &[libc::EINVAL]
+        )?;
+
+        // exactly the maximum
+        let rv = test_utils::check_system_call!(
+            || unsafe {
+                libc::readv(
+                    read_fd,
+                    read_iovs.as_mut_ptr() as *const libc::iovec,
+                    libc::UIO_MAXIOV,
+                )
+            },
+            &[]
+        )?;
+        test_utils::result_assert_eq(rv, libc::UIO_MAXIOV as isize, "Unexpected read size")?;
+
+        drop(read_iovs);
+        test_utils::result_assert_eq(
+            &read_buf[..max_iovs],
+            &write_buf[..max_iovs],
+            "Buffers differ",
+        )?;
+
+        Ok(())
+    })
+}
+
+/// Test that empty iovecs are skipped, and aren't treated as the end of the data or buffer.
+fn test_readv_writev_empty_iovs() -> Result<(), String> {
+    let mut fds = [0 as libc::c_int; 2];
+    test_utils::check_system_call!(|| { unsafe { libc::pipe(fds.as_mut_ptr()) } }, &[])?;
+
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+
+    test_utils::run_and_close_fds(&[write_fd, read_fd], || {
+        // only empty iovecs
+        let write_iovs = iov_helper([&[][..], &[][..]]);
+        let rv = test_utils::check_system_call!(
+            || unsafe {
+                libc::writev(
+                    write_fd,
+                    write_iovs.as_ptr() as *const libc::iovec,
+                    write_iovs.len() as i32,
+                )
+            },
+            &[]
+        )?;
+        test_utils::result_assert_eq(rv, 0, "Expected to write 0 bytes")?;
+
+        // leading, consecutive, and trailing empty iovecs
+        let write_iovs = iov_helper([
+            &[][..],
+            &[][..],
+            &[1, 2][..],
+            &[][..],
+            &[][..],
+            &[3][..],
+            &[][..],
+        ]);
+        let rv = test_utils::check_system_call!(
+            || unsafe {
+                libc::writev(
+                    write_fd,
+                    write_iovs.as_ptr() as *const libc::iovec,
+                    write_iovs.len() as i32,
+                )
+            },
+            &[]
+        )?;
+        test_utils::result_assert_eq(rv, 3, "Expected to write 3 bytes")?;
+
+        let read_iovs = [
+            &mut [][..],
+            &mut [0; 1][..],
+            &mut [][..],
+            &mut [][..],
+            &mut [0; 5][..],
+        ];
+        let mut read_iovs = iov_helper_mut(read_iovs);
+        let rv = test_utils::check_system_call!(
+            || unsafe {
+                libc::readv(
+                    read_fd,
+                    read_iovs.as_mut_ptr() as *const libc::iovec,
+                    read_iovs.len() as i32,
+                )
+            },
+            &[]
+        )?;
+        test_utils::result_assert_eq(rv, 3, "Expected to read 3 bytes")?;
+
+        let read: Vec<u8> = read_iovs.iter().flat_map(|x| x.iter().copied()).collect();
+        test_utils::result_assert_eq(&read[..3], &[1, 2, 3][..], "Buffers differ")?;
+
+        Ok(())
+    })
+}
+
+/// Test that writev() returns `EINVAL` if an iovec length is too large.
+fn test_writev_len_overflow() -> Result<(), String> {
+    let mut fds = [0 as libc::c_int; 2];
+    test_utils::check_system_call!(|| { unsafe { libc::pipe(fds.as_mut_ptr()) } }, &[])?;
+
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+
+    test_utils::run_and_close_fds(&[write_fd, read_fd], || {
+        let buf = [1u8, 2, 3];
+        let write_iovs = [
+            libc::iovec {
+                iov_base: buf.as_ptr() as *mut libc::c_void,
+                iov_len: buf.len(),
+            },
+            libc::iovec {
+                iov_base: buf.as_ptr() as *mut libc::c_void,
+                iov_len: usize::MAX,
+            },
+        ];
+
+        test_utils::check_system_call!(
+            || unsafe { libc::writev(write_fd, write_iovs.as_ptr(), write_iovs.len() as i32) },
+            &[libc::EINVAL]
+        )?;
+
+        // nothing was written
+        test_utils::result_assert(
+            !test_utils::is_readable(read_fd, 0).unwrap(),
+            "Pipe shouldn't be readable",
+        )?;
+
+        Ok(())
+    })
+}
+
 fn test_large_read_write() -> Result<(), String> {
     let mut fds = [0 as libc::c_int; 2];
     test_utils::check_system_call!(|| { unsafe { libc::pipe(fds.as_mut_ptr()) } }, &[])?;
@@ -502,13 +809,7 @@ fn test_read_after_write_close_with_empty_buffer() -> Result<(), String> {
         // read fd should be POLLHUP
         assert_eq!(
             test_utils::poll_status(read_fd, 0).unwrap(),
-            // shadow doesn't support POLLHUP
-            // https://github.com/shadow/shadow/issues/2181
-            if test_utils::running_in_shadow() {
-                PollFlags::POLLIN
-            } else {
-                PollFlags::POLLHUP
-            }
+            PollFlags::POLLHUP
         );
 
         // the write fd is closed, so reading should return 0
@@ -552,13 +853,7 @@ fn test_read_after_write_close_with_nonempty_buffer() -> Result<(), String> {
         // read fd should be POLLHUP
         assert_eq!(
             test_utils::poll_status(read_fd, 0).unwrap(),
-            // shadow doesn't support POLLHUP
-            // https://github.com/shadow/shadow/issues/2181
-            if test_utils::running_in_shadow() {
-                PollFlags::POLLIN
-            } else {
-                PollFlags::POLLHUP
-            }
+            PollFlags::POLLHUP
         );
 
         // the write fd is closed, so reading should return 0
@@ -569,6 +864,41 @@ fn test_read_after_write_close_with_nonempty_buffer() -> Result<(), String> {
     Ok(())
 }
 
+/// Poll the read end after the write end was closed, first with and then without buffered data.
+fn test_poll_after_write_close() -> Result<(), String> {
+    let mut fds = [0 as libc::c_int; 2];
+    test_utils::check_system_call!(
+        || { unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK) } },
+        &[]
+    )?;
+
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+
+    test_utils::run_and_close_fds(&[read_fd], || {
+        test_utils::run_and_close_fds(&[write_fd], || {
+            nix::unistd::write(write_fd, &[1, 2, 3]).unwrap();
+        });
+
+        let poll_read_end = || {
+            let mut poll_fds = [nix::poll::PollFd::new(read_fd, PollFlags::POLLIN)];
+            nix::poll::poll(&mut poll_fds, 0).unwrap();
+            poll_fds[0].revents().unwrap()
+        };
+
+        // the remaining data is still readable
+        assert_eq!(poll_read_end(), PollFlags::POLLIN | PollFlags::POLLHUP);
+
+        let mut buf = vec![0u8; 10];
+        assert_eq!(nix::unistd::read(read_fd, &mut buf).unwrap(), 3);
+
+        // POLLHUP is reported even though we only asked for POLLIN
+        assert_eq!(poll_read_end(), PollFlags::POLLHUP);
+        assert_eq!(nix::unistd::read(read_fd, &mut buf).unwrap(), 0);
+    });
+
+    Ok(())
+}
+
 fn test_write_after_read_close_with_full_buffer() -> Result<(), String> {
     let mut fds = [0 as libc::c_int; 2];
     test_utils::check_system_call!(
@@ -600,13 +930,7 @@ fn test_write_after_read_close_with_full_buffer() -> Result<(), String> {
         // write fd should be POLLERR
         assert_eq!(
             test_utils::poll_status(write_fd, 0).unwrap(),
-            // shadow doesn't support POLLERR
-            // https://github.com/shadow/shadow/issues/2181
-            if test_utils::running_in_shadow() {
-                PollFlags::POLLOUT
-            } else {
-                PollFlags::POLLERR
-            }
+            PollFlags::POLLERR
         );
 
         // the read fd is closed, so writing should return EPIPE
@@ -618,13 +942,7 @@ fn test_write_after_read_close_with_full_buffer() -> Result<(), String> {
         // write fd should be POLLERR
         assert_eq!(
             test_utils::poll_status(write_fd, 0).unwrap(),
-            // shadow doesn't support POLLERR
-            // https://github.com/shadow/shadow/issues/2181
-            if test_utils::running_in_shadow() {
-                PollFlags::POLLOUT
-            } else {
-                PollFlags::POLLERR
-            }
+            PollFlags::POLLERR
         );
     });
 
@@ -1121,6 +1439,78 @@ fn test_o_direct_full_buffer_2() -> Result<(), String> {
     })
 }
 
+// each write is a separate packet, and each read returns at most one packet
+fn test_o_direct_packets() -> Result<(), String> {
+    let mut fds = [0 as libc::c_int; 2];
+    test_utils::check_system_call!(
+        || { unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_DIRECT) } },
+        &[]
+    )?;
+
+    test_utils::result_assert(fds[0] > 0, "fds[0] not set")?;
+    test_utils::result_assert(fds[1] > 0, "fds[1] not set")?;
+
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+
+    test_utils::run_and_close_fds(&[write_fd, read_fd], || {
+        assert_eq!(nix::unistd::write(write_fd, &[1, 2, 3]), Ok(3));
+        assert_eq!(nix::unistd::write(write_fd, &[4, 5]), Ok(2));
+
+        let mut in_buf = [0u8; 10];
+
+        // the read buffer is large enough for both packets, but only the first is returned
+        let len = nix::unistd::read(read_fd, &mut in_buf).unwrap();
+        assert_eq!(&in_buf[..len], &[1, 2, 3]);
+
+        let len = nix::unistd::read(read_fd, &mut in_buf).unwrap();
+        assert_eq!(&in_buf[..len], &[4, 5]);
+
+        // no packets left
+        assert_eq!(
+            nix::unistd::read(read_fd, &mut in_buf).err(),
+            Some(nix::errno::Errno::EWOULDBLOCK)
+        );
+    });
+
+    Ok(())
+}
+
+// a read with a buffer smaller than the packet discards the rest of the packet
+fn test_o_direct_short_read() -> Result<(), String> {
+    let mut fds = [0 as libc::c_int; 2];
+    test_utils::check_system_call!(
+        || { unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_DIRECT) } },
+        &[]
+    )?;
+
+    test_utils::result_assert(fds[0] > 0, "fds[0] not set")?;
+    test_utils::result_assert(fds[1] > 0, "fds[1] not set")?;
+
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+
+    test_utils::run_and_close_fds(&[write_fd, read_fd], || {
+        assert_eq!(nix::unistd::write(write_fd, &[1, 2, 3]), Ok(3));
+        assert_eq!(nix::unistd::write(write_fd, &[4, 5]), Ok(2));
+
+        let mut in_buf = [0u8; 2];
+
+        // only the first two bytes of the first packet are returned
+        assert_eq!(nix::unistd::read(read_fd, &mut in_buf), Ok(2));
+        assert_eq!(in_buf, [1, 2]);
+
+        // the remaining byte of the first packet was discarded
+        assert_eq!(nix::unistd::read(read_fd, &mut in_buf), Ok(2));
+        assert_eq!(in_buf, [4, 5]);
+
+        assert_eq!(
+            nix::unistd::read(read_fd, &mut in_buf).err(),
+            Some(nix::errno::Errno::EWOULDBLOCK)
+        );
+    });
+
+    Ok(())
+}
+
 fn test_close_during_blocking_read() -> Result<(), String> {
     let mut fds = [0 as libc::c_int; 2];
     test_utils::check_system_call!(|| { unsafe { libc::pipe2(fds.as_mut_ptr(), 0) } }, &[])?;
@@ -1167,6 +1557,47 @@ fn test_close_during_blocking_read() -> Result<(), String> {
     Ok(())
 }
 
+/// Setting `O_NONBLOCK` while a `read()` is blocked shouldn't make the `read()` fail. It should
+/// still return the data once it arrives.
+fn test_nonblock_set_during_blocking_read() -> Result<(), String> {
+    let mut fds = [0 as libc::c_int; 2];
+    test_utils::check_system_call!(|| { unsafe { libc::pipe2(fds.as_mut_ptr(), 0) } }, &[])?;
+
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+
+    test_utils::run_and_close_fds(&[read_fd, write_fd], || {
+        let thread_handle = std::thread::spawn(move || {
+            // 2. wait for the read() to start
+            std::thread::sleep(Duration::from_millis(100));
+
+            // 3. make the read end non-blocking while the read() is blocked
+            nix::fcntl::fcntl(
+                read_fd,
+                nix::fcntl::FcntlArg::F_SETFL(nix::fcntl::OFlag::O_NONBLOCK),
+            )
+            .unwrap();
+            std::thread::sleep(Duration::from_millis(100));
+
+            // 4. wake the reader by writing
+            assert_eq!(nix::unistd::write(write_fd, &[1, 2, 3]), Ok(3));
+        });
+
+        // 1. the read will block until there are bytes to read
+        let mut buf = vec![0u8; 10];
+        assert_eq!(nix::unistd::read(read_fd, &mut buf), Ok(3));
+
+        thread_handle.join().unwrap();
+
+        // 5. new reads are non-blocking
+        assert_eq!(
+            nix::unistd::read(read_fd, &mut buf),
+            Err(nix::errno::Errno::EAGAIN)
+        );
+    });
+
+    Ok(())
+}
+
 fn test_close_during_blocking_write() -> Result<(), String> {
     let mut fds = [0 as libc::c_int; 2];
     test_utils::check_system_call!(|| { unsafe { libc::pipe2(fds.as_mut_ptr(), 0) } }, &[])?;
@@ -1224,3 +1655,295 @@ fn test_close_during_blocking_write() -> Result<(), String> {
 
     Ok(())
 }
+
+/// Write to a non-blocking pipe until it's full.
+fn fill_pipe(write_fd: libc::c_int) {
+    loop {
+        match nix::unistd::write(write_fd, &[0u8; 1024]) {
+            Ok(n) => assert!(n > 0),
+            Err(nix::errno::Errno::EWOULDBLOCK) => break,
+            Err(e) => panic!("Unexpected error {}", e),
+        }
+    }
+}
+
+fn test_writev_full_buffer_nonblocking() -> Result<(), String> {
+    let mut fds = [0 as libc::c_int; 2];
+    test_utils::check_system_call!(
+        || { unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK) } },
+        &[]
+    )?;
+
+    test_utils::result_assert(fds[0] > 0, "fds[0] not set")?;
+    test_utils::result_assert(fds[1] > 0, "fds[1] not set")?;
+
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+
+    test_utils::run_and_close_fds(&[write_fd, read_fd], || {
+        fill_pipe(write_fd);
+
+        // a small write to a full pipe should fail without writing any bytes
+        let iovs = iov_helper([&[1u8; 10][..], &[2u8; 10][..]]);
+        assert_eq!(
+            nix::sys::uio::writev(write_fd, &iovs),
+            Err(nix::errno::Errno::EWOULDBLOCK)
+        );
+
+        // make a small amount of space in the pipe
+        let mut buf = vec![0u8; 100];
+        assert_eq!(nix::unistd::read(read_fd, &mut buf), Ok(100));
+
+        // a write of at most PIPE_BUF bytes is atomic, so should not be partially written
+        let iovs = iov_helper([&[1u8; 150][..], &[2u8; 150][..]]);
+        assert_eq!(
+            nix::sys::uio::writev(write_fd, &iovs),
+            Err(nix::errno::Errno::EWOULDBLOCK)
+        );
+
+        Ok(())
+    })
+}
+
+fn test_pwritev2_full_buffer_nowait() -> Result<(), String> {
+    let mut fds = [0 as libc::c_int; 2];
+    test_utils::check_system_call!(
+        || { unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK) } },
+        &[]
+    )?;
+
+    test_utils::result_assert(fds[0] > 0, "fds[0] not set")?;
+    test_utils::result_assert(fds[1] > 0, "fds[1] not set")?;
+
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+
+    test_utils::run_and_close_fds(&[write_fd, read_fd], || {
+        fill_pipe(write_fd);
+
+        // make the write end blocking
+        use nix::fcntl::{FcntlArg, OFlag};
+        nix::fcntl::fcntl(write_fd, FcntlArg::F_SETFL(OFlag::empty())).unwrap();
+
+        let buf = [1u8; 10];
+        let iov = libc::iovec {
+            iov_base: buf.as_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        };
+
+        // the write shouldn't block since RWF_NOWAIT was given
+        let rv = unsafe { libc::pwritev2(write_fd, &iov, 1, -1, libc::RWF_NOWAIT) };
+        let errno = test_utils::get_errno();
+        assert_eq!(rv, -1);
+
+        // older linux kernels don't support RWF_NOWAIT for pipes
+        if test_utils::running_in_shadow() {
+            assert_eq!(errno, libc::EAGAIN);
+        } else {
+            assert!([libc::EAGAIN, libc::EOPNOTSUPP].contains(&errno));
+        }
+
+        Ok(())
+    })
+}
+
+fn test_atomic_writes_two_writers() -> Result<(), String> {
+    const MSG_LEN: usize = 512;
+    const NUM_MSGS: usize = 200;
+
+    let mut fds = [0 as libc::c_int; 2];
+    test_utils::check_system_call!(|| { unsafe { libc::pipe2(fds.as_mut_ptr(), 0) } }, &[])?;
+
+    test_utils::result_assert(fds[0] > 0, "fds[0] not set")?;
+    test_utils::result_assert(fds[1] > 0, "fds[1] not set")?;
+
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+
+    // each writer writes many small messages, which will fill the pipe and block the writers
+    let writer = move |id: u8| {
+        let msg = [id; MSG_LEN];
+        for _ in 0..NUM_MSGS {
+            assert_eq!(nix::unistd::write(write_fd, &msg), Ok(MSG_LEN));
+        }
+    };
+
+    let writer_1 = std::thread::spawn(move || writer(1));
+    let writer_2 = std::thread::spawn(move || writer(2));
+
+    // read all of the messages
+    let mut buf = vec![0u8; 2 * MSG_LEN * NUM_MSGS];
+    let mut num_read = 0;
+    while num_read < buf.len() {
+        let n = nix::unistd::read(read_fd, &mut buf[num_read..]).unwrap();
+        assert!(n > 0);
+        num_read += n;
+    }
+
+    writer_1.join().unwrap();
+    writer_2.join().unwrap();
+
+    nix::unistd::close(write_fd).unwrap();
+    nix::unistd::close(read_fd).unwrap();
+
+    // since the writes were atomic, each message should only contain bytes from one writer
+    for msg in buf.chunks(MSG_LEN) {
+        assert!(msg.iter().all(|x| *x == msg[0]), "A message was split");
+    }
+
+    // each writer should have written all of its messages
+    for id in [1, 2] {
+        let count = buf.chunks(MSG_LEN).filter(|msg| msg[0] == id).count();
+        assert_eq!(count, NUM_MSGS);
+    }
+
+    Ok(())
+}
+
+fn test_tee() -> Result<(), String> {
+    use nix::fcntl::SpliceFFlags;
+
+    let (src_read_fd, src_write_fd) = nix::unistd::pipe().unwrap();
+    let (dst_read_fd, dst_write_fd) = nix::unistd::pipe().unwrap();
+
+    test_utils::run_and_close_fds(&[src_read_fd, dst_read_fd, dst_write_fd], || {
+        test_utils::run_and_close_fds(&[src_write_fd], || {
+            // the source pipe is empty but still has a writer
+            assert_eq!(
+                nix::fcntl::tee(
+                    src_read_fd,
+                    dst_write_fd,
+                    100,
+                    SpliceFFlags::SPLICE_F_NONBLOCK
+                ),
+                Err(nix::errno::Errno::EAGAIN)
+            );
+
+            assert_eq!(nix::unistd::write(src_write_fd, b"hello"), Ok(5));
+
+            // only up to `len` bytes are duplicated
+            assert_eq!(
+                nix::fcntl::tee(
+                    src_read_fd,
+                    dst_write_fd,
+                    3,
+                    SpliceFFlags::SPLICE_F_NONBLOCK
+                ),
+                Ok(3)
+            );
+            assert_eq!(
+                nix::fcntl::tee(
+                    src_read_fd,
+                    dst_write_fd,
+                    100,
+                    SpliceFFlags::SPLICE_F_NONBLOCK
+                ),
+                Ok(5)
+            );
+        });
+
+        let mut buf = [0u8; 100];
+
+        // the destination pipe has both copies
+        assert_eq!(nix::unistd::read(dst_read_fd, &mut buf), Ok(8));
+        assert_eq!(&buf[..8], b"helhello");
+
+        // the source pipe still has the data
+        assert_eq!(nix::unistd::read(src_read_fd, &mut buf), Ok(5));
+        assert_eq!(&buf[..5], b"hello");
+
+        // the source pipe is empty and has no writers
+        assert_eq!(
+            nix::fcntl::tee(src_read_fd, dst_write_fd, 100, SpliceFFlags::empty()),
+            Ok(0)
+        );
+    });
+
+    Ok(())
+}
+
+fn test_tee_blocking() -> Result<(), String> {
+    let (src_read_fd, src_write_fd) = nix::unistd::pipe().unwrap();
+    let (dst_read_fd, dst_write_fd) = nix::unistd::pipe().unwrap();
+
+    test_utils::run_and_close_fds(
+        &[src_read_fd, src_write_fd, dst_read_fd, dst_write_fd],
+        || {
+            let writer = std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(100));
+                assert_eq!(nix::unistd::write(src_write_fd, b"hello"), Ok(5));
+            });
+
+            // should block until the writer writes to the source pipe
+            assert_eq!(
+                nix::fcntl::tee(
+                    src_read_fd,
+                    dst_write_fd,
+                    100,
+                    nix::fcntl::SpliceFFlags::empty()
+                ),
+                Ok(5)
+            );
+
+            writer.join().unwrap();
+
+            let mut buf = [0u8; 100];
+            assert_eq!(nix::unistd::read(dst_read_fd, &mut buf), Ok(5));
+            assert_eq!(nix::unistd::read(src_read_fd, &mut buf), Ok(5));
+        },
+    );
+
+    Ok(())
+}
+
+fn test_tee_invalid_fds() -> Result<(), String> {
+    use nix::fcntl::SpliceFFlags;
+
+    let (read_fd, write_fd) = nix::unistd::pipe().unwrap();
+    let (other_read_fd, other_write_fd) = nix::unistd::pipe().unwrap();
+    let event_fd = unsafe { libc::eventfd(0, 0) };
+    assert!(event_fd >= 0);
+
+    test_utils::run_and_close_fds(
+        &[read_fd, write_fd, other_read_fd, other_write_fd, event_fd],
+        || {
+            assert_eq!(nix::unistd::write(write_fd, b"hello"), Ok(5));
+
+            // both ends of the same pipe
+            assert_eq!(
+                nix::fcntl::tee(read_fd, write_fd, 100, SpliceFFlags::SPLICE_F_NONBLOCK),
+                Err(nix::errno::Errno::EINVAL)
+            );
+
+            // not a pipe
+            assert_eq!(
+                nix::fcntl::tee(read_fd, event_fd, 100, SpliceFFlags::SPLICE_F_NONBLOCK),
+                Err(nix::errno::Errno::EINVAL)
+            );
+
+            // the wrong ends of the pipes
+            assert_eq!(
+                nix::fcntl::tee(
+                    write_fd,
+                    other_write_fd,
+                    100,
+                    SpliceFFlags::SPLICE_F_NONBLOCK
+                ),
+                Err(nix::errno::Errno::EBADF)
+            );
+            assert_eq!(
+                nix::fcntl::tee(read_fd, other_read_fd, 100, SpliceFFlags::SPLICE_F_NONBLOCK),
+                Err(nix::errno::Errno::EBADF)
+            );
+
+            // invalid fd
+            assert_eq!(
+                nix::fcntl::tee(read_fd, -1, 100, SpliceFFlags::SPLICE_F_NONBLOCK),
+                Err(nix::errno::Errno::EBADF)
+            );
+
+            // nothing was consumed from the source pipe
+            let mut buf = [0u8; 100];
+            assert_eq!(nix::unistd::read(read_fd, &mut buf), Ok(5));
+        },
+    );
+
+    Ok(())
+}
@@ -5,6 +5,7 @@
 #![allow(clippy::too_many_arguments)]
 
 use std::cmp::Ordering;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 use std::time::Duration;
 
 use nix::sys::signal;
@@ -280,6 +281,166 @@ fn test_poll_args_common(
     })
 }
 
+static SIGUSR1_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn count_sigusr1(_signo: i32) {
+    SIGUSR1_COUNT.fetch_add(1, AtomicOrdering::SeqCst);
+}
+
+/// Test that ppoll() installs its signal mask only for the duration of the call, so that a signal
+/// that's blocked outside of the call can interrupt it.
+fn test_ppoll_sigmask() -> Result<(), String> {
+    let fd = get_pollable_fd()?;
+
+    unsafe {
+        signal::sigaction(
+            Signal::SIGUSR1,
+            &signal::SigAction::new(
+                signal::SigHandler::Handler(count_sigusr1),
+                signal::SaFlags::empty(),
+                signal::SigSet::empty(),
+            ),
+        )
+    }
+    .unwrap();
+    SIGUSR1_COUNT.store(0, AtomicOrdering::SeqCst);
+
+    // block SIGUSR1 outside of ppoll()
+    let mut sigset_to_block = signal::SigSet::empty();
+    sigset_to_block.add(Signal::SIGUSR1);
+    let mut orig_mask = signal::SigSet::empty();
+    signal::pthread_sigmask(
+        signal::SigmaskHow::SIG_BLOCK,
+        Some(&sigset_to_block),
+        Some(&mut orig_mask),
+    )
+    .unwrap();
+
+    let rv = test_utils::run_and_close_fds(&[fd], || {
+        // the same mask, but with SIGUSR1 unblocked
+        let mut ppoll_mask = signal::SigSet::thread_get_mask().unwrap();
+        ppoll_mask.remove(Signal::SIGUSR1);
+
+        // never readable
+        let mut pfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout_ts = libc::timespec {
+            tv_sec: 5,
+            tv_nsec: 0,
+        };
+
+        let interruptor = test_utils::Interruptor::new(Duration::from_millis(10), Signal::SIGUSR1);
+
+        // should be interrupted by the signal, even though it's blocked outside of ppoll()
+        let instant_before = std::time::Instant::now();
+        test_utils::check_system_call!(
+            || unsafe { libc::ppoll(&mut pfd, 1, &timeout_ts, ppoll_mask.as_ref()) },
+            &[libc::EINTR]
+        )?;
+        let elapsed = instant_before.elapsed();
+        drop(interruptor);
+
+        test_utils::result_assert(
+            elapsed < Duration::from_secs(5),
+            "ppoll() wasn't interrupted before its timeout",
+        )?;
+        test_utils::result_assert_eq(
+            SIGUSR1_COUNT.load(AtomicOrdering::SeqCst),
+            1,
+            "The signal handler wasn't run",
+        )?;
+
+        // the original mask should have been restored
+        let mask = signal::SigSet::thread_get_mask().unwrap();
+        test_utils::result_assert(
+            mask.contains(Signal::SIGUSR1),
+            "SIGUSR1 was not blocked after ppoll()",
+        )?;
+
+        // a new signal should stay pending while blocked
+        signal::raise(Signal::SIGUSR1).unwrap();
+        test_utils::result_assert_eq(
+            SIGUSR1_COUNT.load(AtomicOrdering::SeqCst),
+            1,
+            "The blocked signal was handled",
+        )?;
+
+        Ok(())
+    });
+
+    // unblocking the signal should run the handler for the pending signal
+    signal::pthread_sigmask(signal::SigmaskHow::SIG_SETMASK, Some(&orig_mask), None).unwrap();
+    rv?;
+
+    test_utils::result_assert_eq(
+        SIGUSR1_COUNT.load(AtomicOrdering::SeqCst),
+        2,
+        "The pending signal wasn't handled after unblocking",
+    )?;
+
+    Ok(())
+}
+
+/// Test that the shortest timeout that the syscall supports (a millisecond for poll and a nanosecond
+/// for ppoll) waits for at least the timeout every time, and that a zero timeout returns
+/// immediately.
+fn test_short_timeouts(poll_fn: PollFn) -> Result<(), String> {
+    let fd = get_pollable_fd()?;
+
+    test_utils::run_and_close_fds(&[fd], || {
+        // the socket has no data, so it will never be readable
+        let mut pfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        let poll = |pfd: &mut libc::pollfd, timeout: Duration| match poll_fn {
+            PollFn::Poll => unsafe { libc::poll(pfd, 1, timeout.as_millis().try_into().unwrap()) },
+            PollFn::PPoll => {
+                let timeout_ts = libc::timespec {
+                    tv_sec: timeout.as_secs().try_into().unwrap(),
+                    tv_nsec: timeout.subsec_nanos().into(),
+                };
+                unsafe { libc::ppoll(pfd, 1, &timeout_ts, std::ptr::null()) }
+            }
+        };
+
+        // a zero timeout should return immediately without blocking
+        let instant_before = std::time::Instant::now();
+        test_utils::result_assert_eq(poll(&mut pfd, Duration::ZERO), 0, "Unexpected events")?;
+        let elapsed = instant_before.elapsed();
+        if test_utils::running_in_shadow() {
+            // shadow should never have blocked, so only the syscall latency should have elapsed
+            test_utils::result_assert(
+                elapsed < Duration::from_millis(1),
+                &format!("A zero timeout took {elapsed:?}"),
+            )?;
+        }
+
+        // the smallest timeout that each syscall supports
+        let timeout = match poll_fn {
+            PollFn::Poll => Duration::from_millis(1),
+            PollFn::PPoll => Duration::from_nanos(1),
+        };
+
+        for _ in 0..3 {
+            let instant_before = std::time::Instant::now();
+            test_utils::result_assert_eq(poll(&mut pfd, timeout), 0, "Unexpected events")?;
+            let elapsed = instant_before.elapsed();
+            test_utils::result_assert(
+                elapsed >= timeout,
+                &format!("Timeout of {timeout:?}, but only {elapsed:?} elapsed"),
+            )?;
+        }
+
+        Ok(())
+    })
+}
+
 fn get_poll_args_test(
     poll_fn: PollFn,
     pfd_null: bool,
@@ -339,6 +500,21 @@ fn main() -> Result<(), String> {
             test_regular_file,
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
+        test_utils::ShadowTest::new(
+            "test_ppoll_sigmask",
+            test_ppoll_sigmask,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_short_timeouts_poll",
+            || test_short_timeouts(PollFn::Poll),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_short_timeouts_ppoll",
+            || test_short_timeouts(PollFn::PPoll),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
     ];
 
     // For each combination of args, test both poll and ppoll
@@ -413,6 +413,86 @@ fn test_handled_tkill_interrupts_syscall() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+fn test_tgkill_targets_thread() -> Result<(), Box<dyn Error>> {
+    let signal = Signal::SIGUSR1;
+    unsafe {
+        signal::sigaction(
+            signal,
+            &signal::SigAction::new(
+                signal::SigHandler::SigAction(signal_action),
+                signal::SaFlags::SA_SIGINFO,
+                signal::SigSet::empty(),
+            ),
+        )
+        .unwrap()
+    };
+
+    let other_thread = BlockedThread::new();
+    let target_thread = BlockedThread::new();
+
+    tgkill(unistd::getpid(), target_thread.tid, signal).unwrap();
+    assert_eq!(target_thread.handle.join().unwrap(), Err(Errno::EINTR));
+
+    // The handler should have run exactly once, on the target thread.
+    let record = signal_channel().recv().unwrap();
+    assert_eq!(record.signal, signal as i32);
+    assert_eq!(record.tid, target_thread.tid);
+    assert_eq!(record.info.unwrap().si_code, SignalCode::SI_TKILL as i32);
+    assert_eq!(signal_channel().recv(), None);
+
+    // The other thread should still be blocked.
+    unistd::write(other_thread.write_fd, &[0]).unwrap();
+    assert_eq!(other_thread.handle.join().unwrap(), Ok(1));
+
+    unsafe {
+        signal::sigaction(
+            signal,
+            &signal::SigAction::new(
+                signal::SigHandler::SigDfl,
+                signal::SaFlags::empty(),
+                signal::SigSet::empty(),
+            ),
+        )
+        .unwrap()
+    };
+    Ok(())
+}
+
+fn test_tkill_tgkill_errors() -> Result<(), Box<dyn Error>> {
+    let pid = unistd::getpid().as_raw();
+    let tid = unistd::gettid().as_raw();
+
+    let tkill = |tid: libc::pid_t, sig: libc::c_int| {
+        Errno::result(unsafe { libc::syscall(libc::SYS_tkill, tid, sig) }).map(drop)
+    };
+    let tgkill = |pid: libc::pid_t, tid: libc::pid_t, sig: libc::c_int| {
+        Errno::result(unsafe { libc::syscall(libc::SYS_tgkill, pid, tid, sig) }).map(drop)
+    };
+
+    // A signal of 0 only checks that the thread exists.
+    assert_eq!(tkill(tid, 0), Ok(()));
+    assert_eq!(tgkill(pid, tid, 0), Ok(()));
+
+    // Ids that can't be valid.
+    assert_eq!(tkill(0, 0), Err(Errno::EINVAL));
+    assert_eq!(tkill(-1, 0), Err(Errno::EINVAL));
+    assert_eq!(tgkill(0, tid, 0), Err(Errno::EINVAL));
+    assert_eq!(tgkill(pid, -1, 0), Err(Errno::EINVAL));
+
+    // Ids that don't exist. Linux's pid limit is well below `i32::MAX`.
+    assert_eq!(tkill(i32::MAX, 0), Err(Errno::ESRCH));
+    assert_eq!(tgkill(pid, i32::MAX, 0), Err(Errno::ESRCH));
+
+    // The thread isn't in this thread group.
+    assert_eq!(tgkill(i32::MAX, tid, 0), Err(Errno::ESRCH));
+
+    // Invalid signals.
+    assert_eq!(tkill(tid, 1000), Err(Errno::EINVAL));
+    assert_eq!(tgkill(pid, tid, -1), Err(Errno::EINVAL));
+
+    Ok(())
+}
+
 fn test_ignored_tkill_doesnt_interrupt_syscall() -> Result<(), Box<dyn Error>> {
     let signal = Signal::SIGUSR1;
     unsafe {
@@ -1350,6 +1430,16 @@ fn main() -> Result<(), Box<dyn Error>> {
             test_handled_tkill_interrupts_syscall,
             all_envs.clone(),
         ),
+        ShadowTest::new(
+            "tgkill targets thread",
+            test_tgkill_targets_thread,
+            all_envs.clone(),
+        ),
+        ShadowTest::new(
+            "tkill and tgkill errors",
+            test_tkill_tgkill_errors,
+            all_envs.clone(),
+        ),
         ShadowTest::new(
             "ignored tkill doesn't interrupt syscall",
             test_ignored_tkill_doesnt_interrupt_syscall,
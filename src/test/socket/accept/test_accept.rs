@@ -226,6 +226,44 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
         }
     }
 
+    for &domain in [libc::AF_INET, libc::AF_UNIX].iter() {
+        tests.extend(vec![test_utils::ShadowTest::new(
+            &format!("test_multiple_blocked_acceptors <domain={domain}>"),
+            move || test_multiple_blocked_acceptors(domain),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        )]);
+    }
+
+    for &domain in [libc::AF_INET, libc::AF_UNIX].iter() {
+        tests.extend(vec![test_utils::ShadowTest::new(
+            &format!("test_recv_timeout <domain={domain}>"),
+            move || test_recv_timeout(domain),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        )]);
+    }
+
+    tests.extend(vec![test_utils::ShadowTest::new(
+        "test_defer_accept",
+        test_defer_accept,
+        set![TestEnv::Libc, TestEnv::Shadow],
+    )]);
+
+    for listener_enabled in [true, false] {
+        tests.extend(vec![test_utils::ShadowTest::new(
+            &format!("test_fast_open <listener_enabled={listener_enabled}>"),
+            move || test_fast_open(listener_enabled),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        )]);
+    }
+
+    for &accept_fn in [AcceptFn::Accept, AcceptFn::Accept4].iter() {
+        tests.extend(vec![test_utils::ShadowTest::new(
+            &format!("test_reset_before_accept <fn={accept_fn:?}>"),
+            move || test_reset_before_accept(accept_fn),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        )]);
+    }
+
     tests
 }
 
@@ -507,8 +545,7 @@ fn test_short_len(
     test_utils::run_and_close_fds(&[fd_client, fd_server], || -> Result<(), String> {
         let fd = check_accept_call(&mut args, accept_fn, expected_errno)?;
         if let Some(fd) = fd {
-            let rv = unsafe { libc::close(fd) };
-            assert_eq!(rv, 0, "Could not close the fd");
+            test_utils::run_and_close_fds(&[fd], || check_cloexec_flag(fd, accept_flag))?;
         }
         Ok(())
     })?;
@@ -621,8 +658,7 @@ fn test_zero_len(
     test_utils::run_and_close_fds(&[fd_client, fd_server], || -> Result<(), String> {
         let fd = check_accept_call(&mut args, accept_fn, expected_errno)?;
         if let Some(fd) = fd {
-            let rv = unsafe { libc::close(fd) };
-            assert_eq!(rv, 0, "Could not close the fd");
+            test_utils::run_and_close_fds(&[fd], || check_cloexec_flag(fd, accept_flag))?;
         }
         Ok(())
     })?;
@@ -788,8 +824,7 @@ fn test_correctness(
     test_utils::run_and_close_fds(&[fd_client, fd_server], || -> Result<(), String> {
         let fd = check_accept_call(&mut args, accept_fn, expected_errno)?;
         if let Some(fd) = fd {
-            let rv = unsafe { libc::close(fd) };
-            assert_eq!(rv, 0, "Could not close the fd");
+            test_utils::run_and_close_fds(&[fd], || check_cloexec_flag(fd, accept_flag))?;
         }
         Ok(())
     })?;
@@ -1001,6 +1036,446 @@ fn test_after_client_closed(
     Ok(())
 }
 
+/// Test that when several threads are blocked in accept() on the same listening socket, each
+/// incoming connection wakes exactly one of them and the others remain blocked.
+fn test_multiple_blocked_acceptors(domain: libc::c_int) -> Result<(), String> {
+    const NUM_THREADS: usize = 4;
+
+    let fd_server = unsafe { libc::socket(domain, libc::SOCK_STREAM, 0) };
+    assert!(fd_server >= 0);
+
+    let (server_addr, server_addr_len) = socket_utils::autobind_helper(fd_server, domain);
+
+    let rv = unsafe { libc::listen(fd_server, NUM_THREADS as i32) };
+    assert_eq!(rv, 0);
+
+    let num_returned = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+    let threads: Vec<_> = (0..NUM_THREADS)
+        .map(|_| {
+            let num_returned = std::sync::Arc::clone(&num_returned);
+            std::thread::spawn(move || {
+                let rv =
+                    unsafe { libc::accept(fd_server, std::ptr::null_mut(), std::ptr::null_mut()) };
+                let errno = test_utils::get_errno();
+                num_returned.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                (rv, errno)
+            })
+        })
+        .collect();
+
+    // give the threads time to block
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    test_utils::result_assert_eq(
+        num_returned.load(std::sync::atomic::Ordering::SeqCst),
+        0,
+        "A thread returned from accept() before any connections",
+    )?;
+
+    let mut client_fds = vec![];
+
+    // connect one client at a time; each should wake a single thread
+    for i in 0..NUM_THREADS {
+        let fd_client = unsafe { libc::socket(domain, libc::SOCK_STREAM, 0) };
+        assert!(fd_client >= 0);
+
+        let rv = unsafe { libc::connect(fd_client, server_addr.as_ptr(), server_addr_len) };
+        assert_eq!(rv, 0);
+        client_fds.push(fd_client);
+
+        // give the woken thread time to return
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        test_utils::result_assert_eq(
+            num_returned.load(std::sync::atomic::Ordering::SeqCst),
+            i + 1,
+            "Unexpected number of threads returned from accept()",
+        )?;
+    }
+
+    for thread in threads {
+        let (fd, errno) = thread.join().unwrap();
+        test_utils::result_assert(
+            fd >= 0,
+            &format!("accept() failed: {}", test_utils::get_errno_message(errno)),
+        )?;
+        nix::unistd::close(fd).unwrap();
+    }
+
+    for fd in client_fds {
+        nix::unistd::close(fd).unwrap();
+    }
+    nix::unistd::close(fd_server).unwrap();
+
+    Ok(())
+}
+
+/// Test that a blocking accept() fails with `EAGAIN` once the listening socket's `SO_RCVTIMEO`
+/// timeout has passed.
+fn test_recv_timeout(domain: libc::c_int) -> Result<(), String> {
+    let fd_server = unsafe { libc::socket(domain, libc::SOCK_STREAM, 0) };
+    assert!(fd_server >= 0);
+
+    socket_utils::autobind_helper(fd_server, domain);
+
+    let rv = unsafe { libc::listen(fd_server, 10) };
+    assert_eq!(rv, 0);
+
+    let timeout = libc::timeval {
+        tv_sec: 0,
+        tv_usec: 200_000,
+    };
+    let rv = unsafe {
+        libc::setsockopt(
+            fd_server,
+            libc::SOL_SOCKET,
+            libc::SO_RCVTIMEO,
+            std::ptr::from_ref(&timeout) as *const libc::c_void,
+            std::mem::size_of_val(&timeout) as libc::socklen_t,
+        )
+    };
+    assert_eq!(rv, 0);
+
+    test_utils::run_and_close_fds(&[fd_server], || {
+        let start = std::time::Instant::now();
+        let rv = unsafe { libc::accept(fd_server, std::ptr::null_mut(), std::ptr::null_mut()) };
+        let errno = test_utils::get_errno();
+        let elapsed = start.elapsed();
+
+        test_utils::result_assert_eq(rv, -1, "Expected accept() to time out")?;
+        test_utils::result_assert_eq(errno, libc::EAGAIN, "Expected EAGAIN")?;
+        test_utils::result_assert(
+            elapsed >= std::time::Duration::from_millis(200),
+            &format!("accept() returned after only {elapsed:?}"),
+        )?;
+        test_utils::result_assert(
+            elapsed < std::time::Duration::from_millis(1000),
+            &format!("accept() didn't return until {elapsed:?}"),
+        )?;
+
+        Ok(())
+    })
+}
+
+/// Test that with `TCP_DEFER_ACCEPT`, accept() doesn't return a connection until the client has
+/// sent data.
+fn test_defer_accept() -> Result<(), String> {
+    let fd_server = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+    assert!(fd_server >= 0);
+
+    let defer_secs: libc::c_int = 5;
+    let rv = unsafe {
+        libc::setsockopt(
+            fd_server,
+            libc::IPPROTO_TCP,
+            libc::TCP_DEFER_ACCEPT,
+            std::ptr::from_ref(&defer_secs) as *const libc::c_void,
+            std::mem::size_of_val(&defer_secs) as libc::socklen_t,
+        )
+    };
+    assert_eq!(rv, 0);
+
+    // linux rounds the timeout up to a whole number of SYN-ACK retransmissions (1+2+4 seconds)
+    let mut optval: libc::c_int = 0;
+    let mut optlen = std::mem::size_of_val(&optval) as libc::socklen_t;
+    let rv = unsafe {
+        libc::getsockopt(
+            fd_server,
+            libc::IPPROTO_TCP,
+            libc::TCP_DEFER_ACCEPT,
+            std::ptr::from_mut(&mut optval) as *mut libc::c_void,
+            &mut optlen,
+        )
+    };
+    assert_eq!(rv, 0);
+    test_utils::result_assert_eq(optval, 7, "Unexpected TCP_DEFER_ACCEPT value")?;
+
+    let (server_addr, server_addr_len) = socket_utils::autobind_helper(fd_server, libc::AF_INET);
+
+    let rv = unsafe { libc::listen(fd_server, 10) };
+    assert_eq!(rv, 0);
+
+    let accepted = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+    let thread = {
+        let accepted = std::sync::Arc::clone(&accepted);
+        std::thread::spawn(move || {
+            let rv = unsafe { libc::accept(fd_server, std::ptr::null_mut(), std::ptr::null_mut()) };
+            let errno = test_utils::get_errno();
+            accepted.store(true, std::sync::atomic::Ordering::SeqCst);
+            (rv, errno)
+        })
+    };
+
+    let fd_client = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+    assert!(fd_client >= 0);
+
+    let rv = unsafe { libc::connect(fd_client, server_addr.as_ptr(), server_addr_len) };
+    assert_eq!(rv, 0);
+
+    // the connection is established, but the client hasn't sent any data yet
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    test_utils::result_assert(
+        !accepted.load(std::sync::atomic::Ordering::SeqCst),
+        "accept() returned before the client sent any data",
+    )?;
+
+    let msg = b"hello";
+    let rv = unsafe { libc::send(fd_client, msg.as_ptr() as *const libc::c_void, msg.len(), 0) };
+    assert_eq!(rv, msg.len() as isize);
+
+    let (fd_accepted, errno) = thread.join().unwrap();
+    test_utils::result_assert(
+        fd_accepted >= 0,
+        &format!("accept() failed: {}", test_utils::get_errno_message(errno)),
+    )?;
+
+    // the data should be waiting for us on the accepted socket
+    let mut buf = [0u8; 5];
+    let rv = unsafe {
+        libc::recv(
+            fd_accepted,
+            buf.as_mut_ptr() as *mut libc::c_void,
+            buf.len(),
+            0,
+        )
+    };
+    test_utils::result_assert_eq(rv, msg.len() as isize, "Unexpected recv() return value")?;
+    test_utils::result_assert_eq(&buf, msg, "Unexpected data received")?;
+
+    nix::unistd::close(fd_accepted).unwrap();
+    nix::unistd::close(fd_client).unwrap();
+    nix::unistd::close(fd_server).unwrap();
+
+    Ok(())
+}
+
+/// Test that a connection that was reset by the client before it was accepted is still returned by
+/// accept as a usable socket, and that the reset is reported by the first read.
+fn test_reset_before_accept(accept_fn: AcceptFn) -> Result<(), String> {
+    let fd_server = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+    assert!(fd_server >= 0);
+
+    let (server_addr, server_addr_len) = socket_utils::autobind_helper(fd_server, libc::AF_INET);
+
+    let rv = unsafe { libc::listen(fd_server, 10) };
+    assert_eq!(rv, 0);
+
+    let fd_client = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+    assert!(fd_client >= 0);
+
+    let rv = unsafe { libc::connect(fd_client, server_addr.as_ptr(), server_addr_len) };
+    assert_eq!(rv, 0);
+
+    let mut client_addr: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+    let mut client_addr_len = std::mem::size_of_val(&client_addr) as libc::socklen_t;
+    let rv = unsafe {
+        libc::getsockname(
+            fd_client,
+            std::ptr::from_mut(&mut client_addr) as *mut libc::sockaddr,
+            &mut client_addr_len,
+        )
+    };
+    assert_eq!(rv, 0);
+
+    // a zero linger timeout makes close() reset the connection
+    let linger = libc::linger {
+        l_onoff: 1,
+        l_linger: 0,
+    };
+    let rv = unsafe {
+        libc::setsockopt(
+            fd_client,
+            libc::SOL_SOCKET,
+            libc::SO_LINGER,
+            std::ptr::from_ref(&linger) as *const libc::c_void,
+            std::mem::size_of_val(&linger) as libc::socklen_t,
+        )
+    };
+    assert_eq!(rv, 0);
+
+    nix::unistd::close(fd_client).unwrap();
+
+    // wait for the server to receive the reset
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let accept_flags = match accept_fn {
+        AcceptFn::Accept => 0,
+        AcceptFn::Accept4 => libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+    };
+
+    let mut args = AcceptArguments {
+        fd: fd_server,
+        addr: Some(SockAddr::dummy_init_inet()),
+        addr_len: Some(SockAddr::dummy_init_inet().ptr_size()),
+        flags: accept_flags,
+    };
+
+    let fd_accepted = match check_accept_call(&mut args, accept_fn, None) {
+        Ok(fd) => fd.unwrap(),
+        Err(e) => {
+            nix::unistd::close(fd_server).unwrap();
+            return Err(e);
+        }
+    };
+
+    test_utils::run_and_close_fds(&[fd_accepted, fd_server], || {
+        test_utils::result_assert_eq(
+            args.addr_len.unwrap() as usize,
+            std::mem::size_of::<libc::sockaddr_in>(),
+            "Unexpected address length",
+        )?;
+        test_utils::result_assert_eq(
+            args.addr.unwrap().as_inet().unwrap().sin_port,
+            client_addr.sin_port,
+            "Unexpected peer port",
+        )?;
+
+        // the accept flags should have been applied to the new socket
+        check_cloexec_flag(fd_accepted, accept_flags)?;
+        let status_flags = unsafe { libc::fcntl(fd_accepted, libc::F_GETFL) };
+        test_utils::result_assert_eq(
+            status_flags & libc::O_NONBLOCK != 0,
+            accept_flags & libc::SOCK_NONBLOCK != 0,
+            "Unexpected O_NONBLOCK flag",
+        )?;
+
+        let mut buf = [0u8; 5];
+
+        // the first read should report the reset
+        test_utils::check_system_call!(
+            || unsafe {
+                libc::recv(
+                    fd_accepted,
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len(),
+                    0,
+                )
+            },
+            &[libc::ECONNRESET],
+        )?;
+
+        // future reads should return EOF
+        let rv = unsafe {
+            libc::recv(
+                fd_accepted,
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+                0,
+            )
+        };
+        test_utils::result_assert_eq(rv, 0, "Expected EOF")?;
+
+        Ok(())
+    })
+}
+
+/// Test that data gathered from several buffers and sent with `MSG_FASTOPEN` is received on the
+/// first read after accept, even if the listener doesn't accept data in the SYN.
+fn test_fast_open(listener_enabled: bool) -> Result<(), String> {
+    let fd_server = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+    assert!(fd_server >= 0);
+
+    let qlen: libc::c_int = if listener_enabled { 5 } else { 0 };
+    let rv = unsafe {
+        libc::setsockopt(
+            fd_server,
+            libc::IPPROTO_TCP,
+            libc::TCP_FASTOPEN,
+            std::ptr::from_ref(&qlen) as *const libc::c_void,
+            std::mem::size_of_val(&qlen) as libc::socklen_t,
+        )
+    };
+    assert_eq!(rv, 0);
+
+    let mut optval: libc::c_int = 0;
+    let mut optlen = std::mem::size_of_val(&optval) as libc::socklen_t;
+    let rv = unsafe {
+        libc::getsockopt(
+            fd_server,
+            libc::IPPROTO_TCP,
+            libc::TCP_FASTOPEN,
+            std::ptr::from_mut(&mut optval) as *mut libc::c_void,
+            &mut optlen,
+        )
+    };
+    assert_eq!(rv, 0);
+    test_utils::result_assert_eq(optval, qlen, "Unexpected TCP_FASTOPEN value")?;
+
+    let (server_addr, server_addr_len) = socket_utils::autobind_helper(fd_server, libc::AF_INET);
+
+    let rv = unsafe { libc::listen(fd_server, 10) };
+    assert_eq!(rv, 0);
+
+    let fd_client = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+    assert!(fd_client >= 0);
+
+    // connect and send the data in a single call
+    let msg = b"hello";
+    let (msg_start, msg_end) = msg.split_at(3);
+    let mut iovs = [
+        libc::iovec {
+            iov_base: msg_start.as_ptr() as *mut libc::c_void,
+            iov_len: msg_start.len(),
+        },
+        libc::iovec {
+            iov_base: msg_end.as_ptr() as *mut libc::c_void,
+            iov_len: msg_end.len(),
+        },
+    ];
+    let hdr = libc::msghdr {
+        msg_name: server_addr.as_ptr() as *mut libc::c_void,
+        msg_namelen: server_addr_len,
+        msg_iov: iovs.as_mut_ptr(),
+        msg_iovlen: iovs.len(),
+        msg_control: std::ptr::null_mut(),
+        msg_controllen: 0,
+        msg_flags: 0,
+    };
+    let rv = unsafe { libc::sendmsg(fd_client, &hdr, libc::MSG_FASTOPEN) };
+    test_utils::result_assert_eq(rv, msg.len() as isize, "Unexpected sendmsg() return value")?;
+
+    let fd_accepted =
+        unsafe { libc::accept(fd_server, std::ptr::null_mut(), std::ptr::null_mut()) };
+    test_utils::result_assert(
+        fd_accepted >= 0,
+        &format!(
+            "accept() failed: {}",
+            test_utils::get_errno_message(test_utils::get_errno())
+        ),
+    )?;
+
+    // the data should be received on the first read
+    let mut buf = [0u8; 5];
+    let rv = unsafe {
+        libc::recv(
+            fd_accepted,
+            buf.as_mut_ptr() as *mut libc::c_void,
+            buf.len(),
+            0,
+        )
+    };
+    test_utils::result_assert_eq(rv, msg.len() as isize, "Unexpected recv() return value")?;
+    test_utils::result_assert_eq(&buf, msg, "Unexpected data received")?;
+
+    nix::unistd::close(fd_accepted).unwrap();
+    nix::unistd::close(fd_client).unwrap();
+    nix::unistd::close(fd_server).unwrap();
+
+    Ok(())
+}
+
+/// Check that the fd returned by accept is close-on-exec only if `SOCK_CLOEXEC` was requested.
+fn check_cloexec_flag(fd: libc::c_int, accept_flag: libc::c_int) -> Result<(), String> {
+    let fd_flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+    let expected_fd_flags = if accept_flag & libc::SOCK_CLOEXEC != 0 {
+        libc::FD_CLOEXEC
+    } else {
+        0
+    };
+
+    test_utils::result_assert_eq(fd_flags, expected_fd_flags, "Unexpected fd flags")
+}
+
 fn check_accept_call(
     args: &mut AcceptArguments,
     accept_fn: AcceptFn,
@@ -132,6 +132,17 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
         }
     }
 
+    for &sock_type in [libc::SOCK_STREAM, libc::SOCK_DGRAM, libc::SOCK_SEQPACKET].iter() {
+        // add details to the test names to avoid duplicates
+        let append_args = |s| format!("{} <type={}>", s, sock_type);
+
+        tests.extend(vec![test_utils::ShadowTest::new(
+            &append_args("test_unix_long_addr"),
+            move || test_unix_long_addr(sock_type),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        )]);
+    }
+
     // tests to repeat for different socket options
     for &sock_type in [libc::SOCK_STREAM, libc::SOCK_DGRAM].iter() {
         for &flag in [0, libc::SOCK_NONBLOCK, libc::SOCK_CLOEXEC].iter() {
@@ -302,6 +313,23 @@ fn test_short_addr(
     test_utils::run_and_close_fds(&[fd], || check_bind_call(&args, Some(libc::EINVAL)))
 }
 
+// test binding a unix socket with an address length that is longer than a unix address
+fn test_unix_long_addr(sock_type: libc::c_int) -> Result<(), String> {
+    let fd = unsafe { libc::socket(libc::AF_UNIX, sock_type, 0) };
+    assert!(fd >= 0);
+
+    let mut addr: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    addr.ss_family = libc::AF_UNIX as u16;
+
+    let args = BindArguments {
+        fd,
+        addr: Some(SockAddr::Generic(addr)),
+        addr_len: std::mem::size_of::<libc::sockaddr_un>() as u32 + 1,
+    };
+
+    test_utils::run_and_close_fds(&[fd], || check_bind_call(&args, Some(libc::EINVAL)))
+}
+
 // test binding an INET socket
 fn test_ipv4(sock_type: libc::c_int, flag: libc::c_int) -> Result<(), String> {
     let fd = unsafe { libc::socket(libc::AF_INET, sock_type | flag, 0) };
@@ -62,8 +62,13 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
         test_utils::ShadowTest::new(
-            "test_short_len",
-            test_short_len,
+            "test_short_len <type=SOCK_STREAM>",
+            || test_short_len(libc::SOCK_STREAM),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_short_len <type=SOCK_DGRAM>",
+            || test_short_len(libc::SOCK_DGRAM),
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
         test_utils::ShadowTest::new(
@@ -88,12 +93,20 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
                 test_utils::ShadowTest::new(
                     &append_args("test_non_existent_server"),
                     move || test_non_existent_server(sock_type, flag),
-                    set![TestEnv::Libc],
+                    if sock_type == libc::SOCK_STREAM {
+                        set![TestEnv::Libc, TestEnv::Shadow]
+                    } else {
+                        set![TestEnv::Libc]
+                    },
                 ),
                 test_utils::ShadowTest::new(
                     &append_args("test_port_zero"),
                     move || test_port_zero(sock_type, flag),
-                    set![TestEnv::Libc],
+                    if sock_type == libc::SOCK_STREAM {
+                        set![TestEnv::Libc, TestEnv::Shadow]
+                    } else {
+                        set![TestEnv::Libc]
+                    },
                 ),
                 test_utils::ShadowTest::new(
                     &append_args("test_after_close"),
@@ -189,11 +202,18 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
             // add details to the test names to avoid duplicates
             let append_args = |s| format!("{} <type={},flag={}>", s, sock_type, flag);
 
-            tests.extend(vec![test_utils::ShadowTest::new(
-                &append_args("test_loopback_listening_connect"),
-                move || test_loopback_listening_connect(sock_type, flag),
-                set![TestEnv::Libc, TestEnv::Shadow],
-            )]);
+            tests.extend(vec![
+                test_utils::ShadowTest::new(
+                    &append_args("test_loopback_listening_connect"),
+                    move || test_loopback_listening_connect(sock_type, flag),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ),
+                test_utils::ShadowTest::new(
+                    &append_args("test_bound_not_listening"),
+                    move || test_bound_not_listening(sock_type, flag),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ),
+            ]);
         }
     }
 
@@ -219,6 +239,17 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
         };
 
         for &sock_type in sock_types.iter() {
+            if domain == libc::AF_UNIX {
+                // add details to the test names to avoid duplicates
+                let append_args = |s| format!("{} <domain={},type={}>", s, domain, sock_type);
+
+                tests.extend(vec![test_utils::ShadowTest::new(
+                    &append_args("test_unix_long_len"),
+                    move || test_unix_long_len(sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                )]);
+            }
+
             for &flag in [0, libc::SOCK_NONBLOCK, libc::SOCK_CLOEXEC].iter() {
                 // add details to the test names to avoid duplicates
                 let append_args =
@@ -387,8 +418,8 @@ fn test_null_addr() -> Result<(), String> {
 }
 
 /// Test connect() using a valid fd and address, but an address length that is too low.
-fn test_short_len() -> Result<(), String> {
-    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+fn test_short_len(sock_type: libc::c_int) -> Result<(), String> {
+    let fd = unsafe { libc::socket(libc::AF_INET, sock_type, 0) };
     assert!(fd >= 0);
 
     let addr = libc::sockaddr_in {
@@ -409,6 +440,23 @@ fn test_short_len() -> Result<(), String> {
     test_utils::run_and_close_fds(&[fd], || check_connect_call(&args, Some(libc::EINVAL)))
 }
 
+/// Test connect() on a unix socket using an address length that is longer than a unix address.
+fn test_unix_long_len(sock_type: libc::c_int) -> Result<(), String> {
+    let fd = unsafe { libc::socket(libc::AF_UNIX, sock_type, 0) };
+    assert!(fd >= 0);
+
+    let mut addr: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    addr.ss_family = libc::AF_UNIX as u16;
+
+    let args = ConnectArguments {
+        fd,
+        addr: Some(SockAddr::Generic(addr)),
+        addr_len: std::mem::size_of::<libc::sockaddr_un>() as u32 + 1,
+    };
+
+    test_utils::run_and_close_fds(&[fd], || check_connect_call(&args, Some(libc::EINVAL)))
+}
+
 /// Test connect() using a valid fd and address, but an address length that is zero.
 fn test_zero_len() -> Result<(), String> {
     let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
@@ -1272,6 +1320,51 @@ fn test_loopback_listening_connect(
     check_connect_call(&args, Some(libc::EISCONN))
 }
 
+/// Test connect() to a socket that is bound but not listening.
+fn test_bound_not_listening(sock_type: libc::c_int, flag: libc::c_int) -> Result<(), String> {
+    let fd_server = unsafe { libc::socket(libc::AF_INET, sock_type, 0) };
+    let fd_client = unsafe { libc::socket(libc::AF_INET, sock_type | flag, 0) };
+    assert!(fd_server >= 0);
+    assert!(fd_client >= 0);
+
+    // bind the server socket, but don't listen
+    let (server_addr, server_addr_len) = socket_utils::autobind_helper(fd_server, libc::AF_INET);
+
+    let args = ConnectArguments {
+        fd: fd_client,
+        addr: Some(server_addr),
+        addr_len: server_addr_len,
+    };
+
+    test_utils::run_and_close_fds(&[fd_server, fd_client], || {
+        if flag & libc::SOCK_NONBLOCK == 0 {
+            check_connect_call(&args, Some(libc::ECONNREFUSED))?;
+
+            // the error was already returned by connect()
+            let error =
+                nix::sys::socket::getsockopt(fd_client, nix::sys::socket::sockopt::SocketError)
+                    .map_err(|e| e.to_string())?;
+            return test_utils::result_assert_eq(error, 0, "Unexpected socket error");
+        }
+
+        check_connect_call(&args, Some(libc::EINPROGRESS))?;
+
+        // the connection should fail asynchronously
+        let mut poll_fds = [nix::poll::PollFd::new(
+            fd_client,
+            nix::poll::PollFlags::POLLOUT,
+        )];
+        let count = nix::poll::poll(&mut poll_fds, 2000).map_err(|e| e.to_string())?;
+        test_utils::result_assert_eq(count, 1, "Unexpected number of ready fds")?;
+
+        let error = nix::sys::socket::getsockopt(fd_client, nix::sys::socket::sockopt::SocketError)
+            .map_err(|e| e.to_string())?;
+        test_utils::result_assert_eq(error, libc::ECONNREFUSED, "Unexpected socket error")?;
+
+        Ok(())
+    })
+}
+
 fn check_connect_call(
     args: &ConnectArguments,
     expected_errno: Option<libc::c_int>,
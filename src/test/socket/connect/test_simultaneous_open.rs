@@ -0,0 +1,114 @@
+//! Tests a TCP simultaneous open. Two instances of this program should be run on different hosts at
+//! the same simulated time, each connecting to the other. Both connections should be established.
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+
+    // args are the local port, the peer's IPv4 address, and the peer's port
+    let local_port: u16 = args.next().unwrap().parse().unwrap();
+    let peer_ip: std::net::Ipv4Addr = args.next().unwrap().parse().unwrap();
+    let peer_port: u16 = args.next().unwrap().parse().unwrap();
+
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM | libc::SOCK_NONBLOCK, 0) };
+    assert!(fd >= 0);
+
+    let local_addr = libc::sockaddr_in {
+        sin_family: libc::AF_INET as u16,
+        sin_port: local_port.to_be(),
+        sin_addr: libc::in_addr {
+            s_addr: libc::INADDR_ANY.to_be(),
+        },
+        sin_zero: [0; 8],
+    };
+
+    let rv = unsafe {
+        libc::bind(
+            fd,
+            std::ptr::from_ref(&local_addr) as *const libc::sockaddr,
+            std::mem::size_of_val(&local_addr) as u32,
+        )
+    };
+    assert_eq!(rv, 0);
+
+    let peer_addr = libc::sockaddr_in {
+        sin_family: libc::AF_INET as u16,
+        sin_port: peer_port.to_be(),
+        sin_addr: libc::in_addr {
+            s_addr: u32::from(peer_ip).to_be(),
+        },
+        sin_zero: [0; 8],
+    };
+
+    let rv = unsafe {
+        libc::connect(
+            fd,
+            std::ptr::from_ref(&peer_addr) as *const libc::sockaddr,
+            std::mem::size_of_val(&peer_addr) as u32,
+        )
+    };
+    assert_eq!(rv, -1);
+    assert_eq!(
+        std::io::Error::last_os_error().raw_os_error(),
+        Some(libc::EINPROGRESS)
+    );
+
+    // wait for the connection to complete
+    let mut pollfd = libc::pollfd {
+        fd,
+        events: libc::POLLOUT,
+        revents: 0,
+    };
+    let rv = unsafe { libc::poll(&mut pollfd, 1, 2000) };
+    assert_eq!(rv, 1);
+    assert_eq!(pollfd.revents & (libc::POLLERR | libc::POLLHUP), 0);
+    assert_ne!(pollfd.revents & libc::POLLOUT, 0);
+
+    // the connection should have succeeded
+    let mut error: libc::c_int = -1;
+    let mut error_len = std::mem::size_of_val(&error) as libc::socklen_t;
+    let rv = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_ERROR,
+            std::ptr::from_mut(&mut error) as *mut libc::c_void,
+            &mut error_len,
+        )
+    };
+    assert_eq!(rv, 0);
+    assert_eq!(error, 0);
+
+    // we should be connected to the peer
+    let mut addr: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+    let mut addr_len = std::mem::size_of_val(&addr) as libc::socklen_t;
+    let rv = unsafe {
+        libc::getpeername(
+            fd,
+            std::ptr::from_mut(&mut addr) as *mut libc::sockaddr,
+            &mut addr_len,
+        )
+    };
+    assert_eq!(rv, 0);
+    assert_eq!(addr.sin_port, peer_addr.sin_port);
+    assert_eq!(addr.sin_addr.s_addr, peer_addr.sin_addr.s_addr);
+
+    // make sure that data can be exchanged in both directions
+    let msg = local_port.to_be_bytes();
+    let rv = unsafe { libc::send(fd, msg.as_ptr() as *const libc::c_void, msg.len(), 0) };
+    assert_eq!(rv, msg.len() as isize);
+
+    let mut pollfd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let rv = unsafe { libc::poll(&mut pollfd, 1, 2000) };
+    assert_eq!(rv, 1);
+
+    let mut buf = [0u8; 2];
+    let rv = unsafe { libc::recv(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+    assert_eq!(rv, buf.len() as isize);
+    assert_eq!(buf, peer_port.to_be_bytes());
+
+    assert_eq!(unsafe { libc::close(fd) }, 0);
+}
@@ -56,6 +56,16 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
             test_non_socket_fd,
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
+        test_utils::ShadowTest::new(
+            "test_invalid_fd_null_ptrs",
+            test_invalid_fd_null_ptrs,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_non_socket_fd_null_ptrs",
+            test_non_socket_fd_null_ptrs,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
         test_utils::ShadowTest::new(
             "test_short_len_inet",
             test_short_len_inet,
@@ -94,6 +104,11 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
                     move || test_non_connected_fd(domain, sock_type),
                     set![TestEnv::Libc, TestEnv::Shadow],
                 ),
+                test_utils::ShadowTest::new(
+                    &append_args("test_non_connected_fd_null_ptrs"),
+                    move || test_non_connected_fd_null_ptrs(domain, sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ),
                 test_utils::ShadowTest::new(
                     &append_args("test_unbound_socket"),
                     move || test_unbound_socket(domain, sock_type),
@@ -106,6 +121,21 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
                 ),
             ]);
 
+            if domain == libc::AF_UNIX {
+                tests.extend(vec![
+                    test_utils::ShadowTest::new(
+                        &append_args("test_unix_passcred_autobind <pass_cred=false>"),
+                        move || test_unix_passcred_autobind(sock_type, false),
+                        set![TestEnv::Libc, TestEnv::Shadow],
+                    ),
+                    test_utils::ShadowTest::new(
+                        &append_args("test_unix_passcred_autobind <pass_cred=true>"),
+                        move || test_unix_passcred_autobind(sock_type, true),
+                        set![TestEnv::Libc, TestEnv::Shadow],
+                    ),
+                ]);
+            }
+
             // if a connection-based socket
             if [libc::SOCK_STREAM, libc::SOCK_SEQPACKET].contains(&sock_type) {
                 tests.extend(vec![
@@ -158,6 +188,16 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
                     move || test_zero_len(method, sock_type),
                     set![TestEnv::Libc, TestEnv::Shadow],
                 ),
+                test_utils::ShadowTest::new(
+                    &append_args("test_null_addr_zero_len"),
+                    move || test_null_addr_zero_len(method, sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ),
+                test_utils::ShadowTest::new(
+                    &append_args("test_negative_len"),
+                    move || test_negative_len(method, sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ),
                 test_utils::ShadowTest::new(
                     &append_args("test_after_close"),
                     move || test_after_close(method, sock_type),
@@ -241,6 +281,30 @@ fn test_non_socket_fd() -> Result<(), String> {
     check_getpeername_call(&mut args, Some(libc::ENOTSOCK))
 }
 
+/// Test getpeername using an argument that cannot be a fd, and NULL pointers. The EBADF should be
+/// returned before the EFAULT.
+fn test_invalid_fd_null_ptrs() -> Result<(), String> {
+    let mut args = GetpeernameArguments {
+        fd: -1,
+        addr: None,
+        addr_len: None,
+    };
+
+    check_getpeername_call(&mut args, Some(libc::EBADF))
+}
+
+/// Test getpeername using a valid fd that is not a socket, and NULL pointers. The ENOTSOCK should be
+/// returned before the EFAULT.
+fn test_non_socket_fd_null_ptrs() -> Result<(), String> {
+    let mut args = GetpeernameArguments {
+        fd: 0, // assume the fd 0 is already open and is not a socket
+        addr: None,
+        addr_len: None,
+    };
+
+    check_getpeername_call(&mut args, Some(libc::ENOTSOCK))
+}
+
 /// Test getpeername using a valid fd, but that is not connected to a peer.
 fn test_non_connected_fd(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
     let fd = unsafe { libc::socket(domain, sock_type, 0) };
@@ -260,6 +324,26 @@ fn test_non_connected_fd(domain: libc::c_int, sock_type: libc::c_int) -> Result<
     })
 }
 
+/// Test getpeername using a valid fd that is not connected to a peer, and NULL pointers. The
+/// ENOTCONN should be returned before the EFAULT.
+fn test_non_connected_fd_null_ptrs(
+    domain: libc::c_int,
+    sock_type: libc::c_int,
+) -> Result<(), String> {
+    let fd = unsafe { libc::socket(domain, sock_type, 0) };
+    assert!(fd >= 0);
+
+    let mut args = GetpeernameArguments {
+        fd,
+        addr: None,
+        addr_len: None,
+    };
+
+    test_utils::run_and_close_fds(&[fd], || {
+        check_getpeername_call(&mut args, Some(libc::ENOTCONN))
+    })
+}
+
 /// Test getpeername using a valid fd, but with a NULL address.
 fn test_null_addr(method: SocketInitMethod, sock_type: libc::c_int) -> Result<(), String> {
     let (fd_client, fd_peer) =
@@ -409,6 +493,52 @@ fn test_zero_len(method: SocketInitMethod, sock_type: libc::c_int) -> Result<(),
     )
 }
 
+/// Test getpeername using a valid fd, a NULL address, and an address length of 0. The address
+/// isn't accessed, so there should be no error.
+fn test_null_addr_zero_len(method: SocketInitMethod, sock_type: libc::c_int) -> Result<(), String> {
+    let (fd_client, fd_peer) =
+        socket_init_helper(method, sock_type, 0, /* bind_client= */ false);
+
+    // getpeername() may mutate addr and addr_len
+    let mut args = GetpeernameArguments {
+        fd: fd_client,
+        addr: None,
+        addr_len: Some(0),
+    };
+
+    test_utils::run_and_close_fds(&[fd_client, fd_peer], || {
+        check_getpeername_call(&mut args, None)
+    })?;
+
+    // the real address length should still be returned
+    test_utils::result_assert_gt(args.addr_len.unwrap(), 0, "Address length was not set")
+}
+
+/// Test getpeername using a valid fd and an address length that is negative when interpreted as an
+/// int.
+fn test_negative_len(method: SocketInitMethod, sock_type: libc::c_int) -> Result<(), String> {
+    let (fd_client, fd_peer) =
+        socket_init_helper(method, sock_type, 0, /* bind_client= */ false);
+
+    // use a NULL address so that we don't need a buffer of this size
+    let mut args = GetpeernameArguments {
+        fd: fd_client,
+        addr: None,
+        addr_len: Some(u32::MAX),
+    };
+
+    test_utils::run_and_close_fds(&[fd_client, fd_peer], || {
+        check_getpeername_call(&mut args, Some(libc::EINVAL))
+    })?;
+
+    // the length should not have been changed
+    test_utils::result_assert_eq(
+        args.addr_len.unwrap(),
+        u32::MAX,
+        "Address length was changed",
+    )
+}
+
 /// Test getpeername on a listening socket.
 fn test_listening_socket(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
     let fd_client = unsafe { libc::socket(domain, sock_type, 0) };
@@ -628,6 +758,119 @@ fn test_connected_before_accepted(
     Ok(())
 }
 
+/// Test that an unbound unix socket with `SO_PASSCRED` enabled is autobound when it connects, so
+/// that the server sees a non-empty peer address. Without `SO_PASSCRED` the peer is unnamed.
+fn test_unix_passcred_autobind(sock_type: libc::c_int, pass_cred: bool) -> Result<(), String> {
+    let fd_client = unsafe { libc::socket(libc::AF_UNIX, sock_type, 0) };
+    let fd_server = unsafe { libc::socket(libc::AF_UNIX, sock_type, 0) };
+    assert!(fd_client >= 0);
+    assert!(fd_server >= 0);
+
+    let optval = pass_cred as libc::c_int;
+    let rv = unsafe {
+        libc::setsockopt(
+            fd_client,
+            libc::SOL_SOCKET,
+            libc::SO_PASSCRED,
+            std::ptr::from_ref(&optval) as *const libc::c_void,
+            std::mem::size_of_val(&optval) as libc::socklen_t,
+        )
+    };
+    assert_eq!(rv, 0);
+
+    let (server_addr, server_addr_len) = autobind_helper(fd_server, libc::AF_UNIX);
+
+    let mut fds = vec![fd_client, fd_server];
+
+    // the client's address as seen by the server
+    let mut addr: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+    let mut addr_len = std::mem::size_of_val(&addr) as libc::socklen_t;
+
+    if sock_type == libc::SOCK_DGRAM {
+        let rv = unsafe { libc::connect(fd_client, server_addr.as_ptr(), server_addr_len) };
+        assert_eq!(rv, 0);
+
+        let rv = unsafe { libc::send(fd_client, [1u8].as_ptr() as *const libc::c_void, 1, 0) };
+        assert_eq!(rv, 1);
+
+        let mut buf = [0u8; 1];
+        let rv = unsafe {
+            libc::recvfrom(
+                fd_server,
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+                0,
+                std::ptr::from_mut(&mut addr) as *mut libc::sockaddr,
+                &mut addr_len,
+            )
+        };
+        assert_eq!(rv, 1);
+    } else {
+        let fd_peer = stream_connect_helper(
+            fd_client,
+            fd_server,
+            server_addr,
+            server_addr_len,
+            /* flags= */ 0,
+        );
+        fds.push(fd_peer);
+
+        let rv = unsafe {
+            libc::getpeername(
+                fd_peer,
+                std::ptr::from_mut(&mut addr) as *mut libc::sockaddr,
+                &mut addr_len,
+            )
+        };
+        assert_eq!(rv, 0);
+    }
+
+    test_utils::run_and_close_fds(&fds, || {
+        test_utils::result_assert_eq(
+            addr.sun_family,
+            libc::AF_UNIX as u16,
+            "Address family was not AF_UNIX",
+        )?;
+
+        if !pass_cred {
+            // address family only
+            return test_utils::result_assert_eq(addr_len, 2, "Unexpected addr length");
+        }
+
+        test_utils::result_assert_eq(
+            addr_len,
+            // address family + null byte + 5-byte abstract address (see unix(7))
+            2 + 1 + 5,
+            "Unexpected addr length",
+        )?;
+
+        test_utils::result_assert_eq(
+            addr.sun_path[0],
+            0,
+            "Abstract socket name did not begin with null byte",
+        )?;
+
+        // the server should see the same address that the client was autobound to
+        let mut client_addr: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+        let mut client_addr_len = std::mem::size_of_val(&client_addr) as libc::socklen_t;
+        let rv = unsafe {
+            libc::getsockname(
+                fd_client,
+                std::ptr::from_mut(&mut client_addr) as *mut libc::sockaddr,
+                &mut client_addr_len,
+            )
+        };
+        assert_eq!(rv, 0);
+
+        test_utils::result_assert_eq(addr_len, client_addr_len, "Unexpected addr length")?;
+        test_utils::result_assert_eq(
+            &addr.sun_path[..6],
+            &client_addr.sun_path[..6],
+            "Unexpected address",
+        )
+    })
+}
+
 /// Test getpeername using a socket connected on loopback.
 fn test_connected_socket(
     method: SocketInitMethod,
@@ -55,11 +55,26 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
             test_non_socket_fd,
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
+        test_utils::ShadowTest::new(
+            "test_invalid_fd_null_ptrs",
+            test_invalid_fd_null_ptrs,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_non_socket_fd_null_ptrs",
+            test_non_socket_fd_null_ptrs,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
         test_utils::ShadowTest::new(
             "test_short_len_inet",
             test_short_len_inet,
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
+        test_utils::ShadowTest::new(
+            "test_implicit_bind_sendto_reply_udp",
+            test_implicit_bind_sendto_reply_udp,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
     ]);
 
     let domains = [libc::AF_INET, libc::AF_UNIX];
@@ -91,6 +106,11 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
                     move || test_autobound_socket(domain, sock_type),
                     set![TestEnv::Libc, TestEnv::Shadow],
                 ),
+                test_utils::ShadowTest::new(
+                    &append_args("test_tiny_len"),
+                    move || test_tiny_len(domain, sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ),
                 test_utils::ShadowTest::new(
                     &append_args("test_after_close"),
                     move || test_after_close(domain, sock_type),
@@ -152,6 +172,16 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
                     move || test_zero_len(method, sock_type),
                     set![TestEnv::Libc, TestEnv::Shadow],
                 ),
+                test_utils::ShadowTest::new(
+                    &append_args("test_null_addr_zero_len"),
+                    move || test_null_addr_zero_len(method, sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ),
+                test_utils::ShadowTest::new(
+                    &append_args("test_negative_len"),
+                    move || test_negative_len(method, sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ),
             ]);
         }
     }
@@ -201,6 +231,30 @@ fn test_non_socket_fd() -> Result<(), String> {
     check_getsockname_call(&mut args, Some(libc::ENOTSOCK))
 }
 
+/// Test getsockname using an argument that cannot be a fd, and NULL pointers. The EBADF should be
+/// returned before the EFAULT.
+fn test_invalid_fd_null_ptrs() -> Result<(), String> {
+    let mut args = GetsocknameArguments {
+        fd: -1,
+        addr: None,
+        addr_len: None,
+    };
+
+    check_getsockname_call(&mut args, Some(libc::EBADF))
+}
+
+/// Test getsockname using a valid fd that is not a socket, and NULL pointers. The ENOTSOCK should be
+/// returned before the EFAULT.
+fn test_non_socket_fd_null_ptrs() -> Result<(), String> {
+    let mut args = GetsocknameArguments {
+        fd: 0, // assume the fd 0 is already open and is not a socket
+        addr: None,
+        addr_len: None,
+    };
+
+    check_getsockname_call(&mut args, Some(libc::ENOTSOCK))
+}
+
 /// Test getsockname using a valid fd, but with a NULL address.
 fn test_null_addr(method: SocketInitMethod, sock_type: libc::c_int) -> Result<(), String> {
     let fd = socket_helper(method, sock_type);
@@ -320,6 +374,48 @@ fn test_zero_len(method: SocketInitMethod, sock_type: libc::c_int) -> Result<(),
     test_utils::result_assert_eq(args.addr.unwrap(), expected_addr, "Address was changed")
 }
 
+/// Test getsockname using a valid fd, a NULL address, and an address length of 0. The address
+/// isn't accessed, so there should be no error.
+fn test_null_addr_zero_len(method: SocketInitMethod, sock_type: libc::c_int) -> Result<(), String> {
+    let fd = socket_helper(method, sock_type);
+
+    // getsockname() may mutate addr and addr_len
+    let mut args = GetsocknameArguments {
+        fd,
+        addr: None,
+        addr_len: Some(0),
+    };
+
+    test_utils::run_and_close_fds(&[fd], || check_getsockname_call(&mut args, None))?;
+
+    // the real address length should still be returned
+    test_utils::result_assert_gt(args.addr_len.unwrap(), 0, "Address length was not set")
+}
+
+/// Test getsockname using a valid fd and an address length that is negative when interpreted as an
+/// int.
+fn test_negative_len(method: SocketInitMethod, sock_type: libc::c_int) -> Result<(), String> {
+    let fd = socket_helper(method, sock_type);
+
+    // use a NULL address so that we don't need a buffer of this size
+    let mut args = GetsocknameArguments {
+        fd,
+        addr: None,
+        addr_len: Some(u32::MAX),
+    };
+
+    test_utils::run_and_close_fds(&[fd], || {
+        check_getsockname_call(&mut args, Some(libc::EINVAL))
+    })?;
+
+    // the length should not have been changed
+    test_utils::result_assert_eq(
+        args.addr_len.unwrap(),
+        u32::MAX,
+        "Address length was changed",
+    )
+}
+
 /// Test getsockname using an unbound socket.
 fn test_unbound_socket(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
     let fd = unsafe { libc::socket(domain, sock_type, 0) };
@@ -538,6 +634,55 @@ fn test_autobound_socket(domain: libc::c_int, sock_type: libc::c_int) -> Result<
     Ok(())
 }
 
+/// Test getsockname using a bound socket and a 4-byte address buffer. The address should be
+/// truncated to fit the buffer, but the returned address length should be the full length.
+fn test_tiny_len(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    let fd = unsafe { libc::socket(domain, sock_type, 0) };
+    assert!(fd >= 0);
+
+    // the full address that was assigned
+    let (bound_addr, bound_addr_len) = autobind_helper(fd, domain);
+
+    // fill the sockaddr with dummy data
+    let addr = match domain {
+        libc::AF_INET => SockAddr::dummy_init_inet(),
+        libc::AF_UNIX => SockAddr::dummy_init_unix(),
+        _ => unimplemented!(),
+    };
+
+    // getsockname() may mutate addr and addr_len
+    let mut args = GetsocknameArguments {
+        fd,
+        addr: Some(addr),
+        addr_len: Some(4),
+    };
+
+    test_utils::run_and_close_fds(&[fd], || check_getsockname_call(&mut args, None))?;
+
+    // check that the returned length is the full length, not the buffer length
+    test_utils::result_assert_eq(
+        args.addr_len.unwrap(),
+        bound_addr_len,
+        "Unexpected addr length",
+    )?;
+
+    let returned = args.addr.unwrap();
+
+    // check that only the first 4 bytes of the address were written
+    test_utils::result_assert_eq(
+        &returned.as_slice()[..4],
+        &bound_addr.as_slice()[..4],
+        "Unexpected address prefix",
+    )?;
+    test_utils::result_assert_eq(
+        &returned.as_slice()[4..],
+        &addr.as_slice()[4..],
+        "Bytes after the buffer should not have changed",
+    )?;
+
+    Ok(())
+}
+
 /// Test getsockname after closing the bound socket.
 fn test_after_close(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
     let fd = unsafe { libc::socket(domain, sock_type, 0) };
@@ -795,6 +940,100 @@ fn test_implicit_bind_sendto(domain: libc::c_int, sock_type: libc::c_int) -> Res
     Ok(())
 }
 
+/// Test that the port returned by getsockname after an implicit bind from sendto() is the source
+/// port seen by the peer, and that the peer can reply to it.
+fn test_implicit_bind_sendto_reply_udp() -> Result<(), String> {
+    let fd_client = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+    let fd_server = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+    assert!(fd_client >= 0);
+    assert!(fd_server >= 0);
+
+    test_utils::run_and_close_fds(&[fd_client, fd_server], || {
+        let (server_addr, server_addr_len) = autobind_helper(fd_server, libc::AF_INET);
+
+        // send a message to the server without binding the client
+        let msg = [1u8, 2, 3];
+        test_utils::check_system_call!(
+            || unsafe {
+                libc::sendto(
+                    fd_client,
+                    msg.as_ptr() as *const core::ffi::c_void,
+                    msg.len(),
+                    0,
+                    server_addr.as_ptr(),
+                    server_addr_len,
+                )
+            },
+            &[],
+        )?;
+
+        let mut client_addr = SockAddr::dummy_init_inet();
+        let mut client_addr_len = client_addr.ptr_size();
+        test_utils::check_system_call!(
+            || unsafe {
+                libc::getsockname(fd_client, client_addr.as_mut_ptr(), &mut client_addr_len)
+            },
+            &[],
+        )?;
+        let client_port = client_addr.as_inet().unwrap().sin_port;
+        test_utils::result_assert(client_port != 0, "Client was not bound to a port")?;
+
+        // the server should see the message as coming from the client's local port
+        let mut buf = [0u8; 10];
+        let mut from_addr = SockAddr::dummy_init_inet();
+        let mut from_addr_len = from_addr.ptr_size();
+        let rv = test_utils::check_system_call!(
+            || unsafe {
+                libc::recvfrom(
+                    fd_server,
+                    buf.as_mut_ptr() as *mut core::ffi::c_void,
+                    buf.len(),
+                    0,
+                    from_addr.as_mut_ptr(),
+                    &mut from_addr_len,
+                )
+            },
+            &[],
+        )?;
+        test_utils::result_assert_eq(rv, msg.len() as isize, "Unexpected message length")?;
+        test_utils::result_assert_eq(
+            from_addr.as_inet().unwrap().sin_port,
+            client_port,
+            "Source port doesn't match getsockname()",
+        )?;
+
+        // reply to the address the message came from
+        test_utils::check_system_call!(
+            || unsafe {
+                libc::sendto(
+                    fd_server,
+                    msg.as_ptr() as *const core::ffi::c_void,
+                    msg.len(),
+                    0,
+                    from_addr.as_ptr(),
+                    from_addr_len,
+                )
+            },
+            &[],
+        )?;
+
+        let rv = test_utils::check_system_call!(
+            || unsafe {
+                libc::recv(
+                    fd_client,
+                    buf.as_mut_ptr() as *mut core::ffi::c_void,
+                    buf.len(),
+                    0,
+                )
+            },
+            &[],
+        )?;
+        test_utils::result_assert_eq(&buf[..rv as usize], &msg[..], "Unexpected reply")?;
+
+        Ok(())
+    })
+}
+
 /// Test getsockname using a listening socket without binding (an implicit bind).
 fn test_implicit_bind_listen(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
     let fd = unsafe { libc::socket(domain, sock_type, 0) };
@@ -68,15 +68,15 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
                 |s| format!("{s} <init_method={init_method:?}, sock_type={sock_type}>");
 
             tests.extend(vec![
+                test_utils::ShadowTest::new(
+                    &append_args("test_fioclex"),
+                    move || test_fioclex(init_method, sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ),
                 test_utils::ShadowTest::new(
                     &append_args("test_fionread"),
                     move || test_fionread(init_method, sock_type),
-                    // TODO: this isn't supported yet in shadow for unix sockets
-                    if init_method.domain() == libc::AF_UNIX {
-                        set![TestEnv::Libc]
-                    } else {
-                        set![TestEnv::Libc, TestEnv::Shadow]
-                    },
+                    set![TestEnv::Libc, TestEnv::Shadow],
                 ),
                 test_utils::ShadowTest::new(
                     &append_args("test_siocgstamp"),
@@ -89,6 +89,16 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
                     },
                 ),
             ]);
+
+            // inet sockets send their data right away, so the send queue is only non-empty for
+            // unix sockets
+            if init_method.domain() == libc::AF_UNIX {
+                tests.push(test_utils::ShadowTest::new(
+                    &append_args("test_siocoutq"),
+                    move || test_siocoutq(init_method, sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ));
+            }
         }
     }
 
@@ -140,6 +150,111 @@ fn test_tty(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
     })
 }
 
+/// Test ioctl() using the `FIOCLEX` and `FIONCLEX` ioctl requests, which change the descriptor's
+/// close-on-exec flag rather than the socket.
+fn test_fioclex(init_method: SocketInitMethod, sock_type: libc::c_int) -> Result<(), String> {
+    let (fd_client, fd_peer) =
+        socket_init_helper(init_method, sock_type, 0, /* bind_client = */ false);
+
+    let get_fd_flags = |fd| {
+        let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+        assert!(flags >= 0);
+        flags
+    };
+
+    test_utils::run_and_close_fds(&[fd_client, fd_peer], || {
+        test_utils::result_assert_eq(get_fd_flags(fd_client), 0, "Unexpected initial flags")?;
+
+        let rv = unsafe { libc::ioctl(fd_client, libc::FIOCLEX) };
+        test_utils::result_assert_eq(rv, 0, "FIOCLEX failed")?;
+        test_utils::result_assert_eq(
+            get_fd_flags(fd_client),
+            libc::FD_CLOEXEC,
+            "Expected FD_CLOEXEC after FIOCLEX",
+        )?;
+
+        // only the descriptor's flag should have changed
+        test_utils::result_assert_eq(get_fd_flags(fd_peer), 0, "Peer flags changed")?;
+
+        let rv = unsafe { libc::ioctl(fd_client, libc::FIONCLEX) };
+        test_utils::result_assert_eq(rv, 0, "FIONCLEX failed")?;
+        test_utils::result_assert_eq(
+            get_fd_flags(fd_client),
+            0,
+            "Expected no FD_CLOEXEC after FIONCLEX",
+        )?;
+
+        Ok(())
+    })
+}
+
+/// Test ioctl() using the `SIOCOUTQ` and `SIOCINQ` ioctl requests while the receiving socket isn't
+/// reading.
+fn test_siocoutq(init_method: SocketInitMethod, sock_type: libc::c_int) -> Result<(), String> {
+    let (fd_client, fd_peer) =
+        socket_init_helper(init_method, sock_type, 0, /* bind_client = */ false);
+
+    let ioctl_int = |fd, request| {
+        let mut out: libc::c_int = 0;
+        let rv = unsafe { libc::ioctl(fd, request, &mut out) };
+        if rv != 0 {
+            let errno = test_utils::get_errno();
+            return Err(format!("Failed ioctl with errno {errno}"));
+        }
+        Ok(out)
+    };
+
+    // `SIOCOUTQ` and `SIOCINQ` have the same values as `TIOCOUTQ` and `FIONREAD`
+    let siocoutq = |fd| ioctl_int(fd, libc::TIOCOUTQ);
+    let siocinq = |fd| ioctl_int(fd, libc::FIONREAD);
+
+    test_utils::run_and_close_fds(&[fd_client, fd_peer], || {
+        test_utils::result_assert_eq(siocoutq(fd_client)?, 0, "Unexpected SIOCOUTQ result")?;
+
+        // send 9 bytes to the peer, split among multiple send() calls
+        let flags = nix::sys::socket::MsgFlags::empty();
+        nix::sys::socket::send(fd_client, &[1, 2, 3], flags).unwrap();
+        nix::sys::socket::send(fd_client, &[4, 5], flags).unwrap();
+        nix::sys::socket::send(fd_client, &[6, 7, 8, 9], flags).unwrap();
+
+        // shadow needs to run events
+        std::thread::sleep(std::time::Duration::from_millis(10));
+
+        // linux includes its buffer overhead in the SIOCOUTQ result, so it may be larger than the
+        // number of bytes sent
+        let outq = siocoutq(fd_client)?;
+        test_utils::result_assert(outq >= 9, &format!("Unexpected SIOCOUTQ result {outq}"))?;
+
+        // dgram sockets only return the number of bytes in the next message
+        let peer_expected_result = match sock_type {
+            libc::SOCK_STREAM | libc::SOCK_SEQPACKET => 9,
+            libc::SOCK_DGRAM => 3,
+            _ => unimplemented!(),
+        };
+        test_utils::result_assert_eq(
+            siocinq(fd_peer)?,
+            peer_expected_result,
+            "Unexpected SIOCINQ result",
+        )?;
+
+        // read everything at the peer
+        let mut buf = [0u8; 9];
+        let mut num_read = 0;
+        while num_read < 9 {
+            let flags = nix::sys::socket::MsgFlags::MSG_DONTWAIT;
+            num_read += nix::sys::socket::recv(fd_peer, &mut buf[num_read..], flags).unwrap();
+        }
+
+        // shadow needs to run events
+        std::thread::sleep(std::time::Duration::from_millis(10));
+
+        test_utils::result_assert_eq(siocoutq(fd_client)?, 0, "Unexpected SIOCOUTQ result")?;
+        test_utils::result_assert_eq(siocinq(fd_peer)?, 0, "Unexpected SIOCINQ result")?;
+
+        Ok(())
+    })
+}
+
 /// Test ioctl() using the `FIONREAD` ioctl request.
 fn test_fionread(init_method: SocketInitMethod, sock_type: libc::c_int) -> Result<(), String> {
     let (fd_client, fd_peer) =
@@ -199,7 +314,7 @@ fn test_fionread(init_method: SocketInitMethod, sock_type: libc::c_int) -> Resul
     })
 }
 
-/// Test ioctl() using the `SIOCGSTAMP` ioctl request.
+/// Test ioctl() using the `SIOCGSTAMP` and `SIOCGSTAMPNS` ioctl requests.
 fn test_siocgstamp(init_method: SocketInitMethod, sock_type: libc::c_int) -> Result<(), String> {
     let (fd_client, fd_peer) = socket_init_helper(
         init_method,
@@ -221,6 +336,19 @@ fn test_siocgstamp(init_method: SocketInitMethod, sock_type: libc::c_int) -> Res
         Ok(out)
     }
 
+    /// Returns the value if successful, otherwise returns the errno.
+    fn ioctl_siocgstampns(fd: libc::c_int) -> Result<libc::timespec, libc::c_int> {
+        // not currently available in the libc crate
+        use linux_api::ioctls::IoctlRequest::SIOCGSTAMPNS;
+
+        let mut out: libc::timespec = unsafe { std::mem::zeroed() };
+        let rv = unsafe { libc::ioctl(fd, SIOCGSTAMPNS as u64, &mut out) };
+        if rv != 0 {
+            return Err(test_utils::get_errno());
+        }
+        Ok(out)
+    }
+
     test_utils::run_and_close_fds(&[fd_client, fd_peer], || {
         // neither socket has received any data, so should return ENOENT for inet sockets
         let expected_result = match (init_method.domain(), sock_type) {
@@ -238,6 +366,11 @@ fn test_siocgstamp(init_method: SocketInitMethod, sock_type: libc::c_int) -> Res
             expected_result,
             "Unexpected SIOCGSTAMP result",
         )?;
+        test_utils::result_assert_eq(
+            ioctl_siocgstampns(fd_peer).map(|_| ()),
+            expected_result.map(|_| ()),
+            "Unexpected SIOCGSTAMPNS result",
+        )?;
 
         // send data from the client to the peer
         let flags = nix::sys::socket::MsgFlags::empty();
@@ -267,6 +400,11 @@ fn test_siocgstamp(init_method: SocketInitMethod, sock_type: libc::c_int) -> Res
             expected_result,
             "Unexpected SIOCGSTAMP result",
         )?;
+        test_utils::result_assert_eq(
+            ioctl_siocgstampns(fd_peer).map(|_| ()),
+            expected_result.map(|_| ()),
+            "Unexpected SIOCGSTAMPNS result",
+        )?;
 
         // receive data at the peer
         let flags = nix::sys::socket::MsgFlags::empty();
@@ -308,12 +446,29 @@ fn test_siocgstamp(init_method: SocketInitMethod, sock_type: libc::c_int) -> Res
                     threshold,
                     "Time difference was too large",
                 )?;
+
+                // SIOCGSTAMPNS should return the same time with nanosecond precision
+                let recv_time_ns = ioctl_siocgstampns(fd_peer).unwrap();
+                let recv_time_ns = Duration::from_secs(recv_time_ns.tv_sec.try_into().unwrap())
+                    + Duration::from_nanos(recv_time_ns.tv_nsec.try_into().unwrap());
+                test_utils::result_assert_lt(
+                    test_utils::time::duration_abs_diff(recv_time, recv_time_ns),
+                    Duration::from_micros(1),
+                    "SIOCGSTAMPNS and SIOCGSTAMP times differ",
+                )?;
+            }
+            Some(e) => {
+                test_utils::result_assert_eq(
+                    ioctl_siocgstamp(fd_peer),
+                    Err(e),
+                    "Unexpected SIOCGSTAMP result",
+                )?;
+                test_utils::result_assert_eq(
+                    ioctl_siocgstampns(fd_peer).map(|_| ()),
+                    Err(e),
+                    "Unexpected SIOCGSTAMPNS result",
+                )?;
             }
-            Some(e) => test_utils::result_assert_eq(
-                ioctl_siocgstamp(fd_peer),
-                Err(e),
-                "Unexpected SIOCGSTAMP result",
-            )?,
         }
 
         Ok(())
@@ -58,6 +58,11 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
             test_invalid_sock_type,
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
+        test_utils::ShadowTest::new(
+            "test_netlink_socket",
+            test_netlink_socket,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
     ];
 
     // tests to repeat for different socket options
@@ -150,6 +155,19 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
         }
     }
 
+    tests.extend(vec![
+        test_utils::ShadowTest::new(
+            "test_reuseport_bind_conflicts",
+            test_reuseport_bind_conflicts,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_reuseport_distribution",
+            test_reuseport_distribution,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+    ]);
+
     for &domain in [libc::AF_INET, libc::AF_UNIX].iter() {
         for &sock_type in [libc::SOCK_STREAM, libc::SOCK_SEQPACKET].iter() {
             // add details to the test names to avoid duplicates
@@ -234,6 +252,16 @@ fn test_invalid_sock_type() -> Result<(), String> {
     test_utils::run_and_close_fds(&[fd], || check_listen_call(&args, Some(libc::EOPNOTSUPP)))
 }
 
+/// Test listen using a netlink socket, which is connectionless.
+fn test_netlink_socket() -> Result<(), String> {
+    let fd = unsafe { libc::socket(libc::AF_NETLINK, libc::SOCK_RAW, libc::NETLINK_ROUTE) };
+    assert!(fd >= 0);
+
+    let args = ListenArguments { fd, backlog: 0 };
+
+    test_utils::run_and_close_fds(&[fd], || check_listen_call(&args, Some(libc::EOPNOTSUPP)))
+}
+
 /// Test listen using a backlog of 0.
 fn test_zero_backlog(
     domain: libc::c_int,
@@ -695,6 +723,207 @@ fn test_reduced_backlog(domain: libc::c_int, sock_type: libc::c_int) -> Result<(
     Ok(())
 }
 
+/// Returns a new TCP socket with `SO_REUSEPORT` set to `reuse_port`.
+fn reuseport_socket(reuse_port: bool) -> libc::c_int {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM | libc::SOCK_NONBLOCK, 0) };
+    assert!(fd >= 0);
+
+    nix::sys::socket::setsockopt(fd, sockopt::ReusePort, &reuse_port).unwrap();
+    assert_eq!(
+        nix::sys::socket::getsockopt(fd, sockopt::ReusePort).unwrap(),
+        reuse_port
+    );
+
+    fd
+}
+
+/// Returns the bound local port of the socket.
+fn local_port(fd: libc::c_int) -> u16 {
+    let addr: nix::sys::socket::SockaddrIn = nix::sys::socket::getsockname(fd).unwrap();
+    addr.port()
+}
+
+/// Test that sockets can only share an address if they all have `SO_REUSEPORT` enabled.
+fn test_reuseport_bind_conflicts() -> Result<(), String> {
+    let fd_1 = reuseport_socket(true);
+    let fd_2 = reuseport_socket(true);
+    let fd_3 = reuseport_socket(false);
+    let fd_4 = reuseport_socket(false);
+    let fd_5 = reuseport_socket(true);
+
+    bind_fd(fd_1, SockAddr::Inet(inet_addr(libc::INADDR_LOOPBACK, 0)));
+    let port = local_port(fd_1);
+    let addr = SockAddr::Inet(inet_addr(libc::INADDR_LOOPBACK, port));
+
+    // another socket with SO_REUSEPORT can share the address
+    bind_fd(fd_2, addr);
+
+    // a socket without SO_REUSEPORT can't
+    let rv = unsafe { libc::bind(fd_3, addr.as_ptr(), addr.ptr_size()) };
+    test_utils::result_assert_eq(rv, -1, "Expected bind() to fail")?;
+    test_utils::result_assert_eq(
+        test_utils::get_errno(),
+        libc::EADDRINUSE,
+        "Unexpected errno",
+    )?;
+
+    // a socket with SO_REUSEPORT can't share an address with a socket without it
+    bind_fd(fd_4, SockAddr::Inet(inet_addr(libc::INADDR_LOOPBACK, 0)));
+    let addr = SockAddr::Inet(inet_addr(libc::INADDR_LOOPBACK, local_port(fd_4)));
+    let rv = unsafe { libc::bind(fd_5, addr.as_ptr(), addr.ptr_size()) };
+    test_utils::result_assert_eq(rv, -1, "Expected bind() to fail")?;
+    test_utils::result_assert_eq(
+        test_utils::get_errno(),
+        libc::EADDRINUSE,
+        "Unexpected errno",
+    )?;
+
+    for fd in [fd_1, fd_2, fd_3, fd_4, fd_5] {
+        nix::unistd::close(fd).unwrap();
+    }
+
+    Ok(())
+}
+
+/// Test that connections to a group of `SO_REUSEPORT` listeners are distributed across the
+/// listeners, and that all packets from a given client are received by the same listener.
+fn test_reuseport_distribution() -> Result<(), String> {
+    const NUM_LISTENERS: usize = 3;
+    const NUM_CLIENTS: usize = 30;
+
+    let listener_fds: Vec<_> = (0..NUM_LISTENERS).map(|_| reuseport_socket(true)).collect();
+
+    bind_fd(
+        listener_fds[0],
+        SockAddr::Inet(inet_addr(libc::INADDR_LOOPBACK, 0)),
+    );
+    let addr = SockAddr::Inet(inet_addr(
+        libc::INADDR_LOOPBACK,
+        local_port(listener_fds[0]),
+    ));
+
+    for &fd in &listener_fds[1..] {
+        bind_fd(fd, addr);
+    }
+
+    for &fd in &listener_fds {
+        let rv = unsafe { libc::listen(fd, NUM_CLIENTS as i32) };
+        assert_eq!(rv, 0);
+    }
+
+    // connect all of the clients
+    let client_fds: Vec<_> = (0..NUM_CLIENTS)
+        .map(|_| {
+            let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+            assert!(fd >= 0);
+            let rv = unsafe { libc::connect(fd, addr.as_ptr(), addr.ptr_size()) };
+            assert_eq!(rv, 0);
+            fd
+        })
+        .collect();
+
+    // each client's port and the index of the listener that accepted its connection
+    let mut accepted = std::collections::HashMap::new();
+
+    // some of the connections may not have been fully established yet, so wait for them
+    while accepted.len() < NUM_CLIENTS {
+        let mut poll_fds: Vec<_> = listener_fds
+            .iter()
+            .map(|&fd| nix::poll::PollFd::new(fd, PollFlags::POLLIN))
+            .collect();
+        let count = nix::poll::poll(&mut poll_fds, 2000).unwrap();
+        test_utils::result_assert(count > 0, "Not all connections were accepted")?;
+
+        for (listener_index, &listener_fd) in listener_fds.iter().enumerate() {
+            let mut peer_addr: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+            let mut peer_addr_len = std::mem::size_of_val(&peer_addr) as libc::socklen_t;
+            let fd = unsafe {
+                libc::accept(
+                    listener_fd,
+                    std::ptr::from_mut(&mut peer_addr) as *mut libc::sockaddr,
+                    &mut peer_addr_len,
+                )
+            };
+
+            if fd < 0 {
+                test_utils::result_assert_eq(
+                    test_utils::get_errno(),
+                    libc::EAGAIN,
+                    "Unexpected errno",
+                )?;
+                continue;
+            }
+
+            let peer_port = u16::from_be(peer_addr.sin_port);
+            let previous = accepted.insert(peer_port, (listener_index, fd));
+            test_utils::result_assert(previous.is_none(), "Connection accepted twice")?;
+        }
+    }
+
+    test_utils::result_assert_eq(
+        accepted.len(),
+        NUM_CLIENTS,
+        "More connections were accepted than expected",
+    )?;
+
+    // every listener should have received some of the connections
+    let mut accepted_per_listener = [0; NUM_LISTENERS];
+    for (listener_index, _) in accepted.values() {
+        accepted_per_listener[*listener_index] += 1;
+    }
+    test_utils::result_assert(
+        accepted_per_listener.iter().all(|x| *x > 0),
+        &format!("Not every listener accepted connections: {accepted_per_listener:?}"),
+    )?;
+
+    // data sent by each client should be received by the socket that accepted its connection
+    for &client_fd in &client_fds {
+        let (_, accepted_fd) = accepted[&local_port(client_fd)];
+
+        let msg = local_port(client_fd).to_be_bytes();
+        let rv =
+            unsafe { libc::send(client_fd, msg.as_ptr() as *const libc::c_void, msg.len(), 0) };
+        assert_eq!(rv, msg.len() as isize);
+
+        let mut poll_fds = [nix::poll::PollFd::new(accepted_fd, PollFlags::POLLIN)];
+        let count = nix::poll::poll(&mut poll_fds, 2000).unwrap();
+        test_utils::result_assert_eq(count, 1, "Accepted socket didn't receive the data")?;
+
+        let mut buf = [0u8; 2];
+        let rv = unsafe {
+            libc::recv(
+                accepted_fd,
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+                0,
+            )
+        };
+        test_utils::result_assert_eq(rv, buf.len() as isize, "Unexpected recv() length")?;
+        test_utils::result_assert_eq(buf, msg, "Unexpected recv() data")?;
+    }
+
+    for fd in client_fds
+        .into_iter()
+        .chain(accepted.into_values().map(|(_, fd)| fd))
+        .chain(listener_fds)
+    {
+        nix::unistd::close(fd).unwrap();
+    }
+
+    Ok(())
+}
+
+fn inet_addr(addr: u32, port: u16) -> libc::sockaddr_in {
+    libc::sockaddr_in {
+        sin_family: libc::AF_INET as u16,
+        sin_port: port.to_be(),
+        sin_addr: libc::in_addr {
+            s_addr: addr.to_be(),
+        },
+        sin_zero: [0; 8],
+    }
+}
+
 /// Bind the fd to the address.
 fn bind_fd(fd: libc::c_int, bind: SockAddr) {
     let (addr, addr_len) = (bind.as_ptr(), bind.ptr_size());
@@ -6,7 +6,9 @@
 use std::collections::VecDeque;
 use std::hash::Hasher;
 use std::os::unix::io::AsRawFd;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
+use nix::sys::signal::{SaFlags, SigAction, SigHandler, SigSet, Signal, sigaction};
 use nix::sys::socket::MsgFlags;
 use rand::RngCore;
 use rand::SeedableRng;
@@ -18,6 +20,9 @@ use test_utils::socket_utils::{
 };
 use test_utils::{AsMutPtr, set};
 
+/// A send/recv flag bit that linux doesn't define.
+const UNRECOGNIZED_MSG_FLAG: libc::c_int = 0x400000;
+
 #[derive(Debug)]
 struct SendtoArguments<'a> {
     fd: libc::c_int,
@@ -154,11 +159,27 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
                     )
                 };
 
-                tests.extend(vec![test_utils::ShadowTest::new(
-                    &append_args("test_not_connected"),
-                    move || test_not_connected(sys_method, domain, sock_type),
-                    set![TestEnv::Libc, TestEnv::Shadow],
-                )]);
+                tests.extend(vec![
+                    test_utils::ShadowTest::new(
+                        &append_args("test_not_connected"),
+                        move || test_not_connected(sys_method, domain, sock_type),
+                        set![TestEnv::Libc, TestEnv::Shadow],
+                    ),
+                    test_utils::ShadowTest::new(
+                        &append_args("test_no_dest_not_connected <listening=false>"),
+                        move || test_no_dest_not_connected(sys_method, domain, sock_type, false),
+                        set![TestEnv::Libc, TestEnv::Shadow],
+                    ),
+                ]);
+
+                // only connection-oriented sockets can listen
+                if sock_type != libc::SOCK_DGRAM {
+                    tests.push(test_utils::ShadowTest::new(
+                        &append_args("test_no_dest_not_connected <listening=true>"),
+                        move || test_no_dest_not_connected(sys_method, domain, sock_type, true),
+                        set![TestEnv::Libc, TestEnv::Shadow],
+                    ));
+                }
             }
         }
 
@@ -220,6 +241,23 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
                         set![TestEnv::Libc, TestEnv::Shadow],
                     ),
                 ]);
+
+                for send_flag in [
+                    libc::MSG_CONFIRM,
+                    libc::MSG_EOR,
+                    libc::MSG_WAITALL,
+                    UNRECOGNIZED_MSG_FLAG,
+                ] {
+                    tests.extend(vec![test_utils::ShadowTest::new(
+                        &append_args(&format!(
+                            "test_send_flag_ignored <send_flag={send_flag:#x}>"
+                        )),
+                        move || {
+                            test_send_flag_ignored(sys_method, init_method, sock_type, send_flag)
+                        },
+                        set![TestEnv::Libc, TestEnv::Shadow],
+                    )]);
+                }
             }
 
             tests.extend(vec![test_utils::ShadowTest::new(
@@ -417,11 +455,18 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
             )]);
         }
 
-        tests.extend(vec![test_utils::ShadowTest::new(
-            &append_args("test_large_buf_udp"),
-            move || test_large_buf_udp(sys_method),
-            set![TestEnv::Libc, TestEnv::Shadow],
-        )]);
+        tests.extend(vec![
+            test_utils::ShadowTest::new(
+                &append_args("test_large_buf_udp"),
+                move || test_large_buf_udp(sys_method),
+                set![TestEnv::Libc, TestEnv::Shadow],
+            ),
+            test_utils::ShadowTest::new(
+                &append_args("test_reset_wakes_blocked_reader"),
+                move || test_reset_wakes_blocked_reader(sys_method),
+                set![TestEnv::Libc, TestEnv::Shadow],
+            ),
+        ]);
     }
 
     let init_methods = [
@@ -482,11 +527,145 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
         }
     }
 
-    tests.extend(vec![test_utils::ShadowTest::new(
-        "test_unix_dgram_multiple_senders",
-        test_unix_dgram_multiple_senders,
-        set![TestEnv::Libc, TestEnv::Shadow],
-    )]);
+    for oob_inline in [false, true] {
+        tests.extend(vec![test_utils::ShadowTest::new(
+            &format!("test_urgent_data <oob_inline={oob_inline}>"),
+            move || test_urgent_data(oob_inline),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        )]);
+    }
+
+    tests.extend(vec![
+        test_utils::ShadowTest::new(
+            "test_unix_dgram_multiple_senders",
+            test_unix_dgram_multiple_senders,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_unix_dgram_scm_rights_ctrunc",
+            test_unix_dgram_scm_rights_ctrunc,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_unix_dgram_multiple_cmsgs",
+            test_unix_dgram_multiple_cmsgs,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_unix_nonblock_set_during_blocking_recv",
+            test_unix_nonblock_set_during_blocking_recv,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_unix_stream_sigpipe",
+            test_unix_stream_sigpipe,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_stream_sigpipe <init_method=UnixSocketpair>",
+            || test_stream_sigpipe(SocketInitMethod::UnixSocketpair),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_stream_sigpipe <init_method=Unix>",
+            || test_stream_sigpipe(SocketInitMethod::Unix),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_stream_sigpipe <init_method=Inet>",
+            || test_stream_sigpipe(SocketInitMethod::Inet),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_unix_stream_short_writev",
+            test_unix_stream_short_writev,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_udp_recv_timestamp <optname=SO_TIMESTAMP>",
+            || test_udp_recv_timestamp(libc::SO_TIMESTAMP),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_udp_recv_timestamp <optname=SO_TIMESTAMPNS>",
+            || test_udp_recv_timestamp(libc::SO_TIMESTAMPNS),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_udp_recv_pktinfo",
+            test_udp_recv_pktinfo,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_udp_recvfrom_multiple_senders",
+            test_udp_recvfrom_multiple_senders,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_udp_recvmsg_truncated",
+            test_udp_recvmsg_truncated,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_udp_send_flags",
+            test_udp_send_flags,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_large_rcvbuf_window",
+            test_large_rcvbuf_window,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        // linux sends udp packets to local network addresses without queuing them in the socket's
+        // send buffer, so the send buffer never fills
+        test_utils::ShadowTest::new(
+            "test_udp_blocking_send_full_buffer",
+            test_udp_blocking_send_full_buffer,
+            set![TestEnv::Shadow],
+        ),
+    ]);
+
+    for init_method in [SocketInitMethod::Inet, SocketInitMethod::UnixSocketpair] {
+        tests.extend(vec![test_utils::ShadowTest::new(
+            &format!("test_recvmmsg_truncated <init_method={init_method:?}>"),
+            move || test_recvmmsg_truncated(init_method),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        )]);
+    }
+
+    for init_method in [SocketInitMethod::Inet, SocketInitMethod::UnixSocketpair] {
+        for sock_type in [libc::SOCK_STREAM, libc::SOCK_DGRAM] {
+            tests.extend(vec![test_utils::ShadowTest::new(
+                &format!("test_recv_timeout <init_method={init_method:?},type={sock_type}>"),
+                move || test_recv_timeout(init_method, sock_type),
+                set![TestEnv::Libc, TestEnv::Shadow],
+            )]);
+        }
+    }
+
+    for sock_type in [libc::SOCK_STREAM, libc::SOCK_DGRAM] {
+        tests.extend(vec![test_utils::ShadowTest::new(
+            &format!("test_unix_blocking_send_full_buffer <type={sock_type}>"),
+            move || test_unix_blocking_send_full_buffer(sock_type),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        )]);
+    }
+
+    for blocked_reader in [false, true] {
+        tests.extend(vec![test_utils::ShadowTest::new(
+            &format!("test_user_timeout <blocked_reader={blocked_reader}>"),
+            move || test_user_timeout(blocked_reader),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        )]);
+    }
+
+    for nonblocking in [true, false] {
+        tests.extend(vec![test_utils::ShadowTest::new(
+            &format!("test_write_while_connecting <nonblocking={nonblocking}>"),
+            move || test_write_while_connecting(nonblocking),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        )]);
+    }
 
     tests
 }
@@ -549,6 +728,47 @@ fn test_not_connected(
     })
 }
 
+/// Test sendto() using a null address on a socket that isn't connected, and so has no destination.
+fn test_no_dest_not_connected(
+    sys_method: SendRecvMethod,
+    domain: libc::c_int,
+    sock_type: libc::c_int,
+    listening: bool,
+) -> Result<(), String> {
+    let fd = unsafe { libc::socket(domain, sock_type | libc::SOCK_NONBLOCK, 0) };
+    assert!(fd >= 0);
+
+    if listening {
+        autobind_helper(fd, domain);
+        assert_eq!(unsafe { libc::listen(fd, 10) }, 0);
+    }
+
+    let expected_errs = match (domain, sock_type) {
+        // may return ENOTCONN for a socket that isn't listening, see "BUGS" in sendto(2)
+        (libc::AF_INET, libc::SOCK_STREAM) if !listening => &[libc::EPIPE, libc::ENOTCONN][..],
+        (libc::AF_INET, libc::SOCK_STREAM) => &[libc::EPIPE][..],
+        (libc::AF_INET, libc::SOCK_DGRAM) => &[libc::EDESTADDRREQ][..],
+        (libc::AF_UNIX, _) => &[libc::ENOTCONN][..],
+        _ => unimplemented!(),
+    };
+
+    let buf = [1u8, 2, 3];
+
+    let args = SendtoArguments {
+        fd,
+        len: buf.len(),
+        buf: Some(&buf),
+        flags: 0,
+        addr: None,
+        addr_len: 0,
+    };
+
+    test_utils::run_and_close_fds(&[fd], || {
+        check_send_call(&args, sys_method, expected_errs, true)?;
+        Ok(())
+    })
+}
+
 /// Test sendto() and recvfrom() using a null buffer, and non-zero buffer length.
 fn test_null_buf(
     sys_method: SendRecvMethod,
@@ -796,6 +1016,31 @@ fn test_zero_len_buf_read_and_recv(
         let rv = unsafe { libc::readv(fd_server, &iov, 1) };
         assert_eq!(rv, 0);
 
+        // PART 3: check what was removed from the receive buffer
+
+        // stream sockets don't remove any data for zero-length reads, but non-stream sockets
+        // discard one message for each of the three recv()/recvmsg() calls above (read() and
+        // readv() never remove a message)
+        let mut remaining = Vec::new();
+        loop {
+            let mut buf = [0u8; 100];
+            match nix::sys::socket::recv(fd_server, &mut buf, MsgFlags::empty()) {
+                Ok(n) => remaining.push(n),
+                Err(nix::errno::Errno::EAGAIN) => break,
+                Err(e) => return Err(format!("Unexpected recv error: {e}")),
+            }
+        }
+
+        if sock_type == libc::SOCK_STREAM {
+            test_utils::result_assert_eq(
+                remaining.iter().sum::<usize>(),
+                100,
+                "Unexpected number of bytes remaining",
+            )?;
+        } else {
+            test_utils::result_assert_eq(remaining, vec![10; 7], "Unexpected messages remaining")?;
+        }
+
         Ok(())
     })
 }
@@ -973,7 +1218,7 @@ fn test_zero_len_msg_read_and_recv(
     })
 }
 
-/// Test sendto() and recvfrom() using the `MSG_DONTWAIT` flag.
+/// Test sending and receiving on a blocking socket using the `MSG_DONTWAIT` flag.
 fn test_flag_dontwait(
     sys_method: SendRecvMethod,
     init_method: SocketInitMethod,
@@ -992,10 +1237,34 @@ fn test_flag_dontwait(
         ..Default::default()
     };
 
+    let send_buf = vec![0u8; 10_000];
+
+    let sendto_args = SendtoArguments {
+        fd: fd_client,
+        len: send_buf.len(),
+        buf: Some(&send_buf),
+        flags: libc::MSG_DONTWAIT,
+        ..Default::default()
+    };
+
     test_utils::run_and_close_fds(&[fd_client, fd_server], || {
         // try to read 10 bytes; an EAGAIN error expected
         check_recv_call(&mut recvfrom_args, sys_method, &[libc::EAGAIN], true)?;
 
+        // inet dgram sockets drop packets rather than filling the send buffer
+        if init_method.domain() == libc::AF_INET && sock_type == libc::SOCK_DGRAM {
+            return Ok(());
+        }
+
+        // send until the buffer is full; an EAGAIN error is expected rather than blocking
+        loop {
+            match check_send_call(&sendto_args, sys_method, &[], false) {
+                Ok(_) => {}
+                Err(_) if test_utils::get_errno() == libc::EAGAIN => break,
+                Err(e) => return Err(e),
+            }
+        }
+
         Ok(())
     })
 }
@@ -1759,6 +2028,120 @@ fn test_send_flag_trunc(
     })
 }
 
+/// Test that a send flag that doesn't apply to the socket, or that isn't a valid flag at all, is
+/// ignored rather than returning an error.
+fn test_send_flag_ignored(
+    sys_method: SendRecvMethod,
+    init_method: SocketInitMethod,
+    sock_type: libc::c_int,
+    send_flag: libc::c_int,
+) -> Result<(), String> {
+    let (fd_client, fd_server) =
+        socket_init_helper(init_method, sock_type, 0, /* bind_client = */ false);
+
+    test_utils::run_and_close_fds(&[fd_client, fd_server], || {
+        let buf_send = vec![1u8; 200];
+        let args = SendtoArguments {
+            fd: fd_client,
+            len: buf_send.len(),
+            buf: Some(&buf_send),
+            flags: send_flag,
+            ..Default::default()
+        };
+
+        check_send_call(&args, sys_method, &[], true)?;
+
+        // shadow needs to run events
+        std::thread::sleep(std::time::Duration::from_millis(10));
+
+        let mut buf_recv = [0u8; 500];
+        let rv = simple_recvfrom_helper(sys_method, fd_server, &mut buf_recv, &[], false)?;
+        test_utils::result_assert_eq(rv, 200, "Expected to read the original msg size")?;
+        test_utils::result_assert_eq(
+            &buf_send[..],
+            &buf_recv[..(rv as usize)],
+            "Expected the buffers to be equal",
+        )?;
+
+        Ok(())
+    })
+}
+
+/// Test the send flags that change how a UDP socket sends a datagram: `MSG_OOB` isn't supported,
+/// and `MSG_DONTROUTE` only allows sending to directly-connected networks.
+fn test_udp_send_flags() -> Result<(), String> {
+    let fd_server =
+        unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_NONBLOCK, 0) };
+    let fd_client = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+    assert!(fd_server >= 0);
+    assert!(fd_client >= 0);
+
+    let sendto = |addr: &nix::sys::socket::SockaddrIn, flags: libc::c_int| {
+        use nix::sys::socket::SockaddrLike;
+        let rv = unsafe {
+            libc::sendto(
+                fd_client,
+                [1u8].as_ptr() as *const libc::c_void,
+                1,
+                flags,
+                addr.as_ptr(),
+                addr.len(),
+            )
+        };
+        nix::errno::Errno::result(rv)
+    };
+
+    test_utils::run_and_close_fds(&[fd_server, fd_client], || {
+        nix::sys::socket::bind(
+            fd_server,
+            &nix::sys::socket::SockaddrIn::new(127, 0, 0, 1, 0),
+        )
+        .unwrap();
+        let server_addr =
+            nix::sys::socket::getsockname::<nix::sys::socket::SockaddrIn>(fd_server).unwrap();
+
+        // udp sockets don't have out-of-band data
+        test_utils::result_assert_eq(
+            sendto(&server_addr, libc::MSG_OOB),
+            Err(nix::errno::Errno::EOPNOTSUPP),
+            "unexpected sendto result with MSG_OOB",
+        )?;
+
+        // an address that isn't on any of the host's networks
+        let off_link_addr = nix::sys::socket::SockaddrIn::new(1, 2, 3, 4, 80);
+        test_utils::result_assert_eq(
+            sendto(&off_link_addr, libc::MSG_DONTROUTE),
+            Err(nix::errno::Errno::ENETUNREACH),
+            "unexpected sendto result with MSG_DONTROUTE",
+        )?;
+
+        // the loopback network is directly connected
+        test_utils::result_assert_eq(
+            sendto(&server_addr, libc::MSG_DONTROUTE),
+            Ok(1),
+            "unexpected sendto result with MSG_DONTROUTE",
+        )?;
+
+        // shadow needs to run events
+        std::thread::sleep(std::time::Duration::from_millis(10));
+
+        // only the datagram sent with MSG_DONTROUTE should have been sent
+        let mut buf = [0u8; 1];
+        test_utils::result_assert_eq(
+            nix::sys::socket::recv(fd_server, &mut buf, MsgFlags::empty()),
+            Ok(1),
+            "unexpected recv result",
+        )?;
+        test_utils::result_assert_eq(
+            nix::sys::socket::recv(fd_server, &mut buf, MsgFlags::empty()),
+            Err(nix::errno::Errno::EAGAIN),
+            "unexpected recv result",
+        )?;
+
+        Ok(())
+    })
+}
+
 /// Test sendto()/recvfrom() on a socket after its peer has been closed, with no buffered data.
 fn test_after_peer_close_empty_buf(
     sys_method: SendRecvMethod,
@@ -1873,57 +2256,217 @@ fn test_after_peer_close_nonempty_buf(
     })
 }
 
-/// Test that recvfrom() on an inet dgram socket returns ECONNREFUSED if a previous sendto() failed.
-fn test_recvfrom_econnrefused_after_sendto(
-    sys_method: SendRecvMethod,
-    init_method: SocketInitMethod,
-    sock_type: libc::c_int,
-    flag: libc::c_int,
-) -> Result<(), String> {
-    let (fd_client, fd_peer) =
-        socket_init_helper(init_method, sock_type, flag, /* bind_client = */ false);
+/// Test that a recvfrom() blocked on a tcp socket returns ECONNRESET when the peer resets the
+/// connection.
+fn test_reset_wakes_blocked_reader(sys_method: SendRecvMethod) -> Result<(), String> {
+    let (fd_client, fd_peer) = socket_init_helper(
+        SocketInitMethod::Inet,
+        libc::SOCK_STREAM,
+        0,
+        /* bind_client = */ false,
+    );
 
-    nix::unistd::close(fd_peer).unwrap();
+    // the peer will never read this data, so closing the peer will send a RST on linux and with
+    // shadow's new tcp stack
+    simple_sendto_helper(sys_method, fd_client, &[1u8, 2], &[], true)?;
+
+    // shadow's legacy tcp stack only sends a RST on close with a zero linger timeout, which the new
+    // tcp stack doesn't support
+    let linger = libc::linger {
+        l_onoff: 1,
+        l_linger: 0,
+    };
+    let rv = unsafe {
+        libc::setsockopt(
+            fd_peer,
+            libc::SOL_SOCKET,
+            libc::SO_LINGER,
+            std::ptr::from_ref(&linger) as *const libc::c_void,
+            std::mem::size_of_val(&linger) as libc::socklen_t,
+        )
+    };
+    assert!(rv == 0 || test_utils::get_errno() == libc::ENOPROTOOPT);
 
     // shadow needs to run events
     assert_eq!(unsafe { libc::usleep(10000) }, 0);
 
-    test_utils::run_and_close_fds(&[fd_client], || {
-        let expected_errnos = match (init_method.domain(), sock_type) {
-            // connectionless unix sockets
-            (libc::AF_UNIX, libc::SOCK_DGRAM) => &[libc::ECONNREFUSED][..],
-            // connection-oriented unix sockets
-            (libc::AF_UNIX, _) => &[libc::EPIPE][..],
-            // non-unix sockets
-            _ => &[][..],
-        };
-
-        // send 3 bytes; unix sockets will return an error
-        simple_sendto_helper(sys_method, fd_client, &[1u8, 2, 3], expected_errnos, true)?;
-
-        let expected_errnos = match (init_method.domain(), sock_type) {
-            // connectionless unix sockets
-            (libc::AF_UNIX, libc::SOCK_DGRAM) => &[libc::EAGAIN][..],
-            // connectionless inet sockets
-            (libc::AF_INET, libc::SOCK_DGRAM) => &[libc::ECONNREFUSED][..],
-            // connection-oriented sockets
-            _ => &[][..],
-        };
-
-        // read 3 bytes using a null sockaddr and a null sockaddr length
-        // connection-oriented sockets will return EOF
-        // inet dgram (udp) socket will return ECONNREFUSED due to the previous sendto()
-        let rv = simple_recvfrom_helper(
+    let reader = std::thread::spawn(move || {
+        // should block until the connection is reset
+        simple_recvfrom_helper(
             sys_method,
             fd_client,
             &mut [1u8, 2, 3],
-            expected_errnos,
+            &[libc::ECONNRESET],
             false,
-        )?;
-        if expected_errnos.is_empty() {
-            // if there was no error, should have returned EOF
-            assert_eq!(0, rv);
-        }
+        )
+    });
+
+    // give the reader time to block
+    assert_eq!(unsafe { libc::usleep(50000) }, 0);
+
+    nix::unistd::close(fd_peer).unwrap();
+
+    test_utils::run_and_close_fds(&[fd_client], || {
+        reader.join().unwrap()?;
+
+        // the reset was already reported by recvfrom(), so there should be no socket error
+        let error = nix::sys::socket::getsockopt(fd_client, nix::sys::socket::sockopt::SocketError)
+            .map_err(|e| e.to_string())?;
+        test_utils::result_assert_eq(error, 0, "Unexpected socket error")?;
+
+        // future reads should return EOF
+        let rv = simple_recvfrom_helper(sys_method, fd_client, &mut [1u8, 2, 3], &[], false)?;
+        test_utils::result_assert_eq(rv, 0, "Expected EOF")?;
+
+        Ok(())
+    })
+}
+
+/// Test that urgent data sent with MSG_OOB is reported with POLLPRI and in select()'s exceptfds,
+/// and is delivered either out-of-band or inline depending on SO_OOBINLINE.
+fn test_urgent_data(oob_inline: bool) -> Result<(), String> {
+    let (fd_client, fd_peer) = socket_init_helper(
+        SocketInitMethod::Inet,
+        libc::SOCK_STREAM,
+        libc::SOCK_NONBLOCK,
+        /* bind_client = */ false,
+    );
+
+    test_utils::run_and_close_fds(&[fd_client, fd_peer], || {
+        if oob_inline {
+            nix::sys::socket::setsockopt(fd_peer, nix::sys::socket::sockopt::OobInline, &true)
+                .map_err(|e| e.to_string())?;
+        }
+
+        let inline = nix::sys::socket::getsockopt(fd_peer, nix::sys::socket::sockopt::OobInline)
+            .map_err(|e| e.to_string())?;
+        test_utils::result_assert_eq(inline, oob_inline, "Unexpected SO_OOBINLINE value")?;
+
+        // only the last byte of an MSG_OOB send is urgent
+        nix::sys::socket::send(fd_client, b"ab", MsgFlags::empty()).map_err(|e| e.to_string())?;
+        nix::sys::socket::send(fd_client, b"cd", MsgFlags::MSG_OOB).map_err(|e| e.to_string())?;
+
+        // shadow needs to run events
+        assert_eq!(unsafe { libc::usleep(10000) }, 0);
+
+        // urgent data should be reported by poll() and select()
+        test_utils::result_assert_eq(
+            poll_revents(fd_peer, libc::POLLIN | libc::POLLPRI),
+            libc::POLLIN | libc::POLLPRI,
+            "Unexpected poll events before reading",
+        )?;
+        test_utils::result_assert(select_exceptional(fd_peer), "Expected an exceptional fd")?;
+
+        // reads stop at the urgent mark
+        let mut buf = [0u8; 10];
+        let rv = nix::sys::socket::recv(fd_peer, &mut buf, MsgFlags::empty())
+            .map_err(|e| e.to_string())?;
+        test_utils::result_assert_eq(
+            &buf[..rv],
+            b"abc".as_slice(),
+            "Unexpected data before the mark",
+        )?;
+
+        if oob_inline {
+            // the urgent byte can't be read out-of-band
+            let rv = nix::sys::socket::recv(fd_peer, &mut buf, MsgFlags::MSG_OOB);
+            test_utils::result_assert_eq(rv, Err(nix::errno::Errno::EINVAL), "Expected EINVAL")?;
+
+            // the urgent byte is in the normal data stream
+            let rv = nix::sys::socket::recv(fd_peer, &mut buf, MsgFlags::empty())
+                .map_err(|e| e.to_string())?;
+            test_utils::result_assert_eq(
+                &buf[..rv],
+                b"d".as_slice(),
+                "Unexpected inline urgent data",
+            )?;
+        } else {
+            // peeking shouldn't consume the urgent byte
+            let rv =
+                nix::sys::socket::recv(fd_peer, &mut buf, MsgFlags::MSG_OOB | MsgFlags::MSG_PEEK)
+                    .map_err(|e| e.to_string())?;
+            test_utils::result_assert_eq(
+                &buf[..rv],
+                b"d".as_slice(),
+                "Unexpected peeked urgent data",
+            )?;
+
+            let rv = nix::sys::socket::recv(fd_peer, &mut buf, MsgFlags::MSG_OOB)
+                .map_err(|e| e.to_string())?;
+            test_utils::result_assert_eq(&buf[..rv], b"d".as_slice(), "Unexpected urgent data")?;
+
+            // the urgent byte was already read
+            let rv = nix::sys::socket::recv(fd_peer, &mut buf, MsgFlags::MSG_OOB);
+            test_utils::result_assert_eq(rv, Err(nix::errno::Errno::EINVAL), "Expected EINVAL")?;
+
+            // the urgent byte is not in the normal data stream
+            let rv = nix::sys::socket::recv(fd_peer, &mut buf, MsgFlags::empty());
+            test_utils::result_assert_eq(rv, Err(nix::errno::Errno::EAGAIN), "Expected EAGAIN")?;
+        }
+
+        // there is no more urgent data
+        test_utils::result_assert_eq(
+            poll_revents(fd_peer, libc::POLLIN | libc::POLLPRI),
+            0,
+            "Unexpected poll events after reading",
+        )?;
+        test_utils::result_assert(!select_exceptional(fd_peer), "Unexpected exceptional fd")?;
+
+        Ok(())
+    })
+}
+
+/// Test that recvfrom() on an inet dgram socket returns ECONNREFUSED if a previous sendto() failed.
+fn test_recvfrom_econnrefused_after_sendto(
+    sys_method: SendRecvMethod,
+    init_method: SocketInitMethod,
+    sock_type: libc::c_int,
+    flag: libc::c_int,
+) -> Result<(), String> {
+    let (fd_client, fd_peer) =
+        socket_init_helper(init_method, sock_type, flag, /* bind_client = */ false);
+
+    nix::unistd::close(fd_peer).unwrap();
+
+    // shadow needs to run events
+    assert_eq!(unsafe { libc::usleep(10000) }, 0);
+
+    test_utils::run_and_close_fds(&[fd_client], || {
+        let expected_errnos = match (init_method.domain(), sock_type) {
+            // connectionless unix sockets
+            (libc::AF_UNIX, libc::SOCK_DGRAM) => &[libc::ECONNREFUSED][..],
+            // connection-oriented unix sockets
+            (libc::AF_UNIX, _) => &[libc::EPIPE][..],
+            // non-unix sockets
+            _ => &[][..],
+        };
+
+        // send 3 bytes; unix sockets will return an error
+        simple_sendto_helper(sys_method, fd_client, &[1u8, 2, 3], expected_errnos, true)?;
+
+        let expected_errnos = match (init_method.domain(), sock_type) {
+            // connectionless unix sockets
+            (libc::AF_UNIX, libc::SOCK_DGRAM) => &[libc::EAGAIN][..],
+            // connectionless inet sockets
+            (libc::AF_INET, libc::SOCK_DGRAM) => &[libc::ECONNREFUSED][..],
+            // connection-oriented sockets
+            _ => &[][..],
+        };
+
+        // read 3 bytes using a null sockaddr and a null sockaddr length
+        // connection-oriented sockets will return EOF
+        // inet dgram (udp) socket will return ECONNREFUSED due to the previous sendto()
+        let rv = simple_recvfrom_helper(
+            sys_method,
+            fd_client,
+            &mut [1u8, 2, 3],
+            expected_errnos,
+            false,
+        )?;
+        if expected_errnos.is_empty() {
+            // if there was no error, should have returned EOF
+            assert_eq!(0, rv);
+        }
         Ok(())
     })
 }
@@ -2055,6 +2598,141 @@ fn test_large_buf(
     })
 }
 
+/// Test that a tcp receiver with a large SO_RCVBUF advertises a window larger than 64 KiB, so that
+/// the sender can have more than 64 KiB in flight while the receiver isn't reading.
+fn test_large_rcvbuf_window() -> Result<(), String> {
+    let fd_client =
+        unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM | libc::SOCK_NONBLOCK, 0) };
+    let fd_server = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+    assert!(fd_client >= 0);
+    assert!(fd_server >= 0);
+
+    // the window scale is chosen during the handshake, so the buffer sizes must be set before
+    // listening and connecting (the accepted socket inherits the listener's buffer size)
+    nix::sys::socket::setsockopt(fd_server, nix::sys::socket::sockopt::RcvBuf, &1_000_000).unwrap();
+    nix::sys::socket::setsockopt(fd_client, nix::sys::socket::sockopt::SndBuf, &1_000_000).unwrap();
+
+    let (server_addr, server_addr_len) = autobind_helper(fd_server, libc::AF_INET);
+    let fd_peer = stream_connect_helper(
+        fd_client,
+        fd_server,
+        server_addr,
+        server_addr_len,
+        libc::SOCK_NONBLOCK,
+    );
+
+    test_utils::run_and_close_fds(&[fd_client, fd_server, fd_peer], || {
+        let send_buf = vec![0u8; 10_000];
+        let mut bytes_sent = 0;
+
+        // send until the receiver's window (or the sender's buffer) is full
+        loop {
+            match nix::sys::socket::send(fd_client, &send_buf, MsgFlags::empty()) {
+                Ok(n) => bytes_sent += n,
+                Err(nix::errno::Errno::EAGAIN) => {
+                    // wait to see if more data can be sent once the in-flight data is acknowledged
+                    if !test_utils::is_writable(fd_client, 100).unwrap() {
+                        break;
+                    }
+                }
+                Err(e) => return Err(format!("Unexpected send error: {e}")),
+            }
+        }
+
+        // read everything that the receiver has buffered
+        let mut recv_buf = vec![0u8; 10_000];
+        let mut bytes_received = 0;
+        loop {
+            match nix::sys::socket::recv(fd_peer, &mut recv_buf, MsgFlags::empty()) {
+                Ok(n) => bytes_received += n,
+                Err(nix::errno::Errno::EAGAIN) => break,
+                Err(e) => return Err(format!("Unexpected recv error: {e}")),
+            }
+        }
+
+        test_utils::result_assert(
+            bytes_received <= bytes_sent,
+            "Received more bytes than were sent",
+        )?;
+
+        // the receiver never read any data before the sender stopped, so all of the received data
+        // must have fit within the receiver's advertised window
+        test_utils::result_assert(
+            bytes_received > u16::MAX as usize,
+            &format!("Only {bytes_received} bytes were in flight; expected more than 64 KiB"),
+        )?;
+
+        Ok(())
+    })
+}
+
+/// Test that with `TCP_USER_TIMEOUT`, a connection is aborted with `ETIMEDOUT` when its sent data
+/// isn't acknowledged within the timeout. The peer never reads, so once its receive buffer is full
+/// none of the remaining data is acknowledged.
+fn test_user_timeout(blocked_reader: bool) -> Result<(), String> {
+    const TIMEOUT_MS: u32 = 2000;
+
+    let (fd_client, fd_peer) = socket_init_helper(
+        SocketInitMethod::Inet,
+        libc::SOCK_STREAM,
+        0,
+        /* bind_client = */ false,
+    );
+
+    test_utils::run_and_close_fds(&[fd_client, fd_peer], || {
+        use nix::sys::socket::sockopt::{SocketError, TcpUserTimeout};
+
+        nix::sys::socket::setsockopt(fd_client, TcpUserTimeout, &TIMEOUT_MS)
+            .map_err(|e| e.to_string())?;
+        let timeout =
+            nix::sys::socket::getsockopt(fd_client, TcpUserTimeout).map_err(|e| e.to_string())?;
+        test_utils::result_assert_eq(timeout, TIMEOUT_MS, "Unexpected TCP_USER_TIMEOUT value")?;
+
+        // send until the peer's receive buffer and our send buffer are full
+        let send_buf = vec![0u8; 10_000];
+        loop {
+            match nix::sys::socket::send(fd_client, &send_buf, MsgFlags::MSG_DONTWAIT) {
+                Ok(_) => {}
+                Err(nix::errno::Errno::EAGAIN) => {
+                    // wait to see if more data can be sent once the in-flight data is acknowledged
+                    if !test_utils::is_writable(fd_client, 100).unwrap() {
+                        break;
+                    }
+                }
+                Err(e) => return Err(format!("Unexpected send error: {e}")),
+            }
+        }
+
+        let start = std::time::Instant::now();
+
+        if blocked_reader {
+            // should block until the connection times out
+            let rv = nix::sys::socket::recv(fd_client, &mut [0u8; 10], MsgFlags::empty());
+            test_utils::result_assert_eq(
+                rv,
+                Err(nix::errno::Errno::ETIMEDOUT),
+                "Unexpected recv result",
+            )?;
+        } else {
+            std::thread::sleep(std::time::Duration::from_millis(
+                u64::from(TIMEOUT_MS) + 1000,
+            ));
+
+            let error =
+                nix::sys::socket::getsockopt(fd_client, SocketError).map_err(|e| e.to_string())?;
+            test_utils::result_assert_eq(error, libc::ETIMEDOUT, "Unexpected socket error")?;
+        }
+
+        // the data may have been waiting on the peer for a little while before we stopped sending
+        test_utils::result_assert(
+            start.elapsed() >= std::time::Duration::from_millis(u64::from(TIMEOUT_MS) / 2),
+            "The connection timed out too early",
+        )?;
+
+        Ok(())
+    })
+}
+
 fn test_bound_to_inaddr_any(
     sys_method: SendRecvMethod,
     sock_type: libc::c_int,
@@ -2363,6 +3041,60 @@ fn test_unix_buffer_full(
     })
 }
 
+/// Test that a blocking send() on a unix socket whose buffer is full, which the socket reports
+/// internally as `EAGAIN`, blocks until the peer has read the buffered data.
+fn test_unix_blocking_send_full_buffer(sock_type: libc::c_int) -> Result<(), String> {
+    let (fd_client, fd_server) = socket_init_helper(
+        SocketInitMethod::UnixSocketpair,
+        sock_type,
+        0,
+        /* bind_client = */ false,
+    );
+
+    test_utils::run_and_close_fds(&[fd_client, fd_server], || {
+        let buf = [1u8; 1000];
+
+        // send without blocking until the buffer is full
+        let mut is_full = false;
+        for _ in 0..100_000 {
+            match nix::sys::socket::send(fd_client, &buf, MsgFlags::MSG_DONTWAIT) {
+                Ok(_) => {}
+                Err(nix::errno::Errno::EAGAIN) => {
+                    is_full = true;
+                    break;
+                }
+                Err(e) => return Err(format!("Unexpected send() error: {e}")),
+            }
+        }
+        test_utils::result_assert(is_full, "The buffer never became full")?;
+
+        // drain the buffer after a delay
+        let reader = std::thread::spawn(move || {
+            assert_eq!(unsafe { libc::usleep(100_000) }, 0);
+
+            let mut recv_buf = [0u8; 1000];
+            while nix::sys::socket::recv(fd_server, &mut recv_buf, MsgFlags::MSG_DONTWAIT).is_ok() {
+            }
+        });
+
+        let time_before = std::time::Instant::now();
+
+        // should block until the reader has drained the buffer
+        let rv = nix::sys::socket::send(fd_client, &buf, MsgFlags::empty());
+
+        let elapsed = time_before.elapsed();
+        reader.join().unwrap();
+
+        test_utils::result_assert_eq(rv, Ok(buf.len()), "Unexpected send() result")?;
+        test_utils::result_assert(
+            elapsed >= std::time::Duration::from_millis(100),
+            &format!("send() returned after {elapsed:?}, before the buffer was drained"),
+        )?;
+
+        Ok(())
+    })
+}
+
 /// Test the behaviour of unix dgram sockets when there are multiple senders.
 fn test_unix_dgram_multiple_senders() -> Result<(), String> {
     // a single destination socket
@@ -2452,64 +3184,1465 @@ fn test_unix_dgram_multiple_senders() -> Result<(), String> {
     Ok(())
 }
 
-// Test the behavior of loopback-bound UDP sockets when sendmsg() is used with an external address
-fn test_dgram_loopback_bound_sendmsg(
-    sys_method: SendRecvMethod,
-    flag: libc::c_int,
-) -> Result<(), String> {
-    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | flag, 0) };
-    assert!(fd >= 0);
+/// Test that when descriptors are passed in an `SCM_RIGHTS` message and the receiver's control
+/// buffer is too small for all of them, the control data is truncated with `MSG_CTRUNC` and the
+/// descriptors that didn't fit are closed.
+fn test_unix_dgram_scm_rights_ctrunc() -> Result<(), String> {
+    let mut fds = [0; 2];
+    let rv = unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_DGRAM, 0, fds.as_mut_ptr()) };
+    assert_eq!(rv, 0);
+    let [fd_client, fd_peer] = fds;
+
+    // a non-blocking pipe so that a read returns EAGAIN rather than blocking if the write end is
+    // still open somewhere
+    let mut pipe_fds = [0; 2];
+    let rv = unsafe { libc::pipe2(pipe_fds.as_mut_ptr(), libc::O_NONBLOCK) };
+    assert_eq!(rv, 0);
+    let [pipe_read_fd, pipe_write_fd] = pipe_fds;
+
+    // send both ends of the pipe
+    let mut send_buf = [1u8, 2, 3];
+    let mut send_iov = libc::iovec {
+        iov_base: send_buf.as_mut_ptr() as *mut libc::c_void,
+        iov_len: send_buf.len(),
+    };
+    let mut send_control = [0u64; 8];
+    let send_msg = libc::msghdr {
+        msg_name: std::ptr::null_mut(),
+        msg_namelen: 0,
+        msg_iov: &mut send_iov,
+        msg_iovlen: 1,
+        msg_control: send_control.as_mut_ptr() as *mut libc::c_void,
+        msg_controllen: unsafe { libc::CMSG_SPACE(std::mem::size_of_val(&pipe_fds) as u32) }
+            as usize,
+        msg_flags: 0,
+    };
 
-    // bind the socket to loopback
-    {
-        let addr = libc::sockaddr_in {
-            sin_family: libc::AF_INET as u16,
-            sin_port: 0u16.to_be(),
-            sin_addr: libc::in_addr {
-                s_addr: libc::INADDR_LOOPBACK.to_be(),
-            },
-            sin_zero: [0; 8],
-        };
-        let rv = unsafe {
-            libc::bind(
-                fd,
-                std::ptr::from_ref(&addr) as *const libc::sockaddr,
-                std::mem::size_of_val(&addr) as u32,
-            )
-        };
-        eprintln!("errno: {}", test_utils::get_errno());
-        assert_eq!(rv, 0);
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&send_msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(std::mem::size_of_val(&pipe_fds) as u32) as usize;
+        std::ptr::copy_nonoverlapping(
+            pipe_fds.as_ptr(),
+            libc::CMSG_DATA(cmsg) as *mut libc::c_int,
+            pipe_fds.len(),
+        );
     }
 
-    // we use a local network address here so that the tests
-    // running outside of shadow would only be trying to connect to a server on a local
-    // network rather than some random server on the internet
-    let other_ip: std::net::Ipv4Addr = "192.168.1.100".parse().unwrap();
+    let rv = unsafe { libc::sendmsg(fd_client, &send_msg, 0) };
+    assert_eq!(rv, send_buf.len() as isize);
 
-    let addr = libc::sockaddr_in {
-        sin_family: libc::AF_INET as u16,
-        sin_port: 11111u16.to_be(),
-        sin_addr: libc::in_addr {
-            s_addr: u32::from(other_ip).to_be(),
-        },
-        sin_zero: [0; 8],
+    // the only remaining references to the pipe are in the unread message
+    nix::unistd::close(pipe_read_fd).unwrap();
+    nix::unistd::close(pipe_write_fd).unwrap();
+
+    // receive with a control buffer that only has space for one descriptor
+    let mut recv_buf = [0u8; 3];
+    let mut recv_iov = libc::iovec {
+        iov_base: recv_buf.as_mut_ptr() as *mut libc::c_void,
+        iov_len: recv_buf.len(),
+    };
+    let recv_control_len =
+        unsafe { libc::CMSG_LEN(std::mem::size_of::<libc::c_int>() as u32) } as usize;
+    let mut recv_control = [0u64; 8];
+    let mut recv_msg = libc::msghdr {
+        msg_name: std::ptr::null_mut(),
+        msg_namelen: 0,
+        msg_iov: &mut recv_iov,
+        msg_iovlen: 1,
+        msg_control: recv_control.as_mut_ptr() as *mut libc::c_void,
+        msg_controllen: recv_control_len,
+        msg_flags: 0,
     };
 
-    let buf_send = vec![1u8; 200];
-    let args = SendtoArguments {
-        fd,
-        len: buf_send.len(),
-        buf: Some(&buf_send),
-        flags: 0,
-        addr: Some(SockAddr::Inet(addr)),
-        addr_len: std::mem::size_of_val(&addr) as u32,
+    let rv = unsafe { libc::recvmsg(fd_peer, &mut recv_msg, 0) };
+    assert_eq!(rv, recv_buf.len() as isize);
+    assert_eq!(recv_buf, send_buf);
+    assert_ne!(recv_msg.msg_flags & libc::MSG_CTRUNC, 0);
+    assert_eq!(recv_msg.msg_controllen, recv_control_len);
+
+    // only the first descriptor (the read end of the pipe) should have been received
+    let cmsg = unsafe { libc::CMSG_FIRSTHDR(&recv_msg) };
+    assert!(!cmsg.is_null());
+    let (cmsg_level, cmsg_type, cmsg_len) =
+        unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type, (*cmsg).cmsg_len) };
+    assert_eq!(cmsg_level, libc::SOL_SOCKET);
+    assert_eq!(cmsg_type, libc::SCM_RIGHTS);
+    assert_eq!(cmsg_len, recv_control_len);
+
+    let recv_fd = unsafe { std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::c_int) };
+    assert!(recv_fd >= 0);
+
+    // the write end of the pipe didn't fit in the control buffer and should have been closed, so
+    // the read end should see an EOF
+    let mut buf = [0u8; 1];
+    assert_eq!(nix::unistd::read(recv_fd, &mut buf), Ok(0));
+
+    nix::unistd::close(recv_fd).unwrap();
+    nix::unistd::close(fd_client).unwrap();
+    nix::unistd::close(fd_peer).unwrap();
+
+    Ok(())
+}
+
+/// Test sending two control messages of different types in a single message, and that the
+/// received control messages are laid out the way that the `CMSG_*` macros expect.
+fn test_unix_dgram_multiple_cmsgs() -> Result<(), String> {
+    let mut fds = [0; 2];
+    let rv = unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_DGRAM, 0, fds.as_mut_ptr()) };
+    assert_eq!(rv, 0);
+    let [fd_client, fd_peer] = fds;
+
+    // the peer needs `SO_PASSCRED` to receive the credentials
+    let enable: libc::c_int = 1;
+    let rv = unsafe {
+        libc::setsockopt(
+            fd_peer,
+            libc::SOL_SOCKET,
+            libc::SO_PASSCRED,
+            std::ptr::from_ref(&enable) as *const libc::c_void,
+            std::mem::size_of_val(&enable) as libc::socklen_t,
+        )
     };
+    assert_eq!(rv, 0);
 
-    check_send_call(&args, sys_method, &[libc::EINVAL], true)?;
+    let mut pipe_fds = [0; 2];
+    let rv = unsafe { libc::pipe(pipe_fds.as_mut_ptr()) };
+    assert_eq!(rv, 0);
+    let [pipe_read_fd, pipe_write_fd] = pipe_fds;
+
+    let creds = libc::ucred {
+        pid: unsafe { libc::getpid() },
+        uid: unsafe { libc::getuid() },
+        gid: unsafe { libc::getgid() },
+    };
+
+    let fd_size = std::mem::size_of::<libc::c_int>() as u32;
+    let creds_size = std::mem::size_of_val(&creds) as u32;
+
+    // the descriptor is followed by padding before the credentials
+    let mut send_buf = [1u8, 2, 3];
+    let mut send_iov = libc::iovec {
+        iov_base: send_buf.as_mut_ptr() as *mut libc::c_void,
+        iov_len: send_buf.len(),
+    };
+    let mut send_control = [0u64; 8];
+    let send_msg = libc::msghdr {
+        msg_name: std::ptr::null_mut(),
+        msg_namelen: 0,
+        msg_iov: &mut send_iov,
+        msg_iovlen: 1,
+        msg_control: send_control.as_mut_ptr() as *mut libc::c_void,
+        msg_controllen: unsafe { libc::CMSG_SPACE(fd_size) + libc::CMSG_SPACE(creds_size) }
+            as usize,
+        msg_flags: 0,
+    };
+
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&send_msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(fd_size) as usize;
+        std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut libc::c_int, pipe_read_fd);
+
+        let cmsg = libc::CMSG_NXTHDR(&send_msg, cmsg);
+        assert!(!cmsg.is_null());
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_CREDENTIALS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(creds_size) as usize;
+        std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut libc::ucred, creds);
+    }
+
+    let rv = unsafe { libc::sendmsg(fd_client, &send_msg, 0) };
+    assert_eq!(rv, send_buf.len() as isize);
+
+    let mut recv_buf = [0u8; 3];
+    let mut recv_iov = libc::iovec {
+        iov_base: recv_buf.as_mut_ptr() as *mut libc::c_void,
+        iov_len: recv_buf.len(),
+    };
+    let mut recv_control = [0u64; 16];
+    let mut recv_msg = libc::msghdr {
+        msg_name: std::ptr::null_mut(),
+        msg_namelen: 0,
+        msg_iov: &mut recv_iov,
+        msg_iovlen: 1,
+        msg_control: recv_control.as_mut_ptr() as *mut libc::c_void,
+        msg_controllen: std::mem::size_of_val(&recv_control),
+        msg_flags: 0,
+    };
+
+    let rv = unsafe { libc::recvmsg(fd_peer, &mut recv_msg, 0) };
+    assert_eq!(rv, recv_buf.len() as isize);
+    assert_eq!(recv_buf, send_buf);
+    assert_eq!(recv_msg.msg_flags & libc::MSG_CTRUNC, 0);
+    assert_eq!(recv_msg.msg_controllen, unsafe {
+        libc::CMSG_SPACE(creds_size) + libc::CMSG_SPACE(fd_size)
+    } as usize,);
+
+    // linux returns the credentials before the descriptors
+    let cmsg = unsafe { libc::CMSG_FIRSTHDR(&recv_msg) };
+    assert!(!cmsg.is_null());
+    let (cmsg_level, cmsg_type, cmsg_len) =
+        unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type, (*cmsg).cmsg_len) };
+    assert_eq!(cmsg_level, libc::SOL_SOCKET);
+    assert_eq!(cmsg_type, libc::SCM_CREDENTIALS);
+    assert_eq!(cmsg_len, unsafe { libc::CMSG_LEN(creds_size) } as usize);
+    let recv_creds =
+        unsafe { std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::ucred) };
+    assert_eq!(
+        (recv_creds.pid, recv_creds.uid, recv_creds.gid),
+        (creds.pid, creds.uid, creds.gid),
+    );
+
+    let cmsg = unsafe { libc::CMSG_NXTHDR(&recv_msg, cmsg) };
+    assert!(!cmsg.is_null());
+    let (cmsg_level, cmsg_type, cmsg_len) =
+        unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type, (*cmsg).cmsg_len) };
+    assert_eq!(cmsg_level, libc::SOL_SOCKET);
+    assert_eq!(cmsg_type, libc::SCM_RIGHTS);
+    assert_eq!(cmsg_len, unsafe { libc::CMSG_LEN(fd_size) } as usize);
+    let recv_fd = unsafe { std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::c_int) };
+
+    assert!(unsafe { libc::CMSG_NXTHDR(&recv_msg, cmsg) }.is_null());
+
+    // the received descriptor should refer to the read end of the pipe
+    assert_eq!(nix::unistd::write(pipe_write_fd, &[4]), Ok(1));
+    let mut buf = [0u8; 1];
+    assert_eq!(nix::unistd::read(recv_fd, &mut buf), Ok(1));
+    assert_eq!(buf, [4]);
+
+    for fd in [recv_fd, pipe_read_fd, pipe_write_fd, fd_client, fd_peer] {
+        nix::unistd::close(fd).unwrap();
+    }
 
     Ok(())
 }
 
+/// Test that setting `O_NONBLOCK` on a unix stream socket while a `recv()` is blocked doesn't make
+/// the `recv()` fail, and that it still returns the data once it arrives.
+fn test_unix_nonblock_set_during_blocking_recv() -> Result<(), String> {
+    let mut fds = [-1; 2];
+    let rv = unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) };
+    assert_eq!(rv, 0);
+    let [fd_client, fd_peer] = fds;
+
+    test_utils::run_and_close_fds(&fds, || {
+        let thread_handle = std::thread::spawn(move || {
+            // wait for the recv() to block
+            std::thread::sleep(std::time::Duration::from_millis(100));
+
+            nix::fcntl::fcntl(
+                fd_client,
+                nix::fcntl::FcntlArg::F_SETFL(nix::fcntl::OFlag::O_NONBLOCK),
+            )
+            .unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(100));
+
+            assert_eq!(nix::unistd::write(fd_peer, &[1, 2, 3]), Ok(3));
+        });
+
+        let mut buf = [0u8; 10];
+        let rv = nix::sys::socket::recv(fd_client, &mut buf, MsgFlags::empty());
+        thread_handle.join().unwrap();
+        test_utils::result_assert_eq(rv, Ok(3), "Expected the blocked recv() to succeed")?;
+
+        // new calls are non-blocking
+        let rv = nix::sys::socket::recv(fd_client, &mut buf, MsgFlags::empty());
+        test_utils::result_assert_eq(rv, Err(nix::errno::Errno::EAGAIN), "Expected EAGAIN")?;
+
+        Ok(())
+    })
+}
+
+/// Test that writing to a unix stream socket whose peer has closed returns `EPIPE` and raises
+/// `SIGPIPE`, unless `MSG_NOSIGNAL` is set.
+fn test_unix_stream_sigpipe() -> Result<(), String> {
+    static NUM_SIGPIPE: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" fn sigpipe_handler(_signal: libc::c_int) {
+        NUM_SIGPIPE.fetch_add(1, Ordering::SeqCst);
+    }
+
+    let mut fds = [-1; 2];
+    let rv = unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) };
+    assert_eq!(rv, 0);
+    let [fd_client, fd_peer] = fds;
+
+    nix::unistd::close(fd_peer).unwrap();
+
+    let action = SigAction::new(
+        SigHandler::Handler(sigpipe_handler),
+        SaFlags::empty(),
+        SigSet::empty(),
+    );
+    let old_action = unsafe { sigaction(Signal::SIGPIPE, &action) }.unwrap();
+
+    let result = test_utils::run_and_close_fds(&[fd_client], || {
+        let buf = [1u8, 2, 3];
+
+        // a write raises SIGPIPE
+        let rv = unsafe { libc::write(fd_client, buf.as_ptr() as *const libc::c_void, buf.len()) };
+        test_utils::result_assert_eq(rv, -1, "Expected the write to fail")?;
+        test_utils::result_assert_eq(test_utils::get_errno(), libc::EPIPE, "Unexpected errno")?;
+        test_utils::result_assert_eq(NUM_SIGPIPE.load(Ordering::SeqCst), 1, "Expected SIGPIPE")?;
+
+        // a sendmsg with MSG_NOSIGNAL doesn't
+        let mut iov = libc::iovec {
+            iov_base: buf.as_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        };
+        let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+
+        let rv = unsafe { libc::sendmsg(fd_client, &msg, libc::MSG_NOSIGNAL) };
+        test_utils::result_assert_eq(rv, -1, "Expected the sendmsg to fail")?;
+        test_utils::result_assert_eq(test_utils::get_errno(), libc::EPIPE, "Unexpected errno")?;
+        test_utils::result_assert_eq(NUM_SIGPIPE.load(Ordering::SeqCst), 1, "Unexpected SIGPIPE")?;
+
+        Ok(())
+    });
+
+    unsafe { sigaction(Signal::SIGPIPE, &old_action) }.unwrap();
+
+    result
+}
+
+/// Test that `sendto()` and `write()` on a stream socket whose peer has closed both return `EPIPE`
+/// and raise `SIGPIPE`, but that `sendto()` with `MSG_NOSIGNAL` doesn't raise `SIGPIPE`.
+fn test_stream_sigpipe(init_method: SocketInitMethod) -> Result<(), String> {
+    static NUM_SIGPIPE: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" fn sigpipe_handler(_signal: libc::c_int) {
+        NUM_SIGPIPE.fetch_add(1, Ordering::SeqCst);
+    }
+
+    let (fd_client, fd_peer) = socket_init_helper(
+        init_method,
+        libc::SOCK_STREAM,
+        0,
+        /* bind_client = */ false,
+    );
+
+    nix::unistd::close(fd_peer).unwrap();
+
+    if init_method.domain() == libc::AF_INET {
+        // a tcp send to a closed peer only fails after the peer's RST has arrived, so shut down
+        // writing instead so that the sends below fail immediately
+        let rv = unsafe { libc::shutdown(fd_client, libc::SHUT_WR) };
+        assert_eq!(rv, 0);
+    }
+
+    // shadow needs to run events
+    assert_eq!(unsafe { libc::usleep(10000) }, 0);
+
+    NUM_SIGPIPE.store(0, Ordering::SeqCst);
+
+    let action = SigAction::new(
+        SigHandler::Handler(sigpipe_handler),
+        SaFlags::empty(),
+        SigSet::empty(),
+    );
+    let old_action = unsafe { sigaction(Signal::SIGPIPE, &action) }.unwrap();
+
+    let result = test_utils::run_and_close_fds(&[fd_client], || {
+        let buf = [1u8, 2, 3];
+        let buf_ptr = buf.as_ptr() as *const libc::c_void;
+
+        let sendto = |flags| unsafe {
+            libc::sendto(fd_client, buf_ptr, buf.len(), flags, std::ptr::null(), 0)
+        };
+
+        // a sendto without flags raises SIGPIPE
+        let rv = sendto(0);
+        test_utils::result_assert_eq(rv, -1, "Expected the sendto to fail")?;
+        test_utils::result_assert_eq(test_utils::get_errno(), libc::EPIPE, "Unexpected errno")?;
+        test_utils::result_assert_eq(NUM_SIGPIPE.load(Ordering::SeqCst), 1, "Expected SIGPIPE")?;
+
+        // a sendto with MSG_NOSIGNAL fails the same way, but doesn't
+        let rv = sendto(libc::MSG_NOSIGNAL);
+        test_utils::result_assert_eq(rv, -1, "Expected the sendto to fail")?;
+        test_utils::result_assert_eq(test_utils::get_errno(), libc::EPIPE, "Unexpected errno")?;
+        test_utils::result_assert_eq(NUM_SIGPIPE.load(Ordering::SeqCst), 1, "Unexpected SIGPIPE")?;
+
+        // a write has no flags, so always raises SIGPIPE
+        let rv = unsafe { libc::write(fd_client, buf_ptr, buf.len()) };
+        test_utils::result_assert_eq(rv, -1, "Expected the write to fail")?;
+        test_utils::result_assert_eq(test_utils::get_errno(), libc::EPIPE, "Unexpected errno")?;
+        test_utils::result_assert_eq(NUM_SIGPIPE.load(Ordering::SeqCst), 2, "Expected SIGPIPE")?;
+
+        Ok(())
+    });
+
+    unsafe { sigaction(Signal::SIGPIPE, &old_action) }.unwrap();
+
+    result
+}
+
+/// Test that a multi-iovec `writev()` to a unix stream socket with a nearly-full send buffer returns
+/// a short count, and that the next `writev()` fails with `EAGAIN` rather than writing anything.
+fn test_unix_stream_short_writev() -> Result<(), String> {
+    let mut fds = [-1; 2];
+    let rv = unsafe {
+        libc::socketpair(
+            libc::AF_UNIX,
+            libc::SOCK_STREAM | libc::SOCK_NONBLOCK,
+            0,
+            fds.as_mut_ptr(),
+        )
+    };
+    assert_eq!(rv, 0);
+    let [fd_client, fd_peer] = fds;
+
+    test_utils::run_and_close_fds(&[fd_client, fd_peer], || {
+        // partially fill the send buffer
+        let first_chunk = vec![0xFFu8; 10_000];
+        let rv = nix::unistd::write(fd_client, &first_chunk).unwrap();
+        test_utils::result_assert_eq(rv, first_chunk.len(), "Unexpected write size")?;
+
+        // a message that is much larger than the send buffer, split into many iovecs that each fit
+        const NUM_IOVS: usize = 64;
+        const IOV_LEN: usize = 16_384;
+        let segments: Vec<Vec<u8>> = (0..NUM_IOVS).map(|i| vec![i as u8; IOV_LEN]).collect();
+        let iovs: Vec<libc::iovec> = segments
+            .iter()
+            .map(|x| libc::iovec {
+                iov_base: x.as_ptr() as *mut libc::c_void,
+                iov_len: x.len(),
+            })
+            .collect();
+
+        // should write as much as fits, spanning several iovecs, and not fail
+        let rv = unsafe { libc::writev(fd_client, iovs.as_ptr(), iovs.len() as libc::c_int) };
+        test_utils::result_assert(rv > 0, &format!("Expected a short write, got {rv}"))?;
+        let num_written = usize::try_from(rv).unwrap();
+        test_utils::result_assert(
+            num_written > IOV_LEN && num_written < NUM_IOVS * IOV_LEN,
+            &format!("Expected a short write spanning several iovecs, got {num_written}"),
+        )?;
+
+        // the buffer is now full, so nothing more can be written
+        let rv = unsafe { libc::writev(fd_client, iovs.as_ptr(), iovs.len() as libc::c_int) };
+        test_utils::result_assert_eq(rv, -1, "Expected the writev to fail")?;
+        test_utils::result_assert_eq(test_utils::get_errno(), libc::EAGAIN, "Unexpected errno")?;
+
+        // the peer should receive exactly the bytes that were reported as written
+        let mut received = Vec::new();
+        let mut recv_buf = vec![0u8; 65_536];
+        loop {
+            match nix::unistd::read(fd_peer, &mut recv_buf) {
+                Ok(n) => received.extend_from_slice(&recv_buf[..n]),
+                Err(nix::errno::Errno::EAGAIN) => break,
+                Err(e) => return Err(format!("Unexpected read error: {e}")),
+            }
+        }
+
+        let expected: Vec<u8> = first_chunk
+            .iter()
+            .copied()
+            .chain(segments.iter().flatten().copied().take(num_written))
+            .collect();
+        test_utils::result_assert_eq(received.len(), expected.len(), "Unexpected read size")?;
+        test_utils::result_assert(received == expected, "Unexpected data received")?;
+
+        Ok(())
+    })
+}
+
+/// Test that enabling `SO_TIMESTAMP` or `SO_TIMESTAMPNS` on a UDP socket causes recvmsg() to return
+/// a control message with the time that the datagram was received.
+fn test_udp_recv_timestamp(optname: libc::c_int) -> Result<(), String> {
+    let (fd_client, fd_peer) = socket_init_helper(
+        SocketInitMethod::Inet,
+        libc::SOCK_DGRAM,
+        libc::SOCK_NONBLOCK,
+        /* bind_client = */ false,
+    );
+
+    let get_timestamp_opt = |optname| {
+        let mut val: libc::c_int = -1;
+        let mut val_len = std::mem::size_of_val(&val) as libc::socklen_t;
+        let rv = unsafe {
+            libc::getsockopt(
+                fd_peer,
+                libc::SOL_SOCKET,
+                optname,
+                std::ptr::from_mut(&mut val) as *mut libc::c_void,
+                &mut val_len,
+            )
+        };
+        assert_eq!(rv, 0);
+        assert_eq!(val_len as usize, std::mem::size_of_val(&val));
+        val
+    };
+
+    let set_timestamp_opt = |val: libc::c_int| {
+        let rv = unsafe {
+            libc::setsockopt(
+                fd_peer,
+                libc::SOL_SOCKET,
+                optname,
+                std::ptr::from_ref(&val) as *const libc::c_void,
+                std::mem::size_of_val(&val) as libc::socklen_t,
+            )
+        };
+        assert_eq!(rv, 0);
+    };
+
+    // the other timestamp option, which should stay disabled
+    let other_optname = match optname {
+        libc::SO_TIMESTAMP => libc::SO_TIMESTAMPNS,
+        libc::SO_TIMESTAMPNS => libc::SO_TIMESTAMP,
+        _ => unimplemented!(),
+    };
+
+    // send a message and return it as received by recvmsg(), along with any timestamp, and the time
+    // that the message was sent
+    let send_and_recv = || {
+        let send_time_before = std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap();
+        assert_eq!(
+            nix::sys::socket::send(fd_client, &[1, 2, 3], MsgFlags::empty()),
+            Ok(3)
+        );
+        let send_time_after = std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap();
+
+        // shadow needs to run events, but we also sleep so that we make the recvmsg() call much
+        // later than the send() call
+        std::thread::sleep(std::time::Duration::from_millis(50));
+
+        let mut recv_buf = [0u8; 3];
+        let mut recv_iov = libc::iovec {
+            iov_base: recv_buf.as_mut_ptr() as *mut libc::c_void,
+            iov_len: recv_buf.len(),
+        };
+
+        // large enough for either timestamp type
+        let mut recv_control = [0u64; 8];
+        let mut recv_msg = libc::msghdr {
+            msg_name: std::ptr::null_mut(),
+            msg_namelen: 0,
+            msg_iov: &mut recv_iov,
+            msg_iovlen: 1,
+            msg_control: recv_control.as_mut_ptr() as *mut libc::c_void,
+            msg_controllen: std::mem::size_of_val(&recv_control),
+            msg_flags: 0,
+        };
+
+        let rv = unsafe { libc::recvmsg(fd_peer, &mut recv_msg, 0) };
+        assert_eq!(rv, recv_buf.len() as isize);
+        assert_eq!(recv_buf, [1, 2, 3]);
+        assert_eq!(recv_msg.msg_flags & libc::MSG_CTRUNC, 0);
+
+        let cmsg = unsafe { libc::CMSG_FIRSTHDR(&recv_msg) };
+        if cmsg.is_null() {
+            assert_eq!(recv_msg.msg_controllen, 0);
+            return (None, send_time_before, send_time_after);
+        }
+
+        let (cmsg_level, cmsg_type, cmsg_len) =
+            unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type, (*cmsg).cmsg_len) };
+        assert_eq!(cmsg_level, libc::SOL_SOCKET);
+
+        let recv_time = match cmsg_type {
+            libc::SCM_TIMESTAMP => {
+                let size = std::mem::size_of::<libc::timeval>();
+                assert_eq!(cmsg_len, unsafe { libc::CMSG_LEN(size as u32) } as usize);
+                let time = unsafe {
+                    std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::timeval)
+                };
+                std::time::Duration::from_secs(time.tv_sec.try_into().unwrap())
+                    + std::time::Duration::from_micros(time.tv_usec.try_into().unwrap())
+            }
+            libc::SCM_TIMESTAMPNS => {
+                let size = std::mem::size_of::<libc::timespec>();
+                assert_eq!(cmsg_len, unsafe { libc::CMSG_LEN(size as u32) } as usize);
+                let time = unsafe {
+                    std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::timespec)
+                };
+                std::time::Duration::from_secs(time.tv_sec.try_into().unwrap())
+                    + std::time::Duration::from_nanos(time.tv_nsec.try_into().unwrap())
+            }
+            x => panic!("Unexpected control message type {x}"),
+        };
+
+        assert!(unsafe { libc::CMSG_NXTHDR(&recv_msg, cmsg) }.is_null());
+
+        (
+            Some((cmsg_type, recv_time)),
+            send_time_before,
+            send_time_after,
+        )
+    };
+
+    test_utils::run_and_close_fds(&[fd_client, fd_peer], || {
+        // timestamps are disabled by default
+        test_utils::result_assert_eq(get_timestamp_opt(optname), 0, "Unexpected option value")?;
+        let (timestamp, _, _) = send_and_recv();
+        test_utils::result_assert(timestamp.is_none(), "Unexpected timestamp")?;
+
+        set_timestamp_opt(1);
+        test_utils::result_assert_eq(get_timestamp_opt(optname), 1, "Unexpected option value")?;
+        test_utils::result_assert_eq(
+            get_timestamp_opt(other_optname),
+            0,
+            "Unexpected option value",
+        )?;
+
+        let (timestamp, send_time_before, send_time_after) = send_and_recv();
+        let (cmsg_type, recv_time) = timestamp.ok_or("Expected a timestamp")?;
+
+        let expected_type = match optname {
+            libc::SO_TIMESTAMP => libc::SCM_TIMESTAMP,
+            libc::SO_TIMESTAMPNS => libc::SCM_TIMESTAMPNS,
+            _ => unimplemented!(),
+        };
+        test_utils::result_assert_eq(cmsg_type, expected_type, "Unexpected control message")?;
+
+        // The message is only travelling over localhost, so the receive time should be the send
+        // time plus a negligible path latency. Since it's localhost, the packet may be given to the
+        // receiving socket within the send() call, so the receive time is somewhere between our
+        // measurements before and after the send() call. We use a small threshold which should be
+        // much shorter than the sleep in `send_and_recv()`.
+        let threshold = std::time::Duration::from_millis(1);
+        test_utils::result_assert(
+            recv_time + threshold >= send_time_before && recv_time <= send_time_after + threshold,
+            &format!(
+                "Receive time {recv_time:?} is not between the send times {send_time_before:?} \
+                 and {send_time_after:?}"
+            ),
+        )?;
+
+        // disabling the option should disable timestamps
+        set_timestamp_opt(0);
+        test_utils::result_assert_eq(get_timestamp_opt(optname), 0, "Unexpected option value")?;
+        let (timestamp, _, _) = send_and_recv();
+        test_utils::result_assert(timestamp.is_none(), "Unexpected timestamp")?;
+
+        Ok(())
+    })
+}
+
+/// Test that enabling `IP_PKTINFO` on a UDP socket bound to `INADDR_ANY` causes recvmsg() to return
+/// a control message with the receiving interface and address.
+fn test_udp_recv_pktinfo() -> Result<(), String> {
+    let fd_server =
+        unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_NONBLOCK, 0) };
+    let fd_client =
+        unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_NONBLOCK, 0) };
+    assert!(fd_server >= 0);
+    assert!(fd_client >= 0);
+
+    // bind the server to the wildcard address
+    let mut server_addr = libc::sockaddr_in {
+        sin_family: libc::AF_INET as u16,
+        sin_port: 0,
+        sin_addr: libc::in_addr {
+            s_addr: libc::INADDR_ANY.to_be(),
+        },
+        sin_zero: [0; 8],
+    };
+    let mut server_addr_len = std::mem::size_of_val(&server_addr) as libc::socklen_t;
+    let rv = unsafe {
+        libc::bind(
+            fd_server,
+            std::ptr::from_ref(&server_addr) as *const libc::sockaddr,
+            server_addr_len,
+        )
+    };
+    assert_eq!(rv, 0);
+    let rv = unsafe {
+        libc::getsockname(
+            fd_server,
+            std::ptr::from_mut(&mut server_addr) as *mut libc::sockaddr,
+            &mut server_addr_len,
+        )
+    };
+    assert_eq!(rv, 0);
+
+    let get_pktinfo_opt = || {
+        let mut val: libc::c_int = -1;
+        let mut val_len = std::mem::size_of_val(&val) as libc::socklen_t;
+        let rv = unsafe {
+            libc::getsockopt(
+                fd_server,
+                libc::IPPROTO_IP,
+                libc::IP_PKTINFO,
+                std::ptr::from_mut(&mut val) as *mut libc::c_void,
+                &mut val_len,
+            )
+        };
+        assert_eq!(rv, 0);
+        assert_eq!(val_len as usize, std::mem::size_of_val(&val));
+        val
+    };
+
+    test_utils::run_and_close_fds(&[fd_server, fd_client], || {
+        test_utils::result_assert_eq(get_pktinfo_opt(), 0, "Unexpected option value")?;
+
+        let val: libc::c_int = 1;
+        let rv = unsafe {
+            libc::setsockopt(
+                fd_server,
+                libc::IPPROTO_IP,
+                libc::IP_PKTINFO,
+                std::ptr::from_ref(&val) as *const libc::c_void,
+                std::mem::size_of_val(&val) as libc::socklen_t,
+            )
+        };
+        test_utils::result_assert_eq(rv, 0, "setsockopt() failed")?;
+        test_utils::result_assert_eq(get_pktinfo_opt(), 1, "Unexpected option value")?;
+
+        // send to the server's port on the loopback address
+        let dst_addr = libc::sockaddr_in {
+            sin_addr: libc::in_addr {
+                s_addr: libc::INADDR_LOOPBACK.to_be(),
+            },
+            ..server_addr
+        };
+        let send_buf = [1u8, 2, 3];
+        let rv = unsafe {
+            libc::sendto(
+                fd_client,
+                send_buf.as_ptr() as *const libc::c_void,
+                send_buf.len(),
+                0,
+                std::ptr::from_ref(&dst_addr) as *const libc::sockaddr,
+                std::mem::size_of_val(&dst_addr) as libc::socklen_t,
+            )
+        };
+        test_utils::result_assert_eq(rv, send_buf.len() as isize, "sendto() failed")?;
+
+        // shadow needs to run events
+        assert_eq!(unsafe { libc::usleep(10000) }, 0);
+
+        let mut recv_buf = [0u8; 3];
+        let mut recv_iov = libc::iovec {
+            iov_base: recv_buf.as_mut_ptr() as *mut libc::c_void,
+            iov_len: recv_buf.len(),
+        };
+
+        let pktinfo_size = std::mem::size_of::<libc::in_pktinfo>() as u32;
+        let mut recv_control = [0u64; 8];
+        let mut recv_msg = libc::msghdr {
+            msg_name: std::ptr::null_mut(),
+            msg_namelen: 0,
+            msg_iov: &mut recv_iov,
+            msg_iovlen: 1,
+            msg_control: recv_control.as_mut_ptr() as *mut libc::c_void,
+            msg_controllen: std::mem::size_of_val(&recv_control),
+            msg_flags: 0,
+        };
+
+        let rv = unsafe { libc::recvmsg(fd_server, &mut recv_msg, 0) };
+        test_utils::result_assert_eq(rv, recv_buf.len() as isize, "recvmsg() failed")?;
+        test_utils::result_assert_eq(recv_buf, send_buf, "Unexpected payload")?;
+        test_utils::result_assert_eq(
+            recv_msg.msg_controllen,
+            unsafe { libc::CMSG_SPACE(pktinfo_size) } as usize,
+            "Unexpected control length",
+        )?;
+
+        let cmsg = unsafe { libc::CMSG_FIRSTHDR(&recv_msg) };
+        assert!(!cmsg.is_null());
+        let (cmsg_level, cmsg_type, cmsg_len) =
+            unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type, (*cmsg).cmsg_len) };
+        test_utils::result_assert_eq(cmsg_level, libc::IPPROTO_IP, "Unexpected cmsg level")?;
+        test_utils::result_assert_eq(cmsg_type, libc::IP_PKTINFO, "Unexpected cmsg type")?;
+        test_utils::result_assert_eq(
+            cmsg_len,
+            unsafe { libc::CMSG_LEN(pktinfo_size) } as usize,
+            "Unexpected cmsg length",
+        )?;
+
+        let pktinfo =
+            unsafe { std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::in_pktinfo) };
+        // the loopback interface is the first interface
+        test_utils::result_assert_eq(pktinfo.ipi_ifindex, 1, "Unexpected interface index")?;
+        test_utils::result_assert_eq(
+            pktinfo.ipi_spec_dst.s_addr,
+            dst_addr.sin_addr.s_addr,
+            "Unexpected local address",
+        )?;
+        test_utils::result_assert_eq(
+            pktinfo.ipi_addr.s_addr,
+            dst_addr.sin_addr.s_addr,
+            "Unexpected destination address",
+        )?;
+
+        Ok(())
+    })
+}
+
+/// Test that when recvmsg() on a UDP socket is given a payload buffer and control buffer that are
+/// both too small, it returns the datagram's source address along with both `MSG_TRUNC` and
+/// `MSG_CTRUNC`.
+fn test_udp_recvmsg_truncated() -> Result<(), String> {
+    // bind the socket to an ephemeral loopback port and return the bound address
+    let bind_loopback = |fd| {
+        let mut addr = libc::sockaddr_in {
+            sin_family: libc::AF_INET as u16,
+            sin_port: 0,
+            sin_addr: libc::in_addr {
+                s_addr: libc::INADDR_LOOPBACK.to_be(),
+            },
+            sin_zero: [0; 8],
+        };
+        let mut addr_len = std::mem::size_of_val(&addr) as libc::socklen_t;
+
+        let rv = unsafe {
+            libc::bind(
+                fd,
+                std::ptr::from_ref(&addr) as *const libc::sockaddr,
+                addr_len,
+            )
+        };
+        assert_eq!(rv, 0);
+
+        let rv = unsafe {
+            libc::getsockname(
+                fd,
+                std::ptr::from_mut(&mut addr) as *mut libc::sockaddr,
+                &mut addr_len,
+            )
+        };
+        assert_eq!(rv, 0);
+
+        addr
+    };
+
+    let new_socket = || {
+        let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_NONBLOCK, 0) };
+        assert!(fd >= 0);
+        fd
+    };
+
+    let dst_fd = new_socket();
+    let src_fd = new_socket();
+
+    let dst_addr = bind_loopback(dst_fd);
+    let src_addr = bind_loopback(src_fd);
+
+    test_utils::run_and_close_fds(&[dst_fd, src_fd], || {
+        // enable timestamps so that the datagram has a control message
+        let val: libc::c_int = 1;
+        let rv = unsafe {
+            libc::setsockopt(
+                dst_fd,
+                libc::SOL_SOCKET,
+                libc::SO_TIMESTAMP,
+                std::ptr::from_ref(&val) as *const libc::c_void,
+                std::mem::size_of_val(&val) as libc::socklen_t,
+            )
+        };
+        test_utils::result_assert_eq(rv, 0, "setsockopt() failed")?;
+
+        let send_buf = [1u8, 2, 3, 4, 5, 6, 7, 8];
+        let rv = unsafe {
+            libc::sendto(
+                src_fd,
+                send_buf.as_ptr() as *const libc::c_void,
+                send_buf.len(),
+                0,
+                std::ptr::from_ref(&dst_addr) as *const libc::sockaddr,
+                std::mem::size_of_val(&dst_addr) as libc::socklen_t,
+            )
+        };
+        test_utils::result_assert_eq(rv, send_buf.len() as isize, "sendto() failed")?;
+
+        // shadow needs to run events
+        assert_eq!(unsafe { libc::usleep(10000) }, 0);
+
+        // a payload buffer that is smaller than the datagram
+        let mut recv_buf = [0u8; 3];
+        let mut recv_iov = libc::iovec {
+            iov_base: recv_buf.as_mut_ptr() as *mut libc::c_void,
+            iov_len: recv_buf.len(),
+        };
+
+        // a control buffer with only enough space for the control message header
+        let recv_control_len = unsafe { libc::CMSG_LEN(0) } as usize;
+        let mut recv_control = [0u64; 8];
+
+        let mut recv_addr: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+        let mut recv_msg = libc::msghdr {
+            msg_name: std::ptr::from_mut(&mut recv_addr) as *mut libc::c_void,
+            msg_namelen: std::mem::size_of_val(&recv_addr) as libc::socklen_t,
+            msg_iov: &mut recv_iov,
+            msg_iovlen: 1,
+            msg_control: recv_control.as_mut_ptr() as *mut libc::c_void,
+            msg_controllen: recv_control_len,
+            msg_flags: 0,
+        };
+
+        let rv = unsafe { libc::recvmsg(dst_fd, &mut recv_msg, 0) };
+        test_utils::result_assert_eq(rv, recv_buf.len() as isize, "recvmsg() failed")?;
+        test_utils::result_assert_eq(recv_buf, [1, 2, 3], "Unexpected payload")?;
+
+        test_utils::result_assert(
+            recv_msg.msg_flags & libc::MSG_TRUNC != 0,
+            "Expected MSG_TRUNC",
+        )?;
+        test_utils::result_assert(
+            recv_msg.msg_flags & libc::MSG_CTRUNC != 0,
+            "Expected MSG_CTRUNC",
+        )?;
+
+        // the source address should be returned
+        test_utils::result_assert_eq(
+            recv_msg.msg_namelen as usize,
+            std::mem::size_of_val(&recv_addr),
+            "Unexpected address length",
+        )?;
+        test_utils::result_assert_eq(
+            recv_addr.sin_family,
+            src_addr.sin_family,
+            "Unexpected address family",
+        )?;
+        test_utils::result_assert_eq(recv_addr.sin_port, src_addr.sin_port, "Unexpected port")?;
+        test_utils::result_assert_eq(
+            recv_addr.sin_addr.s_addr,
+            src_addr.sin_addr.s_addr,
+            "Unexpected address",
+        )?;
+
+        // only the truncated header of the timestamp control message should have been written
+        test_utils::result_assert_eq(
+            recv_msg.msg_controllen,
+            recv_control_len,
+            "Unexpected control length",
+        )?;
+
+        let cmsg = unsafe { libc::CMSG_FIRSTHDR(&recv_msg) };
+        assert!(!cmsg.is_null());
+        let (cmsg_level, cmsg_type, cmsg_len) =
+            unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type, (*cmsg).cmsg_len) };
+        test_utils::result_assert_eq(cmsg_level, libc::SOL_SOCKET, "Unexpected cmsg level")?;
+        test_utils::result_assert_eq(cmsg_type, libc::SCM_TIMESTAMP, "Unexpected cmsg type")?;
+        test_utils::result_assert_eq(cmsg_len, recv_control_len, "Unexpected cmsg length")?;
+
+        // the rest of the datagram should have been discarded
+        let rv = unsafe {
+            libc::recv(
+                dst_fd,
+                recv_buf.as_mut_ptr() as *mut libc::c_void,
+                recv_buf.len(),
+                0,
+            )
+        };
+        test_utils::result_assert_eq(rv, -1, "Expected no more datagrams")?;
+        test_utils::result_assert_eq(test_utils::get_errno(), libc::EAGAIN, "Unexpected errno")?;
+
+        Ok(())
+    })
+}
+
+/// Test that recvmmsg() sets the length and flags of each message separately when only some of the
+/// datagrams are truncated.
+fn test_recvmmsg_truncated(init_method: SocketInitMethod) -> Result<(), String> {
+    let (fd_client, fd_server) = socket_init_helper(
+        init_method,
+        libc::SOCK_DGRAM,
+        libc::SOCK_NONBLOCK,
+        /* bind_client = */ false,
+    );
+
+    test_utils::run_and_close_fds(&[fd_client, fd_server], || {
+        let datagrams: [&[u8]; 3] = [&[1, 2], &[3, 4, 5, 6, 7, 8, 9, 10], &[11, 12, 13, 14, 15]];
+
+        for datagram in datagrams {
+            simple_sendto_helper(SendRecvMethod::ToFrom, fd_client, datagram, &[], true)?;
+        }
+
+        // shadow needs to run events
+        assert_eq!(unsafe { libc::usleep(10000) }, 0);
+
+        // space for one more message than was sent
+        const NUM_MSGS: usize = 4;
+        const BUF_LEN: usize = 5;
+
+        let mut bufs = [[0u8; BUF_LEN]; NUM_MSGS];
+        let mut iovs = bufs.each_mut().map(|buf| libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        });
+        let mut msgs = iovs.each_mut().map(|iov| libc::mmsghdr {
+            msg_hdr: libc::msghdr {
+                msg_name: std::ptr::null_mut(),
+                msg_namelen: 0,
+                msg_iov: iov,
+                msg_iovlen: 1,
+                msg_control: std::ptr::null_mut(),
+                msg_controllen: 0,
+                // the flags should be overwritten
+                msg_flags: libc::MSG_TRUNC | libc::MSG_CTRUNC,
+            },
+            msg_len: 0,
+        });
+
+        let rv = test_utils::check_system_call!(
+            || unsafe {
+                libc::recvmmsg(
+                    fd_server,
+                    msgs.as_mut_ptr(),
+                    msgs.len() as libc::c_uint,
+                    0,
+                    std::ptr::null_mut(),
+                )
+            },
+            &[],
+        )?;
+        test_utils::result_assert_eq(rv, 3, "Unexpected number of messages")?;
+
+        let msg_lens = msgs[..3].iter().map(|msg| msg.msg_len).collect::<Vec<_>>();
+        let msg_flags = msgs[..3]
+            .iter()
+            .map(|msg| msg.msg_hdr.msg_flags)
+            .collect::<Vec<_>>();
+        test_utils::result_assert_eq(msg_lens, vec![2, 5, 5], "Unexpected message lengths")?;
+        test_utils::result_assert_eq(
+            msg_flags,
+            vec![0, libc::MSG_TRUNC, 0],
+            "Unexpected message flags",
+        )?;
+
+        // the truncated part of the second datagram is discarded
+        test_utils::result_assert_eq(&bufs[0][..2], &[1, 2][..], "Unexpected payload")?;
+        test_utils::result_assert_eq(bufs[1], [3, 4, 5, 6, 7], "Unexpected payload")?;
+        test_utils::result_assert_eq(bufs[2], [11, 12, 13, 14, 15], "Unexpected payload")?;
+
+        // the unused message shouldn't have been modified
+        test_utils::result_assert_eq(msgs[3].msg_len, 0, "Unexpected message length")?;
+
+        // there are no datagrams left
+        test_utils::check_system_call!(
+            || unsafe {
+                libc::recvmmsg(
+                    fd_server,
+                    msgs.as_mut_ptr(),
+                    msgs.len() as libc::c_uint,
+                    0,
+                    std::ptr::null_mut(),
+                )
+            },
+            &[libc::EAGAIN],
+        )?;
+
+        Ok(())
+    })
+}
+
+/// Test that a write to a TCP socket whose non-blocking connect() is still in progress fails with
+/// `EAGAIN` if the socket is non-blocking, or otherwise blocks until the connection is established.
+fn test_write_while_connecting(nonblocking: bool) -> Result<(), String> {
+    let fd_client =
+        unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM | libc::SOCK_NONBLOCK, 0) };
+    let fd_server = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+    assert!(fd_client >= 0);
+    assert!(fd_server >= 0);
+
+    let (server_addr, server_addr_len) = autobind_helper(fd_server, libc::AF_INET);
+    assert_eq!(unsafe { libc::listen(fd_server, 10) }, 0);
+
+    test_utils::run_and_close_fds(&[fd_client, fd_server], || {
+        let rv = unsafe { libc::connect(fd_client, server_addr.as_ptr(), server_addr_len) };
+        test_utils::result_assert_eq(rv, -1, "Expected connect() to not complete immediately")?;
+        test_utils::result_assert_eq(
+            test_utils::get_errno(),
+            libc::EINPROGRESS,
+            "Expected EINPROGRESS",
+        )?;
+
+        if !nonblocking {
+            nix::fcntl::fcntl(
+                fd_client,
+                nix::fcntl::FcntlArg::F_SETFL(nix::fcntl::OFlag::empty()),
+            )
+            .unwrap();
+        }
+
+        let buf = [1u8, 2, 3];
+        let mut rv = nix::unistd::write(fd_client, &buf);
+
+        if nonblocking {
+            // shadow needs to run events before the connection is established, but linux may have
+            // already completed the handshake over the loopback interface
+            if running_in_shadow() || rv.is_err() {
+                test_utils::result_assert_eq(
+                    rv,
+                    Err(nix::errno::Errno::EAGAIN),
+                    "Expected EAGAIN while connecting",
+                )?;
+
+                // wait for the connection to be established
+                test_utils::result_assert(
+                    test_utils::is_writable(fd_client, 1000).unwrap(),
+                    "Socket didn't become writable",
+                )?;
+                rv = nix::unistd::write(fd_client, &buf);
+            }
+        }
+
+        test_utils::result_assert_eq(rv, Ok(3), "Expected the write to succeed once connected")?;
+
+        let fd_peer =
+            unsafe { libc::accept(fd_server, std::ptr::null_mut(), std::ptr::null_mut()) };
+        assert!(fd_peer >= 0);
+
+        test_utils::run_and_close_fds(&[fd_peer], || {
+            let mut recv_buf = [0u8; 10];
+            let rv = nix::unistd::read(fd_peer, &mut recv_buf);
+            test_utils::result_assert_eq(rv, Ok(3), "Unexpected number of bytes read")?;
+            test_utils::result_assert_eq(&recv_buf[..3], &buf[..], "Unexpected payload")
+        })
+    })
+}
+
+/// Test that a blocking recvfrom() and read() fail with `EAGAIN` once the socket's `SO_RCVTIMEO`
+/// timeout has passed.
+fn test_recv_timeout(init_method: SocketInitMethod, sock_type: libc::c_int) -> Result<(), String> {
+    let (fd_client, fd_server) =
+        socket_init_helper(init_method, sock_type, 0, /* bind_client = */ false);
+
+    test_utils::run_and_close_fds(&[fd_client, fd_server], || {
+        let timeout = libc::timeval {
+            tv_sec: 0,
+            tv_usec: 200_000,
+        };
+        let rv = unsafe {
+            libc::setsockopt(
+                fd_server,
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                std::ptr::from_ref(&timeout) as *const libc::c_void,
+                std::mem::size_of_val(&timeout) as libc::socklen_t,
+            )
+        };
+        assert_eq!(rv, 0);
+
+        let mut buf = [0u8; 10];
+
+        for name in ["recvfrom", "read"] {
+            let start = std::time::Instant::now();
+            let rv = match name {
+                "recvfrom" => unsafe {
+                    libc::recvfrom(
+                        fd_server,
+                        buf.as_mut_ptr() as *mut libc::c_void,
+                        buf.len(),
+                        0,
+                        std::ptr::null_mut(),
+                        std::ptr::null_mut(),
+                    )
+                },
+                "read" => unsafe {
+                    libc::read(fd_server, buf.as_mut_ptr() as *mut libc::c_void, buf.len())
+                },
+                _ => unreachable!(),
+            };
+            let errno = test_utils::get_errno();
+            let elapsed = start.elapsed();
+
+            test_utils::result_assert_eq(rv, -1, &format!("Expected {name}() to time out"))?;
+            test_utils::result_assert_eq(errno, libc::EAGAIN, "Expected EAGAIN")?;
+            test_utils::result_assert(
+                elapsed >= std::time::Duration::from_millis(200),
+                &format!("{name}() returned after only {elapsed:?}"),
+            )?;
+            test_utils::result_assert(
+                elapsed < std::time::Duration::from_millis(1000),
+                &format!("{name}() didn't return until {elapsed:?}"),
+            )?;
+        }
+
+        // data that arrives before the timeout is received as usual
+        simple_sendto_helper(SendRecvMethod::ToFrom, fd_client, &[1, 2, 3], &[], true)?;
+        let rv = unsafe {
+            libc::recvfrom(
+                fd_server,
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+                0,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            )
+        };
+        test_utils::result_assert_eq(rv, 3, "Expected to receive the data")?;
+
+        Ok(())
+    })
+}
+
+/// Test that a blocking sendto() on a UDP socket with a full send buffer blocks until the socket has
+/// sent some of its buffered datagrams to the network, and then sends the datagram.
+fn test_udp_blocking_send_full_buffer() -> Result<(), String> {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+    assert!(fd >= 0);
+
+    // use a small send buffer
+    let sndbuf: libc::c_int = 4096;
+    let rv = unsafe {
+        libc::setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_SNDBUF,
+            std::ptr::from_ref(&sndbuf) as *const libc::c_void,
+            std::mem::size_of_val(&sndbuf) as libc::socklen_t,
+        )
+    };
+    assert_eq!(rv, 0);
+
+    // the dummy host in the test config; packets sent to it are limited by the host's bandwidth, so
+    // the datagrams will queue in the send buffer
+    let dst_addr = nix::sys::socket::SockaddrIn::new(192, 168, 1, 100, 11111);
+    let buf = [1u8; 1000];
+
+    test_utils::run_and_close_fds(&[fd], || {
+        // send without blocking until the send buffer is full
+        let mut is_full = false;
+        for _ in 0..10_000 {
+            match nix::sys::socket::sendto(fd, &buf, &dst_addr, MsgFlags::MSG_DONTWAIT) {
+                Ok(n) => test_utils::result_assert_eq(n, buf.len(), "Unexpected send length")?,
+                Err(nix::errno::Errno::EAGAIN) => {
+                    is_full = true;
+                    break;
+                }
+                Err(e) => return Err(format!("Unexpected sendto() error: {e}")),
+            }
+        }
+        test_utils::result_assert(is_full, "The send buffer never became full")?;
+
+        test_utils::result_assert_eq(
+            poll_revents(fd, libc::POLLOUT) & libc::POLLOUT,
+            0,
+            "Socket with a full send buffer is writable",
+        )?;
+
+        // shadow doesn't move time forward for syscalls that don't block, so the time will only
+        // change if sendto() blocks until the buffered datagrams are sent
+        let time_before = std::time::Instant::now();
+
+        test_utils::result_assert_eq(
+            nix::sys::socket::sendto(fd, &buf, &dst_addr, MsgFlags::empty()),
+            Ok(buf.len()),
+            "Unexpected sendto() result",
+        )?;
+
+        test_utils::result_assert(
+            time_before.elapsed() > std::time::Duration::ZERO,
+            "sendto() didn't block",
+        )?;
+
+        Ok(())
+    })
+}
+
+/// Test that recvfrom() on an unconnected UDP socket returns the source address of each datagram
+/// when multiple senders have sent to it.
+fn test_udp_recvfrom_multiple_senders() -> Result<(), String> {
+    // bind the socket to an ephemeral loopback port and return the bound address
+    let bind_loopback = |fd| {
+        let mut addr = libc::sockaddr_in {
+            sin_family: libc::AF_INET as u16,
+            sin_port: 0,
+            sin_addr: libc::in_addr {
+                s_addr: libc::INADDR_LOOPBACK.to_be(),
+            },
+            sin_zero: [0; 8],
+        };
+        let mut addr_len = std::mem::size_of_val(&addr) as libc::socklen_t;
+
+        let rv = unsafe {
+            libc::bind(
+                fd,
+                std::ptr::from_ref(&addr) as *const libc::sockaddr,
+                addr_len,
+            )
+        };
+        assert_eq!(rv, 0);
+
+        let rv = unsafe {
+            libc::getsockname(
+                fd,
+                std::ptr::from_mut(&mut addr) as *mut libc::sockaddr,
+                &mut addr_len,
+            )
+        };
+        assert_eq!(rv, 0);
+
+        addr
+    };
+
+    let new_socket = || {
+        let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_NONBLOCK, 0) };
+        assert!(fd >= 0);
+        fd
+    };
+
+    let dst_fd = new_socket();
+    let src_fds = [new_socket(), new_socket()];
+
+    let dst_addr = bind_loopback(dst_fd);
+    let src_addrs = src_fds.map(bind_loopback);
+
+    test_utils::run_and_close_fds(&[dst_fd, src_fds[0], src_fds[1]], || {
+        // interleave the senders; each datagram's payload is its index in this list
+        let senders = [0, 1, 1, 0];
+
+        for (i, &sender) in senders.iter().enumerate() {
+            let buf = [i as u8];
+            let rv = unsafe {
+                libc::sendto(
+                    src_fds[sender],
+                    buf.as_ptr() as *const libc::c_void,
+                    buf.len(),
+                    0,
+                    std::ptr::from_ref(&dst_addr) as *const libc::sockaddr,
+                    std::mem::size_of_val(&dst_addr) as libc::socklen_t,
+                )
+            };
+            test_utils::result_assert_eq(rv, 1, "sendto() failed")?;
+        }
+
+        // shadow needs to run events
+        assert_eq!(unsafe { libc::usleep(10000) }, 0);
+
+        for _ in 0..senders.len() {
+            let mut buf = [0u8; 10];
+            let mut addr: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+            let mut addr_len = std::mem::size_of_val(&addr) as libc::socklen_t;
+
+            let rv = unsafe {
+                libc::recvfrom(
+                    dst_fd,
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len(),
+                    0,
+                    std::ptr::from_mut(&mut addr) as *mut libc::sockaddr,
+                    &mut addr_len,
+                )
+            };
+            test_utils::result_assert_eq(rv, 1, "recvfrom() failed")?;
+
+            let expected = src_addrs[senders[usize::from(buf[0])]];
+
+            test_utils::result_assert_eq(
+                addr_len as usize,
+                std::mem::size_of_val(&addr),
+                "Unexpected address length",
+            )?;
+            test_utils::result_assert_eq(
+                addr.sin_addr.s_addr,
+                expected.sin_addr.s_addr,
+                "Unexpected source address",
+            )?;
+            test_utils::result_assert_eq(
+                addr.sin_port,
+                expected.sin_port,
+                "Unexpected source port",
+            )?;
+        }
+
+        Ok(())
+    })
+}
+
+// Test the behavior of loopback-bound UDP sockets when sendmsg() is used with an external address
+fn test_dgram_loopback_bound_sendmsg(
+    sys_method: SendRecvMethod,
+    flag: libc::c_int,
+) -> Result<(), String> {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | flag, 0) };
+    assert!(fd >= 0);
+
+    // bind the socket to loopback
+    {
+        let addr = libc::sockaddr_in {
+            sin_family: libc::AF_INET as u16,
+            sin_port: 0u16.to_be(),
+            sin_addr: libc::in_addr {
+                s_addr: libc::INADDR_LOOPBACK.to_be(),
+            },
+            sin_zero: [0; 8],
+        };
+        let rv = unsafe {
+            libc::bind(
+                fd,
+                std::ptr::from_ref(&addr) as *const libc::sockaddr,
+                std::mem::size_of_val(&addr) as u32,
+            )
+        };
+        eprintln!("errno: {}", test_utils::get_errno());
+        assert_eq!(rv, 0);
+    }
+
+    // we use a local network address here so that the tests
+    // running outside of shadow would only be trying to connect to a server on a local
+    // network rather than some random server on the internet
+    let other_ip: std::net::Ipv4Addr = "192.168.1.100".parse().unwrap();
+
+    let addr = libc::sockaddr_in {
+        sin_family: libc::AF_INET as u16,
+        sin_port: 11111u16.to_be(),
+        sin_addr: libc::in_addr {
+            s_addr: u32::from(other_ip).to_be(),
+        },
+        sin_zero: [0; 8],
+    };
+
+    let buf_send = vec![1u8; 200];
+    let args = SendtoArguments {
+        fd,
+        len: buf_send.len(),
+        buf: Some(&buf_send),
+        flags: 0,
+        addr: Some(SockAddr::Inet(addr)),
+        addr_len: std::mem::size_of_val(&addr) as u32,
+    };
+
+    check_send_call(&args, sys_method, &[libc::EINVAL], true)?;
+
+    Ok(())
+}
+
+/// Returns the events reported by a non-blocking poll() of the fd.
+fn poll_revents(fd: libc::c_int, events: libc::c_short) -> libc::c_short {
+    let mut pollfd = libc::pollfd {
+        fd,
+        events,
+        revents: 0,
+    };
+    let rv = unsafe { libc::poll(&mut pollfd, 1, 0) };
+    assert!(rv >= 0);
+    pollfd.revents
+}
+
+/// Returns true if a non-blocking select() reports an exceptional condition for the fd.
+fn select_exceptional(fd: libc::c_int) -> bool {
+    let mut exceptfds = unsafe { std::mem::zeroed::<libc::fd_set>() };
+    unsafe { libc::FD_SET(fd, &mut exceptfds) };
+    let mut timeout = libc::timeval {
+        tv_sec: 0,
+        tv_usec: 0,
+    };
+    let rv = unsafe {
+        libc::select(
+            fd + 1,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            &mut exceptfds,
+            &mut timeout,
+        )
+    };
+    assert!(rv >= 0);
+    unsafe { libc::FD_ISSET(fd, &exceptfds) }
+}
+
 /// A helper function to call sendto() and recvfrom() with valid values
 /// and a user-provided fd.
 fn fd_test_helper(
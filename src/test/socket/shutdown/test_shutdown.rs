@@ -154,6 +154,29 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
         }
     }
 
+    for &domain in [libc::AF_UNIX, libc::AF_INET].iter() {
+        tests.extend(vec![test_utils::ShadowTest::new(
+            &format!("test_rdwr_wakes_blocked <domain={}>", domain),
+            move || test_rdwr_wakes_blocked(domain),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        )])
+    }
+
+    for &flag in flags.iter() {
+        tests.extend(vec![
+            test_utils::ShadowTest::new(
+                &format!("test_unix_write_then_shutdown <flag={}>", flag),
+                move || test_unix_write_then_shutdown(flag),
+                set![TestEnv::Libc, TestEnv::Shadow],
+            ),
+            test_utils::ShadowTest::new(
+                &format!("test_unix_poll_after_peer_shutdown <flag={}>", flag),
+                move || test_unix_poll_after_peer_shutdown(flag),
+                set![TestEnv::Libc, TestEnv::Shadow],
+            ),
+        ])
+    }
+
     tests
 }
 
@@ -898,6 +921,209 @@ fn test_tcp_fin_correctness(domain: libc::c_int, flag: libc::c_int) -> Result<()
     })
 }
 
+/// Test that data written to a unix stream socket immediately before `shutdown(SHUT_WR)` is still
+/// received by the peer, followed by an EOF.
+fn test_unix_write_then_shutdown(flag: libc::c_int) -> Result<(), String> {
+    let mut fds = [-1; 2];
+    let rv =
+        unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM | flag, 0, fds.as_mut_ptr()) };
+    assert_eq!(rv, 0);
+    let [fd_client, fd_server] = fds;
+
+    test_utils::run_and_close_fds(&fds, || {
+        const NUM_BYTES: usize = 20000;
+        let buf: Vec<u8> = (0..NUM_BYTES).map(|x| x as u8).collect();
+
+        write_all(fd_client, &buf);
+
+        check_shutdown_call(
+            &ShutdownArguments {
+                fd: fd_client,
+                how: libc::SHUT_WR,
+            },
+            &[],
+        )?;
+
+        // read at the server until an EOF
+        let read_buf = read_all(fd_server);
+
+        test_utils::result_assert_eq(&read_buf, &buf, "Bytes written and read do not match.")?;
+
+        // the client can no longer write
+        let rv = unsafe {
+            libc::send(
+                fd_client,
+                buf.as_ptr() as *const libc::c_void,
+                1,
+                libc::MSG_NOSIGNAL,
+            )
+        };
+        test_utils::result_assert_eq(rv, -1, "Expected the send to fail")?;
+        test_utils::result_assert_eq(test_utils::get_errno(), libc::EPIPE, "Unexpected errno")?;
+
+        // but the server can still write to the client
+        write_all(fd_server, &buf[..1]);
+        let mut read_buf = [0u8; 1];
+        test_utils::result_assert_eq(
+            read_once(fd_client, &mut read_buf),
+            1,
+            "Expected to read a byte",
+        )?;
+
+        Ok(())
+    })
+}
+
+/// Test that after the peer of a unix stream socket runs `shutdown(SHUT_WR)`, the remaining data
+/// can be read followed by an EOF, and `poll` reports `POLLRDHUP`.
+fn test_unix_poll_after_peer_shutdown(flag: libc::c_int) -> Result<(), String> {
+    let mut fds = [-1; 2];
+    let rv =
+        unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM | flag, 0, fds.as_mut_ptr()) };
+    assert_eq!(rv, 0);
+    let [fd_client, fd_server] = fds;
+
+    test_utils::run_and_close_fds(&fds, || {
+        let poll_server = || {
+            let mut pfd = libc::pollfd {
+                fd: fd_server,
+                events: libc::POLLIN | libc::POLLRDHUP,
+                revents: 0,
+            };
+            assert_eq!(unsafe { libc::poll(&mut pfd, 1, 0) }, 1);
+            pfd.revents
+        };
+
+        write_all(fd_client, &[1, 2, 3]);
+
+        // the server has data to read, but the client hasn't shut down yet
+        test_utils::result_assert_eq(poll_server(), libc::POLLIN, "Unexpected poll events")?;
+
+        check_shutdown_call(
+            &ShutdownArguments {
+                fd: fd_client,
+                how: libc::SHUT_WR,
+            },
+            &[],
+        )?;
+
+        test_utils::result_assert_eq(
+            poll_server(),
+            libc::POLLIN | libc::POLLRDHUP,
+            "Unexpected poll events",
+        )?;
+
+        // the remaining data can still be read, followed by an EOF
+        let mut read_buf = [0u8; 10];
+        test_utils::result_assert_eq(read_once(fd_server, &mut read_buf), 3, "Expected 3 bytes")?;
+        test_utils::result_assert_eq(&read_buf[..3], &[1u8, 2, 3][..], "Unexpected bytes")?;
+        test_utils::result_assert_eq(read_once(fd_server, &mut read_buf), 0, "Expected an EOF")?;
+
+        test_utils::result_assert_eq(
+            poll_server(),
+            libc::POLLIN | libc::POLLRDHUP,
+            "Unexpected poll events",
+        )?;
+
+        Ok(())
+    })
+}
+
+/// Test that `shutdown(SHUT_RDWR)` wakes both a thread blocked in a read and a thread blocked in a
+/// write on the same socket.
+fn test_rdwr_wakes_blocked(domain: libc::c_int) -> Result<(), String> {
+    let (fd_client, fd_server) = if domain == libc::AF_UNIX {
+        let mut fds = [-1; 2];
+        let rv = unsafe { libc::socketpair(domain, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) };
+        assert_eq!(rv, 0);
+        (fds[0], fds[1])
+    } else {
+        setup_stream_sockets(domain, 0)
+    };
+
+    test_utils::run_and_close_fds(&[fd_client, fd_server], || {
+        // the server never reads, so a write at the client will block once its buffer is full
+        fill_send_buffer(fd_client);
+
+        let reader = std::thread::spawn(move || {
+            let mut buf = [0u8; 10];
+            let rv = read_once(fd_client, &mut buf);
+            (rv, test_utils::get_errno())
+        });
+
+        let writer = std::thread::spawn(move || {
+            let buf = [0u8; 10];
+            let rv = unsafe {
+                libc::send(
+                    fd_client,
+                    buf.as_ptr() as *const libc::c_void,
+                    buf.len(),
+                    libc::MSG_NOSIGNAL,
+                )
+            };
+            (rv, test_utils::get_errno())
+        });
+
+        // wait for both threads to block
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        check_shutdown_call(
+            &ShutdownArguments {
+                fd: fd_client,
+                how: libc::SHUT_RDWR,
+            },
+            &[],
+        )?;
+
+        let (rv, errno) = reader.join().unwrap();
+        test_utils::result_assert_eq(
+            rv,
+            0,
+            &format!("Expected the blocked read to return an EOF, but got errno {errno}"),
+        )?;
+
+        let (rv, errno) = writer.join().unwrap();
+        test_utils::result_assert_eq(rv, -1, "Expected the blocked send to fail")?;
+        test_utils::result_assert_eq(errno, libc::EPIPE, "Unexpected errno")?;
+
+        Ok(())
+    })
+}
+
+/// Write to the socket without blocking until no more data can be sent. The peer must not be
+/// reading from the socket.
+fn fill_send_buffer(fd: libc::c_int) {
+    let buf = [0u8; 4096];
+
+    loop {
+        let mut bytes_written = 0;
+
+        loop {
+            let rv = unsafe {
+                libc::send(
+                    fd,
+                    buf.as_ptr() as *const libc::c_void,
+                    buf.len(),
+                    libc::MSG_DONTWAIT | libc::MSG_NOSIGNAL,
+                )
+            };
+            if rv == -1 {
+                assert_eq!(test_utils::get_errno(), libc::EAGAIN);
+                break;
+            }
+            bytes_written += rv;
+        }
+
+        // stop once no data could be written
+        if bytes_written == 0 {
+            break;
+        }
+
+        // give any data in flight time to reach the peer, which may free up space in our buffer
+        assert_eq!(unsafe { libc::usleep(10000) }, 0);
+    }
+}
+
 fn check_shutdown_call(
     args: &ShutdownArguments,
     expected_errnos: &[libc::c_int],
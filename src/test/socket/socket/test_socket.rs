@@ -95,7 +95,12 @@ fn main() -> Result<(), String> {
         ErrorCondition {
             domain: Cond::Any,
             sock_type: Cond::Any,
-            flag: Cond::Not(&[0, libc::SOCK_NONBLOCK, libc::SOCK_CLOEXEC]),
+            flag: Cond::Not(&[
+                0,
+                libc::SOCK_NONBLOCK,
+                libc::SOCK_CLOEXEC,
+                libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+            ]),
             protocol: Cond::Any,
             expected_errno: Some(libc::EINVAL),
         },
@@ -221,7 +226,12 @@ fn get_passing_tests() -> Vec<(SocketFn, SocketArguments)> {
     // the different arguments to try (including invalid args)
     let domains = [libc::AF_INET, libc::AF_UNIX, 0xABBA];
     let sock_types = [libc::SOCK_STREAM, libc::SOCK_DGRAM, libc::SOCK_SEQPACKET];
-    let flags = [0, libc::SOCK_NONBLOCK, libc::SOCK_CLOEXEC];
+    let flags = [
+        0,
+        libc::SOCK_NONBLOCK,
+        libc::SOCK_CLOEXEC,
+        libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+    ];
     let protocols = [0, libc::IPPROTO_TCP, libc::IPPROTO_UDP];
 
     // product of the sets of arguments
@@ -268,7 +278,13 @@ fn get_all_tests() -> Vec<(SocketFn, SocketArguments)> {
         libc::SOCK_RDM,
         0xABBA,
     ];
-    let flags = [0, libc::SOCK_NONBLOCK, libc::SOCK_CLOEXEC, 0xABBA];
+    let flags = [
+        0,
+        libc::SOCK_NONBLOCK,
+        libc::SOCK_CLOEXEC,
+        libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+        0xABBA,
+    ];
     let protocols = [0, libc::IPPROTO_TCP, libc::IPPROTO_UDP];
 
     // product of the sets of arguments
@@ -432,5 +448,29 @@ fn check_socket_call(
         }
     }
 
+    if let Some(fd) = fd {
+        check_socket_flags(fd, args.flag)?;
+    }
+
     Ok(fd)
 }
+
+/// Check that the SOCK_NONBLOCK and SOCK_CLOEXEC flags given to `socket()` were applied to the new
+/// socket's file status and descriptor.
+fn check_socket_flags(fd: libc::c_int, flag: libc::c_int) -> Result<(), String> {
+    let status_flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    let descriptor_flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+    assert!(status_flags >= 0);
+    assert!(descriptor_flags >= 0);
+
+    test_utils::result_assert_eq(
+        status_flags & libc::O_NONBLOCK != 0,
+        flag & libc::SOCK_NONBLOCK != 0,
+        "Unexpected O_NONBLOCK status flag",
+    )?;
+    test_utils::result_assert_eq(
+        descriptor_flags & libc::FD_CLOEXEC != 0,
+        flag & libc::SOCK_CLOEXEC != 0,
+        "Unexpected FD_CLOEXEC descriptor flag",
+    )
+}
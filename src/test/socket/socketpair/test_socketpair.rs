@@ -47,11 +47,23 @@ fn main() -> Result<(), String> {
 }
 
 fn get_tests() -> Vec<test_utils::ShadowTest<Option<[libc::c_int; 2]>, String>> {
-    let mut tests: Vec<test_utils::ShadowTest<_, _>> = vec![test_utils::ShadowTest::new(
-        "test_null_fds",
-        test_null_fds,
-        set![TestEnv::Libc, TestEnv::Shadow],
-    )];
+    let mut tests: Vec<test_utils::ShadowTest<_, _>> = vec![
+        test_utils::ShadowTest::new(
+            "test_null_fds",
+            test_null_fds,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_invalid_flag",
+            test_invalid_flag,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_unnamed_peername",
+            test_unnamed_peername,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+    ];
 
     // tests to repeat for different socket options
     for &domain in [libc::AF_UNIX, libc::AF_LOCAL, libc::AF_INET].iter() {
@@ -104,6 +116,68 @@ fn test_null_fds() -> Result<Option<[libc::c_int; 2]>, String> {
     Ok(None)
 }
 
+/// Test socketpair with a socket type that has an unsupported flag bit set.
+fn test_invalid_flag() -> Result<Option<[libc::c_int; 2]>, String> {
+    for sock_type in [libc::SOCK_STREAM, libc::SOCK_DGRAM, libc::SOCK_SEQPACKET] {
+        // socketpair() may mutate fds
+        let mut args = SocketpairArguments {
+            domain: libc::AF_UNIX,
+            sock_type,
+            flag: 1 << 30,
+            protocol: 0,
+            fds: Some([44, 55]),
+        };
+
+        check_socketpair_call(&mut args, Some(&[libc::EINVAL]))?;
+
+        // the flag is checked before the domain
+        args.domain = libc::AF_INET;
+        check_socketpair_call(&mut args, Some(&[libc::EINVAL]))?;
+    }
+
+    Ok(None)
+}
+
+/// Test that getpeername on either end of a unix socketpair returns an unnamed address.
+fn test_unnamed_peername() -> Result<Option<[libc::c_int; 2]>, String> {
+    for sock_type in [libc::SOCK_STREAM, libc::SOCK_DGRAM, libc::SOCK_SEQPACKET] {
+        let mut fds = [-1; 2];
+        let rv = unsafe { libc::socketpair(libc::AF_UNIX, sock_type, 0, fds.as_mut_ptr()) };
+        assert_eq!(rv, 0);
+
+        test_utils::run_and_close_fds(&fds, || {
+            for fd in fds {
+                let mut addr = libc::sockaddr_un {
+                    sun_family: 123u16,
+                    sun_path: [1; 108],
+                };
+                let mut size = std::mem::size_of_val(&addr) as u32;
+
+                let rv = unsafe {
+                    libc::getpeername(
+                        fd,
+                        std::ptr::from_mut(&mut addr) as *mut libc::sockaddr,
+                        std::ptr::from_mut(&mut size),
+                    )
+                };
+                test_utils::result_assert_eq(rv, 0, "getpeername() failed")?;
+
+                // an unnamed address only has the address family
+                test_utils::result_assert_eq(size, 2, "Unexpected addr length")?;
+                test_utils::result_assert_eq(
+                    addr.sun_family,
+                    libc::AF_UNIX as u16,
+                    "Address family was not AF_UNIX",
+                )?;
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(None)
+}
+
 /// Test socketpair with various arguments.
 fn test_arguments(
     domain: libc::c_int,
@@ -154,6 +228,17 @@ fn test_arguments(
         return Ok(None);
     }
 
+    // both fds should only be close-on-exec if it was requested
+    let expected_fd_flags = if flag & libc::SOCK_CLOEXEC != 0 {
+        libc::FD_CLOEXEC
+    } else {
+        0
+    };
+    for fd in args.fds.unwrap() {
+        let fd_flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+        test_utils::result_assert_eq(fd_flags, expected_fd_flags, "Unexpected fd flags")?;
+    }
+
     Ok(args.fds)
 }
 
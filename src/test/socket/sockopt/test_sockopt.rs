@@ -67,8 +67,14 @@ fn main() -> Result<(), String> {
     let filter_libc_passing = std::env::args().any(|x| x == "--libc-passing");
     // should we summarize the results rather than exit on a failed test
     let summarize = std::env::args().any(|x| x == "--summarize");
+    // should we only run the tests for options that the new tcp stack supports?
+    let new_tcp = std::env::args().any(|x| x == "--new-tcp");
 
-    let mut tests = get_tests();
+    let mut tests = if new_tcp {
+        get_new_tcp_tests()
+    } else {
+        get_tests()
+    };
     if filter_shadow_passing {
         tests.retain(|x| x.passing(TestEnv::Shadow));
     }
@@ -99,6 +105,21 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
             test_non_socket_fd,
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
+        test_utils::ShadowTest::new(
+            "test_non_socket_file <pipe>",
+            || test_non_socket_file(NonSocketFile::Pipe),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_non_socket_file <eventfd>",
+            || test_non_socket_file(NonSocketFile::EventFd),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_non_socket_file <regular>",
+            || test_non_socket_file(NonSocketFile::Regular),
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
         test_utils::ShadowTest::new(
             "test_long_len",
             test_long_len,
@@ -141,6 +162,45 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
         ),
     ];
 
+    // linux's delayed ACK heuristics make the timing unpredictable, so only run in shadow
+    tests.push(test_utils::ShadowTest::new(
+        "test_tcp_quickack_latency",
+        test_tcp_quickack_latency,
+        set![TestEnv::Shadow],
+    ));
+
+    // linux's delayed ACK heuristics make the timing unpredictable, so only run in shadow
+    tests.push(test_utils::ShadowTest::new(
+        "test_tcp_nodelay_latency",
+        test_tcp_nodelay_latency,
+        set![TestEnv::Shadow],
+    ));
+    tests.push(test_utils::ShadowTest::new(
+        "test_tcp_nagle_delayed_ack_latency",
+        test_tcp_nagle_delayed_ack_latency,
+        set![TestEnv::Shadow],
+    ));
+
+    // the test system may not have an "eth0" interface, so only run in shadow
+    tests.push(test_utils::ShadowTest::new(
+        "test_so_bindtodevice_routing",
+        test_so_bindtodevice_routing,
+        set![TestEnv::Shadow],
+    ));
+
+    tests.push(test_utils::ShadowTest::new(
+        "test_so_dontroute_routing",
+        test_so_dontroute_routing,
+        set![TestEnv::Libc, TestEnv::Shadow],
+    ));
+
+    // the result depends on the system's linux security module, so only run in shadow
+    tests.push(test_utils::ShadowTest::new(
+        "test_so_peersec",
+        test_so_peersec,
+        set![TestEnv::Shadow],
+    ));
+
     let domains = [libc::AF_INET];
     let sock_types = [libc::SOCK_STREAM, libc::SOCK_DGRAM];
 
@@ -170,6 +230,11 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
                     move || test_so_type(domain, sock_type),
                     set![TestEnv::Libc, TestEnv::Shadow],
                 ),
+                test_utils::ShadowTest::new(
+                    &append_args("test_so_type_huge_len"),
+                    move || test_so_type_huge_len(domain, sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ),
                 test_utils::ShadowTest::new(
                     &append_args("test_so_domain"),
                     move || test_so_domain(domain, sock_type),
@@ -197,6 +262,38 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
                     // above test
                     set![TestEnv::Libc],
                 ),
+                test_utils::ShadowTest::new(
+                    &append_args("test_so_mark"),
+                    move || test_so_mark(domain, sock_type),
+                    // linux requires CAP_NET_ADMIN to set the mark
+                    set![TestEnv::Shadow],
+                ),
+                test_utils::ShadowTest::new(
+                    &append_args("test_so_bindtodevice"),
+                    move || test_so_bindtodevice(domain, sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ),
+                test_utils::ShadowTest::new(
+                    &append_args("test_so_dontroute"),
+                    move || test_so_dontroute(domain, sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ),
+                test_utils::ShadowTest::new(
+                    &append_args("test_so_priority"),
+                    move || test_so_priority(domain, sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ),
+                test_utils::ShadowTest::new(
+                    &append_args("test_so_busy_poll"),
+                    move || test_so_busy_poll(domain, sock_type),
+                    // linux may require CAP_NET_ADMIN to increase the busy polling timeout
+                    set![TestEnv::Shadow],
+                ),
+                test_utils::ShadowTest::new(
+                    &append_args("test_so_incoming_cpu"),
+                    move || test_so_incoming_cpu(domain, sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ),
                 test_utils::ShadowTest::new(
                     &append_args("test_tcp_info"),
                     move || test_tcp_info(domain, sock_type),
@@ -212,15 +309,124 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
                     move || test_tcp_congestion(domain, sock_type),
                     set![TestEnv::Libc, TestEnv::Shadow],
                 ),
+                test_utils::ShadowTest::new(
+                    &append_args("test_tcp_quickack"),
+                    move || test_tcp_quickack(domain, sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ),
             ];
 
             tests.extend(more_tests);
+
+            // urgent data only applies to stream sockets
+            if sock_type == libc::SOCK_STREAM {
+                tests.push(test_utils::ShadowTest::new(
+                    &append_args("test_so_oobinline"),
+                    move || test_so_oobinline(domain, sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ));
+            }
+        }
+    }
+
+    let unix_sock_types = [libc::SOCK_STREAM, libc::SOCK_DGRAM, libc::SOCK_SEQPACKET];
+
+    for &sock_type in unix_sock_types.iter() {
+        let domain = libc::AF_UNIX;
+
+        // add details to the test names to avoid duplicates
+        let append_args = |s| format!("{} <domain={},sock_type={}>", s, domain, sock_type);
+
+        let more_tests: Vec<test_utils::ShadowTest<_, _>> = vec![
+            test_utils::ShadowTest::new(
+                &append_args("test_so_type"),
+                move || test_so_type(domain, sock_type),
+                set![TestEnv::Libc, TestEnv::Shadow],
+            ),
+            test_utils::ShadowTest::new(
+                &append_args("test_so_type_huge_len"),
+                move || test_so_type_huge_len(domain, sock_type),
+                set![TestEnv::Libc, TestEnv::Shadow],
+            ),
+            test_utils::ShadowTest::new(
+                &append_args("test_so_domain"),
+                move || test_so_domain(domain, sock_type),
+                set![TestEnv::Libc, TestEnv::Shadow],
+            ),
+            test_utils::ShadowTest::new(
+                &append_args("test_so_protocol"),
+                move || test_so_protocol(domain, sock_type),
+                set![TestEnv::Libc, TestEnv::Shadow],
+            ),
+            test_utils::ShadowTest::new(
+                &append_args("test_so_acceptconn"),
+                move || test_so_acceptconn(domain, sock_type),
+                set![TestEnv::Libc, TestEnv::Shadow],
+            ),
+        ];
+
+        tests.extend(more_tests);
+
+        // only connection-oriented sockets can listen
+        if sock_type != libc::SOCK_DGRAM {
+            tests.push(test_utils::ShadowTest::new(
+                &append_args("test_unix_so_acceptconn_listening"),
+                move || test_unix_so_acceptconn_listening(sock_type),
+                set![TestEnv::Libc, TestEnv::Shadow],
+            ));
         }
     }
 
     tests
 }
 
+/// Tests of tcp socket options, for shadow's new tcp stack (`--use-new-tcp`). Most other tcp
+/// options aren't supported by the new tcp stack yet.
+fn get_new_tcp_tests() -> Vec<test_utils::ShadowTest<(), String>> {
+    let domain = libc::AF_INET;
+    let sock_type = libc::SOCK_STREAM;
+
+    let append_args = |s| format!("{} <domain={},sock_type={}>", s, domain, sock_type);
+
+    vec![
+        test_utils::ShadowTest::new(
+            &append_args("test_so_mark"),
+            move || test_so_mark(domain, sock_type),
+            set![TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            &append_args("test_so_bindtodevice"),
+            move || test_so_bindtodevice(domain, sock_type),
+            set![TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            &append_args("test_so_busy_poll"),
+            move || test_so_busy_poll(domain, sock_type),
+            set![TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            &append_args("test_so_incoming_cpu"),
+            move || test_so_incoming_cpu(domain, sock_type),
+            set![TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            &append_args("test_so_priority"),
+            move || test_so_priority(domain, sock_type),
+            set![TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            &append_args("test_so_dontroute"),
+            move || test_so_dontroute(domain, sock_type),
+            set![TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_so_dontroute_routing",
+            test_so_dontroute_routing,
+            set![TestEnv::Shadow],
+        ),
+    ]
+}
+
 /// Test getsockopt() and setsockopt() using an argument that cannot be a fd.
 fn test_invalid_fd() -> Result<(), String> {
     let fd = -1;
@@ -269,6 +475,65 @@ fn test_non_socket_fd() -> Result<(), String> {
     Ok(())
 }
 
+/// A type of file that isn't a socket.
+#[derive(Debug, Clone, Copy)]
+enum NonSocketFile {
+    Pipe,
+    EventFd,
+    Regular,
+}
+
+/// Test getsockopt() and setsockopt() with several socket options on files that are not sockets.
+fn test_non_socket_file(file_type: NonSocketFile) -> Result<(), String> {
+    let (fds, path) = match file_type {
+        NonSocketFile::Pipe => {
+            let mut fds = [-1; 2];
+            assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+            (fds.to_vec(), None)
+        }
+        NonSocketFile::EventFd => {
+            let fd = unsafe { libc::eventfd(0, 0) };
+            assert!(fd >= 0);
+            (vec![fd], None)
+        }
+        NonSocketFile::Regular => {
+            let (fd, path) = nix::unistd::mkstemp(&b"testsockopt_XXXXXX"[..]).unwrap();
+            (vec![fd], Some(path))
+        }
+    };
+
+    let options = [
+        (libc::SOL_SOCKET, libc::SO_SNDBUF),
+        (libc::SOL_SOCKET, libc::SO_TYPE),
+        (libc::SOL_TCP, libc::TCP_NODELAY),
+        (-1, libc::SO_SNDBUF),
+    ];
+
+    let rv = test_utils::run_and_close_fds(&fds, || {
+        for &fd in &fds {
+            for (level, optname) in options {
+                let optval = 1i32.to_ne_bytes();
+
+                let mut get_args =
+                    GetsockoptArguments::new(fd, level, optname, Some(optval.into()));
+                let mut set_args =
+                    SetsockoptArguments::new(fd, level, optname, Some(optval.into()));
+
+                check_getsockopt_call(&mut get_args, &[libc::ENOTSOCK])?;
+                check_setsockopt_call(&mut set_args, &[libc::ENOTSOCK])?;
+            }
+        }
+
+        Ok(())
+    });
+
+    if let Some(path) = path {
+        nix::unistd::unlink(&path).unwrap();
+    }
+
+    rv
+}
+
 /// Test getsockopt() and setsockopt() using a non-null optval and a long optlen.
 fn test_long_len() -> Result<(), String> {
     let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM | libc::SOCK_NONBLOCK, 0) };
@@ -685,6 +950,58 @@ fn test_so_type(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), Strin
     })
 }
 
+/// Test getsockopt() using the SO_TYPE option and a huge optlen, which should only be treated as an
+/// upper bound on the size of the option.
+fn test_so_type_huge_len(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    let fd = unsafe { libc::socket(domain, sock_type | libc::SOCK_NONBLOCK, 0) };
+    assert!(fd >= 0);
+
+    test_utils::run_and_close_fds(&[fd], || {
+        // the buffer is larger than the option, but much smaller than the optlen
+        let mut optval = [0xffu8; 8];
+        let mut optlen = libc::c_int::MAX as libc::socklen_t;
+
+        let rv = unsafe {
+            libc::getsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_TYPE,
+                optval.as_mut_ptr() as *mut libc::c_void,
+                &mut optlen,
+            )
+        };
+        test_utils::result_assert_eq(rv, 0, "getsockopt() failed")?;
+
+        test_utils::result_assert_eq(optlen, 4, "The optlen should be the size of an int")?;
+
+        let returned_optval = i32::from_ne_bytes(optval[..4].try_into().unwrap());
+        test_utils::result_assert_eq(returned_optval, sock_type, "Wrong socket type")?;
+        test_utils::result_assert_eq(
+            &optval[4..],
+            &[0xff; 4],
+            "Bytes after the option should not have changed",
+        )?;
+
+        // linux interprets the optlen as a signed int, so this optlen is negative
+        let mut optlen = libc::socklen_t::MAX;
+
+        test_utils::check_system_call!(
+            || unsafe {
+                libc::getsockopt(
+                    fd,
+                    libc::SOL_SOCKET,
+                    libc::SO_TYPE,
+                    optval.as_mut_ptr() as *mut libc::c_void,
+                    &mut optlen,
+                )
+            },
+            &[libc::EINVAL],
+        )?;
+
+        Ok(())
+    })
+}
+
 /// Test getsockopt() and setsockopt() using the SO_DOMAIN option.
 fn test_so_domain(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
     let fd = unsafe { libc::socket(domain, sock_type | libc::SOCK_NONBLOCK, 0) };
@@ -788,6 +1105,55 @@ fn test_so_acceptconn(domain: libc::c_int, sock_type: libc::c_int) -> Result<(),
     })
 }
 
+/// Test getsockopt() using the SO_ACCEPTCONN option on a bound unix socket, before and after
+/// listen(), and on a socket that connected to it.
+fn test_unix_so_acceptconn_listening(sock_type: libc::c_int) -> Result<(), String> {
+    let fd_server = unsafe { libc::socket(libc::AF_UNIX, sock_type | libc::SOCK_NONBLOCK, 0) };
+    let fd_client = unsafe { libc::socket(libc::AF_UNIX, sock_type | libc::SOCK_NONBLOCK, 0) };
+    assert!(fd_server >= 0);
+    assert!(fd_client >= 0);
+
+    let (server_addr, server_addr_len) =
+        test_utils::socket_utils::autobind_helper(fd_server, libc::AF_UNIX);
+
+    let get_acceptconn = |fd| {
+        let mut get_args =
+            GetsockoptArguments::new(fd, libc::SOL_SOCKET, libc::SO_ACCEPTCONN, Some(vec![0; 4]));
+        check_getsockopt_call(&mut get_args, &[])?;
+        Ok::<_, String>(i32::from_ne_bytes(
+            get_args.optval.unwrap()[..].try_into().unwrap(),
+        ))
+    };
+
+    test_utils::run_and_close_fds(&[fd_server, fd_client], || {
+        test_utils::result_assert_eq(
+            get_acceptconn(fd_server)?,
+            0,
+            "Wrong value returned for SO_ACCEPTCONN before listen()",
+        )?;
+
+        let rv = unsafe { libc::listen(fd_server, 10) };
+        test_utils::result_assert_eq(rv, 0, "listen() failed")?;
+
+        test_utils::result_assert_eq(
+            get_acceptconn(fd_server)?,
+            1,
+            "Wrong value returned for SO_ACCEPTCONN after listen()",
+        )?;
+
+        let rv = unsafe { libc::connect(fd_client, server_addr.as_ptr(), server_addr_len) };
+        test_utils::result_assert_eq(rv, 0, "connect() failed")?;
+
+        test_utils::result_assert_eq(
+            get_acceptconn(fd_client)?,
+            0,
+            "Wrong value returned for SO_ACCEPTCONN on a connected socket",
+        )?;
+
+        Ok(())
+    })
+}
+
 /// Test getsockopt() and setsockopt() using the SO_BROADCAST option with the value 0.
 fn test_so_broadcast_0(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
     let fd = unsafe { libc::socket(domain, sock_type | libc::SOCK_NONBLOCK, 0) };
@@ -868,6 +1234,496 @@ fn test_so_broadcast(domain: libc::c_int, sock_type: libc::c_int) -> Result<(),
     })
 }
 
+/// Test getsockopt() and setsockopt() using the SO_OOBINLINE option.
+fn test_so_oobinline(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    let fd = unsafe { libc::socket(domain, sock_type | libc::SOCK_NONBLOCK, 0) };
+    assert!(fd >= 0);
+
+    let level = libc::SOL_SOCKET;
+    let optname = libc::SO_OOBINLINE;
+    let zero = 0i32.to_ne_bytes();
+    let one = 1i32.to_ne_bytes();
+    let ten = 10i32.to_ne_bytes();
+
+    let mut get_args = GetsockoptArguments::new(fd, level, optname, Some(zero.into()));
+
+    let mut set_args_1 = SetsockoptArguments::new(fd, level, optname, Some(one.into()));
+    let mut set_args_2 = SetsockoptArguments::new(fd, level, optname, Some(zero.into()));
+    let mut set_args_3 = SetsockoptArguments::new(fd, level, optname, Some(ten.into()));
+
+    test_utils::run_and_close_fds(&[fd], || {
+        // initially should be 0
+        check_getsockopt_call(&mut get_args, &[])?;
+        let returned_optval =
+            i32::from_ne_bytes(get_args.optval.as_ref().unwrap()[..].try_into().unwrap());
+        test_utils::result_assert_eq(returned_optval, 0, "unexpected value from SO_OOBINLINE")?;
+
+        // set to 1
+        check_setsockopt_call(&mut set_args_1, &[])?;
+
+        // should now be 1
+        check_getsockopt_call(&mut get_args, &[])?;
+        let returned_optval =
+            i32::from_ne_bytes(get_args.optval.as_ref().unwrap()[..].try_into().unwrap());
+        test_utils::result_assert_eq(returned_optval, 1, "unexpected value from SO_OOBINLINE")?;
+
+        // set to 0
+        check_setsockopt_call(&mut set_args_2, &[])?;
+
+        // should now be 0
+        check_getsockopt_call(&mut get_args, &[])?;
+        let returned_optval =
+            i32::from_ne_bytes(get_args.optval.as_ref().unwrap()[..].try_into().unwrap());
+        test_utils::result_assert_eq(returned_optval, 0, "unexpected value from SO_OOBINLINE")?;
+
+        // set to 10
+        check_setsockopt_call(&mut set_args_3, &[])?;
+
+        // should now be 1
+        check_getsockopt_call(&mut get_args, &[])?;
+        let returned_optval =
+            i32::from_ne_bytes(get_args.optval.as_ref().unwrap()[..].try_into().unwrap());
+        test_utils::result_assert_eq(returned_optval, 1, "unexpected value from SO_OOBINLINE")?;
+
+        Ok(())
+    })
+}
+
+/// Test getsockopt() and setsockopt() using the SO_MARK option.
+fn test_so_mark(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    let fd = unsafe { libc::socket(domain, sock_type, 0) };
+    assert!(fd >= 0);
+
+    let level = libc::SOL_SOCKET;
+    let optname = libc::SO_MARK;
+    let zero = 0u32.to_ne_bytes();
+    let mark = 0xABCDu32.to_ne_bytes();
+
+    let mut get_args = GetsockoptArguments::new(fd, level, optname, Some(zero.into()));
+    let mut set_args = SetsockoptArguments::new(fd, level, optname, Some(mark.into()));
+    let mut set_args_short = SetsockoptArguments::new(fd, level, optname, Some(vec![0; 2]));
+
+    test_utils::run_and_close_fds(&[fd], || {
+        // initially should be 0
+        check_getsockopt_call(&mut get_args, &[])?;
+        let returned_optval =
+            u32::from_ne_bytes(get_args.optval.as_ref().unwrap()[..].try_into().unwrap());
+        test_utils::result_assert_eq(returned_optval, 0, "unexpected value from SO_MARK")?;
+
+        check_setsockopt_call(&mut set_args, &[])?;
+        check_setsockopt_call(&mut set_args_short, &[libc::EINVAL])?;
+
+        check_getsockopt_call(&mut get_args, &[])?;
+        let returned_optval =
+            u32::from_ne_bytes(get_args.optval.as_ref().unwrap()[..].try_into().unwrap());
+        test_utils::result_assert_eq(returned_optval, 0xABCD, "unexpected value from SO_MARK")?;
+
+        Ok(())
+    })
+}
+
+/// Test getsockopt() and setsockopt() using the SO_BINDTODEVICE option.
+fn test_so_bindtodevice(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    let fd = unsafe { libc::socket(domain, sock_type, 0) };
+    assert!(fd >= 0);
+
+    let level = libc::SOL_SOCKET;
+    let optname = libc::SO_BINDTODEVICE;
+
+    let mut get_args = GetsockoptArguments::new(fd, level, optname, Some(vec![0; libc::IFNAMSIZ]));
+    let mut get_args_short = GetsockoptArguments::new(fd, level, optname, Some(vec![0; 4]));
+
+    // the name doesn't need a NUL
+    let mut set_args_lo = SetsockoptArguments::new(fd, level, optname, Some(b"lo".to_vec()));
+    let mut set_args_empty = SetsockoptArguments::new(fd, level, optname, Some(b"\0".to_vec()));
+    let mut set_args_invalid =
+        SetsockoptArguments::new(fd, level, optname, Some(b"notadevice0\0".to_vec()));
+
+    test_utils::run_and_close_fds(&[fd], || {
+        // the buffer must be able to hold any interface name, even if the socket isn't bound to
+        // an interface
+        check_getsockopt_call(&mut get_args_short, &[libc::EINVAL])?;
+
+        // initially the socket isn't bound to an interface, so returns an empty value
+        check_getsockopt_call(&mut get_args, &[])?;
+        test_utils::result_assert_eq(get_args.optlen, Some(0), "unexpected SO_BINDTODEVICE len")?;
+
+        // an empty name does nothing if the socket isn't bound to an interface
+        check_setsockopt_call(&mut set_args_empty, &[])?;
+        check_setsockopt_call(&mut set_args_invalid, &[libc::ENODEV])?;
+        check_setsockopt_call(&mut set_args_lo, &[])?;
+
+        // the returned name includes the NUL
+        check_getsockopt_call(&mut get_args, &[])?;
+        test_utils::result_assert_eq(get_args.optlen, Some(3), "unexpected SO_BINDTODEVICE len")?;
+        test_utils::result_assert_eq(
+            &get_args.optval.as_ref().unwrap()[..3],
+            &b"lo\0"[..],
+            "unexpected SO_BINDTODEVICE name",
+        )?;
+
+        // the buffer must be able to hold any interface name
+        check_getsockopt_call(&mut get_args_short, &[libc::EINVAL])?;
+
+        Ok(())
+    })
+}
+
+/// Test getsockopt() and setsockopt() using the SO_DONTROUTE option.
+fn test_so_dontroute(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    let fd = unsafe { libc::socket(domain, sock_type, 0) };
+    assert!(fd >= 0);
+
+    let level = libc::SOL_SOCKET;
+    let optname = libc::SO_DONTROUTE;
+    let zero = 0i32.to_ne_bytes();
+    let ten = 10i32.to_ne_bytes();
+
+    let mut get_args = GetsockoptArguments::new(fd, level, optname, Some(zero.into()));
+    let mut set_args_on = SetsockoptArguments::new(fd, level, optname, Some(ten.into()));
+    let mut set_args_off = SetsockoptArguments::new(fd, level, optname, Some(zero.into()));
+    let mut set_args_short = SetsockoptArguments::new(fd, level, optname, Some(vec![1; 2]));
+
+    let get_value = |get_args: &mut GetsockoptArguments| -> Result<i32, String> {
+        check_getsockopt_call(get_args, &[])?;
+        Ok(i32::from_ne_bytes(
+            get_args.optval.as_ref().unwrap()[..].try_into().unwrap(),
+        ))
+    };
+
+    test_utils::run_and_close_fds(&[fd], || {
+        // initially should be 0
+        test_utils::result_assert_eq(get_value(&mut get_args)?, 0, "unexpected SO_DONTROUTE")?;
+
+        // any non-zero value enables the option, and is returned as 1
+        check_setsockopt_call(&mut set_args_on, &[])?;
+        test_utils::result_assert_eq(get_value(&mut get_args)?, 1, "unexpected SO_DONTROUTE")?;
+
+        check_setsockopt_call(&mut set_args_off, &[])?;
+        test_utils::result_assert_eq(get_value(&mut get_args)?, 0, "unexpected SO_DONTROUTE")?;
+
+        check_setsockopt_call(&mut set_args_short, &[libc::EINVAL])?;
+
+        Ok(())
+    })
+}
+
+/// Test getsockopt() and setsockopt() using the SO_PRIORITY option.
+fn test_so_priority(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    let fd = unsafe { libc::socket(domain, sock_type, 0) };
+    assert!(fd >= 0);
+
+    let level = libc::SOL_SOCKET;
+    let optname = libc::SO_PRIORITY;
+    let zero = 0i32.to_ne_bytes();
+
+    let mut get_args = GetsockoptArguments::new(fd, level, optname, Some(zero.into()));
+    let mut set_args_5 =
+        SetsockoptArguments::new(fd, level, optname, Some(5i32.to_ne_bytes().into()));
+    let mut set_args_7 =
+        SetsockoptArguments::new(fd, level, optname, Some(7i32.to_ne_bytes().into()));
+    let mut set_args_short = SetsockoptArguments::new(fd, level, optname, Some(vec![1; 2]));
+
+    let get_value = |get_args: &mut GetsockoptArguments| -> Result<i32, String> {
+        check_getsockopt_call(get_args, &[])?;
+        Ok(i32::from_ne_bytes(
+            get_args.optval.as_ref().unwrap()[..].try_into().unwrap(),
+        ))
+    };
+
+    // priorities above 6 require CAP_NET_ADMIN
+    let is_root = unsafe { libc::geteuid() } == 0;
+
+    test_utils::run_and_close_fds(&[fd], || {
+        // initially should be 0
+        test_utils::result_assert_eq(get_value(&mut get_args)?, 0, "unexpected SO_PRIORITY")?;
+
+        check_setsockopt_call(&mut set_args_5, &[])?;
+        test_utils::result_assert_eq(get_value(&mut get_args)?, 5, "unexpected SO_PRIORITY")?;
+
+        check_setsockopt_call(&mut set_args_short, &[libc::EINVAL])?;
+
+        if is_root {
+            check_setsockopt_call(&mut set_args_7, &[])?;
+            test_utils::result_assert_eq(get_value(&mut get_args)?, 7, "unexpected SO_PRIORITY")?;
+        } else {
+            check_setsockopt_call(&mut set_args_7, &[libc::EPERM])?;
+            test_utils::result_assert_eq(get_value(&mut get_args)?, 5, "unexpected SO_PRIORITY")?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Test getsockopt() and setsockopt() using the SO_BUSY_POLL option.
+fn test_so_busy_poll(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    let fd = unsafe { libc::socket(domain, sock_type, 0) };
+    assert!(fd >= 0);
+
+    let level = libc::SOL_SOCKET;
+    let optname = libc::SO_BUSY_POLL;
+    let zero = 0i32.to_ne_bytes();
+
+    let mut get_args = GetsockoptArguments::new(fd, level, optname, Some(zero.into()));
+    let mut set_args_50 =
+        SetsockoptArguments::new(fd, level, optname, Some(50i32.to_ne_bytes().into()));
+    let mut set_args_negative =
+        SetsockoptArguments::new(fd, level, optname, Some((-1i32).to_ne_bytes().into()));
+    let mut set_args_short = SetsockoptArguments::new(fd, level, optname, Some(vec![1; 2]));
+
+    let get_value = |get_args: &mut GetsockoptArguments| -> Result<i32, String> {
+        check_getsockopt_call(get_args, &[])?;
+        Ok(i32::from_ne_bytes(
+            get_args.optval.as_ref().unwrap()[..].try_into().unwrap(),
+        ))
+    };
+
+    test_utils::run_and_close_fds(&[fd], || {
+        // initially should be 0
+        test_utils::result_assert_eq(get_value(&mut get_args)?, 0, "unexpected SO_BUSY_POLL")?;
+
+        check_setsockopt_call(&mut set_args_50, &[])?;
+        test_utils::result_assert_eq(get_value(&mut get_args)?, 50, "unexpected SO_BUSY_POLL")?;
+
+        check_setsockopt_call(&mut set_args_negative, &[libc::EINVAL])?;
+        check_setsockopt_call(&mut set_args_short, &[libc::EINVAL])?;
+        test_utils::result_assert_eq(get_value(&mut get_args)?, 50, "unexpected SO_BUSY_POLL")?;
+
+        Ok(())
+    })
+}
+
+/// Test getsockopt() using the SO_INCOMING_CPU option.
+fn test_so_incoming_cpu(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    let fd = unsafe { libc::socket(domain, sock_type, 0) };
+    assert!(fd >= 0);
+
+    let level = libc::SOL_SOCKET;
+    let optname = libc::SO_INCOMING_CPU;
+
+    let mut get_args =
+        GetsockoptArguments::new(fd, level, optname, Some(0i32.to_ne_bytes().into()));
+
+    test_utils::run_and_close_fds(&[fd], || {
+        check_getsockopt_call(&mut get_args, &[])?;
+        test_utils::result_assert_eq(get_args.optlen, Some(4), "unexpected optlen")?;
+
+        // a socket that hasn't received anything hasn't been assigned a cpu
+        let cpu = i32::from_ne_bytes(get_args.optval.as_ref().unwrap()[..].try_into().unwrap());
+        test_utils::result_assert_eq(cpu, -1, "unexpected SO_INCOMING_CPU")?;
+
+        Ok(())
+    })
+}
+
+/// Test that sockets with SO_DONTROUTE can't send to or connect to addresses that aren't on a
+/// directly-connected network.
+fn test_so_dontroute_routing() -> Result<(), String> {
+    let dont_route = |fd: libc::c_int| {
+        let mut args = SetsockoptArguments::new(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_DONTROUTE,
+            Some(1i32.to_ne_bytes().into()),
+        );
+        check_setsockopt_call(&mut args, &[])
+    };
+
+    let fd_server =
+        unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_NONBLOCK, 0) };
+    let fd_udp = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+    let fd_tcp = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+    for fd in [fd_server, fd_udp, fd_tcp] {
+        assert!(fd >= 0);
+    }
+
+    test_utils::run_and_close_fds(&[fd_server, fd_udp, fd_tcp], || {
+        dont_route(fd_udp)?;
+        dont_route(fd_tcp)?;
+
+        // an address that isn't on any of the host's networks
+        let off_link_addr = nix::sys::socket::SockaddrIn::new(1, 2, 3, 4, 80);
+        let flags = nix::sys::socket::MsgFlags::empty();
+
+        test_utils::result_assert_eq(
+            nix::sys::socket::sendto(fd_udp, &[1], &off_link_addr, flags),
+            Err(nix::errno::Errno::ENETUNREACH),
+            "unexpected sendto result",
+        )?;
+        test_utils::result_assert_eq(
+            nix::sys::socket::connect(fd_tcp, &off_link_addr),
+            Err(nix::errno::Errno::ENETUNREACH),
+            "unexpected connect result",
+        )?;
+
+        // the loopback network is directly connected
+        nix::sys::socket::bind(
+            fd_server,
+            &nix::sys::socket::SockaddrIn::new(127, 0, 0, 1, 0),
+        )
+        .unwrap();
+        let server_addr =
+            nix::sys::socket::getsockname::<nix::sys::socket::SockaddrIn>(fd_server).unwrap();
+
+        test_utils::result_assert_eq(
+            nix::sys::socket::sendto(fd_udp, &[2], &server_addr, flags),
+            Ok(1),
+            "unexpected sendto result",
+        )?;
+
+        // shadow needs to run events
+        std::thread::sleep(std::time::Duration::from_millis(10));
+
+        let mut buf = [0u8; 1];
+        test_utils::result_assert_eq(
+            nix::sys::socket::recv(fd_server, &mut buf, flags),
+            Ok(1),
+            "unexpected recv result",
+        )?;
+        test_utils::result_assert_eq(buf, [2], "unexpected packet received")?;
+
+        Ok(())
+    })
+}
+
+/// Test that SO_PEERSEC returns a security context for connected unix sockets.
+fn test_so_peersec() -> Result<(), String> {
+    let mut fds = [0; 2];
+    let rv = unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) };
+    assert_eq!(rv, 0);
+
+    let fd_unconnected = unsafe { libc::socket(libc::AF_UNIX, libc::SOCK_STREAM, 0) };
+    let fd_inet = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+    assert!(fd_unconnected >= 0);
+    assert!(fd_inet >= 0);
+
+    let level = libc::SOL_SOCKET;
+    let optname = libc::SO_PEERSEC;
+
+    let mut get_args = GetsockoptArguments::new(fds[0], level, optname, Some(vec![0; 64]));
+    let mut get_args_short = GetsockoptArguments::new(fds[0], level, optname, Some(vec![0; 4]));
+    let mut get_args_unconnected =
+        GetsockoptArguments::new(fd_unconnected, level, optname, Some(vec![0; 64]));
+    let mut get_args_inet = GetsockoptArguments::new(fd_inet, level, optname, Some(vec![0; 64]));
+
+    test_utils::run_and_close_fds(&[fds[0], fds[1], fd_unconnected, fd_inet], || {
+        // the returned context includes the NUL
+        check_getsockopt_call(&mut get_args, &[])?;
+        test_utils::result_assert_eq(get_args.optlen, Some(11), "unexpected SO_PEERSEC len")?;
+        test_utils::result_assert_eq(
+            &get_args.optval.as_ref().unwrap()[..11],
+            &b"unconfined\0"[..],
+            "unexpected SO_PEERSEC context",
+        )?;
+
+        // a buffer that's too small returns the required length
+        check_getsockopt_call(&mut get_args_short, &[libc::ERANGE])?;
+        test_utils::result_assert_eq(get_args_short.optlen, Some(11), "unexpected SO_PEERSEC len")?;
+
+        check_getsockopt_call(&mut get_args_unconnected, &[libc::ENOTCONN])?;
+        check_getsockopt_call(&mut get_args_inet, &[libc::ENOPROTOOPT])?;
+
+        Ok(())
+    })
+}
+
+/// Test that sockets bound to an interface using SO_BINDTODEVICE only send and receive packets on
+/// that interface.
+fn test_so_bindtodevice_routing() -> Result<(), String> {
+    // the address of the "eth0" interface
+    let eth0_addr = nix::ifaddrs::getifaddrs()
+        .unwrap()
+        .filter(|x| x.interface_name == "eth0")
+        .find_map(|x| x.address.and_then(|x| x.as_sockaddr_in().map(|x| x.ip())))
+        .unwrap();
+    let eth0_addr = std::net::Ipv4Addr::from(eth0_addr);
+
+    let bind_to_device = |fd: libc::c_int, name: &[u8]| {
+        let mut args = SetsockoptArguments::new(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_BINDTODEVICE,
+            Some(name.into()),
+        );
+        check_setsockopt_call(&mut args, &[])
+    };
+
+    let fd_server =
+        unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_NONBLOCK, 0) };
+    let fd_eth0 = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+    let fd_lo = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+    let fd_tcp = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+    for fd in [fd_server, fd_eth0, fd_lo, fd_tcp] {
+        assert!(fd >= 0);
+    }
+
+    test_utils::run_and_close_fds(&[fd_server, fd_eth0, fd_lo, fd_tcp], || {
+        bind_to_device(fd_server, b"eth0")?;
+        bind_to_device(fd_eth0, b"eth0")?;
+        bind_to_device(fd_lo, b"lo")?;
+        bind_to_device(fd_tcp, b"eth0")?;
+
+        // the server is bound to 0.0.0.0, but should only receive packets arriving on eth0
+        nix::sys::socket::bind(fd_server, &nix::sys::socket::SockaddrIn::new(0, 0, 0, 0, 0))
+            .unwrap();
+        let port = nix::sys::socket::getsockname::<nix::sys::socket::SockaddrIn>(fd_server)
+            .unwrap()
+            .port();
+
+        let lo_server_addr = nix::sys::socket::SockaddrIn::from(std::net::SocketAddrV4::new(
+            std::net::Ipv4Addr::LOCALHOST,
+            port,
+        ));
+        let eth0_server_addr =
+            nix::sys::socket::SockaddrIn::from(std::net::SocketAddrV4::new(eth0_addr, port));
+        let flags = nix::sys::socket::MsgFlags::empty();
+
+        // can't send to an address that isn't routed through the interface
+        test_utils::result_assert_eq(
+            nix::sys::socket::sendto(fd_eth0, &[1], &lo_server_addr, flags),
+            Err(nix::errno::Errno::ENETUNREACH),
+            "unexpected sendto result",
+        )?;
+        test_utils::result_assert_eq(
+            nix::sys::socket::sendto(fd_lo, &[1], &eth0_server_addr, flags),
+            Err(nix::errno::Errno::ENETUNREACH),
+            "unexpected sendto result",
+        )?;
+        test_utils::result_assert_eq(
+            nix::sys::socket::connect(fd_tcp, &lo_server_addr),
+            Err(nix::errno::Errno::ENETUNREACH),
+            "unexpected connect result",
+        )?;
+
+        // a packet arriving on the loopback interface is dropped by the server
+        nix::sys::socket::sendto(fd_lo, &[2], &lo_server_addr, flags).unwrap();
+        // a packet arriving on eth0 is received
+        nix::sys::socket::sendto(fd_eth0, &[3], &eth0_server_addr, flags).unwrap();
+
+        // shadow needs to run events
+        std::thread::sleep(std::time::Duration::from_millis(10));
+
+        let mut buf = [0u8; 1];
+        let (n, src) =
+            nix::sys::socket::recvfrom::<nix::sys::socket::SockaddrIn>(fd_server, &mut buf)
+                .unwrap();
+        test_utils::result_assert_eq(&buf[..n], &[3][..], "unexpected packet received")?;
+        test_utils::result_assert_eq(
+            std::net::Ipv4Addr::from(src.unwrap().ip()),
+            eth0_addr,
+            "unexpected source address",
+        )?;
+
+        test_utils::result_assert_eq(
+            nix::sys::socket::recv(fd_server, &mut buf, flags),
+            Err(nix::errno::Errno::EAGAIN),
+            "expected no more packets",
+        )?;
+
+        Ok(())
+    })
+}
+
 /// Test getsockopt() and setsockopt() using the TCP_INFO option.
 fn test_tcp_info(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
     let fd = unsafe { libc::socket(domain, sock_type, 0) };
@@ -905,13 +1761,9 @@ fn test_tcp_nodelay(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), S
     let level = libc::SOL_TCP;
     let optname = libc::TCP_NODELAY;
 
-    // shadow doesn't support setting a value of 0
-    let optval = 1i32.to_ne_bytes();
     let zero = 0i32.to_ne_bytes();
 
-    let mut get_args_1 = GetsockoptArguments::new(fd, level, optname, Some(zero.into()));
-    let mut get_args_2 = GetsockoptArguments::new(fd, level, optname, Some(zero.into()));
-    let mut set_args = SetsockoptArguments::new(fd, level, optname, Some(optval.into()));
+    let mut get_args = GetsockoptArguments::new(fd, level, optname, Some(zero.into()));
 
     test_utils::run_and_close_fds(&[fd], || {
         let expected_errnos = if sock_type == libc::SOCK_STREAM {
@@ -919,26 +1771,96 @@ fn test_tcp_nodelay(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), S
         } else {
             vec![libc::ENOPROTOOPT, libc::EOPNOTSUPP]
         };
-        check_getsockopt_call(&mut get_args_1, &expected_errnos)?;
+        check_getsockopt_call(&mut get_args, &expected_errnos)?;
 
         if sock_type == libc::SOCK_STREAM {
-            // in shadow will return 1, but in linux will return 0
-            let value = u32::from_ne_bytes(get_args_1.optval.unwrap().try_into().unwrap());
-            test_utils::result_assert([0, 1].contains(&value), "Unexpected value for TCP_NODELAY")?;
+            // nagle's algorithm is enabled by default
+            let value = u32::from_ne_bytes(get_args.optval.unwrap().try_into().unwrap());
+            test_utils::result_assert_eq(value, 0, "Unexpected value for TCP_NODELAY")?;
         }
 
-        check_setsockopt_call(&mut set_args, &expected_errnos)?;
-        check_getsockopt_call(&mut get_args_2, &expected_errnos)?;
+        for val in [1i32, 0] {
+            let optval = val.to_ne_bytes();
+            let mut set_args = SetsockoptArguments::new(fd, level, optname, Some(optval.into()));
+            let mut get_args = GetsockoptArguments::new(fd, level, optname, Some(zero.into()));
 
-        if sock_type == libc::SOCK_STREAM {
-            let value = u32::from_ne_bytes(get_args_2.optval.unwrap().try_into().unwrap());
-            test_utils::result_assert_eq(value, 1, "Unexpected value for TCP_NODELAY")?;
+            check_setsockopt_call(&mut set_args, &expected_errnos)?;
+            check_getsockopt_call(&mut get_args, &expected_errnos)?;
+
+            if sock_type == libc::SOCK_STREAM {
+                let value = i32::from_ne_bytes(get_args.optval.unwrap().try_into().unwrap());
+                test_utils::result_assert_eq(value, val, "Unexpected value for TCP_NODELAY")?;
+            }
         }
 
         Ok(())
     })
 }
 
+/// Test that many small writes reach the receiver sooner with TCP_NODELAY enabled, since nagle's
+/// algorithm otherwise holds back the later writes until the first write is acknowledged.
+fn test_tcp_nodelay_latency() -> Result<(), String> {
+    let nagle = tcp_small_writes_latency(false)?;
+    let no_delay = tcp_small_writes_latency(true)?;
+
+    test_utils::result_assert(
+        no_delay < nagle,
+        &format!("TCP_NODELAY didn't reduce the write latency ({no_delay:?} >= {nagle:?})"),
+    )?;
+
+    Ok(())
+}
+
+/// Write many small chunks back-to-back over a loopback TCP connection, and return the time until
+/// the receiver has all of the data. If `no_delay` is set, the sender enables TCP_NODELAY.
+fn tcp_small_writes_latency(no_delay: bool) -> Result<std::time::Duration, String> {
+    const WRITES: usize = 20;
+    const WRITE_LEN: usize = 10;
+
+    let (fd_client, fd_peer) = tcp_loopback_connection();
+
+    let mut elapsed = std::time::Duration::ZERO;
+
+    test_utils::run_and_close_fds(&[fd_client, fd_peer], || {
+        if no_delay {
+            let optval = 1i32.to_ne_bytes();
+            let mut set_args = SetsockoptArguments::new(
+                fd_client,
+                libc::SOL_TCP,
+                libc::TCP_NODELAY,
+                Some(optval.into()),
+            );
+            check_setsockopt_call(&mut set_args, &[])?;
+        }
+
+        let start = std::time::Instant::now();
+
+        let buf = [1u8; WRITE_LEN];
+        for _ in 0..WRITES {
+            let rv =
+                unsafe { libc::send(fd_client, buf.as_ptr() as *const libc::c_void, WRITE_LEN, 0) };
+            assert_eq!(rv, WRITE_LEN as isize);
+        }
+
+        // wait until the server has received all of the data
+        loop {
+            let mut available: libc::c_int = 0;
+            let rv =
+                unsafe { libc::ioctl(fd_peer, libc::FIONREAD, std::ptr::from_mut(&mut available)) };
+            assert_eq!(rv, 0);
+            if available as usize == WRITES * WRITE_LEN {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_micros(10));
+        }
+        elapsed = start.elapsed();
+
+        Ok(())
+    })?;
+
+    Ok(elapsed)
+}
+
 /// Test getsockopt() and setsockopt() using the TCP_CONGESTION option.
 fn test_tcp_congestion(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
     let fd = unsafe { libc::socket(domain, sock_type, 0) };
@@ -1017,6 +1939,217 @@ fn test_tcp_congestion(domain: libc::c_int, sock_type: libc::c_int) -> Result<()
     })
 }
 
+/// Test getsockopt() and setsockopt() using the TCP_QUICKACK option.
+fn test_tcp_quickack(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    let fd = unsafe { libc::socket(domain, sock_type, 0) };
+    assert!(fd >= 0);
+
+    let level = libc::SOL_TCP;
+    let optname = libc::TCP_QUICKACK;
+
+    let zero = 0i32.to_ne_bytes();
+
+    test_utils::run_and_close_fds(&[fd], || {
+        let expected_errnos = if sock_type == libc::SOCK_STREAM {
+            vec![]
+        } else {
+            vec![libc::ENOPROTOOPT, libc::EOPNOTSUPP]
+        };
+
+        for val in [1i32, 0] {
+            let optval = val.to_ne_bytes();
+            let mut set_args = SetsockoptArguments::new(fd, level, optname, Some(optval.into()));
+            let mut get_args = GetsockoptArguments::new(fd, level, optname, Some(zero.into()));
+
+            check_setsockopt_call(&mut set_args, &expected_errnos)?;
+            check_getsockopt_call(&mut get_args, &expected_errnos)?;
+
+            if sock_type == libc::SOCK_STREAM {
+                let value = i32::from_ne_bytes(get_args.optval.unwrap().try_into().unwrap());
+                test_utils::result_assert_eq(value, val, "Unexpected value for TCP_QUICKACK")?;
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// Test that enabling TCP_QUICKACK on the receiver of a ping-pong workload makes the sender's
+/// requests get acknowledged sooner.
+fn test_tcp_quickack_latency() -> Result<(), String> {
+    let delayed = tcp_ping_pong_ack_latency(false)?;
+    let quick = tcp_ping_pong_ack_latency(true)?;
+
+    test_utils::result_assert(
+        quick < delayed,
+        &format!("TCP_QUICKACK didn't reduce the ACK latency ({quick:?} >= {delayed:?})"),
+    )?;
+
+    Ok(())
+}
+
+/// Run a ping-pong workload over a loopback TCP connection, and return the total time that the
+/// client waited for its requests to be acknowledged by the server. If `quickack` is set, the server
+/// enables TCP_QUICKACK before each request.
+fn tcp_ping_pong_ack_latency(quickack: bool) -> Result<std::time::Duration, String> {
+    const ROUNDS: usize = 5;
+
+    let (fd_client, fd_peer) = tcp_loopback_connection();
+
+    let mut total = std::time::Duration::ZERO;
+
+    test_utils::run_and_close_fds(&[fd_client, fd_peer], || {
+        for _ in 0..ROUNDS {
+            if quickack {
+                let optval = 1i32.to_ne_bytes();
+                let mut set_args = SetsockoptArguments::new(
+                    fd_peer,
+                    libc::SOL_TCP,
+                    libc::TCP_QUICKACK,
+                    Some(optval.into()),
+                );
+                check_setsockopt_call(&mut set_args, &[])?;
+            }
+
+            // send the request
+            let rv = unsafe { libc::send(fd_client, [1u8].as_ptr() as *const libc::c_void, 1, 0) };
+            assert_eq!(rv, 1);
+
+            // wait for the request to be acknowledged; the server doesn't read it until later so
+            // the ACK can't be piggybacked on the response
+            let start = std::time::Instant::now();
+            loop {
+                let mut unacked: libc::c_int = 0;
+                let rv = unsafe {
+                    libc::ioctl(fd_client, libc::TIOCOUTQ, std::ptr::from_mut(&mut unacked))
+                };
+                assert_eq!(rv, 0);
+                if unacked == 0 {
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_micros(10));
+            }
+            total += start.elapsed();
+
+            // the server reads the request and sends the response
+            let mut buf = [0u8; 1];
+            let rv = unsafe { libc::recv(fd_peer, buf.as_mut_ptr() as *mut libc::c_void, 1, 0) };
+            assert_eq!(rv, 1);
+            let rv = unsafe { libc::send(fd_peer, buf.as_ptr() as *const libc::c_void, 1, 0) };
+            assert_eq!(rv, 1);
+
+            let rv = unsafe { libc::recv(fd_client, buf.as_mut_ptr() as *mut libc::c_void, 1, 0) };
+            assert_eq!(rv, 1);
+        }
+
+        Ok(())
+    })?;
+
+    Ok(total)
+}
+
+/// Test that a request sent using two small writes is delayed by the receiver's delayed ACK when
+/// nagle's algorithm is enabled, since the second write is held back until the first write is
+/// acknowledged.
+fn test_tcp_nagle_delayed_ack_latency() -> Result<(), String> {
+    // the delayed ACK timeout at the start of a connection
+    const DELAYED_ACK_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(1);
+
+    let nagle = tcp_write_write_read_latency(false)?;
+    let no_delay = tcp_write_write_read_latency(true)?;
+
+    test_utils::result_assert(
+        nagle >= no_delay + DELAYED_ACK_TIMEOUT,
+        &format!("Nagle's algorithm didn't wait for the delayed ACK ({nagle:?}, {no_delay:?})"),
+    )?;
+
+    Ok(())
+}
+
+/// Send a request using two small writes over a loopback TCP connection and return the time until
+/// the response is received. If `no_delay` is set, the client enables TCP_NODELAY.
+fn tcp_write_write_read_latency(no_delay: bool) -> Result<std::time::Duration, String> {
+    const HEADER_LEN: usize = 10;
+    const BODY_LEN: usize = 10;
+
+    let (fd_client, fd_peer) = tcp_loopback_connection();
+
+    let mut elapsed = std::time::Duration::ZERO;
+
+    test_utils::run_and_close_fds(&[fd_client, fd_peer], || {
+        if no_delay {
+            let optval = 1i32.to_ne_bytes();
+            let mut set_args = SetsockoptArguments::new(
+                fd_client,
+                libc::SOL_TCP,
+                libc::TCP_NODELAY,
+                Some(optval.into()),
+            );
+            check_setsockopt_call(&mut set_args, &[])?;
+        }
+
+        let start = std::time::Instant::now();
+
+        // send the request header and body using separate writes
+        for len in [HEADER_LEN, BODY_LEN] {
+            let buf = vec![1u8; len];
+            let rv = unsafe { libc::send(fd_client, buf.as_ptr() as *const libc::c_void, len, 0) };
+            assert_eq!(rv, len as isize);
+        }
+
+        // the server waits for the entire request before responding
+        let mut buf = [0u8; HEADER_LEN + BODY_LEN];
+        let mut received = 0;
+        while received < buf.len() {
+            let rv = unsafe {
+                libc::recv(
+                    fd_peer,
+                    buf[received..].as_mut_ptr() as *mut libc::c_void,
+                    buf.len() - received,
+                    0,
+                )
+            };
+            assert!(rv > 0);
+            received += rv as usize;
+        }
+
+        let rv = unsafe { libc::send(fd_peer, buf.as_ptr() as *const libc::c_void, 1, 0) };
+        assert_eq!(rv, 1);
+
+        let rv = unsafe { libc::recv(fd_client, buf.as_mut_ptr() as *mut libc::c_void, 1, 0) };
+        assert_eq!(rv, 1);
+
+        elapsed = start.elapsed();
+
+        Ok(())
+    })?;
+
+    Ok(elapsed)
+}
+
+/// Connect a new TCP socket to a listening socket on the loopback interface, and return the
+/// connected client socket and the accepted peer socket. The listening socket is closed.
+fn tcp_loopback_connection() -> (libc::c_int, libc::c_int) {
+    let fd_server = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+    let fd_client = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+    assert!(fd_server >= 0);
+    assert!(fd_client >= 0);
+
+    let (server_addr, server_addr_len) =
+        test_utils::socket_utils::autobind_helper(fd_server, libc::AF_INET);
+    assert_eq!(unsafe { libc::listen(fd_server, 10) }, 0);
+
+    let rv = unsafe { libc::connect(fd_client, server_addr.as_ptr(), server_addr_len) };
+    assert_eq!(rv, 0);
+
+    let fd_peer = unsafe { libc::accept(fd_server, std::ptr::null_mut(), std::ptr::null_mut()) };
+    assert!(fd_peer >= 0);
+
+    assert_eq!(unsafe { libc::close(fd_server) }, 0);
+
+    (fd_client, fd_peer)
+}
+
 fn check_getsockopt_call(
     args: &mut GetsockoptArguments,
     expected_errnos: &[libc::c_int],
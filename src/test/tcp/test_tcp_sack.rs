@@ -0,0 +1,150 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Compare TCP loss recovery with and without selective acknowledgements (SACK). A client on a host
+//! with SACK enabled and a client on a host with SACK disabled each send the same amount of data to
+//! the server over a lossy network, and the server checks that the transfer using SACK finished
+//! sooner. The server's host has SACK enabled, so the server also checks that SACK is only used on
+//! the connection if the client's host permits it. SACK is only supported by the legacy TCP stack.
+//!
+//! Usage:
+//!   test_tcp_sack server <port>
+//!   test_tcp_sack client <address> <sack|nosack>
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::fd::AsRawFd;
+use std::time::{Duration, Instant};
+
+const DATA_LEN: usize = 256 * 1024;
+
+const TAG_SACK: u8 = b's';
+const TAG_NO_SACK: u8 = b'n';
+
+fn main() -> Result<(), String> {
+    let args: Vec<String> = std::env::args().collect();
+
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[1..] {
+        ["server", port] => {
+            let port: u16 = port.parse().map_err(|e| format!("Invalid port: {e}"))?;
+            run_server(port)
+        }
+        ["client", address, mode] => {
+            let use_sack = match mode {
+                "sack" => true,
+                "nosack" => false,
+                _ => return Err(format!("Invalid mode: {mode}")),
+            };
+            run_client(address, use_sack)
+        }
+        _ => Err(format!(
+            "Usage: {0} server <port> | {0} client <address> <sack|nosack>",
+            args[0]
+        )),
+    }
+}
+
+/// Receive one transfer from each client, and check that the transfer with SACK was faster.
+fn run_server(port: u16) -> Result<(), String> {
+    let listener = TcpListener::bind(("0.0.0.0", port)).map_err(|e| e.to_string())?;
+
+    let mut sack_time = None;
+    let mut no_sack_time = None;
+
+    for _ in 0..2 {
+        let (mut stream, _) = listener.accept().map_err(|e| e.to_string())?;
+
+        let mut tag = [0u8];
+        stream.read_exact(&mut tag).map_err(|e| e.to_string())?;
+
+        // SACK is negotiated during the handshake
+        if uses_sack(&stream)? != (tag[0] == TAG_SACK) {
+            return Err(format!(
+                "Unexpected SACK use for the client with tag {}",
+                tag[0]
+            ));
+        }
+
+        let start = Instant::now();
+        let mut buf = vec![0u8; DATA_LEN];
+        stream.read_exact(&mut buf).map_err(|e| e.to_string())?;
+        let elapsed = start.elapsed();
+
+        // let the client know that all of the data was received
+        stream.write_all(&[0]).map_err(|e| e.to_string())?;
+
+        match tag[0] {
+            TAG_SACK => sack_time = Some(elapsed),
+            TAG_NO_SACK => no_sack_time = Some(elapsed),
+            x => return Err(format!("Unexpected tag: {x}")),
+        }
+    }
+
+    let (Some(sack_time), Some(no_sack_time)) = (sack_time, no_sack_time) else {
+        return Err("Didn't receive a transfer from each client".into());
+    };
+
+    if sack_time >= no_sack_time {
+        return Err(format!(
+            "SACK didn't reduce the transfer time ({sack_time:?} >= {no_sack_time:?})"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Send the data to the server, and wait for the server to receive it.
+fn run_client(address: &str, use_sack: bool) -> Result<(), String> {
+    let mut stream = TcpStream::connect(address).map_err(|e| e.to_string())?;
+
+    if uses_sack(&stream)? != use_sack {
+        return Err(format!(
+            "Expected the socket to have SACK {}",
+            if use_sack { "enabled" } else { "disabled" }
+        ));
+    }
+
+    let tag = if use_sack { TAG_SACK } else { TAG_NO_SACK };
+    stream.write_all(&[tag]).map_err(|e| e.to_string())?;
+    stream
+        .write_all(&vec![1u8; DATA_LEN])
+        .map_err(|e| e.to_string())?;
+
+    let mut done = [0u8];
+    stream.read_exact(&mut done).map_err(|e| e.to_string())?;
+
+    // give the server's final ACKs time to arrive before closing
+    std::thread::sleep(Duration::from_millis(100));
+
+    Ok(())
+}
+
+/// Check the `TCPI_OPT_SACK` flag of the socket's `TCP_INFO`.
+fn uses_sack(stream: &TcpStream) -> Result<bool, String> {
+    // the libc package doesn't expose 'struct tcp_info', but 'tcpi_options' is its 6th byte
+    const TCPI_OPTIONS_OFFSET: usize = 5;
+    const TCPI_OPT_SACK: u8 = 2;
+
+    let mut info = [0u8; 256];
+    let mut info_len = info.len() as libc::socklen_t;
+
+    let rv = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_TCP,
+            libc::TCP_INFO,
+            info.as_mut_ptr() as *mut libc::c_void,
+            &mut info_len,
+        )
+    };
+    if rv != 0 {
+        return Err(format!(
+            "getsockopt(TCP_INFO) failed: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    Ok(info[TCPI_OPTIONS_OFFSET] & TCPI_OPT_SACK != 0)
+}